@@ -103,18 +103,15 @@ static jintArray dc_array2jintArray_n_unref(JNIEnv *env, dc_array_t* ca)
 {
     /* takes a C-array of type dc_array_t and converts it it a Java-Array.
     then the C-array is freed and the Java-Array is returned. */
-    int i, icnt = ca? dc_array_get_cnt(ca) : 0;
-    jintArray ret = (*env)->NewIntArray(env, icnt); if (ret==NULL) { return NULL; }
+    size_t icnt = 0;
+    uint32_t* ids = ca? dc_array_get_all_ids(ca, &icnt) : NULL;
+    jintArray ret = (*env)->NewIntArray(env, icnt);
 
+    if (ret && icnt) {
+        (*env)->SetIntArrayRegion(env, ret, 0, icnt, (const jint*)ids);
+    }
+    dc_array_ids_unref(ids);
     if (ca) {
-        if (icnt) {
-            jint* temp = calloc(icnt, sizeof(jint));
-            for (i = 0; i < icnt; i++) {
-                temp[i] = (jint)dc_array_get_id(ca, i);
-            }
-            (*env)->SetIntArrayRegion(env, ret, 0, icnt, temp);
-            free(temp);
-        }
         dc_array_unref(ca);
     }
 
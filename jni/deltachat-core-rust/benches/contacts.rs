@@ -1,12 +1,12 @@
 #![recursion_limit = "256"]
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use deltachat::contact::Contact;
+use deltachat::contact::{Contact, ContactListFilter, ContactSortOrder};
 use deltachat::context::Context;
 use deltachat::stock_str::StockStrings;
 use deltachat::Events;
 use tempfile::tempdir;
 
-async fn address_book_benchmark(n: u32, read_count: u32) {
+async fn create_address_book(n: u32) -> (tempfile::TempDir, Context) {
     let dir = tempdir().unwrap();
     let dbfile = dir.path().join("db.sqlite");
     let id = 100;
@@ -20,6 +20,11 @@ async fn address_book_benchmark(n: u32, read_count: u32) {
         .join("");
 
     Contact::add_address_book(&context, &book).await.unwrap();
+    (dir, context)
+}
+
+async fn address_book_benchmark(n: u32, read_count: u32) {
+    let (_dir, context) = create_address_book(n).await;
 
     let query: Option<&str> = None;
     for _ in 0..read_count {
@@ -27,6 +32,20 @@ async fn address_book_benchmark(n: u32, read_count: u32) {
     }
 }
 
+async fn address_book_page_benchmark(n: u32, read_count: u32) {
+    let (_dir, context) = create_address_book(n).await;
+
+    let filter = ContactListFilter {
+        sort: ContactSortOrder::Name,
+        offset: (n / 2) as usize,
+        limit: Some(50),
+        ..Default::default()
+    };
+    for _ in 0..read_count {
+        Contact::get_page(&context, &filter).await.unwrap();
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let rt = tokio::runtime::Runtime::new().unwrap();
 
@@ -39,6 +58,11 @@ fn criterion_benchmark(c: &mut Criterion) {
         b.to_async(&rt)
             .iter(|| async { address_book_benchmark(black_box(100), black_box(1000)).await })
     });
+
+    c.bench_function("create 5000 contacts and read a page 100 times", |b| {
+        b.to_async(&rt)
+            .iter(|| async { address_book_page_benchmark(black_box(5000), black_box(100)).await })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,142 @@
+//! Fixture generator for synthetic mailboxes used by the receive benchmarks.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Message encrypted by bob@example.net to alice@example.org.
+const ENCRYPTED_MSG: &str =
+    include_str!("../../test-data/message/encrypted_with_received_headers.eml");
+const ENCRYPTED_MSG_ID: &str = "Message-ID: <Mr.adQpEwndXLH.LPDdlFVJ7wG@example.net>";
+
+/// Kind of messages in a synthetic mailbox.
+#[derive(Debug, Clone, Copy)]
+pub enum MailKind {
+    /// Short plain text chat messages from a handful of senders.
+    Plain,
+
+    /// Chat messages encrypted to alice@example.org.
+    Encrypted,
+
+    /// Classic emails consisting of a large HTML part.
+    LargeHtml,
+
+    /// Group chat messages with many recipients.
+    ManyRecipients,
+}
+
+impl MailKind {
+    pub const ALL: [MailKind; 4] = [
+        MailKind::Plain,
+        MailKind::Encrypted,
+        MailKind::LargeHtml,
+        MailKind::ManyRecipients,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MailKind::Plain => "plain",
+            MailKind::Encrypted => "encrypted",
+            MailKind::LargeHtml => "large-html",
+            MailKind::ManyRecipients => "many-recipients",
+        }
+    }
+}
+
+fn plain_msg(seed: u32, i: usize) -> String {
+    let sender = i % 10;
+    format!(
+        "Subject: Benchmark
+Message-ID: Plain.{seed}.{i}@testrun.org
+Date: Sat, 07 Dec 2019 19:00:27 +0000
+To: alice@example.org
+From: sender{sender}@testrun.org
+Chat-Version: 1.0
+MIME-Version: 1.0
+Content-Type: text/plain; charset=utf-8; format=flowed; delsp=no
+
+Hello {i}, this is a short chat message.
+"
+    )
+}
+
+fn encrypted_msg(seed: u32, i: usize) -> String {
+    ENCRYPTED_MSG.replacen(
+        ENCRYPTED_MSG_ID,
+        &format!("Message-ID: <Enc.{seed}.{i}@example.net>"),
+        1,
+    )
+}
+
+fn large_html_msg(seed: u32, i: usize) -> String {
+    let paragraphs: String = (0..2000)
+        .map(|p| {
+            format!(
+                "<p style=\"color: #333\">Paragraph {p} with <b>bold</b>, \
+                 <a href=\"https://example.org/{p}\">a link</a> and some text.</p>\n"
+            )
+        })
+        .collect();
+    format!(
+        "Subject: Newsletter {i}
+Message-ID: Html.{seed}.{i}@example.net
+Date: Sat, 07 Dec 2019 19:00:27 +0000
+To: alice@example.org
+From: newsletter@example.net
+MIME-Version: 1.0
+Content-Type: text/html; charset=utf-8
+
+<html><head><title>Newsletter</title></head><body>
+{paragraphs}</body></html>
+"
+    )
+}
+
+fn many_recipients_msg(seed: u32, i: usize) -> String {
+    let recipients = (0..100)
+        .map(|r| format!("member{r}@example.net"))
+        .collect::<Vec<String>>()
+        .join(",\n ");
+    format!(
+        "Subject: Group message
+Message-ID: Group.{seed}.{i}@example.net
+Date: Sat, 07 Dec 2019 19:00:27 +0000
+To: alice@example.org,
+ {recipients}
+From: member0@example.net
+Chat-Version: 1.0
+Chat-Group-ID: group{seed}
+Chat-Group-Name: Benchmark group
+MIME-Version: 1.0
+Content-Type: text/plain; charset=utf-8; format=flowed; delsp=no
+
+Hello group, message {i}.
+"
+    )
+}
+
+/// Generates `count` raw messages of the given kind.
+///
+/// Messages generated with different `seed` have different Message-IDs,
+/// so they are not skipped as duplicates when received by the same account.
+pub fn generate_mailbox(kind: MailKind, count: usize, seed: u32) -> Vec<Vec<u8>> {
+    (0..count)
+        .map(|i| match kind {
+            MailKind::Plain => plain_msg(seed, i),
+            MailKind::Encrypted => encrypted_msg(seed, i),
+            MailKind::LargeHtml => large_html_msg(seed, i),
+            MailKind::ManyRecipients => many_recipients_msg(seed, i),
+        })
+        .map(String::into_bytes)
+        .collect()
+}
+
+/// Writes a synthetic mailbox to `dir` as `.eml` files, one file per message.
+pub fn write_mailbox(dir: &Path, kind: MailKind, count: usize) -> io::Result<()> {
+    let dir = dir.join(kind.name());
+    fs::create_dir_all(&dir)?;
+    for (i, raw) in generate_mailbox(kind, count, 0).iter().enumerate() {
+        fs::write(dir.join(format!("{i:04}.eml")), raw)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+#![recursion_limit = "256"]
+use std::path::Path;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use deltachat::{
+    config::Config, context::Context, key::preconfigure_keypair, receive_imf::receive_imf,
+    stock_str::StockStrings, Events,
+};
+use tempfile::tempdir;
+
+mod fixtures;
+
+use fixtures::{generate_mailbox, write_mailbox, MailKind};
+
+/// Number of messages received per iteration.
+const MAILBOX_SIZE: usize = 100;
+
+async fn create_context() -> Context {
+    let dir = tempdir().unwrap();
+    let dbfile = dir.path().join("db.sqlite");
+    let id = 100;
+    let context = Context::new(dbfile.as_path(), id, Events::new(), StockStrings::new())
+        .await
+        .unwrap();
+
+    let addr = "alice@example.org";
+    context.set_config(Config::Addr, Some(addr)).await.unwrap();
+    context
+        .set_config(Config::ConfiguredAddr, Some(addr))
+        .await
+        .unwrap();
+    context
+        .set_config(Config::Configured, Some("1"))
+        .await
+        .unwrap();
+    // Receive classic emails as well.
+    context
+        .set_config(Config::ShowEmails, Some("2"))
+        .await
+        .unwrap();
+    preconfigure_keypair(
+        &context,
+        include_str!("../../test-data/key/alice-secret.asc"),
+    )
+    .await
+    .unwrap();
+    context
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    // Set `DC_BENCH_FIXTURES_DIR` to inspect the generated messages.
+    if let Some(dir) = std::env::var_os("DC_BENCH_FIXTURES_DIR") {
+        for kind in MailKind::ALL {
+            write_mailbox(Path::new(&dir), kind, MAILBOX_SIZE).unwrap();
+        }
+    }
+
+    let mut group = c.benchmark_group("Receive pipeline");
+    for kind in MailKind::ALL {
+        group.bench_function(
+            format!("Receive {MAILBOX_SIZE} {} msgs", kind.name()),
+            |b| {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                let context = rt.block_on(create_context());
+                let mut seed = 0;
+
+                b.to_async(&rt).iter_batched(
+                    || {
+                        seed += 1;
+                        generate_mailbox(kind, MAILBOX_SIZE, seed)
+                    },
+                    |mailbox| {
+                        let ctx = context.clone();
+                        async move {
+                            for raw in &mailbox {
+                                receive_imf(&ctx, black_box(raw), false).await.unwrap();
+                            }
+                        }
+                    },
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -415,10 +415,22 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    As for `displayname` and `selfstatus`, also the avatar is sent to the recipients.
  *                    To save traffic, however, the avatar is attached only as needed
  *                    and also recoded to a reasonable size.
+ *                    SVG images are rasterized to PNG.
  * - `e2ee_enabled` = 0=no end-to-end-encryption, 1=prefer end-to-end-encryption (default)
  * - `mdns_enabled` = 0=do not send or request read receipts,
  *                    1=send and request read receipts
  *                    default=send and request read receipts, only send but not request if `bot` is set
+ * - `mdns_batch_delay` = delay in seconds for which read receipts are collected before sending,
+ *                    so that read receipts for several messages are sent as a single message,
+ *                    0=send read receipts at once (default).
+ * - `minimal_headers` = 1=strip optional metadata from outgoing messages:
+ *                    the Date header is rounded down to minutes and names of images, videos
+ *                    and voice messages do not contain the time of sending,
+ *                    0=send full metadata (default).
+ * - `send_domain_limits` = outbound rate limits per recipient domain
+ *                    as whitespace-separated `domain=N` entries,
+ *                    where N is the number of messages per minute, e.g. `gmail.com=20`.
+ *                    Messages to other domains are not limited (default: no limits).
  * - `bcc_self`     = 0=do not send a copy of outgoing messages to self,
  *                    1=send a copy of outgoing messages to self (default).
  *                    Sending messages to self is needed for a proper multi-account setup,
@@ -453,7 +465,8 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    0=do not save mime headers (default)
  * - `delete_device_after` = 0=do not delete messages from device automatically (default),
  *                    >=1=seconds, after which messages are deleted automatically from the device.
- *                    Messages in the "saved messages" chat (see dc_chat_is_self_talk()) are skipped.
+ *                    Messages in the "saved messages" chat (see dc_chat_is_self_talk()) are skipped,
+ *                    as well as chats and contacts exempted using the JSON-RPC API.
  *                    Messages are deleted whether they were seen or not, the UI should clearly point that out.
  *                    See also dc_estimate_deletion_cnt().
  * - `delete_server_after` = 0=do not delete messages from server automatically (default),
@@ -462,6 +475,57 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    "Saved messages" are deleted from the server as well as
  *                    e-mails matching the `show_emails` settings above, the UI should clearly point that out.
  *                    See also dc_estimate_deletion_cnt().
+ * - `deletion_undo_window` = seconds during which deleted messages and chats
+ *                    can be restored using dc_undo_last_deletion(), default 30.
+ *                    0=delete immediately.
+ * - `send_delay` = seconds outgoing messages are kept back before they are submitted, at most 60.
+ *                    Meanwhile, sending can be cancelled using dc_cancel_send(),
+ *                    see also dc_msg_get_send_delay_end().
+ *                    0=submit messages immediately (default).
+ * - `member_digest_threshold` = number of members above which membership changes in groups
+ *                    are summarized in one info message per day instead of one info message each.
+ *                    Changes affecting yourself are always shown.
+ *                    0=show every membership change (default).
+ * - `abuse_address` = address messages reported with dc_report_msg() are sent to
+ *                    if the chat has no moderators.
+ *                    If unset, reports are sent to the admin contact of chatmail relays (default).
+ * - `timezone` = IANA name of the timezone used for day markers, e.g. `Europe/Berlin`.
+ *                    UIs should update it when the timezone of the device changes;
+ *                    changing it emits #DC_EVENT_MSGS_CHANGED without chat ID
+ *                    and #DC_EVENT_CHATLIST_CHANGED so that message lists are reloaded.
+ *                    If unset, the timezone of the device is used (default).
+ * - `locale` = BCP 47 language tag used for sorting contact and chat names, e.g. `de-DE`.
+ *                    Locale-aware sorting requires core to be built with the `icu-collation` feature.
+ *                    If unset, names are compared case-insensitively (default).
+ * - `auto_archive_days` = number of days without activity after which chats are archived
+ *                    during housekeeping, see #DC_EVENT_CHATS_AUTO_ARCHIVED.
+ *                    Pinned chats, verified 1:1 chats, Saved Messages and the device chat
+ *                    are never archived automatically.
+ *                    0=do not archive chats automatically (default).
+ * - `watch_keywords` = newline-separated keywords to watch incoming messages for.
+ *                    Matching messages are returned by dc_get_watched_msgs()
+ *                    and flagged in #DC_EVENT_INCOMING_MSG, see dc_msg_get_watched_keyword().
+ *                    The option is synchronized to other devices.
+ * - `email_rules` = newline-separated rules assigning incoming classic emails,
+ *                    one rule per line as `<condition> <value> <action>`.
+ *                    `<condition>` is one of `sender-domain`, `list-id` or `plus-suffix`
+ *                    (the part after `+` in an own address the email is sent to),
+ *                    `<action>` is one of `show`, `hide` or `mailbox`.
+ *                    `mailbox` routes matching emails into a single chat, see dc_get_mailbox_chat_id().
+ *                    The first matching rule applies, if no rule matches, `show_emails` decides.
+ *                    The option is synchronized to other devices.
+ * - `remote_backup_url` = HTTPS URL of a WebDAV collection or S3 bucket
+ *                    encrypted backups are uploaded to automatically, unset by default.
+ *                    Backups can be listed and restored using the JSON-RPC API.
+ * - `remote_backup_kind` = `webdav` (default) or `s3`.
+ * - `remote_backup_user` = WebDAV user or S3 access key ID.
+ * - `remote_backup_password` = WebDAV password or S3 secret access key.
+ * - `remote_backup_region` = region of the S3 bucket, default `us-east-1`.
+ * - `remote_backup_passphrase` = passphrase the remote backups are encrypted with,
+ *                    needed to restore them.
+ * - `remote_backup_interval` = hours between automated remote backups, default 24.
+ *                    0=upload backups only on demand.
+ * - `remote_backup_keep` = number of remote backups to keep, default 7.
  * - `media_quality` = DC_MEDIA_QUALITY_BALANCED (0) =
  *                    good outgoing images/videos/voice quality at reasonable sizes (default)
  *                    DC_MEDIA_QUALITY_WORSE (1)
@@ -470,6 +534,21 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    The library uses the `media_quality` setting to use different defaults
  *                    for recoding images sent with type #DC_MSG_IMAGE.
  *                    If needed, recoding other file types is up to the UI.
+ * - `jpeg_quality` = JPEG quality used when recoding images, 1-100, default 75.
+ * - `balanced_image_size` = Maximum width and height of images sent with
+ *                    DC_MEDIA_QUALITY_BALANCED, larger images are scaled down, default 1280.
+ * - `balanced_image_bytes` = Maximum size in bytes of images sent without recoding
+ *                    with DC_MEDIA_QUALITY_BALANCED, default 500000.
+ * - `worse_image_size` = Same as `balanced_image_size` for DC_MEDIA_QUALITY_WORSE, default 640.
+ * - `worse_image_bytes` = Same as `balanced_image_bytes` for DC_MEDIA_QUALITY_WORSE, default 130000.
+ * - `max_image_pixels` = Maximum number of pixels of images decoded for recoding,
+ *                    default 100000000, 0=no limit.
+ *                    Larger images, e.g. decompression bombs, are sent as #DC_MSG_FILE instead.
+ * - `max_image_decoded_bytes` = Maximum number of bytes allocated for decoding an image,
+ *                    default 536870912 (512 MiB), 0=no limit.
+ * - `strip_attachment_metadata` = 1=remove metadata such as the location, the recording device or the author
+ *                    from videos, audio files and PDF documents before sending them (default),
+ *                    0=send them unmodified.
  * - `webrtc_instance` = webrtc instance to use for videochats in the form
  *                    `[basicwebrtc:|jitsi:]https://example.com/subdir#roomname=$ROOM`
  *                    if the URL is prefixed by `basicwebrtc`, the server is assumed to be of the type
@@ -511,6 +590,10 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    and older Delta Chat versions.
  *                    1 = enable.
  *                    0 = disable (default).
+ * - `protect_subject` = 1=send the subject of encrypted messages only encrypted
+ *                    as a protected header, the unencrypted subject is `[...]` (default).
+ *                    0=additionally send the subject unencrypted
+ *                    for email clients not supporting protected headers.
  * - `gossip_period` = How often to gossip Autocrypt keys in chats with multiple recipients, in
  *                    seconds. 2 days by default.
  *                    This is not supposed to be changed by UIs and only used for testing.
@@ -537,6 +620,9 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  * - `webxdc_realtime_enabled` = Whether the realtime APIs should be enabled.
  *                               0 = WebXDC realtime API is disabled and behaves as noop.
  *                               1 = WebXDC realtime API is enabled (default).
+ * - `lazy_start` = 1=defer starting IO in dc_start_io() until the chatlist is loaded for the first time
+ *                    or a few seconds passed, so the chatlist appears sooner on slow devices,
+ *                    0=start IO immediately (default).
  *
  * If you want to retrieve a value, use dc_get_config().
  *
@@ -591,6 +677,64 @@ char*           dc_get_config                (dc_context_t* context, const char*
 int             dc_set_stock_translation(dc_context_t* context, uint32_t stock_id, const char* stock_msg);
 
 
+/**
+ * Load translations of the strings generated by the core for this account.
+ *
+ * Translations set with dc_set_stock_translation() are shared by all accounts
+ * of an account manager.
+ * This function allows to use a different language for a single account.
+ * Strings missing in the file fall back to the shared translations.
+ *
+ * The file is a JSON object mapping the @ref DC_STR constants to the translations,
+ * e.g. `{"1": "Keine Nachrichten."}`.
+ * The translations are stored in the account,
+ * so they are used after restarts and the file can be deleted afterwards.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param path Path to the translations file,
+ *     NULL to use the shared translations again.
+ * @return int (==0 on error, 1 on success)
+ */
+int             dc_set_stock_translations_file(dc_context_t* context, const char* path);
+
+
+/**
+ * Set a template for the body of outgoing messages using a stock string.
+ *
+ * Unlike translations set with dc_set_stock_translation(),
+ * templates are stored in the account and take precedence over the translation.
+ * They allow to customize the texts shown to recipients not using Delta Chat,
+ * e.g. in group member added or removed messages.
+ *
+ * Only the following stock strings can be customized:
+ * #DC_STR_I_ADDED_MEMBER, #DC_STR_I_REMOVED_MEMBER, #DC_STR_I_LEFT_GROUP,
+ * #DC_STR_AC_SETUP_MSG_BODY, #DC_STR_SUBJECT_FOR_NEW_CONTACT and #DC_STR_SECUREJOIN_MSG_BODY.
+ * The template may only use placeholders as `%1$s` that are used by the default string.
+ *
+ * The function will emit warnings if it returns an error state.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param stock_id The integer ID of the stock message, one of the @ref DC_STR constants.
+ * @param template The template to be used, NULL to reset the template.
+ * @return int (==0 on error, 1 on success)
+ */
+int             dc_set_stock_template(dc_context_t* context, uint32_t stock_id, const char* template);
+
+
+/**
+ * Get the template set with dc_set_stock_template().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param stock_id The integer ID of the stock message, one of the @ref DC_STR constants.
+ * @return The template or NULL if no template is set.
+ *     The returned string must be released using dc_str_unref().
+ */
+char*           dc_get_stock_template(dc_context_t* context, uint32_t stock_id);
+
+
 /**
  * Set configuration values from a QR code.
  * Before this function is called, dc_check_qr() should confirm the type of the
@@ -625,6 +769,25 @@ int             dc_set_config_from_qr   (dc_context_t* context, const char* qr);
 char*           dc_get_info                  (const dc_context_t* context);
 
 
+/**
+ * Get the features of the server of the account,
+ * so that the UI can enable features depending on the server.
+ *
+ * The features are updated when the account is configured
+ * and whenever the connection to the server is established.
+ * They are returned as a JSON object with the keys
+ * `push`, `chatmail` and `inbox_only` (booleans),
+ * `max_message_size` (bytes) and `retention_days` (numbers or null if unknown).
+ * #DC_EVENT_SERVER_CAPABILITIES_CHANGED is emitted when they change.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return JSON object, must be released using dc_str_unref() after usage.
+ *     Empty string on errors.
+ */
+char*           dc_get_server_capabilities   (const dc_context_t* context);
+
+
 /**
  * Get URL that can be used to initiate an OAuth2 authorization.
  *
@@ -827,6 +990,80 @@ void            dc_start_io     (dc_context_t* context);
  */
 void            dc_stop_io(dc_context_t* context);
 
+
+/**
+ * Pause IO for the given reason, e.g. while a backup is in progress
+ * or while the battery saver is on.
+ *
+ * Unlike dc_stop_io(), pauses are counted per reason,
+ * so different parts of the app can pause IO without interfering with each other.
+ * IO is resumed when dc_resume_io() was called for every call to dc_pause_io().
+ * If dc_start_io() or dc_stop_io() is called while IO is paused,
+ * the call takes effect when IO is resumed.
+ *
+ * The reasons are shown in dc_get_connectivity_html().
+ *
+ * @memberof dc_context_t
+ * @param context The context object as created by dc_context_new().
+ * @param reason Non-empty reason for pausing IO.
+ * @return 1=success, 0=error.
+ */
+int             dc_pause_io                  (dc_context_t* context, const char* reason);
+
+
+/**
+ * Release one pause of IO made with dc_pause_io() for the given reason.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as created by dc_context_new().
+ * @param reason The reason passed to dc_pause_io().
+ * @return 1=success, 0=error, e.g. because IO is not paused for the reason.
+ */
+int             dc_resume_io                 (dc_context_t* context, const char* reason);
+
+
+/**
+ * Check whether this process owns the account.
+ *
+ * If several processes use the same account,
+ * e.g. the app and a share target running in a separate process,
+ * only the process which called dc_start_io() first owns it.
+ * In other processes, dc_start_io() starts IO only
+ * once the owner released the account with dc_release_ownership() or exited;
+ * #DC_EVENT_OWNERSHIP_CHANGED is emitted then.
+ * Other processes can still read the database and create messages,
+ * which are sent by the owner process.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return 1=this process owns the account, 0=another process owns it.
+ */
+int             dc_is_owner                  (dc_context_t* context);
+
+
+/**
+ * Stop IO and release the ownership of the account,
+ * so that another process can start IO, see dc_is_owner().
+ *
+ * Call this when a short-lived process such as a share target is done with the account.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ */
+void            dc_release_ownership         (dc_context_t* context);
+
+
+/**
+ * Get the reasons IO is currently paused for with dc_pause_io().
+ *
+ * @memberof dc_context_t
+ * @param context The context object as created by dc_context_new().
+ * @return The reasons in alphabetical order, separated by newlines,
+ *     empty string if IO is not paused.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_io_pause_reasons      (dc_context_t* context);
+
 /**
  * This function should be called when there is a hint
  * that the network is available again,
@@ -877,6 +1114,7 @@ int             dc_preconfigure_keypair        (dc_context_t* context, const cha
 #define         DC_GCL_NO_SPECIALS           0x02
 #define         DC_GCL_ADD_ALLDONE_HINT      0x04
 #define         DC_GCL_FOR_FORWARDING        0x08
+#define         DC_GCL_SORT_BY_NAME          0x10
 
 
 /**
@@ -917,6 +1155,9 @@ int             dc_preconfigure_keypair        (dc_context_t* context, const cha
  *       not needed when DC_GCL_ARCHIVED_ONLY is already set)
  *     - if the flag DC_GCL_ADD_ALLDONE_HINT is set, DC_CHAT_ID_ALLDONE_HINT
  *       is added as needed.
+ *     - if the flag DC_GCL_SORT_BY_NAME is set, chats are sorted alphabetically by name
+ *       according to the `locale` config option.
+ *       Special entries and pinned chats stay on top.
  * @param query_str An optional query for filtering the list. Only chats matching this query
  *     are returned. Give NULL for no filtering. When `is:unread` is contained in the query,
  *     the chatlist is filtered such that only chats with unread messages show up.
@@ -931,6 +1172,26 @@ int             dc_preconfigure_keypair        (dc_context_t* context, const cha
 dc_chatlist_t*  dc_get_chatlist              (dc_context_t* context, int flags, const char* query_str, uint32_t query_id);
 
 
+/**
+ * Enable or disable granular events for changes of the default chatlist.
+ *
+ * If enabled, changes of the chatlist returned by dc_get_chatlist() without flags and query
+ * are additionally reported as @ref DC_EVENT_CHATLIST_ITEM_MOVED, @ref DC_EVENT_CHATLIST_ITEM_REMOVED
+ * and @ref DC_EVENT_CHATLIST_ITEM_FRESH_MSG_CNT_CHANGED,
+ * so the UI can update the list without reloading it.
+ * The events are relative to the chatlist at the time of enabling,
+ * so the chatlist should be loaded after calling this function.
+ *
+ * The events are disabled by default
+ * as computing them requires loading the chatlist after each change.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param enabled 1=enable chatlist diff events, 0=disable them.
+ */
+void            dc_set_chatlist_diff_events  (dc_context_t* context, int enabled);
+
+
 // handle chats
 
 /**
@@ -1194,6 +1455,34 @@ int dc_send_webxdc_status_update (dc_context_t* context, uint32_t msg_id, const
 char* dc_get_webxdc_status_updates (dc_context_t* context, uint32_t msg_id, uint32_t serial);
 
 
+/**
+ * Export a webxdc app together with all its status updates to a file,
+ * e.g. to back up a shared list independently of the chat
+ * or to move it to another chat using dc_import_webxdc().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message with the webxdc instance.
+ * @param path The file to write.
+ * @return 1=success, 0=error
+ */
+int dc_export_webxdc (dc_context_t* context, uint32_t msg_id, const char* path);
+
+
+/**
+ * Import a webxdc app exported with dc_export_webxdc().
+ * A new instance is sent to the given chat
+ * together with all status updates of the exported app.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat to send the app to.
+ * @param path The file written by dc_export_webxdc().
+ * @return The ID of the new webxdc instance, 0 on errors.
+ */
+uint32_t dc_import_webxdc (dc_context_t* context, uint32_t chat_id, const char* path);
+
+
 /**
  * Set Webxdc file as integration.
  * see dc_init_webxdc_integration() for more details about Webxdc integrations.
@@ -1355,6 +1644,24 @@ int             dc_was_device_msg_ever_added (dc_context_t* context, const char*
 dc_msg_t*       dc_get_draft                 (dc_context_t* context, uint32_t chat_id);
 
 
+/**
+ * Get the members of a protected chat to whom a message can't be encrypted.
+ *
+ * Messages to protected chats are only encrypted to verified keys,
+ * so sending fails if the key of a member became unavailable or unverified.
+ * The UI should call this function before sending
+ * and warn the user about the returned members.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID to check.
+ * @return An array of contact IDs, empty if the message can be sent
+ *     or the chat is not protected.
+ *     Must be freed using dc_array_unref() when done.
+ */
+dc_array_t*     dc_validate_draft            (dc_context_t* context, uint32_t chat_id);
+
+
 #define         DC_GCM_ADDDAYMARKER          0x01
 #define         DC_GCM_INFO_ONLY             0x02
 
@@ -1491,6 +1798,86 @@ dc_array_t*     dc_get_fresh_msgs            (dc_context_t* context);
 dc_array_t*     dc_get_next_msgs             (dc_context_t* context);
 
 
+/**
+ * Verify the attachments of all messages
+ * against the checksums stored when they were created.
+ *
+ * Storage corruption may break attachments silently,
+ * the UI can use this function e.g. in the storage settings
+ * to show the affected messages so that the user can delete them
+ * or ask the sender to resend them.
+ * Attachments created by older versions are assumed to be intact.
+ *
+ * Every attachment is read completely, so this function may take a while
+ * and should not be called from the UI thread.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @return An array of IDs of messages with corrupted or missing attachments,
+ *     must be dc_array_unref()'d when no longer used.
+ *     On errors, the list is empty. NULL is never returned.
+ */
+dc_array_t*     dc_check_blob_integrity      (dc_context_t* context);
+
+
+/**
+ * Rotate the JPEG or PNG image at the given path in place
+ * as described by its Exif orientation.
+ *
+ * Unlike the recoding done when sending, the image is not scaled down,
+ * so the UI can use this function to fix the orientation of draft attachments
+ * and avatar crops before showing or editing them.
+ * The Exif metadata is removed as it is not valid for the rotated image.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param path The image file to rotate.
+ * @return 1=the image was rotated,
+ *     0=the image does not need to be rotated or there was an error.
+ */
+int             dc_normalize_orientation     (dc_context_t* context, const char* path);
+
+
+/**
+ * Callback extracting the metadata of a video, see dc_set_video_decoder().
+ *
+ * The callback is called from a background thread
+ * and may be called from several threads at the same time.
+ *
+ * @param userdata The pointer given to dc_set_video_decoder().
+ * @param path The video file.
+ * @param poster_path The file to write a poster frame to,
+ *     encoded as JPEG or PNG. The core deletes the file after reading it.
+ *     Do not create the file if no poster frame can be extracted.
+ * @param duration_ms Set this to the duration in milliseconds,
+ *     leave it at 0 if the duration is unknown.
+ * @param width Set this to the width in pixels as displayed, i.e. after applying the rotation,
+ *     leave it at 0 if the dimensions are unknown.
+ * @param height Set this to the height in pixels as displayed,
+ *     leave it at 0 if the dimensions are unknown.
+ * @return 1=success, 0=the video cannot be decoded.
+ */
+typedef int (*dc_video_decoder_t) (void* userdata, const char* path, const char* poster_path, uint32_t* duration_ms, uint32_t* width, uint32_t* height);
+
+
+/**
+ * Set the decoder used to extract poster frames, durations and dimensions of videos.
+ *
+ * The core does not contain a video decoder.
+ * Without a decoder, duration and dimensions are read from MP4 and QuickTime containers,
+ * but no thumbnail is sent along with videos.
+ * Values returned by the decoder take precedence over the values read by the core.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param decoder The callback, see dc_video_decoder_t.
+ *     NULL removes a decoder set before.
+ * @param userdata Pointer passed to the callback as is,
+ *     must be valid until the decoder is replaced or the context is released.
+ */
+void            dc_set_video_decoder         (dc_context_t* context, dc_video_decoder_t decoder, void* userdata);
+
+
 /**
  * Waits for notification of new messages
  * and returns an array of new message IDs.
@@ -1532,6 +1919,39 @@ dc_array_t*     dc_wait_next_msgs            (dc_context_t* context);
 void            dc_marknoticed_chat          (dc_context_t* context, uint32_t chat_id);
 
 
+/**
+ * Get the first incoming message after the read position of a chat.
+ *
+ * The read position is the newest message marked as seen with dc_markseen_msgs()
+ * and is synchronized across own devices,
+ * so the UI can scroll to the returned message when opening the chat
+ * and continue where the user stopped reading, also on another device.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The chat ID.
+ * @return The ID of the first unread message, 0 if there are no unread messages.
+ */
+uint32_t        dc_get_first_unread_msg      (dc_context_t* context, uint32_t chat_id);
+
+
+/**
+ * Tell the core which chat the user is interacting with.
+ *
+ * While the app is in the foreground, see dc_accounts_set_foreground(),
+ * new messages of this chat are fetched before other folders are scanned
+ * and webxdc updates of this chat are sent without delay.
+ *
+ * Call this function when a chat is opened
+ * and with `chat_id=0` when it is closed.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The chat ID the user is interacting with, 0 if no chat is open.
+ */
+void            dc_set_interactive_chat      (dc_context_t* context, uint32_t chat_id);
+
+
 /**
  * Returns all message IDs of the given types in a given chat or any chat.
  * Typically used to show a gallery.
@@ -1553,6 +1973,50 @@ void            dc_marknoticed_chat          (dc_context_t* context, uint32_t ch
 dc_array_t*     dc_get_chat_media            (dc_context_t* context, uint32_t chat_id, int msg_type, int msg_type2, int msg_type3);
 
 
+/**
+ * Get incoming messages containing any of the keywords
+ * set with the `watch_keywords` config option.
+ * Messages are checked when they are received.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The chat ID to get watched messages for, 0 for all chats.
+ * @return An array with message IDs, newest first.
+ *     Must be dc_array_unref()'d after usage.
+ */
+dc_array_t*     dc_get_watched_msgs          (dc_context_t* context, uint32_t chat_id);
+
+
+/**
+ * Get the chat collecting classic emails
+ * assigned by rules with the `mailbox` action, see the `email_rules` config option.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @return The chat ID of the mailbox chat,
+ *     0 if no email was assigned to the mailbox chat yet.
+ */
+uint32_t        dc_get_mailbox_chat_id       (dc_context_t* context);
+
+
+/**
+ * Save copies of all attachments of a chat to a directory,
+ * e.g. to export all media of a chat.
+ *
+ * The original file names are used,
+ * files with the same name are numbered as with dc_msg_save_file_ex()
+ * and DC_SAVE_FILE_AUTO_NUMBER.
+ * Messages that are not fully downloaded are skipped.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The chat to save the attachments of.
+ * @param dir Destination directory, created if it does not exist.
+ * @return The number of saved files, -1 on failure.
+ */
+int             dc_save_all_attachments      (dc_context_t* context, uint32_t chat_id, const char* dir);
+
+
 /**
  * Set chat visibility to pinned, archived or normal.
  *
@@ -1567,6 +2031,68 @@ dc_array_t*     dc_get_chat_media            (dc_context_t* context, uint32_t ch
 void            dc_set_chat_visibility       (dc_context_t* context, uint32_t chat_id, int visibility);
 
 
+#define         DC_MDN_POLICY_DEFAULT        0
+#define         DC_MDN_POLICY_ALWAYS         1
+#define         DC_MDN_POLICY_NEVER          2
+#define         DC_MDN_POLICY_VERIFIED_ONLY  3
+
+
+/**
+ * Set the read receipt policy of a chat.
+ *
+ * The chat policy takes precedence over the policy of the contact
+ * set by dc_set_contact_mdn_policy().
+ * If neither is set, the `mdns_enabled` config option decides
+ * whether read receipts are sent.
+ * Changing the policy also affects read receipts not sent yet.
+ *
+ * Calling this function results in the event #DC_EVENT_CHAT_MODIFIED.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat to set the policy for.
+ * @param policy DC_MDN_POLICY_DEFAULT to use the contact policy,
+ *     DC_MDN_POLICY_ALWAYS to always send read receipts,
+ *     DC_MDN_POLICY_NEVER to never send read receipts
+ *     or DC_MDN_POLICY_VERIFIED_ONLY to send read receipts only to verified contacts.
+ */
+void            dc_set_chat_mdn_policy       (dc_context_t* context, uint32_t chat_id, int policy);
+
+
+/**
+ * Set the subject template of a chat.
+ *
+ * The template is used as the subject of outgoing messages
+ * instead of the automatic subject such as "Message from Alice",
+ * this is useful for classic email recipients whose clients thread messages by subject.
+ * Replies are prefixed with "Re: ".
+ * `{name}` in the template is replaced with the own display name.
+ * A subject set with dc_msg_set_subject() takes precedence over the template.
+ *
+ * Calling this function results in the event #DC_EVENT_CHAT_MODIFIED.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat to set the template for.
+ * @param template The subject template.
+ *     NULL or an empty string restores the automatic subject.
+ * @return 1=success, 0=error.
+ */
+int             dc_set_chat_subject_template (dc_context_t* context, uint32_t chat_id, const char* template);
+
+
+/**
+ * Get the subject template of a chat set with dc_set_chat_subject_template().
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat.
+ * @return The subject template, empty string if none is set.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_chat_subject_template (dc_context_t* context, uint32_t chat_id);
+
+
 /**
  * Delete a chat.
  *
@@ -1594,6 +2120,55 @@ void            dc_set_chat_visibility       (dc_context_t* context, uint32_t ch
  */
 void            dc_delete_chat               (dc_context_t* context, uint32_t chat_id);
 
+
+/**
+ * Cancel recoding images of messages being sent to a chat,
+ * e.g. because the user discarded the message while it was being prepared.
+ *
+ * The progress of recoding is reported by #DC_EVENT_IMAGE_RECODE_PROGRESS.
+ * If recoding is cancelled, sending the message fails
+ * and #DC_EVENT_IMAGE_RECODE_PROGRESS is emitted with data2=0.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat to cancel image recoding for.
+ */
+void            dc_cancel_image_recoding     (dc_context_t* context, uint32_t chat_id);
+
+
+/**
+ * Stage the attachment of a message being composed.
+ *
+ * The attachment set with dc_msg_set_file() is copied into the blobdir
+ * and prepared for sending in the background,
+ * e.g. images are recoded, so that sending the message later is instant.
+ * The message object itself is not changed.
+ * The progress is reported by #DC_EVENT_ATTACHMENT_STAGING_PROGRESS.
+ *
+ * Once the message is sent, the staged attachment is added to it with dc_msg_set_staged_attachment().
+ * Staged attachments that are not used are discarded
+ * when the draft of the chat is deleted with dc_set_draft()
+ * and automatically after a day.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat the message will be sent to.
+ * @param msg The message object with the attachment to stage.
+ * @return The ID of the staged attachment, 0 on errors.
+ */
+uint32_t        dc_stage_attachment          (dc_context_t* context, uint32_t chat_id, const dc_msg_t* msg);
+
+
+/**
+ * Discard a staged attachment, e.g. because the user removed it from the message being composed.
+ * If the attachment is still being prepared, preparing it is cancelled.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param staged_id The ID of the staged attachment as returned from dc_stage_attachment().
+ */
+void            dc_discard_staged_attachment (dc_context_t* context, uint32_t staged_id);
+
 /**
  * Block a chat.
  *
@@ -1609,6 +2184,22 @@ void            dc_delete_chat               (dc_context_t* context, uint32_t ch
  */
 void            dc_block_chat                (dc_context_t* context, uint32_t chat_id);
 
+
+/**
+ * Leave a group, delete it and block the contact who added the user.
+ *
+ * This is meant for groups that look like spam,
+ * see dc_chat_is_quarantined().
+ * If the group is a contact request, no leave message is sent,
+ * so the creator does not learn that the address is in use.
+ * Later messages to the group do not recreate it.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the group chat.
+ */
+void            dc_leave_and_block_creator   (dc_context_t* context, uint32_t chat_id);
+
 /**
  * Accept a contact request chat.
  *
@@ -2002,46 +2593,172 @@ char*           dc_get_mime_headers          (dc_context_t* context, uint32_t ms
  *
  * @memberof dc_context_t
  * @param context The context object.
- * @param msg_ids An array of uint32_t containing all message IDs that should be deleted.
- * @param msg_cnt The number of messages IDs in the msg_ids array.
+ * @param msg_ids An array of uint32_t containing all message IDs that should be deleted.
+ * @param msg_cnt The number of messages IDs in the msg_ids array.
+ */
+void            dc_delete_msgs               (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
+
+
+/**
+ * Restore the messages or the chat deleted last.
+ *
+ * Deletions done by dc_delete_msgs() or dc_delete_chat()
+ * can be restored for the number of seconds configured in `deletion_undo_window`,
+ * afterwards the data is purged.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @return 1 if something was restored, 0 if there is nothing to restore.
+ */
+int             dc_undo_last_deletion        (dc_context_t* context);
+
+
+/**
+ * Cancel sending a message kept back because of the `send_delay` option of dc_set_config()
+ * and delete the message.
+ * Nothing of the message has left the device at this point.
+ *
+ * Cancelling fails once the delay is over, see dc_msg_get_send_delay_end().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message to cancel sending.
+ * @return 1 if sending was cancelled, 0 if the delay is over or on errors.
+ */
+int             dc_cancel_send               (dc_context_t* context, uint32_t msg_id);
+
+
+/**
+ * Forward messages to another chat.
+ *
+ * All types of messages can be forwarded,
+ * however, they will be flagged as such (dc_msg_is_forwarded() is set).
+ *
+ * Original sender, info-state and webxdc updates are not forwarded on purpose.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_ids An array of uint32_t containing all message IDs that should be forwarded.
+ * @param msg_cnt The number of messages IDs in the msg_ids array.
+ * @param chat_id The destination chat ID.
+ */
+void            dc_forward_msgs              (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt, uint32_t chat_id);
+
+
+/**
+ * Resend messages and make information available for newly added chat members.
+ * Resending sends out the original message, however, recipients and webxdc-status may differ.
+ * Clients that already have the original message can still ignore the resent message as
+ * they have tracked the state by dedicated updates.
+ *
+ * Some messages cannot be resent, eg. info-messages, drafts, already pending messages or messages that are not sent by SELF.
+ * In this case, the return value indicates an error and the error string from dc_get_last_error() should be shown to the user.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_ids An array of uint32_t containing all message IDs that should be resend.
+ *     All messages must belong to the same chat.
+ * @param msg_cnt The number of messages IDs in the msg_ids array.
+ * @return 1=all messages are queued for resending, 0=error
+ */
+int             dc_resend_msgs               (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
+
+
+/**
+ * Report an offending message, e.g. spam in a public group.
+ *
+ * The report contains the message with its headers and the given reason.
+ * It is sent to the moderators of the chat
+ * or, if there are none, to the address configured as `abuse_address`
+ * or the admin contact of chatmail relays.
+ * The recipients get #DC_EVENT_INCOMING_MSG_REPORT.
+ *
+ * If there is nobody to send the report to, the return value indicates an error
+ * and the error string from dc_get_last_error() should be shown to the user.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the message to report.
+ * @param reason The reason for the report as given by the user, may be NULL.
+ * @return 1=report is queued for sending, 0=error
+ */
+int             dc_report_msg                (dc_context_t* context, uint32_t msg_id, const char* reason);
+
+
+/**
+ * Place a call in a 1:1 chat.
+ *
+ * The core only transports the signaling messages,
+ * the media connection has to be established by the UI,
+ * e.g. using WebRTC with the given payload as SDP offer.
+ *
+ * A call invite is added to the chat as an info message,
+ * the callee gets #DC_EVENT_INCOMING_CALL.
+ * When the callee accepts the call, #DC_EVENT_OUTGOING_CALL_ACCEPTED is emitted.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param chat_id The 1:1 chat to place the call in.
+ * @param payload Opaque payload passed to the callee, typically an SDP offer.
+ * @return ID of the call invite message, 0 on errors.
+ */
+uint32_t        dc_place_outgoing_call       (dc_context_t* context, uint32_t chat_id, const char* payload);
+
+
+/**
+ * Accept an incoming call,
+ * typically after #DC_EVENT_INCOMING_CALL was received.
+ *
+ * Other devices of the user get #DC_EVENT_INCOMING_CALL_ACCEPTED and stop ringing.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id ID of the call invite message.
+ * @param payload Opaque payload passed to the caller, typically an SDP answer.
+ * @return 1=success, 0=error
+ */
+int             dc_accept_incoming_call      (dc_context_t* context, uint32_t msg_id, const char* payload);
+
+
+/**
+ * End a call.
+ *
+ * An incoming call that was not accepted yet is rejected,
+ * an outgoing call that was not accepted yet is cancelled.
+ * The other side gets #DC_EVENT_CALL_ENDED or #DC_EVENT_CALL_MISSED.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id ID of the call invite message.
+ * @return 1=success, 0=error
  */
-void            dc_delete_msgs               (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
+int             dc_end_call                  (dc_context_t* context, uint32_t msg_id);
 
 
 /**
- * Forward messages to another chat.
- *
- * All types of messages can be forwarded,
- * however, they will be flagged as such (dc_msg_is_forwarded() is set).
- *
- * Original sender, info-state and webxdc updates are not forwarded on purpose.
+ * Get the state of a call.
  *
  * @memberof dc_context_t
  * @param context The context object.
- * @param msg_ids An array of uint32_t containing all message IDs that should be forwarded.
- * @param msg_cnt The number of messages IDs in the msg_ids array.
- * @param chat_id The destination chat ID.
+ * @param msg_id ID of the call invite message.
+ * @return One of the @ref DC_CALL_STATE constants.
  */
-void            dc_forward_msgs              (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt, uint32_t chat_id);
+int             dc_get_call_state            (dc_context_t* context, uint32_t msg_id);
 
 
 /**
- * Resend messages and make information available for newly added chat members.
- * Resending sends out the original message, however, recipients and webxdc-status may differ.
- * Clients that already have the original message can still ignore the resent message as
- * they have tracked the state by dedicated updates.
+ * Get the payload of the other side of a call.
  *
- * Some messages cannot be resent, eg. info-messages, drafts, already pending messages or messages that are not sent by SELF.
- * In this case, the return value indicates an error and the error string from dc_get_last_error() should be shown to the user.
+ * For incoming calls, this is the payload of the caller, e.g. an SDP offer.
+ * For outgoing calls, this is the payload of the callee once the call was accepted.
  *
  * @memberof dc_context_t
  * @param context The context object.
- * @param msg_ids An array of uint32_t containing all message IDs that should be resend.
- *     All messages must belong to the same chat.
- * @param msg_cnt The number of messages IDs in the msg_ids array.
- * @return 1=all messages are queued for resending, 0=error
+ * @param msg_id ID of the call invite message.
+ * @return The payload, empty string if there is none.
+ *     Must be released using dc_str_unref() after usage.
  */
-int             dc_resend_msgs               (dc_context_t* context, const uint32_t* msg_ids, int msg_cnt);
+char*           dc_get_call_payload          (dc_context_t* context, uint32_t msg_id);
 
 
 /**
@@ -2151,6 +2868,10 @@ uint32_t        dc_create_contact            (dc_context_t* context, const char*
 
 #define         DC_GCL_VERIFIED_ONLY         0x01
 #define         DC_GCL_ADD_SELF              0x02
+#define         DC_GCL_WITH_CHAT_ONLY        0x04
+
+#define         DC_CONTACT_SORT_LAST_SEEN    0
+#define         DC_CONTACT_SORT_NAME         1
 
 
 /**
@@ -2179,6 +2900,34 @@ uint32_t        dc_create_contact            (dc_context_t* context, const char*
 int             dc_add_address_book          (dc_context_t* context, const char* addr_book);
 
 
+/**
+ * Apply changes of an address book to the contacts.
+ *
+ * Unlike dc_add_address_book(), only the changed entries need to be passed,
+ * so the OS address book does not need to be resubmitted on every change.
+ * `source` tags the address book the entries come from, e.g. an account on the device;
+ * it is tracked for every contact which address books list it.
+ *
+ * Contacts in `upserts` are added or get their name updated
+ * unless the name was changed manually by the user.
+ * Addresses in `deletions` are removed from the address book `source`.
+ * A contact known only from address books is deleted once it is removed from all of them;
+ * if there are chats with the contact, only the name from the address book is removed.
+ *
+ * If any contact is modified, the event #DC_EVENT_CONTACTS_CHANGED is sent.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param source Non-empty tag of the address book.
+ * @param upserts Added or changed entries, a multi-line string in the format
+ *     `Name one\nAddress one\nName two\nAddress two` as for dc_add_address_book().
+ *     NULL if there are none.
+ * @param deletions Removed e-mail addresses, one per line. NULL if there are none.
+ * @return The number of modified contacts, -1 on errors.
+ */
+int             dc_apply_address_book_delta  (dc_context_t* context, const char* source, const char* upserts, const char* deletions);
+
+
 /**
  * Returns known and unblocked contacts.
  *
@@ -2198,6 +2947,42 @@ int             dc_add_address_book          (dc_context_t* context, const char*
 dc_array_t*     dc_get_contacts              (dc_context_t* context, uint32_t flags, const char* query);
 
 
+/**
+ * Returns a page of known and unblocked contacts.
+ *
+ * Unlike dc_get_contacts(), filtering, sorting and paging is done by the database,
+ * so this is suitable for accounts with a lot of contacts.
+ * SELF is never returned.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param flags A combination of flags:
+ *     - if the flag DC_GCL_VERIFIED_ONLY is set, only contacts with a verified key are returned.
+ *     - if the flag DC_GCL_WITH_CHAT_ONLY is set, only contacts with an unblocked 1:1 chat are returned.
+ * @param query A string to filter the list. NULL for no filtering.
+ * @param sort DC_CONTACT_SORT_LAST_SEEN to return recently seen contacts first
+ *     or DC_CONTACT_SORT_NAME to sort alphabetically by name.
+ * @param offset The number of contacts to skip.
+ * @param limit The maximum number of contacts to return, 0 for no limit.
+ * @return An array containing the contact IDs of the page. Must be dc_array_unref()'d
+ *     after usage.
+ */
+dc_array_t*     dc_get_contacts_page         (dc_context_t* context, uint32_t flags, const char* query, int sort, int offset, int limit);
+
+
+/**
+ * Returns the number of known and unblocked contacts matching the filter,
+ * see dc_get_contacts_page().
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param flags A combination of flags as for dc_get_contacts_page().
+ * @param query A string to filter the list. NULL for no filtering.
+ * @return The number of matching contacts.
+ */
+int             dc_get_contacts_cnt          (dc_context_t* context, uint32_t flags, const char* query);
+
+
 /**
  * Get the number of blocked contacts.
  *
@@ -2232,6 +3017,89 @@ dc_array_t*     dc_get_blocked_contacts      (dc_context_t* context);
 void            dc_block_contact             (dc_context_t* context, uint32_t contact_id, int block);
 
 
+/**
+ * Set the read receipt policy of a contact.
+ *
+ * The policy is used for chats that have no policy set with dc_set_chat_mdn_policy().
+ * May result in a #DC_EVENT_CONTACTS_CHANGED event.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param contact_id The ID of the contact to set the policy for.
+ * @param policy One of the DC_MDN_POLICY_* constants,
+ *     DC_MDN_POLICY_DEFAULT uses the `mdns_enabled` config option.
+ */
+void            dc_set_contact_mdn_policy    (dc_context_t* context, uint32_t contact_id, int policy);
+
+
+/**
+ * Get the note on a contact.
+ *
+ * Notes are free-text information about a contact, e.g. where the contact was met.
+ * They are never sent to the contact
+ * but synchronized to other devices of the user.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param contact_id The ID of the contact.
+ * @return The note, empty string if there is none.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_contact_note          (dc_context_t* context, uint32_t contact_id);
+
+
+/**
+ * Set the note on a contact, see dc_get_contact_note().
+ * Results in a #DC_EVENT_CONTACTS_CHANGED event.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param contact_id The ID of the contact.
+ * @param note The new note. NULL or an empty string removes the note.
+ */
+void            dc_set_contact_note          (dc_context_t* context, uint32_t contact_id, const char* note);
+
+
+/**
+ * Get the tags of a contact.
+ *
+ * Tags are local strings to group contacts,
+ * they are never sent to the contact
+ * but synchronized to other devices of the user.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param contact_id The ID of the contact.
+ * @return The tags in alphabetical order, separated by newlines.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_get_contact_tags          (dc_context_t* context, uint32_t contact_id);
+
+
+/**
+ * Replace the tags of a contact, see dc_get_contact_tags().
+ * Results in a #DC_EVENT_CONTACTS_CHANGED event.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param contact_id The ID of the contact.
+ * @param tags The new tags, separated by newlines.
+ *     Tags are trimmed, empty and duplicate tags are ignored.
+ */
+void            dc_set_contact_tags          (dc_context_t* context, uint32_t contact_id, const char* tags);
+
+
+/**
+ * Get the contacts tagged with a given tag.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param tag The tag to look for.
+ * @return An array containing the contact IDs. Must be dc_array_unref()'d after usage.
+ */
+dc_array_t*     dc_get_contacts_by_tag       (dc_context_t* context, const char* tag);
+
+
 /**
  * Get encryption info for a contact.
  * Get a multi-line encryption info, containing your fingerprint and the
@@ -2282,6 +3150,9 @@ dc_contact_t*   dc_get_contact               (dc_context_t* context, uint32_t co
 #define         DC_IMEX_IMPORT_SELF_KEYS      2 // param1 is a directory where the keys are searched in and read from
 #define         DC_IMEX_EXPORT_BACKUP        11 // param1 is a directory where the backup is written to, param2 is a passphrase to encrypt the backup
 #define         DC_IMEX_IMPORT_BACKUP        12 // param1 is the file with the backup to import, param2 is the backup's passphrase
+#define         DC_IMEX_MERGE_BACKUP         13 // param1 is the file with the backup to merge, param2 is the backup's passphrase
+#define         DC_IMEX_EXPORT_IDENTITY      21 // param1 is a directory where the identity bundle is written to, param2 is a passphrase to encrypt the bundle
+#define         DC_IMEX_IMPORT_IDENTITY      22 // param1 is the identity bundle file to import, param2 is the bundle's passphrase
 
 
 /**
@@ -2300,6 +3171,21 @@ dc_contact_t*   dc_get_contact               (dc_context_t* context, uint32_t co
  *   The file is normally created by DC_IMEX_EXPORT_BACKUP and detected by dc_imex_has_backup(). Importing a backup
  *   is only possible as long as the context is not configured or used in another way.
  *
+ * - **DC_IMEX_MERGE_BACKUP** (13) - `param1` is the backup file to merge into the configured account,
+ *   `param2` is the passphrase. Chats, contacts and messages missing in the account are added,
+ *   messages are deduplicated by their Message-ID and groups by their group ID.
+ *   Keys and settings of the account are not changed.
+ *
+ * - **DC_IMEX_EXPORT_IDENTITY** (21) - Export an identity bundle to the directory given as `param1`
+ *   encrypted with the passphrase given as `param2`, which must not be empty.
+ *   The bundle contains the own keys, the verified keys of contacts and the login settings,
+ *   but no messages, so it is small enough to be stored in a password manager.
+ *   The name of the bundle is `delta-chat-identity-<addr>.asc`.
+ *
+ * - **DC_IMEX_IMPORT_IDENTITY** (22) - `param1` is the identity bundle file to import, `param2` is the passphrase.
+ *   Importing an identity bundle is only possible as long as the context is not configured.
+ *   Afterwards, the account can be configured with dc_configure().
+ *
  * - **DC_IMEX_EXPORT_SELF_KEYS** (1) - Export all private keys and all public keys of the user to the
  *   directory given as `param1`. The default key is written to the files `public-key-default.asc`
  *   and `private-key-default.asc`, if there are more keys, they are written to files as
@@ -3017,6 +3903,39 @@ int dc_receive_backup (dc_context_t* context, const char* qr);
 dc_accounts_t* dc_accounts_new                  (const char* dir, int writable);
 
 
+/**
+ * Create a new account manager with encrypted configuration.
+ *
+ * Works like dc_accounts_new(),
+ * but the account manager configuration containing the list of accounts
+ * and the selected account is encrypted with the given passphrase.
+ * If the directory already contains an encrypted configuration,
+ * the passphrase is used to decrypt it.
+ *
+ * Accounts which have a database encrypted with the same passphrase
+ * are opened automatically.
+ *
+ * @memberof dc_accounts_t
+ * @param dir The directory to create the context-databases in.
+ * @param writable Whether the returned account manager is writable, see dc_accounts_new().
+ * @param passphrase The passphrase to encrypt or decrypt the configuration with, must not be empty.
+ * @return An account manager object, must be freed using dc_accounts_unref() after usage.
+ *     On errors, e.g. if the passphrase is wrong, NULL is returned.
+ */
+dc_accounts_t* dc_accounts_new_encrypted        (const char* dir, int writable, const char* passphrase);
+
+
+/**
+ * Check if the account manager configuration in the given directory is encrypted,
+ * i.e. the account manager must be created using dc_accounts_new_encrypted().
+ *
+ * @memberof dc_accounts_t
+ * @param dir The account manager directory.
+ * @return 1=configuration is encrypted, 0=configuration is not encrypted or does not exist.
+ */
+int            dc_accounts_is_config_encrypted  (const char* dir);
+
+
 /**
  * Free an account manager object.
  *
@@ -3076,6 +3995,52 @@ uint32_t       dc_accounts_add_closed_account   (dc_accounts_t* accounts);
  */
 uint32_t       dc_accounts_migrate_account      (dc_accounts_t* accounts, const char* dbfile);
 
+/**
+ * Encrypt the account manager configuration with the given passphrase
+ * or store it unencrypted.
+ *
+ * This does not change the passphrases of the account databases.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param passphrase The new passphrase, NULL to remove the encryption.
+ * @return 1=success, 0=error
+ */
+int            dc_accounts_set_config_passphrase (dc_accounts_t* accounts, const char* passphrase);
+
+
+/**
+ * Move an account out of the account manager,
+ * e.g. to add it to the account manager of another app installation
+ * using dc_accounts_import_account().
+ *
+ * The database and all blob files of the account are _moved_ to the given directory,
+ * so the directory must be on the same filesystem as the account manager directory.
+ * Unlike a backup, the exported account keeps its UUID.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param account_id The account ID to export.
+ * @param path The directory to move the account to, must not exist yet.
+ * @return 1=success, 0=error
+ */
+int            dc_accounts_export_account       (dc_accounts_t* accounts, uint32_t account_id, const char* path);
+
+/**
+ * Add an account exported with dc_accounts_export_account() to the account manager.
+ * The account data is _moved_ into the account manager directory.
+ * Moreover, the newly added account will be the selected one.
+ *
+ * Fails if the account manager already contains an account with the same UUID.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param path The directory created by dc_accounts_export_account().
+ * @return The account ID, use dc_accounts_get_account() to get the context object.
+ *     On errors, 0 is returned.
+ */
+uint32_t       dc_accounts_import_account       (dc_accounts_t* accounts, const char* path);
+
 
 /**
  * Remove an account from the account manager.
@@ -3195,6 +4160,70 @@ void           dc_accounts_maybe_network        (dc_accounts_t* accounts);
 void           dc_accounts_maybe_network_lost    (dc_accounts_t* accounts);
 
 
+#define DC_NETWORK_UNKNOWN   0
+#define DC_NETWORK_NONE      1
+#define DC_NETWORK_WIFI      2
+#define DC_NETWORK_CELLULAR  3
+#define DC_NETWORK_VPN       4
+
+
+/**
+ * This function should be called when the device switched to another network,
+ * e.g. from Wi-Fi to cellular or when a VPN was enabled.
+ *
+ * Unlike dc_accounts_maybe_network(), connections made over the old network
+ * are dropped instead of waiting for them to time out,
+ * connection backoff is reset and the proxy configuration is reloaded,
+ * so that messages arrive seconds after switching the network.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param kind The class of the new network, one of the DC_NETWORK_* constants.
+ *     DC_NETWORK_NONE indicates that the network is lost,
+ *     DC_NETWORK_UNKNOWN can be used if the class is not known.
+ */
+void           dc_accounts_on_network_changed    (dc_accounts_t* accounts, int kind);
+
+
+/**
+ * Set the identity of the network the device is connected to,
+ * typically called together with dc_accounts_on_network_changed().
+ *
+ * The identity should be an opaque string that is the same
+ * whenever the device connects to the same network,
+ * e.g. a hash of the Wi-Fi SSID.
+ * The server ports that work are remembered per network,
+ * so that alternative ports needed on networks blocking the standard ports,
+ * e.g. hotel Wi-Fi, are only used there.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param identity The identity of the network,
+ *     NULL or an empty string if the network is not known.
+ */
+void           dc_accounts_set_network_identity  (dc_accounts_t* accounts, const char* identity);
+
+
+/**
+ * Tell the core whether the app is in the foreground,
+ * i.e. whether the user is interacting with it.
+ *
+ * Background jobs such as the daily housekeeping
+ * pause while the app is in the foreground,
+ * see #DC_EVENT_HOUSEKEEPING_PROGRESS.
+ * Full scans of folders other than the watched ones
+ * and sending webxdc updates of chats other than the one set with dc_set_interactive_chat()
+ * are deferred as well, but not indefinitely.
+ * Call this function with `foreground=1` when the app is opened
+ * and with `foreground=0` when it goes to the background.
+ *
+ * @memberof dc_accounts_t
+ * @param accounts The account manager as created by dc_accounts_new().
+ * @param foreground 1=app is in the foreground, 0=app is in the background.
+ */
+void           dc_accounts_set_foreground        (dc_accounts_t* accounts, int foreground);
+
+
 /**
  * Perform a background fetch for all accounts in parallel with a timeout.
  * Pauses the scheduler, fetches messages from imap and then resumes the scheduler.
@@ -3286,6 +4315,57 @@ size_t           dc_array_get_cnt            (const dc_array_t* array);
 uint32_t         dc_array_get_id             (const dc_array_t* array, size_t index);
 
 
+/**
+ * Copy a range of items as IDs into a buffer.
+ *
+ * This is faster than calling dc_array_get_id() for each item,
+ * e.g. when converting large arrays of message IDs
+ * to arrays of another language.
+ *
+ * Example:
+ *
+ * ~~~
+ * uint32_t ids[100];
+ * size_t start = 0, cnt;
+ * while ((cnt = dc_array_get_ids(array, start, 100, ids)) > 0) {
+ *     // process cnt ids
+ *     start += cnt;
+ * }
+ * ~~~
+ *
+ * @memberof dc_array_t
+ * @param array The array object.
+ * @param start The index of the first item to copy.
+ * @param count The maximum number of items to copy, `ret_ids` must have room for this number of items.
+ * @param ret_ids The buffer the IDs are written to.
+ * @return The number of copied IDs,
+ *     less than `count` if the end of the array is reached,
+ *     0 if `start` is beyond the end of the array or on errors.
+ */
+size_t           dc_array_get_ids            (const dc_array_t* array, size_t start, size_t count, uint32_t* ret_ids);
+
+
+/**
+ * Get all items as a contiguous buffer of IDs.
+ *
+ * @memberof dc_array_t
+ * @param array The array object.
+ * @param ret_cnt A pointer to a size_t. The number of IDs in the returned buffer will be written here.
+ * @return The buffer with the IDs, must be freed using dc_array_ids_unref() after usage.
+ *     NULL if the array is empty or on errors.
+ */
+uint32_t*        dc_array_get_all_ids        (const dc_array_t* array, size_t* ret_cnt);
+
+
+/**
+ * Free a buffer returned by dc_array_get_all_ids().
+ *
+ * @memberof dc_array_t
+ * @param ids The buffer to free. If NULL is given, nothing is done.
+ */
+void             dc_array_ids_unref          (uint32_t* ids);
+
+
 /**
  * Return the latitude of the item at the given index.
  *
@@ -3717,6 +4797,17 @@ uint32_t        dc_chat_get_color            (const dc_chat_t* chat);
 int             dc_chat_get_visibility       (const dc_chat_t* chat);
 
 
+/**
+ * Get the read receipt policy of a chat
+ * as set by dc_set_chat_mdn_policy().
+ *
+ * @memberof dc_chat_t
+ * @param chat The chat object.
+ * @return One of the DC_MDN_POLICY_* constants.
+ */
+int             dc_chat_get_mdn_policy       (const dc_chat_t* chat);
+
+
 /**
  * Check if a chat is a contact request chat.
  *
@@ -3736,6 +4827,24 @@ int             dc_chat_get_visibility       (const dc_chat_t* chat);
 int             dc_chat_is_contact_request   (const dc_chat_t* chat);
 
 
+/**
+ * Check if a chat is a quarantined group.
+ *
+ * Groups created by unknown, unverified contacts with many recipients
+ * are likely spam. They are contact requests
+ * for which dc_chat_is_contact_request() returns 1 as well,
+ * but the UI should show a warning
+ * and offer to leave the group and block its creator with dc_leave_and_block_creator().
+ *
+ * Accepting the chat with dc_accept_chat() lifts the quarantine.
+ *
+ * @memberof dc_chat_t
+ * @param chat The chat object.
+ * @return 1=chat is quarantined, 0=chat is not quarantined.
+ */
+int             dc_chat_is_quarantined       (const dc_chat_t* chat);
+
+
 /**
  * Check if a group chat is still unpromoted.
  *
@@ -4105,6 +5214,35 @@ char*           dc_msg_get_text               (const dc_msg_t* msg);
 char*           dc_msg_get_subject            (const dc_msg_t* msg);
 
 
+/**
+ * Get the watch keyword found in an incoming message,
+ * see the `watch_keywords` option of dc_set_config().
+ *
+ * The UI may highlight the keyword in the message
+ * and show a notification for the message even if the chat is muted.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The keyword, NULL if the message does not contain a watch keyword.
+ *     Must be released using dc_str_unref() after usage.
+ */
+char*           dc_msg_get_watched_keyword    (const dc_msg_t* msg);
+
+
+/**
+ * Get the time at which an outgoing message kept back
+ * because of the `send_delay` option of dc_set_config() is submitted.
+ * Until then, sending can be cancelled using dc_cancel_send()
+ * and the UI may show the message as cancellable.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The timestamp in seconds since the epoch,
+ *     0 if the message is not kept back (anymore).
+ */
+int64_t         dc_msg_get_send_delay_end     (const dc_msg_t* msg);
+
+
 /**
  * Find out full path of the file associated with a message.
  *
@@ -4134,6 +5272,29 @@ char*           dc_msg_get_file               (const dc_msg_t* msg);
 int             dc_msg_save_file              (const dc_msg_t* msg, const char* path);
 
 
+/**
+ * Save file copy at the user-provided path,
+ * resolving conflicts with an existing file at the path.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param path Destination file path with filename and extension.
+ * @param policy What to do if a file already exists at the path:
+ *     - DC_SAVE_FILE_ERROR (0): fail, same as dc_msg_save_file()
+ *     - DC_SAVE_FILE_OVERWRITE (1): replace the existing file
+ *     - DC_SAVE_FILE_AUTO_NUMBER (2): add a number to the file name,
+ *       e.g. `photo (1).jpg` is used if `photo.jpg` exists
+ * @return The path the file was saved at, NULL on failure.
+ *     The returned string must be released using dc_str_unref().
+ */
+char*           dc_msg_save_file_ex           (const dc_msg_t* msg, const char* path, int policy);
+
+
+#define DC_SAVE_FILE_ERROR       0
+#define DC_SAVE_FILE_OVERWRITE   1
+#define DC_SAVE_FILE_AUTO_NUMBER 2
+
+
 /**
  * Get an original attachment filename, with extension but without the path. To get the full path,
  * use dc_msg_get_file().
@@ -4260,17 +5421,33 @@ int             dc_msg_get_height             (const dc_msg_t* msg);
 
 
 /**
- * Get the duration of audio or video. The duration is returned in milliseconds (ms).
- * If the duration is unknown or if the associated file is no audio or video file,
- * 0 is returned.
+ * Get the duration of audio or video. The duration is returned in milliseconds (ms).
+ * If the duration is unknown or if the associated file is no audio or video file,
+ * 0 is returned.
+ *
+ * See also dc_msg_get_width() and dc_msg_get_height().
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The duration in milliseconds, if applicable. 0 otherwise or if unknown.
+ */
+int             dc_msg_get_duration           (const dc_msg_t* msg);
+
+
+/**
+ * Get the thumbnail of a video message.
  *
- * See also dc_msg_get_width() and dc_msg_get_height().
+ * The thumbnail is a small JPEG image
+ * sent along with the video or extracted from it,
+ * so that a preview can be shown without decoding the video.
  *
  * @memberof dc_msg_t
  * @param msg The message object.
- * @return The duration in milliseconds, if applicable. 0 otherwise or if unknown.
+ * @return The full path of the thumbnail.
+ *     If the message has no thumbnail, an empty string is returned.
+ *     NULL is never returned and the returned value must be released using dc_str_unref().
  */
-int             dc_msg_get_duration           (const dc_msg_t* msg);
+char*           dc_msg_get_thumbnail          (const dc_msg_t* msg);
 
 
 /**
@@ -4391,11 +5568,30 @@ char*           dc_msg_get_summarytext        (const dc_msg_t* msg, int approx_c
 char*           dc_msg_get_override_sender_name(const dc_msg_t* msg);
 
 
+/**
+ * Get the known contact whose name the unknown sender of the message uses.
+ *
+ * Messages from unknown senders using the name of a known contact
+ * possibly impersonate that contact, e.g. to trick the user into trusting a phishing message.
+ * The UI should show a warning next to such messages.
+ * Messages signed with a key of the known contact are not flagged.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return The ID of the contact whose name is used,
+ *     DC_CONTACT_ID_SELF if the sender uses our own name,
+ *     0 if the message does not impersonate a known contact.
+ */
+uint32_t        dc_msg_get_impersonated_contact_id(const dc_msg_t* msg);
+
+
 
 /**
  * Check if a message has a deviating timestamp.
  * A message has a deviating timestamp
  * when it is sent on another day as received/sorted by.
+ * Days are computed in the timezone set in the `timezone` config option,
+ * as for day markers.
  *
  * When the UI displays normally only the time beside the message and the full day as headlines,
  * the UI should display the full date directly beside the message if the timestamp is deviating.
@@ -4453,6 +5649,20 @@ int             dc_msg_is_sent                (const dc_msg_t* msg);
 int             dc_msg_is_forwarded           (const dc_msg_t* msg);
 
 
+/**
+ * Check if the message was removed from the server by another client,
+ * e.g. deleted or expunged by a different mail program.
+ *
+ * The local copy is kept,
+ * but partially downloaded messages cannot be downloaded anymore.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @return 1=message was removed from the server, 0=message is still on the server or unknown.
+ */
+int             dc_msg_is_removed_from_server (const dc_msg_t* msg);
+
+
 /**
  * Check if the message is an informational message, created by the
  * device or by another users. Such messages are not "typed" by the user but
@@ -4816,6 +6026,21 @@ void            dc_msg_set_dimension          (dc_msg_t* msg, int width, int hei
 void            dc_msg_set_duration           (dc_msg_t* msg, int duration);
 
 
+/**
+ * Set whether an image should be sent in original quality.
+ *
+ * The image is then sent as #DC_MSG_IMAGE without scaling it down or recoding it,
+ * regardless of the `media_quality` config option,
+ * so metadata such as the location where the photo was taken is kept as well.
+ * Images too large for common providers are scaled down anyway.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param original_quality 1=send in original quality, 0=scale down as usual.
+ */
+void            dc_msg_set_original_quality   (dc_msg_t* msg, int original_quality);
+
+
 /**
  * Set any location that should be bound to the message object.
  * The function is useful to add a marker to the map
@@ -4859,6 +6084,22 @@ void            dc_msg_set_location           (dc_msg_t* msg, double latitude, d
 void            dc_msg_latefiling_mediasize   (dc_msg_t* msg, int width, int height, int duration);
 
 
+/**
+ * Set an attachment staged with dc_stage_attachment() as the attachment of the message.
+ *
+ * If the attachment is still being prepared, the function waits until it is ready.
+ * The viewtype and the file of the message are replaced by the staged ones,
+ * the text and the quote are kept.
+ * The staged attachment is consumed, it must be staged for the chat the message is sent to.
+ *
+ * @memberof dc_msg_t
+ * @param msg The message object.
+ * @param staged_id The ID of the staged attachment as returned from dc_stage_attachment().
+ * @return 1=success, 0=preparing the attachment failed or it does not exist.
+ */
+int             dc_msg_set_staged_attachment  (dc_msg_t* msg, uint32_t staged_id);
+
+
 /**
  * Set the message replying to.
  * This allows optionally to reply to an explicit message
@@ -5178,6 +6419,17 @@ int             dc_contact_is_blocked        (const dc_contact_t* contact);
  */
 int             dc_contact_is_verified       (dc_contact_t* contact);
 
+
+/**
+ * Get the read receipt policy of a contact
+ * as set by dc_set_contact_mdn_policy().
+ *
+ * @memberof dc_contact_t
+ * @param contact The contact object.
+ * @return One of the DC_MDN_POLICY_* constants.
+ */
+int             dc_contact_get_mdn_policy    (dc_contact_t* contact);
+
 /**
  * Returns whether contact is a bot.
  *
@@ -5865,6 +7117,25 @@ void  dc_event_emitter_unref(dc_event_emitter_t* emitter);
 // Alias for backwards compatibility, use dc_event_emtitter_unref instead.
 #define dc_accounts_event_emitter_unref dc_event_emitter_unref
 
+/**
+ * Set the time window in which low-priority events are merged.
+ *
+ * Events such as #DC_EVENT_MSGS_CHANGED or #DC_EVENT_CHATLIST_CHANGED
+ * are then delayed by up to the given window
+ * and identical events emitted meanwhile are dropped.
+ * #DC_EVENT_MSGS_CHANGED events of the same chat, but for different messages,
+ * are merged into one event with data2 set to 0.
+ * Other events, e.g. #DC_EVENT_INCOMING_MSG, are delivered without delay.
+ *
+ * This reduces the number of UI updates, e.g. while fetching many messages.
+ * Coalescing is disabled by default.
+ *
+ * @memberof dc_event_emitter_t
+ * @param emitter Event emitter object as returned from dc_get_event_emitter().
+ * @param milliseconds Coalescing window in milliseconds, 0 to disable coalescing.
+ */
+void  dc_event_emitter_set_coalescing_window(dc_event_emitter_t* emitter, uint32_t milliseconds);
+
 /**
  * @class dc_event_t
  *
@@ -6153,6 +7424,16 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_INCOMING_WEBXDC_NOTIFY   2003
 
 
+/**
+ * A report of an offending message was received from another user,
+ * see dc_report_msg().
+ *
+ * @param data1 (int) ID of the report
+ * @param data2 0
+ */
+#define DC_EVENT_INCOMING_MSG_REPORT      2004
+
+
 /**
  * There is a fresh message. Typically, the user will show an notification
  * when receiving this message.
@@ -6162,6 +7443,9 @@ void dc_event_unref(dc_event_t* event);
  * If the message is a webxdc info message,
  * dc_msg_get_parent() returns the webxdc instance the notification belongs to.
  *
+ * If dc_msg_get_watched_keyword() returns a keyword for the message,
+ * the UI may show a notification even if the chat is muted.
+ *
  * @param data1 (int) chat_id
  * @param data2 (int) msg_id
  */
@@ -6181,6 +7465,20 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_INCOMING_MSG_BUNCH       2006
 
 
+/**
+ * An incoming message from an unknown sender uses the name of a known contact
+ * and possibly impersonates it.
+ * The UI may show a warning next to the message or the contact request.
+ *
+ * The event is emitted in addition to #DC_EVENT_INCOMING_MSG or #DC_EVENT_MSGS_CHANGED.
+ * Use dc_msg_get_impersonated_contact_id() to get the contact whose name is used.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) msg_id
+ */
+#define DC_EVENT_INCOMING_MSG_IMPERSONATION 2007
+
+
 /**
  * Messages were marked noticed or seen.
  * The UI may update badge counters or stop showing a chatlist-item with a bold font.
@@ -6236,6 +7534,26 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_MSG_DELETED              2016
 
 
+/**
+ * The state of a single message changed.
+ *
+ * This event is emitted in addition to the less specific events
+ * such as #DC_EVENT_MSG_DELIVERED, #DC_EVENT_MSG_READ or #DC_EVENT_MSGS_NOTICED,
+ * so the UI can update a single message
+ * instead of reloading the whole chat.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) msg_id _and_ (char*) details as JSON.
+ *      - dc_event_get_data2_int() returns the msg_id.
+ *      - dc_event_get_data2_str() returns a JSON object with the keys
+ *        `old_state` and `new_state` (one of the @ref DC_STATE constants each)
+ *        and `cause` (one of `smtp_accepted`, `send_failed`, `mdn_received`,
+ *        `resend_requested` or `marked_seen`),
+ *        string must be passed to dc_str_unref() afterwards.
+ */
+#define DC_EVENT_MSG_STATE_CHANGED        2017
+
+
 /**
  * Chat changed. The name or the image of a chat group was changed or members were added or removed.
  * Or the verify state of a chat has changed.
@@ -6255,6 +7573,15 @@ void dc_event_unref(dc_event_t* event);
  */
 #define DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED 2021
 
+/**
+ * Inactive chats were archived during housekeeping
+ * as configured by the `auto_archive_days` option of dc_set_config().
+ *
+ * @param data1 (int) Number of archived chats.
+ * @param data2 (char*) Comma-separated IDs of the archived chats.
+ */
+#define DC_EVENT_CHATS_AUTO_ARCHIVED      2022
+
 
 /**
  * Contact(s) created, renamed, verified, blocked or deleted.
@@ -6295,6 +7622,25 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_IMEX_PROGRESS            2051
 
 
+/**
+ * Detailed progress of a backup export or import started by dc_imex().
+ *
+ * The event is emitted together with #DC_EVENT_IMEX_PROGRESS
+ * and when the phase of the transfer changes,
+ * so the UI can show the transferred bytes and the remaining time.
+ *
+ * @param data1 (int) Transferred bytes in permille.
+ * @param data2 (int) Estimated remaining time in seconds, 0 if not known yet _and_ (char*) details as JSON.
+ *      - dc_event_get_data2_int() returns the estimated remaining time.
+ *      - dc_event_get_data2_str() returns a JSON object with the keys
+ *        `phase` (one of `database`, `blobs` or `finishing`),
+ *        `items_done`, `items_total` (null if unknown), `bytes_done`, `bytes_total`
+ *        and `eta_secs` (null if not known yet),
+ *        string must be passed to dc_str_unref() afterwards.
+ */
+#define DC_EVENT_IMEX_PROGRESS_DETAIL     2053
+
+
 /**
  * A file has been exported. A file has been written by dc_imex().
  * This event may be sent multiple times by a single call to dc_imex().
@@ -6308,6 +7654,39 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_IMEX_FILE_WRITTEN        2052
 
 
+/**
+ * Inform about the progress of housekeeping.
+ *
+ * Housekeeping runs automatically once a day
+ * and pauses while the app is in the foreground, see dc_accounts_set_foreground().
+ *
+ * @param data1 (int) 0=error or cancelled, 1-999=progress in permille, 1000=success and done
+ * @param data2 0
+ */
+#define DC_EVENT_HOUSEKEEPING_PROGRESS    2055
+
+
+/**
+ * Inform about the progress of recoding an image of a message being sent,
+ * see dc_send_msg() and dc_prepare_msg().
+ *
+ * The recoding can be cancelled with dc_cancel_image_recoding().
+ *
+ * @param data1 (int) ID of the chat the message is sent to.
+ * @param data2 (int) 0=error or cancelled, 1-999=progress in permille, 1000=success and done
+ */
+#define DC_EVENT_IMAGE_RECODE_PROGRESS    2056
+
+
+/**
+ * Inform about the progress of preparing an attachment staged with dc_stage_attachment().
+ *
+ * @param data1 (int) ID of the staged attachment.
+ * @param data2 (int) 0=error or cancelled, 500=copied into the blobdir, 1000=ready
+ */
+#define DC_EVENT_ATTACHMENT_STAGING_PROGRESS 2057
+
+
 /**
  * Progress information of a secure-join handshake from the view of the inviter
  * (Alice, the person who shows the QR code).
@@ -6353,6 +7732,50 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_CONNECTIVITY_CHANGED             2100
 
 
+/**
+ * The provider status endpoint reports an incident.
+ *
+ * The endpoint is checked when connecting to the server fails repeatedly,
+ * so the UI may show the incident to the user
+ * to tell that the problem is likely on the server side.
+ * Every incident is reported only once.
+ *
+ * @param data1 (char*) Short summary of the incident.
+ * @param data2 (char*) Description of the incident, possibly followed by a link to the incident page.
+ */
+#define DC_EVENT_PROVIDER_INCIDENT                2101
+
+
+/**
+ * A problem with the account was detected by the periodic health evaluation.
+ *
+ * The UI should show an actionable warning depending on the category,
+ * e.g. ask the user to check the password for #DC_HEALTH_AUTH_FAILURES.
+ * Every warning is reported only once until the problem is fixed.
+ *
+ * @param data1 (int) Category of the warning, one of the @ref DC_HEALTH constants.
+ * @param data2 (char*) Technical details, not translated.
+ */
+#define DC_EVENT_ACCOUNT_HEALTH_WARNING           2102
+
+
+/**
+ * The features of the server changed,
+ * e.g. because the account moved to another server or the server was updated.
+ * UIs that enable features depending on the server
+ * should query them again with dc_get_server_capabilities().
+ */
+#define DC_EVENT_SERVER_CAPABILITIES_CHANGED      2103
+
+
+/**
+ * The process became the owner of the account or released the ownership,
+ * see dc_is_owner().
+ * IO runs only while the process owns the account.
+ */
+#define DC_EVENT_OWNERSHIP_CHANGED                2104
+
+
 /**
  * The user's avatar changed.
  * You can get the new avatar file with `dc_get_config(context, "selfavatar")`.
@@ -6428,6 +7851,59 @@ void dc_event_unref(dc_event_t* event);
 
 #define DC_EVENT_ACCOUNTS_BACKGROUND_FETCH_DONE   2200
 
+
+/**
+ * A contact calls and the device should ring,
+ * see dc_accept_incoming_call().
+ *
+ * The payload of the caller can be retrieved with dc_get_call_payload().
+ *
+ * @param data1 (int) chat_id of the 1:1 chat with the caller
+ * @param data2 (int) msg_id of the call invite message
+ */
+#define DC_EVENT_INCOMING_CALL                 2260
+
+
+/**
+ * An incoming call was accepted on this or another device of the user.
+ * The device should stop ringing.
+ *
+ * @param data1 (int) chat_id of the 1:1 chat with the caller
+ * @param data2 (int) msg_id of the call invite message
+ */
+#define DC_EVENT_INCOMING_CALL_ACCEPTED        2261
+
+
+/**
+ * An outgoing call was accepted by the callee.
+ *
+ * The payload of the callee can be retrieved with dc_get_call_payload().
+ *
+ * @param data1 (int) chat_id of the 1:1 chat with the callee
+ * @param data2 (int) msg_id of the call invite message
+ */
+#define DC_EVENT_OUTGOING_CALL_ACCEPTED        2262
+
+
+/**
+ * A call was ended or rejected.
+ *
+ * @param data1 (int) chat_id of the 1:1 chat of the call
+ * @param data2 (int) msg_id of the call invite message
+ */
+#define DC_EVENT_CALL_ENDED                    2263
+
+
+/**
+ * An incoming call was cancelled by the caller before it was accepted
+ * or was received too late to ring.
+ * The UI may show a notification about the missed call.
+ *
+ * @param data1 (int) chat_id of the 1:1 chat with the caller
+ * @param data2 (int) msg_id of the call invite message
+ */
+#define DC_EVENT_CALL_MISSED                   2264
+
 /**
  * Inform that set of chats or the order of the chats in the chatlist has changed.
  *
@@ -6445,6 +7921,40 @@ void dc_event_unref(dc_event_t* event);
 
 #define DC_EVENT_CHATLIST_ITEM_CHANGED         2301
 
+/**
+ * A chat was inserted into the chatlist or moved to another position.
+ *
+ * The UI should remove the chat from its old position, if any, and insert it at the given index.
+ * Only emitted if enabled with dc_set_chatlist_diff_events().
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) new index of the chat in the chatlist
+ */
+
+#define DC_EVENT_CHATLIST_ITEM_MOVED           2304
+
+/**
+ * A chat was removed from the chatlist, e.g. because it was archived or deleted.
+ *
+ * Only emitted if enabled with dc_set_chatlist_diff_events().
+ *
+ * @param data1 (int) chat_id
+ * @param data2 0
+ */
+
+#define DC_EVENT_CHATLIST_ITEM_REMOVED         2305
+
+/**
+ * The number of fresh messages of a chat in the chatlist changed.
+ *
+ * Only emitted if enabled with dc_set_chatlist_diff_events().
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) new number of fresh messages, see dc_get_fresh_msg_cnt()
+ */
+
+#define DC_EVENT_CHATLIST_ITEM_FRESH_MSG_CNT_CHANGED 2306
+
 /**
  * Inform that the list of accounts has changed (an account removed or added or (not yet implemented) the account order changes)
  *
@@ -6640,6 +8150,88 @@ void dc_event_unref(dc_event_t* event);
 
 
 
+/**
+ * @}
+ */
+
+
+/**
+ * @defgroup DC_HEALTH DC_HEALTH
+ *
+ * These constants describe the category of an account health warning
+ * reported by #DC_EVENT_ACCOUNT_HEALTH_WARNING.
+ *
+ * @addtogroup DC_HEALTH
+ * @{
+ */
+
+/**
+ * Logging in to the server failed repeatedly because of wrong credentials.
+ * The user should check the password.
+ */
+#define DC_HEALTH_AUTH_FAILURES       1
+
+/**
+ * The server reports that the mailbox uses most of its quota.
+ * The user should delete old messages.
+ */
+#define DC_HEALTH_QUOTA_WARNING       2
+
+/**
+ * The mailbox is nearly full and new messages may be rejected soon.
+ */
+#define DC_HEALTH_MAILBOX_NEARLY_FULL 3
+
+/**
+ * The TLS certificate of the server expires soon or is expired.
+ */
+#define DC_HEALTH_CERTIFICATE_EXPIRY  4
+
+/**
+ * Several outgoing messages failed recently, e.g. because they bounced.
+ */
+#define DC_HEALTH_BOUNCES             5
+
+/**
+ * @}
+ */
+
+
+/**
+ * @defgroup DC_CALL_STATE DC_CALL_STATE
+ *
+ * These constants describe the state of a call,
+ * see dc_get_call_state().
+ *
+ * @addtogroup DC_CALL_STATE
+ * @{
+ */
+
+/**
+ * The call is ringing and was not answered yet.
+ */
+#define DC_CALL_STATE_PENDING  0
+
+/**
+ * The call was accepted by the callee.
+ */
+#define DC_CALL_STATE_ACTIVE   1
+
+/**
+ * The call was rejected by the callee.
+ */
+#define DC_CALL_STATE_DECLINED 2
+
+/**
+ * The call was ended after it was accepted.
+ */
+#define DC_CALL_STATE_ENDED    3
+
+/**
+ * The call was not answered in time or cancelled by the caller.
+ */
+#define DC_CALL_STATE_MISSED   4
+
 /**
  * @}
  */
@@ -7451,6 +9043,23 @@ void dc_event_unref(dc_event_t* event);
 /// Used as a device message after a successful backup transfer.
 #define DC_STR_BACKUP_TRANSFER_MSG_BODY 163
 
+/// "I added member %1$s."
+///
+/// Used in the body of outgoing member added messages, visible only in classic email clients.
+/// - %1$s will be replaced by the name and address of the added member
+#define DC_STR_I_ADDED_MEMBER 164
+
+/// "I removed member %1$s."
+///
+/// Used in the body of outgoing member removed messages, visible only in classic email clients.
+/// - %1$s will be replaced by the name and address of the removed member
+#define DC_STR_I_REMOVED_MEMBER 165
+
+/// "I left the group."
+///
+/// Used in the body of outgoing messages when leaving a group, visible only in classic email clients.
+#define DC_STR_I_LEFT_GROUP 166
+
 /// "Messages are guaranteed to be end-to-end encrypted from now on."
 ///
 /// Used in info messages.
@@ -7507,9 +9116,68 @@ void dc_event_unref(dc_event_t* event);
 /// Used as info message.
 #define DC_STR_SECUREJOIN_WAIT_TIMEOUT 191
 
+/// "Secure-Join: %1$s"
+///
+/// `%1$s` will be replaced by the step of the Secure-Join protocol, e.g. `vc-request`
+///
+/// Used in the body of Secure-Join messages, visible only in classic email clients.
+#define DC_STR_SECUREJOIN_MSG_BODY 192
+
+/// "Mailbox"
+///
+/// Used as the name of the chat collecting classic emails, see the `email_rules` config option.
+#define DC_STR_MAILBOX 193
+
+/// "Members joined: %1$s, left: %2$s"
+///
+/// `%1$s` will be replaced by the number of members who joined the group,
+/// `%2$s` by the number of members who left it.
+///
+/// Used as the text of the info message summarizing membership changes in large groups,
+/// see the `member_digest_threshold` config option.
+#define DC_STR_MEMBER_CHANGES_DIGEST 194
+
+/// "Incoming call"
+///
+/// Used as text of call invites received from other users.
+#define DC_STR_INCOMING_CALL 195
+
+/// "Outgoing call"
+///
+/// Used as text of call invites sent by the user.
+#define DC_STR_OUTGOING_CALL 196
+
+/// "Missed call"
+///
+/// Used as text of incoming calls that were not answered.
+#define DC_STR_MISSED_CALL 197
+
+/// "Account %1$s configured"
+///
+/// Used in the activity log.
+/// - %1$s will be replaced by the configured address
+#define DC_STR_ACTIVITY_CONFIGURED 198
+
+/// "Backup exported"
+///
+/// Used in the activity log.
+#define DC_STR_ACTIVITY_BACKUP_EXPORTED 199
+
+/// "Setting %1$s changed"
+///
+/// Used in the activity log.
+/// - %1$s will be replaced by the name of the changed config key
+#define DC_STR_ACTIVITY_CONFIG_CHANGED 201
+
 /// "Contact". Deprecated, currently unused.
 #define DC_STR_CONTACT 200
 
+/// "Paused: %1$s"
+///
+/// Used in the connectivity view if IO is paused.
+/// - %1$s will be replaced by the reason for pausing
+#define DC_STR_IO_PAUSED 202
+
 /**
  * @}
  */
@@ -28,6 +28,19 @@ pub(crate) fn get_id(&self, index: usize) -> u32 {
         }
     }
 
+    /// Copies the IDs starting at `start` into `ids`.
+    ///
+    /// Returns the number of copied IDs, which is less than `ids.len()`
+    /// if the end of the array is reached.
+    pub(crate) fn copy_ids(&self, start: usize, ids: &mut [u32]) -> usize {
+        let end = self.len().min(start.saturating_add(ids.len()));
+        let count = end.saturating_sub(start);
+        for (i, id) in ids.iter_mut().take(count).enumerate() {
+            *id = self.get_id(start + i);
+        }
+        count
+    }
+
     pub(crate) fn get_timestamp(&self, index: usize) -> Option<i64> {
         match self {
             Self::MsgIds(_) => None,
@@ -129,6 +142,23 @@ fn test_dc_array() {
         assert_eq!(arr.search_id(1), None);
     }
 
+    #[test]
+    fn test_dc_array_copy_ids() {
+        let ids: Vec<u32> = (2..12).collect();
+        let arr: dc_array_t = ids.into();
+
+        let mut buf = [0u32; 4];
+        assert_eq!(arr.copy_ids(0, &mut buf), 4);
+        assert_eq!(buf, [2, 3, 4, 5]);
+
+        let mut buf = [0u32; 4];
+        assert_eq!(arr.copy_ids(8, &mut buf), 2);
+        assert_eq!(buf, [10, 11, 0, 0]);
+
+        assert_eq!(arr.copy_ids(10, &mut buf), 0);
+        assert_eq!(arr.copy_ids(usize::MAX, &mut buf), 0);
+    }
+
     #[test]
     #[should_panic]
     fn test_dc_array_out_of_bounds() {
@@ -16,6 +16,7 @@
 use std::fmt::Write;
 use std::future::Future;
 use std::ops::Deref;
+use std::path::Path;
 use std::ptr;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -24,13 +25,14 @@
 use anyhow::Context as _;
 use deltachat::chat::{ChatId, ChatVisibility, MessageListOptions, MuteDuration, ProtectionStatus};
 use deltachat::constants::DC_MSG_ID_LAST_SPECIAL;
-use deltachat::contact::{Contact, ContactId, Origin};
+use deltachat::contact::{AddressBookEntry, Contact, ContactId, ContactListFilter, Origin};
 use deltachat::context::{Context, ContextBuilder};
 use deltachat::ephemeral::Timer as EphemeralTimer;
 use deltachat::imex::BackupProvider;
 use deltachat::key::preconfigure_keypair;
 use deltachat::message::MsgId;
 use deltachat::qr_code_generator::{create_qr_svg, generate_backup_qr, get_securejoin_qr_svg};
+use deltachat::staging::StagedAttachmentId;
 use deltachat::stock_str::StockMessage;
 use deltachat::webxdc::StatusUpdateSerial;
 use deltachat::*;
@@ -321,6 +323,82 @@ fn spawn<T>(fut: T) -> JoinHandle<T::Output>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_stock_translations_file(
+    context: *mut dc_context_t,
+    path: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_stock_translations_file()");
+        return 0;
+    }
+    let path = to_opt_string_lossy(path);
+    let ctx = &*context;
+
+    block_on(async move {
+        ctx.set_stock_translations_file(path.as_deref().map(Path::new))
+            .await
+            .context("set_stock_translations_file failed")
+            .log_err(ctx)
+            .is_ok() as libc::c_int
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_stock_template(
+    context: *mut dc_context_t,
+    stock_id: u32,
+    template: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_stock_template()");
+        return 0;
+    }
+    let template = to_opt_string_lossy(template);
+    let ctx = &*context;
+
+    block_on(async move {
+        match StockMessage::from_u32(stock_id) {
+            Some(id) => match ctx.set_stock_template(id, template.as_deref()).await {
+                Ok(()) => 1,
+                Err(err) => {
+                    warn!(ctx, "set_stock_template failed: {err:#}");
+                    0
+                }
+            },
+            None => {
+                warn!(ctx, "invalid stock message id {stock_id}");
+                0
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_stock_template(
+    context: *mut dc_context_t,
+    stock_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_stock_template()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        let Some(id) = StockMessage::from_u32(stock_id) else {
+            warn!(ctx, "invalid stock message id {stock_id}");
+            return ptr::null_mut();
+        };
+        ctx.get_stock_template(id)
+            .await
+            .context("Failed to get stock template")
+            .log_err(ctx)
+            .unwrap_or_default()
+            .strdup()
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_set_config_from_qr(
     context: *mut dc_context_t,
@@ -362,6 +440,28 @@ fn spawn<T>(fut: T) -> JoinHandle<T::Output>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_server_capabilities(
+    context: *const dc_context_t,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_server_capabilities()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+    block_on(async move {
+        match ctx.get_server_capabilities().await {
+            Ok(capabilities) => serde_json::to_string(&capabilities)
+                .unwrap_or_log_default(ctx, "dc_get_server_capabilities() failed to serialise")
+                .strdup(),
+            Err(err) => {
+                warn!(ctx, "failed to get server capabilities: {err:#}");
+                "".strdup()
+            }
+        }
+    })
+}
+
 fn render_info(
     info: BTreeMap<&'static str, String>,
 ) -> std::result::Result<String, std::fmt::Error> {
@@ -533,23 +633,40 @@ fn spawn_configure(ctx: Context) {
         EventType::ReactionsChanged { .. } => 2001,
         EventType::IncomingReaction { .. } => 2002,
         EventType::IncomingWebxdcNotify { .. } => 2003,
+        EventType::IncomingMsgReport { .. } => 2004,
         EventType::IncomingMsg { .. } => 2005,
         EventType::IncomingMsgBunch { .. } => 2006,
+        EventType::IncomingMsgImpersonation { .. } => 2007,
         EventType::MsgsNoticed { .. } => 2008,
         EventType::MsgDelivered { .. } => 2010,
         EventType::MsgFailed { .. } => 2012,
         EventType::MsgRead { .. } => 2015,
         EventType::MsgDeleted { .. } => 2016,
+        EventType::MsgStateChanged { .. } => 2017,
         EventType::ChatModified(_) => 2020,
         EventType::ChatEphemeralTimerModified { .. } => 2021,
+        EventType::ChatsAutoArchived { .. } => 2022,
         EventType::ContactsChanged(_) => 2030,
         EventType::LocationChanged(_) => 2035,
         EventType::ConfigureProgress { .. } => 2041,
         EventType::ImexProgress(_) => 2051,
+        EventType::ImexProgressDetail { .. } => 2053,
         EventType::ImexFileWritten(_) => 2052,
+        EventType::HousekeepingProgress(_) => 2055,
+        EventType::ImageRecodeProgress { .. } => 2056,
+        EventType::AttachmentStagingProgress { .. } => 2057,
         EventType::SecurejoinInviterProgress { .. } => 2060,
         EventType::SecurejoinJoinerProgress { .. } => 2061,
         EventType::ConnectivityChanged => 2100,
+        EventType::ProviderIncident { .. } => 2101,
+        EventType::AccountHealthWarning { .. } => 2102,
+        EventType::ServerCapabilitiesChanged => 2103,
+        EventType::OwnershipChanged => 2104,
+        EventType::IncomingCall { .. } => 2260,
+        EventType::IncomingCallAccepted { .. } => 2261,
+        EventType::OutgoingCallAccepted { .. } => 2262,
+        EventType::CallEnded { .. } => 2263,
+        EventType::CallMissed { .. } => 2264,
         EventType::SelfavatarChanged => 2110,
         EventType::ConfigSynced { .. } => 2111,
         EventType::WebxdcStatusUpdate { .. } => 2120,
@@ -559,6 +676,9 @@ fn spawn_configure(ctx: Context) {
         EventType::AccountsBackgroundFetchDone => 2200,
         EventType::ChatlistChanged => 2300,
         EventType::ChatlistItemChanged { .. } => 2301,
+        EventType::ChatlistItemMoved { .. } => 2304,
+        EventType::ChatlistItemRemoved { .. } => 2305,
+        EventType::ChatlistItemFreshMsgCountChanged { .. } => 2306,
         EventType::AccountsChanged => 2302,
         EventType::AccountsItemChanged => 2303,
         EventType::EventChannelOverflow { .. } => 2400,
@@ -589,6 +709,9 @@ fn spawn_configure(ctx: Context) {
         | EventType::Warning(_)
         | EventType::Error(_)
         | EventType::ConnectivityChanged
+        | EventType::ProviderIncident { .. }
+        | EventType::ServerCapabilitiesChanged
+        | EventType::OwnershipChanged
         | EventType::SelfavatarChanged
         | EventType::ConfigSynced { .. }
         | EventType::IncomingMsgBunch { .. }
@@ -602,20 +725,34 @@ fn spawn_configure(ctx: Context) {
         EventType::MsgsChanged { chat_id, .. }
         | EventType::ReactionsChanged { chat_id, .. }
         | EventType::IncomingMsg { chat_id, .. }
+        | EventType::IncomingMsgImpersonation { chat_id, .. }
         | EventType::MsgsNoticed(chat_id)
         | EventType::MsgDelivered { chat_id, .. }
         | EventType::MsgFailed { chat_id, .. }
         | EventType::MsgRead { chat_id, .. }
+        | EventType::MsgStateChanged { chat_id, .. }
         | EventType::MsgDeleted { chat_id, .. }
         | EventType::ChatModified(chat_id)
-        | EventType::ChatEphemeralTimerModified { chat_id, .. } => chat_id.to_u32() as libc::c_int,
+        | EventType::ChatEphemeralTimerModified { chat_id, .. }
+        | EventType::ImageRecodeProgress { chat_id, .. }
+        | EventType::IncomingCall { chat_id, .. }
+        | EventType::IncomingCallAccepted { chat_id, .. }
+        | EventType::OutgoingCallAccepted { chat_id, .. }
+        | EventType::CallEnded { chat_id, .. }
+        | EventType::CallMissed { chat_id, .. } => chat_id.to_u32() as libc::c_int,
         EventType::ContactsChanged(id) | EventType::LocationChanged(id) => {
             let id = id.unwrap_or_default();
             id.to_u32() as libc::c_int
         }
-        EventType::ConfigureProgress { progress, .. } | EventType::ImexProgress(progress) => {
-            *progress as libc::c_int
-        }
+        EventType::ConfigureProgress { progress, .. }
+        | EventType::ImexProgress(progress)
+        | EventType::HousekeepingProgress(progress) => *progress as libc::c_int,
+        EventType::AttachmentStagingProgress { staged_id, .. } => staged_id.to_u32() as libc::c_int,
+        EventType::ImexProgressDetail {
+            bytes_done,
+            bytes_total,
+            ..
+        } => std::cmp::min(1000 * bytes_done / (*bytes_total).max(1), 1000) as libc::c_int,
         EventType::ImexFileWritten(_) => 0,
         EventType::SecurejoinInviterProgress { contact_id, .. }
         | EventType::SecurejoinJoinerProgress { contact_id, .. } => {
@@ -628,6 +765,14 @@ fn spawn_configure(ctx: Context) {
         EventType::ChatlistItemChanged { chat_id } => {
             chat_id.unwrap_or_default().to_u32() as libc::c_int
         }
+        EventType::ChatlistItemMoved { chat_id, .. }
+        | EventType::ChatlistItemRemoved { chat_id }
+        | EventType::ChatlistItemFreshMsgCountChanged { chat_id, .. } => {
+            chat_id.to_u32() as libc::c_int
+        }
+        EventType::ChatsAutoArchived { chat_ids } => chat_ids.len() as libc::c_int,
+        EventType::AccountHealthWarning { category, .. } => *category as libc::c_int,
+        EventType::IncomingMsgReport { report_id } => *report_id as libc::c_int,
         EventType::EventChannelOverflow { n } => *n as libc::c_int,
         #[allow(unreachable_patterns)]
         #[cfg(test)]
@@ -661,19 +806,27 @@ fn spawn_configure(ctx: Context) {
         | EventType::LocationChanged(_)
         | EventType::ConfigureProgress { .. }
         | EventType::ImexProgress(_)
+        | EventType::HousekeepingProgress(_)
         | EventType::ImexFileWritten(_)
         | EventType::MsgsNoticed(_)
         | EventType::ConnectivityChanged
+        | EventType::ProviderIncident { .. }
+        | EventType::AccountHealthWarning { .. }
         | EventType::WebxdcInstanceDeleted { .. }
         | EventType::IncomingMsgBunch { .. }
+        | EventType::ServerCapabilitiesChanged
+        | EventType::OwnershipChanged
         | EventType::SelfavatarChanged
         | EventType::AccountsBackgroundFetchDone
         | EventType::ChatlistChanged
         | EventType::ChatlistItemChanged { .. }
+        | EventType::ChatlistItemRemoved { .. }
         | EventType::AccountsChanged
         | EventType::AccountsItemChanged
         | EventType::ConfigSynced { .. }
         | EventType::ChatModified(_)
+        | EventType::ChatsAutoArchived { .. }
+        | EventType::IncomingMsgReport { .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { .. }
         | EventType::EventChannelOverflow { .. } => 0,
         EventType::MsgsChanged { msg_id, .. }
@@ -681,18 +834,32 @@ fn spawn_configure(ctx: Context) {
         | EventType::IncomingReaction { msg_id, .. }
         | EventType::IncomingWebxdcNotify { msg_id, .. }
         | EventType::IncomingMsg { msg_id, .. }
+        | EventType::IncomingMsgImpersonation { msg_id, .. }
         | EventType::MsgDelivered { msg_id, .. }
         | EventType::MsgFailed { msg_id, .. }
         | EventType::MsgRead { msg_id, .. }
-        | EventType::MsgDeleted { msg_id, .. } => msg_id.to_u32() as libc::c_int,
+        | EventType::MsgStateChanged { msg_id, .. }
+        | EventType::MsgDeleted { msg_id, .. }
+        | EventType::IncomingCall { msg_id, .. }
+        | EventType::IncomingCallAccepted { msg_id, .. }
+        | EventType::OutgoingCallAccepted { msg_id, .. }
+        | EventType::CallEnded { msg_id, .. }
+        | EventType::CallMissed { msg_id, .. } => msg_id.to_u32() as libc::c_int,
         EventType::SecurejoinInviterProgress { progress, .. }
-        | EventType::SecurejoinJoinerProgress { progress, .. } => *progress as libc::c_int,
+        | EventType::SecurejoinJoinerProgress { progress, .. }
+        | EventType::ImageRecodeProgress { progress, .. }
+        | EventType::AttachmentStagingProgress { progress, .. } => *progress as libc::c_int,
         EventType::ChatEphemeralTimerModified { timer, .. } => timer.to_u32() as libc::c_int,
+        EventType::ImexProgressDetail { eta_secs, .. } => {
+            eta_secs.unwrap_or_default() as libc::c_int
+        }
         EventType::WebxdcStatusUpdate {
             status_update_serial,
             ..
         } => status_update_serial.to_u32() as libc::c_int,
         EventType::WebxdcRealtimeData { data, .. } => data.len() as libc::c_int,
+        EventType::ChatlistItemMoved { index, .. } => *index as libc::c_int,
+        EventType::ChatlistItemFreshMsgCountChanged { count, .. } => *count as libc::c_int,
         #[allow(unreachable_patterns)]
         #[cfg(test)]
         _ => unreachable!("This is just to silence a rust_analyzer false-positive"),
@@ -709,6 +876,9 @@ fn spawn_configure(ctx: Context) {
     let event = &(*event).typ;
 
     match event {
+        EventType::ProviderIncident { title, .. } => {
+            title.to_c_string().unwrap_or_default().into_raw()
+        }
         EventType::IncomingWebxdcNotify { href, .. } => {
             if let Some(href) = href {
                 href.to_c_string().unwrap_or_default().into_raw()
@@ -744,9 +914,14 @@ fn spawn_configure(ctx: Context) {
             let data2 = msg.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
+        EventType::ProviderIncident { details, .. }
+        | EventType::AccountHealthWarning { details, .. } => {
+            details.to_c_string().unwrap_or_default().into_raw()
+        }
         EventType::MsgsChanged { .. }
         | EventType::ReactionsChanged { .. }
         | EventType::IncomingMsg { .. }
+        | EventType::IncomingMsgImpersonation { .. }
         | EventType::ImapInboxIdle
         | EventType::MsgsNoticed(_)
         | EventType::MsgDelivered { .. }
@@ -757,16 +932,30 @@ fn spawn_configure(ctx: Context) {
         | EventType::ContactsChanged(_)
         | EventType::LocationChanged(_)
         | EventType::ImexProgress(_)
+        | EventType::HousekeepingProgress(_)
+        | EventType::ImageRecodeProgress { .. }
+        | EventType::AttachmentStagingProgress { .. }
         | EventType::SecurejoinInviterProgress { .. }
         | EventType::SecurejoinJoinerProgress { .. }
         | EventType::ConnectivityChanged
+        | EventType::ServerCapabilitiesChanged
+        | EventType::OwnershipChanged
         | EventType::SelfavatarChanged
         | EventType::WebxdcStatusUpdate { .. }
         | EventType::WebxdcInstanceDeleted { .. }
         | EventType::AccountsBackgroundFetchDone
         | EventType::ChatEphemeralTimerModified { .. }
         | EventType::IncomingMsgBunch { .. }
+        | EventType::IncomingMsgReport { .. }
+        | EventType::IncomingCall { .. }
+        | EventType::IncomingCallAccepted { .. }
+        | EventType::OutgoingCallAccepted { .. }
+        | EventType::CallEnded { .. }
+        | EventType::CallMissed { .. }
         | EventType::ChatlistItemChanged { .. }
+        | EventType::ChatlistItemMoved { .. }
+        | EventType::ChatlistItemRemoved { .. }
+        | EventType::ChatlistItemFreshMsgCountChanged { .. }
         | EventType::ChatlistChanged
         | EventType::AccountsChanged
         | EventType::AccountsItemChanged
@@ -783,6 +972,12 @@ fn spawn_configure(ctx: Context) {
             let data2 = file.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
+        EventType::ChatsAutoArchived { chat_ids } => chat_ids
+            .iter()
+            .map(|chat_id| chat_id.to_u32().to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+            .strdup(),
         EventType::ConfigSynced { key } => {
             let data2 = key.to_string().to_c_string().unwrap_or_default();
             data2.into_raw()
@@ -800,6 +995,35 @@ fn spawn_configure(ctx: Context) {
         EventType::IncomingWebxdcNotify { text, .. } => {
             text.to_c_string().unwrap_or_default().into_raw()
         }
+        EventType::ImexProgressDetail {
+            phase,
+            items_done,
+            items_total,
+            bytes_done,
+            bytes_total,
+            eta_secs,
+        } => serde_json::json!({
+            "phase": phase.to_string(),
+            "items_done": items_done,
+            "items_total": items_total,
+            "bytes_done": bytes_done,
+            "bytes_total": bytes_total,
+            "eta_secs": eta_secs,
+        })
+        .to_string()
+        .strdup(),
+        EventType::MsgStateChanged {
+            old_state,
+            new_state,
+            cause,
+            ..
+        } => serde_json::json!({
+            "cause": cause.to_string(),
+            "old_state": *old_state as u32,
+            "new_state": *new_state as u32,
+        })
+        .to_string()
+        .strdup(),
         #[allow(unreachable_patterns)]
         #[cfg(test)]
         _ => unreachable!("This is just to silence a rust_analyzer false-positive"),
@@ -840,6 +1064,19 @@ fn spawn_configure(ctx: Context) {
     drop(Box::from_raw(emitter));
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_event_emitter_set_coalescing_window(
+    emitter: *mut dc_event_emitter_t,
+    milliseconds: u32,
+) {
+    if emitter.is_null() {
+        eprintln!("ignoring careless call to dc_event_emitter_set_coalescing_window()");
+        return;
+    }
+    let emitter = &*emitter;
+    emitter.set_coalescing_window(Duration::from_millis(milliseconds.into()));
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_next_event(events: *mut dc_event_emitter_t) -> *mut dc_event_t {
     if events.is_null() {
@@ -870,6 +1107,70 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_pause_io(
+    context: *mut dc_context_t,
+    reason: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || reason.is_null() {
+        eprintln!("ignoring careless call to dc_pause_io()");
+        return 0;
+    }
+    let ctx = &*context;
+    let reason = to_string_lossy(reason);
+    block_on(ctx.pause_io(&reason))
+        .context("Failed to pause IO")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_resume_io(
+    context: *mut dc_context_t,
+    reason: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || reason.is_null() {
+        eprintln!("ignoring careless call to dc_resume_io()");
+        return 0;
+    }
+    let ctx = &*context;
+    let reason = to_string_lossy(reason);
+    block_on(ctx.resume_io(&reason))
+        .context("Failed to resume IO")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_is_owner(context: *mut dc_context_t) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_is_owner()");
+        return 0;
+    }
+    let ctx = &*context;
+    ctx.is_owner() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_release_ownership(context: *mut dc_context_t) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_release_ownership()");
+        return;
+    }
+    let ctx = &*context;
+    block_on(ctx.release_ownership())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_io_pause_reasons(context: *mut dc_context_t) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_io_pause_reasons()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+    ctx.get_io_pause_reasons().join("\n").strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_maybe_network(context: *mut dc_context_t) {
     if context.is_null() {
@@ -933,6 +1234,23 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_chatlist_diff_events(
+    context: *mut dc_context_t,
+    enabled: libc::c_int,
+) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_chatlist_diff_events()");
+        return;
+    }
+    let ctx = &*context;
+
+    block_on(chatlist::set_chatlist_diff_events(ctx, enabled != 0))
+        .context("Failed to set chatlist diff events")
+        .log_err(ctx)
+        .ok();
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_create_chat_by_contact_id(
     context: *mut dc_context_t,
@@ -1118,6 +1436,41 @@ fn spawn_configure(ctx: Context) {
     .strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_export_webxdc(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    path: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || path.is_null() {
+        eprintln!("ignoring careless call to dc_export_webxdc()");
+        return 0;
+    }
+    let ctx = &*context;
+    block_on(ctx.export_webxdc(MsgId::new(msg_id), as_path(path)))
+        .context("Failed to export webxdc")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_import_webxdc(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    path: *const libc::c_char,
+) -> u32 {
+    if context.is_null() || path.is_null() {
+        eprintln!("ignoring careless call to dc_import_webxdc()");
+        return 0;
+    }
+    let ctx = &*context;
+    block_on(ctx.import_webxdc(ChatId::new(chat_id), as_path(path)))
+        .context("Failed to import webxdc")
+        .log_err(ctx)
+        .map(|msg_id| msg_id.to_u32())
+        .unwrap_or_default()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_set_webxdc_integration(
     context: *mut dc_context_t,
@@ -1225,6 +1578,31 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_validate_draft(
+    context: *mut dc_context_t,
+    chat_id: u32,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_validate_draft()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        let arr = dc_array_t::from(
+            ChatId::new(chat_id)
+                .validate_draft(ctx)
+                .await
+                .unwrap_or_log_default(ctx, "Failed validate_draft")
+                .iter()
+                .map(|(contact_id, _)| contact_id.to_u32())
+                .collect::<Vec<u32>>(),
+        );
+        Box::into_raw(Box::new(arr))
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_draft(context: *mut dc_context_t, chat_id: u32) -> *mut dc_msg_t {
     if context.is_null() {
@@ -1385,6 +1763,126 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_check_blob_integrity(
+    context: *mut dc_context_t,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_check_blob_integrity()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        let arr = dc_array_t::from(
+            check_blob_integrity(ctx)
+                .await
+                .context("Failed to check blob integrity")
+                .log_err(ctx)
+                .unwrap_or_default()
+                .iter()
+                .map(|msg_id| msg_id.to_u32())
+                .collect::<Vec<u32>>(),
+        );
+        Box::into_raw(Box::new(arr))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_normalize_orientation(
+    context: *mut dc_context_t,
+    path: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || path.is_null() {
+        eprintln!("ignoring careless call to dc_normalize_orientation()");
+        return 0;
+    }
+    let ctx = &*context;
+    block_on(normalize_orientation(ctx, as_path(path)))
+        .context("Failed to normalize image orientation")
+        .log_err(ctx)
+        .unwrap_or_default() as libc::c_int
+}
+
+/// Video decoder callback as registered with `dc_set_video_decoder()`.
+pub type dc_video_decoder_t = unsafe extern "C" fn(
+    userdata: *mut libc::c_void,
+    path: *const libc::c_char,
+    poster_path: *const libc::c_char,
+    duration_ms: *mut u32,
+    width: *mut u32,
+    height: *mut u32,
+) -> libc::c_int;
+
+/// [VideoDecoder] calling a callback registered by the UI.
+#[derive(Debug)]
+struct CallbackVideoDecoder {
+    decoder: dc_video_decoder_t,
+    userdata: *mut libc::c_void,
+
+    /// Directory to write poster frames to, the blob directory.
+    poster_dir: std::path::PathBuf,
+}
+
+// The callback must be thread-safe as documented for `dc_set_video_decoder()`.
+unsafe impl Send for CallbackVideoDecoder {}
+unsafe impl Sync for CallbackVideoDecoder {}
+
+impl VideoDecoder for CallbackVideoDecoder {
+    fn decode(&self, path: &Path) -> anyhow::Result<VideoMetadata> {
+        let poster_path = self.poster_dir.join(format!(
+            ".video-poster-{:016x}.tmp",
+            rand::thread_rng().gen::<u64>()
+        ));
+        let c_path = path.to_c_string()?;
+        let c_poster_path = poster_path.to_c_string()?;
+        let mut duration_ms = 0;
+        let mut width = 0;
+        let mut height = 0;
+        let res = unsafe {
+            (self.decoder)(
+                self.userdata,
+                c_path.as_ptr(),
+                c_poster_path.as_ptr(),
+                &mut duration_ms,
+                &mut width,
+                &mut height,
+            )
+        };
+        let poster = std::fs::read(&poster_path).ok();
+        std::fs::remove_file(&poster_path).ok();
+        anyhow::ensure!(res != 0, "Video decoder callback failed");
+        let has_size = width > 0 && height > 0;
+        Ok(VideoMetadata {
+            duration_ms: (duration_ms > 0).then_some(duration_ms),
+            width: has_size.then_some(width),
+            height: has_size.then_some(height),
+            poster,
+        })
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_video_decoder(
+    context: *mut dc_context_t,
+    decoder: Option<dc_video_decoder_t>,
+    userdata: *mut libc::c_void,
+) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_video_decoder()");
+        return;
+    }
+    let ctx = &*context;
+    let decoder = decoder.map(|decoder| {
+        Arc::new(CallbackVideoDecoder {
+            decoder,
+            userdata,
+            poster_dir: ctx.get_blobdir().to_path_buf(),
+        }) as Arc<dyn VideoDecoder>
+    });
+    ctx.set_video_decoder(decoder);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_next_msgs(context: *mut dc_context_t) -> *mut dc_array::dc_array_t {
     if context.is_null() {
@@ -1429,6 +1927,23 @@ fn spawn_configure(ctx: Context) {
     Box::into_raw(Box::new(arr))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_first_unread_msg(context: *mut dc_context_t, chat_id: u32) -> u32 {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_first_unread_msg()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(chat::get_first_unread_msg(ctx, ChatId::new(chat_id)))
+        .context("Failed to get first unread message")
+        .log_err(ctx)
+        .ok()
+        .flatten()
+        .map(|msg_id| msg_id.to_u32())
+        .unwrap_or(0)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_marknoticed_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -1446,6 +1961,21 @@ fn spawn_configure(ctx: Context) {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_interactive_chat(context: *mut dc_context_t, chat_id: u32) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_interactive_chat()");
+        return;
+    }
+    let ctx = &*context;
+    let chat_id = match chat_id {
+        0 => None,
+        id => Some(ChatId::new(id)),
+    };
+
+    block_on(ctx.set_interactive_chat(chat_id))
+}
+
 fn from_prim<S, T>(s: S) -> Option<T>
 where
     T: FromPrimitive,
@@ -1488,6 +2018,68 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_watched_msgs(
+    context: *mut dc_context_t,
+    chat_id: u32,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_watched_msgs()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+    let chat_id = if chat_id == 0 {
+        None
+    } else {
+        Some(ChatId::new(chat_id))
+    };
+
+    block_on(async move {
+        Box::into_raw(Box::new(
+            watch::get_watched_msgs(ctx, chat_id)
+                .await
+                .unwrap_or_log_default(ctx, "Failed get_watched_msgs")
+                .into(),
+        ))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_mailbox_chat_id(context: *mut dc_context_t) -> u32 {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_mailbox_chat_id()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(email_rules::get_mailbox_chat(ctx))
+        .unwrap_or_log_default(ctx, "Failed get_mailbox_chat")
+        .map(|chat_id| chat_id.to_u32())
+        .unwrap_or_default()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_save_all_attachments(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    dir: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || dir.is_null() {
+        eprintln!("ignoring careless call to dc_save_all_attachments()");
+        return -1;
+    }
+    let ctx = &*context;
+    let dir = to_string_lossy(dir);
+    block_on(async move {
+        chat::save_all_attachments(ctx, ChatId::new(chat_id), Path::new(&dir))
+            .await
+            .context("Failed to save attachments")
+            .log_err(ctx)
+            .map(|paths| paths.len().try_into().unwrap_or(libc::c_int::MAX))
+            .unwrap_or(-1)
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_set_chat_visibility(
     context: *mut dc_context_t,
@@ -1522,6 +2114,70 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_chat_mdn_policy(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    policy: libc::c_int,
+) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_chat_mdn_policy()");
+        return;
+    }
+    let ctx = &*context;
+    let Some(policy) = from_prim(policy) else {
+        warn!(
+            ctx,
+            "ignoring careless call to dc_set_chat_mdn_policy(): unknown policy"
+        );
+        return;
+    };
+
+    block_on(async move {
+        ChatId::new(chat_id)
+            .set_mdn_policy(ctx, policy)
+            .await
+            .context("Failed setting chat MDN policy")
+            .log_err(ctx)
+            .ok();
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_chat_subject_template(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    template: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_chat_subject_template()");
+        return 0;
+    }
+    let ctx = &*context;
+    let template = to_string_lossy(template);
+    block_on(ChatId::new(chat_id).set_subject_template(ctx, &template))
+        .context("Failed to set chat subject template")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_chat_subject_template(
+    context: *mut dc_context_t,
+    chat_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_chat_subject_template()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+    block_on(ChatId::new(chat_id).get_subject_template(ctx))
+        .context("Failed to get chat subject template")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .strdup()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_delete_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -1540,6 +2196,51 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_cancel_image_recoding(context: *mut dc_context_t, chat_id: u32) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_cancel_image_recoding()");
+        return;
+    }
+    let ctx = &*context;
+
+    ChatId::new(chat_id).cancel_image_recoding(ctx);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_stage_attachment(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    msg: *const dc_msg_t,
+) -> u32 {
+    if context.is_null() || msg.is_null() {
+        eprintln!("ignoring careless call to dc_stage_attachment()");
+        return 0;
+    }
+    let ctx = &*context;
+    let ffi_msg = &*msg;
+
+    block_on(ChatId::new(chat_id).stage_attachment(ctx, &ffi_msg.message))
+        .context("Failed to stage attachment")
+        .log_err(ctx)
+        .map(|id| id.to_u32())
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_discard_staged_attachment(context: *mut dc_context_t, staged_id: u32) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_discard_staged_attachment()");
+        return;
+    }
+    let ctx = &*context;
+
+    block_on(StagedAttachmentId::new(staged_id).discard(ctx))
+        .context("Failed to discard staged attachment")
+        .log_err(ctx)
+        .ok();
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_block_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -1558,6 +2259,23 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_leave_and_block_creator(context: *mut dc_context_t, chat_id: u32) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_leave_and_block_creator()");
+        return;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        group_spam::leave_and_block_creator(ctx, ChatId::new(chat_id))
+            .await
+            .context("Failed to leave group and block creator")
+            .log_err(ctx)
+            .ok();
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accept_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -1973,6 +2691,34 @@ fn from_prim<S, T>(s: S) -> Option<T>
         .ok();
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_undo_last_deletion(context: *mut dc_context_t) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_undo_last_deletion()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(undo::undo_last_deletion(ctx))
+        .context("failed dc_undo_last_deletion() call")
+        .log_err(ctx)
+        .unwrap_or_default() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_cancel_send(context: *mut dc_context_t, msg_id: u32) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_cancel_send()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(undo::cancel_send(ctx, MsgId::new(msg_id)))
+        .context("failed dc_cancel_send() call")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_forward_msgs(
     context: *mut dc_context_t,
@@ -2019,6 +2765,112 @@ fn from_prim<S, T>(s: S) -> Option<T>
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_report_msg(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    reason: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_report_msg()");
+        return 0;
+    }
+    let ctx = &*context;
+    let reason = to_string_lossy(reason);
+
+    if let Err(err) = block_on(report::report_msg(ctx, MsgId::new(msg_id), &reason)) {
+        error!(ctx, "Reporting failed: {err:#}");
+        0
+    } else {
+        1
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_place_outgoing_call(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    payload: *const libc::c_char,
+) -> u32 {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_place_outgoing_call()");
+        return 0;
+    }
+    let ctx = &*context;
+    let payload = to_string_lossy(payload);
+
+    block_on(ctx.place_outgoing_call(ChatId::new(chat_id), &payload))
+        .context("Failed to place call")
+        .log_err(ctx)
+        .map(|msg_id| msg_id.to_u32())
+        .unwrap_or_default()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accept_incoming_call(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    payload: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_accept_incoming_call()");
+        return 0;
+    }
+    let ctx = &*context;
+    let payload = to_string_lossy(payload);
+
+    block_on(ctx.accept_incoming_call(MsgId::new(msg_id), &payload))
+        .context("Failed to accept call")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_end_call(context: *mut dc_context_t, msg_id: u32) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_end_call()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.end_call(MsgId::new(msg_id)))
+        .context("Failed to end call")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_call_state(context: *mut dc_context_t, msg_id: u32) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_call_state()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.get_call_info(MsgId::new(msg_id)))
+        .log_err(ctx)
+        .map(|info| info.state as libc::c_int)
+        .unwrap_or_default()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_call_payload(
+    context: *mut dc_context_t,
+    msg_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_call_payload()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+
+    match block_on(ctx.get_call_info(MsgId::new(msg_id))).log_err(ctx) {
+        Ok(info) if info.is_incoming => info.payload.strdup(),
+        Ok(info) => info.answer.unwrap_or_default().strdup(),
+        Err(_) => "".strdup(),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_markseen_msgs(
     context: *mut dc_context_t,
@@ -2150,6 +3002,38 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_apply_address_book_delta(
+    context: *mut dc_context_t,
+    source: *const libc::c_char,
+    upserts: *const libc::c_char,
+    deletions: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || source.is_null() {
+        eprintln!("ignoring careless call to dc_apply_address_book_delta()");
+        return -1;
+    }
+    let ctx = &*context;
+    let source = to_string_lossy(source);
+    let upserts = AddressBookEntry::parse_list(&to_opt_string_lossy(upserts).unwrap_or_default());
+    let deletions: Vec<String> = to_opt_string_lossy(deletions)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    block_on(async move {
+        Contact::apply_address_book_delta(ctx, &source, &upserts, &deletions)
+            .await
+            .context("Failed to apply address book delta")
+            .log_err(ctx)
+            .map(|cnt| cnt as libc::c_int)
+            .unwrap_or(-1)
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_contacts(
     context: *mut dc_context_t,
@@ -2173,6 +3057,70 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+fn contact_list_filter(flags: u32, query: *const libc::c_char) -> ContactListFilter {
+    ContactListFilter {
+        query: to_opt_string_lossy(query),
+        verified_only: (flags & constants::DC_GCL_VERIFIED_ONLY) != 0,
+        with_chat_only: (flags & constants::DC_GCL_WITH_CHAT_ONLY) != 0,
+        ..Default::default()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_contacts_page(
+    context: *mut dc_context_t,
+    flags: u32,
+    query: *const libc::c_char,
+    sort: libc::c_int,
+    offset: libc::c_int,
+    limit: libc::c_int,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_contacts_page()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+    let filter = ContactListFilter {
+        sort: from_prim(sort).unwrap_or_default(),
+        offset: usize::try_from(offset).unwrap_or_default(),
+        limit: usize::try_from(limit).ok().filter(|limit| *limit > 0),
+        ..contact_list_filter(flags, query)
+    };
+
+    block_on(async move {
+        match Contact::get_page(ctx, &filter).await.log_err(ctx) {
+            Ok(entries) => Box::into_raw(Box::new(dc_array_t::from(
+                entries
+                    .iter()
+                    .map(|entry| entry.id.to_u32())
+                    .collect::<Vec<u32>>(),
+            ))),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_contacts_cnt(
+    context: *mut dc_context_t,
+    flags: u32,
+    query: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_contacts_cnt()");
+        return 0;
+    }
+    let ctx = &*context;
+    let filter = contact_list_filter(flags, query);
+
+    block_on(async move {
+        Contact::get_count(ctx, &filter)
+            .await
+            .log_err(ctx)
+            .unwrap_or_default() as libc::c_int
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_blocked_cnt(context: *mut dc_context_t) -> libc::c_int {
     if context.is_null() {
@@ -2242,6 +3190,133 @@ fn from_prim<S, T>(s: S) -> Option<T>
     });
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_contact_mdn_policy(
+    context: *mut dc_context_t,
+    contact_id: u32,
+    policy: libc::c_int,
+) {
+    let contact_id = ContactId::new(contact_id);
+    if context.is_null() || contact_id.is_special() {
+        eprintln!("ignoring careless call to dc_set_contact_mdn_policy()");
+        return;
+    }
+    let ctx = &*context;
+    let Some(policy) = from_prim(policy) else {
+        warn!(
+            ctx,
+            "ignoring careless call to dc_set_contact_mdn_policy(): unknown policy"
+        );
+        return;
+    };
+
+    block_on(async move {
+        contact_id
+            .set_mdn_policy(ctx, policy)
+            .await
+            .context("Failed setting contact MDN policy")
+            .log_err(ctx)
+            .ok();
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_contact_note(
+    context: *mut dc_context_t,
+    contact_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_contact_note()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+    block_on(ContactId::new(contact_id).get_note(ctx))
+        .context("Failed to get contact note")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .strdup()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_contact_note(
+    context: *mut dc_context_t,
+    contact_id: u32,
+    note: *const libc::c_char,
+) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_contact_note()");
+        return;
+    }
+    let ctx = &*context;
+    let note = to_string_lossy(note);
+    block_on(ContactId::new(contact_id).set_note(ctx, &note))
+        .context("Failed to set contact note")
+        .log_err(ctx)
+        .ok();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_contact_tags(
+    context: *mut dc_context_t,
+    contact_id: u32,
+) -> *mut libc::c_char {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_get_contact_tags()");
+        return "".strdup();
+    }
+    let ctx = &*context;
+    block_on(ContactId::new(contact_id).get_tags(ctx))
+        .context("Failed to get contact tags")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .join("\n")
+        .strdup()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_contact_tags(
+    context: *mut dc_context_t,
+    contact_id: u32,
+    tags: *const libc::c_char,
+) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_contact_tags()");
+        return;
+    }
+    let ctx = &*context;
+    let tags: Vec<String> = to_string_lossy(tags).lines().map(Into::into).collect();
+    block_on(ContactId::new(contact_id).set_tags(ctx, &tags))
+        .context("Failed to set contact tags")
+        .log_err(ctx)
+        .ok();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_get_contacts_by_tag(
+    context: *mut dc_context_t,
+    tag: *const libc::c_char,
+) -> *mut dc_array::dc_array_t {
+    if context.is_null() || tag.is_null() {
+        eprintln!("ignoring careless call to dc_get_contacts_by_tag()");
+        return ptr::null_mut();
+    }
+    let ctx = &*context;
+    let tag = to_string_lossy(tag);
+
+    block_on(async move {
+        Box::into_raw(Box::new(dc_array_t::from(
+            contact_notes::get_contacts_by_tag(ctx, &tag)
+                .await
+                .context("Failed to get contacts by tag")
+                .log_err(ctx)
+                .unwrap_or_default()
+                .iter()
+                .map(|id| id.to_u32())
+                .collect::<Vec<u32>>(),
+        )))
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_get_contact_encrinfo(
     context: *mut dc_context_t,
@@ -2657,17 +3732,59 @@ fn spawn_imex(ctx: Context, what: imex::ImexMode, param1: String, passphrase: Op
         eprintln!("ignoring careless call to dc_array_get_cnt()");
         return 0;
     }
-
-    (*array).len()
+
+    (*array).len()
+}
+#[no_mangle]
+pub unsafe extern "C" fn dc_array_get_id(array: *const dc_array_t, index: libc::size_t) -> u32 {
+    if array.is_null() {
+        eprintln!("ignoring careless call to dc_array_get_id()");
+        return 0;
+    }
+
+    (*array).get_id(index)
+}
+#[no_mangle]
+pub unsafe extern "C" fn dc_array_get_ids(
+    array: *const dc_array_t,
+    start: libc::size_t,
+    count: libc::size_t,
+    ret_ids: *mut u32,
+) -> libc::size_t {
+    if array.is_null() || ret_ids.is_null() {
+        eprintln!("ignoring careless call to dc_array_get_ids()");
+        return 0;
+    }
+
+    let ret_ids = std::slice::from_raw_parts_mut(ret_ids, count);
+    (*array).copy_ids(start, ret_ids)
+}
+#[no_mangle]
+pub unsafe extern "C" fn dc_array_get_all_ids(
+    array: *const dc_array_t,
+    ret_cnt: *mut libc::size_t,
+) -> *mut u32 {
+    if array.is_null() || ret_cnt.is_null() {
+        eprintln!("ignoring careless call to dc_array_get_all_ids()");
+        return ptr::null_mut();
+    }
+
+    let cnt = (*array).len();
+    *ret_cnt = 0;
+    if cnt == 0 {
+        return ptr::null_mut();
+    }
+    let ptr = libc::malloc(cnt * std::mem::size_of::<u32>()) as *mut u32;
+    if ptr.is_null() {
+        return ptr::null_mut();
+    }
+    let ids = std::slice::from_raw_parts_mut(ptr, cnt);
+    *ret_cnt = (*array).copy_ids(0, ids);
+    ptr
 }
 #[no_mangle]
-pub unsafe extern "C" fn dc_array_get_id(array: *const dc_array_t, index: libc::size_t) -> u32 {
-    if array.is_null() {
-        eprintln!("ignoring careless call to dc_array_get_id()");
-        return 0;
-    }
-
-    (*array).get_id(index)
+pub unsafe extern "C" fn dc_array_ids_unref(ids: *mut u32) {
+    libc::free(ids as *mut _)
 }
 #[no_mangle]
 pub unsafe extern "C" fn dc_array_get_latitude(
@@ -3070,6 +4187,16 @@ pub struct ChatWrapper {
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_chat_get_mdn_policy(chat: *mut dc_chat_t) -> libc::c_int {
+    if chat.is_null() {
+        eprintln!("ignoring careless call to dc_chat_get_mdn_policy()");
+        return 0;
+    }
+    let ffi_chat = &*chat;
+    ffi_chat.chat.get_mdn_policy() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_chat_is_contact_request(chat: *mut dc_chat_t) -> libc::c_int {
     if chat.is_null() {
@@ -3080,6 +4207,16 @@ pub struct ChatWrapper {
     ffi_chat.chat.is_contact_request() as libc::c_int
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_chat_is_quarantined(chat: *mut dc_chat_t) -> libc::c_int {
+    if chat.is_null() {
+        eprintln!("ignoring careless call to dc_chat_is_quarantined()");
+        return 0;
+    }
+    let ffi_chat = &*chat;
+    ffi_chat.chat.is_quarantined() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_chat_is_unpromoted(chat: *mut dc_chat_t) -> libc::c_int {
     if chat.is_null() {
@@ -3393,6 +4530,53 @@ pub struct MessageWrapper {
         .unwrap_or_else(|| "".strdup())
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_thumbnail(msg: *mut dc_msg_t) -> *mut libc::c_char {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_thumbnail()");
+        return "".strdup();
+    }
+    let ffi_msg = &*msg;
+    let ctx = &*ffi_msg.context;
+    ffi_msg
+        .message
+        .get_thumbnail(ctx)
+        .map(|p| p.to_string_lossy().strdup())
+        .unwrap_or_else(|| "".strdup())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_watched_keyword(msg: *mut dc_msg_t) -> *mut libc::c_char {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_watched_keyword()");
+        return ptr::null_mut();
+    }
+    let ffi_msg = &*msg;
+    let ctx = &*ffi_msg.context;
+    block_on(ffi_msg.message.get_id().get_watched_keyword(ctx))
+        .context("Failed to get watched keyword")
+        .log_err(ctx)
+        .ok()
+        .flatten()
+        .map_or(ptr::null_mut(), |keyword| keyword.strdup())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_send_delay_end(msg: *mut dc_msg_t) -> i64 {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_send_delay_end()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    let ctx = &*ffi_msg.context;
+    block_on(ffi_msg.message.get_id().get_send_delay_end(ctx))
+        .context("Failed to get send delay end")
+        .log_err(ctx)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_save_file(
     msg: *mut dc_msg_t,
@@ -3421,6 +4605,34 @@ pub struct MessageWrapper {
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_save_file_ex(
+    msg: *mut dc_msg_t,
+    path: *const libc::c_char,
+    policy: libc::c_int,
+) -> *mut libc::c_char {
+    if msg.is_null() || path.is_null() {
+        eprintln!("ignoring careless call to dc_msg_save_file_ex()");
+        return ptr::null_mut();
+    }
+    let ffi_msg = &*msg;
+    let ctx = &*ffi_msg.context;
+    let path = to_string_lossy(path);
+    let Some(policy) = from_prim(policy) else {
+        eprintln!("ignoring dc_msg_save_file_ex() with invalid policy {policy}");
+        return ptr::null_mut();
+    };
+    block_on(
+        ffi_msg
+            .message
+            .save_file_ex(ctx, &std::path::PathBuf::from(path), policy),
+    )
+    .context("Failed to save file from message")
+    .log_err(ctx)
+    .map(|path| path.to_string_lossy().strdup())
+    .unwrap_or_else(|_| ptr::null_mut())
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_get_filename(msg: *mut dc_msg_t) -> *mut libc::c_char {
     if msg.is_null() {
@@ -3642,6 +4854,20 @@ pub struct MessageWrapper {
     ffi_msg.message.get_override_sender_name().strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_get_impersonated_contact_id(msg: *mut dc_msg_t) -> u32 {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_get_impersonated_contact_id()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg
+        .message
+        .get_impersonated_contact()
+        .map(|contact_id| contact_id.to_u32())
+        .unwrap_or_default()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_has_deviating_timestamp(msg: *mut dc_msg_t) -> libc::c_int {
     if msg.is_null() {
@@ -3649,7 +4875,12 @@ pub struct MessageWrapper {
         return 0;
     }
     let ffi_msg = &*msg;
-    ffi_msg.message.has_deviating_timestamp().into()
+    let ctx = &*ffi_msg.context;
+    block_on(ffi_msg.message.has_deviating_timestamp(ctx))
+        .context("Failed to check for deviating timestamp")
+        .log_err(ctx)
+        .unwrap_or_default()
+        .into()
 }
 
 #[no_mangle]
@@ -3682,6 +4913,16 @@ pub struct MessageWrapper {
     ffi_msg.message.is_forwarded().into()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_is_removed_from_server(msg: *mut dc_msg_t) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_is_removed_from_server()");
+        return 0;
+    }
+    let ffi_msg = &*msg;
+    ffi_msg.message.is_removed_from_server().into()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_is_info(msg: *mut dc_msg_t) -> libc::c_int {
     if msg.is_null() {
@@ -3827,6 +5068,19 @@ pub struct MessageWrapper {
         .set_override_sender_name(to_opt_string_lossy(name))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_original_quality(
+    msg: *mut dc_msg_t,
+    original_quality: libc::c_int,
+) {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_set_original_quality()");
+        return;
+    }
+    let ffi_msg = &mut *msg;
+    ffi_msg.message.set_original_quality(original_quality != 0)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_set_file(
     msg: *mut dc_msg_t,
@@ -3906,6 +5160,28 @@ pub struct MessageWrapper {
     .ok();
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_msg_set_staged_attachment(
+    msg: *mut dc_msg_t,
+    staged_id: u32,
+) -> libc::c_int {
+    if msg.is_null() {
+        eprintln!("ignoring careless call to dc_msg_set_staged_attachment()");
+        return 0;
+    }
+    let ffi_msg = &mut *msg;
+    let ctx = &*ffi_msg.context;
+
+    block_on(
+        ffi_msg
+            .message
+            .set_staged_attachment(ctx, StagedAttachmentId::new(staged_id)),
+    )
+    .context("Cannot set staged attachment")
+    .log_err(ctx)
+    .is_ok() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_msg_get_error(msg: *mut dc_msg_t) -> *mut libc::c_char {
     if msg.is_null() {
@@ -4180,6 +5456,16 @@ pub struct ContactWrapper {
     ffi_contact.contact.is_blocked() as libc::c_int
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_contact_get_mdn_policy(contact: *mut dc_contact_t) -> libc::c_int {
+    if contact.is_null() {
+        eprintln!("ignoring careless call to dc_contact_get_mdn_policy()");
+        return 0;
+    }
+    let ffi_contact = &*contact;
+    ffi_contact.contact.get_mdn_policy() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_contact_is_verified(contact: *mut dc_contact_t) -> libc::c_int {
     if contact.is_null() {
@@ -4677,6 +5963,50 @@ fn new(accounts: Accounts) -> Self {
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_new_encrypted(
+    dir: *const libc::c_char,
+    writable: libc::c_int,
+    passphrase: *const libc::c_char,
+) -> *mut dc_accounts_t {
+    setup_panic!();
+
+    if dir.is_null() || passphrase.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_new_encrypted()");
+        return ptr::null_mut();
+    }
+
+    let accs = block_on(Accounts::new_encrypted(
+        as_path(dir).into(),
+        writable != 0,
+        to_string_lossy(passphrase),
+    ));
+
+    match accs {
+        Ok(accs) => Box::into_raw(Box::new(AccountsWrapper::new(accs))),
+        Err(err) => {
+            eprintln!("failed to create accounts: {err:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_is_config_encrypted(dir: *const libc::c_char) -> libc::c_int {
+    if dir.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_is_config_encrypted()");
+        return 0;
+    }
+
+    match block_on(Accounts::is_config_encrypted(as_path(dir))) {
+        Ok(encrypted) => encrypted.into(),
+        Err(err) => {
+            eprintln!("failed to check accounts config: {err:#}");
+            0
+        }
+    }
+}
+
 /// Release the accounts structure.
 ///
 /// This function releases the memory of the `dc_accounts_t` structure.
@@ -4845,6 +6175,88 @@ fn new(accounts: Accounts) -> Self {
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_set_config_passphrase(
+    accounts: *mut dc_accounts_t,
+    passphrase: *const libc::c_char,
+) -> libc::c_int {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_set_config_passphrase()");
+        return 0;
+    }
+
+    let accounts = &mut *accounts;
+    let passphrase = to_opt_string_lossy(passphrase);
+
+    block_on(async move {
+        let mut accounts = accounts.write().await;
+        match accounts.set_config_passphrase(passphrase).await {
+            Ok(()) => 1,
+            Err(err) => {
+                accounts.emit_event(EventType::Error(format!(
+                    "Failed to set accounts config passphrase: {err:#}"
+                )));
+                0
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_export_account(
+    accounts: *mut dc_accounts_t,
+    account_id: u32,
+    path: *const libc::c_char,
+) -> libc::c_int {
+    if accounts.is_null() || path.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_export_account()");
+        return 0;
+    }
+
+    let accounts = &mut *accounts;
+    let path = to_string_lossy(path);
+
+    block_on(async move {
+        let mut accounts = accounts.write().await;
+        match accounts.export_account(account_id, Path::new(&path)).await {
+            Ok(()) => 1,
+            Err(err) => {
+                accounts.emit_event(EventType::Error(format!(
+                    "Failed to export account: {err:#}"
+                )));
+                0
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_import_account(
+    accounts: *mut dc_accounts_t,
+    path: *const libc::c_char,
+) -> u32 {
+    if accounts.is_null() || path.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_import_account()");
+        return 0;
+    }
+
+    let accounts = &mut *accounts;
+    let path = to_string_lossy(path);
+
+    block_on(async move {
+        let mut accounts = accounts.write().await;
+        match accounts.import_account(Path::new(&path)).await {
+            Ok(id) => id,
+            Err(err) => {
+                accounts.emit_event(EventType::Error(format!(
+                    "Failed to import account: {err:#}"
+                )));
+                0
+            }
+        }
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accounts_get_all(accounts: *mut dc_accounts_t) -> *mut dc_array_t {
     if accounts.is_null() {
@@ -4892,6 +6304,41 @@ fn new(accounts: Accounts) -> Self {
     block_on(async move { accounts.read().await.maybe_network().await });
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_on_network_changed(
+    accounts: *mut dc_accounts_t,
+    kind: libc::c_int,
+) {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_on_network_changed()");
+        return;
+    }
+
+    let accounts = &*accounts;
+    let kind = from_prim(kind).unwrap_or_default();
+    block_on(async move { accounts.read().await.on_network_changed(kind).await });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_set_network_identity(
+    accounts: *mut dc_accounts_t,
+    identity: *const libc::c_char,
+) {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_set_network_identity()");
+        return;
+    }
+
+    let accounts = &*accounts;
+    let identity = to_opt_string_lossy(identity);
+    block_on(async move {
+        accounts
+            .read()
+            .await
+            .set_network_identity(identity.as_deref())
+    });
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accounts_maybe_network_lost(accounts: *mut dc_accounts_t) {
     if accounts.is_null() {
@@ -4903,6 +6350,20 @@ fn new(accounts: Accounts) -> Self {
     block_on(async move { accounts.read().await.maybe_network_lost().await });
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_accounts_set_foreground(
+    accounts: *mut dc_accounts_t,
+    foreground: libc::c_int,
+) {
+    if accounts.is_null() {
+        eprintln!("ignoring careless call to dc_accounts_set_foreground()");
+        return;
+    }
+
+    let accounts = &*accounts;
+    block_on(async move { accounts.read().await.set_foreground(foreground != 0).await });
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_accounts_background_fetch(
     accounts: *mut dc_accounts_t,
@@ -5,65 +5,98 @@
 use std::time::Duration;
 use std::{collections::HashMap, str::FromStr};
 
-use anyhow::{anyhow, bail, ensure, Context, Result};
+use anyhow::{anyhow, bail, Context};
 pub use deltachat::accounts::Accounts;
 use deltachat::chat::{
-    self, add_contact_to_chat, forward_msgs, get_chat_media, get_chat_msgs, get_chat_msgs_ex,
-    marknoticed_chat, remove_contact_from_chat, Chat, ChatId, ChatItem, MessageListOptions,
-    ProtectionStatus,
+    self, add_contact_to_chat, forward_msgs, get_chat_media, get_chat_msgs_ex,
+    get_first_unread_msg, marknoticed_chat, remove_contact_from_chat, Chat, ChatId,
+    MessageListOptions, ProtectionStatus,
 };
 use deltachat::chatlist::Chatlist;
 use deltachat::config::Config;
-use deltachat::constants::DC_MSG_ID_DAYMARKER;
+use deltachat::constants::{DC_MSG_ID_DAYMARKER, DC_VERSION_STR};
 use deltachat::contact::{may_be_valid_addr, Contact, ContactId, Origin};
 use deltachat::context::get_info;
 use deltachat::ephemeral::Timer;
 use deltachat::location;
 use deltachat::message::get_msg_read_receipts;
-use deltachat::message::{
-    self, delete_msgs, markseen_msgs, Message, MessageState, MsgId, Viewtype,
-};
+use deltachat::message::{self, delete_msgs, markseen_msgs, Message, MsgId, Viewtype};
+use deltachat::notes;
 use deltachat::peer_channels::{
     leave_webxdc_realtime, send_webxdc_realtime_advertisement, send_webxdc_realtime_data,
 };
+use deltachat::peerstate;
 use deltachat::provider::get_provider_info;
 use deltachat::qr::{self, Qr};
-use deltachat::qr_code_generator::{generate_backup_qr, get_securejoin_qr_svg};
+use deltachat::qr_code_generator::{create_qr_svg, generate_backup_qr, get_securejoin_qr_svg};
 use deltachat::reaction::{get_msg_reactions, send_reaction};
 use deltachat::securejoin;
+use deltachat::simplify::simplify;
+use deltachat::staging::StagedAttachmentId;
 use deltachat::stock_str::StockMessage;
-use deltachat::webxdc::StatusUpdateSerial;
+use deltachat::tools::sanitize_filename;
+use deltachat::webxdc::{StatusUpdateQuery, StatusUpdateSerial};
 use deltachat::EventEmitter;
-use deltachat::{imex, info};
+use deltachat::{
+    activity, contact_notes, email_rules, group_history, group_spam, housekeeping, imex, info,
+    member_digest, remote_backup, report, timezone,
+};
 use sanitize_filename::is_sanitized;
 use tokio::fs;
 use tokio::sync::{watch, Mutex, RwLock};
 use walkdir::WalkDir;
 use yerpc::rpc;
+use yerpc::RpcServer as _;
 
+mod chat_watcher;
+pub mod error;
 pub mod types;
 
+use error::Result;
 use num_traits::FromPrimitive;
 use types::account::Account;
-use types::chat::FullChat;
-use types::contact::{ContactObject, VcardContact};
+use types::activity::JsonrpcActivityEntry;
+use types::backup::{BackupInfo, RemoteBackupInfo};
+use types::calls::CallInfo;
+use types::chat::{FullChat, MemberChange, UnencryptableMember};
+use types::config_audit::ConfigChange;
+use types::contact::{
+    AddressBookEntry, ContactListEntry, ContactListFilter, ContactObject, VcardContact,
+};
 use types::events::Event;
+use types::health::AccountHealth;
+use types::housekeeping::HousekeepingStatus;
 use types::http::HttpResponse;
-use types::message::{MessageData, MessageObject, MessageReadReceipt};
+use types::mdn::MdnPolicy;
+use types::message::{
+    MessageData, MessageObject, MessageReadReceipt, MessageReport, SaveFilePolicy, Todo,
+};
+use types::network::NetworkKind;
+use types::network_stats::{DeliveryLatencyStats, NetworkStats};
+use types::peerstate::PeerstateInfo;
 use types::provider_info::ProviderInfo;
+use types::quota::{QuotaHistoryEntry, QuotaUsage};
 use types::reactions::JSONRPCReactions;
-use types::webxdc::WebxdcMessageInfo;
+use types::server_capabilities::ServerCapabilities;
+use types::sql_stats::SqlStats;
+use types::startup::StartupTimings;
+use types::utils::SimplifiedText;
+use types::webxdc::{WebxdcMessageInfo, WebxdcStatusUpdateEntry};
 
+use self::chat_watcher::ChatWatcher;
 use self::types::message::{MessageInfo, MessageLoadResult};
 use self::types::{
-    chat::{BasicChat, JSONRPCChatVisibility, MuteDuration},
+    chat::{
+        BasicChat, ChatStats, JSONRPCChatVisibility, MuteDuration, RetentionPolicy, SafetyNumber,
+    },
     location::JsonrpcLocation,
     message::{
-        JSONRPCMessageListItem, MessageNotificationInfo, MessageSearchResult, MessageViewtype,
+        JSONRPCMessageListItem, MessageListChange, MessageNotificationInfo, MessageSearchResult,
+        MessageViewtype,
     },
 };
 use crate::api::types::chat_list::{get_chat_list_item_by_id, ChatListItemFetchResult};
-use crate::api::types::qr::QrObject;
+use crate::api::types::qr::{InviteLinkPreview, QrObject};
 
 #[derive(Debug)]
 struct AccountState {
@@ -72,6 +105,9 @@ struct AccountState {
     /// If there is currently is a call to [`CommandApi::provide_backup`] this will be
     /// `Some`, otherwise `None`.
     backup_provider_qr: watch::Sender<Option<Qr>>,
+
+    /// Watchers of chat message lists by chat ID, see [`CommandApi::watch_chat`].
+    chat_watchers: BTreeMap<u32, ChatWatcher>,
 }
 
 impl Default for AccountState {
@@ -79,6 +115,7 @@ fn default() -> Self {
         let tx = watch::Sender::new(None);
         Self {
             backup_provider_qr: tx,
+            chat_watchers: BTreeMap::new(),
         }
     }
 }
@@ -105,7 +142,6 @@ pub fn new(accounts: Accounts) -> Self {
         }
     }
 
-    #[allow(dead_code)]
     pub async fn from_arc(accounts: Arc<RwLock<Accounts>>) -> Self {
         let event_emitter = Arc::new(accounts.read().await.get_event_emitter());
         CommandApi {
@@ -115,7 +151,20 @@ pub async fn from_arc(accounts: Arc<RwLock<Accounts>>) -> Self {
         }
     }
 
-    async fn get_context(&self, id: u32) -> Result<deltachat::context::Context> {
+    /// Returns the OpenRPC specification of the API
+    /// with the core version as `info.version`
+    /// and the schema of the error `data` as `ErrorData`.
+    pub fn openrpc_document() -> anyhow::Result<serde_json::Value> {
+        let mut spec: serde_json::Value =
+            serde_json::from_str(&CommandApi::openrpc_specification()?)?;
+        if let Some(info) = spec.get_mut("info").and_then(|info| info.as_object_mut()) {
+            info.insert("version".to_string(), DC_VERSION_STR.as_str().into());
+        }
+        error::add_openrpc_schemas(&mut spec)?;
+        Ok(spec)
+    }
+
+    async fn get_context(&self, id: u32) -> anyhow::Result<deltachat::context::Context> {
         let sc = self
             .accounts
             .read()
@@ -134,7 +183,7 @@ async fn with_state<F, T>(&self, id: u32, with_state: F) -> T
         with_state(state)
     }
 
-    async fn inner_get_backup_qr(&self, account_id: u32) -> Result<Qr> {
+    async fn inner_get_backup_qr(&self, account_id: u32) -> anyhow::Result<Qr> {
         let mut receiver = self
             .with_state(account_id, |state| state.backup_provider_qr.subscribe())
             .await;
@@ -162,6 +211,8 @@ async fn sleep(&self, delay: f64) {
     // ---------------------------------------------
 
     /// Checks if an email address is valid.
+    ///
+    /// Same as [`utils_check_email_validity`](Self::utils_check_email_validity).
     async fn check_email_validity(&self, email: String) -> bool {
         may_be_valid_addr(&email)
     }
@@ -171,13 +222,74 @@ async fn get_system_info(&self) -> BTreeMap<&'static str, String> {
         get_info()
     }
 
+    /// Returns the [OpenRPC](https://open-rpc.org) specification of this API,
+    /// describing all methods and the JSON schemas of their parameters and results.
+    ///
+    /// The specification matches the running core,
+    /// its `info.version` is the core version,
+    /// so client generators can check whether they are in sync.
+    async fn get_openrpc_specification(&self) -> Result<serde_json::Value> {
+        Ok(CommandApi::openrpc_document()?)
+    }
+
     /// Get the next event.
     async fn get_next_event(&self) -> Result<Event> {
-        self.event_emitter
+        Ok(self
+            .event_emitter
             .recv()
             .await
             .map(|event| event.into())
-            .context("event channel is closed")
+            .context("event channel is closed")?)
+    }
+
+    /// Set the time window in milliseconds in which low-priority events are merged.
+    ///
+    /// Events such as `MsgsChanged` or `ChatlistChanged` are then delayed
+    /// by up to this window and identical events emitted meanwhile are dropped.
+    /// Other events are delivered without delay.
+    /// 0 disables coalescing, which is the default.
+    async fn set_event_coalescing_window(&self, milliseconds: u32) -> Result<()> {
+        self.event_emitter
+            .set_coalescing_window(Duration::from_millis(milliseconds.into()));
+        Ok(())
+    }
+
+    // ---------------------------------------------
+    // Utils
+    //
+    // Helpers that do not need an account.
+    // Bindings should use these instead of reimplementing them,
+    // so all clients behave the same.
+    // ---------------------------------------------
+
+    /// Checks if an email address is valid.
+    async fn utils_check_email_validity(&self, email: String) -> bool {
+        may_be_valid_addr(&email)
+    }
+
+    /// Creates an SVG image of a QR code containing `content`.
+    ///
+    /// The QR code has the Delta Chat logo in the center.
+    async fn utils_create_qr_svg(&self, content: String) -> Result<String> {
+        Ok(create_qr_svg(&content)?)
+    }
+
+    /// Sanitizes a file name the same way as names of attachments are sanitized.
+    ///
+    /// Directories and characters not allowed in file names are removed,
+    /// the extension is lowercased and the name is truncated.
+    /// Returns an empty string if nothing is left.
+    async fn utils_sanitize_filename(&self, name: String) -> String {
+        sanitize_filename(&name)
+    }
+
+    /// Simplifies message text for display,
+    /// removing quotes, signatures, trailing empty lines etc.
+    ///
+    /// `is_chat_message` should be set for messages sent by Delta Chat,
+    /// quotes are not removed from them.
+    async fn utils_simplify_text(&self, text: String, is_chat_message: bool) -> SimplifiedText {
+        simplify(text, is_chat_message).into()
     }
 
     // ---------------------------------------------
@@ -185,17 +297,52 @@ async fn get_next_event(&self) -> Result<Event> {
     // ---------------------------------------------
 
     async fn add_account(&self) -> Result<u32> {
-        self.accounts.write().await.add_account().await
+        Ok(self.accounts.write().await.add_account().await?)
     }
 
     /// Imports/migrated an existing account from a database path into this account manager.
     /// Returns the ID of new account.
     async fn migrate_account(&self, path_to_db: String) -> Result<u32> {
-        self.accounts
+        Ok(self
+            .accounts
             .write()
             .await
             .migrate_account(std::path::PathBuf::from(path_to_db))
+            .await?)
+    }
+
+    /// Moves the account out of this account manager into the directory `path`,
+    /// so it can be added to another account manager with `import_account()`.
+    async fn export_account(&self, account_id: u32, path: String) -> Result<()> {
+        self.accounts
+            .write()
+            .await
+            .export_account(account_id, Path::new(&path))
+            .await?;
+        self.states.lock().await.remove(&account_id);
+        Ok(())
+    }
+
+    /// Adds an account exported with `export_account()` to this account manager.
+    /// Returns the ID of new account.
+    async fn import_account(&self, path: String) -> Result<u32> {
+        Ok(self
+            .accounts
+            .write()
             .await
+            .import_account(Path::new(&path))
+            .await?)
+    }
+
+    /// Encrypts the account manager configuration with `passphrase`
+    /// or stores it unencrypted if `passphrase` is `null`.
+    async fn set_accounts_config_passphrase(&self, passphrase: Option<String>) -> Result<()> {
+        Ok(self
+            .accounts
+            .write()
+            .await
+            .set_config_passphrase(passphrase)
+            .await?)
     }
 
     async fn remove_account(&self, account_id: u32) -> Result<()> {
@@ -215,7 +362,7 @@ async fn get_all_account_ids(&self) -> Vec<u32> {
     /// Select account id for internally selected state.
     /// TODO: Likely this is deprecated as all methods take an account id now.
     async fn select_account(&self, id: u32) -> Result<()> {
-        self.accounts.write().await.select_account(id).await
+        Ok(self.accounts.write().await.select_account(id).await?)
     }
 
     /// Get the selected account id of the internal state..
@@ -281,16 +428,34 @@ async fn stop_io(&self, account_id: u32) -> Result<()> {
         Ok(())
     }
 
+    /// Pauses background tasks of a single account for `reason`.
+    ///
+    /// Pauses are counted per reason, background tasks are resumed
+    /// when `resume_io()` was called for every pause.
+    async fn pause_io(&self, account_id: u32, reason: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.pause_io(&reason).await?)
+    }
+
+    /// Releases one pause of background tasks made with `pause_io()` for `reason`.
+    async fn resume_io(&self, account_id: u32, reason: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.resume_io(&reason).await?)
+    }
+
+    /// Returns the reasons background tasks of the account are paused for with `pause_io()`.
+    async fn get_io_pause_reasons(&self, account_id: u32) -> Result<Vec<String>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_io_pause_reasons())
+    }
+
     /// Get top-level info for an account.
     async fn get_account_info(&self, account_id: u32) -> Result<Account> {
         let context_option = self.accounts.read().await.get_account(account_id);
         if let Some(ctx) = context_option {
             Ok(Account::from_context(&ctx, account_id).await?)
         } else {
-            Err(anyhow!(
-                "account with id {} doesn't exist anymore",
-                account_id
-            ))
+            Err(anyhow!("account with id {} doesn't exist anymore", account_id).into())
         }
     }
 
@@ -334,13 +499,205 @@ async fn get_provider_info(
     /// Checks if the context is already configured.
     async fn is_configured(&self, account_id: u32) -> Result<bool> {
         let ctx = self.get_context(account_id).await?;
-        ctx.is_configured().await
+        Ok(ctx.is_configured().await?)
     }
 
     /// Get system info for an account.
     async fn get_info(&self, account_id: u32) -> Result<BTreeMap<&'static str, String>> {
         let ctx = self.get_context(account_id).await?;
-        ctx.get_info().await
+        Ok(ctx.get_info().await?)
+    }
+
+    /// Returns the features of the server of an account,
+    /// so that UIs can enable features depending on the server.
+    ///
+    /// The features are updated when the account is configured
+    /// and whenever the connection to the server is established.
+    async fn get_server_capabilities(&self, account_id: u32) -> Result<ServerCapabilities> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_server_capabilities().await?.into())
+    }
+
+    /// Returns execution time statistics of database queries,
+    /// the most recent queries which took longer than 100 ms
+    /// and connection pool metrics.
+    async fn get_sql_stats(&self, account_id: u32) -> Result<SqlStats> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_sql_stats().await.into())
+    }
+
+    /// Returns statistics of profile data and Autocrypt gossip
+    /// attached to outgoing messages since the account was opened.
+    async fn get_network_stats(&self, account_id: u32) -> Result<NetworkStats> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_network_stats().into())
+    }
+
+    /// Returns the quota usage as reported by the server recently,
+    /// `null` if the quota was not queried yet.
+    ///
+    /// Fails if the server does not support quota.
+    async fn get_quota_usage(&self, account_id: u32) -> Result<Option<QuotaUsage>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_quota_usage().await?.map(Into::into))
+    }
+
+    /// Returns the quota usage recorded since the timestamp `since`, oldest first.
+    async fn get_quota_history(
+        &self,
+        account_id: u32,
+        since: i64,
+    ) -> Result<Vec<QuotaHistoryEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_quota_history(since)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Returns the number of messages `cleanup_server_messages` would delete from the server.
+    async fn estimate_server_cleanup(&self, account_id: u32, older_than_days: u32) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .estimate_server_cleanup(older_than_days)
+            .await?
+            .try_into()?)
+    }
+
+    /// Deletes messages older than `older_than_days` days from the server
+    /// to free space when the quota is nearly exceeded. Messages are kept on the device.
+    ///
+    /// Returns the number of messages scheduled for deletion.
+    async fn cleanup_server_messages(&self, account_id: u32, older_than_days: u32) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .cleanup_server_messages(older_than_days)
+            .await?
+            .try_into()?)
+    }
+
+    /// Evaluates the health of the account, e.g. repeated login failures or a nearly full mailbox.
+    ///
+    /// Warnings are also reported by the periodic evaluation
+    /// with `AccountHealthWarning` events.
+    async fn get_account_health(&self, account_id: u32) -> Result<AccountHealth> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_account_health().await?.into())
+    }
+
+    /// Returns delivery latencies of outgoing messages queued since the timestamp `since`.
+    ///
+    /// Pass `since=0` to get the latencies of all recorded messages.
+    async fn get_delivery_latency_stats(
+        &self,
+        account_id: u32,
+        since: i64,
+    ) -> Result<DeliveryLatencyStats> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_delivery_latency_stats(since).await?.into())
+    }
+
+    /// Returns delivery latencies of outgoing messages queued since the timestamp `since`,
+    /// grouped by the provider ID or, for unknown providers, the domain they were sent with.
+    async fn get_delivery_latency_stats_by_provider(
+        &self,
+        account_id: u32,
+        since: i64,
+    ) -> Result<BTreeMap<String, DeliveryLatencyStats>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_delivery_latency_stats_by_provider(since)
+            .await?
+            .into_iter()
+            .map(|(provider, stats)| (provider, stats.into()))
+            .collect())
+    }
+
+    /// Returns true if this process owns the account and may run IO.
+    ///
+    /// If several processes start IO for the same account, only the first one owns it.
+    /// IO is started in other processes once the owner released the account,
+    /// the `OwnershipChanged` event is emitted then.
+    async fn is_owner(&self, account_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.is_owner())
+    }
+
+    /// Stops IO and releases the ownership of the account,
+    /// so that another process can start IO.
+    async fn release_ownership(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.release_ownership().await;
+        Ok(())
+    }
+
+    /// Returns the durations of the startup phases in milliseconds,
+    /// `null` for phases which did not run yet.
+    async fn get_startup_timings(&self, account_id: u32) -> Result<StartupTimings> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.get_startup_timings().into())
+    }
+
+    /// Starts housekeeping in the background,
+    /// i.e. removing unused files, pruning caches and optimizing the database.
+    ///
+    /// Progress is reported with `HousekeepingProgress` events
+    /// which reach `1000` on success or `0` on failure or cancellation.
+    /// Fails if housekeeping is already running.
+    async fn start_housekeeping(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(housekeeping::start_housekeeping(&ctx).await?)
+    }
+
+    /// Cancels running housekeeping after the current step.
+    async fn cancel_housekeeping(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        housekeeping::cancel_housekeeping(&ctx).await;
+        Ok(())
+    }
+
+    /// Returns whether housekeeping is running, its progress and the time of the last run.
+    async fn get_housekeeping_status(&self, account_id: u32) -> Result<HousekeepingStatus> {
+        let ctx = self.get_context(account_id).await?;
+        let status = housekeeping::get_housekeeping_status(&ctx).await?;
+        Ok(status.into())
+    }
+
+    /// Verifies the attachments of all messages against the checksums stored when they were created
+    /// and returns the IDs of the messages with corrupted or missing attachments.
+    ///
+    /// Every attachment is read completely, so this may take a while.
+    async fn check_blob_integrity(&self, account_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(deltachat::check_blob_integrity(&ctx)
+            .await?
+            .iter()
+            .map(|msg_id| msg_id.to_u32())
+            .collect())
+    }
+
+    /// Rotates the JPEG or PNG image at `path` in place as described by its Exif orientation,
+    /// without scaling it down like when sending.
+    ///
+    /// Returns false if the image does not need to be rotated.
+    async fn normalize_image_orientation(&self, account_id: u32, path: String) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(deltachat::normalize_orientation(&ctx, Path::new(&path)).await?)
+    }
+
+    /// Returns a chronological feed of significant events of the account
+    /// starting at the `since` timestamp,
+    /// e.g. group memberships, verification changes, configuration and backups.
+    async fn get_activity_log(
+        &self,
+        account_id: u32,
+        since: i64,
+    ) -> Result<Vec<JsonrpcActivityEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        let log = activity::get_activity_log(&ctx, since).await?;
+        Ok(log.into_iter().map(Into::into).collect())
     }
 
     async fn get_blob_dir(&self, account_id: u32) -> Result<Option<String>> {
@@ -356,7 +713,7 @@ async fn draft_self_report(&self, account_id: u32) -> Result<u32> {
     /// Sets the given configuration key.
     async fn set_config(&self, account_id: u32, key: String, value: Option<String>) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        set_config(&ctx, &key, value.as_deref()).await
+        Ok(set_config(&ctx, &key, value.as_deref()).await?)
     }
 
     /// Updates a batch of configuration values.
@@ -381,7 +738,27 @@ async fn batch_set_config(
     /// Internally, the function will call dc_set_config() with the appropriate keys,
     async fn set_config_from_qr(&self, account_id: u32, qr_content: String) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        qr::set_config_from_qr(&ctx, &qr_content).await
+        Ok(qr::set_config_from_qr(&ctx, &qr_content).await?)
+    }
+
+    /// Returns the recorded config changes made by the user,
+    /// received from other devices or made by scanning QR codes, newest first.
+    async fn get_config_changes(&self, account_id: u32) -> Result<Vec<ConfigChange>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_config_changes()
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Restores the value a config key had before the change `change_id`.
+    ///
+    /// Fails if the key was changed again since or if the change cannot be rolled back.
+    async fn rollback_config_change(&self, account_id: u32, change_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.rollback_config_change(change_id).await?)
     }
 
     async fn check_qr(&self, account_id: u32, qr_content: String) -> Result<QrObject> {
@@ -391,10 +768,19 @@ async fn check_qr(&self, account_id: u32, qr_content: String) -> Result<QrObject
         Ok(qr_object)
     }
 
+    /// Returns the preview of a group or contact invite link
+    /// without contacting the network or creating any contacts.
+    ///
+    /// Use this to show the inviter and the group before the user decides to join.
+    async fn get_invite_link_preview(&self, link: String) -> Result<InviteLinkPreview> {
+        let preview = qr::get_invite_link_preview(&link)?;
+        Ok(preview.into())
+    }
+
     /// Returns configuration value for the given key.
     async fn get_config(&self, account_id: u32, key: String) -> Result<Option<String>> {
         let ctx = self.get_context(account_id).await?;
-        get_config(&ctx, &key).await
+        Ok(get_config(&ctx, &key).await?)
     }
 
     async fn batch_get_config(
@@ -422,17 +808,56 @@ async fn set_stock_strings(&self, strings: HashMap<u32, String>) -> Result<()> {
         Ok(())
     }
 
+    /// Loads translations of core-generated strings for this account from the file at `path`,
+    /// `null` resets the account to the translations set with `set_stock_strings()`.
+    ///
+    /// The file is a JSON object mapping stock string IDs to translations.
+    async fn set_stock_translations_file(
+        &self,
+        account_id: u32,
+        path: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .set_stock_translations_file(path.as_deref().map(Path::new))
+            .await?)
+    }
+
+    /// Sets the template for the body of outgoing messages using the stock string `stock_id`,
+    /// `null` resets the template.
+    ///
+    /// Templates are stored in the account and take precedence over translations.
+    /// The template may only use placeholders used by the default string.
+    async fn set_stock_template(
+        &self,
+        account_id: u32,
+        stock_id: u32,
+        template: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        let stock_id = StockMessage::from_u32(stock_id).context("Unknown stock string")?;
+        Ok(ctx
+            .set_stock_template(stock_id, template.as_deref())
+            .await?)
+    }
+
+    /// Returns the template set with `set_stock_template()`.
+    async fn get_stock_template(&self, account_id: u32, stock_id: u32) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        let stock_id = StockMessage::from_u32(stock_id).context("Unknown stock string")?;
+        Ok(ctx.get_stock_template(stock_id).await?)
+    }
+
     /// Configures this account with the currently set parameters.
     /// Setup the credential config before calling this.
     async fn configure(&self, account_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
         ctx.stop_io().await;
-        let result = ctx.configure().await;
-        if result.is_err() {
+        if let Err(err) = ctx.configure().await {
             if let Ok(true) = ctx.is_configured().await {
                 ctx.start_io().await;
             }
-            return result;
+            return Err(err.into());
         }
         ctx.start_io().await;
         Ok(())
@@ -452,13 +877,13 @@ async fn export_self_keys(
         passphrase: Option<String>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        imex::imex(
+        Ok(imex::imex(
             &ctx,
             imex::ImexMode::ExportSelfKeys,
             path.as_ref(),
             passphrase,
         )
-        .await
+        .await?)
     }
 
     async fn import_self_keys(
@@ -468,13 +893,13 @@ async fn import_self_keys(
         passphrase: Option<String>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        imex::imex(
+        Ok(imex::imex(
             &ctx,
             imex::ImexMode::ImportSelfKeys,
             path.as_ref(),
             passphrase,
         )
-        .await
+        .await?)
     }
 
     /// Returns the message IDs of all _fresh_ messages of any chat.
@@ -506,7 +931,7 @@ async fn get_fresh_msgs(&self, account_id: u32) -> Result<Vec<u32>> {
     /// e.g. using "gray" instead of "red" color.
     async fn get_fresh_msg_cnt(&self, account_id: u32, chat_id: u32) -> Result<usize> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id).get_fresh_msg_cnt(&ctx).await
+        Ok(ChatId::new(chat_id).get_fresh_msg_cnt(&ctx).await?)
     }
 
     /// Gets messages to be processed by the bot and returns their IDs.
@@ -563,7 +988,7 @@ async fn estimate_auto_deletion_count(
         seconds: i64,
     ) -> Result<usize> {
         let ctx = self.get_context(account_id).await?;
-        message::estimate_deletion_cnt(&ctx, from_server, seconds).await
+        Ok(message::estimate_deletion_cnt(&ctx, from_server, seconds).await?)
     }
 
     // ---------------------------------------------
@@ -572,7 +997,7 @@ async fn estimate_auto_deletion_count(
 
     async fn initiate_autocrypt_key_transfer(&self, account_id: u32) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
-        deltachat::imex::initiate_key_transfer(&ctx).await
+        Ok(deltachat::imex::initiate_key_transfer(&ctx).await?)
     }
 
     async fn continue_autocrypt_key_transfer(
@@ -582,7 +1007,10 @@ async fn continue_autocrypt_key_transfer(
         setup_code: String,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        deltachat::imex::continue_key_transfer(&ctx, MsgId::new(message_id), &setup_code).await
+        Ok(
+            deltachat::imex::continue_key_transfer(&ctx, MsgId::new(message_id), &setup_code)
+                .await?,
+        )
     }
 
     // ---------------------------------------------
@@ -611,6 +1039,16 @@ async fn get_chatlist_entries(
         Ok(l)
     }
 
+    /// Enables or disables `ChatlistItemMoved`, `ChatlistItemRemoved`
+    /// and `ChatlistItemFreshMsgCountChanged` events for the default chatlist.
+    ///
+    /// The events are relative to the chatlist at the time of enabling,
+    /// so the chatlist should be loaded with `get_chatlist_entries()` afterwards.
+    async fn set_chatlist_diff_events(&self, account_id: u32, enabled: bool) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(deltachat::chatlist::set_chatlist_diff_events(&ctx, enabled).await?)
+    }
+
     /// Returns chats similar to the given one.
     ///
     /// Experimental API, subject to change without notice.
@@ -655,24 +1093,32 @@ async fn get_chatlist_items_by_entries(
 
     async fn get_full_chat_by_id(&self, account_id: u32, chat_id: u32) -> Result<FullChat> {
         let ctx = self.get_context(account_id).await?;
-        FullChat::try_from_dc_chat_id(&ctx, chat_id).await
+        Ok(FullChat::try_from_dc_chat_id(&ctx, chat_id).await?)
     }
 
     /// get basic info about a chat,
     /// use chatlist_get_full_chat_by_id() instead if you need more information
     async fn get_basic_chat_info(&self, account_id: u32, chat_id: u32) -> Result<BasicChat> {
         let ctx = self.get_context(account_id).await?;
-        BasicChat::try_from_dc_chat_id(&ctx, chat_id).await
+        Ok(BasicChat::try_from_dc_chat_id(&ctx, chat_id).await?)
     }
 
     async fn accept_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id).accept(&ctx).await
+        Ok(ChatId::new(chat_id).accept(&ctx).await?)
     }
 
     async fn block_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id).block(&ctx).await
+        Ok(ChatId::new(chat_id).block(&ctx).await?)
+    }
+
+    /// Leaves a group that looks like spam, deletes it and blocks the contact who added the user.
+    ///
+    /// No leave message is sent if the group is a contact request.
+    async fn leave_and_block_creator(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(group_spam::leave_and_block_creator(&ctx, ChatId::new(chat_id)).await?)
     }
 
     /// Delete a chat.
@@ -695,7 +1141,43 @@ async fn block_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
     /// To leave a chat explicitly, use leave_group()
     async fn delete_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id).delete(&ctx).await
+        Ok(ChatId::new(chat_id).delete(&ctx).await?)
+    }
+
+    /// Cancel recoding images of messages being sent to a chat,
+    /// e.g. because the user discarded the message while it was being prepared.
+    ///
+    /// Sending these messages fails then.
+    async fn cancel_image_recoding(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ChatId::new(chat_id).cancel_image_recoding(&ctx);
+        Ok(())
+    }
+
+    /// Stage an attachment for a message to be sent to a chat.
+    ///
+    /// The file is copied into the blobdir and prepared for sending in the background,
+    /// progress is reported with `AttachmentStagingProgress` events.
+    /// Pass the returned ID as `stagedAttachment` of the message data when sending the message.
+    /// Unused staged attachments are discarded when the draft is deleted and after a day.
+    async fn stage_attachment(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        file: String,
+        viewtype: Option<MessageViewtype>,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let mut msg = Message::new(viewtype.map_or(Viewtype::File, Into::into));
+        msg.set_file(file, None);
+        let id = ChatId::new(chat_id).stage_attachment(&ctx, &msg).await?;
+        Ok(id.to_u32())
+    }
+
+    /// Discard a staged attachment, cancelling its preparation if it is still running.
+    async fn discard_staged_attachment(&self, account_id: u32, staged_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(StagedAttachmentId::new(staged_id).discard(&ctx).await?)
     }
 
     /// Get encryption info for a chat.
@@ -705,7 +1187,33 @@ async fn delete_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
     /// returns Multi-line text
     async fn get_chat_encryption_info(&self, account_id: u32, chat_id: u32) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id).get_encryption_info(&ctx).await
+        Ok(ChatId::new(chat_id).get_encryption_info(&ctx).await?)
+    }
+
+    /// Get the safety number of a 1:1 chat.
+    ///
+    /// The safety number can be compared with the contact over another channel,
+    /// e.g. during a phone call, as an alternative to scanning QR codes.
+    /// Both sides of the chat get the same safety number.
+    async fn get_chat_safety_number(&self, account_id: u32, chat_id: u32) -> Result<SafetyNumber> {
+        let ctx = self.get_context(account_id).await?;
+        let safety_number = securejoin::get_safety_number(&ctx, ChatId::new(chat_id)).await?;
+        Ok(safety_number.into())
+    }
+
+    /// Mark the contact of a 1:1 chat as verified
+    /// after the safety number was compared with the contact.
+    ///
+    /// `digits` must be the safety number the user compared;
+    /// if the safety number changed in the meantime, an error is returned.
+    async fn mark_chat_safety_number_verified(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        digits: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(securejoin::mark_safety_number_verified(&ctx, ChatId::new(chat_id), &digits).await?)
     }
 
     /// Get QR code text that will offer a [SecureJoin](https://securejoin.delta.chat/) invitation.
@@ -723,6 +1231,15 @@ async fn get_chat_securejoin_qr_code(
         Ok(qr)
     }
 
+    /// Get an HTTPS invite link for the group with the given `chat_id`.
+    ///
+    /// The link can be shared as text and contains the number of members,
+    /// see `get_invite_link_preview()`.
+    async fn get_chat_invite_link(&self, account_id: u32, chat_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(securejoin::get_group_invite_link(&ctx, ChatId::new(chat_id)).await?)
+    }
+
     /// Get QR code (text and SVG) that will offer a Setup-Contact or Verified-Group invitation.
     /// The QR code is compatible to the OPENPGP4FPR format
     /// so that a basic fingerprint comparison also works e.g. with OpenKeychain.
@@ -778,7 +1295,7 @@ async fn secure_join(&self, account_id: u32, qr: String) -> Result<u32> {
 
     async fn leave_group(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        remove_contact_from_chat(&ctx, ChatId::new(chat_id), ContactId::SELF).await
+        Ok(remove_contact_from_chat(&ctx, ChatId::new(chat_id), ContactId::SELF).await?)
     }
 
     /// Remove a member from a group.
@@ -794,7 +1311,10 @@ async fn remove_contact_from_chat(
         contact_id: u32,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        remove_contact_from_chat(&ctx, ChatId::new(chat_id), ContactId::new(contact_id)).await
+        Ok(
+            remove_contact_from_chat(&ctx, ChatId::new(chat_id), ContactId::new(contact_id))
+                .await?,
+        )
     }
 
     /// Add a member to a group.
@@ -812,7 +1332,7 @@ async fn add_contact_to_chat(
         contact_id: u32,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        add_contact_to_chat(&ctx, ChatId::new(chat_id), ContactId::new(contact_id)).await
+        Ok(add_contact_to_chat(&ctx, ChatId::new(chat_id), ContactId::new(contact_id)).await?)
     }
 
     /// Get the contact IDs belonging to a chat.
@@ -861,9 +1381,9 @@ async fn create_group_chat(&self, account_id: u32, name: String, protect: bool)
             true => ProtectionStatus::Protected,
             false => ProtectionStatus::Unprotected,
         };
-        chat::create_group_chat(&ctx, protect, &name)
+        Ok(chat::create_group_chat(&ctx, protect, &name)
             .await
-            .map(|id| id.to_u32())
+            .map(|id| id.to_u32())?)
     }
 
     /// Create a new broadcast list.
@@ -877,9 +1397,9 @@ async fn create_group_chat(&self, account_id: u32, name: String, protect: bool)
     /// after creating the broadcast list.
     async fn create_broadcast_list(&self, account_id: u32) -> Result<u32> {
         let ctx = self.get_context(account_id).await?;
-        chat::create_broadcast_list(&ctx)
+        Ok(chat::create_broadcast_list(&ctx)
             .await
-            .map(|id| id.to_u32())
+            .map(|id| id.to_u32())?)
     }
 
     /// Set group name.
@@ -890,7 +1410,7 @@ async fn create_broadcast_list(&self, account_id: u32) -> Result<u32> {
     /// Sends out #DC_EVENT_CHAT_MODIFIED and #DC_EVENT_MSGS_CHANGED if a status message was sent.
     async fn set_chat_name(&self, account_id: u32, chat_id: u32, new_name: String) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        chat::set_chat_name(&ctx, ChatId::new(chat_id), &new_name).await
+        Ok(chat::set_chat_name(&ctx, ChatId::new(chat_id), &new_name).await?)
     }
 
     /// Set group profile image.
@@ -913,8 +1433,12 @@ async fn set_chat_profile_image(
         image_path: Option<String>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        chat::set_chat_profile_image(&ctx, ChatId::new(chat_id), &image_path.unwrap_or_default())
-            .await
+        Ok(chat::set_chat_profile_image(
+            &ctx,
+            ChatId::new(chat_id),
+            &image_path.unwrap_or_default(),
+        )
+        .await?)
     }
 
     async fn set_chat_visibility(
@@ -925,9 +1449,95 @@ async fn set_chat_visibility(
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
 
-        ChatId::new(chat_id)
+        Ok(ChatId::new(chat_id)
             .set_visibility(&ctx, visibility.into_core_type())
-            .await
+            .await?)
+    }
+
+    /// Sets the read receipt policy of the chat.
+    ///
+    /// The chat policy takes precedence over the policy of the contact.
+    async fn set_chat_mdn_policy(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        policy: MdnPolicy,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ChatId::new(chat_id)
+            .set_mdn_policy(&ctx, policy.into())
+            .await?)
+    }
+
+    /// Returns the read receipt policy of the chat.
+    async fn get_chat_mdn_policy(&self, account_id: u32, chat_id: u32) -> Result<MdnPolicy> {
+        let ctx = self.get_context(account_id).await?;
+        let chat = Chat::load_from_db(&ctx, ChatId::new(chat_id)).await?;
+        Ok(chat.get_mdn_policy().into())
+    }
+
+    /// Sets the subject template of the chat,
+    /// used instead of the automatic subject of outgoing messages.
+    ///
+    /// `{name}` in the template is replaced with the own display name.
+    /// An empty template restores the automatic subject.
+    async fn set_chat_subject_template(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        template: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ChatId::new(chat_id)
+            .set_subject_template(&ctx, &template)
+            .await?)
+    }
+
+    /// Returns the subject template of the chat, empty if none is set.
+    async fn get_chat_subject_template(&self, account_id: u32, chat_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ChatId::new(chat_id).get_subject_template(&ctx).await?)
+    }
+
+    /// Returns the incoming messages containing a watch keyword, newest first,
+    /// see the `watch_keywords` config option.
+    ///
+    /// If `chat_id` is set, only messages of this chat are returned.
+    async fn get_watched_msgs(&self, account_id: u32, chat_id: Option<u32>) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = deltachat::watch::get_watched_msgs(&ctx, chat_id.map(ChatId::new)).await?;
+        Ok(msg_ids.iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the watch keyword found in the message, if any.
+    async fn get_watched_keyword(&self, account_id: u32, msg_id: u32) -> Result<Option<String>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(MsgId::new(msg_id).get_watched_keyword(&ctx).await?)
+    }
+
+    /// Returns the chat collecting classic emails assigned by rules with the `mailbox` action,
+    /// see the `email_rules` config option.
+    ///
+    /// Returns `null` if no email was assigned to the mailbox chat yet.
+    async fn get_mailbox_chat_id(&self, account_id: u32) -> Result<Option<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let chat_id = email_rules::get_mailbox_chat(&ctx).await?;
+        Ok(chat_id.map(|chat_id| chat_id.to_u32()))
+    }
+
+    /// Returns the start of the local day containing `timestamp` as used for day markers,
+    /// see the `timezone` config option.
+    async fn get_day_start(&self, account_id: u32, timestamp: i64) -> Result<i64> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(timezone::get_day_start(&ctx, timestamp).await?)
+    }
+
+    /// Returns the membership changes of the group summarized in digests, oldest first,
+    /// see the `member_digest_threshold` config option.
+    async fn get_member_changes(&self, account_id: u32, chat_id: u32) -> Result<Vec<MemberChange>> {
+        let ctx = self.get_context(account_id).await?;
+        let changes = member_digest::get_member_changes(&ctx, ChatId::new(chat_id)).await?;
+        Ok(changes.into_iter().map(Into::into).collect())
     }
 
     async fn set_chat_ephemeral_timer(
@@ -937,9 +1547,9 @@ async fn set_chat_ephemeral_timer(
         timer: u32,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id)
+        Ok(ChatId::new(chat_id)
             .set_ephemeral_timer(&ctx, Timer::from_u32(timer))
-            .await
+            .await?)
     }
 
     async fn get_chat_ephemeral_timer(&self, account_id: u32, chat_id: u32) -> Result<u32> {
@@ -950,6 +1560,113 @@ async fn get_chat_ephemeral_timer(&self, account_id: u32, chat_id: u32) -> Resul
             .to_u32())
     }
 
+    /// Sets the local history retention policy of the chat.
+    ///
+    /// Messages older than `keep_days` days
+    /// and messages exceeding the `keep_last` newest ones are deleted from this device.
+    /// 0 disables the respective limit.
+    /// Unlike the ephemeral timer, the policy is not sent to other chat members.
+    async fn set_chat_retention_policy(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        keep_days: u32,
+        keep_last: u32,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ChatId::new(chat_id)
+            .set_retention_policy(
+                &ctx,
+                deltachat::ephemeral::RetentionPolicy {
+                    keep_days,
+                    keep_last,
+                },
+            )
+            .await?)
+    }
+
+    async fn get_chat_retention_policy(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<RetentionPolicy> {
+        let ctx = self.get_context(account_id).await?;
+        let policy = ChatId::new(chat_id).get_retention_policy(&ctx).await?;
+        Ok(policy.into())
+    }
+
+    /// Exempts the chat from the `delete_device_after` setting or removes the exemption.
+    async fn set_chat_auto_delete_exempt(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        exempt: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ChatId::new(chat_id)
+            .set_auto_delete_exempt(&ctx, exempt)
+            .await?)
+    }
+
+    async fn is_chat_auto_delete_exempt(&self, account_id: u32, chat_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ChatId::new(chat_id).is_auto_delete_exempt(&ctx).await?)
+    }
+
+    /// Exempts the 1:1 chat with the contact from the `delete_device_after` setting
+    /// or removes the exemption.
+    ///
+    /// Unlike the exemption of a chat, it persists if the chat is deleted and created again.
+    async fn set_contact_auto_delete_exempt(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+        exempt: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id)
+            .set_auto_delete_exempt(&ctx, exempt)
+            .await?)
+    }
+
+    async fn is_contact_auto_delete_exempt(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+    ) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id)
+            .is_auto_delete_exempt(&ctx)
+            .await?)
+    }
+
+    /// Returns message statistics of the chat,
+    /// such as the number of messages per member and the average response time.
+    async fn get_chat_stats(&self, account_id: u32, chat_id: u32) -> Result<ChatStats> {
+        let ctx = self.get_context(account_id).await?;
+        let stats = chat::get_stats(&ctx, ChatId::new(chat_id)).await?;
+        Ok(stats.into())
+    }
+
+    /// Sets the number of recent messages shared with members joining the group via QR code.
+    ///
+    /// History is only shared in protected groups, 0 disables sharing.
+    async fn set_chat_history_sharing(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        count: u32,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(group_history::set_history_sharing(&ctx, ChatId::new(chat_id), count).await?)
+    }
+
+    /// Returns the number of recent messages shared with members joining the group.
+    async fn get_chat_history_sharing(&self, account_id: u32, chat_id: u32) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(group_history::get_history_sharing(&ctx, ChatId::new(chat_id)).await?)
+    }
+
     /// Add a message to the device-chat.
     /// Device-messages usually contain update information
     /// and some hints that are added during the program runs, multi-device etc.
@@ -990,31 +1707,19 @@ async fn add_device_message(
     ///  See also markseen_msgs().
     async fn marknoticed_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        marknoticed_chat(&ctx, ChatId::new(chat_id)).await
+        Ok(marknoticed_chat(&ctx, ChatId::new(chat_id)).await?)
     }
 
+    /// Returns the first incoming message after the read position of the chat,
+    /// which is synchronized across own devices, `null` if there are no unread messages.
     async fn get_first_unread_message_of_chat(
         &self,
         account_id: u32,
         chat_id: u32,
     ) -> Result<Option<u32>> {
         let ctx = self.get_context(account_id).await?;
-
-        // TODO: implement this in core with an SQL query, that will be way faster
-        let messages = get_chat_msgs(&ctx, ChatId::new(chat_id)).await?;
-        let mut first_unread_message_id = None;
-        for item in messages.into_iter().rev() {
-            if let ChatItem::Message { msg_id } = item {
-                match msg_id.get_state(&ctx).await? {
-                    MessageState::InSeen => break,
-                    MessageState::InFresh | MessageState::InNoticed => {
-                        first_unread_message_id = Some(msg_id)
-                    }
-                    _ => continue,
-                }
-            }
-        }
-        Ok(first_unread_message_id.map(|id| id.to_u32()))
+        let msg_id = get_first_unread_msg(&ctx, ChatId::new(chat_id)).await?;
+        Ok(msg_id.map(|id| id.to_u32()))
     }
 
     /// Set mute duration of a chat.
@@ -1036,7 +1741,7 @@ async fn set_chat_mute_duration(
         duration: MuteDuration,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        chat::set_muted(&ctx, ChatId::new(chat_id), duration.try_into_core_type()?).await
+        Ok(chat::set_muted(&ctx, ChatId::new(chat_id), duration.try_into_core_type()?).await?)
     }
 
     /// Check whether the chat is currently muted (can be changed by set_chat_mute_duration()).
@@ -1078,7 +1783,7 @@ async fn is_chat_muted(&self, account_id: u32, chat_id: u32) -> Result<bool> {
     /// One #DC_EVENT_MSGS_NOTICED event is emitted per modified chat.
     async fn markseen_msgs(&self, account_id: u32, msg_ids: Vec<u32>) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        markseen_msgs(&ctx, msg_ids.into_iter().map(MsgId::new).collect()).await
+        Ok(markseen_msgs(&ctx, msg_ids.into_iter().map(MsgId::new).collect()).await?)
     }
 
     async fn get_message_ids(
@@ -1132,6 +1837,59 @@ async fn get_message_list_items(
             .collect::<Vec<JSONRPCMessageListItem>>())
     }
 
+    /// Starts watching the message list of a chat.
+    ///
+    /// Returns the current message list like `get_message_list_items()`.
+    /// Afterwards, changes of the message list can be received with `get_next_chat_changes()`
+    /// instead of reloading the whole list on events.
+    /// Watching an already watched chat restarts the watcher.
+    async fn watch_chat(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        info_only: bool,
+        add_daymarker: bool,
+    ) -> Result<Vec<JSONRPCMessageListItem>> {
+        let ctx = self.get_context(account_id).await?;
+        let (watcher, items) =
+            ChatWatcher::new(ctx, ChatId::new(chat_id), info_only, add_daymarker).await?;
+        self.states
+            .lock()
+            .await
+            .entry(account_id)
+            .or_default()
+            .chat_watchers
+            .insert(chat_id, watcher);
+        Ok(items.into_iter().map(Into::into).collect())
+    }
+
+    /// Waits for the next changes of the message list of a chat watched with `watch_chat()`.
+    ///
+    /// Returns all changes since the previous call.
+    async fn get_next_chat_changes(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Vec<MessageListChange>> {
+        let changes = self
+            .states
+            .lock()
+            .await
+            .get(&account_id)
+            .and_then(|state| state.chat_watchers.get(&chat_id))
+            .map(|watcher| watcher.changes())
+            .with_context(|| format!("Chat {chat_id} is not watched"))?;
+        Ok(chat_watcher::next_changes(changes).await?)
+    }
+
+    /// Stops watching the message list of a chat.
+    async fn unwatch_chat(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        if let Some(state) = self.states.lock().await.get_mut(&account_id) {
+            state.chat_watchers.remove(&chat_id);
+        }
+        Ok(())
+    }
+
     async fn get_message(&self, account_id: u32, msg_id: u32) -> Result<MessageObject> {
         let ctx = self.get_context(account_id).await?;
         let msg_id = MsgId::new(msg_id);
@@ -1144,7 +1902,7 @@ async fn get_message(&self, account_id: u32, msg_id: u32) -> Result<MessageObjec
 
     async fn get_message_html(&self, account_id: u32, message_id: u32) -> Result<Option<String>> {
         let ctx = self.get_context(account_id).await?;
-        MsgId::new(message_id).get_html(&ctx).await
+        Ok(MsgId::new(message_id).get_html(&ctx).await?)
     }
 
     /// get multiple messages in one call,
@@ -1183,7 +1941,7 @@ async fn get_message_notification_info(
         message_id: u32,
     ) -> Result<MessageNotificationInfo> {
         let ctx = self.get_context(account_id).await?;
-        MessageNotificationInfo::from_msg_id(&ctx, MsgId::new(message_id)).await
+        Ok(MessageNotificationInfo::from_msg_id(&ctx, MsgId::new(message_id)).await?)
     }
 
     /// Delete messages. The messages are deleted on the current device and
@@ -1191,7 +1949,33 @@ async fn get_message_notification_info(
     async fn delete_messages(&self, account_id: u32, message_ids: Vec<u32>) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
         let msgs: Vec<MsgId> = message_ids.into_iter().map(MsgId::new).collect();
-        delete_msgs(&ctx, &msgs).await
+        Ok(delete_msgs(&ctx, &msgs).await?)
+    }
+
+    /// Restores the messages or the chat deleted last
+    /// if this happened within the `deletion_undo_window`.
+    ///
+    /// Returns false if there is nothing to restore.
+    async fn undo_last_deletion(&self, account_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(deltachat::undo::undo_last_deletion(&ctx).await?)
+    }
+
+    /// Cancels sending a message kept back because of the `send_delay` config option
+    /// and deletes the message.
+    ///
+    /// Fails if the delay is over.
+    async fn cancel_send(&self, account_id: u32, msg_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(deltachat::undo::cancel_send(&ctx, MsgId::new(msg_id)).await?)
+    }
+
+    /// Returns the timestamp at which a message kept back
+    /// because of the `send_delay` config option is submitted,
+    /// `null` if the message is not kept back (anymore).
+    async fn get_send_delay_end(&self, account_id: u32, msg_id: u32) -> Result<Option<i64>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(MsgId::new(msg_id).get_send_delay_end(&ctx).await?)
     }
 
     /// Get an informational text for a single message. The text is multiline and may
@@ -1201,7 +1985,7 @@ async fn delete_messages(&self, account_id: u32, message_ids: Vec<u32>) -> Resul
     /// max. text returned by dc_msg_get_text() (about 30000 characters).
     async fn get_message_info(&self, account_id: u32, message_id: u32) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
-        MsgId::new(message_id).get_info(&ctx).await
+        Ok(MsgId::new(message_id).get_info(&ctx).await?)
     }
 
     /// Returns additional information for single message.
@@ -1211,7 +1995,7 @@ async fn get_message_info_object(
         message_id: u32,
     ) -> Result<MessageInfo> {
         let ctx = self.get_context(account_id).await?;
-        MessageInfo::from_msg_id(&ctx, MsgId::new(message_id)).await
+        Ok(MessageInfo::from_msg_id(&ctx, MsgId::new(message_id)).await?)
     }
 
     /// Returns contacts that sent read receipts and the time of reading.
@@ -1232,6 +2016,60 @@ async fn get_message_read_receipts(
         Ok(receipts)
     }
 
+    /// Returns the root message of the reply thread the message belongs to.
+    ///
+    /// If the message is not a reply to a message of the same chat,
+    /// the message itself is returned.
+    async fn get_thread_root(&self, account_id: u32, message_id: u32) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg = Message::load_from_db(&ctx, MsgId::new(message_id)).await?;
+        Ok(msg.get_thread_root(&ctx).await?.to_u32())
+    }
+
+    /// Returns the replies in the thread with the given root message,
+    /// including replies to replies, in the order of the chat.
+    async fn get_thread_replies(&self, account_id: u32, root_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let replies = message::get_thread_replies(&ctx, MsgId::new(root_id)).await?;
+        Ok(replies.into_iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
+    /// Returns the number of fresh replies in the thread with the given root message.
+    async fn get_thread_fresh_msg_cnt(&self, account_id: u32, root_id: u32) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(message::get_thread_fresh_msg_cnt(&ctx, MsgId::new(root_id)).await?)
+    }
+
+    /// Adds a todo item to the Saved Messages chat and returns its message ID.
+    ///
+    /// `due` is an optional due date as a unix timestamp.
+    async fn add_todo(&self, account_id: u32, text: String, due: Option<i64>) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = notes::add_todo(&ctx, &text, due).await?;
+        Ok(msg_id.to_u32())
+    }
+
+    /// Marks a message of the Saved Messages chat as a todo item
+    /// or turns it back into a regular message if `todo` is `null`.
+    async fn set_todo(&self, account_id: u32, msg_id: u32, todo: Option<Todo>) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(notes::set_todo(&ctx, MsgId::new(msg_id), todo.map(Into::into)).await?)
+    }
+
+    /// Checks or unchecks a todo item and returns whether it is checked now.
+    async fn toggle_todo(&self, account_id: u32, msg_id: u32) -> Result<bool> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(notes::toggle_todo(&ctx, MsgId::new(msg_id)).await?)
+    }
+
+    /// Returns the todo items of the Saved Messages chat,
+    /// unchecked items first, then ordered by due date.
+    async fn get_todos(&self, account_id: u32, include_done: bool) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_ids = notes::get_todos(&ctx, include_done).await?;
+        Ok(msg_ids.into_iter().map(|msg_id| msg_id.to_u32()).collect())
+    }
+
     /// Asks the core to start downloading a message fully.
     /// This function is typically called when the user hits the "Download" button
     /// that is shown by the UI in case `download_state` is `'Available'` or `'Failure'`
@@ -1245,7 +2083,7 @@ async fn get_message_read_receipts(
     /// To reflect these changes a @ref DC_EVENT_MSGS_CHANGED event will be emitted.
     async fn download_full_message(&self, account_id: u32, message_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        MsgId::new(message_id).download_full(&ctx).await
+        Ok(MsgId::new(message_id).download_full(&ctx).await?)
     }
 
     /// Search messages containing the given query string.
@@ -1300,11 +2138,11 @@ async fn get_contact(&self, account_id: u32, contact_id: u32) -> Result<ContactO
         let ctx = self.get_context(account_id).await?;
         let contact_id = ContactId::new(contact_id);
 
-        ContactObject::try_from_dc_contact(
+        Ok(ContactObject::try_from_dc_contact(
             &ctx,
             deltachat::contact::Contact::get_by_id(&ctx, contact_id).await?,
         )
-        .await
+        .await?)
     }
 
     /// Add a single contact as a result of an explicit user action.
@@ -1318,9 +2156,7 @@ async fn create_contact(
     ) -> Result<u32> {
         let ctx = self.get_context(account_id).await?;
         if !may_be_valid_addr(&email) {
-            bail!(anyhow!(
-                "provided email address is not a valid email address"
-            ))
+            return Err(anyhow!("provided email address is not a valid email address").into());
         }
         let contact_id = Contact::create(&ctx, &name.unwrap_or_default(), &email).await?;
         Ok(contact_id.to_u32())
@@ -1330,19 +2166,77 @@ async fn create_contact(
     async fn create_chat_by_contact_id(&self, account_id: u32, contact_id: u32) -> Result<u32> {
         let ctx = self.get_context(account_id).await?;
         let contact = Contact::get_by_id(&ctx, ContactId::new(contact_id)).await?;
-        ChatId::create_for_contact(&ctx, contact.id)
+        Ok(ChatId::create_for_contact(&ctx, contact.id)
             .await
-            .map(|id| id.to_u32())
+            .map(|id| id.to_u32())?)
     }
 
     async fn block_contact(&self, account_id: u32, contact_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        Contact::block(&ctx, ContactId::new(contact_id)).await
+        Ok(Contact::block(&ctx, ContactId::new(contact_id)).await?)
     }
 
     async fn unblock_contact(&self, account_id: u32, contact_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        Contact::unblock(&ctx, ContactId::new(contact_id)).await
+        Ok(Contact::unblock(&ctx, ContactId::new(contact_id)).await?)
+    }
+
+    /// Sets the read receipt policy of the contact,
+    /// used for chats without a read receipt policy.
+    async fn set_contact_mdn_policy(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+        policy: MdnPolicy,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id)
+            .set_mdn_policy(&ctx, policy.into())
+            .await?)
+    }
+
+    /// Returns the local note on the contact, empty if there is none.
+    async fn get_contact_note(&self, account_id: u32, contact_id: u32) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id).get_note(&ctx).await?)
+    }
+
+    /// Sets the local note on the contact, synchronized to other devices.
+    async fn set_contact_note(&self, account_id: u32, contact_id: u32, note: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id).set_note(&ctx, &note).await?)
+    }
+
+    /// Returns the local tags of the contact in alphabetical order.
+    async fn get_contact_tags(&self, account_id: u32, contact_id: u32) -> Result<Vec<String>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id).get_tags(&ctx).await?)
+    }
+
+    /// Replaces the local tags of the contact, synchronized to other devices.
+    async fn set_contact_tags(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ContactId::new(contact_id).set_tags(&ctx, &tags).await?)
+    }
+
+    /// Returns all tags used for any contact in alphabetical order.
+    ///
+    /// Use `tag` of `ContactListFilter` to list the contacts with a given tag.
+    async fn get_all_contact_tags(&self, account_id: u32) -> Result<Vec<String>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(contact_notes::get_all_tags(&ctx).await?)
+    }
+
+    /// Returns the read receipt policy of the contact.
+    async fn get_contact_mdn_policy(&self, account_id: u32, contact_id: u32) -> Result<MdnPolicy> {
+        let ctx = self.get_context(account_id).await?;
+        let contact = Contact::get_by_id(&ctx, ContactId::new(contact_id)).await?;
+        Ok(contact.get_mdn_policy().into())
     }
 
     async fn get_blocked_contacts(&self, account_id: u32) -> Result<Vec<ContactObject>> {
@@ -1395,6 +2289,63 @@ async fn get_contacts(
         Ok(contacts)
     }
 
+    /// Apply changes of an address book to the contacts.
+    ///
+    /// Only the changed entries need to be passed.
+    /// `source` tags the address book the entries come from, e.g. an account on the device.
+    /// Contacts in `upserts` are added or get their name updated
+    /// unless the name was changed manually by the user.
+    /// Addresses in `deletions` are removed from the address book `source`;
+    /// a contact known only from address books is deleted once it is removed from all of them.
+    ///
+    /// Returns the number of modified contacts.
+    async fn apply_address_book_delta(
+        &self,
+        account_id: u32,
+        source: String,
+        upserts: Vec<AddressBookEntry>,
+        deletions: Vec<String>,
+    ) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        let upserts: Vec<_> = upserts.into_iter().map(Into::into).collect();
+        Ok(Contact::apply_address_book_delta(&ctx, &source, &upserts, &deletions).await?)
+    }
+
+    /// Get a page of known and unblocked contacts matching `filter` as lightweight entries.
+    ///
+    /// Filtering, sorting and paging is done by the core,
+    /// so this is suitable for accounts with a lot of contacts.
+    /// Self is never returned.
+    ///
+    /// `limit` is the maximum number of entries to return, `null` for no limit.
+    async fn get_contacts_page(
+        &self,
+        account_id: u32,
+        filter: ContactListFilter,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<ContactListEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        let filter = deltachat::contact::ContactListFilter {
+            offset,
+            limit,
+            ..filter.into()
+        };
+        let entries = Contact::get_page(&ctx, &filter).await?;
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+
+    /// Get the number of known and unblocked contacts matching `filter`,
+    /// see `get_contacts_page()`.
+    async fn get_contacts_count(
+        &self,
+        account_id: u32,
+        filter: ContactListFilter,
+    ) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Contact::get_count(&ctx, &filter.into()).await?)
+    }
+
     async fn get_contacts_by_ids(
         &self,
         account_id: u32,
@@ -1447,6 +2398,28 @@ async fn change_contact_name(
         Ok(())
     }
 
+    /// Sets a nickname and an avatar for the contact
+    /// overriding the ones chosen by the contact.
+    ///
+    /// The override is stored only locally and never sent to anyone.
+    /// `null` removes the nickname or avatar override respectively.
+    async fn set_contact_local_override(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+        nickname: Option<String>,
+        avatar_path: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(Contact::set_local_override(
+            &ctx,
+            ContactId::new(contact_id),
+            nickname.as_deref(),
+            avatar_path.as_deref().map(Path::new),
+        )
+        .await?)
+    }
+
     /// Get encryption info for a contact.
     /// Get a multi-line encryption info, containing your fingerprint and the
     /// fingerprint of the contact, used e.g. to compare the fingerprints for a simple out-of-band verification.
@@ -1456,7 +2429,22 @@ async fn get_contact_encryption_info(
         contact_id: u32,
     ) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
-        Contact::get_encrinfo(&ctx, ContactId::new(contact_id)).await
+        Ok(Contact::get_encrinfo(&ctx, ContactId::new(contact_id)).await?)
+    }
+
+    /// Returns the Autocrypt peerstate of a contact for debugging encryption issues,
+    /// `null` if no key of the contact is known.
+    ///
+    /// Contains the encryption preference, the fingerprints of the known keys,
+    /// the verifiers of the verified key and the related timestamps.
+    async fn get_peerstate_info(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+    ) -> Result<Option<PeerstateInfo>> {
+        let ctx = self.get_context(account_id).await?;
+        let info = peerstate::get_peerstate_info(&ctx, ContactId::new(contact_id)).await?;
+        Ok(info.map(Into::into))
     }
 
     /// Check if an e-mail address belongs to a known and unblocked contact.
@@ -1502,7 +2490,7 @@ async fn import_vcard(&self, account_id: u32, path: String) -> Result<Vec<u32>>
     async fn make_vcard(&self, account_id: u32, contacts: Vec<u32>) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
         let contacts: Vec<_> = contacts.iter().map(|&c| ContactId::new(c)).collect();
-        deltachat::contact::make_vcard(&ctx, &contacts).await
+        Ok(deltachat::contact::make_vcard(&ctx, &contacts).await?)
     }
 
     /// Sets vCard containing the given contacts to the message draft.
@@ -1516,7 +2504,7 @@ async fn set_draft_vcard(
         let contacts: Vec<_> = contacts.iter().map(|&c| ContactId::new(c)).collect();
         let mut msg = Message::load_from_db(&ctx, MsgId::new(msg_id)).await?;
         msg.make_vcard(&ctx, &contacts).await?;
-        msg.get_chat_id().set_draft(&ctx, Some(&mut msg)).await
+        Ok(msg.get_chat_id().set_draft(&ctx, Some(&mut msg)).await?)
     }
 
     // ---------------------------------------------
@@ -1578,13 +2566,13 @@ async fn export_backup(
         passphrase: Option<String>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        imex::imex(
+        Ok(imex::imex(
             &ctx,
             imex::ImexMode::ExportBackup,
             destination.as_ref(),
             passphrase,
         )
-        .await
+        .await?)
     }
 
     async fn import_backup(
@@ -1594,13 +2582,108 @@ async fn import_backup(
         passphrase: Option<String>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        imex::imex(
+        Ok(imex::imex(
             &ctx,
             imex::ImexMode::ImportBackup,
             path.as_ref(),
             passphrase,
         )
-        .await
+        .await?)
+    }
+
+    /// Merges the backup at `path` into the configured account.
+    ///
+    /// Chats, contacts and messages missing in the account are added,
+    /// messages already existing are skipped.
+    async fn merge_backup(
+        &self,
+        account_id: u32,
+        path: String,
+        passphrase: Option<String>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(imex::imex(&ctx, imex::ImexMode::MergeBackup, path.as_ref(), passphrase).await?)
+    }
+
+    /// Exports an identity bundle to the `destination` directory.
+    ///
+    /// The bundle contains the own keys, the verified keys of contacts and the login settings,
+    /// but no messages, and is encrypted with `passphrase`.
+    async fn export_identity(
+        &self,
+        account_id: u32,
+        destination: String,
+        passphrase: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(imex::imex(
+            &ctx,
+            imex::ImexMode::ExportIdentity,
+            destination.as_ref(),
+            Some(passphrase),
+        )
+        .await?)
+    }
+
+    /// Imports the identity bundle at `path` into the unconfigured account.
+    async fn import_identity(
+        &self,
+        account_id: u32,
+        path: String,
+        passphrase: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(imex::imex(
+            &ctx,
+            imex::ImexMode::ImportIdentity,
+            path.as_ref(),
+            Some(passphrase),
+        )
+        .await?)
+    }
+
+    /// Verifies the backup file at `path` without importing it
+    /// and returns a summary of its contents.
+    ///
+    /// Fails if the backup is truncated, the passphrase is wrong
+    /// or the database in the backup is corrupted.
+    async fn verify_backup(
+        &self,
+        account_id: u32,
+        path: String,
+        passphrase: Option<String>,
+    ) -> Result<BackupInfo> {
+        let ctx = self.get_context(account_id).await?;
+        let info = imex::verify_backup(&ctx, path.as_ref(), passphrase.unwrap_or_default()).await?;
+        Ok(info.into())
+    }
+
+    /// Uploads a backup to the target set by the `remote_backup_*` config options now.
+    ///
+    /// Unlike `export_backup()`, this does not stop IO.
+    /// Fails if a remote backup is already running.
+    async fn run_remote_backup(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(remote_backup::run_remote_backup(&ctx).await?)
+    }
+
+    /// Returns the backups stored on the target set by the `remote_backup_*` config options,
+    /// oldest first.
+    ///
+    /// The account may be unconfigured, e.g. to restore a backup on a new device.
+    async fn list_remote_backups(&self, account_id: u32) -> Result<Vec<RemoteBackupInfo>> {
+        let ctx = self.get_context(account_id).await?;
+        let backups = remote_backup::list_remote_backups(&ctx).await?;
+        Ok(backups.into_iter().map(Into::into).collect())
+    }
+
+    /// Downloads and imports a remote backup listed by `list_remote_backups()`.
+    ///
+    /// Only possible as long as the account is not configured.
+    /// Progress of the import is reported with `ImexProgress` events.
+    async fn restore_remote_backup(&self, account_id: u32, id: i64) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(remote_backup::restore_remote_backup(&ctx, id).await?)
     }
 
     /// Offers a backup for remote devices to retrieve.
@@ -1628,7 +2711,7 @@ async fn provide_backup(&self, account_id: u32) -> Result<()> {
         })
         .await;
 
-        res
+        Ok(res?)
     }
 
     /// Returns the text of the QR code for the running [`CommandApi::provide_backup`].
@@ -1647,7 +2730,7 @@ async fn get_backup_qr(&self, account_id: u32) -> Result<String> {
         .await
         .context("Backup provider did not start in time")?
         .context("Failed to get backup QR code")?;
-        qr::format_backup(&qr)
+        Ok(qr::format_backup(&qr)?)
     }
 
     /// Returns the rendered QR code for the running [`CommandApi::provide_backup`].
@@ -1669,7 +2752,7 @@ async fn get_backup_qr_svg(&self, account_id: u32) -> Result<String> {
         .await
         .context("Backup provider did not start in time")?
         .context("Failed to get backup QR code")?;
-        generate_backup_qr(&ctx, &qr).await
+        Ok(generate_backup_qr(&ctx, &qr).await?)
     }
 
     /// Gets a backup from a remote provider.
@@ -1699,6 +2782,53 @@ async fn maybe_network(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Indicate that the device switched to a network of class `kind`.
+    ///
+    /// Unlike `maybe_network()`, connections made over the old network are dropped
+    /// and connection backoff is reset, so that messages arrive shortly after switching.
+    /// `None` indicates that the network is lost.
+    async fn on_network_changed(&self, kind: NetworkKind) -> Result<()> {
+        self.accounts
+            .read()
+            .await
+            .on_network_changed(kind.into())
+            .await;
+        Ok(())
+    }
+
+    /// Sets the identity of the network the device is connected to,
+    /// e.g. a hash of the Wi-Fi SSID, or `null` if it is not known.
+    ///
+    /// Server ports that work are remembered per network,
+    /// so that alternative ports needed on networks blocking the standard ports
+    /// are only used there.
+    async fn set_network_identity(&self, identity: Option<String>) -> Result<()> {
+        self.accounts
+            .read()
+            .await
+            .set_network_identity(identity.as_deref());
+        Ok(())
+    }
+
+    /// Tells all accounts whether the app is in the foreground,
+    /// i.e. whether the user is interacting with it.
+    ///
+    /// Background jobs such as the daily housekeeping pause while the app is in the foreground.
+    async fn set_foreground(&self, foreground: bool) -> Result<()> {
+        self.accounts.read().await.set_foreground(foreground).await;
+        Ok(())
+    }
+
+    /// Tells which chat the user is interacting with, `null` if no chat is open.
+    ///
+    /// While the app is in the foreground, new messages of this chat are fetched first
+    /// and webxdc updates of this chat are sent without delay.
+    async fn set_interactive_chat(&self, account_id: u32, chat_id: Option<u32>) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.set_interactive_chat(chat_id.map(ChatId::new)).await;
+        Ok(())
+    }
+
     /// Get the current connectivity, i.e. whether the device is connected to the IMAP server.
     /// One of:
     /// - DC_CONNECTIVITY_NOT_CONNECTED (1000-1999): Show e.g. the string "Not connected" or a red dot
@@ -1729,7 +2859,7 @@ async fn get_connectivity(&self, account_id: u32) -> Result<u32> {
     /// and the improvement instantly reaches all UIs.
     async fn get_connectivity_html(&self, account_id: u32) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
-        ctx.get_connectivity_html().await
+        Ok(ctx.get_connectivity_html().await?)
     }
 
     // ---------------------------------------------
@@ -1770,8 +2900,9 @@ async fn send_webxdc_status_update(
         _descr: Option<String>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ctx.send_webxdc_status_update(MsgId::new(instance_msg_id), &update_str)
-            .await
+        Ok(ctx
+            .send_webxdc_status_update(MsgId::new(instance_msg_id), &update_str)
+            .await?)
     }
 
     async fn send_webxdc_realtime_data(
@@ -1781,7 +2912,7 @@ async fn send_webxdc_realtime_data(
         data: Vec<u8>,
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        send_webxdc_realtime_data(&ctx, MsgId::new(instance_msg_id), data).await
+        Ok(send_webxdc_realtime_data(&ctx, MsgId::new(instance_msg_id), data).await?)
     }
 
     async fn send_webxdc_realtime_advertisement(
@@ -1802,7 +2933,7 @@ async fn send_webxdc_realtime_advertisement(
 
     async fn leave_webxdc_realtime(&self, account_id: u32, instance_message_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        leave_webxdc_realtime(&ctx, MsgId::new(instance_message_id)).await
+        Ok(leave_webxdc_realtime(&ctx, MsgId::new(instance_message_id)).await?)
     }
 
     async fn get_webxdc_status_updates(
@@ -1812,11 +2943,66 @@ async fn get_webxdc_status_updates(
         last_known_serial: u32,
     ) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
-        ctx.get_webxdc_status_updates(
-            MsgId::new(instance_msg_id),
-            StatusUpdateSerial::new(last_known_serial),
-        )
-        .await
+        Ok(ctx
+            .get_webxdc_status_updates(
+                MsgId::new(instance_msg_id),
+                StatusUpdateSerial::new(last_known_serial),
+            )
+            .await?)
+    }
+
+    /// Returns status updates of a webxdc instance with serials
+    /// larger than `after_serial` and up to `until_serial`, ordered by serial.
+    ///
+    /// At most `max_count` updates are returned,
+    /// pass the serial of the last returned update as `after_serial` to get the next page.
+    /// If `from_contact_id` is set, only updates sent by this contact are returned.
+    async fn get_webxdc_status_update_entries(
+        &self,
+        account_id: u32,
+        instance_msg_id: u32,
+        after_serial: u32,
+        until_serial: Option<u32>,
+        max_count: Option<u32>,
+        from_contact_id: Option<u32>,
+    ) -> Result<Vec<WebxdcStatusUpdateEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        let query = StatusUpdateQuery {
+            after_serial: StatusUpdateSerial::new(after_serial),
+            until_serial: until_serial.map(StatusUpdateSerial::new),
+            max_count,
+            from_id: from_contact_id.map(ContactId::new),
+        };
+        Ok(ctx
+            .get_webxdc_status_update_entries(MsgId::new(instance_msg_id), query)
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<anyhow::Result<_>>()?)
+    }
+
+    /// Exports a webxdc app with all its status updates to the file `path`.
+    async fn export_webxdc(
+        &self,
+        account_id: u32,
+        instance_msg_id: u32,
+        path: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .export_webxdc(MsgId::new(instance_msg_id), path.as_ref())
+            .await?)
+    }
+
+    /// Imports a webxdc app exported with `export_webxdc` by sending it to the chat.
+    ///
+    /// Returns the message ID of the new webxdc instance.
+    async fn import_webxdc(&self, account_id: u32, chat_id: u32, path: String) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = ctx
+            .import_webxdc(ChatId::new(chat_id), path.as_ref())
+            .await?;
+        Ok(msg_id.to_u32())
     }
 
     /// Get info from a webxdc message
@@ -1826,7 +3012,7 @@ async fn get_webxdc_info(
         instance_msg_id: u32,
     ) -> Result<WebxdcMessageInfo> {
         let ctx = self.get_context(account_id).await?;
-        WebxdcMessageInfo::get_for_message(&ctx, MsgId::new(instance_msg_id)).await
+        Ok(WebxdcMessageInfo::get_for_message(&ctx, MsgId::new(instance_msg_id)).await?)
     }
 
     /// Get href from a WebxdcInfoMessage which might include a hash holding
@@ -1862,7 +3048,7 @@ async fn get_webxdc_blob(
     /// `file` is the .xdc to use as Webxdc integration.
     async fn set_webxdc_integration(&self, account_id: u32, file_path: String) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ctx.set_webxdc_integration(&file_path).await
+        Ok(ctx.set_webxdc_integration(&file_path).await?)
     }
 
     /// Returns Webxdc instance used for optional integrations.
@@ -1904,7 +3090,7 @@ async fn forward_messages(
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
         let message_ids: Vec<MsgId> = message_ids.into_iter().map(MsgId::new).collect();
-        forward_msgs(&ctx, &message_ids, ChatId::new(chat_id)).await
+        Ok(forward_msgs(&ctx, &message_ids, ChatId::new(chat_id)).await?)
     }
 
     /// Resend messages and make information available for newly added chat members.
@@ -1918,7 +3104,92 @@ async fn forward_messages(
     async fn resend_messages(&self, account_id: u32, message_ids: Vec<u32>) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
         let message_ids: Vec<MsgId> = message_ids.into_iter().map(MsgId::new).collect();
-        chat::resend_msgs(&ctx, &message_ids).await
+        Ok(chat::resend_msgs(&ctx, &message_ids).await?)
+    }
+
+    /// Reports an offending message, e.g. spam in a public group,
+    /// to the moderators of the chat or the address set as `abuse_address`.
+    ///
+    /// Fails if there is nobody to send the report to.
+    async fn report_message(&self, account_id: u32, msg_id: u32, reason: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(report::report_msg(&ctx, MsgId::new(msg_id), &reason).await?)
+    }
+
+    /// Sets the moderators of the group reported messages are sent to.
+    async fn set_chat_moderators(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        contact_ids: Vec<u32>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_ids: Vec<ContactId> = contact_ids.into_iter().map(ContactId::new).collect();
+        Ok(report::set_moderators(&ctx, ChatId::new(chat_id), &contact_ids).await?)
+    }
+
+    /// Returns the moderators of the group reported messages are sent to.
+    async fn get_chat_moderators(&self, account_id: u32, chat_id: u32) -> Result<Vec<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_ids = report::get_moderators(&ctx, ChatId::new(chat_id)).await?;
+        Ok(contact_ids.iter().map(|id| id.to_u32()).collect())
+    }
+
+    /// Returns the reports of offending messages received from other users, newest first.
+    async fn get_message_reports(&self, account_id: u32) -> Result<Vec<MessageReport>> {
+        let ctx = self.get_context(account_id).await?;
+        let reports = report::get_msg_reports(&ctx).await?;
+        Ok(reports.into_iter().map(Into::into).collect())
+    }
+
+    /// Deletes a received report, e.g. after it was handled.
+    async fn delete_message_report(&self, account_id: u32, report_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(report::delete_msg_report(&ctx, report_id).await?)
+    }
+
+    /// Places a call in a 1:1 chat and returns the ID of the call invite message.
+    ///
+    /// `payload` is passed to the callee, typically an SDP offer.
+    async fn place_outgoing_call(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        payload: String,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = ctx
+            .place_outgoing_call(ChatId::new(chat_id), &payload)
+            .await?;
+        Ok(msg_id.to_u32())
+    }
+
+    /// Accepts an incoming call.
+    ///
+    /// `payload` is passed to the caller, typically an SDP answer.
+    async fn accept_incoming_call(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        payload: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .accept_incoming_call(MsgId::new(msg_id), &payload)
+            .await?)
+    }
+
+    /// Ends, rejects or cancels a call.
+    async fn end_call(&self, account_id: u32, msg_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.end_call(MsgId::new(msg_id)).await?)
+    }
+
+    /// Returns information about a call.
+    async fn get_call_info(&self, account_id: u32, msg_id: u32) -> Result<CallInfo> {
+        let ctx = self.get_context(account_id).await?;
+        let info = ctx.get_call_info(MsgId::new(msg_id)).await?;
+        Ok(info.into())
     }
 
     async fn send_sticker(
@@ -1999,7 +3270,45 @@ async fn can_send(&self, account_id: u32, chat_id: u32) -> Result<bool> {
     async fn save_msg_file(&self, account_id: u32, msg_id: u32, path: String) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
         let message = Message::load_from_db(&ctx, MsgId::new(msg_id)).await?;
-        message.save_file(&ctx, Path::new(&path)).await
+        Ok(message.save_file(&ctx, Path::new(&path)).await?)
+    }
+
+    /// Saves a file copy at the user-provided path,
+    /// resolving conflicts with existing files according to `policy`.
+    ///
+    /// Returns the path the file was saved at.
+    async fn save_msg_file_ex(
+        &self,
+        account_id: u32,
+        msg_id: u32,
+        path: String,
+        policy: SaveFilePolicy,
+    ) -> Result<String> {
+        let ctx = self.get_context(account_id).await?;
+        let message = Message::load_from_db(&ctx, MsgId::new(msg_id)).await?;
+        let path = message
+            .save_file_ex(&ctx, Path::new(&path), policy.into())
+            .await?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Saves copies of all attachments of a chat to the directory `dir`,
+    /// e.g. to export all media of a chat.
+    ///
+    /// The original file names are used, files with the same name are numbered.
+    /// Returns the paths of the saved files.
+    async fn save_all_attachments(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        dir: String,
+    ) -> Result<Vec<String>> {
+        let ctx = self.get_context(account_id).await?;
+        let paths = chat::save_all_attachments(&ctx, ChatId::new(chat_id), Path::new(&dir)).await?;
+        Ok(paths
+            .into_iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
     }
 
     // ---------------------------------------------
@@ -2009,7 +3318,7 @@ async fn save_msg_file(&self, account_id: u32, msg_id: u32, path: String) -> Res
 
     async fn remove_draft(&self, account_id: u32, chat_id: u32) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
-        ChatId::new(chat_id).set_draft(&ctx, None).await
+        Ok(ChatId::new(chat_id).set_draft(&ctx, None).await?)
     }
 
     ///  Get draft for a chat, if any.
@@ -2022,11 +3331,25 @@ async fn get_draft(&self, account_id: u32, chat_id: u32) -> Result<Option<Messag
         }
     }
 
+    /// Returns the members of a protected chat to whom a message can't be encrypted
+    /// because their key became unavailable or unverified.
+    ///
+    /// Call this before sending to warn the user instead of letting the message fail.
+    async fn validate_draft(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Vec<UnencryptableMember>> {
+        let ctx = self.get_context(account_id).await?;
+        let problems = ChatId::new(chat_id).validate_draft(&ctx).await?;
+        Ok(problems.into_iter().map(Into::into).collect())
+    }
+
     async fn send_videochat_invitation(&self, account_id: u32, chat_id: u32) -> Result<u32> {
         let ctx = self.get_context(account_id).await?;
-        chat::send_videochat_invitation(&ctx, ChatId::new(chat_id))
+        Ok(chat::send_videochat_invitation(&ctx, ChatId::new(chat_id))
             .await
-            .map(|msg_id| msg_id.to_u32())
+            .map(|msg_id| msg_id.to_u32())?)
     }
 
     // ---------------------------------------------
@@ -2042,10 +3365,10 @@ async fn misc_get_sticker_folder(&self, account_id: u32) -> Result<String> {
             .context("account folder not found")?;
         let sticker_folder_path = account_folder.join("stickers");
         fs::create_dir_all(&sticker_folder_path).await?;
-        sticker_folder_path
+        Ok(sticker_folder_path
             .to_str()
             .map(|s| s.to_owned())
-            .context("path conversion to string failed")
+            .context("path conversion to string failed")?)
     }
 
     /// Saves a sticker to a collection/folder in the account's sticker folder.
@@ -2057,19 +3380,16 @@ async fn misc_save_sticker(
     ) -> Result<()> {
         let ctx = self.get_context(account_id).await?;
         let message = Message::load_from_db(&ctx, MsgId::new(msg_id)).await?;
-        ensure!(
-            message.get_viewtype() == Viewtype::Sticker,
-            "message {} is not a sticker",
-            msg_id
-        );
+        if message.get_viewtype() != Viewtype::Sticker {
+            return Err(anyhow!("message {} is not a sticker", msg_id).into());
+        }
         let account_folder = ctx
             .get_dbfile()
             .parent()
             .context("account folder not found")?;
-        ensure!(
-            is_sanitized(&collection),
-            "illegal characters in collection name"
-        );
+        if !is_sanitized(&collection) {
+            return Err(anyhow!("illegal characters in collection name").into());
+        }
         let destination_path = account_folder.join("stickers").join(collection);
         fs::create_dir_all(&destination_path).await?;
         let file = message.get_filename().context("no file?")?;
@@ -2232,7 +3552,9 @@ async fn misc_set_draft(
                 .await?;
         }
 
-        ChatId::new(chat_id).set_draft(&ctx, Some(&mut draft)).await
+        Ok(ChatId::new(chat_id)
+            .set_draft(&ctx, Some(&mut draft))
+            .await?)
     }
 
     // send the chat's current set draft
@@ -2245,10 +3567,7 @@ async fn misc_send_draft(&self, account_id: u32, chat_id: u32) -> Result<u32> {
                 .to_u32();
             Ok(msg_id)
         } else {
-            Err(anyhow!(
-                "chat with id {} doesn't have draft message",
-                chat_id
-            ))
+            Err(anyhow!("chat with id {} doesn't have draft message", chat_id).into())
         }
     }
 }
@@ -0,0 +1,308 @@
+//! Watching the message list of a chat for changes.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context as _, Result};
+use deltachat::chat::{get_chat_msgs_ex, ChatId, ChatItem, MessageListOptions};
+use deltachat::context::Context;
+use deltachat::{EventEmitter, EventType};
+use tokio::task::JoinHandle;
+
+use super::types::message::MessageListChange;
+
+/// Watcher of the message list of a single chat.
+///
+/// The watcher stops when dropped.
+#[derive(Debug)]
+pub(crate) struct ChatWatcher {
+    /// Batches of changes not yet received by the client.
+    changes: async_channel::Receiver<Vec<MessageListChange>>,
+
+    /// Task updating the message list on events.
+    task: JoinHandle<()>,
+}
+
+impl Drop for ChatWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl ChatWatcher {
+    /// Starts watching the chat.
+    ///
+    /// Returns the watcher and the current message list.
+    pub(crate) async fn new(
+        ctx: Context,
+        chat_id: ChatId,
+        info_only: bool,
+        add_daymarker: bool,
+    ) -> Result<(Self, Vec<ChatItem>)> {
+        // Subscribe before loading the message list so no change is missed.
+        let events = ctx.get_event_emitter();
+        let items = load_items(&ctx, chat_id, info_only, add_daymarker).await?;
+        let (sender, changes) = async_channel::unbounded();
+        let task = tokio::spawn(watch(
+            ctx,
+            chat_id,
+            info_only,
+            add_daymarker,
+            items.clone(),
+            events,
+            sender,
+        ));
+        Ok((Self { changes, task }, items))
+    }
+
+    /// Returns the receiver of the batches of changes.
+    pub(crate) fn changes(&self) -> async_channel::Receiver<Vec<MessageListChange>> {
+        self.changes.clone()
+    }
+}
+
+/// Waits for the next changes and returns all changes received so far.
+pub(crate) async fn next_changes(
+    changes: async_channel::Receiver<Vec<MessageListChange>>,
+) -> Result<Vec<MessageListChange>> {
+    let mut res = changes.recv().await.context("Chat is not watched")?;
+    while let Ok(more) = changes.try_recv() {
+        res.extend(more);
+    }
+    Ok(res)
+}
+
+async fn load_items(
+    ctx: &Context,
+    chat_id: ChatId,
+    info_only: bool,
+    add_daymarker: bool,
+) -> Result<Vec<ChatItem>> {
+    get_chat_msgs_ex(
+        ctx,
+        chat_id,
+        MessageListOptions {
+            info_only,
+            add_daymarker,
+        },
+    )
+    .await
+}
+
+async fn watch(
+    ctx: Context,
+    chat_id: ChatId,
+    info_only: bool,
+    add_daymarker: bool,
+    mut items: Vec<ChatItem>,
+    events: EventEmitter,
+    sender: async_channel::Sender<Vec<MessageListChange>>,
+) {
+    while let Some(event) = events.recv().await {
+        if event.id != ctx.get_id() {
+            continue;
+        }
+        let msg_id = match event.typ {
+            EventType::MsgsChanged {
+                chat_id: event_chat_id,
+                msg_id,
+            }
+            | EventType::IncomingMsg {
+                chat_id: event_chat_id,
+                msg_id,
+                ..
+            }
+            | EventType::ReactionsChanged {
+                chat_id: event_chat_id,
+                msg_id,
+                ..
+            }
+            | EventType::MsgStateChanged {
+                chat_id: event_chat_id,
+                msg_id,
+                ..
+            }
+            | EventType::MsgDeleted {
+                chat_id: event_chat_id,
+                msg_id,
+            } if event_chat_id == chat_id || event_chat_id.is_unset() => msg_id,
+            _ => continue,
+        };
+
+        let new_items = match load_items(&ctx, chat_id, info_only, add_daymarker).await {
+            Ok(new_items) => new_items,
+            Err(err) => {
+                ctx.emit_event(EventType::Warning(format!(
+                    "Failed to reload message list of watched chat {chat_id}: {err:#}."
+                )));
+                continue;
+            }
+        };
+        let mut changes = diff_message_list(&items, &new_items);
+        let item = ChatItem::Message { msg_id };
+        if !msg_id.is_unset() && items.contains(&item) && new_items.contains(&item) {
+            changes.push(MessageListChange::Changed {
+                msg_id: msg_id.to_u32(),
+            });
+        }
+        items = new_items;
+
+        if !changes.is_empty() && sender.send(changes).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Key identifying a message list item.
+fn item_key(item: &ChatItem) -> (bool, i64) {
+    match item {
+        ChatItem::Message { msg_id } => (false, i64::from(msg_id.to_u32())),
+        ChatItem::DayMarker { timestamp } => (true, *timestamp),
+    }
+}
+
+/// Returns the changes turning the message list `old` into `new`.
+///
+/// Items changing their position relative to other items,
+/// e.g. resent messages, are reported as removed and inserted again.
+fn diff_message_list(old: &[ChatItem], new: &[ChatItem]) -> Vec<MessageListChange> {
+    let old_keys: BTreeSet<_> = old.iter().map(item_key).collect();
+    let new_keys: BTreeSet<_> = new.iter().map(item_key).collect();
+
+    // Keys of the items kept in the new list, in the new order.
+    let kept_new: Vec<_> = new
+        .iter()
+        .map(item_key)
+        .filter(|key| old_keys.contains(key))
+        .collect();
+    // Positions of the kept items among the kept items of the old list.
+    let kept_old: BTreeMap<_, usize> = old
+        .iter()
+        .map(item_key)
+        .filter(|key| new_keys.contains(key))
+        .enumerate()
+        .map(|(i, key)| (key, i))
+        .collect();
+    let kept_old_positions: Vec<usize> = kept_new
+        .iter()
+        .filter_map(|key| kept_old.get(key).copied())
+        .collect();
+    let moved = moved_items(&kept_old_positions);
+    let moved_keys: BTreeSet<_> = kept_new
+        .iter()
+        .enumerate()
+        .filter(|(i, _key)| moved.contains(i))
+        .map(|(_i, key)| *key)
+        .collect();
+
+    let mut changes = Vec::new();
+    for item in old {
+        let key = item_key(item);
+        if !new_keys.contains(&key) || moved_keys.contains(&key) {
+            changes.push(MessageListChange::Removed {
+                item: (*item).into(),
+            });
+        }
+    }
+    for (index, item) in new.iter().enumerate() {
+        let key = item_key(item);
+        if !old_keys.contains(&key) || moved_keys.contains(&key) {
+            changes.push(MessageListChange::Inserted {
+                index: u32::try_from(index).unwrap_or(u32::MAX),
+                item: (*item).into(),
+            });
+        }
+    }
+    changes
+}
+
+/// Returns the indexes of the elements of `positions`
+/// which are not part of its longest increasing subsequence.
+fn moved_items(positions: &[usize]) -> BTreeSet<usize> {
+    // Indexes of the last elements of the increasing subsequences of each length.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = Vec::with_capacity(positions.len());
+    let position = |i: usize| positions.get(i).copied().unwrap_or_default();
+    for (i, &pos) in positions.iter().enumerate() {
+        let len = tails.partition_point(|&t| position(t) < pos);
+        prev.push(len.checked_sub(1).and_then(|l| tails.get(l).copied()));
+        match tails.get_mut(len) {
+            Some(tail) => *tail = i,
+            None => tails.push(i),
+        }
+    }
+
+    let mut kept = BTreeSet::new();
+    let mut cur = tails.last().copied();
+    while let Some(i) = cur {
+        kept.insert(i);
+        cur = prev.get(i).copied().flatten();
+    }
+    (0..positions.len()).filter(|i| !kept.contains(i)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use deltachat::message::MsgId;
+
+    use super::*;
+    use crate::api::types::message::JSONRPCMessageListItem;
+
+    fn msgs(ids: &[u32]) -> Vec<ChatItem> {
+        ids.iter()
+            .map(|id| ChatItem::Message {
+                msg_id: MsgId::new(*id),
+            })
+            .collect()
+    }
+
+    fn chat_item(item: JSONRPCMessageListItem) -> ChatItem {
+        match item {
+            JSONRPCMessageListItem::Message { msg_id } => ChatItem::Message {
+                msg_id: MsgId::new(msg_id),
+            },
+            JSONRPCMessageListItem::DayMarker { timestamp } => ChatItem::DayMarker { timestamp },
+        }
+    }
+
+    /// Applies the changes to the message list `old`.
+    fn apply(old: &[ChatItem], changes: Vec<MessageListChange>) -> Vec<ChatItem> {
+        let mut items = old.to_vec();
+        for change in changes {
+            match change {
+                MessageListChange::Removed { item } => {
+                    let item = chat_item(item);
+                    items.retain(|i| *i != item);
+                }
+                MessageListChange::Inserted { index, item } => {
+                    items.insert(index as usize, chat_item(item));
+                }
+                MessageListChange::Changed { .. } => {}
+            }
+        }
+        items
+    }
+
+    #[test]
+    fn test_diff_message_list() {
+        let cases: [(&[u32], &[u32]); 6] = [
+            (&[], &[1, 2]),
+            (&[1, 2, 3], &[1, 2, 3, 4]),
+            (&[1, 2, 3], &[1, 3]),
+            (&[1, 2, 3, 4], &[1, 3, 4, 2]),
+            (&[1, 2, 3, 4], &[4, 1, 2, 3, 5]),
+            (&[1, 2, 3], &[3, 2, 1]),
+        ];
+        for (old, new) in cases {
+            let (old, new) = (msgs(old), msgs(new));
+            let changes = diff_message_list(&old, &new);
+            assert_eq!(apply(&old, changes), new);
+        }
+
+        assert!(diff_message_list(&msgs(&[1, 2]), &msgs(&[1, 2])).is_empty());
+        // A single moved message is removed and inserted again.
+        assert_eq!(
+            diff_message_list(&msgs(&[1, 2, 3, 4]), &msgs(&[1, 3, 4, 2])).len(),
+            2
+        );
+    }
+}
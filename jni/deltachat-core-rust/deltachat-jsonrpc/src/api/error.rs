@@ -0,0 +1,175 @@
+//! Errors returned to JSON-RPC clients.
+//!
+//! Failed requests are answered with a JSON-RPC error object
+//! whose `message` is the human-readable error message
+//! and whose `data` is an [`ErrorData`] object,
+//! so that clients do not need to parse the message
+//! to decide how to present the error or whether to retry.
+//!
+//! As errors do not appear in the method signatures,
+//! the `ErrorData` schema is added to the OpenRPC specification by [`add_openrpc_schemas`]
+//! and its TypeScript definition is written to `typescript/generated/errors.ts`
+//! when the tests are run, along with the bindings of the methods.
+
+use anyhow::Context as _;
+use deltachat::error as core_error;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Code of the JSON-RPC error object for all errors returned by the API methods.
+const ERROR_CODE: i32 = -1;
+
+/// Result type of the API methods.
+pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Error returned by the API methods.
+///
+/// Any error that can be converted into [`anyhow::Error`]
+/// can be converted into this error, so `?` works as usual.
+#[derive(Debug)]
+pub struct Error(anyhow::Error);
+
+impl<E> From<E> for Error
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        Error(err.into())
+    }
+}
+
+impl From<Error> for yerpc::Error {
+    fn from(err: Error) -> Self {
+        let data = ErrorData::new(&err.0);
+        yerpc::Error {
+            code: ERROR_CODE,
+            message: data.message.clone(),
+            data: serde_json::to_value(data).ok(),
+        }
+    }
+}
+
+/// Kind of an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TypeDef, schemars::JsonSchema)]
+pub enum ErrorKind {
+    /// The error could not be classified.
+    Other,
+
+    /// The network is unavailable or the connection failed.
+    Network,
+
+    /// Local I/O error, e.g. a missing file or a full disk.
+    Io,
+
+    /// The server rejected the credentials.
+    Authentication,
+
+    /// The IMAP server returned an error.
+    Imap,
+
+    /// The SMTP server returned an error.
+    Smtp,
+}
+
+impl From<core_error::ErrorKind> for ErrorKind {
+    fn from(kind: core_error::ErrorKind) -> Self {
+        match kind {
+            core_error::ErrorKind::Other => ErrorKind::Other,
+            core_error::ErrorKind::Network => ErrorKind::Network,
+            core_error::ErrorKind::Io => ErrorKind::Io,
+            core_error::ErrorKind::Authentication => ErrorKind::Authentication,
+            core_error::ErrorKind::Imap => ErrorKind::Imap,
+            core_error::ErrorKind::Smtp => ErrorKind::Smtp,
+        }
+    }
+}
+
+/// `data` of the JSON-RPC error object.
+#[derive(Debug, Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorData {
+    kind: ErrorKind,
+
+    /// True if the request may succeed when retried later.
+    retryable: bool,
+
+    /// Underlying error code:
+    /// the OS error number for I/O and network errors,
+    /// the response code such as `AUTHENTICATIONFAILED` for IMAP errors, see RFC 5530,
+    /// or the reply code for SMTP errors.
+    code: Option<String>,
+
+    /// Human-readable error message, same as the `message` of the error object.
+    message: String,
+}
+
+impl ErrorData {
+    fn new(err: &anyhow::Error) -> Self {
+        let info = core_error::classify(err);
+        ErrorData {
+            kind: info.kind.into(),
+            retryable: info.retryable,
+            code: info.code,
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+/// Adds the schemas of [`ErrorData`] and the types it uses
+/// to the `components` of the OpenRPC specification `spec`.
+pub(crate) fn add_openrpc_schemas(spec: &mut serde_json::Value) -> anyhow::Result<()> {
+    let (_, definitions) = yerpc::openrpc::generate_schema::<ErrorData>();
+    let schemas = spec
+        .pointer_mut("/components/schemas")
+        .and_then(|schemas| schemas.as_object_mut())
+        .context("OpenRPC specification has no components")?;
+    for (name, schema) in definitions {
+        schemas.insert(name, serde_json::to_value(schema)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Generates the TypeScript definitions of the error data.
+    #[test]
+    fn generate_ts_error_types() {
+        let outdir = Path::new(env!("CARGO_MANIFEST_DIR")).join("typescript/generated");
+        std::fs::create_dir_all(&outdir).unwrap();
+        yerpc::typescript::export_types_to_file::<ErrorData>(&outdir.join("errors.ts"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_error_envelope() {
+        let err: Error = std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into();
+        let err = yerpc::Error::from(err);
+        assert_eq!(err.code, ERROR_CODE);
+        let data = err.data.unwrap();
+        assert_eq!(data["kind"], "Network");
+        assert_eq!(data["retryable"], true);
+        assert_eq!(data["message"], err.message);
+
+        let err: Error = anyhow::anyhow!("account with id 5 not found").into();
+        let err = yerpc::Error::from(err);
+        assert_eq!(err.message, "account with id 5 not found");
+        let data = err.data.unwrap();
+        assert_eq!(data["kind"], "Other");
+        assert_eq!(data["retryable"], false);
+        assert!(data["code"].is_null());
+    }
+
+    #[test]
+    fn test_openrpc_schemas() -> anyhow::Result<()> {
+        let mut spec = serde_json::json!({ "components": { "schemas": {} } });
+        add_openrpc_schemas(&mut spec)?;
+        let schemas = &spec["components"]["schemas"];
+        assert!(schemas["ErrorData"]["properties"]["retryable"].is_object());
+        assert!(schemas["ErrorKind"].is_object());
+        Ok(())
+    }
+}
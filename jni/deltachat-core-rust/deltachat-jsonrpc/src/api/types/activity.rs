@@ -0,0 +1,61 @@
+use deltachat::activity;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ActivityKind")]
+pub enum JsonrpcActivityKind {
+    MemberAdded,
+    MemberRemoved,
+    GroupNameChanged,
+    EphemeralTimerChanged,
+    ProtectionEnabled,
+    ProtectionDisabled,
+    Configured,
+    BackupExported,
+    ConfigChanged,
+}
+
+impl From<activity::ActivityKind> for JsonrpcActivityKind {
+    fn from(kind: activity::ActivityKind) -> Self {
+        match kind {
+            activity::ActivityKind::MemberAdded => JsonrpcActivityKind::MemberAdded,
+            activity::ActivityKind::MemberRemoved => JsonrpcActivityKind::MemberRemoved,
+            activity::ActivityKind::GroupNameChanged => JsonrpcActivityKind::GroupNameChanged,
+            activity::ActivityKind::EphemeralTimerChanged => {
+                JsonrpcActivityKind::EphemeralTimerChanged
+            }
+            activity::ActivityKind::ProtectionEnabled => JsonrpcActivityKind::ProtectionEnabled,
+            activity::ActivityKind::ProtectionDisabled => JsonrpcActivityKind::ProtectionDisabled,
+            activity::ActivityKind::Configured => JsonrpcActivityKind::Configured,
+            activity::ActivityKind::BackupExported => JsonrpcActivityKind::BackupExported,
+            activity::ActivityKind::ConfigChanged => JsonrpcActivityKind::ConfigChanged,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "ActivityEntry", rename_all = "camelCase")]
+pub struct JsonrpcActivityEntry {
+    /// Unix timestamp of the event.
+    timestamp: i64,
+    kind: JsonrpcActivityKind,
+    /// Chat the event happened in, if any.
+    chat_id: Option<u32>,
+    /// Info message the entry is reconstructed from, if any.
+    msg_id: Option<u32>,
+    /// Human-readable description.
+    text: String,
+}
+
+impl From<activity::ActivityEntry> for JsonrpcActivityEntry {
+    fn from(entry: activity::ActivityEntry) -> Self {
+        JsonrpcActivityEntry {
+            timestamp: entry.timestamp,
+            kind: entry.kind.into(),
+            chat_id: entry.chat_id.map(|id| id.to_u32()),
+            msg_id: entry.msg_id.map(|id| id.to_u32()),
+            text: entry.text,
+        }
+    }
+}
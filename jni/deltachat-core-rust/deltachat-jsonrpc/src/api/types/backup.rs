@@ -0,0 +1,74 @@
+use deltachat::imex;
+use deltachat::remote_backup;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// Address of the account the backup was made of.
+    addr: Option<String>,
+
+    /// Unix timestamp of the backup.
+    backup_time: i64,
+
+    /// Version of the database schema.
+    db_version: i32,
+
+    /// Number of messages, excluding special and trashed ones.
+    msg_count: usize,
+
+    /// Number of chats, excluding special ones.
+    chat_count: usize,
+
+    /// Number of contacts, excluding special ones.
+    contact_count: usize,
+
+    /// Number of blobs.
+    blob_count: usize,
+
+    /// Total size of the blobs in bytes.
+    blob_bytes: u64,
+}
+
+impl From<imex::BackupInfo> for BackupInfo {
+    fn from(info: imex::BackupInfo) -> Self {
+        BackupInfo {
+            addr: info.addr,
+            backup_time: info.backup_time,
+            db_version: info.db_version,
+            msg_count: info.msg_count,
+            chat_count: info.chat_count,
+            contact_count: info.contact_count,
+            blob_count: info.blob_count,
+            blob_bytes: info.blob_bytes,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteBackupInfo {
+    /// ID of the backup to pass to `restore_remote_backup()`.
+    id: i64,
+
+    /// Unix timestamp of the backup.
+    timestamp: i64,
+
+    /// Total size of the database snapshot and the blobs of the backup in bytes.
+    size: u64,
+
+    /// Number of blobs in the backup.
+    blob_count: usize,
+}
+
+impl From<remote_backup::RemoteBackupInfo> for RemoteBackupInfo {
+    fn from(info: remote_backup::RemoteBackupInfo) -> Self {
+        RemoteBackupInfo {
+            id: info.id,
+            timestamp: info.timestamp,
+            size: info.size,
+            blob_count: info.blob_count,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use deltachat::calls;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// State of a call.
+#[derive(Debug, Clone, Copy, Serialize, TypeDef, schemars::JsonSchema)]
+pub enum CallState {
+    /// The call is ringing and was not answered yet.
+    Pending,
+
+    /// The call was accepted by the callee.
+    Active,
+
+    /// The call was rejected by the callee.
+    Declined,
+
+    /// The call was ended after it was accepted.
+    Ended,
+
+    /// The call was not answered in time or cancelled by the caller.
+    Missed,
+}
+
+impl From<calls::CallState> for CallState {
+    fn from(state: calls::CallState) -> Self {
+        match state {
+            calls::CallState::Pending => CallState::Pending,
+            calls::CallState::Active => CallState::Active,
+            calls::CallState::Declined => CallState::Declined,
+            calls::CallState::Ended => CallState::Ended,
+            calls::CallState::Missed => CallState::Missed,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CallInfo {
+    /// ID of the call invite message.
+    msg_id: u32,
+
+    /// ID of the 1:1 chat the call belongs to.
+    chat_id: u32,
+
+    /// True if the call was placed by the chat partner.
+    is_incoming: bool,
+
+    state: CallState,
+
+    /// Payload of the caller, e.g. an SDP offer.
+    payload: String,
+
+    /// Payload of the callee, set once the call is accepted.
+    answer: Option<String>,
+
+    /// Time the call was placed.
+    timestamp: i64,
+}
+
+impl From<calls::CallInfo> for CallInfo {
+    fn from(info: calls::CallInfo) -> Self {
+        CallInfo {
+            msg_id: info.msg_id.to_u32(),
+            chat_id: info.chat_id.to_u32(),
+            is_incoming: info.is_incoming,
+            state: info.state.into(),
+            payload: info.payload,
+            answer: info.answer,
+            timestamp: info.timestamp,
+        }
+    }
+}
@@ -6,12 +6,16 @@
 use deltachat::constants::Chattype;
 use deltachat::contact::{Contact, ContactId};
 use deltachat::context::Context;
+use deltachat::ephemeral;
+use deltachat::member_digest;
+use deltachat::securejoin;
 use num_traits::cast::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
 
 use super::color_int_to_hex_string;
 use super::contact::ContactObject;
+use super::message::MessageViewtype;
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -43,6 +47,8 @@ pub struct FullChat {
     fresh_message_counter: usize,
     // is_group - please check over chat.type in frontend instead
     is_contact_request: bool,
+    /// True if the chat is a contact request that looks like an unsolicited group addition.
+    is_quarantined: bool,
     is_protection_broken: bool,
     is_device_chat: bool,
     self_in_group: bool,
@@ -114,6 +120,7 @@ pub async fn try_from_dc_chat_id(context: &Context, chat_id: u32) -> Result<Self
             color,
             fresh_message_counter,
             is_contact_request: chat.is_contact_request(),
+            is_quarantined: chat.is_quarantined(),
             is_protection_broken: chat.is_protection_broken(),
             is_device_chat: chat.is_device_talk(),
             self_in_group: contact_ids.contains(&ContactId::SELF),
@@ -161,6 +168,8 @@ pub struct BasicChat {
     is_self_talk: bool,
     color: String,
     is_contact_request: bool,
+    /// True if the chat is a contact request that looks like an unsolicited group addition.
+    is_quarantined: bool,
     is_protection_broken: bool,
     is_device_chat: bool,
     is_muted: bool,
@@ -189,6 +198,7 @@ pub async fn try_from_dc_chat_id(context: &Context, chat_id: u32) -> Result<Self
             is_self_talk: chat.is_self_talk(),
             color,
             is_contact_request: chat.is_contact_request(),
+            is_quarantined: chat.is_quarantined(),
             is_protection_broken: chat.is_protection_broken(),
             is_device_chat: chat.is_device_talk(),
             is_muted: chat.is_muted(),
@@ -239,3 +249,154 @@ pub fn into_core_type(self) -> ChatVisibility {
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetyNumber {
+    /// The 60 digits of the safety number.
+    digits: String,
+
+    /// The digits formatted as 12 space-separated groups of 5 digits.
+    formatted: String,
+
+    /// Emoji representation of the safety number.
+    emojis: Vec<String>,
+}
+
+impl From<securejoin::SafetyNumber> for SafetyNumber {
+    fn from(safety_number: securejoin::SafetyNumber) -> Self {
+        SafetyNumber {
+            digits: safety_number.digits().to_string(),
+            formatted: safety_number.formatted(),
+            emojis: safety_number
+                .emojis()
+                .iter()
+                .map(|emoji| emoji.to_string())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    /// Messages older than this number of days are deleted, 0 means no limit.
+    keep_days: u32,
+
+    /// Only this number of the newest messages is kept, 0 means no limit.
+    keep_last: u32,
+}
+
+impl From<ephemeral::RetentionPolicy> for RetentionPolicy {
+    fn from(policy: ephemeral::RetentionPolicy) -> Self {
+        RetentionPolicy {
+            keep_days: policy.keep_days,
+            keep_last: policy.keep_last,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatStats {
+    /// Number of messages, info messages are not counted.
+    msg_cnt: usize,
+
+    first_timestamp: Option<i64>,
+    last_timestamp: Option<i64>,
+
+    /// Statistics per sender, the most active senders first.
+    members: Vec<ChatMemberStats>,
+
+    /// Number of messages with attachments per viewtype.
+    media_cnt: Vec<ChatMediaCount>,
+
+    /// Average number of seconds between a message
+    /// and the previous message from a different sender.
+    avg_response_time: Option<i64>,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMemberStats {
+    contact_id: u32,
+    msg_cnt: usize,
+    first_timestamp: i64,
+    last_timestamp: i64,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMediaCount {
+    viewtype: MessageViewtype,
+    count: usize,
+}
+
+impl From<chat::ChatStats> for ChatStats {
+    fn from(stats: chat::ChatStats) -> Self {
+        ChatStats {
+            msg_cnt: stats.msg_cnt,
+            first_timestamp: stats.first_timestamp,
+            last_timestamp: stats.last_timestamp,
+            members: stats
+                .members
+                .into_iter()
+                .map(|member| ChatMemberStats {
+                    contact_id: member.contact_id.to_u32(),
+                    msg_cnt: member.msg_cnt,
+                    first_timestamp: member.first_timestamp,
+                    last_timestamp: member.last_timestamp,
+                })
+                .collect(),
+            media_cnt: stats
+                .media_cnt
+                .into_iter()
+                .map(|(viewtype, count)| ChatMediaCount {
+                    viewtype: viewtype.into(),
+                    count,
+                })
+                .collect(),
+            avg_response_time: stats.avg_response_time,
+        }
+    }
+}
+
+/// Recorded addition or removal of a group member, see the `member_digest_threshold` config option.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberChange {
+    pub contact_id: u32,
+    /// True if the contact was added, false if it was removed or left the group.
+    pub added: bool,
+    pub by_contact_id: u32,
+    pub timestamp: i64,
+}
+
+impl From<member_digest::MemberChange> for MemberChange {
+    fn from(change: member_digest::MemberChange) -> Self {
+        MemberChange {
+            contact_id: change.contact_id.to_u32(),
+            added: change.added,
+            by_contact_id: change.by_contact_id.to_u32(),
+            timestamp: change.timestamp,
+        }
+    }
+}
+
+/// Member of a protected chat to whom a message can't be encrypted.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnencryptableMember {
+    pub contact_id: u32,
+    /// True if no key of the member is known, false if the key is not verified.
+    pub missing_key: bool,
+}
+
+impl From<(ContactId, chat::KeyProblem)> for UnencryptableMember {
+    fn from((contact_id, problem): (ContactId, chat::KeyProblem)) -> Self {
+        UnencryptableMember {
+            contact_id: contact_id.to_u32(),
+            missing_key: problem == chat::KeyProblem::Missing,
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use deltachat::chat::{Chat, ChatId};
+use deltachat::chat::{self, Chat, ChatId};
 use deltachat::chatlist::get_last_message_for_chat;
 use deltachat::constants::*;
 use deltachat::contact::{Contact, ContactId};
@@ -47,6 +47,8 @@ pub enum ChatListItemFetchResult {
         was_seen_recently: bool,
         last_message_type: Option<MessageViewtype>,
         last_message_id: Option<u32>,
+        /// whether messages to the chat can be encrypted end-to-end, to show a padlock
+        encryption_state: ChatEncryption,
     },
     #[serde(rename_all = "camelCase")]
     ArchiveLink { fresh_message_counter: usize },
@@ -54,6 +56,26 @@ pub enum ChatListItemFetchResult {
     Error { id: u32, error: String },
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum ChatEncryption {
+    /// The key of at least one member is missing or the chat is a mailing list.
+    Unavailable,
+    /// Keys of all members are known, but not all of them are verified.
+    Available,
+    /// Verified keys of all members are known.
+    Verified,
+}
+
+impl From<chat::ChatEncryption> for ChatEncryption {
+    fn from(state: chat::ChatEncryption) -> Self {
+        match state {
+            chat::ChatEncryption::Unavailable => ChatEncryption::Unavailable,
+            chat::ChatEncryption::Available => ChatEncryption::Available,
+            chat::ChatEncryption::Verified => ChatEncryption::Verified,
+        }
+    }
+}
+
 pub(crate) async fn get_chat_list_item_by_id(
     ctx: &deltachat::context::Context,
     entry: u32,
@@ -125,6 +147,7 @@ pub(crate) async fn get_chat_list_item_by_id(
     };
 
     let color = color_int_to_hex_string(chat.get_color(ctx).await?);
+    let encryption_state = chat_id.get_encryption_state(ctx).await?.into();
 
     Ok(ChatListItemFetchResult::ChatListItem {
         id: chat_id.to_u32(),
@@ -152,5 +175,6 @@ pub(crate) async fn get_chat_list_item_by_id(
         was_seen_recently,
         last_message_type: message_type,
         last_message_id: last_msgid.map(|id| id.to_u32()),
+        encryption_state,
     })
 }
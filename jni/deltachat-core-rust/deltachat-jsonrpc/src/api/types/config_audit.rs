@@ -0,0 +1,64 @@
+use deltachat::config_audit;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Origin of a config change.
+#[derive(Debug, Clone, Copy, Serialize, TypeDef, schemars::JsonSchema)]
+pub enum ConfigOrigin {
+    /// The change was made using the API, usually by the user.
+    User,
+
+    /// The change was received from another device in a sync message.
+    Sync,
+
+    /// The change was made by scanning a QR code.
+    Qr,
+}
+
+impl From<config_audit::ConfigOrigin> for ConfigOrigin {
+    fn from(origin: config_audit::ConfigOrigin) -> Self {
+        match origin {
+            config_audit::ConfigOrigin::User => ConfigOrigin::User,
+            config_audit::ConfigOrigin::Sync => ConfigOrigin::Sync,
+            config_audit::ConfigOrigin::Qr => ConfigOrigin::Qr,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChange {
+    /// ID of the change, used to roll it back.
+    id: u32,
+
+    /// Changed config key.
+    key: String,
+
+    /// Hex-encoded SHA-256 hash of the previous value, `null` if the key was unset.
+    old_value_hash: Option<String>,
+
+    /// Hex-encoded SHA-256 hash of the new value, `null` if the key was unset.
+    new_value_hash: Option<String>,
+
+    /// Timestamp of the change.
+    timestamp: i64,
+
+    origin: ConfigOrigin,
+
+    /// True if the change can be rolled back.
+    can_rollback: bool,
+}
+
+impl From<config_audit::ConfigChange> for ConfigChange {
+    fn from(change: config_audit::ConfigChange) -> Self {
+        ConfigChange {
+            id: change.id,
+            key: change.key,
+            old_value_hash: change.old_value_hash,
+            new_value_hash: change.new_value_hash,
+            timestamp: change.timestamp,
+            origin: change.origin.into(),
+            can_rollback: change.can_rollback,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use deltachat::color;
 use deltachat::context::Context;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
 
 use super::color_int_to_hex_string;
@@ -122,3 +122,100 @@ fn from(vc: deltachat_contact_tools::VcardContact) -> Self {
         }
     }
 }
+
+/// Added or changed entry of an address book.
+#[derive(Debug, Clone, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBookEntry {
+    name: String,
+    address: String,
+}
+
+impl From<AddressBookEntry> for deltachat::contact::AddressBookEntry {
+    fn from(entry: AddressBookEntry) -> Self {
+        Self {
+            name: entry.name,
+            addr: entry.address,
+        }
+    }
+}
+
+/// Sort order of contact list pages.
+#[derive(Debug, Clone, Copy, Deserialize, TypeDef, schemars::JsonSchema)]
+pub enum ContactSortOrder {
+    /// Recently seen contacts first.
+    LastSeen,
+
+    /// Alphabetically by name, case-insensitive.
+    Name,
+}
+
+impl From<ContactSortOrder> for deltachat::contact::ContactSortOrder {
+    fn from(sort: ContactSortOrder) -> Self {
+        match sort {
+            ContactSortOrder::LastSeen => deltachat::contact::ContactSortOrder::LastSeen,
+            ContactSortOrder::Name => deltachat::contact::ContactSortOrder::Name,
+        }
+    }
+}
+
+/// Filter for contact list pages.
+#[derive(Debug, Clone, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactListFilter {
+    /// String to search for in names and addresses.
+    query: Option<String>,
+
+    /// Return only contacts with a verified key.
+    verified_only: bool,
+
+    /// Return only contacts with an unblocked 1:1 chat.
+    with_chat_only: bool,
+
+    /// Return only contacts tagged with this tag.
+    tag: Option<String>,
+
+    sort: ContactSortOrder,
+}
+
+impl From<ContactListFilter> for deltachat::contact::ContactListFilter {
+    fn from(filter: ContactListFilter) -> Self {
+        Self {
+            query: filter.query,
+            verified_only: filter.verified_only,
+            with_chat_only: filter.with_chat_only,
+            tag: filter.tag,
+            sort: filter.sort.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Lightweight contact list entry.
+#[derive(Clone, Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactListEntry {
+    id: u32,
+    display_name: String,
+    address: String,
+    /// Contact color as hex string.
+    color: String,
+    last_seen: i64,
+    /// Whether we have a verified key for the contact.
+    ///
+    /// Use `Contact.isVerified` to decide whether to show a green checkmark.
+    has_verified_key: bool,
+}
+
+impl From<deltachat::contact::ContactListEntry> for ContactListEntry {
+    fn from(entry: deltachat::contact::ContactListEntry) -> Self {
+        Self {
+            id: entry.id.to_u32(),
+            display_name: entry.display_name,
+            address: entry.addr,
+            color: color_int_to_hex_string(entry.color),
+            last_seen: entry.last_seen,
+            has_verified_key: entry.has_verified_key,
+        }
+    }
+}
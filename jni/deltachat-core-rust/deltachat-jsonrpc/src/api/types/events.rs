@@ -2,6 +2,8 @@
 use serde::Serialize;
 use typescript_type_def::TypeDef;
 
+use super::health::HealthCategory;
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
@@ -120,7 +122,23 @@ pub enum EventType {
     ///
     /// There is no extra #DC_EVENT_MSGS_CHANGED event sent together with this event.
     #[serde(rename_all = "camelCase")]
-    IncomingMsg { chat_id: u32, msg_id: u32 },
+    IncomingMsg {
+        chat_id: u32,
+        msg_id: u32,
+        /// Whether the message contains a watch keyword.
+        /// The UI may show a notification even if the chat is muted then.
+        watched: bool,
+    },
+
+    /// An incoming message from an unknown sender uses the name of a known contact
+    /// and possibly impersonates it.
+    #[serde(rename_all = "camelCase")]
+    IncomingMsgImpersonation {
+        chat_id: u32,
+        msg_id: u32,
+        /// ID of the contact whose name is used.
+        contact_id: u32,
+    },
 
     /// Downloading a bunch of messages just finished. This is an
     /// event to allow the UI to only show one notification per message bunch,
@@ -128,6 +146,41 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     IncomingMsgBunch,
 
+    /// A report of an offending message was received from another user.
+    #[serde(rename_all = "camelCase")]
+    IncomingMsgReport { report_id: u32 },
+
+    /// A contact calls and the device should ring.
+    #[serde(rename_all = "camelCase")]
+    IncomingCall {
+        chat_id: u32,
+        msg_id: u32,
+        /// Payload of the caller, e.g. an SDP offer.
+        payload: String,
+    },
+
+    /// An incoming call was accepted on this or another device of the user.
+    #[serde(rename_all = "camelCase")]
+    IncomingCallAccepted { chat_id: u32, msg_id: u32 },
+
+    /// An outgoing call was accepted by the callee.
+    #[serde(rename_all = "camelCase")]
+    OutgoingCallAccepted {
+        chat_id: u32,
+        msg_id: u32,
+        /// Payload of the callee, e.g. an SDP answer.
+        payload: String,
+    },
+
+    /// A call was ended or rejected.
+    #[serde(rename_all = "camelCase")]
+    CallEnded { chat_id: u32, msg_id: u32 },
+
+    /// An incoming call was cancelled by the caller before it was accepted
+    /// or was received too late to ring.
+    #[serde(rename_all = "camelCase")]
+    CallMissed { chat_id: u32, msg_id: u32 },
+
     /// Messages were seen or noticed.
     /// chat id is always set.
     #[serde(rename_all = "camelCase")]
@@ -148,6 +201,19 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     MsgRead { chat_id: u32, msg_id: u32 },
 
+    /// State of a single message changed, see `Message.state`.
+    ///
+    /// Emitted in addition to the less specific events such as `MsgDelivered`, `MsgRead`
+    /// or `MsgsNoticed`, so UIs can update a single message instead of reloading the whole chat.
+    #[serde(rename_all = "camelCase")]
+    MsgStateChanged {
+        chat_id: u32,
+        msg_id: u32,
+        old_state: u32,
+        new_state: u32,
+        cause: MsgStateChangeCause,
+    },
+
     /// A single message is deleted.
     #[serde(rename_all = "camelCase")]
     MsgDeleted { chat_id: u32, msg_id: u32 },
@@ -166,6 +232,11 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ChatEphemeralTimerModified { chat_id: u32, timer: u32 },
 
+    /// Inactive chats were archived during housekeeping
+    /// as configured by the `auto_archive_days` option.
+    #[serde(rename_all = "camelCase")]
+    ChatsAutoArchived { chat_ids: Vec<u32> },
+
     /// Contact(s) created, renamed, blocked or deleted.
     ///
     /// @param data1 (int) If set, this is the contact_id of an added contact that should be selected.
@@ -198,6 +269,29 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ImexProgress { progress: usize },
 
+    /// Detailed progress of a backup export or import,
+    /// emitted together with `ImexProgress` and when the phase changes.
+    #[serde(rename_all = "camelCase")]
+    ImexProgressDetail {
+        /// Current phase.
+        phase: ImexPhase,
+
+        /// Number of archive entries transferred completely.
+        items_done: usize,
+
+        /// Total number of archive entries, `null` if unknown as during import.
+        items_total: Option<usize>,
+
+        /// Number of bytes transferred.
+        bytes_done: u64,
+
+        /// Expected total number of bytes.
+        bytes_total: u64,
+
+        /// Estimated remaining time in seconds, `null` if not known yet.
+        eta_secs: Option<u64>,
+    },
+
     /// A file has been exported. A file has been written by imex().
     /// This event may be sent multiple times by a single call to imex().
     ///
@@ -208,6 +302,38 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ImexFileWritten { path: String },
 
+    /// Inform about the progress of housekeeping.
+    ///
+    /// @param data1 (usize) 0=error or cancelled, 1-999=progress in permille, 1000=success and done
+    /// @param data2 0
+    #[serde(rename_all = "camelCase")]
+    HousekeepingProgress { progress: usize },
+
+    /// Inform about the progress of recoding an image of a message being sent.
+    ///
+    /// The recoding can be cancelled with `cancel_image_recoding()`.
+    #[serde(rename_all = "camelCase")]
+    ImageRecodeProgress {
+        /// ID of the chat the message is sent to.
+        chat_id: u32,
+
+        /// 0=error or cancelled, 1-999=progress in permille, 1000=success and done
+        progress: usize,
+    },
+
+    /// Inform about the progress of preparing an attachment staged with `stage_attachment()`.
+    #[serde(rename_all = "camelCase")]
+    AttachmentStagingProgress {
+        /// ID of the chat the attachment is staged for.
+        chat_id: u32,
+
+        /// ID of the staged attachment.
+        staged_id: u32,
+
+        /// 0=error or cancelled, 500=copied into the blobdir, 1000=ready
+        progress: usize,
+    },
+
     /// Progress information of a secure-join handshake from the view of the inviter
     /// (Alice, the person who shows the QR code).
     ///
@@ -240,6 +366,39 @@ pub enum EventType {
     /// getConnectivityHtml() for details.
     ConnectivityChanged,
 
+    /// The provider status endpoint reports an incident.
+    ///
+    /// The endpoint is checked when connecting to the server fails repeatedly.
+    /// Every incident is reported only once.
+    #[serde(rename_all = "camelCase")]
+    ProviderIncident {
+        /// Short summary of the incident.
+        title: String,
+
+        /// Description of the incident, possibly followed by a link to the incident page.
+        details: String,
+    },
+
+    /// A problem with the account was detected by the periodic health evaluation.
+    ///
+    /// Every warning is reported only once until the problem is fixed.
+    #[serde(rename_all = "camelCase")]
+    AccountHealthWarning {
+        /// Machine-readable category of the warning.
+        category: HealthCategory,
+
+        /// Technical details, not translated.
+        details: String,
+    },
+
+    /// The features of the server changed,
+    /// they can be queried with `get_server_capabilities`.
+    ServerCapabilitiesChanged,
+
+    /// The process became the owner of the account or released the ownership,
+    /// IO runs only while the process owns the account.
+    OwnershipChanged,
+
     /// Deprecated by `ConfigSynced`.
     SelfavatarChanged,
 
@@ -286,6 +445,25 @@ pub enum EventType {
     #[serde(rename_all = "camelCase")]
     ChatlistItemChanged { chat_id: Option<u32> },
 
+    /// A chat was inserted into the chatlist or moved to another position.
+    ///
+    /// The UI should remove the chat from its old position, if any, and insert it at `index`.
+    /// Only emitted if enabled with `set_chatlist_diff_events()`.
+    #[serde(rename_all = "camelCase")]
+    ChatlistItemMoved { chat_id: u32, index: u32 },
+
+    /// A chat was removed from the chatlist, e.g. because it was archived or deleted.
+    ///
+    /// Only emitted if enabled with `set_chatlist_diff_events()`.
+    #[serde(rename_all = "camelCase")]
+    ChatlistItemRemoved { chat_id: u32 },
+
+    /// The number of fresh messages of a chat in the chatlist changed.
+    ///
+    /// Only emitted if enabled with `set_chatlist_diff_events()`.
+    #[serde(rename_all = "camelCase")]
+    ChatlistItemFreshMsgCountChanged { chat_id: u32, count: u32 },
+
     /// Inform that the list of accounts has changed (an account removed or added or (not yet implemented) the account order changes)
     ///
     /// This event is only emitted by the account manager
@@ -353,11 +531,56 @@ fn from(event: CoreEventType) -> Self {
                 text,
                 href,
             },
-            CoreEventType::IncomingMsg { chat_id, msg_id } => IncomingMsg {
+            CoreEventType::IncomingMsg {
+                chat_id,
+                msg_id,
+                watched,
+            } => IncomingMsg {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+                watched,
+            },
+            CoreEventType::IncomingMsgImpersonation {
+                chat_id,
+                msg_id,
+                contact_id,
+            } => IncomingMsgImpersonation {
                 chat_id: chat_id.to_u32(),
                 msg_id: msg_id.to_u32(),
+                contact_id: contact_id.to_u32(),
             },
             CoreEventType::IncomingMsgBunch => IncomingMsgBunch,
+            CoreEventType::IncomingMsgReport { report_id } => IncomingMsgReport { report_id },
+            CoreEventType::IncomingCall {
+                chat_id,
+                msg_id,
+                payload,
+            } => IncomingCall {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+                payload,
+            },
+            CoreEventType::IncomingCallAccepted { chat_id, msg_id } => IncomingCallAccepted {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+            },
+            CoreEventType::OutgoingCallAccepted {
+                chat_id,
+                msg_id,
+                payload,
+            } => OutgoingCallAccepted {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+                payload,
+            },
+            CoreEventType::CallEnded { chat_id, msg_id } => CallEnded {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+            },
+            CoreEventType::CallMissed { chat_id, msg_id } => CallMissed {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+            },
             CoreEventType::MsgsNoticed(chat_id) => MsgsNoticed {
                 chat_id: chat_id.to_u32(),
             },
@@ -373,6 +596,19 @@ fn from(event: CoreEventType) -> Self {
                 chat_id: chat_id.to_u32(),
                 msg_id: msg_id.to_u32(),
             },
+            CoreEventType::MsgStateChanged {
+                chat_id,
+                msg_id,
+                old_state,
+                new_state,
+                cause,
+            } => MsgStateChanged {
+                chat_id: chat_id.to_u32(),
+                msg_id: msg_id.to_u32(),
+                old_state: old_state as u32,
+                new_state: new_state as u32,
+                cause: cause.into(),
+            },
             CoreEventType::MsgDeleted { chat_id, msg_id } => MsgDeleted {
                 chat_id: chat_id.to_u32(),
                 msg_id: msg_id.to_u32(),
@@ -386,6 +622,9 @@ fn from(event: CoreEventType) -> Self {
                     timer: timer.to_u32(),
                 }
             }
+            CoreEventType::ChatsAutoArchived { chat_ids } => ChatsAutoArchived {
+                chat_ids: chat_ids.iter().map(|chat_id| chat_id.to_u32()).collect(),
+            },
             CoreEventType::ContactsChanged(contact) => ContactsChanged {
                 contact_id: contact.map(|c| c.to_u32()),
             },
@@ -396,9 +635,38 @@ fn from(event: CoreEventType) -> Self {
                 ConfigureProgress { progress, comment }
             }
             CoreEventType::ImexProgress(progress) => ImexProgress { progress },
+            CoreEventType::ImexProgressDetail {
+                phase,
+                items_done,
+                items_total,
+                bytes_done,
+                bytes_total,
+                eta_secs,
+            } => ImexProgressDetail {
+                phase: phase.into(),
+                items_done,
+                items_total,
+                bytes_done,
+                bytes_total,
+                eta_secs,
+            },
             CoreEventType::ImexFileWritten(path) => ImexFileWritten {
                 path: path.to_str().unwrap_or_default().to_owned(),
             },
+            CoreEventType::HousekeepingProgress(progress) => HousekeepingProgress { progress },
+            CoreEventType::ImageRecodeProgress { chat_id, progress } => ImageRecodeProgress {
+                chat_id: chat_id.to_u32(),
+                progress,
+            },
+            CoreEventType::AttachmentStagingProgress {
+                chat_id,
+                staged_id,
+                progress,
+            } => AttachmentStagingProgress {
+                chat_id: chat_id.to_u32(),
+                staged_id: staged_id.to_u32(),
+                progress,
+            },
             CoreEventType::SecurejoinInviterProgress {
                 contact_id,
                 progress,
@@ -414,6 +682,15 @@ fn from(event: CoreEventType) -> Self {
                 progress,
             },
             CoreEventType::ConnectivityChanged => ConnectivityChanged,
+            CoreEventType::ProviderIncident { title, details } => {
+                ProviderIncident { title, details }
+            }
+            CoreEventType::AccountHealthWarning { category, details } => AccountHealthWarning {
+                category: category.into(),
+                details,
+            },
+            CoreEventType::ServerCapabilitiesChanged => ServerCapabilitiesChanged,
+            CoreEventType::OwnershipChanged => OwnershipChanged,
             CoreEventType::SelfavatarChanged => SelfavatarChanged,
             CoreEventType::ConfigSynced { key } => ConfigSynced {
                 key: key.to_string(),
@@ -441,6 +718,19 @@ fn from(event: CoreEventType) -> Self {
             CoreEventType::ChatlistItemChanged { chat_id } => ChatlistItemChanged {
                 chat_id: chat_id.map(|id| id.to_u32()),
             },
+            CoreEventType::ChatlistItemMoved { chat_id, index } => ChatlistItemMoved {
+                chat_id: chat_id.to_u32(),
+                index: u32::try_from(index).unwrap_or(u32::MAX),
+            },
+            CoreEventType::ChatlistItemRemoved { chat_id } => ChatlistItemRemoved {
+                chat_id: chat_id.to_u32(),
+            },
+            CoreEventType::ChatlistItemFreshMsgCountChanged { chat_id, count } => {
+                ChatlistItemFreshMsgCountChanged {
+                    chat_id: chat_id.to_u32(),
+                    count: u32::try_from(count).unwrap_or(u32::MAX),
+                }
+            }
             CoreEventType::ChatlistChanged => ChatlistChanged,
             CoreEventType::EventChannelOverflow { n } => EventChannelOverflow { n },
             CoreEventType::AccountsChanged => AccountsChanged,
@@ -451,3 +741,59 @@ fn from(event: CoreEventType) -> Self {
         }
     }
 }
+
+/// Cause of a message state change.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum MsgStateChangeCause {
+    /// The SMTP server accepted the message.
+    SmtpAccepted,
+
+    /// Sending the message failed or a non-delivery notification was received.
+    SendFailed,
+
+    /// A read receipt was received.
+    MdnReceived,
+
+    /// Resending the message was requested.
+    ResendRequested,
+
+    /// The message was marked as seen or noticed on this device.
+    MarkedSeen,
+}
+
+impl From<deltachat::message::MsgStateChangeCause> for MsgStateChangeCause {
+    fn from(cause: deltachat::message::MsgStateChangeCause) -> Self {
+        use deltachat::message::MsgStateChangeCause as Cause;
+        match cause {
+            Cause::SmtpAccepted => Self::SmtpAccepted,
+            Cause::SendFailed => Self::SendFailed,
+            Cause::MdnReceived => Self::MdnReceived,
+            Cause::ResendRequested => Self::ResendRequested,
+            Cause::MarkedSeen => Self::MarkedSeen,
+        }
+    }
+}
+
+/// Phase of a backup export or import.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum ImexPhase {
+    /// The database is written into or read from the backup.
+    Database,
+
+    /// Files are written into or read from the backup.
+    Blobs,
+
+    /// The imported database is set up or the exported backup is completed.
+    Finishing,
+}
+
+impl From<deltachat::imex::ImexPhase> for ImexPhase {
+    fn from(phase: deltachat::imex::ImexPhase) -> Self {
+        use deltachat::imex::ImexPhase as Phase;
+        match phase {
+            Phase::Database => Self::Database,
+            Phase::Blobs => Self::Blobs,
+            Phase::Finishing => Self::Finishing,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use deltachat::health;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Category of an account health warning.
+#[derive(Debug, Clone, Copy, Serialize, TypeDef, schemars::JsonSchema)]
+pub enum HealthCategory {
+    /// Logging in to the server failed repeatedly because of wrong credentials.
+    AuthFailures,
+
+    /// The server reports that the mailbox uses most of its quota.
+    QuotaWarning,
+
+    /// The mailbox is nearly full and new messages may be rejected soon.
+    MailboxNearlyFull,
+
+    /// The TLS certificate of the server expires soon or is expired.
+    CertificateExpiry,
+
+    /// Several outgoing messages failed recently.
+    Bounces,
+}
+
+impl From<health::HealthCategory> for HealthCategory {
+    fn from(category: health::HealthCategory) -> Self {
+        match category {
+            health::HealthCategory::AuthFailures => HealthCategory::AuthFailures,
+            health::HealthCategory::QuotaWarning => HealthCategory::QuotaWarning,
+            health::HealthCategory::MailboxNearlyFull => HealthCategory::MailboxNearlyFull,
+            health::HealthCategory::CertificateExpiry => HealthCategory::CertificateExpiry,
+            health::HealthCategory::Bounces => HealthCategory::Bounces,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthWarning {
+    /// Machine-readable category of the warning.
+    category: HealthCategory,
+
+    /// Technical details, not translated.
+    details: String,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountHealth {
+    /// Health score from 0 to 100, 100 meaning no problems were detected.
+    score: u32,
+
+    /// Detected problems, at most one per category.
+    warnings: Vec<HealthWarning>,
+}
+
+impl From<health::AccountHealth> for AccountHealth {
+    fn from(health: health::AccountHealth) -> Self {
+        AccountHealth {
+            score: health.score,
+            warnings: health
+                .warnings
+                .into_iter()
+                .map(|warning| HealthWarning {
+                    category: warning.category.into(),
+                    details: warning.details,
+                })
+                .collect(),
+        }
+    }
+}
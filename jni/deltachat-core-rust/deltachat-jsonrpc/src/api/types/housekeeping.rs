@@ -0,0 +1,30 @@
+use deltachat::housekeeping;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HousekeepingStatus {
+    /// Whether housekeeping is currently running.
+    running: bool,
+
+    /// Progress of the running job in permille, 0 if not running.
+    progress: usize,
+
+    /// Whether the running job is paused because the app is in the foreground.
+    paused: bool,
+
+    /// Unix timestamp of the start of the last housekeeping run, 0 if it never ran.
+    last_run: i64,
+}
+
+impl From<housekeeping::HousekeepingStatus> for HousekeepingStatus {
+    fn from(status: housekeeping::HousekeepingStatus) -> Self {
+        HousekeepingStatus {
+            running: status.running,
+            progress: status.progress,
+            paused: status.paused,
+            last_run: status.last_run,
+        }
+    }
+}
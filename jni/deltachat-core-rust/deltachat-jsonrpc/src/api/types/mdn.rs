@@ -0,0 +1,41 @@
+use deltachat::mdn;
+use serde::{Deserialize, Serialize};
+use typescript_type_def::TypeDef;
+
+/// Read receipt policy of a chat or a contact.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+pub enum MdnPolicy {
+    /// Not set, the contact policy or the `mdns_enabled` config option is used.
+    Default,
+
+    /// Always send read receipts.
+    Always,
+
+    /// Never send read receipts.
+    Never,
+
+    /// Send read receipts only if the contact is verified.
+    VerifiedOnly,
+}
+
+impl From<MdnPolicy> for mdn::MdnPolicy {
+    fn from(policy: MdnPolicy) -> Self {
+        match policy {
+            MdnPolicy::Default => mdn::MdnPolicy::Default,
+            MdnPolicy::Always => mdn::MdnPolicy::Always,
+            MdnPolicy::Never => mdn::MdnPolicy::Never,
+            MdnPolicy::VerifiedOnly => mdn::MdnPolicy::VerifiedOnly,
+        }
+    }
+}
+
+impl From<mdn::MdnPolicy> for MdnPolicy {
+    fn from(policy: mdn::MdnPolicy) -> Self {
+        match policy {
+            mdn::MdnPolicy::Default => MdnPolicy::Default,
+            mdn::MdnPolicy::Always => MdnPolicy::Always,
+            mdn::MdnPolicy::Never => MdnPolicy::Never,
+            mdn::MdnPolicy::VerifiedOnly => MdnPolicy::VerifiedOnly,
+        }
+    }
+}
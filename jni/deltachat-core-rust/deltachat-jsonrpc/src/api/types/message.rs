@@ -9,7 +9,10 @@
 use deltachat::message::Message;
 use deltachat::message::MsgId;
 use deltachat::message::Viewtype;
+use deltachat::notes;
 use deltachat::reaction::get_msg_reactions;
+use deltachat::report;
+use deltachat::staging::StagedAttachmentId;
 use num_traits::cast::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
@@ -60,6 +63,9 @@ pub struct MessageObject {
     is_info: bool,
     is_forwarded: bool,
 
+    /// True if the message was removed from the server by another client.
+    is_removed_from_server: bool,
+
     /// True if the message was sent by a bot.
     is_bot: bool,
 
@@ -83,6 +89,15 @@ pub struct MessageObject {
     file_bytes: u64,
     file_name: Option<String>,
 
+    /// Path to the thumbnail of a video attachment.
+    thumbnail: Option<String>,
+
+    /// Path to the preview image of the first page of a PDF attachment.
+    preview_blob: Option<String>,
+
+    /// Waveform of a voice message, 64 peaks in the range 0..=255.
+    waveform: Option<Vec<u8>>,
+
     webxdc_info: Option<WebxdcMessageInfo>,
 
     webxdc_href: Option<String>,
@@ -92,6 +107,13 @@ pub struct MessageObject {
     reactions: Option<JSONRPCReactions>,
 
     vcard_contact: Option<VcardContact>,
+
+    /// Todo state if the message is a todo item in Saved Messages.
+    todo: Option<Todo>,
+
+    /// ID of the known contact whose name the unknown sender uses,
+    /// the message possibly impersonates that contact.
+    impersonated_contact_id: Option<u32>,
 }
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
@@ -206,13 +228,14 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
             timestamp: message.get_timestamp(),
             sort_timestamp: message.get_sort_timestamp(),
             received_timestamp: message.get_received_timestamp(),
-            has_deviating_timestamp: message.has_deviating_timestamp(),
+            has_deviating_timestamp: message.has_deviating_timestamp(context).await?,
 
             subject: message.get_subject().to_owned(),
             show_padlock: message.get_showpadlock(),
             is_setupmessage: message.is_setupmessage(),
             is_info: message.is_info(),
             is_forwarded: message.is_forwarded(),
+            is_removed_from_server: message.is_removed_from_server(),
             is_bot: message.is_bot(),
             system_message_type: message.get_info_type().into(),
 
@@ -241,6 +264,13 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
             file_mime: message.get_filemime(),
             file_bytes,
             file_name: message.get_filename(),
+            thumbnail: message
+                .get_thumbnail(context)
+                .and_then(|path| path.to_str().map(|s| s.to_owned())),
+            preview_blob: message
+                .get_preview_blob(context)
+                .and_then(|path| path.to_str().map(|s| s.to_owned())),
+            waveform: message.get_waveform(),
             webxdc_info,
 
             // On a WebxdcInfoMessage this might include a hash holding
@@ -252,6 +282,11 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Option<Self
             reactions,
 
             vcard_contact: vcard_contacts.first().cloned(),
+
+            todo: message.get_todo().map(Into::into),
+            impersonated_contact_id: message
+                .get_impersonated_contact()
+                .map(|contact_id| contact_id.to_u32()),
         };
         Ok(Some(message_object))
     }
@@ -403,6 +438,14 @@ pub enum SystemMessageType {
 
     /// This message contains a users iroh node address.
     IrohNodeAddr,
+
+    /// Hidden message sharing recent group messages with a new member.
+    GroupHistory,
+    MsgReport,
+    CallInvite,
+    CallAccepted,
+    CallRejected,
+    CallEnded,
 }
 
 impl From<deltachat::mimeparser::SystemMessage> for SystemMessageType {
@@ -426,6 +469,12 @@ fn from(system_message_type: deltachat::mimeparser::SystemMessage) -> Self {
             SystemMessage::WebxdcInfoMessage => SystemMessageType::WebxdcInfoMessage,
             SystemMessage::InvalidUnencryptedMail => SystemMessageType::InvalidUnencryptedMail,
             SystemMessage::IrohNodeAddr => SystemMessageType::IrohNodeAddr,
+            SystemMessage::GroupHistory => SystemMessageType::GroupHistory,
+            SystemMessage::MsgReport => SystemMessageType::MsgReport,
+            SystemMessage::CallInvite => SystemMessageType::CallInvite,
+            SystemMessage::CallAccepted => SystemMessageType::CallAccepted,
+            SystemMessage::CallRejected => SystemMessageType::CallRejected,
+            SystemMessage::CallEnded => SystemMessageType::CallEnded,
             SystemMessage::SecurejoinWait => SystemMessageType::SecurejoinWait,
             SystemMessage::SecurejoinWaitTimeout => SystemMessageType::SecurejoinWaitTimeout,
         }
@@ -561,13 +610,39 @@ pub enum JSONRPCMessageListItem {
     },
 
     /// Day marker, separating messages that correspond to different
-    /// days according to local time.
+    /// days according to local time, see the `timezone` config option.
     DayMarker {
-        /// Marker timestamp, for day markers, in unix milliseconds
+        /// Start of the local day as unix timestamp in seconds,
+        /// i.e. midnight in the configured timezone.
+        ///
+        /// Before the `timezone` config option was added,
+        /// this was midnight UTC of the local day,
+        /// so it must now be formatted in local time rather than in UTC.
         timestamp: i64,
     },
 }
 
+/// Change of the message list of a watched chat.
+///
+/// A batch of changes is applied in order:
+/// first the removals, then the insertions at ascending indexes.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum MessageListChange {
+    /// Item was inserted, `index` is the position in the new message list.
+    Inserted {
+        index: u32,
+        item: JSONRPCMessageListItem,
+    },
+
+    /// Item was removed.
+    Removed { item: JSONRPCMessageListItem },
+
+    /// Message was changed, e.g. its state or reactions, and should be reloaded.
+    #[serde(rename_all = "camelCase")]
+    Changed { msg_id: u32 },
+}
+
 impl From<ChatItem> for JSONRPCMessageListItem {
     fn from(item: ChatItem) -> Self {
         match item {
@@ -591,6 +666,12 @@ pub struct MessageData {
     /// Quoted message id. Takes preference over `quoted_text` (see below).
     pub quoted_message_id: Option<u32>,
     pub quoted_text: Option<String>,
+    /// Send an image in original quality, without scaling it down.
+    pub original_quality: Option<bool>,
+    /// Email subject, if not set, a subject is generated when sending.
+    pub subject: Option<String>,
+    /// ID of an attachment staged with `stage_attachment()`, replaces `viewtype` and `file`.
+    pub staged_attachment: Option<u32>,
 }
 
 impl MessageData {
@@ -615,6 +696,17 @@ pub(crate) async fn create_message(self, context: &Context) -> Result<Message> {
         if let Some((latitude, longitude)) = self.location {
             message.set_location(latitude, longitude);
         }
+        if let Some(original_quality) = self.original_quality {
+            message.set_original_quality(original_quality);
+        }
+        if let Some(subject) = self.subject {
+            message.set_subject(subject);
+        }
+        if let Some(id) = self.staged_attachment {
+            message
+                .set_staged_attachment(context, StagedAttachmentId::new(id))
+                .await?;
+        }
         if let Some(id) = self.quoted_message_id {
             let quoted_message = Message::load_from_db(context, MsgId::new(id))
                 .await
@@ -638,6 +730,46 @@ pub struct MessageReadReceipt {
     pub timestamp: i64,
 }
 
+/// Report of an offending message received from another user.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageReport {
+    pub id: u32,
+    /// Contact who reported the message.
+    pub reporter_id: u32,
+    /// Message-ID of the reported message.
+    pub rfc724_mid: String,
+    /// ID of the group the message was sent to, empty for other chats.
+    pub grpid: String,
+    pub chat_name: String,
+    /// Address of the sender of the reported message.
+    pub from_addr: String,
+    pub msg_timestamp: i64,
+    pub text: String,
+    pub headers: String,
+    pub reason: String,
+    /// Time the report was received.
+    pub timestamp: i64,
+}
+
+impl From<report::MsgReport> for MessageReport {
+    fn from(report: report::MsgReport) -> Self {
+        MessageReport {
+            id: report.id,
+            reporter_id: report.reporter_id.to_u32(),
+            rfc724_mid: report.rfc724_mid,
+            grpid: report.grpid,
+            chat_name: report.chat_name,
+            from_addr: report.from_addr,
+            msg_timestamp: report.msg_timestamp,
+            text: report.text,
+            headers: report.headers,
+            reason: report.reason,
+            timestamp: report.timestamp,
+        }
+    }
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageInfo {
@@ -705,3 +837,55 @@ fn from(value: deltachat::ephemeral::Timer) -> Self {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Todo {
+    /// Whether the todo item is checked.
+    done: bool,
+
+    /// Due date as a unix timestamp.
+    due: Option<i64>,
+}
+
+impl From<notes::Todo> for Todo {
+    fn from(todo: notes::Todo) -> Self {
+        Todo {
+            done: todo.done,
+            due: todo.due,
+        }
+    }
+}
+
+impl From<Todo> for notes::Todo {
+    fn from(todo: Todo) -> Self {
+        notes::Todo {
+            done: todo.done,
+            due: todo.due,
+        }
+    }
+}
+
+/// What to do if the destination of a saved file already exists.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TypeDef, schemars::JsonSchema)]
+pub enum SaveFilePolicy {
+    /// Fail.
+    Error,
+
+    /// Replace the existing file.
+    Overwrite,
+
+    /// Save the file under a name with a number added,
+    /// e.g. `photo (1).jpg` instead of `photo.jpg`.
+    AutoNumber,
+}
+
+impl From<SaveFilePolicy> for deltachat::message::SaveFilePolicy {
+    fn from(policy: SaveFilePolicy) -> Self {
+        match policy {
+            SaveFilePolicy::Error => deltachat::message::SaveFilePolicy::Error,
+            SaveFilePolicy::Overwrite => deltachat::message::SaveFilePolicy::Overwrite,
+            SaveFilePolicy::AutoNumber => deltachat::message::SaveFilePolicy::AutoNumber,
+        }
+    }
+}
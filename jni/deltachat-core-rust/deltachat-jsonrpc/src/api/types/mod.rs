@@ -1,14 +1,29 @@
 pub mod account;
+pub mod activity;
+pub mod backup;
+pub mod calls;
 pub mod chat;
 pub mod chat_list;
+pub mod config_audit;
 pub mod contact;
 pub mod events;
+pub mod health;
+pub mod housekeeping;
 pub mod http;
 pub mod location;
+pub mod mdn;
 pub mod message;
+pub mod network;
+pub mod network_stats;
+pub mod peerstate;
 pub mod provider_info;
 pub mod qr;
+pub mod quota;
 pub mod reactions;
+pub mod server_capabilities;
+pub mod sql_stats;
+pub mod startup;
+pub mod utils;
 pub mod webxdc;
 
 pub fn color_int_to_hex_string(color: u32) -> String {
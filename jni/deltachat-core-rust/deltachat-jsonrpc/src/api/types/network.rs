@@ -0,0 +1,34 @@
+use deltachat::net;
+use serde::Deserialize;
+use typescript_type_def::TypeDef;
+
+/// Class of the network the device is connected to.
+#[derive(Debug, Clone, Copy, Deserialize, TypeDef, schemars::JsonSchema)]
+pub enum NetworkKind {
+    /// The network class is not known.
+    Unknown,
+
+    /// There is no network.
+    None,
+
+    /// Wi-Fi or other unmetered network.
+    Wifi,
+
+    /// Cellular network.
+    Cellular,
+
+    /// Virtual private network.
+    Vpn,
+}
+
+impl From<NetworkKind> for net::NetworkKind {
+    fn from(kind: NetworkKind) -> Self {
+        match kind {
+            NetworkKind::Unknown => net::NetworkKind::Unknown,
+            NetworkKind::None => net::NetworkKind::None,
+            NetworkKind::Wifi => net::NetworkKind::Wifi,
+            NetworkKind::Cellular => net::NetworkKind::Cellular,
+            NetworkKind::Vpn => net::NetworkKind::Vpn,
+        }
+    }
+}
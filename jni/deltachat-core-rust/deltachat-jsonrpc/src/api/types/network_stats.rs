@@ -0,0 +1,88 @@
+use deltachat::net;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStats {
+    /// Number of messages the own avatar was attached to.
+    avatars_attached: u64,
+
+    /// Number of messages the own avatar was not attached to because of large group throttling.
+    avatars_throttled: u64,
+
+    /// Number of `Autocrypt-Gossip` headers sent.
+    gossip_headers: u64,
+
+    /// Number of messages sent without gossip because of large group throttling.
+    gossip_throttled: u64,
+}
+
+impl From<net::NetworkStats> for NetworkStats {
+    fn from(stats: net::NetworkStats) -> Self {
+        NetworkStats {
+            avatars_attached: stats.avatars_attached,
+            avatars_throttled: stats.avatars_throttled,
+            gossip_headers: stats.gossip_headers,
+            gossip_throttled: stats.gossip_throttled,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencySummary {
+    /// Number of messages that completed the stage.
+    count: u64,
+
+    /// Mean duration in milliseconds.
+    mean_ms: u64,
+
+    /// Median duration in milliseconds.
+    median_ms: u64,
+
+    /// 95th percentile of the duration in milliseconds.
+    p95_ms: u64,
+
+    /// Maximum duration in milliseconds.
+    max_ms: u64,
+}
+
+impl From<net::LatencySummary> for LatencySummary {
+    fn from(summary: net::LatencySummary) -> Self {
+        LatencySummary {
+            count: summary.count,
+            mean_ms: summary.mean_ms,
+            median_ms: summary.median_ms,
+            p95_ms: summary.p95_ms,
+            max_ms: summary.max_ms,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryLatencyStats {
+    /// Number of messages queued for sending.
+    messages: u64,
+
+    /// Time from queueing a message until it is submitted to the SMTP server.
+    queued_to_submitted: LatencySummary,
+
+    /// Time from submitting a message until the SMTP server accepted it.
+    submitted_to_accepted: LatencySummary,
+
+    /// Time from the SMTP server accepting a message until the first read receipt.
+    accepted_to_mdn: LatencySummary,
+}
+
+impl From<net::DeliveryLatencyStats> for DeliveryLatencyStats {
+    fn from(stats: net::DeliveryLatencyStats) -> Self {
+        DeliveryLatencyStats {
+            messages: stats.messages,
+            queued_to_submitted: stats.queued_to_submitted.into(),
+            submitted_to_accepted: stats.submitted_to_accepted.into(),
+            accepted_to_mdn: stats.accepted_to_mdn.into(),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use deltachat::peerstate;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerstateInfo {
+    /// E-mail address of the contact.
+    addr: String,
+
+    /// Encryption preference of the contact: `mutual`, `nopreference` or `reset`.
+    prefer_encrypt: String,
+
+    /// Timestamp of the latest peerstate update.
+    last_seen: i64,
+
+    /// Timestamp of the latest `Autocrypt` header reception.
+    last_seen_autocrypt: i64,
+
+    /// Timestamp of the latest `Autocrypt-Gossip` header reception.
+    gossip_timestamp: i64,
+
+    /// Fingerprint of the key received in the `Autocrypt` header.
+    public_key_fingerprint: Option<String>,
+
+    /// Fingerprint of the key received in the `Autocrypt-Gossip` header.
+    gossip_key_fingerprint: Option<String>,
+
+    /// Fingerprint of the verified key.
+    verified_key_fingerprint: Option<String>,
+
+    /// Fingerprint of the secondary verified key.
+    secondary_verified_key_fingerprint: Option<String>,
+
+    /// Address that introduced the verified key.
+    verifier: Option<String>,
+
+    /// Address that introduced the secondary verified key.
+    secondary_verifier: Option<String>,
+
+    /// Addresses that introduced the verified key, the direct verifier first.
+    verifier_chain: Vec<String>,
+
+    /// Whether we think the contact knows our key as verified.
+    backward_verified: bool,
+
+    /// Whether the key used for opportunistic encryption changed.
+    fingerprint_changed: bool,
+}
+
+impl From<peerstate::PeerstateInfo> for PeerstateInfo {
+    fn from(info: peerstate::PeerstateInfo) -> Self {
+        PeerstateInfo {
+            addr: info.addr,
+            prefer_encrypt: info.prefer_encrypt,
+            last_seen: info.last_seen,
+            last_seen_autocrypt: info.last_seen_autocrypt,
+            gossip_timestamp: info.gossip_timestamp,
+            public_key_fingerprint: info.public_key_fingerprint,
+            gossip_key_fingerprint: info.gossip_key_fingerprint,
+            verified_key_fingerprint: info.verified_key_fingerprint,
+            secondary_verified_key_fingerprint: info.secondary_verified_key_fingerprint,
+            verifier: info.verifier,
+            secondary_verifier: info.secondary_verifier,
+            verifier_chain: info.verifier_chain,
+            backward_verified: info.backward_verified,
+            fingerprint_changed: info.fingerprint_changed,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use deltachat::qr::Qr;
+use deltachat::qr::{self, Qr};
 use serde::Serialize;
 use typescript_type_def::TypeDef;
 
@@ -213,6 +213,7 @@ fn from(qr: Qr) -> Self {
             Qr::Backup2 {
                 ref node_addr,
                 auth_token,
+                ..
             } => QrObject::Backup2 {
                 node_addr: serde_json::to_string(node_addr).unwrap_or_default(),
                 auth_token,
@@ -303,3 +304,27 @@ fn from(qr: Qr) -> Self {
         }
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteLinkPreview {
+    /// Address of the inviter.
+    inviter_addr: String,
+    /// Display name of the inviter, may be empty.
+    inviter_name: String,
+    /// Group name if the link is a group invite link.
+    grpname: Option<String>,
+    /// Number of group members at the time the link was created, if known.
+    member_count: Option<u32>,
+}
+
+impl From<qr::InviteLinkPreview> for InviteLinkPreview {
+    fn from(preview: qr::InviteLinkPreview) -> Self {
+        InviteLinkPreview {
+            inviter_addr: preview.inviter_addr,
+            inviter_name: preview.inviter_name,
+            grpname: preview.grpname,
+            member_count: preview.member_count,
+        }
+    }
+}
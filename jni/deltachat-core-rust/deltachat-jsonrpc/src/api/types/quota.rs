@@ -0,0 +1,86 @@
+use deltachat::quota;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaResourceUsage {
+    /// Name of the quota root, often empty.
+    root: String,
+
+    /// Name of the resource, e.g. `STORAGE` or `MESSAGE`.
+    name: String,
+
+    /// Current usage, in bytes for `STORAGE`.
+    usage: u64,
+
+    /// Limit, in bytes for `STORAGE`.
+    limit: u64,
+
+    /// Usage in percent of the limit.
+    percent: u64,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaUsage {
+    /// Usage of all resources of all quota roots.
+    resources: Vec<QuotaResourceUsage>,
+
+    /// Highest usage of all resources in percent.
+    highest_percent: u64,
+
+    /// Timestamp of the query.
+    timestamp: i64,
+
+    /// True if the UI should suggest to clean up old messages on the server.
+    cleanup_suggested: bool,
+}
+
+impl From<quota::QuotaUsage> for QuotaUsage {
+    fn from(usage: quota::QuotaUsage) -> Self {
+        QuotaUsage {
+            resources: usage
+                .resources
+                .into_iter()
+                .map(|resource| QuotaResourceUsage {
+                    root: resource.root,
+                    name: resource.name,
+                    usage: resource.usage,
+                    limit: resource.limit,
+                    percent: resource.percent,
+                })
+                .collect(),
+            highest_percent: usage.highest_percent,
+            timestamp: usage.timestamp,
+            cleanup_suggested: usage.cleanup_suggested,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaHistoryEntry {
+    /// Timestamp of the query.
+    timestamp: i64,
+
+    /// Highest usage of all resources in percent.
+    highest_percent: u64,
+
+    /// Used storage in bytes, `null` if the server has no storage quota.
+    storage_usage: Option<u64>,
+
+    /// Storage limit in bytes, `null` if the server has no storage quota.
+    storage_limit: Option<u64>,
+}
+
+impl From<quota::QuotaHistoryEntry> for QuotaHistoryEntry {
+    fn from(entry: quota::QuotaHistoryEntry) -> Self {
+        QuotaHistoryEntry {
+            timestamp: entry.timestamp,
+            highest_percent: entry.highest_percent,
+            storage_usage: entry.storage_usage,
+            storage_limit: entry.storage_limit,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use deltachat::server_capabilities;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Features of the server of an account
+/// as found out when the server was last connected.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerCapabilities {
+    /// True if the server notifies about new messages via push notifications.
+    push: bool,
+
+    /// True if the account is on a chatmail server.
+    chatmail: bool,
+
+    /// Maximum size of a message in bytes the server accepts, if it is known.
+    max_message_size: Option<u64>,
+
+    /// True if all messages are kept in the inbox and no `DeltaChat` folder is used.
+    inbox_only: bool,
+
+    /// Number of days after which the server deletes messages, if it does.
+    retention_days: Option<u32>,
+}
+
+impl From<server_capabilities::ServerCapabilities> for ServerCapabilities {
+    fn from(capabilities: server_capabilities::ServerCapabilities) -> Self {
+        ServerCapabilities {
+            push: capabilities.push,
+            chatmail: capabilities.chatmail,
+            max_message_size: capabilities.max_message_size,
+            inbox_only: capabilities.inbox_only,
+            retention_days: capabilities.retention_days,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use deltachat::context;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementStats {
+    /// Statement text with whitespace collapsed.
+    sql: String,
+
+    /// Number of executions.
+    count: u64,
+
+    /// Total execution time in milliseconds.
+    total_ms: u64,
+
+    /// Longest execution time in milliseconds.
+    max_ms: u64,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowQuery {
+    /// Unix timestamp of the query.
+    timestamp: i64,
+
+    /// Statement text with whitespace collapsed, parameter values are not recorded.
+    sql: String,
+
+    /// Number of parameter placeholders in the statement.
+    param_count: usize,
+
+    /// Execution time in milliseconds.
+    duration_ms: u64,
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStats {
+    /// Number of connections in the pool.
+    size: usize,
+
+    /// Number of times a connection could not be allocated immediately.
+    waits: u64,
+
+    /// Total time spent waiting for a connection in milliseconds.
+    total_wait_ms: u64,
+
+    /// Capacity of the prepared statement cache of each connection.
+    statement_cache_capacity: usize,
+
+    /// Number of statements found in the prepared statement cache.
+    statement_cache_hits: u64,
+
+    /// Number of statements which had to be prepared.
+    statement_cache_misses: u64,
+
+    /// Ratio of statement cache hits to all lookups, `null` if there were no lookups.
+    statement_cache_hit_ratio: Option<f64>,
+}
+
+impl From<context::PoolStats> for PoolStats {
+    fn from(stats: context::PoolStats) -> Self {
+        PoolStats {
+            statement_cache_hit_ratio: stats.statement_cache_hit_ratio(),
+            size: stats.size,
+            waits: stats.waits,
+            total_wait_ms: stats.total_wait_ms,
+            statement_cache_capacity: stats.statement_cache_capacity,
+            statement_cache_hits: stats.statement_cache_hits,
+            statement_cache_misses: stats.statement_cache_misses,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlStats {
+    /// Statements, the ones with the highest total execution time first.
+    statements: Vec<StatementStats>,
+
+    /// Most recent slow queries, oldest first.
+    slow_queries: Vec<SlowQuery>,
+
+    /// Connection pool metrics, `null` if the database is closed.
+    pool: Option<PoolStats>,
+
+    /// Size of the write-ahead log file in bytes.
+    wal_size: u64,
+}
+
+impl From<context::SqlStats> for SqlStats {
+    fn from(stats: context::SqlStats) -> Self {
+        SqlStats {
+            statements: stats
+                .statements
+                .into_iter()
+                .map(|statement| StatementStats {
+                    sql: statement.sql,
+                    count: statement.count,
+                    total_ms: statement.total_ms,
+                    max_ms: statement.max_ms,
+                })
+                .collect(),
+            slow_queries: stats
+                .slow_queries
+                .into_iter()
+                .map(|query| SlowQuery {
+                    timestamp: query.timestamp,
+                    sql: query.sql,
+                    param_count: query.param_count,
+                    duration_ms: query.duration_ms,
+                })
+                .collect(),
+            pool: stats.pool.map(Into::into),
+            wal_size: stats.wal_size,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use deltachat::startup;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupTimings {
+    /// Time to open the database connections.
+    db_open_ms: Option<u64>,
+
+    /// Time to run database migrations.
+    migrations_ms: Option<u64>,
+
+    /// Time to load the configuration.
+    config_load_ms: Option<u64>,
+
+    /// Time to start the IO scheduler.
+    scheduler_start_ms: Option<u64>,
+
+    /// Time from creating the account until IO start was requested.
+    io_start_requested_ms: Option<u64>,
+
+    /// Time from creating the account until the chatlist was loaded for the first time.
+    first_chatlist_ms: Option<u64>,
+}
+
+impl From<startup::StartupTimings> for StartupTimings {
+    fn from(timings: startup::StartupTimings) -> Self {
+        StartupTimings {
+            db_open_ms: timings.db_open_ms,
+            migrations_ms: timings.migrations_ms,
+            config_load_ms: timings.config_load_ms,
+            scheduler_start_ms: timings.scheduler_start_ms,
+            io_start_requested_ms: timings.io_start_requested_ms,
+            first_chatlist_ms: timings.first_chatlist_ms,
+        }
+    }
+}
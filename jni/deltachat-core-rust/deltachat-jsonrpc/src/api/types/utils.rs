@@ -0,0 +1,35 @@
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+/// Message text with quotes, signatures etc. removed.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimplifiedText {
+    /// The text to display.
+    text: String,
+
+    /// True if the text is a forwarded message.
+    is_forwarded: bool,
+
+    /// True if parts of the text were removed,
+    /// so the full text should be available in the UI.
+    is_cut: bool,
+
+    /// Quote at the top of the text, if any.
+    top_quote: Option<String>,
+
+    /// Removed footer, usually the signature, if any.
+    footer: Option<String>,
+}
+
+impl From<deltachat::simplify::SimplifiedText> for SimplifiedText {
+    fn from(simplified: deltachat::simplify::SimplifiedText) -> Self {
+        SimplifiedText {
+            text: simplified.text,
+            is_forwarded: simplified.is_forwarded,
+            is_cut: simplified.is_cut,
+            top_quote: simplified.top_quote,
+            footer: simplified.footer,
+        }
+    }
+}
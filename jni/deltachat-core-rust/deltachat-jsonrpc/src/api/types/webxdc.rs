@@ -1,7 +1,7 @@
 use deltachat::{
     context::Context,
     message::{Message, MsgId},
-    webxdc::WebxdcInfo,
+    webxdc::{StatusUpdateEntry, WebxdcInfo},
 };
 use serde::Serialize;
 use typescript_type_def::TypeDef;
@@ -77,3 +77,34 @@ pub async fn get_for_message(
         })
     }
 }
+
+/// Status update of a webxdc instance.
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebxdcStatusUpdateEntry {
+    serial: u32,
+    /// Sender of the update, 0 for updates stored before senders were recorded.
+    from_id: u32,
+    /// The payload as JSON string.
+    payload: String,
+    info: Option<String>,
+    href: Option<String>,
+    document: Option<String>,
+    summary: Option<String>,
+}
+
+impl TryFrom<StatusUpdateEntry> for WebxdcStatusUpdateEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(entry: StatusUpdateEntry) -> anyhow::Result<Self> {
+        Ok(Self {
+            serial: entry.serial.to_u32(),
+            from_id: entry.from_id.to_u32(),
+            payload: serde_json::to_string(&entry.item.payload)?,
+            info: entry.item.info,
+            href: entry.item.href,
+            document: entry.item.document,
+            summary: entry.item.summary,
+        })
+    }
+}
@@ -2,6 +2,8 @@
 #![cfg_attr(not(test), forbid(clippy::indexing_slicing))]
 #![cfg_attr(not(test), forbid(clippy::string_slice))]
 pub mod api;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 pub use yerpc;
 
 #[cfg(test)]
@@ -55,6 +57,45 @@ async fn basic_json_rpc_functionality() -> anyhow::Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_get_openrpc_specification() -> anyhow::Result<()> {
+        let tmp_dir = TempDir::new().unwrap().path().into();
+        let writable = true;
+        let accounts = Accounts::new(tmp_dir, writable).await?;
+        let api = CommandApi::new(accounts);
+
+        let (sender, receiver) = unbounded::<String>();
+
+        let (client, mut rx) = RpcClient::new();
+        let session = RpcSession::new(client, api);
+        tokio::spawn({
+            async move {
+                while let Some(message) = rx.next().await {
+                    let message = serde_json::to_string(&message)?;
+                    sender.send(message).await?;
+                }
+                let res: Result<(), anyhow::Error> = Ok(());
+                res
+            }
+        });
+
+        let request =
+            r#"{"jsonrpc":"2.0","method":"get_openrpc_specification","params":[],"id":1}"#;
+        session.handle_incoming(request).await;
+        let result: serde_json::Value = serde_json::from_str(&receiver.recv().await?)?;
+        let spec = &result["result"];
+        assert_eq!(
+            spec["info"]["version"],
+            *deltachat::constants::DC_VERSION_STR
+        );
+        let methods = spec["methods"].as_array().unwrap();
+        assert!(methods
+            .iter()
+            .any(|method| method["name"] == "get_openrpc_specification"));
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_batch_set_config() -> anyhow::Result<()> {
         let tmp_dir = TempDir::new().unwrap().path().into();
@@ -1,13 +1,11 @@
 #![recursion_limit = "256"]
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use axum::{extract::ws::WebSocketUpgrade, response::Response, routing::get, Extension, Router};
-use yerpc::axum::handle_ws_rpc;
-use yerpc::{RpcClient, RpcSession};
-
-mod api;
-use api::{Accounts, CommandApi};
+use deltachat_jsonrpc::api::{Accounts, CommandApi};
+use deltachat_jsonrpc::websocket;
+use tokio::sync::RwLock;
 
 const DEFAULT_PORT: u16 = 20808;
 
@@ -19,29 +17,33 @@ async fn main() -> Result<(), std::io::Error> {
     let port = std::env::var("DC_PORT")
         .map(|port| port.parse::<u16>().expect("DC_PORT must be a number"))
         .unwrap_or(DEFAULT_PORT);
+    let ip = std::env::var("DC_BIND_ADDR")
+        .map(|ip| {
+            ip.parse::<IpAddr>()
+                .expect("DC_BIND_ADDR must be an IP address")
+        })
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    let auth_token = std::env::var("DC_AUTH_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
     log::info!("Starting with accounts directory `{path}`.");
     let writable = true;
     let accounts = Accounts::new(PathBuf::from(&path), writable).await.unwrap();
-    let state = CommandApi::new(accounts);
-
-    let app = Router::new()
-        .route("/ws", get(handler))
-        .layer(Extension(state.clone()));
+    let accounts = Arc::new(RwLock::new(accounts));
+    let state = CommandApi::from_arc(accounts.clone()).await;
 
     tokio::spawn(async move {
-        state.accounts.write().await.start_io().await;
+        accounts.write().await.start_io().await;
     });
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    if !ip.is_loopback() && auth_token.is_none() {
+        log::warn!(
+            "Listening on {ip} without DC_AUTH_TOKEN, everyone who can connect has full access."
+        );
+    }
+    let addr = SocketAddr::new(ip, port);
     log::info!("JSON-RPC WebSocket server listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    websocket::serve(state, addr, auth_token).await.unwrap();
 
     Ok(())
 }
-
-async fn handler(ws: WebSocketUpgrade, Extension(api): Extension<CommandApi>) -> Response {
-    let (client, out_receiver) = RpcClient::new();
-    let session = RpcSession::new(client.clone(), api.clone());
-    handle_ws_rpc(ws, out_receiver, session).await
-}
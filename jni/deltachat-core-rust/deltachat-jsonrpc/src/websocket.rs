@@ -0,0 +1,129 @@
+//! Built-in WebSocket server exposing the JSON-RPC API.
+//!
+//! The server accepts WebSocket connections on the `/ws` path.
+//! Each connection gets its own JSON-RPC session
+//! sharing the account manager of the [`CommandApi`].
+//!
+//! If an authentication token is configured,
+//! clients must provide it either in the `Authorization: Bearer <token>` header
+//! or, for clients such as web browsers which cannot set headers for WebSocket connections,
+//! in the `token` query parameter.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::Query;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router};
+use serde::Deserialize;
+use yerpc::axum::handle_ws_rpc;
+use yerpc::{RpcClient, RpcSession};
+
+use crate::api::CommandApi;
+
+/// State shared by all connections.
+#[derive(Debug)]
+struct ServerState {
+    api: CommandApi,
+
+    /// Token clients must provide, `None` if authentication is disabled.
+    auth_token: Option<String>,
+}
+
+/// Query parameters of the WebSocket endpoint.
+#[derive(Debug, Deserialize)]
+struct WsQuery {
+    token: Option<String>,
+}
+
+/// Returns a router serving the JSON-RPC API on the `/ws` path.
+///
+/// Can be used to add the JSON-RPC API to an existing axum application.
+pub fn router(api: CommandApi, auth_token: Option<String>) -> Router {
+    let state = Arc::new(ServerState { api, auth_token });
+    Router::new()
+        .route("/ws", get(handler))
+        .layer(Extension(state))
+}
+
+/// Serves the JSON-RPC API over WebSocket on `addr` until an error occurs.
+///
+/// If `auth_token` is set, clients must provide it to connect.
+/// Listening on addresses other than localhost without authentication
+/// gives everyone who can connect full access to all accounts.
+pub async fn serve(api: CommandApi, addr: SocketAddr, auth_token: Option<String>) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {addr}"))?;
+    axum::serve(listener, router(api, auth_token))
+        .await
+        .context("WebSocket server failed")
+}
+
+async fn handler(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    Query(query): Query<WsQuery>,
+    Extension(state): Extension<Arc<ServerState>>,
+) -> Response {
+    if let Some(auth_token) = &state.auth_token {
+        let provided = bearer_token(&headers).or(query.token.as_deref());
+        if !provided.is_some_and(|token| tokens_equal(token, auth_token)) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+
+    let (client, out_receiver) = RpcClient::new();
+    let session = RpcSession::new(client.clone(), state.api.clone());
+    handle_ws_rpc(ws, out_receiver, session).await
+}
+
+/// Returns the token of the `Authorization: Bearer` header if there is any.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Compares tokens in constant time to not leak the token through timing.
+fn tokens_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic foo"));
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        assert_eq!(bearer_token(&headers), Some("secret"));
+    }
+
+    #[test]
+    fn test_tokens_equal() {
+        assert!(tokens_equal("secret", "secret"));
+        assert!(!tokens_equal("secret", "secreT"));
+        assert!(!tokens_equal("secret", "secret2"));
+        assert!(!tokens_equal("", "secret"));
+    }
+}
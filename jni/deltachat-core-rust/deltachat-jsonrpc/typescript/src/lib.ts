@@ -1,5 +1,6 @@
 export * as RPC from "../generated/jsonrpc.js";
 export * as T from "../generated/types.js";
+export type { ErrorData, ErrorKind } from "../generated/errors.js";
 export { RawClient } from "../generated/client.js";
 export * from "./client.js";
 export * as yerpc from "yerpc";
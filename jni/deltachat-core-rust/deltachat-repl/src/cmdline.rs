@@ -359,6 +359,7 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
                  fetch\n\
                  connectivity\n\
                  maybenetwork\n\
+                 netfaults [off|latency <ms>|drop <count>|reset <bytes>|throttle <bytes-per-sec>]\n\
                  housekeeping\n\
                  help imex (Import/Export)\n\
                  ==============================Chat commands==\n\
@@ -551,6 +552,29 @@ pub async fn cmdline(context: Context, line: &str, chat_id: &mut ChatId) -> Resu
         "maybenetwork" => {
             context.maybe_network().await;
         }
+        #[cfg(feature = "net-fault-injection")]
+        "netfaults" => {
+            use deltachat::net::fault::{get_net_faults, set_net_faults, NetFaults};
+
+            let mut faults = get_net_faults();
+            match arg1 {
+                "" => {}
+                "off" => faults = NetFaults::default(),
+                "latency" => faults.latency = Duration::from_millis(arg2.parse()?),
+                "drop" => faults.drop_connections = arg2.parse()?,
+                "reset" => faults.reset_after_bytes = Some(arg2.parse()?).filter(|n| *n > 0),
+                "throttle" => {
+                    faults.throttle_bytes_per_sec = Some(arg2.parse()?).filter(|n| *n > 0)
+                }
+                _ => bail!("Unknown network fault {arg1:?}."),
+            }
+            set_net_faults(faults.clone());
+            println!("{faults:#?}");
+        }
+        #[cfg(not(feature = "net-fault-injection"))]
+        "netfaults" => {
+            bail!("Compiled without the net-fault-injection feature.");
+        }
         "housekeeping" => {
             sql::housekeeping(&context).await.log_err(&context).ok();
         }
@@ -41,26 +41,46 @@ class EventType(str, Enum):
     REACTIONS_CHANGED = "ReactionsChanged"
     INCOMING_MSG = "IncomingMsg"
     INCOMING_MSG_BUNCH = "IncomingMsgBunch"
+    INCOMING_MSG_IMPERSONATION = "IncomingMsgImpersonation"
+    INCOMING_MSG_REPORT = "IncomingMsgReport"
+    INCOMING_CALL = "IncomingCall"
+    INCOMING_CALL_ACCEPTED = "IncomingCallAccepted"
+    OUTGOING_CALL_ACCEPTED = "OutgoingCallAccepted"
+    CALL_ENDED = "CallEnded"
+    CALL_MISSED = "CallMissed"
     MSGS_NOTICED = "MsgsNoticed"
     MSG_DELIVERED = "MsgDelivered"
     MSG_FAILED = "MsgFailed"
     MSG_READ = "MsgRead"
     MSG_DELETED = "MsgDeleted"
+    MSG_STATE_CHANGED = "MsgStateChanged"
     CHAT_MODIFIED = "ChatModified"
     CHAT_EPHEMERAL_TIMER_MODIFIED = "ChatEphemeralTimerModified"
+    CHATS_AUTO_ARCHIVED = "ChatsAutoArchived"
     CONTACTS_CHANGED = "ContactsChanged"
     LOCATION_CHANGED = "LocationChanged"
     CONFIGURE_PROGRESS = "ConfigureProgress"
     IMEX_PROGRESS = "ImexProgress"
+    IMEX_PROGRESS_DETAIL = "ImexProgressDetail"
     IMEX_FILE_WRITTEN = "ImexFileWritten"
+    HOUSEKEEPING_PROGRESS = "HousekeepingProgress"
+    IMAGE_RECODE_PROGRESS = "ImageRecodeProgress"
+    ATTACHMENT_STAGING_PROGRESS = "AttachmentStagingProgress"
     SECUREJOIN_INVITER_PROGRESS = "SecurejoinInviterProgress"
     SECUREJOIN_JOINER_PROGRESS = "SecurejoinJoinerProgress"
     CONNECTIVITY_CHANGED = "ConnectivityChanged"
+    PROVIDER_INCIDENT = "ProviderIncident"
+    ACCOUNT_HEALTH_WARNING = "AccountHealthWarning"
+    SERVER_CAPABILITIES_CHANGED = "ServerCapabilitiesChanged"
+    OWNERSHIP_CHANGED = "OwnershipChanged"
     SELFAVATAR_CHANGED = "SelfavatarChanged"
     WEBXDC_STATUS_UPDATE = "WebxdcStatusUpdate"
     WEBXDC_INSTANCE_DELETED = "WebxdcInstanceDeleted"
     CHATLIST_CHANGED = "ChatlistChanged"
     CHATLIST_ITEM_CHANGED = "ChatlistItemChanged"
+    CHATLIST_ITEM_MOVED = "ChatlistItemMoved"
+    CHATLIST_ITEM_REMOVED = "ChatlistItemRemoved"
+    CHATLIST_ITEM_FRESH_MSG_COUNT_CHANGED = "ChatlistItemFreshMsgCountChanged"
     ACCOUNTS_CHANGED = "AccountsChanged"
     ACCOUNTS_ITEM_CHANGED = "AccountsItemChanged"
     CONFIG_SYNCED = "ConfigSynced"
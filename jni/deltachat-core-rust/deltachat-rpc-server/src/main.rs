@@ -12,7 +12,6 @@
 use futures_lite::stream::StreamExt;
 use tokio::io::{self, AsyncBufReadExt, BufReader};
 use tracing_subscriber::EnvFilter;
-use yerpc::RpcServer as _;
 
 #[cfg(target_family = "unix")]
 use tokio::signal::unix as signal_unix;
@@ -49,7 +48,10 @@ async fn main_impl() -> Result<()> {
             if let Some(arg) = args.next() {
                 return Err(anyhow!("Unrecognized argument {:?}", arg));
             }
-            println!("{}", CommandApi::openrpc_specification()?);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&CommandApi::openrpc_document()?)?
+            );
             return Ok(());
         } else {
             return Err(anyhow!("Unrecognized option {:?}", first_arg));
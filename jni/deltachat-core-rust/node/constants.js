@@ -1,6 +1,11 @@
 // Generated!
 
 module.exports = {
+  DC_CALL_STATE_ACTIVE: 1,
+  DC_CALL_STATE_DECLINED: 2,
+  DC_CALL_STATE_ENDED: 3,
+  DC_CALL_STATE_MISSED: 4,
+  DC_CALL_STATE_PENDING: 0,
   DC_CERTCK_ACCEPT_INVALID: 2,
   DC_CERTCK_ACCEPT_INVALID_CERTIFICATES: 3,
   DC_CERTCK_AUTO: 0,
@@ -25,6 +30,8 @@ module.exports = {
   DC_CONTACT_ID_INFO: 2,
   DC_CONTACT_ID_LAST_SPECIAL: 9,
   DC_CONTACT_ID_SELF: 1,
+  DC_CONTACT_SORT_LAST_SEEN: 0,
+  DC_CONTACT_SORT_NAME: 1,
   DC_DOWNLOAD_AVAILABLE: 10,
   DC_DOWNLOAD_DONE: 0,
   DC_DOWNLOAD_FAILURE: 20,
@@ -33,9 +40,17 @@ module.exports = {
   DC_EVENT_ACCOUNTS_BACKGROUND_FETCH_DONE: 2200,
   DC_EVENT_ACCOUNTS_CHANGED: 2302,
   DC_EVENT_ACCOUNTS_ITEM_CHANGED: 2303,
+  DC_EVENT_ACCOUNT_HEALTH_WARNING: 2102,
+  DC_EVENT_ATTACHMENT_STAGING_PROGRESS: 2057,
+  DC_EVENT_CALL_ENDED: 2263,
+  DC_EVENT_CALL_MISSED: 2264,
   DC_EVENT_CHANNEL_OVERFLOW: 2400,
   DC_EVENT_CHATLIST_CHANGED: 2300,
   DC_EVENT_CHATLIST_ITEM_CHANGED: 2301,
+  DC_EVENT_CHATLIST_ITEM_FRESH_MSG_CNT_CHANGED: 2306,
+  DC_EVENT_CHATLIST_ITEM_MOVED: 2304,
+  DC_EVENT_CHATLIST_ITEM_REMOVED: 2305,
+  DC_EVENT_CHATS_AUTO_ARCHIVED: 2022,
   DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED: 2021,
   DC_EVENT_CHAT_MODIFIED: 2020,
   DC_EVENT_CONFIGURE_PROGRESS: 2041,
@@ -45,14 +60,21 @@ module.exports = {
   DC_EVENT_DELETED_BLOB_FILE: 151,
   DC_EVENT_ERROR: 400,
   DC_EVENT_ERROR_SELF_NOT_IN_GROUP: 410,
+  DC_EVENT_HOUSEKEEPING_PROGRESS: 2055,
+  DC_EVENT_IMAGE_RECODE_PROGRESS: 2056,
   DC_EVENT_IMAP_CONNECTED: 102,
   DC_EVENT_IMAP_INBOX_IDLE: 106,
   DC_EVENT_IMAP_MESSAGE_DELETED: 104,
   DC_EVENT_IMAP_MESSAGE_MOVED: 105,
   DC_EVENT_IMEX_FILE_WRITTEN: 2052,
   DC_EVENT_IMEX_PROGRESS: 2051,
+  DC_EVENT_IMEX_PROGRESS_DETAIL: 2053,
+  DC_EVENT_INCOMING_CALL: 2260,
+  DC_EVENT_INCOMING_CALL_ACCEPTED: 2261,
   DC_EVENT_INCOMING_MSG: 2005,
   DC_EVENT_INCOMING_MSG_BUNCH: 2006,
+  DC_EVENT_INCOMING_MSG_IMPERSONATION: 2007,
+  DC_EVENT_INCOMING_MSG_REPORT: 2004,
   DC_EVENT_INCOMING_REACTION: 2002,
   DC_EVENT_INCOMING_WEBXDC_NOTIFY: 2003,
   DC_EVENT_INFO: 100,
@@ -63,11 +85,16 @@ module.exports = {
   DC_EVENT_MSG_DELIVERED: 2010,
   DC_EVENT_MSG_FAILED: 2012,
   DC_EVENT_MSG_READ: 2015,
+  DC_EVENT_MSG_STATE_CHANGED: 2017,
   DC_EVENT_NEW_BLOB_FILE: 150,
+  DC_EVENT_OUTGOING_CALL_ACCEPTED: 2262,
+  DC_EVENT_OWNERSHIP_CHANGED: 2104,
+  DC_EVENT_PROVIDER_INCIDENT: 2101,
   DC_EVENT_REACTIONS_CHANGED: 2001,
   DC_EVENT_SECUREJOIN_INVITER_PROGRESS: 2060,
   DC_EVENT_SECUREJOIN_JOINER_PROGRESS: 2061,
   DC_EVENT_SELFAVATAR_CHANGED: 2110,
+  DC_EVENT_SERVER_CAPABILITIES_CHANGED: 2103,
   DC_EVENT_SMTP_CONNECTED: 101,
   DC_EVENT_SMTP_MESSAGE_SENT: 103,
   DC_EVENT_WARNING: 300,
@@ -80,13 +107,23 @@ module.exports = {
   DC_GCL_ARCHIVED_ONLY: 1,
   DC_GCL_FOR_FORWARDING: 8,
   DC_GCL_NO_SPECIALS: 2,
+  DC_GCL_SORT_BY_NAME: 16,
   DC_GCL_VERIFIED_ONLY: 1,
+  DC_GCL_WITH_CHAT_ONLY: 4,
   DC_GCM_ADDDAYMARKER: 1,
   DC_GCM_INFO_ONLY: 2,
+  DC_HEALTH_AUTH_FAILURES: 1,
+  DC_HEALTH_BOUNCES: 5,
+  DC_HEALTH_CERTIFICATE_EXPIRY: 4,
+  DC_HEALTH_MAILBOX_NEARLY_FULL: 3,
+  DC_HEALTH_QUOTA_WARNING: 2,
   DC_IMEX_EXPORT_BACKUP: 11,
+  DC_IMEX_EXPORT_IDENTITY: 21,
   DC_IMEX_EXPORT_SELF_KEYS: 1,
   DC_IMEX_IMPORT_BACKUP: 12,
+  DC_IMEX_IMPORT_IDENTITY: 22,
   DC_IMEX_IMPORT_SELF_KEYS: 2,
+  DC_IMEX_MERGE_BACKUP: 13,
   DC_INFO_AUTOCRYPT_SETUP_MESSAGE: 6,
   DC_INFO_EPHEMERAL_TIMER_CHANGED: 10,
   DC_INFO_GROUP_IMAGE_CHANGED: 3,
@@ -107,6 +144,10 @@ module.exports = {
   DC_KEY_GEN_RSA4096: 3,
   DC_LP_AUTH_NORMAL: 4,
   DC_LP_AUTH_OAUTH2: 2,
+  DC_MDN_POLICY_ALWAYS: 1,
+  DC_MDN_POLICY_DEFAULT: 0,
+  DC_MDN_POLICY_NEVER: 2,
+  DC_MDN_POLICY_VERIFIED_ONLY: 3,
   DC_MEDIA_QUALITY_BALANCED: 0,
   DC_MEDIA_QUALITY_WORSE: 1,
   DC_MSG_AUDIO: 40,
@@ -123,6 +164,11 @@ module.exports = {
   DC_MSG_VIDEOCHAT_INVITATION: 70,
   DC_MSG_VOICE: 41,
   DC_MSG_WEBXDC: 80,
+  DC_NETWORK_CELLULAR: 3,
+  DC_NETWORK_NONE: 1,
+  DC_NETWORK_UNKNOWN: 0,
+  DC_NETWORK_VPN: 4,
+  DC_NETWORK_WIFI: 2,
   DC_PROVIDER_STATUS_BROKEN: 3,
   DC_PROVIDER_STATUS_OK: 1,
   DC_PROVIDER_STATUS_PREPARATION: 2,
@@ -148,6 +194,9 @@ module.exports = {
   DC_QR_WEBRTC_INSTANCE: 260,
   DC_QR_WITHDRAW_VERIFYCONTACT: 500,
   DC_QR_WITHDRAW_VERIFYGROUP: 502,
+  DC_SAVE_FILE_AUTO_NUMBER: 2,
+  DC_SAVE_FILE_ERROR: 0,
+  DC_SAVE_FILE_OVERWRITE: 1,
   DC_SHOW_EMAILS_ACCEPTED_CONTACTS: 1,
   DC_SHOW_EMAILS_ALL: 2,
   DC_SHOW_EMAILS_OFF: 0,
@@ -165,6 +214,9 @@ module.exports = {
   DC_STATE_OUT_PENDING: 20,
   DC_STATE_OUT_PREPARING: 18,
   DC_STATE_UNDEFINED: 0,
+  DC_STR_ACTIVITY_BACKUP_EXPORTED: 199,
+  DC_STR_ACTIVITY_CONFIGURED: 198,
+  DC_STR_ACTIVITY_CONFIG_CHANGED: 201,
   DC_STR_AC_SETUP_MSG_BODY: 43,
   DC_STR_AC_SETUP_MSG_SUBJECT: 42,
   DC_STR_ADD_MEMBER_BY_OTHER: 129,
@@ -244,14 +296,22 @@ module.exports = {
   DC_STR_GROUP_NAME_CHANGED_BY_OTHER: 125,
   DC_STR_GROUP_NAME_CHANGED_BY_YOU: 124,
   DC_STR_IMAGE: 9,
+  DC_STR_INCOMING_CALL: 195,
   DC_STR_INCOMING_MESSAGES: 103,
   DC_STR_INVALID_UNENCRYPTED_MAIL: 174,
+  DC_STR_IO_PAUSED: 202,
+  DC_STR_I_ADDED_MEMBER: 164,
+  DC_STR_I_LEFT_GROUP: 166,
+  DC_STR_I_REMOVED_MEMBER: 165,
   DC_STR_LAST_MSG_SENT_SUCCESSFULLY: 111,
   DC_STR_LOCATION: 66,
   DC_STR_LOCATION_ENABLED_BY_OTHER: 137,
   DC_STR_LOCATION_ENABLED_BY_YOU: 136,
+  DC_STR_MAILBOX: 193,
+  DC_STR_MEMBER_CHANGES_DIGEST: 194,
   DC_STR_MESSAGES: 114,
   DC_STR_MESSAGE_ADD_MEMBER: 173,
+  DC_STR_MISSED_CALL: 197,
   DC_STR_MSGACTIONBYME: 63,
   DC_STR_MSGACTIONBYUSER: 62,
   DC_STR_MSGADDMEMBER: 17,
@@ -267,6 +327,7 @@ module.exports = {
   DC_STR_NOT_CONNECTED: 121,
   DC_STR_NOT_SUPPORTED_BY_PROVIDER: 113,
   DC_STR_ONE_MOMENT: 106,
+  DC_STR_OUTGOING_CALL: 196,
   DC_STR_OUTGOING_MESSAGES: 104,
   DC_STR_PARTIAL_DOWNLOAD_MSG_BODY: 99,
   DC_STR_PART_OF_TOTAL_USED: 116,
@@ -278,6 +339,7 @@ module.exports = {
   DC_STR_REMOVE_MEMBER_BY_YOU: 130,
   DC_STR_REPLY_NOUN: 90,
   DC_STR_SAVED_MESSAGES: 69,
+  DC_STR_SECUREJOIN_MSG_BODY: 192,
   DC_STR_SECUREJOIN_WAIT: 190,
   DC_STR_SECUREJOIN_WAIT_TIMEOUT: 191,
   DC_STR_SECURE_JOIN_GROUP_QR_DESC: 120,
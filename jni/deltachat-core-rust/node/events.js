@@ -18,23 +18,35 @@ module.exports = {
   2001: 'DC_EVENT_REACTIONS_CHANGED',
   2002: 'DC_EVENT_INCOMING_REACTION',
   2003: 'DC_EVENT_INCOMING_WEBXDC_NOTIFY',
+  2004: 'DC_EVENT_INCOMING_MSG_REPORT',
   2005: 'DC_EVENT_INCOMING_MSG',
   2006: 'DC_EVENT_INCOMING_MSG_BUNCH',
+  2007: 'DC_EVENT_INCOMING_MSG_IMPERSONATION',
   2008: 'DC_EVENT_MSGS_NOTICED',
   2010: 'DC_EVENT_MSG_DELIVERED',
   2012: 'DC_EVENT_MSG_FAILED',
   2015: 'DC_EVENT_MSG_READ',
   2016: 'DC_EVENT_MSG_DELETED',
+  2017: 'DC_EVENT_MSG_STATE_CHANGED',
   2020: 'DC_EVENT_CHAT_MODIFIED',
   2021: 'DC_EVENT_CHAT_EPHEMERAL_TIMER_MODIFIED',
+  2022: 'DC_EVENT_CHATS_AUTO_ARCHIVED',
   2030: 'DC_EVENT_CONTACTS_CHANGED',
   2035: 'DC_EVENT_LOCATION_CHANGED',
   2041: 'DC_EVENT_CONFIGURE_PROGRESS',
   2051: 'DC_EVENT_IMEX_PROGRESS',
   2052: 'DC_EVENT_IMEX_FILE_WRITTEN',
+  2053: 'DC_EVENT_IMEX_PROGRESS_DETAIL',
+  2055: 'DC_EVENT_HOUSEKEEPING_PROGRESS',
+  2056: 'DC_EVENT_IMAGE_RECODE_PROGRESS',
+  2057: 'DC_EVENT_ATTACHMENT_STAGING_PROGRESS',
   2060: 'DC_EVENT_SECUREJOIN_INVITER_PROGRESS',
   2061: 'DC_EVENT_SECUREJOIN_JOINER_PROGRESS',
   2100: 'DC_EVENT_CONNECTIVITY_CHANGED',
+  2101: 'DC_EVENT_PROVIDER_INCIDENT',
+  2102: 'DC_EVENT_ACCOUNT_HEALTH_WARNING',
+  2103: 'DC_EVENT_SERVER_CAPABILITIES_CHANGED',
+  2104: 'DC_EVENT_OWNERSHIP_CHANGED',
   2110: 'DC_EVENT_SELFAVATAR_CHANGED',
   2111: 'DC_EVENT_CONFIG_SYNCED',
   2120: 'DC_EVENT_WEBXDC_STATUS_UPDATE',
@@ -42,9 +54,17 @@ module.exports = {
   2150: 'DC_EVENT_WEBXDC_REALTIME_DATA',
   2151: 'DC_EVENT_WEBXDC_REALTIME_ADVERTISEMENT',
   2200: 'DC_EVENT_ACCOUNTS_BACKGROUND_FETCH_DONE',
+  2260: 'DC_EVENT_INCOMING_CALL',
+  2261: 'DC_EVENT_INCOMING_CALL_ACCEPTED',
+  2262: 'DC_EVENT_OUTGOING_CALL_ACCEPTED',
+  2263: 'DC_EVENT_CALL_ENDED',
+  2264: 'DC_EVENT_CALL_MISSED',
   2300: 'DC_EVENT_CHATLIST_CHANGED',
   2301: 'DC_EVENT_CHATLIST_ITEM_CHANGED',
   2302: 'DC_EVENT_ACCOUNTS_CHANGED',
   2303: 'DC_EVENT_ACCOUNTS_ITEM_CHANGED',
+  2304: 'DC_EVENT_CHATLIST_ITEM_MOVED',
+  2305: 'DC_EVENT_CHATLIST_ITEM_REMOVED',
+  2306: 'DC_EVENT_CHATLIST_ITEM_FRESH_MSG_CNT_CHANGED',
   2400: 'DC_EVENT_CHANNEL_OVERFLOW'
 }
@@ -51,6 +51,8 @@ def process_opt(data):
         value = str(opt_data[key])
         if key == "max_smtp_rcpt_to":
             value = "Some(" + value + ")"
+        if key == "status_url":
+            value = 'Some("' + value + '")'
         if value in {"True", "False"}:
             value = value.lower()
         opt += "        " + key + ": " + value + ",\n"
@@ -7,6 +7,7 @@
 use anyhow::{ensure, Context as _, Result};
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use rand::{Rng, RngCore};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
@@ -20,6 +21,8 @@
 
 use crate::context::{Context, ContextBuilder};
 use crate::events::{Event, EventEmitter, EventType, Events};
+use crate::net::NetworkKind;
+use crate::pgp;
 use crate::push::PushSubscriber;
 use crate::stock_str::StockStrings;
 
@@ -47,33 +50,58 @@ pub struct Accounts {
 impl Accounts {
     /// Loads or creates an accounts folder at the given `dir`.
     pub async fn new(dir: PathBuf, writable: bool) -> Result<Self> {
+        Accounts::new_ex(dir, writable, None).await
+    }
+
+    /// Loads or creates an accounts folder at the given `dir`
+    /// with the account manager configuration encrypted using `passphrase`.
+    ///
+    /// If the configuration already exists, `passphrase` is used to decrypt it.
+    /// Account databases which cannot be opened without a passphrase
+    /// are opened using the same `passphrase`.
+    /// The number of accounts is hidden by padding the account directories with decoys.
+    pub async fn new_encrypted(dir: PathBuf, writable: bool, passphrase: String) -> Result<Self> {
+        ensure!(!passphrase.is_empty(), "passphrase must not be empty");
+        Accounts::new_ex(dir, writable, Some(passphrase)).await
+    }
+
+    async fn new_ex(dir: PathBuf, writable: bool, passphrase: Option<String>) -> Result<Self> {
         if writable && !dir.exists() {
-            Accounts::create(&dir).await?;
+            Accounts::create(&dir, passphrase.clone()).await?;
         }
 
-        Accounts::open(dir, writable).await
+        Accounts::open(dir, writable, passphrase).await
+    }
+
+    /// Returns true if the account manager configuration in `dir` is encrypted,
+    /// i.e. the account manager must be created with [`Accounts::new_encrypted`].
+    pub async fn is_config_encrypted(dir: &Path) -> Result<bool> {
+        let bytes = fs::read(dir.join(CONFIG_NAME))
+            .await
+            .context("failed to read accounts config")?;
+        Ok(is_encrypted_config(&bytes))
     }
 
     /// Creates a new default structure.
-    async fn create(dir: &Path) -> Result<()> {
+    async fn create(dir: &Path, passphrase: Option<String>) -> Result<()> {
         fs::create_dir_all(dir)
             .await
             .context("failed to create folder")?;
 
-        Config::new(dir).await?;
+        Config::new(dir, passphrase).await?;
 
         Ok(())
     }
 
     /// Opens an existing accounts structure. Will error if the folder doesn't exist,
     /// no account exists and no config exists.
-    async fn open(dir: PathBuf, writable: bool) -> Result<Self> {
+    async fn open(dir: PathBuf, writable: bool, passphrase: Option<String>) -> Result<Self> {
         ensure!(dir.exists(), "directory does not exist");
 
         let config_file = dir.join(CONFIG_NAME);
         ensure!(config_file.exists(), "{:?} does not exist", config_file);
 
-        let config = Config::from_file(config_file, writable)
+        let config = Config::from_file(config_file, writable, passphrase)
             .await
             .context("failed to load accounts config")?;
         let events = Events::new();
@@ -265,6 +293,117 @@ pub async fn migrate_account(&mut self, dbfile: PathBuf) -> Result<u32> {
         }
     }
 
+    /// Moves the account `id` out of this account manager into the directory `path`.
+    ///
+    /// The directory must not exist yet.
+    /// It contains the database and the blobs of the account
+    /// and can be added to another account manager with [`Accounts::import_account`],
+    /// the account keeps its UUID.
+    /// Like [`Accounts::migrate_account`], the files are moved and not copied,
+    /// so `path` must be on the same filesystem as the account manager directory.
+    pub async fn export_account(&mut self, id: u32, path: &Path) -> Result<()> {
+        let cfg = self
+            .config
+            .get_account(id)
+            .with_context(|| format!("no account with id {id}"))?;
+        ensure!(!path.exists(), "{} already exists", path.display());
+        let ctx = self
+            .accounts
+            .remove(&id)
+            .with_context(|| format!("no account with id {id}"))?;
+        ctx.stop_io().await;
+        // Close the database so the files are not in use while moving them.
+        ctx.sql.close().await;
+        drop(ctx);
+
+        // Write the metadata before moving the account,
+        // so that a failure leaves the account in this account manager.
+        let account_path = self.dir.join(&cfg.dir);
+        let metadata_file = account_path.join(EXPORTED_ACCOUNT_NAME);
+        let exported = ExportedAccount { uuid: cfg.uuid };
+        let res = async {
+            let metadata = toml::to_string_pretty(&exported)?;
+            fs::write(&metadata_file, metadata)
+                .await
+                .context("failed to write account metadata")?;
+            try_many_times(|| fs::rename(&account_path, path))
+                .await
+                .context("failed to move account data")
+        }
+        .await;
+        if let Err(err) = res {
+            fs::remove_file(&metadata_file).await.ok();
+            self.reopen_account(&cfg).await?;
+            return Err(err);
+        }
+
+        self.config.remove_account(id).await?;
+        self.emit_event(EventType::AccountsChanged);
+        Ok(())
+    }
+
+    /// Adds the account exported with [`Accounts::export_account`] to this account manager.
+    ///
+    /// The account data is moved from `path` into the account manager directory.
+    /// Fails if the account manager already contains an account with the same UUID.
+    ///
+    /// Returns the ID of the new account.
+    pub async fn import_account(&mut self, path: &Path) -> Result<u32> {
+        let metadata_file = path.join(EXPORTED_ACCOUNT_NAME);
+        let metadata = fs::read_to_string(&metadata_file)
+            .await
+            .with_context(|| format!("no exported account found at {}", path.display()))?;
+        let exported: ExportedAccount =
+            toml::from_str(&metadata).context("failed to parse account metadata")?;
+        ensure!(
+            path.join(DB_NAME).exists(),
+            "no database found in {}",
+            path.display()
+        );
+        ensure!(
+            !self.config.has_uuid(exported.uuid),
+            "account {} already exists",
+            exported.uuid
+        );
+
+        let old_id = self.config.get_selected_account();
+        let account_config = self
+            .config
+            .new_account_with_uuid(exported.uuid)
+            .await
+            .context("failed to create new account")?;
+        let account_path = self.dir.join(&account_config.dir);
+        if let Err(err) = try_many_times(|| fs::rename(path, &account_path)).await {
+            self.config.remove_account(account_config.id).await?;
+            self.select_account(old_id).await.ok();
+            return Err(err).context("failed to move account data");
+        }
+        // The account is moved already, a leftover metadata file does no harm.
+        if let Err(err) = fs::remove_file(account_path.join(EXPORTED_ACCOUNT_NAME)).await {
+            warn!(self, "Failed to remove account metadata: {err:#}.");
+        }
+
+        self.reopen_account(&account_config).await?;
+        self.emit_event(EventType::AccountsChanged);
+        Ok(account_config.id)
+    }
+
+    /// Opens the existing account `cfg` and adds it to the loaded accounts.
+    async fn reopen_account(&mut self, cfg: &AccountConfig) -> Result<()> {
+        let ctx = ContextBuilder::new(cfg.dbfile(&self.dir))
+            .with_id(cfg.id)
+            .with_events(self.events.clone())
+            .with_stock_strings(self.stockstrings.clone())
+            .with_push_subscriber(self.push_subscriber.clone())
+            .build()
+            .await?;
+        // Try to open without a passphrase,
+        // but do not return an error if account is passphare-protected.
+        ctx.open("".to_string()).await?;
+        self.accounts.insert(cfg.id, ctx);
+        Ok(())
+    }
+
     /// Get a list of all account ids.
     pub fn get_all(&self) -> Vec<u32> {
         self.accounts.keys().copied().collect()
@@ -294,6 +433,33 @@ pub async fn maybe_network(&self) {
         }
     }
 
+    /// Notifies all accounts that the device switched to a network of class `kind`.
+    ///
+    /// See [`Context::on_network_changed`].
+    pub async fn on_network_changed(&self, kind: NetworkKind) {
+        for account in self.accounts.values() {
+            account.on_network_changed(kind).await;
+        }
+    }
+
+    /// Tells all accounts whether the app is in the foreground.
+    ///
+    /// See [`Context::set_foreground`].
+    pub async fn set_foreground(&self, foreground: bool) {
+        for account in self.accounts.values() {
+            account.set_foreground(foreground).await;
+        }
+    }
+
+    /// Sets the identity of the network the device is connected to for all accounts.
+    ///
+    /// See [`Context::set_network_identity`].
+    pub fn set_network_identity(&self, identity: Option<&str>) {
+        for account in self.accounts.values() {
+            account.set_network_identity(identity);
+        }
+    }
+
     /// Notifies all accounts that the network connection may have been lost.
     pub async fn maybe_network_lost(&self) {
         for account in self.accounts.values() {
@@ -368,6 +534,23 @@ pub fn emit_event(&self, event: EventType) {
         self.events.emit(Event { id: 0, typ: event })
     }
 
+    /// Encrypts the account manager configuration with `passphrase`
+    /// or stores it unencrypted if `passphrase` is `None`.
+    ///
+    /// This does not change the passphrases of the account databases.
+    pub async fn set_config_passphrase(&mut self, passphrase: Option<String>) -> Result<()> {
+        if let Some(passphrase) = &passphrase {
+            ensure!(!passphrase.is_empty(), "passphrase must not be empty");
+        }
+        self.config.passphrase = passphrase;
+        self.config.sync().await
+    }
+
+    /// Returns true if the account manager configuration is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.config.passphrase.is_some()
+    }
+
     /// Returns event emitter.
     pub fn get_event_emitter(&self) -> EventEmitter {
         self.events.get_emitter()
@@ -390,11 +573,26 @@ pub async fn set_push_device_token(&self, token: &str) -> Result<()> {
 /// Database file name.
 const DB_NAME: &str = "dc.db";
 
+/// Name of the metadata file of accounts exported with [`Accounts::export_account`].
+const EXPORTED_ACCOUNT_NAME: &str = "account.toml";
+
+/// If the configuration is encrypted,
+/// the number of account directories is padded to a multiple of this
+/// with decoys, see [`Config::pad_account_dirs`].
+const ACCOUNT_DIRS_PADDING: usize = 4;
+
+/// Returns true if the account manager configuration file contents are encrypted.
+fn is_encrypted_config(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"-----BEGIN PGP MESSAGE-----")
+}
+
 /// Account manager configuration file.
 #[derive(Debug)]
 struct Config {
     file: PathBuf,
     inner: InnerConfig,
+    /// Passphrase the configuration file is encrypted with.
+    passphrase: Option<String>,
     // We lock the lockfile in the Config constructors to protect also from having multiple Config
     // objects for the same config file.
     lock_task: Option<JoinHandle<anyhow::Result<()>>>,
@@ -409,6 +607,10 @@ struct InnerConfig {
     pub selected_account: u32,
     pub next_id: u32,
     pub accounts: Vec<AccountConfig>,
+
+    /// Decoy account directories, relative to the account manager directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decoys: Vec<PathBuf>,
 }
 
 impl Drop for Config {
@@ -471,11 +673,13 @@ async fn new_nosync(file: PathBuf, lock: bool) -> Result<Self> {
             accounts: Vec::new(),
             selected_account: 0,
             next_id: 1,
+            decoys: Vec::new(),
         };
         if !lock {
             let cfg = Self {
                 file,
                 inner,
+                passphrase: None,
                 lock_task: None,
             };
             return Ok(cfg);
@@ -484,15 +688,19 @@ async fn new_nosync(file: PathBuf, lock: bool) -> Result<Self> {
         let cfg = Self {
             file,
             inner,
+            passphrase: None,
             lock_task,
         };
         Ok(cfg)
     }
 
     /// Creates a new configuration file in the given account manager directory.
-    pub async fn new(dir: &Path) -> Result<Self> {
+    ///
+    /// The file is encrypted if `passphrase` is set.
+    pub async fn new(dir: &Path, passphrase: Option<String>) -> Result<Self> {
         let lock = true;
         let mut cfg = Self::new_nosync(dir.join(CONFIG_NAME), lock).await?;
+        cfg.passphrase = passphrase;
         cfg.sync().await?;
 
         Ok(cfg)
@@ -509,11 +717,19 @@ async fn sync(&mut self) -> Result<()> {
             .context("Config is read-only")?
             .is_finished());
 
+        self.pad_account_dirs().await?;
+
         let tmp_path = self.file.with_extension("toml.tmp");
         let mut file = fs::File::create(&tmp_path)
             .await
             .context("failed to create a tmp config")?;
-        file.write_all(toml::to_string_pretty(&self.inner)?.as_bytes())
+        let mut data = toml::to_string_pretty(&self.inner)?;
+        if let Some(passphrase) = &self.passphrase {
+            data = pgp::symm_encrypt(passphrase, data.as_bytes())
+                .await
+                .context("failed to encrypt config")?;
+        }
+        file.write_all(data.as_bytes())
             .await
             .context("failed to write a tmp config")?;
         file.sync_data()
@@ -526,12 +742,72 @@ async fn sync(&mut self) -> Result<()> {
         Ok(())
     }
 
+    /// Adds or removes decoy account directories,
+    /// so that the number of account directories does not reveal the number of accounts
+    /// if the configuration is encrypted.
+    /// Decoys are removed if the configuration is not encrypted.
+    ///
+    /// Decoys contain random data in place of an encrypted account database.
+    async fn pad_account_dirs(&mut self) -> Result<()> {
+        let dir = self
+            .file
+            .parent()
+            .context("Cannot get config file directory")?
+            .to_path_buf();
+        let account_count = self.inner.accounts.len();
+        let decoy_count = match self.passphrase {
+            Some(_) => {
+                account_count.max(1).div_ceil(ACCOUNT_DIRS_PADDING) * ACCOUNT_DIRS_PADDING
+                    - account_count
+            }
+            None => 0,
+        };
+        while self.inner.decoys.len() > decoy_count {
+            if let Some(decoy) = self.inner.decoys.pop() {
+                try_many_times(|| fs::remove_dir_all(dir.join(&decoy)))
+                    .await
+                    .context("failed to remove decoy account directory")?;
+            }
+        }
+        while self.inner.decoys.len() < decoy_count {
+            let decoy = PathBuf::from(Uuid::new_v4().to_string());
+            let dbfile = dir.join(&decoy).join(DB_NAME);
+            fs::create_dir_all(Context::derive_blobdir(&dbfile))
+                .await
+                .context("failed to create decoy account directory")?;
+            let data = {
+                let mut rng = rand::thread_rng();
+                let mut data = vec![0; rng.gen_range(8..32) * 4096];
+                rng.fill_bytes(&mut data);
+                data
+            };
+            fs::write(&dbfile, data)
+                .await
+                .context("failed to write decoy account database")?;
+            self.inner.decoys.push(decoy);
+        }
+        Ok(())
+    }
+
     /// Read a configuration from the given file into memory.
-    pub async fn from_file(file: PathBuf, writable: bool) -> Result<Self> {
+    ///
+    /// `passphrase` is required if the file is encrypted and ignored otherwise.
+    pub async fn from_file(
+        file: PathBuf,
+        writable: bool,
+        passphrase: Option<String>,
+    ) -> Result<Self> {
         let mut config = Self::new_nosync(file, writable).await?;
-        let bytes = fs::read(&config.file)
+        let mut bytes = fs::read(&config.file)
             .await
             .context("Failed to read file")?;
+        if is_encrypted_config(&bytes) {
+            let passphrase = passphrase.context("Config is encrypted, passphrase required")?;
+            bytes = pgp::symm_decrypt(&passphrase, std::io::Cursor::new(bytes))
+                .await
+                .context("Failed to decrypt config")?;
+            config.passphrase = Some(passphrase);
+        }
         let s = std::str::from_utf8(&bytes)?;
         config.inner = toml::from_str(s).context("Failed to parse config")?;
 
@@ -580,7 +856,12 @@ pub async fn load_accounts(
                 .with_context(|| format!("failed to create context from file {:?}", &dbfile))?;
             // Try to open without a passphrase,
             // but do not return an error if account is passphare-protected.
-            ctx.open("".to_string()).await?;
+            if !ctx.open("".to_string()).await? {
+                if let Some(passphrase) = &self.passphrase {
+                    // Account databases may share the passphrase with the configuration.
+                    ctx.open(passphrase.clone()).await?;
+                }
+            }
 
             accounts.insert(account_config.id, ctx);
         }
@@ -590,9 +871,13 @@ pub async fn load_accounts(
 
     /// Creates a new account in the account manager directory.
     async fn new_account(&mut self) -> Result<AccountConfig> {
+        self.new_account_with_uuid(Uuid::new_v4()).await
+    }
+
+    /// Creates a new account with the given UUID in the account manager directory.
+    async fn new_account_with_uuid(&mut self, uuid: Uuid) -> Result<AccountConfig> {
         let id = {
             let id = self.inner.next_id;
-            let uuid = Uuid::new_v4();
             let target_dir = PathBuf::from(uuid.to_string());
 
             self.inner.accounts.push(AccountConfig {
@@ -641,6 +926,11 @@ fn get_account(&self, id: u32) -> Option<AccountConfig> {
         self.inner.accounts.iter().find(|e| e.id == id).cloned()
     }
 
+    /// Returns true if there is an account with the given UUID.
+    fn has_uuid(&self, uuid: Uuid) -> bool {
+        self.inner.accounts.iter().any(|e| e.uuid == uuid)
+    }
+
     /// Returns the ID of selected account.
     pub fn get_selected_account(&self) -> u32 {
         self.inner.selected_account
@@ -694,6 +984,13 @@ async fn try_many_times<F, Fut, T>(f: F) -> std::result::Result<(), T>
     Ok(())
 }
 
+/// Metadata of an account exported with [`Accounts::export_account`].
+#[derive(Serialize, Deserialize, Debug)]
+struct ExportedAccount {
+    /// Universally unique account identifier.
+    uuid: Uuid,
+}
+
 /// Configuration of a single account.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 struct AccountConfig {
@@ -845,6 +1142,59 @@ async fn test_migrate_account() {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_import_account() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut accounts1 = Accounts::new(dir.path().join("accounts1"), true).await?;
+        let mut accounts2 = Accounts::new(dir.path().join("accounts2"), true).await?;
+        accounts2.add_account().await?;
+
+        let id = accounts1.add_account().await?;
+        let ctx = accounts1.get_account(id).context("no account")?;
+        ctx.set_config(crate::config::Config::Addr, Some("me@mail.com"))
+            .await?;
+        drop(ctx);
+        let uuid = accounts1.config.get_account(id).context("no config")?.uuid;
+
+        let path = dir.path().join("exported");
+        accounts1.export_account(id, &path).await?;
+        assert!(accounts1.get_account(id).is_none());
+        assert!(accounts1.config.get_account(id).is_none());
+        // Exporting to an existing path fails.
+        let id1 = accounts1.add_account().await?;
+        assert!(accounts1.export_account(id1, &path).await.is_err());
+        assert!(accounts1.get_account(id1).is_some());
+
+        let imported_id = accounts2.import_account(&path).await?;
+        assert!(!path.exists());
+        assert_eq!(accounts2.get_selected_account_id(), Some(imported_id));
+        let ctx = accounts2.get_account(imported_id).context("no account")?;
+        assert_eq!(
+            ctx.get_config(crate::config::Config::Addr).await?,
+            Some("me@mail.com".to_string())
+        );
+        assert_eq!(
+            accounts2
+                .config
+                .get_account(imported_id)
+                .context("no config")?
+                .uuid,
+            uuid
+        );
+
+        // Importing an account with the same UUID again fails.
+        fs::create_dir(&path).await?;
+        fs::write(path.join(DB_NAME), b"").await?;
+        fs::write(
+            path.join(EXPORTED_ACCOUNT_NAME),
+            toml::to_string(&ExportedAccount { uuid })?,
+        )
+        .await?;
+        assert!(accounts2.import_account(&path).await.is_err());
+        assert_eq!(accounts2.get_all().len(), 2);
+        Ok(())
+    }
+
     /// Tests that accounts are sorted by ID.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_accounts_sorted() {
@@ -1021,6 +1371,84 @@ async fn test_encrypted_account() -> Result<()> {
         Ok(())
     }
 
+    async fn count_account_dirs(dir: &Path) -> Result<usize> {
+        let mut count = 0;
+        let mut entries = fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_encrypted_config() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let p: PathBuf = dir.path().join("accounts");
+
+        assert!(Accounts::new_encrypted(p.clone(), true, String::new())
+            .await
+            .is_err());
+        assert!(!p.exists());
+
+        let mut accounts = Accounts::new_encrypted(p.clone(), true, "foobar".to_string()).await?;
+        assert!(accounts.is_encrypted());
+        let account_id = accounts.add_closed_account().await?;
+        let account = accounts.get_account(account_id).context("no account")?;
+        assert!(account.open("foobar".to_string()).await?);
+        let config_bytes = fs::read(p.join(CONFIG_NAME)).await?;
+        assert!(!String::from_utf8_lossy(&config_bytes).contains("selected_account"));
+        let account_dir = accounts
+            .config
+            .get_account(account_id)
+            .context("no config")?
+            .dir;
+        assert!(!String::from_utf8_lossy(&config_bytes).contains(&*account_dir.to_string_lossy()));
+
+        // Account directories are padded with decoys.
+        assert_eq!(count_account_dirs(&p).await?, ACCOUNT_DIRS_PADDING);
+        for _ in 0..ACCOUNT_DIRS_PADDING {
+            accounts.add_closed_account().await?;
+        }
+        assert_eq!(count_account_dirs(&p).await?, 2 * ACCOUNT_DIRS_PADDING);
+        for id in accounts.get_all() {
+            if id != account_id {
+                accounts.remove_account(id).await?;
+            }
+        }
+        assert_eq!(count_account_dirs(&p).await?, ACCOUNT_DIRS_PADDING);
+        drop(account);
+        drop(accounts);
+
+        assert!(Accounts::is_config_encrypted(&p).await?);
+        assert!(Accounts::new(p.clone(), false).await.is_err());
+        assert!(
+            Accounts::new_encrypted(p.clone(), false, "barfoo".to_string())
+                .await
+                .is_err()
+        );
+
+        // Account database is opened with the same passphrase.
+        let mut accounts = Accounts::new_encrypted(p.clone(), true, "foobar".to_string()).await?;
+        assert_eq!(accounts.get_all(), vec![account_id]);
+        let account = accounts.get_selected_account().context("no account")?;
+        assert!(account.is_open().await);
+
+        accounts.set_config_passphrase(None).await?;
+        assert!(!accounts.is_encrypted());
+        assert_eq!(count_account_dirs(&p).await?, 1);
+        drop(account);
+        drop(accounts);
+        assert!(!Accounts::is_config_encrypted(&p).await?);
+        let accounts = Accounts::new(p.clone(), false).await?;
+        assert_eq!(accounts.get_all(), vec![account_id]);
+        let account = accounts.get_selected_account().context("no account")?;
+        assert!(!account.is_open().await);
+
+        Ok(())
+    }
+
     /// Tests that accounts share stock string translations.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_accounts_share_translations() -> Result<()> {
@@ -0,0 +1,260 @@
+//! # Account activity log.
+//!
+//! The activity log is a chronological feed of significant events of the account
+//! to be displayed e.g. on a "security & activity" screen.
+//! It is mostly reconstructed from info messages and the audit log of config changes,
+//! only configurations and backups are recorded separately.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use num_traits::FromPrimitive;
+use serde::Serialize;
+
+use crate::chat::ChatId;
+use crate::constants::DC_CHAT_ID_LAST_SPECIAL;
+use crate::context::Context;
+use crate::message::MsgId;
+use crate::mimeparser::SystemMessage;
+use crate::param::Params;
+use crate::stock_str;
+use crate::tools::time;
+
+/// Number of recorded events kept.
+const MAX_EVENTS: u32 = 200;
+
+/// Kind of an [`ActivityEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, Serialize)]
+#[repr(u32)]
+pub enum ActivityKind {
+    /// A member was added to a group, this includes joining a group.
+    MemberAdded = 1,
+
+    /// A member was removed from a group, this includes leaving a group.
+    MemberRemoved = 2,
+
+    /// A group was renamed.
+    GroupNameChanged = 3,
+
+    /// The ephemeral timer of a chat was changed.
+    EphemeralTimerChanged = 4,
+
+    /// A chat became protected because its members were verified.
+    ProtectionEnabled = 5,
+
+    /// A chat lost its protection, e.g. because a verified key changed.
+    ProtectionDisabled = 6,
+
+    /// The account was configured successfully.
+    Configured = 7,
+
+    /// A backup was exported.
+    BackupExported = 8,
+
+    /// A config key was changed, see [`Context::get_config_changes`].
+    ConfigChanged = 9,
+}
+
+/// Entry of the activity log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActivityEntry {
+    /// Unix timestamp of the event.
+    pub timestamp: i64,
+
+    /// Kind of the event.
+    pub kind: ActivityKind,
+
+    /// Chat the event happened in, if any.
+    pub chat_id: Option<ChatId>,
+
+    /// Info message the entry is reconstructed from, if any.
+    pub msg_id: Option<MsgId>,
+
+    /// Human-readable description, the text of the info message if there is one.
+    pub text: String,
+}
+
+fn activity_kind(cmd: SystemMessage) -> Option<ActivityKind> {
+    match cmd {
+        SystemMessage::MemberAddedToGroup => Some(ActivityKind::MemberAdded),
+        SystemMessage::MemberRemovedFromGroup => Some(ActivityKind::MemberRemoved),
+        SystemMessage::GroupNameChanged => Some(ActivityKind::GroupNameChanged),
+        SystemMessage::EphemeralTimerChanged => Some(ActivityKind::EphemeralTimerChanged),
+        SystemMessage::ChatProtectionEnabled => Some(ActivityKind::ProtectionEnabled),
+        SystemMessage::ChatProtectionDisabled => Some(ActivityKind::ProtectionDisabled),
+        _ => None,
+    }
+}
+
+/// Records an event which is not reconstructed from other data,
+/// `arg` is the argument of the event's text, e.g. the configured address.
+pub(crate) async fn record(context: &Context, kind: ActivityKind, arg: &str) -> Result<()> {
+    context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute(
+                "INSERT INTO activity_events (timestamp, kind, arg) VALUES (?, ?, ?)",
+                (time(), kind as u32, arg),
+            )?;
+            transaction.execute(
+                "DELETE FROM activity_events WHERE id<=(SELECT MAX(id) FROM activity_events)-?",
+                (MAX_EVENTS,),
+            )?;
+            Ok(())
+        })
+        .await
+}
+
+/// Returns the activity log of the account starting at the `since` timestamp,
+/// oldest entries first.
+pub async fn get_activity_log(context: &Context, since: i64) -> Result<Vec<ActivityEntry>> {
+    let mut entries = context
+        .sql
+        .query_map(
+            "SELECT id, chat_id, timestamp, param, txt FROM msgs
+             WHERE chat_id>? AND timestamp>=? AND hidden=0
+             AND (param LIKE 'S=%' OR param LIKE '%' || char(10) || 'S=%')",
+            (DC_CHAT_ID_LAST_SPECIAL, since),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let chat_id: ChatId = row.get(1)?;
+                let timestamp: i64 = row.get(2)?;
+                let param: String = row.get(3)?;
+                let text: String = row.get(4)?;
+                Ok((msg_id, chat_id, timestamp, param, text))
+            },
+            |rows| {
+                let mut entries = Vec::new();
+                for row in rows {
+                    let (msg_id, chat_id, timestamp, param, text) = row?;
+                    let cmd = Params::from_str(&param).unwrap_or_default().get_cmd();
+                    if let Some(kind) = activity_kind(cmd) {
+                        entries.push(ActivityEntry {
+                            timestamp,
+                            kind,
+                            chat_id: Some(chat_id),
+                            msg_id: Some(msg_id),
+                            text,
+                        });
+                    }
+                }
+                Ok(entries)
+            },
+        )
+        .await?;
+
+    let events = context
+        .sql
+        .query_map(
+            "SELECT timestamp, kind, arg FROM activity_events WHERE timestamp>=?",
+            (since,),
+            |row| {
+                let timestamp: i64 = row.get(0)?;
+                let kind: u32 = row.get(1)?;
+                let arg: String = row.get(2)?;
+                Ok((timestamp, kind, arg))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    for (timestamp, kind, arg) in events {
+        let (kind, text) = match ActivityKind::from_u32(kind) {
+            Some(kind @ ActivityKind::Configured) => {
+                (kind, stock_str::activity_configured(context, &arg).await)
+            }
+            Some(kind @ ActivityKind::BackupExported) => {
+                (kind, stock_str::activity_backup_exported(context).await)
+            }
+            _ => {
+                warn!(context, "Unknown activity event kind {kind}.");
+                continue;
+            }
+        };
+        entries.push(ActivityEntry {
+            timestamp,
+            kind,
+            chat_id: None,
+            msg_id: None,
+            text,
+        });
+    }
+
+    // Config changes are returned newest first.
+    for change in context.get_config_changes().await?.into_iter().rev() {
+        if change.timestamp >= since {
+            entries.push(ActivityEntry {
+                timestamp: change.timestamp,
+                kind: ActivityKind::ConfigChanged,
+                chat_id: None,
+                msg_id: None,
+                text: stock_str::activity_config_changed(context, &change.key).await,
+            });
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.timestamp);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{add_contact_to_chat, create_group_chat, ProtectionStatus};
+    use crate::config::Config;
+    use crate::imex::{imex, ImexMode};
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_activity_log() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        // Forget the config changes made to set up the account.
+        t.sql.execute("DELETE FROM config_changes", ()).await?;
+        let start = time();
+        assert!(get_activity_log(&t, start).await?.is_empty());
+
+        let chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, "Group").await?;
+        // Promote the group, so adding members creates info messages.
+        t.send_text(chat_id, "Hi").await;
+        let bob_id = t
+            .add_or_lookup_contact_id(&TestContext::new_bob().await)
+            .await;
+        add_contact_to_chat(&t, chat_id, bob_id).await?;
+
+        t.set_config(Config::Displayname, Some("Alice")).await?;
+        record(&t, ActivityKind::Configured, "alice@example.org").await?;
+        let backup_dir = tempfile::tempdir()?;
+        for _ in 0..2 {
+            imex(&t, ImexMode::ExportBackup, backup_dir.path(), None).await?;
+        }
+
+        let log = get_activity_log(&t, start).await?;
+        let entries_of = |kind: ActivityKind| log.iter().filter(move |entry| entry.kind == kind);
+        assert_eq!(
+            entries_of(ActivityKind::MemberAdded)
+                .next()
+                .unwrap()
+                .chat_id,
+            Some(chat_id)
+        );
+        assert_eq!(
+            entries_of(ActivityKind::ConfigChanged).next().unwrap().text,
+            "Setting displayname changed"
+        );
+        assert_eq!(
+            entries_of(ActivityKind::Configured).next().unwrap().text,
+            "Account alice@example.org configured"
+        );
+        // All backups are reported, not only the last one.
+        assert_eq!(entries_of(ActivityKind::BackupExported).count(), 2);
+        assert!(log
+            .iter()
+            .zip(log.iter().skip(1))
+            .all(|(a, b)| a.timestamp <= b.timestamp));
+
+        assert_eq!(get_activity_log(&t, time() + 60).await?, Vec::new());
+        Ok(())
+    }
+}
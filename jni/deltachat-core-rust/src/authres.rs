@@ -45,6 +45,11 @@ pub(crate) async fn handle_authres(
 pub(crate) struct DkimResults {
     /// Whether DKIM passed for this particular e-mail.
     pub dkim_passed: bool,
+
+    /// Whether an Authentication-Results header of our provider says that DKIM passed.
+    ///
+    /// Unlike `dkim_passed`, this is false if the provider does not add such headers.
+    pub dkim_verified: bool,
 }
 
 impl fmt::Display for DkimResults {
@@ -218,6 +223,7 @@ async fn compute_dkim_results(
     mut authres: ParsedAuthresHeaders,
 ) -> Result<DkimResults> {
     let mut dkim_passed = false;
+    let mut dkim_verified = false;
 
     let ids_config = context.get_config(Config::AuthservIdCandidates).await?;
     let ids = parse_authservid_candidates_config(&ids_config);
@@ -235,6 +241,7 @@ async fn compute_dkim_results(
             match current_dkim_passed {
                 DkimResult::Passed => {
                     dkim_passed = true;
+                    dkim_verified = true;
                     break;
                 }
                 DkimResult::Failed => {
@@ -248,7 +255,10 @@ async fn compute_dkim_results(
         }
     }
 
-    Ok(DkimResults { dkim_passed })
+    Ok(DkimResults {
+        dkim_passed,
+        dkim_verified,
+    })
 }
 
 fn parse_authservid_candidates_config(config: &Option<String>) -> BTreeSet<&str> {
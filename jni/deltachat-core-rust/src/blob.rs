@@ -1,29 +1,46 @@
 //! # Blob directory management.
 
 use core::cmp::max;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt;
-use std::io::{Cursor, Seek};
+use std::io::{Cursor, Seek, Write};
 use std::iter::FusedIterator;
 use std::mem;
 use std::path::{Path, PathBuf};
 
-use anyhow::{format_err, Context as _, Result};
+use anyhow::{bail, format_err, Context as _, Result};
 use base64::Engine as _;
 use futures::StreamExt;
 use image::codecs::jpeg::JpegEncoder;
+use image::error::ImageError;
 use image::ImageReader;
-use image::{DynamicImage, GenericImage, GenericImageView, ImageFormat, Pixel, Rgba};
+use image::{DynamicImage, GenericImage, GenericImageView, ImageFormat, Limits, Pixel, Rgba};
 use num_traits::FromPrimitive;
-use tokio::io::AsyncWriteExt;
-use tokio::{fs, io};
+use resvg::{tiny_skia, usvg};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio_stream::wrappers::ReadDirStream;
+use tokio_util::sync::CancellationToken;
+use unicode_normalization::UnicodeNormalization;
 
+use crate::chat::ChatId;
 use crate::config::Config;
 use crate::constants::{self, MediaQuality};
 use crate::context::Context;
 use crate::events::EventType;
+use crate::heif;
 use crate::log::LogExt;
+use crate::tools::get_filemeta_from_path;
+
+mod animation;
+pub(crate) mod avatar_cache;
+pub(crate) mod integrity;
+mod metadata_scrub;
+pub(crate) mod pdf;
+pub(crate) mod video;
+pub(crate) mod waveform;
 
 /// Represents a file in the blob directory.
 ///
@@ -37,6 +54,187 @@ pub struct BlobObject<'a> {
     name: String,
 }
 
+/// Error returned when an image exceeds the decoding limits.
+///
+/// Such images are not recoded but sent as files.
+#[derive(Debug, thiserror::Error)]
+#[error("Image exceeds decoding limits: {0}")]
+pub(crate) struct ImageLimitsExceeded(String);
+
+/// Error returned if recoding an image was cancelled,
+/// see [`ChatId::cancel_image_recoding`].
+#[derive(Debug, thiserror::Error)]
+#[error("Image recoding cancelled")]
+pub(crate) struct ImageRecodeCancelled;
+
+/// Cancellation and progress reporting of an image recoding.
+#[derive(Debug, Default)]
+struct RecodeControl {
+    /// Chat to emit [`EventType::ImageRecodeProgress`] events for,
+    /// `None` if no events should be emitted.
+    chat_id: Option<ChatId>,
+
+    /// Token to cancel the recoding.
+    cancel: CancellationToken,
+}
+
+/// Image recodings of messages being sent, see [`ChatId::cancel_image_recoding`].
+#[derive(Debug, Default)]
+pub(crate) struct ImageRecodes {
+    /// ID of the next recoding.
+    next_id: u64,
+
+    /// Chat and cancellation token of each running recoding.
+    running: HashMap<u64, (ChatId, CancellationToken)>,
+}
+
+impl ImageRecodes {
+    /// Cancels all recodings of messages being sent to the chat.
+    ///
+    /// Returns the number of cancelled recodings.
+    pub(crate) fn cancel(&mut self, chat_id: ChatId) -> usize {
+        let len = self.running.len();
+        self.running.retain(|_, (recode_chat_id, cancel)| {
+            if *recode_chat_id != chat_id {
+                return true;
+            }
+            cancel.cancel();
+            false
+        });
+        len - self.running.len()
+    }
+}
+
+/// Registration of a running recoding in [`ImageRecodes`], removed on drop.
+struct ImageRecodeGuard<'a> {
+    context: &'a Context,
+    id: u64,
+}
+
+impl<'a> ImageRecodeGuard<'a> {
+    fn new(context: &'a Context, chat_id: ChatId, cancel: CancellationToken) -> Self {
+        let mut recodes = context.image_recodes.lock();
+        let id = recodes.next_id;
+        recodes.next_id += 1;
+        recodes.running.insert(id, (chat_id, cancel));
+        Self { context, id }
+    }
+}
+
+impl Drop for ImageRecodeGuard<'_> {
+    fn drop(&mut self) {
+        self.context.image_recodes.lock().running.remove(&self.id);
+    }
+}
+
+impl RecodeControl {
+    /// Returns [`ImageRecodeCancelled`] if the recoding was cancelled.
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(ImageRecodeCancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Emits the progress in permille.
+    fn progress(&self, context: &Context, progress: usize) {
+        if let Some(chat_id) = self.chat_id {
+            context.emit_event(EventType::ImageRecodeProgress { chat_id, progress });
+        }
+    }
+}
+
+/// Limits for decoding images, protecting against decompression bombs.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DecodingLimits {
+    /// Maximum number of pixels, 0 for no limit.
+    max_pixels: u64,
+
+    /// Maximum number of bytes allocated by the decoder, 0 for no limit.
+    max_bytes: u64,
+}
+
+impl DecodingLimits {
+    /// Loads the limits from [`Config::MaxImagePixels`] and [`Config::MaxImageDecodedBytes`].
+    pub(crate) async fn from_config(context: &Context) -> Result<Self> {
+        Ok(Self {
+            max_pixels: context.get_config_u64(Config::MaxImagePixels).await?,
+            max_bytes: context.get_config_u64(Config::MaxImageDecodedBytes).await?,
+        })
+    }
+
+    /// Checks the dimensions read from the image header before decoding.
+    pub(crate) fn check_dimensions(&self, width: u32, height: u32) -> Result<()> {
+        let pixels = u64::from(width) * u64::from(height);
+        if self.max_pixels > 0 && pixels > self.max_pixels {
+            return Err(ImageLimitsExceeded(format!(
+                "{width}x{height} pixels, at most {} allowed",
+                self.max_pixels
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Checks the number of bytes a decoder is going to allocate.
+    pub(crate) fn check_alloc(&self, bytes: u64) -> Result<()> {
+        if self.max_bytes > 0 && bytes > self.max_bytes {
+            return Err(ImageLimitsExceeded(format!(
+                "{bytes} bytes, at most {} allowed",
+                self.max_bytes
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the limits passed to the decoder.
+    fn image_limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_alloc = (self.max_bytes > 0).then_some(self.max_bytes);
+        limits
+    }
+}
+
+/// Maximum size of attachments scrubbed by [`BlobObject::scrub_metadata`],
+/// larger files are sent as is to avoid reading them into memory.
+const MAX_SCRUB_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Maximum length of a blob file name in bytes,
+/// most file systems do not allow longer names.
+const MAX_FILE_NAME_LEN: usize = 255;
+
+/// Maximum length of a blob path.
+///
+/// Windows limits paths to 260 UTF-16 code units unless long paths are enabled,
+/// other platforms usually allow 4096 bytes.
+/// Length in bytes is never smaller than the length in UTF-16 code units.
+#[cfg(windows)]
+const MAX_PATH_LEN: usize = 260;
+#[cfg(not(windows))]
+const MAX_PATH_LEN: usize = 4096;
+
+/// Device names reserved by Windows, files with such a stem cannot be created there.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
+    "COM8", "COM9", "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Returns the JPEG quality for recoding images from [`Config::JpegQuality`].
+async fn get_jpeg_quality(context: &Context) -> Result<u8> {
+    let quality = context.get_config_int(Config::JpegQuality).await?;
+    Ok(u8::try_from(quality.clamp(1, 100)).unwrap_or(75))
+}
+
+/// Returns true if `name` is a reserved device name on Windows,
+/// with or without an extension.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
 #[derive(Debug, Clone)]
 enum ImageOutputFormat {
     Png,
@@ -70,6 +268,10 @@ pub async fn create(
             blobdir,
             name: format!("$BLOBDIR/{name}"),
         };
+        blob.store_checksum(context, &hex::encode(Sha256::digest(data)))
+            .await
+            .log_err(context)
+            .ok();
         context.emit_event(EventType::NewBlobFile(blob.as_name().to_string()));
         Ok(blob)
     }
@@ -82,6 +284,7 @@ async fn create_new_file(
         ext: &str,
     ) -> Result<(String, fs::File)> {
         const MAX_ATTEMPT: u32 = 16;
+        let stem = BlobObject::truncate_stem(dir, stem, ext);
         let mut attempt = 0;
         let mut name = format!("{stem}{ext}");
         loop {
@@ -107,6 +310,29 @@ async fn create_new_file(
         }
     }
 
+    /// Truncates `stem` so that the file name in `dir`,
+    /// including a random suffix added on name conflicts,
+    /// does not exceed the file name and path length limits.
+    fn truncate_stem<'b>(dir: &Path, stem: &'b str, ext: &str) -> &'b str {
+        // Room for "-" and a random `u32`.
+        const SUFFIX_LEN: usize = 11;
+        let max_name_len =
+            MAX_FILE_NAME_LEN.min(MAX_PATH_LEN.saturating_sub(dir.as_os_str().len() + 1));
+        let mut len = max_name_len.saturating_sub(ext.len() + SUFFIX_LEN);
+        if stem.len() <= len {
+            return stem;
+        }
+        while !stem.is_char_boundary(len) {
+            len -= 1;
+        }
+        let stem = stem.get(..len).unwrap_or_default();
+        if ext.is_empty() {
+            stem.trim_end_matches(['.', ' '])
+        } else {
+            stem
+        }
+    }
+
     /// Creates a new blob object with unique name by copying an existing file.
     ///
     /// This creates a new blob as described in [BlobObject::create]
@@ -121,12 +347,15 @@ pub async fn create_and_copy(context: &'a Context, src: &Path) -> Result<BlobObj
         let (name, mut dst_file) =
             BlobObject::create_new_file(context, context.get_blobdir(), &stem, &ext).await?;
         let name_for_err = name.clone();
-        if let Err(err) = io::copy(&mut src_file, &mut dst_file).await {
-            // Attempt to remove the failed file, swallow errors resulting from that.
-            let path = context.get_blobdir().join(&name_for_err);
-            fs::remove_file(path).await.ok();
-            return Err(err).context("failed to copy file");
-        }
+        let sha256 = match integrity::copy_hashed(&mut src_file, &mut dst_file).await {
+            Ok(sha256) => sha256,
+            Err(err) => {
+                // Attempt to remove the failed file, swallow errors resulting from that.
+                let path = context.get_blobdir().join(&name_for_err);
+                fs::remove_file(path).await.ok();
+                return Err(err).context("failed to copy file");
+            }
+        };
 
         // workaround, see create() for details
         let _ = dst_file.flush().await;
@@ -135,6 +364,48 @@ pub async fn create_and_copy(context: &'a Context, src: &Path) -> Result<BlobObj
             blobdir: context.get_blobdir(),
             name: format!("$BLOBDIR/{name}"),
         };
+        blob.store_checksum(context, &sha256)
+            .await
+            .log_err(context)
+            .ok();
+        context.emit_event(EventType::NewBlobFile(blob.as_name().to_string()));
+        Ok(blob)
+    }
+
+    /// Creates a new blob object with unique name from the data returned by a reader.
+    ///
+    /// Same as [BlobObject::create], but the data is copied in chunks
+    /// and never held in memory as a whole.
+    /// If reading fails, the partially written file is removed.
+    pub async fn create_from_reader(
+        context: &'a Context,
+        suggested_name: &str,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<BlobObject<'a>> {
+        let (stem, ext) = BlobObject::sanitise_name(suggested_name);
+        let (name, mut file) =
+            BlobObject::create_new_file(context, context.get_blobdir(), &stem, &ext).await?;
+        let sha256 = match integrity::copy_hashed(reader, &mut file).await {
+            Ok(sha256) => sha256,
+            Err(err) => {
+                // Attempt to remove the failed file, swallow errors resulting from that.
+                let path = context.get_blobdir().join(&name);
+                fs::remove_file(path).await.ok();
+                return Err(err).context("failed to write blob");
+            }
+        };
+
+        // workaround, see create() for details
+        let _ = file.flush().await;
+
+        let blob = BlobObject {
+            blobdir: context.get_blobdir(),
+            name: format!("$BLOBDIR/{name}"),
+        };
+        blob.store_checksum(context, &sha256)
+            .await
+            .log_err(context)
+            .ok();
         context.emit_event(EventType::NewBlobFile(blob.as_name().to_string()));
         Ok(blob)
     }
@@ -252,7 +523,12 @@ pub fn suffix(&self) -> Option<&str> {
     /// ".txt")` while "bar" is returned as `("bar", "")`.
     ///
     /// The extension part will always be lowercased.
-    fn sanitise_name(name: &str) -> (String, String) {
+    ///
+    /// The name is normalized to Unicode NFC, so names look the same
+    /// on all platforms.  Windows reserved device names such as `CON`
+    /// are prefixed with an underscore and trailing dots and spaces,
+    /// which Windows does not allow, are removed.
+    pub(crate) fn sanitise_name(name: &str) -> (String, String) {
         let mut name = name;
         for part in name.rsplit('/') {
             if !part.is_empty() {
@@ -266,6 +542,11 @@ fn sanitise_name(name: &str) -> (String, String) {
                 break;
             }
         }
+        let mut name: String = name.nfc().collect();
+        if is_reserved_name(&name) {
+            // `sanitize_filename` would remove the whole name.
+            name.insert(0, '_');
+        }
         let opts = sanitize_filename::Options {
             truncate: true,
             windows: true,
@@ -298,12 +579,17 @@ fn sanitise_name(name: &str) -> (String, String) {
             format!(".{ext}")
             // ".tar.gz"
         };
-        let stem = name
+        let stem: String = name
             .strip_suffix(&ext)
             .unwrap_or_default()
             .chars()
             .take(64)
             .collect();
+        let stem = if ext.is_empty() {
+            stem.trim_end_matches(['.', ' ']).to_string()
+        } else {
+            stem
+        };
         (stem, ext.to_lowercase())
     }
 
@@ -312,6 +598,9 @@ fn sanitise_name(name: &str) -> (String, String) {
     /// This is slightly less strict than stanitise_name, presumably
     /// someone already created a file with such a name so we just
     /// ensure it's not actually a path in disguise is actually utf-8.
+    /// Names which cannot be copied to Windows file systems,
+    /// i.e. reserved device names and names ending with a dot or space,
+    /// are rejected as well.
     fn is_acceptible_blob_name(name: impl AsRef<OsStr>) -> bool {
         let uname = match name.as_ref().to_str() {
             Some(name) => name,
@@ -326,9 +615,22 @@ fn is_acceptible_blob_name(name: impl AsRef<OsStr>) -> bool {
         if uname.find('\0').is_some() {
             return false;
         }
+        if uname.ends_with(['.', ' ']) || is_reserved_name(uname) {
+            return false;
+        }
         true
     }
 
+    /// Returns the hex-encoded SHA-256 hash of the blob content.
+    ///
+    /// The file is hashed in chunks, so large attachments are not read into memory.
+    pub(crate) async fn sha256(&self) -> Result<String> {
+        let path = self.to_abs_path();
+        tokio::task::spawn_blocking(move || integrity::hash_file(&path))
+            .await?
+            .with_context(|| format!("Failed to read blob {}", self.as_name()))
+    }
+
     /// Returns path to the stored Base64-decoded blob.
     ///
     /// If `data` represents an image of known format, this adds the corresponding extension to
@@ -354,8 +656,6 @@ pub(crate) async fn store_from_base64(
     }
 
     pub async fn recode_to_avatar_size(&mut self, context: &Context) -> Result<()> {
-        let blob_abs = self.to_abs_path();
-
         let img_wh =
             match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
                 .unwrap_or_default()
@@ -363,9 +663,26 @@ pub async fn recode_to_avatar_size(&mut self, context: &Context) -> Result<()> {
                 MediaQuality::Balanced => constants::BALANCED_AVATAR_SIZE,
                 MediaQuality::Worse => constants::WORSE_AVATAR_SIZE,
             };
+        let jpeg_quality = get_jpeg_quality(context).await?;
+        let mut cache = context.avatar_cache.lock().await;
+        let key = self.avatar_key(img_wh, jpeg_quality).await?;
+        if let Some(blob) = cache.get(context, &key).await? {
+            info!(
+                context,
+                "Using cached avatar {} for {}.",
+                blob.as_name(),
+                self.as_name()
+            );
+            self.name = blob.name;
+            return Ok(());
+        }
+
+        self.rasterize_svg(context, img_wh).await?;
+        let blob_abs = self.to_abs_path();
 
         let maybe_sticker = &mut false;
         let strict_limits = true;
+        let limits = DecodingLimits::from_config(context).await?;
         // max_bytes is 20_000 bytes: Outlook servers don't allow headers larger than 32k.
         // 32 / 4 * 3 = 24k if you account for base64 encoding. To be safe, we reduced this to 20k.
         if let Some(new_name) = self.recode_to_size(
@@ -374,10 +691,18 @@ pub async fn recode_to_avatar_size(&mut self, context: &Context) -> Result<()> {
             maybe_sticker,
             img_wh,
             20_000,
+            jpeg_quality,
             strict_limits,
+            limits,
         )? {
             self.name = new_name;
         }
+        // The image may have been rewritten in place.
+        self.update_checksum(context).await.log_err(context).ok();
+        // Setting the recoded avatar again does not need another recoding either.
+        let recoded_key = self.avatar_key(img_wh, jpeg_quality).await?;
+        cache.insert(key, self);
+        cache.insert(recoded_key, self);
         Ok(())
     }
 
@@ -393,211 +718,511 @@ pub async fn recode_to_image_size(
         context: &Context,
         maybe_sticker: &mut bool,
     ) -> Result<()> {
-        let blob_abs = self.to_abs_path();
-        let (img_wh, max_bytes) =
+        self.recode_to_image_size_ex(context, None, maybe_sticker)
+            .await
+    }
+
+    /// Same as [`BlobObject::recode_to_image_size`],
+    /// emitting [`EventType::ImageRecodeProgress`] events for the chat `chat_id`.
+    ///
+    /// The image is recoded on a blocking thread, so the async runtime is not blocked.
+    /// The recoding can be cancelled with [`ChatId::cancel_image_recoding`],
+    /// [`ImageRecodeCancelled`] is returned then.
+    pub(crate) async fn recode_to_image_size_ex(
+        &mut self,
+        context: &Context,
+        chat_id: Option<ChatId>,
+        maybe_sticker: &mut bool,
+    ) -> Result<()> {
+        let (size_key, bytes_key) =
             match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
                 .unwrap_or_default()
             {
-                MediaQuality::Balanced => (
-                    constants::BALANCED_IMAGE_SIZE,
-                    constants::BALANCED_IMAGE_BYTES,
-                ),
-                MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
+                MediaQuality::Balanced => (Config::BalancedImageSize, Config::BalancedImageBytes),
+                MediaQuality::Worse => (Config::WorseImageSize, Config::WorseImageBytes),
             };
+        // The values are checked when set, but may be invalid if set via raw config.
+        let img_wh = context
+            .get_config_parsed::<u32>(size_key)
+            .await?
+            .unwrap_or_default()
+            .max(constants::MIN_IMAGE_SIZE);
+        let max_bytes = context
+            .get_config_parsed::<usize>(bytes_key)
+            .await?
+            .unwrap_or_default()
+            .max(constants::MIN_IMAGE_BYTES);
+        let jpeg_quality = get_jpeg_quality(context).await?;
+        if *maybe_sticker {
+            self.rasterize_svg(context, img_wh).await?;
+        }
         let strict_limits = false;
-        if let Some(new_name) = self.recode_to_size(
-            context,
-            blob_abs,
-            maybe_sticker,
-            img_wh,
-            max_bytes,
-            strict_limits,
-        )? {
-            self.name = new_name;
+        let limits = DecodingLimits::from_config(context).await?;
+        let cancel = CancellationToken::new();
+        let _guard = chat_id.map(|chat_id| ImageRecodeGuard::new(context, chat_id, cancel.clone()));
+        let control = RecodeControl {
+            chat_id,
+            cancel: cancel.clone(),
+        };
+
+        let ctx = context.clone();
+        let src = self.to_abs_path();
+        let mut is_sticker = *maybe_sticker;
+        let task = tokio::task::spawn_blocking(move || {
+            let res = recode_image(
+                &ctx,
+                &src,
+                src.clone(),
+                &mut is_sticker,
+                img_wh,
+                max_bytes,
+                jpeg_quality,
+                strict_limits,
+                limits,
+                &control,
+            );
+            res.map(|new_name| (new_name, is_sticker))
+        });
+        let res = tokio::select! {
+            res = task => res.context("Image recoding failed")?,
+            // The task keeps running until the next checkpoint, but the result is not awaited.
+            _ = cancel.cancelled() => Err(ImageRecodeCancelled.into()),
+        };
+        let res = res.map(|(new_name, is_sticker)| {
+            *maybe_sticker = is_sticker;
+            if let Some(new_name) = new_name {
+                self.name = new_name;
+            }
+        });
+        if res.is_ok() {
+            // The image may have been rewritten in place.
+            self.update_checksum(context).await.log_err(context).ok();
+        }
+        if let Some(chat_id) = chat_id {
+            let progress = if res.is_ok() { 1000 } else { 0 };
+            context.emit_event(EventType::ImageRecodeProgress { chat_id, progress });
+        }
+        res
+    }
+
+    /// Returns true if the blob is an animated GIF, APNG or WebP image.
+    pub(crate) async fn is_animated(&self) -> Result<bool> {
+        let path = self.to_abs_path();
+        tokio::task::spawn_blocking(move || {
+            let reader = ImageReader::open(path)?.with_guessed_format()?;
+            anyhow::Ok(match reader.format() {
+                Some(fmt) => animation::is_animated(fmt, reader.into_inner()),
+                None => false,
+            })
+        })
+        .await?
+    }
+
+    /// Replaces an SVG blob with a PNG rendering fitting into `img_wh`.
+    ///
+    /// Many clients can't display SVG and SVG may contain scripts, so it is never sent as is.
+    /// Blobs of other types are left untouched.
+    async fn rasterize_svg(&mut self, context: &Context, img_wh: u32) -> Result<()> {
+        if self.suffix() != Some("svg") {
+            return Ok(());
         }
+        let data = fs::read(self.to_abs_path())
+            .await
+            .context("Failed to read SVG")?;
+        let encoded = tokio::task::block_in_place(|| render_svg(&data, img_wh))?;
+        let stem = Path::new(self.as_file_name())
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or("image");
+        let blob = BlobObject::create(context, &format!("{stem}.png"), &encoded).await?;
+        info!(
+            context,
+            "Rasterized SVG {} to {}.",
+            self.as_name(),
+            blob.as_name()
+        );
+        self.name = blob.name;
         Ok(())
     }
 
+    /// Replaces a HEIF blob with a JPEG conversion.
+    ///
+    /// Returns `false` if the blob is not a HEIF image or HEIF support is not enabled.
+    pub(crate) async fn convert_heif_to_jpeg(&mut self, context: &Context) -> Result<bool> {
+        if !heif::SUPPORTED || !heif::is_heif(Path::new(&self.name)) {
+            return Ok(false);
+        }
+        let limits = DecodingLimits::from_config(context).await?;
+        let quality = get_jpeg_quality(context).await?;
+        let data = fs::read(self.to_abs_path())
+            .await
+            .context("Failed to read HEIF image")?;
+        let encoded = tokio::task::block_in_place(|| {
+            let img = heif::decode(&data, &limits)?;
+            let mut encoded = Vec::new();
+            encode_img(&img, ImageOutputFormat::Jpeg { quality }, &mut encoded)?;
+            anyhow::Ok(encoded)
+        })?;
+        let stem = Path::new(self.as_file_name())
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or("image");
+        let blob = BlobObject::create(context, &format!("{stem}.jpg"), &encoded).await?;
+        info!(
+            context,
+            "Converted HEIF image {} to {}.",
+            self.as_name(),
+            blob.as_name()
+        );
+        self.name = blob.name;
+        Ok(true)
+    }
+
+    /// Removes privacy-sensitive metadata from videos, audio files and documents,
+    /// see [`metadata_scrub`].
+    ///
+    /// The scrubbed file is stored as a new blob with the same file name,
+    /// the original blob is not modified as it may be used by other messages.
+    /// Returns true if metadata was removed.
+    pub(crate) async fn scrub_metadata(&mut self, context: &Context) -> Result<bool> {
+        let path = self.to_abs_path();
+        let len = fs::metadata(&path).await?.len();
+        if len > MAX_SCRUB_BYTES {
+            info!(
+                context,
+                "Not removing metadata from {}, it is too large ({len} bytes).",
+                self.as_name()
+            );
+            return Ok(false);
+        }
+        // Only read files of supported formats into memory.
+        let mut header = Vec::with_capacity(metadata_scrub::HEADER_LEN);
+        fs::File::open(&path)
+            .await?
+            .take(metadata_scrub::HEADER_LEN as u64)
+            .read_to_end(&mut header)
+            .await
+            .context("Failed to read attachment")?;
+        if !metadata_scrub::is_supported(&header) {
+            return Ok(false);
+        }
+        let data = fs::read(&path).await.context("Failed to read attachment")?;
+        let Some(scrubbed) = tokio::task::block_in_place(|| metadata_scrub::scrub(data))? else {
+            return Ok(false);
+        };
+        let blob = BlobObject::create(context, self.as_file_name(), &scrubbed).await?;
+        info!(
+            context,
+            "Removed metadata from {}, stored as {}.",
+            self.as_name(),
+            blob.as_name()
+        );
+        self.name = blob.name;
+        Ok(true)
+    }
+
     /// If `!strict_limits`, then if `max_bytes` is exceeded, reduce the image to `img_wh` and just
     /// proceed with the result.
+    ///
+    /// Images exceeding the decoding `limits` are not decoded, [`ImageLimitsExceeded`] is returned
+    /// instead.
+    #[allow(clippy::too_many_arguments)]
     fn recode_to_size(
         &mut self,
         context: &Context,
-        mut blob_abs: PathBuf,
+        blob_abs: PathBuf,
         maybe_sticker: &mut bool,
-        mut img_wh: u32,
+        img_wh: u32,
         max_bytes: usize,
+        jpeg_quality: u8,
         strict_limits: bool,
+        limits: DecodingLimits,
     ) -> Result<Option<String>> {
-        // Add white background only to avatars to spare the CPU.
-        let mut add_white_bg = img_wh <= constants::BALANCED_AVATAR_SIZE;
-        let mut no_exif = false;
-        let no_exif_ref = &mut no_exif;
-        let res = tokio::task::block_in_place(move || {
-            let mut file = std::fs::File::open(self.to_abs_path())?;
-            let (nr_bytes, exif) = image_metadata(&file)?;
-            *no_exif_ref = exif.is_none();
-            // It's strange that BufReader modifies a file position while it takes a non-mut
-            // reference. Ok, just rewind it.
-            file.rewind()?;
-            let imgreader = ImageReader::new(std::io::BufReader::new(&file)).with_guessed_format();
-            let imgreader = match imgreader {
-                Ok(ir) => ir,
-                _ => {
-                    file.rewind()?;
-                    ImageReader::with_format(
-                        std::io::BufReader::new(&file),
-                        ImageFormat::from_path(&blob_abs)?,
-                    )
-                }
-            };
-            let fmt = imgreader.format().context("No format??")?;
-            let mut img = imgreader.decode().context("image decode failure")?;
-            let orientation = exif.as_ref().map(|exif| exif_orientation(exif, context));
-            let mut encoded = Vec::new();
-            let mut changed_name = None;
-
-            if *maybe_sticker {
-                let x_max = img.width().saturating_sub(1);
-                let y_max = img.height().saturating_sub(1);
-                *maybe_sticker = img.in_bounds(x_max, y_max)
-                    && (img.get_pixel(0, 0).0[3] == 0
-                        || img.get_pixel(x_max, 0).0[3] == 0
-                        || img.get_pixel(0, y_max).0[3] == 0
-                        || img.get_pixel(x_max, y_max).0[3] == 0);
+        let src = self.to_abs_path();
+        tokio::task::block_in_place(move || {
+            recode_image(
+                context,
+                &src,
+                blob_abs,
+                maybe_sticker,
+                img_wh,
+                max_bytes,
+                jpeg_quality,
+                strict_limits,
+                limits,
+                &RecodeControl::default(),
+            )
+        })
+    }
+}
+
+/// Recodes the image at `src` as described in [`BlobObject::recode_to_size`],
+/// writing the result to `blob_abs` or a file with the extension changed to `.jpg`.
+///
+/// This blocks, so it should run on a blocking thread.
+/// The recoding is aborted with [`ImageRecodeCancelled`] at the next checkpoint
+/// if it is cancelled via `control`.
+#[allow(clippy::too_many_arguments)]
+fn recode_image(
+    context: &Context,
+    src: &Path,
+    mut blob_abs: PathBuf,
+    maybe_sticker: &mut bool,
+    mut img_wh: u32,
+    max_bytes: usize,
+    jpeg_quality: u8,
+    strict_limits: bool,
+    limits: DecodingLimits,
+    control: &RecodeControl,
+) -> Result<Option<String>> {
+    // Add white background only to avatars to spare the CPU.
+    let mut add_white_bg = img_wh <= constants::BALANCED_AVATAR_SIZE;
+    let mut no_exif = false;
+    let no_exif_ref = &mut no_exif;
+    let res = (|| -> Result<Option<String>> {
+        control.check_cancelled()?;
+        if let Ok((width, height)) = get_filemeta_from_path(src) {
+            limits.check_dimensions(width, height)?;
+        }
+        let mut file = std::fs::File::open(src)?;
+        let (nr_bytes, exif) = image_metadata(&file)?;
+        control.progress(context, 100);
+        *no_exif_ref = exif.is_none();
+        // It's strange that BufReader modifies a file position while it takes a non-mut
+        // reference. Ok, just rewind it.
+        file.rewind()?;
+        let imgreader = ImageReader::new(std::io::BufReader::new(&file)).with_guessed_format();
+        let mut imgreader = match imgreader {
+            Ok(ir) => ir,
+            _ => {
+                file.rewind()?;
+                ImageReader::with_format(
+                    std::io::BufReader::new(&file),
+                    ImageFormat::from_path(&blob_abs)?,
+                )
             }
-            if *maybe_sticker && exif.is_none() {
-                return Ok(None);
+        };
+        let fmt = imgreader.format().context("No format??")?;
+        if matches!(fmt, ImageFormat::Gif | ImageFormat::Png | ImageFormat::WebP) {
+            // `file` is shared with `imgreader`, so it is rewound via a shared reference.
+            (&file).rewind()?;
+            if animation::is_animated(fmt, std::io::BufReader::new(&file)) {
+                return animation::recode_animation(
+                    context,
+                    &file,
+                    nr_bytes,
+                    fmt,
+                    blob_abs.clone(),
+                    maybe_sticker,
+                    img_wh,
+                    max_bytes,
+                    exif.is_some(),
+                    strict_limits,
+                    limits,
+                    control,
+                );
             }
+            (&file).rewind()?;
+        }
+        imgreader.limits(limits.image_limits());
+        let mut img = match imgreader.decode() {
+            Ok(img) => img,
+            Err(ImageError::Limits(err)) => return Err(ImageLimitsExceeded(err.to_string()).into()),
+            Err(err) => return Err(err).context("image decode failure"),
+        };
+        control.check_cancelled()?;
+        control.progress(context, 500);
+        let orientation = exif.as_ref().map(|exif| exif_orientation(exif, context));
+        let mut encoded = Vec::new();
+        let mut changed_name = None;
+
+        if *maybe_sticker {
+            let x_max = img.width().saturating_sub(1);
+            let y_max = img.height().saturating_sub(1);
+            *maybe_sticker = img.in_bounds(x_max, y_max)
+                && (img.get_pixel(0, 0).0[3] == 0
+                    || img.get_pixel(x_max, 0).0[3] == 0
+                    || img.get_pixel(0, y_max).0[3] == 0
+                    || img.get_pixel(x_max, y_max).0[3] == 0);
+        }
+        if *maybe_sticker && exif.is_none() {
+            return Ok(None);
+        }
 
-            img = match orientation {
-                Some(90) => img.rotate90(),
-                Some(180) => img.rotate180(),
-                Some(270) => img.rotate270(),
-                _ => img,
-            };
+        if let Some(orientation) = orientation {
+            img = apply_orientation(img, orientation);
+        }
 
-            let exceeds_wh = img.width() > img_wh || img.height() > img_wh;
-            let exceeds_max_bytes = nr_bytes > max_bytes as u64;
+        let exceeds_wh = img.width() > img_wh || img.height() > img_wh;
+        let exceeds_max_bytes = nr_bytes > max_bytes as u64;
 
-            let jpeg_quality = 75;
-            let ofmt = match fmt {
-                ImageFormat::Png if !exceeds_max_bytes => ImageOutputFormat::Png,
-                ImageFormat::Jpeg => {
-                    add_white_bg = false;
-                    ImageOutputFormat::Jpeg {
-                        quality: jpeg_quality,
-                    }
-                }
-                _ => ImageOutputFormat::Jpeg {
+        let ofmt = match fmt {
+            ImageFormat::Png if !exceeds_max_bytes => ImageOutputFormat::Png,
+            ImageFormat::Jpeg => {
+                add_white_bg = false;
+                ImageOutputFormat::Jpeg {
                     quality: jpeg_quality,
-                },
-            };
-            // We need to rewrite images with Exif to remove metadata such as location,
-            // camera model, etc.
-            //
-            // TODO: Fix lost animation and transparency when recoding using the `image` crate. And
-            // also `Viewtype::Gif` (maybe renamed to `Animation`) should be used for animated
-            // images.
-            let do_scale = exceeds_max_bytes
-                || strict_limits
-                    && (exceeds_wh
-                        || exif.is_some() && {
-                            if mem::take(&mut add_white_bg) {
-                                self::add_white_bg(&mut img);
-                            }
-                            encoded_img_exceeds_bytes(
-                                context,
-                                &img,
-                                ofmt.clone(),
-                                max_bytes,
-                                &mut encoded,
-                            )?
-                        });
-
-            if do_scale {
-                if !exceeds_wh {
-                    img_wh = max(img.width(), img.height());
-                    // PNGs and WebPs may be huge because of animation, which is lost by the `image`
-                    // crate when recoding, so don't scale them down.
-                    if matches!(fmt, ImageFormat::Jpeg) || !encoded.is_empty() {
-                        img_wh = img_wh * 2 / 3;
-                    }
                 }
-
-                loop {
-                    if mem::take(&mut add_white_bg) {
-                        self::add_white_bg(&mut img);
-                    }
-                    let new_img = img.thumbnail(img_wh, img_wh);
-
-                    if encoded_img_exceeds_bytes(
-                        context,
-                        &new_img,
-                        ofmt.clone(),
-                        max_bytes,
-                        &mut encoded,
-                    )? && strict_limits
-                    {
-                        if img_wh < 20 {
-                            return Err(format_err!(
-                                "Failed to scale image to below {}B.",
-                                max_bytes,
-                            ));
+            }
+            _ => ImageOutputFormat::Jpeg {
+                quality: jpeg_quality,
+            },
+        };
+        // We need to rewrite images with Exif to remove metadata such as location,
+        // camera model, etc.
+        //
+        // Animated images are handled by `animation::recode_animation()` above.
+        //
+        // TODO: Fix lost transparency when recoding using the `image` crate. And
+        // also `Viewtype::Gif` (maybe renamed to `Animation`) should be used for animated
+        // images.
+        let do_scale = exceeds_max_bytes
+            || strict_limits
+                && (exceeds_wh
+                    || exif.is_some() && {
+                        if mem::take(&mut add_white_bg) {
+                            self::add_white_bg(&mut img);
                         }
-
-                        img_wh = img_wh * 2 / 3;
-                    } else {
-                        info!(
+                        encoded_img_exceeds_bytes(
                             context,
-                            "Final scaled-down image size: {}B ({}px).",
-                            encoded.len(),
-                            img_wh
-                        );
-                        break;
-                    }
+                            &img,
+                            ofmt.clone(),
+                            max_bytes,
+                            &mut encoded,
+                        )?
+                    });
+
+        if do_scale {
+            if !exceeds_wh {
+                img_wh = max(img.width(), img.height());
+                // PNGs and WebPs may be huge because of animation, which is lost by the `image`
+                // crate when recoding, so don't scale them down.
+                if matches!(fmt, ImageFormat::Jpeg) || !encoded.is_empty() {
+                    img_wh = img_wh * 2 / 3;
                 }
             }
 
-            if do_scale || exif.is_some() {
-                // The file format is JPEG/PNG now, we may have to change the file extension
-                if !matches!(fmt, ImageFormat::Jpeg)
-                    && matches!(ofmt, ImageOutputFormat::Jpeg { .. })
-                {
-                    blob_abs = blob_abs.with_extension("jpg");
-                    let file_name = blob_abs.file_name().context("No image file name (???)")?;
-                    let file_name = file_name.to_str().context("Filename is no UTF-8 (???)")?;
-                    changed_name = Some(format!("$BLOBDIR/{file_name}"));
+            let mut progress = 600;
+            loop {
+                control.check_cancelled()?;
+                control.progress(context, progress);
+                // Each attempt takes less time as the image gets smaller.
+                progress += (900 - progress) / 2;
+                if mem::take(&mut add_white_bg) {
+                    self::add_white_bg(&mut img);
                 }
-
-                if encoded.is_empty() {
-                    if mem::take(&mut add_white_bg) {
-                        self::add_white_bg(&mut img);
+                let new_img = img.thumbnail(img_wh, img_wh);
+
+                if encoded_img_exceeds_bytes(
+                    context,
+                    &new_img,
+                    ofmt.clone(),
+                    max_bytes,
+                    &mut encoded,
+                )? && strict_limits
+                {
+                    if img_wh < 20 {
+                        return Err(format_err!(
+                            "Failed to scale image to below {}B.",
+                            max_bytes,
+                        ));
                     }
-                    encode_img(&img, ofmt, &mut encoded)?;
+
+                    img_wh = img_wh * 2 / 3;
+                } else {
+                    info!(
+                        context,
+                        "Final scaled-down image size: {}B ({}px).",
+                        encoded.len(),
+                        img_wh
+                    );
+                    break;
                 }
+            }
+        }
 
-                std::fs::write(&blob_abs, &encoded)
-                    .context("failed to write recoded blob to file")?;
+        if do_scale || exif.is_some() {
+            // The file format is JPEG/PNG now, we may have to change the file extension
+            if !matches!(fmt, ImageFormat::Jpeg) && matches!(ofmt, ImageOutputFormat::Jpeg { .. }) {
+                blob_abs = blob_abs.with_extension("jpg");
+                let file_name = blob_abs.file_name().context("No image file name (???)")?;
+                let file_name = file_name.to_str().context("Filename is no UTF-8 (???)")?;
+                changed_name = Some(format!("$BLOBDIR/{file_name}"));
             }
 
-            Ok(changed_name)
-        });
-        match res {
-            Ok(_) => res,
-            Err(err) => {
-                if !strict_limits && no_exif {
-                    warn!(
-                        context,
-                        "Cannot recode image, using original data: {err:#}.",
-                    );
-                    Ok(None)
-                } else {
-                    Err(err)
+            if encoded.is_empty() {
+                if mem::take(&mut add_white_bg) {
+                    self::add_white_bg(&mut img);
                 }
+                encode_img(&img, ofmt, &mut encoded)?;
             }
+
+            // Don't overwrite the blob if the message was discarded meanwhile.
+            control.check_cancelled()?;
+            std::fs::write(&blob_abs, &encoded).context("failed to write recoded blob to file")?;
         }
+
+        Ok(changed_name)
+    })();
+    match res {
+        Ok(_) => res,
+        Err(err) => {
+            if !strict_limits
+                && no_exif
+                && !err.is::<ImageLimitsExceeded>()
+                && !err.is::<ImageRecodeCancelled>()
+            {
+                warn!(
+                    context,
+                    "Cannot recode image, using original data: {err:#}.",
+                );
+                Ok(None)
+            } else {
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Renders SVG `data` to PNG so that it fits into `img_wh`, keeping the aspect ratio.
+///
+/// External resources referenced by the SVG are not loaded.
+fn render_svg(data: &[u8], img_wh: u32) -> Result<Vec<u8>> {
+    // Images are neither loaded from files, which would leak local files of the sender,
+    // nor decoded from data URLs.
+    let options = usvg::Options {
+        resources_dir: None,
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: Box::new(|_: &str, _: std::sync::Arc<Vec<u8>>, _: &usvg::Options| None),
+            resolve_string: Box::new(|_: &str, _: &usvg::Options| None),
+        },
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(data, &options)
+        .map_err(|err| format_err!("Failed to parse SVG: {err}"))?;
+    let size = tree.size();
+    let scale = img_wh as f32 / size.width().max(size.height());
+    let width = ((size.width() * scale).round() as u32).max(1);
+    let height = ((size.height() * scale).round() as u32).max(1);
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).context("Invalid SVG size")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let mut img = image::RgbaImage::new(width, height);
+    for (dst, src) in img.pixels_mut().zip(pixmap.pixels()) {
+        let color = src.demultiply();
+        *dst = Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
     }
+    let mut encoded = Vec::new();
+    encode_img(
+        &DynamicImage::ImageRgba8(img),
+        ImageOutputFormat::Png,
+        &mut encoded,
+    )?;
+    Ok(encoded)
 }
 
 /// Returns image file size and Exif.
@@ -622,6 +1247,95 @@ fn exif_orientation(exif: &exif::Exif, context: &Context) -> i32 {
     0
 }
 
+/// Rotates `img` by `orientation` degrees clockwise as returned by [`exif_orientation`].
+fn apply_orientation(img: DynamicImage, orientation: i32) -> DynamicImage {
+    match orientation {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Rotates the JPEG or PNG image at `path` in place as described by its Exif orientation.
+///
+/// Unlike the recoding done when sending, the image is not scaled down,
+/// so UIs can use this to fix the orientation of draft attachments and avatar crops
+/// before showing or editing them.
+/// The Exif metadata is removed as it is not valid for the rotated image.
+///
+/// Returns false if the image does not need to be rotated.
+pub async fn normalize_orientation(context: &Context, path: &Path) -> Result<bool> {
+    let limits = DecodingLimits::from_config(context).await?;
+    let jpeg_quality = get_jpeg_quality(context).await?;
+    let rotated =
+        tokio::task::block_in_place(|| rotate_by_exif(context, path, limits, jpeg_quality))?;
+    if rotated {
+        info!(context, "Normalized orientation of {}.", path.display());
+        if let Ok(blob) = BlobObject::from_path(context, path) {
+            blob.update_checksum(context).await.log_err(context).ok();
+        }
+    }
+    Ok(rotated)
+}
+
+/// Rotates the image at `path` as described in [`normalize_orientation`].
+///
+/// This blocks, so it should run on a blocking thread.
+fn rotate_by_exif(
+    context: &Context,
+    path: &Path,
+    limits: DecodingLimits,
+    jpeg_quality: u8,
+) -> Result<bool> {
+    let file = std::fs::File::open(path)?;
+    let (_, exif) = image_metadata(&file)?;
+    let orientation = exif
+        .as_ref()
+        .map_or(0, |exif| exif_orientation(exif, context));
+    if orientation == 0 {
+        return Ok(false);
+    }
+    if let Ok((width, height)) = get_filemeta_from_path(path) {
+        limits.check_dimensions(width, height)?;
+    }
+    let mut reader = ImageReader::open(path)?.with_guessed_format()?;
+    let ofmt = match reader.format() {
+        Some(ImageFormat::Jpeg) => ImageOutputFormat::Jpeg {
+            quality: jpeg_quality,
+        },
+        Some(ImageFormat::Png) => ImageOutputFormat::Png,
+        fmt => bail!("Cannot rotate images of format {fmt:?}"),
+    };
+    reader.limits(limits.image_limits());
+    let img = match reader.decode() {
+        Ok(img) => img,
+        Err(ImageError::Limits(err)) => return Err(ImageLimitsExceeded(err.to_string()).into()),
+        Err(err) => return Err(err).context("image decode failure"),
+    };
+    let img = apply_orientation(img, orientation);
+    let mut encoded = Vec::new();
+    encode_img(&img, ofmt, &mut encoded)?;
+
+    // Write to a temporary file in the same directory and rename it over the original
+    // so that the image is not lost if writing fails halfway.
+    let mut tmp_name = path.file_name().context("no file name")?.to_os_string();
+    tmp_name.push(format!(".{}.tmp", rand::random::<u32>()));
+    let tmp_path = path.with_file_name(tmp_name);
+    let replace = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(&encoded)?;
+        file.sync_data()?;
+        drop(file);
+        std::fs::rename(&tmp_path, path)
+    };
+    if let Err(err) = replace() {
+        std::fs::remove_file(&tmp_path).ok();
+        return Err(err).context("failed to write rotated image");
+    }
+    Ok(true)
+}
+
 impl fmt::Display for BlobObject<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "$BLOBDIR/{}", self.name)
@@ -871,6 +1585,16 @@ async fn test_create_long_names() {
         assert!(blobname.len() < 128);
     }
 
+    #[test]
+    fn test_truncate_stem() {
+        let dir = Path::new("/blobdir");
+        let stem = "\u{42f}".repeat(200);
+        let truncated = BlobObject::truncate_stem(dir, &stem, ".txt");
+        assert!(truncated.len() + ".txt".len() + 11 <= MAX_FILE_NAME_LEN);
+        assert!(stem.starts_with(truncated));
+        assert_eq!(BlobObject::truncate_stem(dir, "short", ".txt"), "short");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_create_and_copy() {
         let t = TestContext::new().await;
@@ -889,6 +1613,17 @@ async fn test_create_and_copy() {
         assert!(!whoops.exists());
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_create_from_reader() {
+        let t = TestContext::new().await;
+        let blob = BlobObject::create_from_reader(&t, "foo.txt", &mut &b"boo"[..])
+            .await
+            .unwrap();
+        assert_eq!(blob.as_name(), "$BLOBDIR/foo.txt");
+        let data = fs::read(blob.to_abs_path()).await.unwrap();
+        assert_eq!(data, b"boo");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_create_from_path() {
         let t = TestContext::new().await;
@@ -931,6 +1666,11 @@ fn test_is_blob_name() {
         assert!(!BlobObject::is_acceptible_blob_name("foo/bar"));
         assert!(!BlobObject::is_acceptible_blob_name("foo\\bar"));
         assert!(!BlobObject::is_acceptible_blob_name("foo\x00bar"));
+        assert!(!BlobObject::is_acceptible_blob_name("foo."));
+        assert!(!BlobObject::is_acceptible_blob_name("foo "));
+        assert!(!BlobObject::is_acceptible_blob_name("CON"));
+        assert!(!BlobObject::is_acceptible_blob_name("com1.txt"));
+        assert!(BlobObject::is_acceptible_blob_name("console.txt"));
     }
 
     #[test]
@@ -983,6 +1723,23 @@ fn test_sanitise_name() {
         let (stem, ext) = BlobObject::sanitise_name("a. tar.tar.gz");
         assert_eq!(stem, "a. tar");
         assert_eq!(ext, ".tar.gz");
+
+        // Decomposed "é" is normalized to the precomposed form.
+        let (stem, ext) = BlobObject::sanitise_name("Cafe\u{301}.txt");
+        assert_eq!(stem, "Caf\u{e9}");
+        assert_eq!(ext, ".txt");
+
+        let (stem, ext) = BlobObject::sanitise_name("CON.txt");
+        assert_eq!(stem, "_CON");
+        assert_eq!(ext, ".txt");
+
+        let (stem, ext) = BlobObject::sanitise_name("nul");
+        assert_eq!(stem, "_nul");
+        assert_eq!(ext, "");
+
+        let (stem, ext) = BlobObject::sanitise_name("trailing dots...");
+        assert_eq!(stem, "trailing dots");
+        assert_eq!(ext, "");
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -1007,7 +1764,9 @@ async fn test_add_white_bg() {
                 maybe_sticker,
                 img_wh,
                 20_000,
+                75,
                 strict_limits,
+                DecodingLimits::default(),
             )
             .unwrap();
             tokio::task::block_in_place(move || {
@@ -1056,7 +1815,9 @@ async fn file_size(path_buf: &Path) -> u64 {
             maybe_sticker,
             1000,
             3000,
+            75,
             strict_limits,
+            DecodingLimits::default(),
         )
         .unwrap();
         assert!(file_size(&avatar_blob).await <= 3000);
@@ -1068,6 +1829,49 @@ async fn file_size(path_buf: &Path) -> u64 {
         });
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_selfavatar_svg() -> Result<()> {
+        let t = TestContext::new().await;
+        let avatar_src = t.dir.path().join("avatar.svg");
+        fs::write(
+            &avatar_src,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50">
+<script>alert(1)</script>
+<rect width="100" height="50" fill="red"/>
+</svg>"#,
+        )
+        .await?;
+        t.set_config(Config::Selfavatar, Some(avatar_src.to_str().unwrap()))
+            .await?;
+        let avatar_cfg = t.get_config(Config::Selfavatar).await?.unwrap();
+        assert!(avatar_cfg.ends_with(".png"));
+        check_image_size(
+            &avatar_cfg,
+            constants::BALANCED_AVATAR_SIZE,
+            constants::BALANCED_AVATAR_SIZE / 2,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_svg_no_images() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let png = dir.path().join("secret.png");
+        std::fs::write(&png, test_utils::AVATAR_900x900_BYTES)?;
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10">
+<image width="10" height="10" xlink:href="{}"/>
+<image width="10" height="10" xlink:href="data:image/png;base64,{}"/>
+</svg>"#,
+            png.display(),
+            base64::engine::general_purpose::STANDARD.encode(test_utils::AVATAR_900x900_BYTES)
+        );
+        let encoded = render_svg(svg.as_bytes(), 10)?;
+        let img = image::load_from_memory(&encoded)?;
+        assert!(img.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_selfavatar_in_blobdir() {
         let t = TestContext::new().await;
@@ -1192,6 +1996,31 @@ async fn test_recode_image_2() {
         assert_correct_rotation(&img_rotated);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_normalize_orientation() -> Result<()> {
+        let t = TestContext::new().await;
+        let bytes = include_bytes!("../test-data/image/rectangle2000x1800-rotated.jpg");
+        let path = t.dir.path().join("rotated.jpg");
+        fs::write(&path, bytes).await?;
+
+        assert!(normalize_orientation(&t, &path).await?);
+        let img = check_image_size(&path, 1800, 2000);
+        assert_correct_rotation(&img);
+        let mut entries = fs::read_dir(t.dir.path()).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            assert!(!entry.file_name().to_string_lossy().ends_with(".tmp"));
+        }
+
+        // The Exif metadata is removed, so the image is not rotated again.
+        assert!(!normalize_orientation(&t, &path).await?);
+        check_image_size(&path, 1800, 2000);
+
+        let blob = BlobObject::create(&t, "rotated.jpg", bytes).await?;
+        assert!(normalize_orientation(&t, &blob.to_abs_path()).await?);
+        assert!(blob.verify(&t).await?);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_recode_image_balanced_png() {
         let bytes = include_bytes!("../test-data/image/screenshot.png");
@@ -1457,6 +2286,160 @@ async fn test_send_big_gif_as_image() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_heif_image() -> Result<()> {
+        // A JPEG with a HEIF extension can't be converted by libheif,
+        // but the `image` crate detects the real format when recoding.
+        let bytes = include_bytes!("../test-data/image/screenshot.jpg");
+        let alice = TestContext::new_alice().await;
+        let bob = TestContext::new_bob().await;
+        let file = alice.get_blobdir().join("photo.heic");
+        fs::write(&file, &bytes).await?;
+
+        let mut blob = BlobObject::new_from_path(&alice, &file).await?;
+        assert!(!blob.convert_heif_to_jpeg(&alice).await.unwrap_or(false));
+        let mut maybe_sticker = false;
+        blob.recode_to_image_size(&alice, &mut maybe_sticker)
+            .await?;
+
+        // HEIF images are only sent as images if they can be converted to JPEG.
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat = alice.create_chat(&bob).await;
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        let alice_msg = Message::load_from_db(&alice, sent.sender_msg_id).await?;
+        let expected_viewtype = if heif::SUPPORTED {
+            Viewtype::Image
+        } else {
+            Viewtype::File
+        };
+        assert_eq!(alice_msg.get_viewtype(), expected_viewtype);
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert_eq!(bob_msg.get_viewtype(), expected_viewtype);
+        if !heif::SUPPORTED {
+            assert_eq!(bob_msg.get_filebytes(&bob).await?, Some(bytes.len() as u64));
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_image_exceeding_decoding_limits() -> Result<()> {
+        let bytes = include_bytes!("../test-data/image/screenshot.png");
+        let alice = TestContext::new_alice().await;
+        let bob = TestContext::new_bob().await;
+        alice
+            .set_config(Config::MaxImagePixels, Some("1000000"))
+            .await?;
+        let file = alice.get_blobdir().join("file.png");
+        fs::write(&file, &bytes).await?;
+
+        let mut blob = BlobObject::new_from_path(&alice, &file).await?;
+        let mut maybe_sticker = false;
+        let err = blob
+            .recode_to_image_size(&alice, &mut maybe_sticker)
+            .await
+            .unwrap_err();
+        assert!(err.is::<ImageLimitsExceeded>());
+
+        // The image is sent as a file without recoding.
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat = alice.create_chat(&bob).await;
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        let alice_msg = Message::load_from_db(&alice, sent.sender_msg_id).await?;
+        assert_eq!(alice_msg.get_viewtype(), Viewtype::File);
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert_eq!(bob_msg.get_filebytes(&bob).await?, Some(bytes.len() as u64));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_image_recode_progress_and_cancel() -> Result<()> {
+        let bytes = include_bytes!("../test-data/image/avatar1000x1000.jpg");
+        let t = TestContext::new_alice().await;
+        let chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, "abc").await?;
+        let file = t.get_blobdir().join("file.jpg");
+        fs::write(&file, &bytes).await?;
+
+        t.evtracker.clear_events();
+        let mut blob = BlobObject::new_from_path(&t, &file).await?;
+        let mut maybe_sticker = false;
+        blob.recode_to_image_size_ex(&t, Some(chat_id), &mut maybe_sticker)
+            .await?;
+        let EventType::ImageRecodeProgress {
+            chat_id: ev_chat_id,
+            progress,
+        } = t
+            .evtracker
+            .get_matching(|evt| {
+                matches!(
+                    evt,
+                    EventType::ImageRecodeProgress { progress, .. } if *progress == 1000
+                )
+            })
+            .await
+        else {
+            unreachable!();
+        };
+        assert_eq!(ev_chat_id, chat_id);
+        assert_eq!(progress, 1000);
+
+        // Finished recodings are forgotten.
+        assert!(t.image_recodes.lock().running.is_empty());
+
+        // A cancelled recoding fails.
+        let control = RecodeControl {
+            chat_id: Some(chat_id),
+            cancel: CancellationToken::new(),
+        };
+        control.cancel.cancel();
+        let err = recode_image(
+            &t,
+            &file,
+            file.clone(),
+            &mut maybe_sticker,
+            constants::BALANCED_IMAGE_SIZE,
+            constants::BALANCED_IMAGE_BYTES,
+            80,
+            false,
+            DecodingLimits::default(),
+            &control,
+        )
+        .unwrap_err();
+        assert!(err.is::<ImageRecodeCancelled>());
+
+        // Only recodings of the given chat are cancelled.
+        let other_chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, "def").await?;
+        let cancel = CancellationToken::new();
+        let other_cancel = CancellationToken::new();
+        let guards = [
+            ImageRecodeGuard::new(&t, chat_id, cancel.clone()),
+            ImageRecodeGuard::new(&t, chat_id, cancel.clone()),
+            ImageRecodeGuard::new(&t, other_chat_id, other_cancel.clone()),
+        ];
+        chat_id.cancel_image_recoding(&t);
+        assert!(cancel.is_cancelled());
+        assert!(!other_cancel.is_cancelled());
+        assert_eq!(t.image_recodes.lock().running.len(), 1);
+        drop(guards);
+        assert!(t.image_recodes.lock().running.is_empty());
+
+        // Recoding images of device messages does not report progress.
+        t.evtracker.clear_events();
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file_from_bytes(&t, "image.jpg", bytes, None)
+            .await?;
+        chat::add_device_msg(&t, None, Some(&mut msg)).await?;
+        let ev = t
+            .evtracker
+            .get_matching_opt(&t, |evt| {
+                matches!(evt, EventType::ImageRecodeProgress { .. })
+            })
+            .await;
+        assert!(ev.is_none());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_increation_in_blobdir() -> Result<()> {
         let t = TestContext::new_alice().await;
@@ -0,0 +1,461 @@
+//! # Recoding of animated images.
+//!
+//! The `image` crate decodes only the first frame of animated images,
+//! so animated GIF, APNG and WebP images are handled separately:
+//! if they only need their metadata removed, the metadata chunks are dropped losslessly,
+//! if they need to be scaled down, all frames are scaled and encoded as an animated GIF.
+//! Images are decoded from the file as they are read instead of loading them into memory.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frame, Frames, ImageDecoder, ImageFormat};
+
+use super::{DecodingLimits, ImageLimitsExceeded, RecodeControl};
+use crate::context::Context;
+use crate::tools::{create_id, TempPathGuard};
+
+/// Returns true if the image read from `r` is an animated GIF, APNG or WebP image.
+pub(super) fn is_animated<R: BufRead + Seek>(fmt: ImageFormat, r: R) -> bool {
+    match fmt {
+        ImageFormat::Gif => GifDecoder::new(r)
+            .map(|decoder| decoder.into_frames().take(2).filter(Result::is_ok).count() > 1)
+            .unwrap_or_default(),
+        ImageFormat::Png => PngDecoder::new(r)
+            .and_then(|decoder| decoder.is_apng())
+            .unwrap_or_default(),
+        ImageFormat::WebP => WebPDecoder::new(r)
+            .map(|decoder| decoder.has_animation())
+            .unwrap_or_default(),
+        _ => false,
+    }
+}
+
+/// Decodes all frames of an animated image.
+///
+/// The frames are composited, i.e. each of them has the size of the whole image.
+fn decode_frames<R: BufRead + Seek>(
+    fmt: ImageFormat,
+    r: R,
+    limits: DecodingLimits,
+) -> Result<Vec<Frame>> {
+    fn collect(mut frames: Frames<'_>, limits: DecodingLimits) -> Result<Vec<Frame>> {
+        let mut res = Vec::new();
+        let mut total_bytes = 0u64;
+        frames.try_for_each(|frame| {
+            let frame = frame?;
+            total_bytes = total_bytes.saturating_add(frame.buffer().as_raw().len() as u64);
+            limits.check_alloc(total_bytes)?;
+            res.push(frame);
+            anyhow::Ok(())
+        })?;
+        Ok(res)
+    }
+
+    let frames = match fmt {
+        ImageFormat::Gif => {
+            let mut decoder = GifDecoder::new(r)?;
+            decoder.set_limits(limits.image_limits())?;
+            collect(decoder.into_frames(), limits)?
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::with_limits(r, limits.image_limits())?;
+            collect(decoder.apng()?.into_frames(), limits)?
+        }
+        ImageFormat::WebP => {
+            let mut decoder = WebPDecoder::new(r)?;
+            decoder.set_limits(limits.image_limits())?;
+            collect(decoder.into_frames(), limits)?
+        }
+        _ => bail!("{fmt:?} images cannot be animated"),
+    };
+    ensure!(!frames.is_empty(), "Animated image has no frames");
+    Ok(frames)
+}
+
+/// Scales all frames to fit into `img_wh` and encodes them as an animated GIF.
+fn encode_scaled_gif(frames: &[Frame], img_wh: u32, encoded: &mut Vec<u8>) -> Result<()> {
+    encoded.clear();
+    let mut encoder = GifEncoder::new(&mut *encoded);
+    encoder.set_repeat(Repeat::Infinite)?;
+    for frame in frames {
+        let scaled = DynamicImage::ImageRgba8(frame.buffer().clone())
+            .thumbnail(img_wh, img_wh)
+            .into_rgba8();
+        encoder.encode_frame(Frame::from_parts(scaled, 0, 0, frame.delay()))?;
+    }
+    Ok(())
+}
+
+/// Copies a PNG or WebP image from `r` to `w`, removing metadata such as Exif and XMP
+/// without touching the image data.
+fn strip_metadata(fmt: ImageFormat, r: &mut impl Read, w: &mut (impl Write + Seek)) -> Result<()> {
+    match fmt {
+        ImageFormat::Png => strip_png_metadata(r, w),
+        ImageFormat::WebP => strip_webp_metadata(r, w),
+        _ => bail!("Cannot remove metadata from {fmt:?} images"),
+    }
+}
+
+/// Reads the 8-byte header of a PNG or WebP chunk.
+///
+/// Returns `false` at the end of the image.
+fn read_chunk_header(r: &mut impl Read, header: &mut [u8; 8]) -> Result<bool> {
+    let len = io::copy(&mut r.by_ref().take(8), &mut Cursor::new(&mut header[..]))?;
+    match len {
+        0 => Ok(false),
+        8 => Ok(true),
+        _ => bail!("Truncated chunk header"),
+    }
+}
+
+fn strip_png_metadata(r: &mut impl Read, w: &mut impl Write) -> Result<()> {
+    let mut signature = [0; 8];
+    r.read_exact(&mut signature).context("PNG too short")?;
+    w.write_all(&signature)?;
+    let mut header = [0; 8];
+    while read_chunk_header(r, &mut header)? {
+        let (len, typ) = header.split_at(4);
+        // Data and CRC.
+        let len = u64::from(u32::from_be_bytes(len.try_into()?)) + 4;
+        let mut chunk = r.by_ref().take(len);
+        let copied = if matches!(typ, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            io::copy(&mut chunk, &mut io::sink())?
+        } else {
+            w.write_all(&header)?;
+            io::copy(&mut chunk, w)?
+        };
+        ensure!(copied == len, "Truncated PNG chunk");
+    }
+    Ok(())
+}
+
+fn strip_webp_metadata(r: &mut impl Read, w: &mut (impl Write + Seek)) -> Result<()> {
+    let mut header = [0; 12];
+    r.read_exact(&mut header).context("WebP too short")?;
+    ensure!(
+        header.starts_with(b"RIFF") && header.ends_with(b"WEBP"),
+        "Not a WebP image"
+    );
+    let start = w.stream_position()?;
+    w.write_all(&header)?;
+    let mut header = [0; 8];
+    while read_chunk_header(r, &mut header)? {
+        let (fourcc, len) = header.split_at(4);
+        let len = u64::from(u32::from_le_bytes(len.try_into()?));
+        // Chunks are padded to an even length, the padding may be missing at the end.
+        let mut chunk = r.by_ref().take(len + (len & 1));
+        let copied = match fourcc {
+            b"EXIF" | b"XMP " => io::copy(&mut chunk, &mut io::sink())?,
+            b"VP8X" => {
+                let mut data = Vec::new();
+                chunk.read_to_end(&mut data)?;
+                // Clear the Exif and XMP flags.
+                if let Some(flags) = data.first_mut() {
+                    *flags &= !0x0c;
+                }
+                w.write_all(&header)?;
+                w.write_all(&data)?;
+                data.len() as u64
+            }
+            _ => {
+                w.write_all(&header)?;
+                io::copy(&mut chunk, w)?
+            }
+        };
+        ensure!(copied >= len, "Truncated WebP chunk");
+    }
+    let end = w.stream_position()?;
+    let riff_len = u32::try_from(end - start - 8)?;
+    w.seek(SeekFrom::Start(start + 4))?;
+    w.write_all(&riff_len.to_le_bytes())?;
+    w.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+/// Recodes an animated image read from `file` of `nr_bytes` bytes
+/// as [`super::recode_image`] does for still images.
+///
+/// If the image does not need to be scaled down, it is left untouched
+/// except for removing metadata if `has_metadata` is set.
+/// Otherwise all frames are scaled down and the image is converted to an animated GIF.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn recode_animation(
+    context: &Context,
+    file: &File,
+    nr_bytes: u64,
+    fmt: ImageFormat,
+    mut blob_abs: PathBuf,
+    maybe_sticker: &mut bool,
+    mut img_wh: u32,
+    max_bytes: usize,
+    has_metadata: bool,
+    strict_limits: bool,
+    limits: DecodingLimits,
+    control: &RecodeControl,
+) -> Result<Option<String>> {
+    let mut reader = BufReader::new(file);
+    reader.rewind()?;
+    let frames = match decode_frames(fmt, reader, limits) {
+        Ok(frames) => frames,
+        Err(err) if err.is::<ImageLimitsExceeded>() => return Err(err),
+        Err(err) => match err.downcast::<image::ImageError>() {
+            Ok(image::ImageError::Limits(err)) => {
+                return Err(ImageLimitsExceeded(err.to_string()).into())
+            }
+            Ok(err) => return Err(err).context("animated image decode failure"),
+            Err(err) => return Err(err),
+        },
+    };
+    control.check_cancelled()?;
+    control.progress(context, 500);
+
+    let first = frames
+        .first()
+        .context("Animated image has no frames")?
+        .buffer();
+    let (width, height) = first.dimensions();
+    if *maybe_sticker {
+        let x_max = width.saturating_sub(1);
+        let y_max = height.saturating_sub(1);
+        *maybe_sticker = [(0, 0), (x_max, 0), (0, y_max), (x_max, y_max)]
+            .iter()
+            .any(|(x, y)| first.get_pixel(*x, *y).0[3] == 0);
+    }
+    if *maybe_sticker && !has_metadata {
+        return Ok(None);
+    }
+
+    let exceeds_wh = width > img_wh || height > img_wh;
+    let exceeds_max_bytes = nr_bytes > max_bytes as u64;
+    if !exceeds_max_bytes && !(strict_limits && exceeds_wh) {
+        if has_metadata && matches!(fmt, ImageFormat::Png | ImageFormat::WebP) {
+            let mut reader = BufReader::new(file);
+            reader.rewind()?;
+            // The source may be `blob_abs` itself, so write to a temporary file first.
+            let file_name = blob_abs.file_name().context("No image file name (???)")?;
+            let tmp_path = TempPathGuard::new(blob_abs.with_file_name(format!(
+                ".{}.{}.tmp",
+                file_name.to_string_lossy(),
+                create_id()
+            )));
+            let mut writer = BufWriter::new(
+                OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&*tmp_path)?,
+            );
+            strip_metadata(fmt, &mut reader, &mut writer)?;
+            writer.flush()?;
+            drop(writer);
+            control.check_cancelled()?;
+            std::fs::rename(&*tmp_path, &blob_abs)
+                .context("failed to write stripped animation to file")?;
+        }
+        return Ok(None);
+    }
+
+    if !exceeds_wh {
+        img_wh = width.max(height) * 2 / 3;
+    }
+    let mut encoded = Vec::new();
+    let mut progress = 600;
+    loop {
+        control.check_cancelled()?;
+        control.progress(context, progress);
+        progress += (900 - progress) / 2;
+        encode_scaled_gif(&frames, img_wh, &mut encoded)?;
+        if encoded.len() > max_bytes && strict_limits {
+            if img_wh < 20 {
+                bail!("Failed to scale animation to below {max_bytes}B.");
+            }
+            img_wh = img_wh * 2 / 3;
+        } else {
+            info!(
+                context,
+                "Final scaled-down animation size: {}B ({}px, {} frames).",
+                encoded.len(),
+                img_wh,
+                frames.len()
+            );
+            break;
+        }
+    }
+
+    let mut changed_name = None;
+    if fmt != ImageFormat::Gif {
+        blob_abs = blob_abs.with_extension("gif");
+        changed_name = Some(blob_name(&blob_abs)?);
+    }
+    control.check_cancelled()?;
+    std::fs::write(&blob_abs, &encoded).context("failed to write recoded animation to file")?;
+    Ok(changed_name)
+}
+
+fn blob_name(blob_abs: &Path) -> Result<String> {
+    let file_name = blob_abs.file_name().context("No image file name (???)")?;
+    let file_name = file_name.to_str().context("Filename is no UTF-8 (???)")?;
+    Ok(format!("$BLOBDIR/{file_name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Delay, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::blob::BlobObject;
+    use crate::config::Config;
+    use crate::constants::MediaQuality;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContext;
+
+    fn animated_gif(size: u32, frame_cnt: u8) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        let mut seed = 0x2545_f491_u32;
+        let frames: Vec<Frame> = (0..frame_cnt)
+            .map(|_| {
+                // Noise does not compress well, so the image is large.
+                let buffer = RgbaImage::from_fn(size, size, |_x, _y| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+                    let [r, g, b, _] = seed.to_le_bytes();
+                    Rgba([r, g, b, 255])
+                });
+                Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(100, 1))
+            })
+            .collect();
+        encode_scaled_gif(&frames, size, &mut encoded).unwrap();
+        encoded
+    }
+
+    #[test]
+    fn test_is_animated() {
+        assert!(is_animated(
+            ImageFormat::Gif,
+            Cursor::new(animated_gif(10, 3))
+        ));
+        assert!(!is_animated(
+            ImageFormat::Gif,
+            Cursor::new(animated_gif(10, 1))
+        ));
+        assert!(!is_animated(
+            ImageFormat::Png,
+            Cursor::new(include_bytes!("../../test-data/image/avatar64x64.png"))
+        ));
+    }
+
+    #[test]
+    fn test_decode_and_scale_frames() -> Result<()> {
+        let data = animated_gif(40, 4);
+        let frames = decode_frames(
+            ImageFormat::Gif,
+            Cursor::new(&data),
+            DecodingLimits::default(),
+        )?;
+        assert_eq!(frames.len(), 4);
+
+        let mut encoded = Vec::new();
+        encode_scaled_gif(&frames, 20, &mut encoded)?;
+        let frames = decode_frames(
+            ImageFormat::Gif,
+            Cursor::new(&encoded),
+            DecodingLimits::default(),
+        )?;
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].buffer().dimensions(), (20, 20));
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_png_metadata() -> Result<()> {
+        let data = include_bytes!("../../test-data/image/avatar64x64.png");
+        let mut stripped = Vec::new();
+        strip_png_metadata(&mut &data[..], &mut stripped)?;
+        assert!(stripped.len() <= data.len());
+        assert!(!stripped.windows(4).any(|w| w == b"tEXt" || w == b"eXIf"));
+        image::load_from_memory_with_format(&stripped, ImageFormat::Png)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_webp_metadata() -> Result<()> {
+        let mut data = b"RIFF\0\0\0\0WEBP".to_vec();
+        data.extend_from_slice(b"VP8X\x0a\0\0\0\x0c\0\0\0\0\0\0\0\0\0");
+        data.extend_from_slice(b"ANIM\x06\0\0\0\0\0\0\0\0\0");
+        data.extend_from_slice(b"EXIF\x03\0\0\0abc\0");
+        data.extend_from_slice(b"XMP \x02\0\0\0xy");
+        let riff_len = (data.len() - 8) as u32;
+        data[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+        let mut stripped = Cursor::new(Vec::new());
+        strip_webp_metadata(&mut &data[..], &mut stripped)?;
+        let stripped = stripped.into_inner();
+        assert_eq!(stripped.len(), 12 + 18 + 14);
+        assert_eq!(&stripped[4..8], &(stripped.len() as u32 - 8).to_le_bytes());
+        // Exif and XMP flags are cleared.
+        assert_eq!(stripped[20], 0);
+        assert!(!stripped.windows(4).any(|w| w == b"EXIF" || w == b"XMP "));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_recode_keeps_animation() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(
+            Config::MediaQuality,
+            Some(&(MediaQuality::Worse as i32).to_string()),
+        )
+        .await?;
+        t.set_config(Config::WorseImageBytes, Some("10000")).await?;
+
+        let data = animated_gif(200, 3);
+        assert!(data.len() > 10_000);
+        let file = t.get_blobdir().join("anim.gif");
+        std::fs::write(&file, &data)?;
+        let mut blob = BlobObject::new_from_path(&t, &file).await?;
+        let mut maybe_sticker = false;
+        blob.recode_to_image_size(&t, &mut maybe_sticker).await?;
+
+        let recoded = std::fs::read(blob.to_abs_path())?;
+        assert!(recoded.len() < data.len());
+        assert!(is_animated(ImageFormat::Gif, Cursor::new(&recoded)));
+        let frames = decode_frames(
+            ImageFormat::Gif,
+            Cursor::new(&recoded),
+            DecodingLimits::default(),
+        )?;
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].buffer().dimensions(), (133, 133));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_gif_scaled_down() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(
+            Config::MediaQuality,
+            Some(&(MediaQuality::Worse as i32).to_string()),
+        )
+        .await?;
+        t.set_config(Config::WorseImageBytes, Some("10000")).await?;
+        let chat = t.create_chat_with_contact("Bob", "bob@example.net").await;
+
+        let data = animated_gif(200, 3);
+        let mut msg = Message::new(Viewtype::Gif);
+        msg.set_file_from_bytes(&t, "anim.gif", &data, None).await?;
+        let sent = t.send_msg(chat.id, &mut msg).await;
+
+        let msg = Message::load_from_db(&t, sent.sender_msg_id).await?;
+        assert_eq!(msg.get_viewtype(), Viewtype::Gif);
+        let recoded = std::fs::read(msg.get_file(&t).context("No file")?)?;
+        assert!(recoded.len() < data.len());
+        assert!(is_animated(ImageFormat::Gif, Cursor::new(&recoded)));
+        Ok(())
+    }
+}
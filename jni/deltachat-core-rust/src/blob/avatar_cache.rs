@@ -0,0 +1,140 @@
+//! # Cache of recoded avatars.
+//!
+//! Recoding an avatar decodes and encodes the image,
+//! which is expensive on mobile devices.
+//! The same avatar is recoded again and again
+//! when it is set for several groups or received repeatedly from a contact,
+//! so the recoded blobs are remembered by the SHA-256 hash of the source image
+//! and the target size and quality.
+//!
+//! Recodings are done one at a time,
+//! so that identical recodings running concurrently are only done once.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::integrity::hash_file;
+use super::BlobObject;
+use crate::context::Context;
+
+/// Maximum number of cached recodings.
+///
+/// The cache is cleared when it is full, it only needs to catch repetitions in a short time.
+const MAX_ENTRIES: usize = 64;
+
+/// Source image and parameters of an avatar recoding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct AvatarKey {
+    /// Hex-encoded SHA-256 hash of the source image.
+    sha256: String,
+
+    /// Maximum width and height.
+    img_wh: u32,
+
+    /// JPEG quality.
+    quality: u8,
+}
+
+/// Cache of recoded avatars, mapping recodings to the names of the resulting blobs.
+#[derive(Debug, Default)]
+pub(crate) struct AvatarCache {
+    entries: Mutex<HashMap<AvatarKey, String>>,
+}
+
+impl AvatarCache {
+    /// Locks the cache for a recoding.
+    ///
+    /// The lock should be held until the result is inserted.
+    pub(super) async fn lock(&self) -> AvatarCacheGuard<'_> {
+        AvatarCacheGuard {
+            entries: self.entries.lock().await,
+        }
+    }
+}
+
+/// Locked [`AvatarCache`].
+#[derive(Debug)]
+pub(super) struct AvatarCacheGuard<'a> {
+    entries: MutexGuard<'a, HashMap<AvatarKey, String>>,
+}
+
+impl AvatarCacheGuard<'_> {
+    /// Returns the cached recoding of `key` if its blob still exists unaltered.
+    pub(super) async fn get<'a>(
+        &mut self,
+        context: &'a Context,
+        key: &AvatarKey,
+    ) -> Result<Option<BlobObject<'a>>> {
+        let Some(name) = self.entries.get(key).cloned() else {
+            return Ok(None);
+        };
+        let blob = BlobObject::from_name(context, name)?;
+        if blob.to_abs_path().exists() && blob.verify(context).await? {
+            Ok(Some(blob))
+        } else {
+            // The blob was removed by the housekeeping.
+            self.entries.remove(key);
+            Ok(None)
+        }
+    }
+
+    /// Remembers that `blob` is the recoding of `key`.
+    pub(super) fn insert(&mut self, key: AvatarKey, blob: &BlobObject<'_>) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.clear();
+        }
+        self.entries.insert(key, blob.as_name().to_string());
+    }
+}
+
+impl BlobObject<'_> {
+    /// Returns the key of recoding the blob to `img_wh` with JPEG quality `quality`.
+    pub(super) async fn avatar_key(&self, img_wh: u32, quality: u8) -> Result<AvatarKey> {
+        let path = self.to_abs_path();
+        let sha256 = tokio::task::spawn_blocking(move || hash_file(&path)).await??;
+        Ok(AvatarKey {
+            sha256,
+            img_wh,
+            quality,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_avatar_cache() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let avatar_src = t.dir.path().join("avatar.jpg");
+        let avatar_bytes = include_bytes!("../../test-data/image/avatar1000x1000.jpg");
+        tokio::fs::write(&avatar_src, avatar_bytes).await?;
+
+        t.set_config(Config::Selfavatar, avatar_src.to_str())
+            .await?;
+        let avatar = t.get_config(Config::Selfavatar).await?.unwrap();
+
+        // The same image is not recoded again.
+        t.set_config(Config::Selfavatar, None).await?;
+        t.set_config(Config::Selfavatar, avatar_src.to_str())
+            .await?;
+        assert_eq!(t.get_config(Config::Selfavatar).await?.unwrap(), avatar);
+
+        // Neither is the recoded avatar.
+        let mut blob = BlobObject::new_from_path(&t, avatar.as_ref()).await?;
+        blob.recode_to_avatar_size(&t).await?;
+        assert_eq!(blob.to_abs_path().to_str(), Some(avatar.as_str()));
+
+        // A removed recoding is not used.
+        tokio::fs::remove_file(&avatar).await?;
+        let mut blob = BlobObject::new_from_path(&t, &avatar_src).await?;
+        blob.recode_to_avatar_size(&t).await?;
+        assert!(blob.to_abs_path().exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! # Integrity checks of blobs.
+//!
+//! The SHA-256 checksum of every blob is stored in the `blob_checksums` table
+//! when the blob is created or rewritten by the core.
+//! Storage corruption, which happens on some Android devices,
+//! is detected by comparing the content to the stored checksum,
+//! see [`BlobObject::verify`] and [`check_blob_integrity`].
+//! Otherwise broken attachments would be shown and forwarded without notice.
+//!
+//! Blobs created by older versions or imported from a backup have no checksum
+//! and are assumed to be intact.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::BlobObject;
+use crate::constants::DC_CHAT_ID_TRASH;
+use crate::context::Context;
+use crate::message::MsgId;
+use crate::param::{Param, Params};
+
+/// Copies `reader` to `writer` and returns the hex-encoded SHA-256 hash of the data.
+pub(super) async fn copy_hashed(
+    reader: &mut (impl AsyncRead + Unpin),
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        let Some(chunk) = buf.get(..n).filter(|chunk| !chunk.is_empty()) else {
+            break;
+        };
+        hasher.update(chunk);
+        writer.write_all(chunk).await?;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Returns the hex-encoded SHA-256 hash of the file at `path`
+/// without reading it into memory as a whole.
+///
+/// This blocks, so it should run on a blocking thread.
+pub(crate) fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+impl BlobObject<'_> {
+    /// Stores `sha256` as checksum of the blob content.
+    pub(super) async fn store_checksum(&self, context: &Context, sha256: &str) -> Result<()> {
+        context
+            .sql
+            .execute(
+                "INSERT OR REPLACE INTO blob_checksums (name, sha256) VALUES (?, ?)",
+                (self.as_file_name(), sha256),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Stores the checksum of the current blob content
+    /// after the blob was rewritten in place.
+    pub(super) async fn update_checksum(&self, context: &Context) -> Result<()> {
+        let path = self.to_abs_path();
+        let sha256 = tokio::task::spawn_blocking(move || hash_file(&path)).await??;
+        self.store_checksum(context, &sha256).await
+    }
+
+    /// Returns the hex-encoded SHA-256 hash of the blob content.
+    ///
+    /// The checksum stored when the blob was created is used if there is one,
+    /// otherwise the blob is hashed.
+    pub(crate) async fn checksum(&self, context: &Context) -> Result<String> {
+        let stored = context
+            .sql
+            .query_get_value::<String>(
+                "SELECT sha256 FROM blob_checksums WHERE name=?",
+                (self.as_file_name(),),
+            )
+            .await?;
+        match stored {
+            Some(sha256) => Ok(sha256),
+            None => self.sha256().await,
+        }
+    }
+
+    /// Checks the blob content against the checksum stored when the blob was created.
+    ///
+    /// Returns false if the blob is corrupted or missing.
+    /// Blobs without a checksum are assumed to be intact.
+    pub async fn verify(&self, context: &Context) -> Result<bool> {
+        let Some(expected) = context
+            .sql
+            .query_get_value::<String>(
+                "SELECT sha256 FROM blob_checksums WHERE name=?",
+                (self.as_file_name(),),
+            )
+            .await?
+        else {
+            return Ok(true);
+        };
+        let path = self.to_abs_path();
+        match tokio::task::spawn_blocking(move || hash_file(&path)).await? {
+            Ok(sha256) => Ok(sha256 == expected),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", self.as_name())),
+        }
+    }
+}
+
+/// Verifies the attachments of all messages and returns the messages with corrupted attachments.
+///
+/// Every blob is read completely, so this may take a while
+/// and should be triggered by the user, e.g. from the storage settings.
+pub async fn check_blob_integrity(context: &Context) -> Result<Vec<MsgId>> {
+    let attachments = context
+        .sql
+        .query_map(
+            "SELECT id, param FROM msgs WHERE chat_id!=? ORDER BY id",
+            (DC_CHAT_ID_TRASH,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let param: String = row.get(1)?;
+                Ok((msg_id, param))
+            },
+            |rows| {
+                let mut attachments = Vec::new();
+                for row in rows {
+                    let (msg_id, param) = row?;
+                    let param: Params = param.parse().unwrap_or_default();
+                    if let Some(file) = param.get(Param::File) {
+                        attachments.push((msg_id, file.to_string()));
+                    }
+                }
+                Ok(attachments)
+            },
+        )
+        .await?;
+
+    // Blobs may be shared by several messages.
+    let mut verified: HashMap<String, bool> = HashMap::new();
+    let mut corrupted = Vec::new();
+    for (msg_id, file) in attachments {
+        let Ok(blob) = BlobObject::from_name(context, file) else {
+            continue;
+        };
+        let intact = match verified.get(blob.as_name()) {
+            Some(intact) => *intact,
+            None => {
+                let intact = blob.verify(context).await?;
+                if !intact {
+                    warn!(context, "Attachment {} is corrupted.", blob.as_name());
+                }
+                verified.insert(blob.as_name().to_string(), intact);
+                intact
+            }
+        };
+        if !intact {
+            corrupted.push(msg_id);
+        }
+    }
+    info!(
+        context,
+        "Verified {} attachments, {} messages have corrupted attachments.",
+        verified.len(),
+        corrupted.len()
+    );
+    Ok(corrupted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_blob_integrity() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let blob = BlobObject::create(alice, "foo.txt", b"hello").await?;
+        assert!(blob.verify(alice).await?);
+
+        let src = alice.dir.path().join("bar.txt");
+        tokio::fs::write(&src, b"world").await?;
+        let copied = BlobObject::create_and_copy(alice, &src).await?;
+        assert!(copied.verify(alice).await?);
+
+        // Blobs without a checksum are assumed to be intact.
+        tokio::fs::write(alice.get_blobdir().join("old.txt"), b"old").await?;
+        let old = BlobObject::from_name(alice, "old.txt".to_string())?;
+        assert!(old.verify(alice).await?);
+
+        let chat_id = alice.create_chat(bob).await.id;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "file.txt", b"content", None)
+            .await?;
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        assert!(check_blob_integrity(alice).await?.is_empty());
+
+        let file = msg.get_file(alice).unwrap();
+        tokio::fs::write(&file, b"c0ntent").await?;
+        let blob = BlobObject::from_path(alice, &file)?;
+        assert!(!blob.verify(alice).await?);
+        assert_eq!(check_blob_integrity(alice).await?, vec![sent.sender_msg_id]);
+
+        tokio::fs::remove_file(&file).await?;
+        assert!(!blob.verify(alice).await?);
+        Ok(())
+    }
+}
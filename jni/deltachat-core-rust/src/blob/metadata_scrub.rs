@@ -0,0 +1,446 @@
+//! # Removal of privacy-sensitive metadata from attachments other than images.
+//!
+//! Videos, audio files and documents may contain the location where they were recorded,
+//! the recording device or the name of the author.
+//! Images lose such metadata when they are recoded,
+//! other attachments are scrubbed by the format-specific functions of this module
+//! before sending, see [`crate::config::Config::StripAttachmentMetadata`].
+//!
+//! Where offsets into the file must not change,
+//! metadata is overwritten in place instead of being removed:
+//!
+//! - MP4 and QuickTime: `udta`, `meta` and XMP boxes are turned into `free` boxes.
+//! - MP3: ID3v2 and ID3v1 tags are removed.
+//! - FLAC: Vorbis comment and picture blocks are turned into padding.
+//! - PDF: author, creator, producer, subject and keywords of the document information
+//!   and uncompressed XMP packets are blanked.
+//!
+//! Other formats are sent as is.
+
+use anyhow::{ensure, Context as _, Result};
+
+/// Box types whose contents are scanned for metadata boxes.
+const MP4_CONTAINER_BOXES: &[&[u8]] = &[b"moov", b"trak"];
+
+/// Box types containing user data, e.g. the location and the recording device.
+const MP4_METADATA_BOXES: &[&[u8]] = &[b"udta", b"meta"];
+
+/// Extended type of `uuid` boxes containing XMP metadata.
+const MP4_XMP_UUID: &[u8] = &[
+    0xbe, 0x7a, 0xcf, 0xcb, 0x97, 0xa9, 0x42, 0xe8, 0x9c, 0x71, 0x99, 0x94, 0x91, 0xe3, 0xaf, 0xac,
+];
+
+/// FLAC metadata block types containing user data.
+const FLAC_VORBIS_COMMENT: u8 = 4;
+const FLAC_PICTURE: u8 = 6;
+const FLAC_PADDING: u8 = 1;
+
+/// Keys of the PDF document information dictionary whose values are blanked.
+const PDF_INFO_KEYS: &[&[u8]] = &[
+    b"/Author",
+    b"/Creator",
+    b"/Producer",
+    b"/Subject",
+    b"/Keywords",
+];
+
+/// Number of bytes at the start of a file needed by [`is_supported`].
+pub(super) const HEADER_LEN: usize = 8;
+
+/// Returns true if `header`, the first [`HEADER_LEN`] bytes of a file,
+/// belong to a format supported by [`scrub`].
+pub(super) fn is_supported(header: &[u8]) -> bool {
+    header.get(4..8) == Some(&b"ftyp"[..])
+        || header.starts_with(b"fLaC")
+        || header.starts_with(b"%PDF-")
+        || header.starts_with(b"ID3")
+        || is_mpeg_audio_frame(header)
+}
+
+/// Removes metadata from `data` if its format is supported.
+///
+/// `data` is modified in place and returned if anything was removed.
+/// Returns `None` if the format is not supported or there is no metadata to remove.
+pub(super) fn scrub(mut data: Vec<u8>) -> Result<Option<Vec<u8>>> {
+    let changed = if data.get(4..8) == Some(&b"ftyp"[..]) {
+        scrub_mp4_boxes(&mut data, false)?
+    } else if data.starts_with(b"fLaC") {
+        scrub_flac(&mut data)?
+    } else if data.starts_with(b"%PDF-") {
+        scrub_pdf(&mut data)
+    } else if data.starts_with(b"ID3") || is_mpeg_audio_frame(&data) {
+        scrub_mp3(&mut data)?
+    } else {
+        false
+    };
+    Ok(changed.then_some(data))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+/// Turns metadata boxes in `data` into `free` boxes of the same size,
+/// so that the offsets of the media data stay valid.
+///
+/// `meta` boxes are only removed inside the movie,
+/// at the top level they contain the image items of HEIF images.
+///
+/// Returns true if anything was changed.
+fn scrub_mp4_boxes(data: &mut [u8], in_movie: bool) -> Result<bool> {
+    let mut changed = false;
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = read_u32(data, pos).context("Truncated MP4 box")?;
+        let (header_len, size) = match size {
+            0 => (8, data.len() - pos),
+            1 => (
+                16,
+                usize::try_from(read_u64(data, pos + 8).context("Truncated MP4 box")?)?,
+            ),
+            size => (8, usize::try_from(size)?),
+        };
+        let end = pos
+            .checked_add(size)
+            .filter(|end| size >= header_len && *end <= data.len())
+            .context("Invalid MP4 box size")?;
+        let typ = data.get(pos + 4..pos + 8).context("Truncated MP4 box")?;
+        let body = data.get(pos + header_len..end).unwrap_or_default();
+        let is_metadata = in_movie && MP4_METADATA_BOXES.contains(&typ)
+            || typ == b"uuid" && body.starts_with(MP4_XMP_UUID);
+        let is_container = MP4_CONTAINER_BOXES.contains(&typ);
+        if is_metadata {
+            if let Some(typ) = data.get_mut(pos + 4..pos + 8) {
+                typ.copy_from_slice(b"free");
+            }
+            if let Some(body) = data.get_mut(pos + header_len..end) {
+                body.fill(0);
+            }
+            changed = true;
+        } else if is_container {
+            if let Some(body) = data.get_mut(pos + header_len..end) {
+                changed |= scrub_mp4_boxes(body, true)?;
+            }
+        }
+        pos = end;
+    }
+    Ok(changed)
+}
+
+/// Turns Vorbis comment and picture blocks into padding blocks of the same size.
+///
+/// Returns true if anything was changed.
+fn scrub_flac(data: &mut [u8]) -> Result<bool> {
+    let mut changed = false;
+    let mut pos = 4;
+    loop {
+        let header = *data.get(pos).context("Truncated FLAC metadata block")?;
+        let len = read_u32(data, pos).context("Truncated FLAC metadata block")? & 0xff_ffff;
+        let end = pos + 4 + usize::try_from(len)?;
+        ensure!(end <= data.len(), "Truncated FLAC metadata block");
+        let is_last = header & 0x80 != 0;
+        if matches!(header & 0x7f, FLAC_VORBIS_COMMENT | FLAC_PICTURE) {
+            if let Some(header) = data.get_mut(pos) {
+                *header = (*header & 0x80) | FLAC_PADDING;
+            }
+            if let Some(body) = data.get_mut(pos + 4..end) {
+                body.fill(0);
+            }
+            changed = true;
+        }
+        if is_last {
+            return Ok(changed);
+        }
+        pos = end;
+    }
+}
+
+/// Returns true if `data` starts with an MPEG audio frame header.
+fn is_mpeg_audio_frame(data: &[u8]) -> bool {
+    matches!(data, [0xff, second, ..] if second & 0xe0 == 0xe0)
+}
+
+/// Removes ID3v2 tags from the beginning and ID3v1 tags from the end of an MP3 file.
+///
+/// Returns true if anything was removed.
+fn scrub_mp3(data: &mut Vec<u8>) -> Result<bool> {
+    let mut start = 0;
+    if data.starts_with(b"ID3") {
+        let header = data.get(..10).context("Truncated ID3 tag")?;
+        let flags = header.get(5).copied().unwrap_or_default();
+        // The size is a "synchsafe" integer, 7 bits per byte.
+        let size = header
+            .get(6..10)
+            .unwrap_or_default()
+            .iter()
+            .fold(0usize, |size, byte| (size << 7) | usize::from(byte & 0x7f));
+        // A footer follows the tag if the flag is set.
+        let footer_len = if flags & 0x10 != 0 { 10 } else { 0 };
+        start = 10 + size + footer_len;
+        ensure!(start <= data.len(), "Truncated ID3 tag");
+    }
+    let mut end = data.len();
+    if end - start >= 128
+        && data
+            .get(end - 128..)
+            .is_some_and(|tag| tag.starts_with(b"TAG"))
+    {
+        end -= 128;
+    }
+    if start == 0 && end == data.len() {
+        return Ok(false);
+    }
+    data.truncate(end);
+    data.drain(..start);
+    Ok(true)
+}
+
+/// Returns the position of `needle` in `haystack` at or after `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
+
+/// Blanks the values of [`PDF_INFO_KEYS`] and the contents of XMP packets.
+///
+/// Metadata inside compressed object streams is not found.
+/// The length of the file is not changed so that the cross-reference table stays valid.
+///
+/// Returns true if anything was changed.
+fn scrub_pdf(data: &mut [u8]) -> bool {
+    let mut changed = false;
+    for key in PDF_INFO_KEYS {
+        let mut pos = 0;
+        while let Some(key_pos) = find(data, key, pos) {
+            pos = key_pos + key.len();
+            if let Some(end) = blank_pdf_string(data, pos) {
+                changed = true;
+                pos = end;
+            }
+        }
+    }
+
+    let mut pos = 0;
+    while let Some(begin) = find(data, b"<?xpacket begin=", pos) {
+        let Some(content_start) = find(data, b"?>", begin).map(|pos| pos + 2) else {
+            break;
+        };
+        let Some(content_end) = find(data, b"<?xpacket end=", content_start) else {
+            break;
+        };
+        if let Some(content) = data.get_mut(content_start..content_end) {
+            content.fill(b' ');
+            changed = true;
+        }
+        pos = content_end;
+    }
+    changed
+}
+
+/// Blanks the string starting at `pos` after optional whitespace.
+///
+/// Literal strings are filled with spaces, hexadecimal strings with whitespace,
+/// which is ignored inside them, so they become empty.
+/// Returns the position after the string or `None` if there is no string.
+fn blank_pdf_string(data: &mut [u8], pos: usize) -> Option<usize> {
+    let start = pos
+        + data
+            .get(pos..)?
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())?;
+    match data.get(start)? {
+        b'(' => {
+            // Literal string, may contain balanced parentheses and escaped characters.
+            let mut depth = 0usize;
+            let mut escaped = false;
+            let mut end = None;
+            for (i, byte) in data.get(start..)?.iter().enumerate() {
+                if escaped {
+                    escaped = false;
+                    continue;
+                }
+                match byte {
+                    b'\\' => escaped = true,
+                    b'(' => depth += 1,
+                    b')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = Some(start + i);
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let end = end?;
+            data.get_mut(start + 1..end)?.fill(b' ');
+            Some(end + 1)
+        }
+        b'<' if data.get(start + 1) != Some(&b'<') => {
+            // Hexadecimal string, `<<` starts a dictionary instead.
+            let end = find(data, b">", start)?;
+            data.get_mut(start + 1..end)?.fill(b' ');
+            Some(end + 1)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{self, ChatId};
+    use crate::config::Config;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContext;
+
+    fn mp4_box(typ: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut res = (u32::try_from(body.len() + 8).unwrap())
+            .to_be_bytes()
+            .to_vec();
+        res.extend_from_slice(typ);
+        res.extend_from_slice(body);
+        res
+    }
+
+    #[test]
+    fn test_scrub_mp4() {
+        let location = mp4_box(b"\xa9xyz", b"+52.5200+013.4050/");
+        let trak = mp4_box(
+            b"trak",
+            &[mp4_box(b"tkhd", &[1; 84]), mp4_box(b"udta", b"camera")].concat(),
+        );
+        let moov = mp4_box(
+            b"moov",
+            &[
+                mp4_box(b"mvhd", &[2; 100]),
+                trak,
+                mp4_box(b"udta", &location),
+            ]
+            .concat(),
+        );
+        let xmp = mp4_box(b"uuid", &[MP4_XMP_UUID, b"<x:xmpmeta/>"].concat());
+        let data = [
+            mp4_box(b"ftyp", b"isom\0\0\x02\0"),
+            moov,
+            xmp,
+            mp4_box(b"mdat", &[0xaa; 100]),
+        ]
+        .concat();
+
+        let scrubbed = scrub(data.clone()).unwrap().unwrap();
+        assert_eq!(scrubbed.len(), data.len());
+        assert!(find(&scrubbed, b"udta", 0).is_none());
+        assert!(find(&scrubbed, b"+52.5200", 0).is_none());
+        assert!(find(&scrubbed, b"xmpmeta", 0).is_none());
+        assert!(find(&scrubbed, b"camera", 0).is_none());
+        assert!(find(&scrubbed, &[0xaa; 100], 0).is_some());
+        assert!(find(&scrubbed, &[1; 84], 0).is_some());
+
+        // Nothing to remove.
+        assert_eq!(scrub(scrubbed).unwrap(), None);
+    }
+
+    #[test]
+    fn test_scrub_mp3() {
+        let frames = [0xff, 0xfb, 0x90, 0x00, 1, 2, 3, 4];
+        let mut tag = b"ID3\x04\x00\x00\x00\x00\x00\x05".to_vec();
+        tag.extend_from_slice(b"TPE1x");
+        let mut id3v1 = b"TAGtitle".to_vec();
+        id3v1.resize(128, 0);
+        let data = [&tag[..], &frames, &id3v1].concat();
+
+        assert_eq!(scrub(data).unwrap().unwrap(), frames);
+        assert_eq!(scrub(frames.to_vec()).unwrap(), None);
+        assert!(scrub(b"ID3\x04\x00\x00\x00\x00\x01\x00".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_scrub_flac() {
+        let mut data = b"fLaC".to_vec();
+        data.extend_from_slice(&[0, 0, 0, 4, 1, 2, 3, 4]);
+        data.extend_from_slice(&[FLAC_VORBIS_COMMENT, 0, 0, 6]);
+        data.extend_from_slice(b"artist");
+        data.extend_from_slice(&[0x80 | FLAC_PICTURE, 0, 0, 3, 9, 9, 9]);
+        data.extend_from_slice(&[0xff, 0xf8, 5, 5]);
+
+        let scrubbed = scrub(data.clone()).unwrap().unwrap();
+        assert_eq!(scrubbed.len(), data.len());
+        assert_eq!(&scrubbed[..12], &data[..12]);
+        assert_eq!(
+            &scrubbed[12..22],
+            &[FLAC_PADDING, 0, 0, 6, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(&scrubbed[22..29], &[0x80 | FLAC_PADDING, 0, 0, 3, 0, 0, 0]);
+        assert_eq!(&scrubbed[29..], &[0xff, 0xf8, 5, 5]);
+    }
+
+    #[test]
+    fn test_scrub_pdf() {
+        let data = b"%PDF-1.4\n1 0 obj\n<< /Title (Report) /Author (Alice (A.) \\) Smith) \
+                     /Producer <FEFF0041> /Creator<<>> >>\nendobj\n\
+                     <?xpacket begin=\"\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?><dc:creator>Alice</dc:creator>\
+                     <?xpacket end=\"w\"?>\n%%EOF\n";
+        let scrubbed = scrub(data.to_vec()).unwrap().unwrap();
+        assert_eq!(scrubbed.len(), data.len());
+        let scrubbed = String::from_utf8(scrubbed).unwrap();
+        assert!(scrubbed.contains("/Title (Report)"));
+        let author = format!("/Author ({})", " ".repeat("Alice (A.) \\) Smith".len()));
+        assert!(scrubbed.contains(&author));
+        assert!(scrubbed.contains("/Producer <        >"));
+        assert!(scrubbed.contains("/Creator<<>>"));
+        assert!(!scrubbed.contains("Alice"));
+        assert!(scrubbed.ends_with("<?xpacket end=\"w\"?>\n%%EOF\n"));
+
+        assert_eq!(scrub(b"%PDF-1.4\n%%EOF\n".to_vec()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_is_supported() {
+        assert!(is_supported(b"\0\0\0\x18ftyp"));
+        assert!(is_supported(b"fLaC\0\0\0\x22"));
+        assert!(is_supported(b"%PDF-1.4"));
+        assert!(is_supported(b"ID3\x04\0\0\0\0"));
+        assert!(is_supported(&[0xff, 0xfb, 0x90, 0x00]));
+        assert!(!is_supported(b"PK\x03\x04\x14\0\0\0"));
+        assert!(!is_supported(b""));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_scrubbed_pdf() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = t.get_self_chat().await.id;
+        let data = b"%PDF-1.4\n1 0 obj\n<< /Author (Alice) >>\nendobj\n%%EOF\n";
+
+        async fn send_pdf(t: &TestContext, chat_id: ChatId, data: &[u8]) -> Result<Vec<u8>> {
+            let file = t.get_blobdir().join("report.pdf");
+            tokio::fs::write(&file, data).await?;
+            let mut msg = Message::new(Viewtype::File);
+            msg.set_file(file.to_str().unwrap(), None);
+            chat::send_msg(t, chat_id, &mut msg).await?;
+            let msg = Message::load_from_db(t, msg.id).await?;
+            assert_eq!(msg.get_filename().unwrap(), "report.pdf");
+            Ok(tokio::fs::read(msg.get_file(t).unwrap()).await?)
+        }
+
+        let sent = send_pdf(&t, chat_id, data).await?;
+        assert!(find(&sent, b"Alice", 0).is_none());
+
+        t.set_config_bool(Config::StripAttachmentMetadata, false)
+            .await?;
+        let sent = send_pdf(&t, chat_id, data).await?;
+        assert_eq!(sent, data);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+//! # Previews of PDF attachments.
+//!
+//! The first page of a PDF attachment is rendered when it is sent
+//! or received in a chat accepted by the user
+//! and stored as a JPEG blob in [`Param::Preview`],
+//! so that UIs can show it like an image thumbnail,
+//! see [`crate::message::Message::get_preview_blob`].
+//! PDFs received from unknown senders are not rendered
+//! as pdfium is a large C++ library which should not parse arbitrary input.
+//!
+//! The preview is not sent, every device renders it itself.
+//! Rendering requires the `pdf-preview` feature and the pdfium library,
+//! without it PDF attachments have no preview.
+//! The library is bound once and used by a single thread, rendering one PDF at a time.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use image::DynamicImage;
+
+use super::{encode_img, get_jpeg_quality, BlobObject, ImageOutputFormat};
+use crate::context::Context;
+use crate::param::{Param, Params};
+
+/// Maximum width and height of a PDF preview.
+const PREVIEW_SIZE: u32 = 640;
+
+/// Returns true if the file at `path` starts with the PDF header.
+fn is_pdf(path: &Path) -> bool {
+    let mut magic = [0; 5];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok()
+        && &magic == b"%PDF-"
+}
+
+/// Renders the preview of the PDF attached to a message
+/// unless it is set already or the attachment is no PDF.
+pub(crate) async fn set_preview_param(context: &Context, param: &mut Params) -> Result<()> {
+    if param.exists(Param::Preview) {
+        return Ok(());
+    }
+    let Some(path) = param.get_path(Param::File, context)? else {
+        return Ok(());
+    };
+    let quality = get_jpeg_quality(context).await?;
+    let pdf_path = path.clone();
+    if !tokio::task::spawn_blocking(move || is_pdf(&pdf_path)).await? {
+        return Ok(());
+    }
+    let page = render_first_page(path).await?;
+    let encoded = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let preview = page.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE);
+        let mut encoded = Vec::new();
+        encode_img(&preview, ImageOutputFormat::Jpeg { quality }, &mut encoded)?;
+        Ok(encoded)
+    })
+    .await??;
+    let blob = BlobObject::create(context, "preview.jpg", &encoded).await?;
+    param.set(Param::Preview, blob.as_name());
+    Ok(())
+}
+
+/// Request to render the first page of the PDF file at the path,
+/// the result is sent back through the channel.
+#[cfg(feature = "pdf-preview")]
+type RenderRequest = (PathBuf, tokio::sync::oneshot::Sender<Result<DynamicImage>>);
+
+/// Returns the sender of requests to the thread rendering PDFs,
+/// starting the thread on first use.
+#[cfg(feature = "pdf-preview")]
+fn renderer() -> &'static std::sync::mpsc::Sender<RenderRequest> {
+    use pdfium_render::prelude::Pdfium;
+
+    static RENDERER: std::sync::OnceLock<std::sync::mpsc::Sender<RenderRequest>> =
+        std::sync::OnceLock::new();
+    RENDERER.get_or_init(|| {
+        let (tx, rx) = std::sync::mpsc::channel::<RenderRequest>();
+        // If the thread cannot be started, the receiver is dropped and requests fail.
+        std::thread::Builder::new()
+            .name("pdf-preview".to_string())
+            .spawn(move || {
+                let pdfium = Pdfium::bind_to_system_library()
+                    .map(Pdfium::new)
+                    .map_err(|err| err.to_string());
+                for (path, result_tx) in rx {
+                    let result = match &pdfium {
+                        Ok(pdfium) => render_page(pdfium, &path),
+                        Err(err) => Err(anyhow::anyhow!("pdfium library not found: {err}")),
+                    };
+                    result_tx.send(result).ok();
+                }
+            })
+            .ok();
+        tx
+    })
+}
+
+/// Renders the first page of the PDF file at `path`
+/// so that it fits into [`PREVIEW_SIZE`].
+#[cfg(feature = "pdf-preview")]
+fn render_page(pdfium: &pdfium_render::prelude::Pdfium, path: &Path) -> Result<DynamicImage> {
+    use anyhow::Context as _;
+    use pdfium_render::prelude::PdfRenderConfig;
+
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .context("Cannot load PDF")?;
+    let page = document.pages().first().context("PDF has no pages")?;
+    // Rendering at the preview size avoids allocating huge bitmaps for large pages.
+    let size = i32::try_from(PREVIEW_SIZE)?;
+    let config = PdfRenderConfig::new()
+        .set_target_width(size)
+        .set_maximum_height(size);
+    let bitmap = page
+        .render_with_config(&config)
+        .context("Cannot render PDF page")?;
+    Ok(bitmap.as_image())
+}
+
+/// Renders the first page of the PDF file at `path` on the rendering thread.
+#[cfg(feature = "pdf-preview")]
+async fn render_first_page(path: PathBuf) -> Result<DynamicImage> {
+    use anyhow::Context as _;
+
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+    renderer()
+        .send((path, result_tx))
+        .ok()
+        .context("PDF rendering thread is not running")?;
+    result_rx.await.context("PDF rendering thread stopped")?
+}
+
+/// Renders the first page of a PDF file.
+///
+/// Always fails as rendering PDFs requires the `pdf-preview` feature.
+#[cfg(not(feature = "pdf-preview"))]
+async fn render_first_page(_path: PathBuf) -> Result<DynamicImage> {
+    anyhow::bail!("PDF rendering is not enabled")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pdf_preview() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "doc.txt", b"%PDF- but text", None)
+            .await?;
+        assert!(is_pdf(&msg.get_file(alice).unwrap()));
+
+        // Other files have no preview.
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "doc.pdf", b"not a pdf", None)
+            .await?;
+        assert!(!is_pdf(&msg.get_file(alice).unwrap()));
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        assert_eq!(msg.get_preview_blob(alice), None);
+        let received = bob.recv_msg(&sent).await;
+        assert_eq!(received.get_preview_blob(bob), None);
+
+        // A preview that is set already is kept.
+        let preview = BlobObject::create(alice, "preview.jpg", b"jpeg").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "doc.pdf", b"%PDF-1.4", None)
+            .await?;
+        msg.param.set(Param::Preview, preview.as_name());
+        alice.send_msg(chat_id, &mut msg).await;
+        assert_eq!(msg.get_preview_blob(alice), Some(preview.to_abs_path()));
+        Ok(())
+    }
+
+    #[cfg(feature = "pdf-preview")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_render_pdf_preview() -> Result<()> {
+        if pdfium_render::prelude::Pdfium::bind_to_system_library().is_err() {
+            // Rendering is only tested where the pdfium library is installed.
+            return Ok(());
+        }
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(
+            alice,
+            "rectangle.pdf",
+            include_bytes!("../../test-data/pdf/rectangle.pdf"),
+            None,
+        )
+        .await?;
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let preview = image::open(msg.get_preview_blob(alice).unwrap())?;
+        // The page is a red rectangle of 200x100 points.
+        assert_eq!(preview.width(), PREVIEW_SIZE);
+        assert_eq!(preview.height(), PREVIEW_SIZE / 2);
+        let pixel = preview.to_rgb8().get_pixel(10, 10).0;
+        assert!(pixel[0] > 200 && pixel[1] < 50 && pixel[2] < 50);
+
+        // PDFs in contact requests are not rendered.
+        let received = bob.recv_msg(&sent).await;
+        assert_eq!(received.get_preview_blob(bob), None);
+
+        // The receiver renders the preview itself once the chat is accepted.
+        received.chat_id.accept(bob).await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(
+            alice,
+            "rectangle.pdf",
+            include_bytes!("../../test-data/pdf/rectangle.pdf"),
+            None,
+        )
+        .await?;
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let received = bob.recv_msg(&sent).await;
+        assert!(received.get_preview_blob(bob).is_some());
+        Ok(())
+    }
+}
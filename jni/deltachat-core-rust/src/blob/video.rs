@@ -0,0 +1,456 @@
+//! # Metadata and thumbnails of video attachments.
+//!
+//! The core does not contain a video decoder.
+//! Duration and dimensions of MP4 and QuickTime videos are read from the container,
+//! poster frames can only be extracted by a [`VideoDecoder`]
+//! which platforms supply with [`Context::set_video_decoder`].
+//!
+//! The sender stores the poster frame as a small JPEG thumbnail
+//! and sends it in the `Chat-Video-Thumbnail` header,
+//! so that the receiver can show a preview even without a video decoder.
+
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{ensure, Context as _, Result};
+use image::ImageReader;
+
+use super::{encode_img, get_jpeg_quality, BlobObject, DecodingLimits, ImageOutputFormat};
+use crate::context::Context;
+use crate::param::{Param, Params};
+
+/// Maximum width and height of a video thumbnail.
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// Maximum size of an encoded video thumbnail.
+///
+/// The thumbnail is sent in a header,
+/// so it is limited like avatars to stay below the header size limits of servers.
+const MAX_THUMBNAIL_BYTES: usize = 20_000;
+
+/// Maximum size of the `moov` box which is read into memory.
+const MAX_MOOV_LEN: u64 = 16 * 1024 * 1024;
+
+/// Metadata of a video.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VideoMetadata {
+    /// Duration in milliseconds.
+    pub duration_ms: Option<u32>,
+
+    /// Width in pixels, as displayed, i.e. after applying the rotation.
+    pub width: Option<u32>,
+
+    /// Height in pixels, as displayed, i.e. after applying the rotation.
+    pub height: Option<u32>,
+
+    /// Poster frame, encoded in any image format the core can decode, e.g. JPEG or PNG.
+    pub poster: Option<Vec<u8>>,
+}
+
+impl VideoMetadata {
+    /// Replaces the values with those set in `other`.
+    fn merge(&mut self, other: VideoMetadata) {
+        self.duration_ms = other.duration_ms.or(self.duration_ms);
+        self.width = other.width.or(self.width);
+        self.height = other.height.or(self.height);
+        self.poster = other.poster.or(self.poster.take());
+    }
+}
+
+/// Video decoder supplied by the platform.
+pub trait VideoDecoder: Send + Sync + std::fmt::Debug {
+    /// Extracts the metadata of the video file at `path`.
+    ///
+    /// This is called from a blocking thread.
+    /// Values that are not set are read from the container by the core if possible.
+    fn decode(&self, path: &Path) -> Result<VideoMetadata>;
+}
+
+impl Context {
+    /// Sets the decoder used to extract poster frames and metadata of videos,
+    /// or `None` to only read the metadata the core can read from the container.
+    pub fn set_video_decoder(&self, decoder: Option<Arc<dyn VideoDecoder>>) {
+        *self.video_decoder.lock() = decoder;
+    }
+}
+
+/// Extracts the metadata of the video at `path`.
+///
+/// Values returned by the decoder set with [`Context::set_video_decoder`]
+/// take precedence over values read from the container.
+pub(crate) async fn get_metadata(context: &Context, path: &Path) -> Result<VideoMetadata> {
+    let decoder = context.video_decoder.lock().clone();
+    let path = path.to_path_buf();
+    let (container, decoded) = tokio::task::spawn_blocking(move || {
+        let container = std::fs::File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| read_mp4_metadata(&mut BufReader::new(file)));
+        let decoded = decoder.map(|decoder| decoder.decode(&path));
+        (container, decoded)
+    })
+    .await?;
+
+    let mut metadata = container.unwrap_or_else(|err| {
+        info!(context, "Cannot read video container metadata: {err:#}.");
+        VideoMetadata::default()
+    });
+    match decoded {
+        Some(Ok(decoded)) => metadata.merge(decoded),
+        Some(Err(err)) => warn!(context, "Video decoder failed: {err:#}."),
+        None => {}
+    }
+    Ok(metadata)
+}
+
+/// Sets the duration, dimensions and thumbnail of the video attached to a message
+/// unless they are set already.
+pub(crate) async fn set_video_params(context: &Context, param: &mut Params) -> Result<()> {
+    let Some(path) = param.get_path(Param::File, context)? else {
+        return Ok(());
+    };
+    let metadata = get_metadata(context, &path).await?;
+
+    if param.get_int(Param::Duration).unwrap_or_default() <= 0 {
+        if let Some(duration_ms) = metadata.duration_ms.and_then(|d| i32::try_from(d).ok()) {
+            param.set_int(Param::Duration, duration_ms);
+        }
+    }
+    if param.get_int(Param::Width).unwrap_or_default() <= 0
+        || param.get_int(Param::Height).unwrap_or_default() <= 0
+    {
+        if let (Some(width), Some(height)) = (metadata.width, metadata.height) {
+            param.set_int(Param::Width, i32::try_from(width)?);
+            param.set_int(Param::Height, i32::try_from(height)?);
+        }
+    }
+    if !param.exists(Param::Thumbnail) {
+        if let Some(poster) = metadata.poster {
+            let thumbnail = store_thumbnail(context, poster).await?;
+            param.set(Param::Thumbnail, thumbnail);
+        }
+    }
+    Ok(())
+}
+
+/// Scales down the poster frame `poster` and stores it as a JPEG blob.
+///
+/// Returns the name of the blob.
+async fn store_thumbnail(context: &Context, poster: Vec<u8>) -> Result<String> {
+    let limits = DecodingLimits::from_config(context).await?;
+    let quality = get_jpeg_quality(context).await?;
+    let encoded = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let mut reader = ImageReader::new(Cursor::new(poster)).with_guessed_format()?;
+        reader.limits(limits.image_limits());
+        let img = reader.decode().context("Cannot decode poster frame")?;
+        let mut size = THUMBNAIL_SIZE;
+        let mut encoded = Vec::new();
+        loop {
+            let thumbnail = img.thumbnail(size, size);
+            encode_img(
+                &thumbnail,
+                ImageOutputFormat::Jpeg { quality },
+                &mut encoded,
+            )?;
+            if encoded.len() <= MAX_THUMBNAIL_BYTES || size <= THUMBNAIL_SIZE / 4 {
+                break;
+            }
+            size /= 2;
+        }
+        ensure!(
+            encoded.len() <= MAX_THUMBNAIL_BYTES,
+            "Thumbnail is too large"
+        );
+        Ok(encoded)
+    })
+    .await??;
+    let blob = BlobObject::create(context, "thumbnail.jpg", &encoded).await?;
+    Ok(blob.as_name().to_string())
+}
+
+/// Reads the duration and dimensions from an MP4 or QuickTime container.
+fn read_mp4_metadata(reader: &mut (impl Read + Seek)) -> Result<VideoMetadata> {
+    let moov = read_moov(reader)?.context("No moov box found")?;
+    Ok(parse_moov(&moov))
+}
+
+/// Reads the body of the top-level `moov` box
+/// without reading the other boxes, in particular the media data.
+fn read_moov(reader: &mut (impl Read + Seek)) -> Result<Option<Vec<u8>>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let mut pos = 0;
+    while pos + 8 <= file_len {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let (size, typ) = header.split_at(4);
+        let mut header_len = 8;
+        let size = match u32::from_be_bytes(size.try_into()?) {
+            // The box extends to the end of the file.
+            0 => file_len - pos,
+            // 64-bit size follows the type.
+            1 => {
+                let mut largesize = [0; 8];
+                reader.read_exact(&mut largesize)?;
+                header_len = 16;
+                u64::from_be_bytes(largesize)
+            }
+            size => u64::from(size),
+        };
+        ensure!(size >= header_len, "Invalid MP4 box size {size}");
+        if typ == b"moov" {
+            let len = size - header_len;
+            ensure!(len <= MAX_MOOV_LEN, "moov box is too large ({len} bytes)");
+            let mut body = vec![0; usize::try_from(len)?];
+            reader.read_exact(&mut body)?;
+            return Ok(Some(body));
+        }
+        pos = pos.checked_add(size).context("Invalid MP4 box size")?;
+    }
+    Ok(None)
+}
+
+/// Returns an iterator over the types and bodies of the boxes in `data`.
+fn boxes(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        let size = read_u32(rest, 0)?;
+        let typ = rest.get(4..8)?;
+        let (header_len, size) = match size {
+            0 => (8, rest.len()),
+            1 => (16, usize::try_from(read_u64(rest, 8)?).ok()?),
+            size => (8, usize::try_from(size).ok()?),
+        };
+        let body = rest.get(header_len..size)?;
+        rest = rest.get(size..)?;
+        Some((typ, body))
+    })
+}
+
+/// Returns the body of the first box of type `typ` in `data`.
+fn find_box<'a>(data: &'a [u8], typ: &[u8]) -> Option<&'a [u8]> {
+    boxes(data).find(|(t, _)| *t == typ).map(|(_, body)| body)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn parse_moov(moov: &[u8]) -> VideoMetadata {
+    let mut metadata = VideoMetadata {
+        duration_ms: find_box(moov, b"mvhd").and_then(parse_mvhd),
+        ..Default::default()
+    };
+    let video_track = boxes(moov)
+        .filter(|(typ, _)| *typ == b"trak")
+        .map(|(_, trak)| trak)
+        .find(|trak| {
+            find_box(trak, b"mdia")
+                .and_then(|mdia| find_box(mdia, b"hdlr"))
+                .and_then(|hdlr| hdlr.get(8..12))
+                == Some(&b"vide"[..])
+        });
+    if let Some((width, height)) = video_track
+        .and_then(|trak| find_box(trak, b"tkhd"))
+        .and_then(parse_tkhd)
+    {
+        metadata.width = Some(width);
+        metadata.height = Some(height);
+    }
+    metadata
+}
+
+/// Returns the duration in milliseconds from the movie header.
+fn parse_mvhd(mvhd: &[u8]) -> Option<u32> {
+    let (timescale, duration) = match mvhd.first()? {
+        0 => (read_u32(mvhd, 12)?, u64::from(read_u32(mvhd, 16)?)),
+        1 => (read_u32(mvhd, 20)?, read_u64(mvhd, 24)?),
+        _ => return None,
+    };
+    if timescale == 0 {
+        return None;
+    }
+    u32::try_from(duration.checked_mul(1000)? / u64::from(timescale)).ok()
+}
+
+/// Returns the displayed width and height from the track header.
+fn parse_tkhd(tkhd: &[u8]) -> Option<(u32, u32)> {
+    let matrix = match tkhd.first()? {
+        0 => 40,
+        1 => 52,
+        _ => return None,
+    };
+    // Width and height are 16.16 fixed-point numbers.
+    let width = read_u32(tkhd, matrix + 36)? >> 16;
+    let height = read_u32(tkhd, matrix + 40)? >> 16;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    // Videos recorded in portrait orientation are usually stored in landscape orientation
+    // with a transformation matrix rotating them by 90 or 270 degrees.
+    let rotated = read_u32(tkhd, matrix)? == 0 && read_u32(tkhd, matrix + 16)? == 0;
+    Some(match rotated {
+        true => (height, width),
+        false => (width, height),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat;
+    use crate::download::DownloadState;
+    use crate::message::{Message, Viewtype};
+    use crate::receive_imf::receive_imf_from_inbox;
+    use crate::test_utils::TestContextManager;
+
+    fn mp4_box(typ: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut res = (u32::try_from(body.len() + 8).unwrap())
+            .to_be_bytes()
+            .to_vec();
+        res.extend_from_slice(typ);
+        res.extend_from_slice(body);
+        res
+    }
+
+    /// Returns a minimal MP4 file with a `moov` box after the media data.
+    fn build_mp4(duration_ms: u32, width: u32, height: u32, rotated: bool) -> Vec<u8> {
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&duration_ms.to_be_bytes());
+
+        let mut tkhd = vec![0; 84];
+        let (a, d) = match rotated {
+            true => (0, 0),
+            false => (0x10000u32, 0x10000u32),
+        };
+        tkhd[40..44].copy_from_slice(&a.to_be_bytes());
+        tkhd[56..60].copy_from_slice(&d.to_be_bytes());
+        tkhd[76..80].copy_from_slice(&(width << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(height << 16).to_be_bytes());
+
+        let mut hdlr = vec![0; 24];
+        hdlr[8..12].copy_from_slice(b"vide");
+
+        let mut sound_hdlr = vec![0; 24];
+        sound_hdlr[8..12].copy_from_slice(b"soun");
+        let sound_trak = mp4_box(
+            b"trak",
+            &[
+                mp4_box(b"tkhd", &[0; 84]),
+                mp4_box(b"mdia", &mp4_box(b"hdlr", &sound_hdlr)),
+            ]
+            .concat(),
+        );
+        let video_trak = mp4_box(
+            b"trak",
+            &[
+                mp4_box(b"tkhd", &tkhd),
+                mp4_box(b"mdia", &mp4_box(b"hdlr", &hdlr)),
+            ]
+            .concat(),
+        );
+        let moov = mp4_box(
+            b"moov",
+            &[mp4_box(b"mvhd", &mvhd), sound_trak, video_trak].concat(),
+        );
+        [
+            mp4_box(b"ftyp", b"isom\0\0\x02\0"),
+            mp4_box(b"mdat", &[0xaa; 1000]),
+            moov,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_read_mp4_metadata() {
+        let data = build_mp4(12_345, 640, 360, false);
+        let metadata = read_mp4_metadata(&mut Cursor::new(data)).unwrap();
+        assert_eq!(
+            metadata,
+            VideoMetadata {
+                duration_ms: Some(12_345),
+                width: Some(640),
+                height: Some(360),
+                poster: None,
+            }
+        );
+
+        let data = build_mp4(1000, 640, 360, true);
+        let metadata = read_mp4_metadata(&mut Cursor::new(data)).unwrap();
+        assert_eq!(metadata.width, Some(360));
+        assert_eq!(metadata.height, Some(640));
+
+        assert!(read_mp4_metadata(&mut Cursor::new(b"not a video".to_vec())).is_err());
+        let data = build_mp4(1000, 640, 360, false);
+        assert!(read_mp4_metadata(&mut Cursor::new(&data[..data.len() / 2])).is_err());
+    }
+
+    #[derive(Debug)]
+    struct TestDecoder;
+
+    impl VideoDecoder for TestDecoder {
+        fn decode(&self, _path: &Path) -> Result<VideoMetadata> {
+            Ok(VideoMetadata {
+                poster: Some(include_bytes!("../../test-data/image/avatar1000x1000.jpg").to_vec()),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_video_thumbnail() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice.set_video_decoder(Some(Arc::new(TestDecoder)));
+
+        let file = alice.get_blobdir().join("video.mp4");
+        tokio::fs::write(&file, build_mp4(4000, 640, 360, true)).await?;
+        let mut msg = Message::new(Viewtype::Video);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat_id = alice.create_chat(bob).await.id;
+        chat::send_msg(alice, chat_id, &mut msg).await?;
+        let sent = alice.pop_sent_msg().await;
+
+        let msg = Message::load_from_db(alice, msg.id).await?;
+        assert_eq!(msg.get_duration(), 4000);
+        assert_eq!((msg.get_width(), msg.get_height()), (360, 640));
+        let thumbnail = msg.get_thumbnail(alice).unwrap();
+        let (width, height) = image::image_dimensions(&thumbnail)?;
+        assert!(width <= THUMBNAIL_SIZE && height <= THUMBNAIL_SIZE);
+
+        // Bob has no video decoder, but receives the thumbnail.
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.get_viewtype(), Viewtype::Video);
+        assert_eq!(msg.get_duration(), 4000);
+        assert_eq!((msg.get_width(), msg.get_height()), (360, 640));
+        let thumbnail = msg.get_thumbnail(bob).unwrap();
+        assert_eq!(image::image_dimensions(&thumbnail)?, (width, height));
+
+        // The thumbnail is shown before a large video is downloaded.
+        let bob2 = &tcm.bob().await;
+        receive_imf_from_inbox(
+            bob2,
+            &msg.rfc724_mid,
+            sent.payload().as_bytes(),
+            false,
+            Some(100_000),
+            false,
+        )
+        .await?;
+        let msg = bob2.get_last_msg().await;
+        assert_eq!(msg.download_state(), DownloadState::Available);
+        let thumbnail = msg.get_thumbnail(bob2).unwrap();
+        assert_eq!(image::image_dimensions(&thumbnail)?, (width, height));
+        Ok(())
+    }
+}
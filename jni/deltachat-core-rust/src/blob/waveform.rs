@@ -0,0 +1,386 @@
+//! # Waveforms of voice messages.
+//!
+//! Voice messages are decoded when they are sent or received
+//! and their waveform is stored in [`Param::Waveform`]
+//! as [`WAVEFORM_LEN`] peaks scaled to `0..=255`,
+//! so that UIs can draw it without decoding the audio themselves,
+//! see [`crate::message::Message::get_waveform`].
+//!
+//! WAV files with 16-bit PCM samples are always supported.
+//! AAC, MP3, Ogg Vorbis and FLAC require the `waveform` feature.
+//! Opus is not supported.
+//! Audio longer than [`MAX_SAMPLES`] gets no waveform.
+
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use base64::Engine as _;
+
+use crate::context::Context;
+use crate::param::{Param, Params};
+
+/// Number of peaks of a waveform.
+pub(crate) const WAVEFORM_LEN: usize = 64;
+
+/// Number of samples of which the peak is computed at once.
+///
+/// The peaks of the windows are reduced to [`WAVEFORM_LEN`] peaks in the end,
+/// so the samples do not need to be kept in memory.
+const WINDOW_LEN: usize = 512;
+
+/// Maximum number of samples decoded, 10 minutes of 48 kHz stereo audio.
+///
+/// Received voice messages are decoded before the sender is checked,
+/// so decoding is stopped instead of taking arbitrarily long.
+const MAX_SAMPLES: usize = 48_000 * 2 * 60 * 10;
+
+/// Collects the peaks of a stream of samples.
+#[derive(Debug, Default)]
+struct PeakCollector {
+    /// Peaks of the completed windows.
+    windows: Vec<f32>,
+
+    /// Peak of the current window.
+    peak: f32,
+
+    /// Number of samples in the current window.
+    count: usize,
+
+    /// Number of all samples.
+    total: usize,
+}
+
+impl PeakCollector {
+    /// Adds a sample in the range `-1.0..=1.0`.
+    fn push(&mut self, sample: f32) {
+        self.peak = self.peak.max(sample.abs());
+        self.count += 1;
+        self.total += 1;
+        if self.count == WINDOW_LEN {
+            self.windows.push(self.peak);
+            self.peak = 0.0;
+            self.count = 0;
+        }
+    }
+
+    /// Fails if [`MAX_SAMPLES`] samples were added.
+    fn check_len(&self) -> Result<()> {
+        if self.total >= MAX_SAMPLES {
+            bail!("Audio is too long for a waveform");
+        }
+        Ok(())
+    }
+
+    /// Returns [`WAVEFORM_LEN`] peaks scaled so that the highest one is 255,
+    /// `None` if there were no samples.
+    fn finish(mut self) -> Option<Vec<u8>> {
+        if self.count > 0 {
+            self.windows.push(self.peak);
+        }
+        let n = self.windows.len();
+        if n == 0 {
+            return None;
+        }
+        let peaks: Vec<f32> = (0..WAVEFORM_LEN)
+            .map(|i| {
+                // If there are less windows than peaks, windows are repeated.
+                let start = (i * n / WAVEFORM_LEN).min(n - 1);
+                let end = ((i + 1) * n / WAVEFORM_LEN).max(start + 1);
+                self.windows
+                    .get(start..end)
+                    .unwrap_or_default()
+                    .iter()
+                    .fold(0.0, |max: f32, &peak| max.max(peak))
+            })
+            .collect();
+        let max = peaks.iter().fold(0.0, |max: f32, &peak| max.max(peak));
+        let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+        // The values are in 0.0..=255.0, so the casts do not truncate.
+        Some(
+            peaks
+                .into_iter()
+                .map(|peak| (peak * scale).round() as u8)
+                .collect(),
+        )
+    }
+}
+
+/// Sets the waveform of the voice message attached to a message
+/// unless it is set already.
+pub(crate) async fn set_waveform_param(context: &Context, param: &mut Params) -> Result<()> {
+    if param.exists(Param::Waveform) {
+        return Ok(());
+    }
+    let Some(path) = param.get_path(Param::File, context)? else {
+        return Ok(());
+    };
+    let waveform = tokio::task::spawn_blocking(move || compute_waveform(&path)).await??;
+    if let Some(waveform) = waveform {
+        param.set(
+            Param::Waveform,
+            base64::engine::general_purpose::STANDARD.encode(waveform),
+        );
+    }
+    Ok(())
+}
+
+/// Decodes the audio file at `path` and returns its waveform,
+/// `None` if it contains no samples.
+///
+/// This blocks, so it should run on a blocking thread.
+fn compute_waveform(path: &Path) -> Result<Option<Vec<u8>>> {
+    let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut collector = PeakCollector::default();
+    let mut riff = [0; 12];
+    let is_wav = file.read_exact(&mut riff).is_ok()
+        && riff.starts_with(b"RIFF")
+        && riff.get(8..) == Some(b"WAVE".as_slice());
+    if is_wav {
+        read_wav(&mut file, &mut collector)?;
+    } else {
+        decode(path, &mut collector)?;
+    }
+    Ok(collector.finish())
+}
+
+/// Reads the samples of a WAV file with 16-bit PCM samples
+/// following the `RIFF` header.
+fn read_wav(reader: &mut impl Read, collector: &mut PeakCollector) -> Result<()> {
+    let mut format_checked = false;
+    loop {
+        let mut header = [0; 8];
+        reader
+            .read_exact(&mut header)
+            .context("No data chunk in WAV file")?;
+        let (id, len) = header.split_at(4);
+        let len = u32::from_le_bytes(len.try_into()?);
+        match id {
+            b"fmt " => {
+                // Only the format and the bits per sample at the start are needed,
+                // the length of the chunk is not trusted.
+                ensure!(len >= 16, "Truncated WAV format chunk");
+                let mut fmt = [0; 16];
+                reader.read_exact(&mut fmt)?;
+                ensure!(
+                    fmt.starts_with(&1u16.to_le_bytes()) && fmt.ends_with(&16u16.to_le_bytes()),
+                    "Only 16-bit PCM WAV files are supported"
+                );
+                let skip = u64::from(len - 16) + u64::from(len % 2);
+                std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+                format_checked = true;
+            }
+            b"data" => {
+                ensure!(format_checked, "No format chunk before WAV data");
+                let mut data = reader.by_ref().take(u64::from(len));
+                let mut sample = [0; 2];
+                while data.read_exact(&mut sample).is_ok() {
+                    collector.check_len()?;
+                    collector.push(f32::from(i16::from_le_bytes(sample)) / 32768.0);
+                }
+                return Ok(());
+            }
+            _ => {
+                // Chunks are padded to an even length.
+                let skip = u64::from(len) + u64::from(len % 2);
+                std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+            }
+        }
+    }
+}
+
+/// Decodes a compressed audio file.
+#[cfg(feature = "waveform")]
+fn decode(path: &Path, collector: &mut PeakCollector) -> Result<()> {
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::errors::Error as DecodeError;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let file = std::fs::File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .context("Unsupported audio format")?
+        .format;
+    let track = format.default_track().context("No audio track")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported audio codec")?;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(());
+            }
+            Err(err) => return Err(err).context("Cannot read audio packet"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Corrupted packets are skipped.
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(err) => return Err(err).context("Cannot decode audio packet"),
+        };
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        samples.copy_interleaved_ref(decoded);
+        for &sample in samples.samples() {
+            collector.push(sample);
+        }
+        collector.check_len()?;
+    }
+}
+
+/// Decodes a compressed audio file.
+///
+/// Always fails as only WAV files are supported without the `waveform` feature.
+#[cfg(not(feature = "waveform"))]
+fn decode(_path: &Path, _collector: &mut PeakCollector) -> Result<()> {
+    anyhow::bail!("Audio decoding is not enabled")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::TestContextManager;
+
+    /// Returns a mono 16-bit PCM WAV file with a tone getting louder.
+    fn wav(samples: usize) -> Vec<u8> {
+        let data_len = u32::try_from(samples * 2).unwrap();
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // Mono
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"LIST");
+        wav.extend_from_slice(&3u32.to_le_bytes());
+        wav.extend_from_slice(b"abc\0");
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for i in 0..samples {
+            let amplitude = i32::try_from(i * 16000 / samples).unwrap();
+            let sample = if i % 2 == 0 { amplitude } else { -amplitude };
+            wav.extend_from_slice(&i16::try_from(sample).unwrap().to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn test_peaks() {
+        let mut collector = PeakCollector::default();
+        for i in 0..WINDOW_LEN * WAVEFORM_LEN * 2 {
+            collector.push(if i < WINDOW_LEN * WAVEFORM_LEN {
+                0.1
+            } else {
+                -0.5
+            });
+        }
+        let waveform = collector.finish().unwrap();
+        assert_eq!(waveform.len(), WAVEFORM_LEN);
+        assert_eq!(waveform[0], 51);
+        assert_eq!(waveform[WAVEFORM_LEN - 1], 255);
+
+        // Short audio has less windows than peaks.
+        let mut collector = PeakCollector::default();
+        collector.push(0.0);
+        assert_eq!(collector.finish().unwrap(), vec![0; WAVEFORM_LEN]);
+
+        assert_eq!(PeakCollector::default().finish(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_voice_message_waveform() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let file = alice.get_blobdir().join("voice.wav");
+        tokio::fs::write(&file, wav(100_000)).await?;
+        let mut msg = Message::new(Viewtype::Voice);
+        msg.set_file(file.to_str().unwrap(), None);
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        let waveform = msg.get_waveform().unwrap();
+        assert_eq!(waveform.len(), WAVEFORM_LEN);
+        assert!(waveform[0] < waveform[WAVEFORM_LEN / 2]);
+        assert_eq!(waveform[WAVEFORM_LEN - 1], 255);
+
+        let received = bob.recv_msg(&sent).await;
+        assert_eq!(received.get_waveform(), Some(waveform));
+
+        // Files which can't be decoded are sent without a waveform.
+        let mut msg = Message::new(Viewtype::Voice);
+        msg.set_file_from_bytes(alice, "voice.opus", b"not audio", None)
+            .await?;
+        chat::send_msg(alice, chat_id, &mut msg).await?;
+        assert_eq!(msg.get_waveform(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_wav() {
+        let mut wav = wav(100);
+        // 8-bit samples.
+        wav[34] = 8;
+        let mut collector = PeakCollector::default();
+        assert!(read_wav(&mut &wav[12..], &mut collector).is_err());
+    }
+
+    #[test]
+    fn test_wav_limits() {
+        // A format chunk claiming to be huge is not read into memory.
+        let mut wav = wav(100);
+        wav[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut collector = PeakCollector::default();
+        assert!(read_wav(&mut &wav[12..], &mut collector).is_err());
+
+        // Extra bytes of the format chunk are skipped.
+        let wav = wav_with_fmt_extension();
+        let mut collector = PeakCollector::default();
+        read_wav(&mut &wav[12..], &mut collector).unwrap();
+        assert!(collector.finish().is_some());
+
+        // Long audio gets no waveform.
+        let mut collector = PeakCollector::default();
+        for _ in 0..MAX_SAMPLES {
+            collector.push(0.5);
+        }
+        assert!(collector.check_len().is_err());
+    }
+
+    /// Returns a WAV file with a format chunk of 18 bytes.
+    fn wav_with_fmt_extension() -> Vec<u8> {
+        let wav = wav(100);
+        let mut extended = Vec::new();
+        extended.extend_from_slice(&wav[..16]);
+        extended.extend_from_slice(&18u32.to_le_bytes());
+        extended.extend_from_slice(&wav[20..36]);
+        extended.extend_from_slice(&0u16.to_le_bytes());
+        extended.extend_from_slice(&wav[36..]);
+        extended
+    }
+}
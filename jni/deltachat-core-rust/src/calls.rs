@@ -0,0 +1,592 @@
+//! # Signaling of voice calls.
+//!
+//! Calls are negotiated with end-to-end encrypted control messages in 1:1 chats.
+//! The media transport is left to the platform,
+//! the core only transports opaque payloads such as SDP offers and answers.
+//!
+//! The caller places a call with [`Context::place_outgoing_call`].
+//! This sends a call invite that is shown as an info message in the chat
+//! and makes the callee's device ring, see [`EventType::IncomingCall`].
+//! The callee accepts the call with [`Context::accept_incoming_call`].
+//! Either side can end or reject the call with [`Context::end_call`].
+//!
+//! All control messages following the invite are hidden
+//! and refer to the invite with the `In-Reply-To` header.
+//! The state of a call is stored in the params of the invite message,
+//! see [`Context::get_call_info`].
+
+use anyhow::{bail, ensure, Result};
+use lettre_email::PartBuilder;
+use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, Chat, ChatId};
+use crate::constants::Chattype;
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::events::EventType;
+use crate::headerdef::HeaderDef;
+use crate::message::{self, Message, MsgId, Viewtype};
+use crate::mimefactory::wrapped_base64_encode;
+use crate::mimeparser::{parse_message_ids, MimeMessage, ParsedAttachment, SystemMessage};
+use crate::param::Param;
+use crate::stock_str;
+use crate::tools::time;
+
+/// Time in seconds after which an unanswered call is considered missed.
+pub const RINGING_TIMEOUT: i64 = 60;
+
+/// State of a call.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, Serialize, Deserialize,
+)]
+#[repr(u32)]
+pub enum CallState {
+    /// The call is ringing and was not answered yet.
+    #[default]
+    Pending = 0,
+
+    /// The call was accepted by the callee.
+    Active = 1,
+
+    /// The call was rejected by the callee.
+    Declined = 2,
+
+    /// The call was ended after it was accepted.
+    Ended = 3,
+
+    /// The call was not answered in time or cancelled by the caller.
+    Missed = 4,
+}
+
+/// Payload of a call control message as sent in `call.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct CallData {
+    /// Opaque payload for the platform, e.g. an SDP offer or answer.
+    payload: String,
+}
+
+/// Information about a call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallInfo {
+    /// ID of the call invite message.
+    pub msg_id: MsgId,
+
+    /// ID of the 1:1 chat the call belongs to.
+    pub chat_id: ChatId,
+
+    /// True if the call was placed by the chat partner.
+    pub is_incoming: bool,
+
+    /// Current state of the call.
+    pub state: CallState,
+
+    /// Payload of the caller, e.g. an SDP offer.
+    pub payload: String,
+
+    /// Payload of the callee, set once the call is accepted.
+    pub answer: Option<String>,
+
+    /// Time the call was placed.
+    pub timestamp: i64,
+}
+
+impl Context {
+    /// Places a call in the 1:1 chat `chat_id`.
+    ///
+    /// `payload` is passed to the callee, typically an SDP offer.
+    /// Returns the ID of the call invite message.
+    pub async fn place_outgoing_call(&self, chat_id: ChatId, payload: &str) -> Result<MsgId> {
+        let chat = Chat::load_from_db(self, chat_id).await?;
+        ensure!(
+            chat.typ == Chattype::Single && !chat.is_self_talk() && !chat.is_device_talk(),
+            "Calls are only possible in 1:1 chats"
+        );
+
+        let mut msg = Message::new(Viewtype::Text);
+        msg.text = stock_str::outgoing_call(self).await;
+        msg.param.set_cmd(SystemMessage::CallInvite);
+        msg.param.set(Param::Arg, payload);
+        msg.param.set_int(Param::GuaranteeE2ee, 1);
+        let msg_id = chat::send_msg(self, chat_id, &mut msg).await?;
+        info!(self, "Placed call {msg_id} in {chat_id}.");
+        Ok(msg_id)
+    }
+
+    /// Accepts the incoming call `msg_id`.
+    ///
+    /// `payload` is passed to the caller, typically an SDP answer.
+    pub async fn accept_incoming_call(&self, msg_id: MsgId, payload: &str) -> Result<()> {
+        let mut invite = load_invite(self, msg_id).await?;
+        ensure!(
+            invite.from_id != ContactId::SELF,
+            "Cannot accept outgoing call"
+        );
+        let state = get_state(&invite);
+        ensure!(
+            state == CallState::Pending,
+            "Cannot accept call in state {state:?}"
+        );
+
+        // Accepting a call from a contact request accepts the chat.
+        invite.chat_id.accept(self).await?;
+        set_state(self, &mut invite, CallState::Active).await?;
+        send_control_msg(self, &invite, SystemMessage::CallAccepted, payload).await?;
+        self.emit_event(EventType::IncomingCallAccepted {
+            chat_id: invite.chat_id,
+            msg_id,
+        });
+        Ok(())
+    }
+
+    /// Ends the call `msg_id`.
+    ///
+    /// An incoming call that was not accepted yet is rejected,
+    /// an outgoing call that was not accepted yet is cancelled.
+    pub async fn end_call(&self, msg_id: MsgId) -> Result<()> {
+        let mut invite = load_invite(self, msg_id).await?;
+        let is_incoming = invite.from_id != ContactId::SELF;
+        let (command, state) = match get_state(&invite) {
+            CallState::Pending if is_incoming => (SystemMessage::CallRejected, CallState::Declined),
+            CallState::Pending | CallState::Active => (SystemMessage::CallEnded, CallState::Ended),
+            state => bail!("Call {msg_id} is already over ({state:?})"),
+        };
+
+        set_state(self, &mut invite, state).await?;
+        // Calls from contact requests are rejected silently.
+        let chat = Chat::load_from_db(self, invite.chat_id).await?;
+        if !chat.is_contact_request() {
+            send_control_msg(self, &invite, command, "").await?;
+        }
+        self.emit_event(EventType::CallEnded {
+            chat_id: invite.chat_id,
+            msg_id,
+        });
+        Ok(())
+    }
+
+    /// Returns information about the call `msg_id`.
+    pub async fn get_call_info(&self, msg_id: MsgId) -> Result<CallInfo> {
+        let invite = load_invite(self, msg_id).await?;
+        Ok(CallInfo {
+            msg_id,
+            chat_id: invite.chat_id,
+            is_incoming: invite.from_id != ContactId::SELF,
+            state: get_state(&invite),
+            payload: invite.param.get(Param::Arg).unwrap_or_default().to_string(),
+            answer: invite.param.get(Param::Arg2).map(|s| s.to_string()),
+            timestamp: invite.timestamp_sent,
+        })
+    }
+}
+
+async fn load_invite(context: &Context, msg_id: MsgId) -> Result<Message> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(
+        msg.param.get_cmd() == SystemMessage::CallInvite,
+        "{msg_id} is not a call"
+    );
+    Ok(msg)
+}
+
+/// Returns the state of the call,
+/// taking into account that unanswered calls stop ringing after [`RINGING_TIMEOUT`].
+fn get_state(invite: &Message) -> CallState {
+    let state = invite
+        .param
+        .get_int(Param::Arg3)
+        .and_then(CallState::from_i32)
+        .unwrap_or_default();
+    if state == CallState::Pending && time() > invite.timestamp_sent + RINGING_TIMEOUT {
+        CallState::Missed
+    } else {
+        state
+    }
+}
+
+async fn set_state(context: &Context, invite: &mut Message, state: CallState) -> Result<()> {
+    invite.param.set_int(Param::Arg3, state as i32);
+    invite.update_param(context).await?;
+    if state == CallState::Missed && invite.from_id != ContactId::SELF {
+        let text = stock_str::missed_call(context).await;
+        chat::update_msg_text_and_timestamp(
+            context,
+            invite.chat_id,
+            invite.id,
+            &text,
+            invite.timestamp_sort,
+        )
+        .await?;
+    } else {
+        context.emit_msgs_changed(invite.chat_id, invite.id);
+    }
+    Ok(())
+}
+
+async fn send_control_msg(
+    context: &Context,
+    invite: &Message,
+    command: SystemMessage,
+    payload: &str,
+) -> Result<()> {
+    let mut msg = Message::new(Viewtype::Text);
+    msg.hidden = true;
+    msg.param.set_cmd(command);
+    msg.param.set(Param::Arg, payload);
+    msg.param.set_int(Param::GuaranteeE2ee, 1);
+    msg.in_reply_to = Some(invite.rfc724_mid.clone());
+    chat::send_msg(context, invite.chat_id, &mut msg).await?;
+    Ok(())
+}
+
+pub(crate) fn build_call_part(payload: &str) -> Result<PartBuilder> {
+    let json = serde_json::to_string(&CallData {
+        payload: payload.to_string(),
+    })?;
+    let encoded_body = wrapped_base64_encode(json.as_bytes());
+
+    Ok(PartBuilder::new()
+        .content_type(&"application/json".parse::<mime::Mime>().unwrap())
+        .header((
+            "Content-Disposition",
+            ParsedAttachment::Call.content_disposition(),
+        ))
+        .header(("Content-Transfer-Encoding", "base64"))
+        .body(encoded_body))
+}
+
+fn parse_call_data(mime_parser: &MimeMessage) -> Result<String> {
+    let Some(json) = &mime_parser.call_data else {
+        bail!("Call message without call data");
+    };
+    let data: CallData = serde_json::from_str(json)?;
+    Ok(data.payload)
+}
+
+/// Handles the received call invite `msg_id`.
+///
+/// Only encrypted invites are accepted, so that nobody can make the device ring by forging one.
+pub(crate) async fn receive_call_invite(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    msg_id: MsgId,
+) -> Result<()> {
+    ensure!(mime_parser.was_encrypted(), "Call invite is not encrypted");
+    let payload = parse_call_data(mime_parser)?;
+    let mut invite = Message::load_from_db(context, msg_id).await?;
+    invite.param.set(Param::Arg, &payload);
+    invite.update_param(context).await?;
+
+    // Invites sent from other devices of the user are outgoing calls.
+    if invite.from_id == ContactId::SELF {
+        return Ok(());
+    }
+
+    let chat_id = invite.chat_id;
+    if get_state(&invite) == CallState::Missed {
+        info!(context, "Received outdated call {msg_id}.");
+        set_state(context, &mut invite, CallState::Missed).await?;
+        context.emit_event(EventType::CallMissed { chat_id, msg_id });
+    } else {
+        let text = stock_str::incoming_call(context).await;
+        chat::update_msg_text_and_timestamp(context, chat_id, msg_id, &text, invite.timestamp_sort)
+            .await?;
+        context.emit_event(EventType::IncomingCall {
+            chat_id,
+            msg_id,
+            payload,
+        });
+    }
+    Ok(())
+}
+
+/// Handles a received call control message from `from_id`.
+///
+/// Only encrypted control messages from the chat partner or other own devices are accepted.
+pub(crate) async fn receive_call_control(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+) -> Result<()> {
+    ensure!(
+        mime_parser.was_encrypted(),
+        "Call control message is not encrypted"
+    );
+    let Some(field) = mime_parser.get_header(HeaderDef::InReplyTo) else {
+        bail!("Call control message without In-Reply-To");
+    };
+    let Some(mut invite) = message::get_by_rfc724_mids(context, &parse_message_ids(field)).await?
+    else {
+        bail!("Call control message for unknown call");
+    };
+    ensure!(
+        invite.param.get_cmd() == SystemMessage::CallInvite,
+        "Call control message does not refer to a call"
+    );
+    let chat_id = invite.chat_id;
+    ensure!(
+        from_id == ContactId::SELF
+            || chat::get_chat_contacts(context, chat_id)
+                .await?
+                .contains(&from_id),
+        "Call control message from {from_id} who is not the chat partner"
+    );
+    let msg_id = invite.id;
+    let is_incoming = invite.from_id != ContactId::SELF;
+    let state = get_state(&invite);
+
+    match mime_parser.is_system_message {
+        SystemMessage::CallAccepted => {
+            ensure!(
+                matches!(state, CallState::Pending | CallState::Missed),
+                "Call {msg_id} cannot be accepted in state {state:?}"
+            );
+            if is_incoming {
+                // Accepted on another device of the user.
+                ensure!(from_id == ContactId::SELF, "Caller cannot accept own call");
+                set_state(context, &mut invite, CallState::Active).await?;
+                context.emit_event(EventType::IncomingCallAccepted { chat_id, msg_id });
+            } else {
+                ensure!(from_id != ContactId::SELF, "Caller cannot accept own call");
+                let payload = parse_call_data(mime_parser)?;
+                invite.param.set(Param::Arg2, &payload);
+                set_state(context, &mut invite, CallState::Active).await?;
+                context.emit_event(EventType::OutgoingCallAccepted {
+                    chat_id,
+                    msg_id,
+                    payload,
+                });
+            }
+        }
+        SystemMessage::CallRejected => {
+            if state == CallState::Pending || state == CallState::Missed {
+                set_state(context, &mut invite, CallState::Declined).await?;
+            }
+            context.emit_event(EventType::CallEnded { chat_id, msg_id });
+        }
+        SystemMessage::CallEnded => {
+            if is_incoming && from_id != ContactId::SELF && state != CallState::Active {
+                // The caller cancelled the call before it was accepted.
+                set_state(context, &mut invite, CallState::Missed).await?;
+                context.emit_event(EventType::CallMissed { chat_id, msg_id });
+            } else {
+                set_state(context, &mut invite, CallState::Ended).await?;
+                context.emit_event(EventType::CallEnded { chat_id, msg_id });
+            }
+        }
+        command => bail!("Unexpected call control message {command:?}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receive_imf::receive_imf;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_call_accept_and_end() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        tcm.send_recv_accept(bob, alice, "Hi").await;
+        let alice_chat = alice.get_chat(bob).await;
+        let alice_msg_id = alice.place_outgoing_call(alice_chat.id, "offer").await?;
+        let sent = alice.pop_sent_msg().await;
+        assert!(sent.payload().contains("Chat-Content: call-invite"));
+
+        let bob_msg = bob.recv_msg(&sent).await;
+        assert!(bob_msg.is_info());
+        assert_eq!(bob_msg.get_info_type(), SystemMessage::CallInvite);
+        let event = bob
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::IncomingCall { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::IncomingCall {
+                chat_id: bob_msg.chat_id,
+                msg_id: bob_msg.id,
+                payload: "offer".to_string()
+            }
+        );
+        let info = bob.get_call_info(bob_msg.id).await?;
+        assert!(info.is_incoming);
+        assert_eq!(info.state, CallState::Pending);
+        assert_eq!(info.payload, "offer");
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert_eq!(bob_msg.get_text(), "Incoming call");
+
+        bob.accept_incoming_call(bob_msg.id, "answer").await?;
+        let sent = bob.pop_sent_msg().await;
+        assert_eq!(
+            bob.get_call_info(bob_msg.id).await?.state,
+            CallState::Active
+        );
+
+        alice.recv_msg_trash(&sent).await;
+        let event = alice
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::OutgoingCallAccepted { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::OutgoingCallAccepted {
+                chat_id: alice_chat.id,
+                msg_id: alice_msg_id,
+                payload: "answer".to_string()
+            }
+        );
+        let info = alice.get_call_info(alice_msg_id).await?;
+        assert!(!info.is_incoming);
+        assert_eq!(info.state, CallState::Active);
+        assert_eq!(info.answer.as_deref(), Some("answer"));
+
+        alice.end_call(alice_msg_id).await?;
+        let sent = alice.pop_sent_msg().await;
+        bob.recv_msg_trash(&sent).await;
+        bob.evtracker
+            .get_matching(|evt| matches!(evt, EventType::CallEnded { .. }))
+            .await;
+        assert_eq!(bob.get_call_info(bob_msg.id).await?.state, CallState::Ended);
+        assert!(bob.end_call(bob_msg.id).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_call_cancelled() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        tcm.send_recv_accept(bob, alice, "Hi").await;
+        let alice_chat = alice.get_chat(bob).await;
+        let alice_msg_id = alice.place_outgoing_call(alice_chat.id, "offer").await?;
+        let bob_msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+
+        alice.end_call(alice_msg_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        bob.evtracker
+            .get_matching(|evt| matches!(evt, EventType::CallMissed { .. }))
+            .await;
+        assert_eq!(
+            bob.get_call_info(bob_msg.id).await?.state,
+            CallState::Missed
+        );
+        let bob_msg = Message::load_from_db(bob, bob_msg.id).await?;
+        assert_eq!(bob_msg.get_text(), "Missed call");
+        assert!(bob
+            .accept_incoming_call(bob_msg.id, "answer")
+            .await
+            .is_err());
+
+        // Calls are only possible in 1:1 chats.
+        let group_id =
+            chat::create_group_chat(alice, chat::ProtectionStatus::Unprotected, "Group").await?;
+        assert!(alice.place_outgoing_call(group_id, "offer").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_call_rejected() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        tcm.send_recv_accept(bob, alice, "Hi").await;
+        let alice_chat = alice.get_chat(bob).await;
+        let alice_msg_id = alice.place_outgoing_call(alice_chat.id, "offer").await?;
+        let bob_msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+        bob_msg.chat_id.accept(bob).await?;
+
+        bob.end_call(bob_msg.id).await?;
+        let sent = bob.pop_sent_msg().await;
+        alice.recv_msg_trash(&sent).await;
+        assert_eq!(
+            alice.get_call_info(alice_msg_id).await?.state,
+            CallState::Declined
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_call_control_from_others() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        tcm.send_recv_accept(bob, alice, "Hi").await;
+        let alice_chat = alice.get_chat(bob).await;
+        alice.place_outgoing_call(alice_chat.id, "offer").await?;
+        let bob_msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+
+        // Unencrypted control messages, even from the caller, are ignored,
+        // as are control messages from others.
+        for (i, from) in ["alice@example.org", "fiona@example.net"]
+            .into_iter()
+            .enumerate()
+        {
+            let raw = format!(
+                "From: {from}\n\
+                 To: bob@example.net\n\
+                 Subject: Call ended\n\
+                 Message-ID: <end{i}@example.org>\n\
+                 In-Reply-To: <{}>\n\
+                 Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+                 Chat-Version: 1.0\n\
+                 Chat-Content: call-ended\n\
+                 \n\
+                 Call ended\n",
+                bob_msg.rfc724_mid
+            );
+            receive_imf(bob, raw.as_bytes(), false).await?;
+            assert_eq!(
+                bob.get_call_info(bob_msg.id).await?.state,
+                CallState::Pending
+            );
+        }
+
+        // Encrypted control messages from others are ignored as well.
+        tcm.send_recv_accept(bob, fiona, "Hi").await;
+        let fiona_chat = fiona.get_chat(bob).await;
+        let mut msg = Message::new(Viewtype::Text);
+        msg.hidden = true;
+        msg.param.set_cmd(SystemMessage::CallEnded);
+        msg.in_reply_to = Some(bob_msg.rfc724_mid.clone());
+        let sent = fiona.send_msg(fiona_chat.id, &mut msg).await;
+        assert!(sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+        bob.recv_msg_trash(&sent).await;
+        assert_eq!(
+            bob.get_call_info(bob_msg.id).await?.state,
+            CallState::Pending
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_unencrypted_call_invite() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        // Alice does not have Bob's key yet.
+        let alice_chat = alice.create_chat_with_contact("", "bob@example.net").await;
+        let mut msg = Message::new(Viewtype::Text);
+        msg.param.set_cmd(SystemMessage::CallInvite);
+        msg.param.set(Param::Arg, "offer");
+        let sent = alice.send_msg(alice_chat.id, &mut msg).await;
+        assert!(!sent.payload().contains("-----BEGIN PGP MESSAGE-----"));
+        assert!(sent.payload().contains("Chat-Content: call-invite"));
+
+        bob.recv_msg(&sent).await;
+        assert!(bob
+            .evtracker
+            .get_matching_opt(bob, |evt| matches!(evt, EventType::IncomingCall { .. }))
+            .await
+            .is_none());
+        Ok(())
+    }
+}
@@ -15,7 +15,7 @@
 use tokio::task;
 
 use crate::aheader::EncryptPreference;
-use crate::blob::BlobObject;
+use crate::blob::{pdf, video, waveform, BlobObject, ImageLimitsExceeded};
 use crate::chatlist::Chatlist;
 use crate::chatlist_events;
 use crate::color::str_to_color;
@@ -23,6 +23,7 @@
 use crate::constants::{
     self, Blocked, Chattype, DC_CHAT_ID_ALLDONE_HINT, DC_CHAT_ID_ARCHIVED_LINK,
     DC_CHAT_ID_LAST_SPECIAL, DC_CHAT_ID_TRASH, DC_RESEND_USER_AVATAR_DAYS,
+    LARGE_GROUP_AVATAR_INTERVAL,
 };
 use crate::contact::{self, Contact, ContactId, Origin};
 use crate::context::Context;
@@ -30,25 +31,33 @@
 use crate::download::DownloadState;
 use crate::ephemeral::Timer as EphemeralTimer;
 use crate::events::EventType;
+use crate::group_spam;
 use crate::html::new_html_mimepart;
 use crate::location;
 use crate::log::LogExt;
-use crate::message::{self, Message, MessageState, MsgId, Viewtype};
+use crate::message::{
+    self, Message, MessageState, MsgId, MsgStateChangeCause, SaveFilePolicy, Viewtype,
+};
 use crate::mimefactory::MimeFactory;
 use crate::mimeparser::SystemMessage;
+use crate::net::latency;
+use crate::net::stats::NetworkCounters;
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
 use crate::receive_imf::ReceivedMsg;
 use crate::securejoin::BobState;
 use crate::smtp::send_msg_to_smtp;
 use crate::sql;
+use crate::staging;
 use crate::stock_str;
 use crate::sync::{self, Sync::*, SyncData};
+use crate::timezone::LocalTimezone;
 use crate::tools::{
     buf_compress, create_id, create_outgoing_rfc724_mid, create_smeared_timestamp,
-    create_smeared_timestamps, get_abs_path, gm2local_offset, smeared_time, time,
+    create_smeared_timestamps, get_abs_path, sanitize_filename, smeared_time, time,
     truncate_msg_text, IsNoneOrEmpty, SystemTime,
 };
+use crate::undo;
 use crate::webxdc::StatusUpdateSerial;
 
 /// An chat item, such as a message or a marker.
@@ -61,9 +70,9 @@ pub enum ChatItem {
     },
 
     /// Day marker, separating messages that correspond to different
-    /// days according to local time.
+    /// days according to local time, see [`crate::timezone`].
     DayMarker {
-        /// Marker timestamp, for day markers
+        /// Start of the local day.
         timestamp: i64,
     },
 }
@@ -105,6 +114,37 @@ pub enum ProtectionStatus {
     ProtectionBroken = 3, // `2` was never used as a value.
 }
 
+/// Whether messages to a chat can be encrypted end-to-end,
+/// see [`ChatId::get_encryption_state`].
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromPrimitive,
+    ToPrimitive,
+    FromSql,
+    ToSql,
+    Serialize,
+    Deserialize,
+)]
+#[repr(u32)]
+pub enum ChatEncryption {
+    /// Messages cannot be encrypted
+    /// because the key of at least one member is missing or the chat is a mailing list.
+    #[default]
+    Unavailable = 1,
+
+    /// Keys of all members are known, but not all of them are verified.
+    Available = 2,
+
+    /// Verified keys of all members are known.
+    Verified = 3,
+}
+
 /// The reason why messages cannot be sent to the chat.
 ///
 /// The reason is mainly for logging and displaying in debug REPL, thus not translated.
@@ -156,6 +196,17 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Reason why a message can't be encrypted to a member of a protected chat,
+/// see [`ChatId::validate_draft`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyProblem {
+    /// No key of the member is known.
+    Missing,
+
+    /// The key of the member is not verified.
+    Unverified,
+}
+
 /// Chat ID, including reserved IDs.
 ///
 /// Some chat IDs are reserved to identify special chat types.  This
@@ -279,6 +330,7 @@ pub(crate) async fn create_for_contact_with_blocked(
     ) -> Result<Self> {
         let chat_id = match ChatIdBlocked::lookup_by_contact(context, contact_id).await? {
             Some(chat) => {
+                undo::restore_chat(context, chat.id).await?;
                 if create_blocked != Blocked::Not || chat.blocked == Blocked::Not {
                     return Ok(chat.id);
                 }
@@ -369,6 +421,10 @@ async fn set_selfavatar_timestamp(self, context: &Context, timestamp: i64) -> Re
                 (timestamp, self),
             )
             .await?;
+        let mut chat = Chat::load_from_db(context, self).await?;
+        chat.param
+            .set_i64(Param::SelfavatarSentTimestamp, timestamp);
+        chat.update_param(context).await?;
         Ok(())
     }
 
@@ -500,6 +556,9 @@ pub(crate) async fn accept_ex(self, context: &Context, sync: sync::Sync) -> Resu
             }
         }
 
+        if chat.is_quarantined() {
+            group_spam::unquarantine(context, self).await?;
+        }
         if self.set_blocked(context, Blocked::Not).await? {
             context.emit_event(EventType::ChatModified(self));
             chatlist_events::emit_chatlist_item_changed(context, self);
@@ -702,6 +761,80 @@ pub(crate) async fn set_visibility_ex(
         Ok(())
     }
 
+    /// Advances the read position of the chat to the message `msg_id`,
+    /// see [`get_first_unread_msg`].
+    ///
+    /// Does nothing if the position is at a newer message already
+    /// or the message does not belong to the chat.
+    /// Returns true if the position was advanced.
+    pub(crate) async fn set_read_position_ex(
+        self,
+        context: &Context,
+        sync: sync::Sync,
+        msg_id: MsgId,
+    ) -> Result<bool> {
+        let advanced = context
+            .sql
+            .execute(
+                "UPDATE chats SET read_position=?1
+                 WHERE id=?2
+                 AND EXISTS (SELECT 1 FROM msgs WHERE id=?1 AND chat_id=?2)
+                 AND IFNULL(
+                     (SELECT (p.timestamp, p.id) < (m.timestamp, m.id)
+                      FROM msgs p, msgs m WHERE p.id=chats.read_position AND m.id=?1),
+                     1
+                 )",
+                (msg_id, self),
+            )
+            .await?
+            > 0;
+        if advanced && sync.into() {
+            let rfc724_mid: String = context
+                .sql
+                .query_get_value("SELECT rfc724_mid FROM msgs WHERE id=?", (msg_id,))
+                .await?
+                .unwrap_or_default();
+            if !rfc724_mid.is_empty() {
+                let chat = Chat::load_from_db(context, self).await?;
+                chat.sync(context, SyncAction::SetReadPosition(rfc724_mid))
+                    .await
+                    .log_err(context)
+                    .ok();
+            }
+        }
+        Ok(advanced)
+    }
+
+    /// Returns the subject template of the chat, empty if none is set.
+    pub async fn get_subject_template(self, context: &Context) -> Result<String> {
+        Ok(context
+            .sql
+            .query_get_value("SELECT subject_template FROM chats WHERE id=?", (self,))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Sets the subject template of the chat.
+    ///
+    /// The template is used as the subject of outgoing messages
+    /// instead of the automatic subject such as "Message from Alice",
+    /// replies are prefixed with "Re: ", so that classic email clients thread them.
+    /// `{name}` in the template is replaced with the own display name.
+    /// An empty template restores the automatic subject.
+    /// Group and broadcast chats always use the chat name as subject and ignore the template.
+    pub async fn set_subject_template(self, context: &Context, template: &str) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        context
+            .sql
+            .execute(
+                "UPDATE chats SET subject_template=? WHERE id=?",
+                (template.trim(), self),
+            )
+            .await?;
+        context.emit_event(EventType::ChatModified(self));
+        Ok(())
+    }
+
     /// Unarchives a chat that is archived and not muted.
     /// Needed after a message is added to a chat so that the chat gets a normal visibility again.
     /// `msg_state` is the state of the message. Matters only for incoming messages currently. For
@@ -755,16 +888,25 @@ pub async fn unarchive_if_not_muted(
     }
 
     /// Emits an appropriate event for a message. `important` is whether a notification should be
-    /// shown.
-    pub(crate) fn emit_msg_event(self, context: &Context, msg_id: MsgId, important: bool) {
+    /// shown, `watched` is whether the message contains a watch keyword.
+    pub(crate) fn emit_msg_event(
+        self,
+        context: &Context,
+        msg_id: MsgId,
+        important: bool,
+        watched: bool,
+    ) {
         if important {
-            context.emit_incoming_msg(self, msg_id);
+            context.emit_incoming_msg(self, msg_id, watched);
         } else {
             context.emit_msgs_changed(self, msg_id);
         }
     }
 
     /// Deletes a chat.
+    ///
+    /// If `Config::DeletionUndoWindow` is set, the chat is only hidden
+    /// and can be restored with [`crate::undo::undo_last_deletion`] until the window passes.
     pub async fn delete(self, context: &Context) -> Result<()> {
         ensure!(
             !self.is_special(),
@@ -772,6 +914,14 @@ pub async fn delete(self, context: &Context) -> Result<()> {
             self
         );
 
+        if undo::get_undo_window(context).await? > 0 {
+            return undo::delete_chat(context, self).await;
+        }
+        self.delete_now(context).await
+    }
+
+    /// Deletes a chat immediately.
+    pub(crate) async fn delete_now(self, context: &Context) -> Result<()> {
         let chat = Chat::load_from_db(context, self).await?;
 
         context
@@ -783,6 +933,8 @@ pub async fn delete(self, context: &Context) -> Result<()> {
                 )?;
                 transaction.execute("DELETE FROM msgs WHERE chat_id=?", (self,))?;
                 transaction.execute("DELETE FROM chats_contacts WHERE chat_id=?", (self,))?;
+                transaction.execute("DELETE FROM staged_attachments WHERE chat_id=?", (self,))?;
+                transaction.execute("DELETE FROM member_changes WHERE chat_id=?", (self,))?;
                 transaction.execute("DELETE FROM chats WHERE id=?", (self,))?;
                 Ok(())
             })
@@ -814,7 +966,10 @@ pub async fn set_draft(self, context: &Context, mut msg: Option<&mut Message>) -
         }
 
         let changed = match &mut msg {
-            None => self.maybe_delete_draft(context).await?,
+            None => {
+                staging::discard_chat_attachments(context, self).await?;
+                self.maybe_delete_draft(context).await?
+            }
             Some(msg) => self.do_set_draft(context, msg).await?,
         };
 
@@ -835,6 +990,17 @@ pub async fn set_draft(self, context: &Context, mut msg: Option<&mut Message>) -
         Ok(())
     }
 
+    /// Cancels recoding images of messages being sent to the chat,
+    /// e.g. because the user discarded the message while it was being prepared.
+    ///
+    /// Sending these messages fails then.
+    pub fn cancel_image_recoding(self, context: &Context) {
+        let cancelled = context.image_recodes.lock().cancel(self);
+        if cancelled > 0 {
+            info!(context, "Cancelled {cancelled} image recodings for {self}.");
+        }
+    }
+
     /// Returns ID of the draft message, if there is one.
     async fn get_draft_msg_id(self, context: &Context) -> Result<Option<MsgId>> {
         let msg_id: Option<MsgId> = context
@@ -861,6 +1027,39 @@ pub async fn get_draft(self, context: &Context) -> Result<Option<Message>> {
         }
     }
 
+    /// Returns the members of a protected chat to whom a message can't be encrypted.
+    ///
+    /// Messages to protected chats are only encrypted to verified keys,
+    /// so sending fails with an error naming the members
+    /// if the key of a member became unavailable or unverified.
+    /// The UI should call this before sending and tell the user which members are affected.
+    /// The list is empty for unprotected chats.
+    pub async fn validate_draft(self, context: &Context) -> Result<Vec<(ContactId, KeyProblem)>> {
+        let chat = Chat::load_from_db(context, self).await?;
+        if !chat.is_protected() {
+            return Ok(Vec::new());
+        }
+        let mut problems = Vec::new();
+        for contact_id in get_chat_contacts(context, self).await? {
+            if contact_id == ContactId::SELF {
+                continue;
+            }
+            let contact = Contact::get_by_id(context, contact_id).await?;
+            let peerstate = Peerstate::from_addr(context, contact.get_addr()).await?;
+            let problem = match peerstate {
+                Some(peerstate) if peerstate.peek_key(true).is_some() => continue,
+                Some(peerstate) if peerstate.peek_key(false).is_some() => KeyProblem::Unverified,
+                _ => KeyProblem::Missing,
+            };
+            info!(
+                context,
+                "Cannot encrypt to {contact_id} in protected {self}: {problem:?} key."
+            );
+            problems.push((contact_id, problem));
+        }
+        Ok(problems)
+    }
+
     /// Deletes draft message, if there is one.
     ///
     /// Returns `true`, if message was deleted, `false` otherwise.
@@ -1338,6 +1537,57 @@ pub async fn get_encryption_info(self, context: &Context) -> Result<String> {
         Ok(ret.trim().to_string())
     }
 
+    /// Returns whether messages to the chat can be encrypted end-to-end.
+    ///
+    /// The state is cached in the database
+    /// and recomputed after the chat members or their peerstates changed,
+    /// so this is cheap enough to be called for every chat of the chatlist.
+    /// A `ChatlistItemChanged` event is emitted for the chat when the cached state is reset.
+    /// Chats without other members, e.g. the "Saved messages" chat, are [`ChatEncryption::Verified`].
+    pub async fn get_encryption_state(self, context: &Context) -> Result<ChatEncryption> {
+        let cached: Option<ChatEncryption> = context
+            .sql
+            .query_get_value("SELECT encryption_state FROM chats WHERE id=?", (self,))
+            .await?
+            .flatten();
+        if let Some(state) = cached {
+            return Ok(state);
+        }
+        let state = self.compute_encryption_state(context).await?;
+        context
+            .sql
+            .execute(
+                "UPDATE chats SET encryption_state=? WHERE id=?",
+                (state, self),
+            )
+            .await?;
+        Ok(state)
+    }
+
+    async fn compute_encryption_state(self, context: &Context) -> Result<ChatEncryption> {
+        let chat = Chat::load_from_db(context, self).await?;
+        if chat.typ == Chattype::Mailinglist || chat.is_device_talk() {
+            return Ok(ChatEncryption::Unavailable);
+        }
+        let mut state = ChatEncryption::Verified;
+        for contact_id in get_chat_contacts(context, self)
+            .await?
+            .into_iter()
+            .filter(|contact_id| !contact_id.is_special())
+        {
+            let contact = Contact::get_by_id(context, contact_id).await?;
+            let peerstate = Peerstate::from_addr(context, contact.get_addr()).await?;
+            match peerstate {
+                Some(peerstate) if peerstate.peek_key(true).is_some() => {}
+                Some(peerstate) if peerstate.peek_key(false).is_some() => {
+                    state = ChatEncryption::Available;
+                }
+                _ => return Ok(ChatEncryption::Unavailable),
+            }
+        }
+        Ok(state)
+    }
+
     /// Bad evil escape hatch.
     ///
     /// Avoid using this, eventually types should be cleaned up enough
@@ -1564,6 +1814,10 @@ pub struct Chat {
 
     /// If the chat is protected (verified).
     pub(crate) protected: ProtectionStatus,
+
+    /// If the group looks like an unsolicited group addition,
+    /// see [`crate::group_spam`].
+    quarantined: bool,
 }
 
 impl Chat {
@@ -1573,7 +1827,8 @@ pub async fn load_from_db(context: &Context, chat_id: ChatId) -> Result<Self> {
             .sql
             .query_row(
                 "SELECT c.type, c.name, c.grpid, c.param, c.archived,
-                    c.blocked, c.locations_send_until, c.muted_until, c.protected
+                    c.blocked, c.locations_send_until, c.muted_until, c.protected,
+                    c.quarantined
              FROM chats c
              WHERE c.id=?;",
                 (chat_id,),
@@ -1589,6 +1844,7 @@ pub async fn load_from_db(context: &Context, chat_id: ChatId) -> Result<Self> {
                         is_sending_locations: row.get(6)?,
                         mute_duration: row.get(7)?,
                         protected: row.get(8)?,
+                        quarantined: row.get(9)?,
                     };
                     Ok(c)
                 },
@@ -1875,6 +2131,15 @@ pub fn is_contact_request(&self) -> bool {
         self.blocked == Blocked::Request
     }
 
+    /// Returns true if the chat is a group that looks like an unsolicited group addition.
+    ///
+    /// Such chats are contact requests created by unknown contacts
+    /// and should be presented with a warning,
+    /// see [`crate::group_spam::leave_and_block_creator`].
+    pub fn is_quarantined(&self) -> bool {
+        self.quarantined
+    }
+
     /// Returns true if the chat is not promoted.
     pub fn is_unpromoted(&self) -> bool {
         self.param.get_bool(Param::Unpromoted).unwrap_or_default()
@@ -2690,7 +2955,11 @@ pub async fn prepare_msg(context: &Context, chat_id: ChatId, msg: &mut Message)
     Ok(msg_id)
 }
 
-async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
+pub(crate) async fn prepare_msg_blob(
+    context: &Context,
+    chat_id: ChatId,
+    msg: &mut Message,
+) -> Result<()> {
     if msg.viewtype == Viewtype::Text || msg.viewtype == Viewtype::VideochatInvitation {
         // the caller should check if the message text is empty
     } else if msg.viewtype.has_file() {
@@ -2699,8 +2968,37 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
             .get_blob(Param::File, context, !msg.is_increation())
             .await?
             .with_context(|| format!("attachment missing for message of type #{}", msg.viewtype))?;
+        if let Some(staged_chat_id) = msg.param.get_int(Param::Staged) {
+            // The attachment was prepared when it was staged.
+            msg.param.remove(Param::Staged);
+            ensure!(
+                staged_chat_id == chat_id.to_u32() as i32,
+                "Attachment was staged for another chat than {chat_id}"
+            );
+            info!(
+                context,
+                "Attaching staged \"{}\" for message type #{}.",
+                blob.to_abs_path().display(),
+                msg.viewtype
+            );
+            return Ok(());
+        }
         let send_as_is = msg.viewtype == Viewtype::File;
 
+        if msg.viewtype == Viewtype::Image {
+            // HEIF images can't be displayed by many clients, send them as JPEG.
+            match blob.convert_heif_to_jpeg(context).await {
+                Ok(true) => {
+                    msg.param.remove(Param::MimeType);
+                }
+                Ok(false) => {}
+                Err(err) => warn!(
+                    context,
+                    "Cannot convert HEIF image, sending as is: {err:#}."
+                ),
+            }
+        }
+
         if msg.viewtype == Viewtype::File || msg.viewtype == Viewtype::Image {
             // Correct the type, take care not to correct already very special
             // formats as GIF or VOICE.
@@ -2729,16 +3027,69 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
             msg.try_set_vcard(context, &blob.to_abs_path()).await?;
         }
 
+        if !matches!(
+            msg.viewtype,
+            Viewtype::Image | Viewtype::Gif | Viewtype::Sticker
+        ) && context
+            .get_config_bool(Config::StripAttachmentMetadata)
+            .await?
+        {
+            if let Err(err) = blob.scrub_metadata(context).await {
+                warn!(context, "Cannot strip metadata, sending as is: {err:#}.");
+            }
+        }
+
+        let original_quality = msg.viewtype == Viewtype::Image
+            && msg.is_original_quality()
+            && match tokio::fs::metadata(blob.to_abs_path()).await {
+                Ok(meta) if meta.len() <= constants::MAX_ORIGINAL_IMAGE_BYTES => true,
+                Ok(meta) => {
+                    warn!(
+                        context,
+                        "Image of {} bytes is too large to send in original quality.",
+                        meta.len()
+                    );
+                    false
+                }
+                Err(err) => return Err(err).context("Failed to get image size"),
+            };
         let mut maybe_sticker = msg.viewtype == Viewtype::Sticker;
+        // Still GIFs are sent as is, animated ones are scaled down if they are too large.
+        let is_animation = msg.viewtype == Viewtype::Gif && blob.is_animated().await?;
         if !send_as_is
+            && !original_quality
             && (msg.viewtype == Viewtype::Image
+                || is_animation
                 || maybe_sticker && !msg.param.exists(Param::ForceSticker))
         {
-            blob.recode_to_image_size(context, &mut maybe_sticker)
-                .await?;
-
-            if !maybe_sticker {
-                msg.viewtype = Viewtype::Image;
+            let orig_name = blob.as_name().to_string();
+            // Device messages are added by the core, the user does not wait for them.
+            let progress_chat_id = match chat_id.is_device_talk(context).await? {
+                true => None,
+                false => Some(chat_id),
+            };
+            match blob
+                .recode_to_image_size_ex(context, progress_chat_id, &mut maybe_sticker)
+                .await
+            {
+                Ok(()) => {
+                    if blob.as_name() != orig_name {
+                        // The image was converted, e.g. an SVG sticker to PNG.
+                        msg.param.remove(Param::MimeType);
+                    }
+                    if !maybe_sticker {
+                        // Animations scaled down are converted to GIF.
+                        msg.viewtype = match blob.suffix() {
+                            Some("gif") => Viewtype::Gif,
+                            _ => Viewtype::Image,
+                        };
+                    }
+                }
+                Err(err) if err.is::<ImageLimitsExceeded>() => {
+                    warn!(context, "Sending image as file: {err:#}.");
+                    msg.viewtype = Viewtype::File;
+                }
+                Err(err) => return Err(err),
             }
         }
         msg.param.set(Param::File, blob.as_name());
@@ -2757,7 +3108,27 @@ async fn prepare_msg_blob(context: &Context, msg: &mut Message) -> Result<()> {
             }
         }
 
+        // Hash the attachment once here instead of every time the message is rendered.
+        msg.param
+            .set(Param::FileHash, blob.checksum(context).await?);
+
         msg.try_calc_and_set_dimensions(context).await?;
+        if msg.viewtype == Viewtype::Video {
+            video::set_video_params(context, &mut msg.param)
+                .await
+                .log_err(context)
+                .ok();
+        } else if msg.viewtype == Viewtype::Voice {
+            waveform::set_waveform_param(context, &mut msg.param)
+                .await
+                .log_err(context)
+                .ok();
+        } else if msg.viewtype == Viewtype::File {
+            pdf::set_preview_param(context, &mut msg.param)
+                .await
+                .log_err(context)
+                .ok();
+        }
 
         info!(
             context,
@@ -2796,6 +3167,26 @@ async fn prepare_msg_common(
         }
     }
 
+    // Messages sent to a chat pending deletion must not be purged with it.
+    undo::restore_chat(context, chat_id).await?;
+
+    // Check the message can be encrypted to all members of a protected chat
+    // instead of failing when it is rendered.
+    if chat.is_protected() && msg.param.get_cmd() != SystemMessage::SecurejoinMessage {
+        let problems = chat_id.validate_draft(context).await?;
+        if !problems.is_empty() {
+            let mut members = Vec::with_capacity(problems.len());
+            for (contact_id, _) in problems {
+                let contact = Contact::get_by_id(context, contact_id).await?;
+                members.push(contact.get_name_n_addr());
+            }
+            bail!(
+                "cannot send to {chat_id}: no verified key of {}",
+                members.join(", ")
+            );
+        }
+    }
+
     // Check a quote reply is not leaking data from other chats.
     // This is meant as a last line of defence, the UI should check that before as well.
     // (We allow Chattype::Single in general for "Reply Privately";
@@ -2823,7 +3214,7 @@ async fn prepare_msg_common(
     // ... then change the MessageState in the message object
     msg.state = change_state_to;
 
-    prepare_msg_blob(context, msg).await?;
+    prepare_msg_blob(context, chat_id, msg).await?;
     if !msg.hidden {
         chat_id.unarchive_if_not_muted(context, msg.state).await?;
     }
@@ -2929,7 +3320,7 @@ async fn send_msg_inner(context: &Context, chat_id: ChatId, msg: &mut Message) -
             context.emit_location_changed(Some(ContactId::SELF)).await?;
         }
 
-        context.scheduler.interrupt_smtp().await;
+        context.interrupt_smtp().await;
     }
 
     Ok(msg.id)
@@ -3059,6 +3450,7 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
     }
 
     if attach_selfavatar {
+        NetworkCounters::inc(&context.network_counters.avatars_attached);
         if let Err(err) = msg.chat_id.set_selfavatar_timestamp(context, now).await {
             error!(context, "Failed to set selfavatar timestamp: {err:#}.");
         }
@@ -3068,9 +3460,16 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
         msg.param.set_int(Param::GuaranteeE2ee, 1);
         msg.update_param(context).await?;
     }
+    if let Some(hash) = &rendered_msg.attachment_hash {
+        if msg.param.get(Param::FileHash) != Some(hash.as_str()) {
+            msg.param.set(Param::FileHash, hash);
+            msg.update_param(context).await?;
+        }
+    }
 
     msg.subject.clone_from(&rendered_msg.subject);
     msg.update_subject(context).await?;
+    let send_after = undo::send_after(context, msg).await?;
     let chunk_size = context.get_max_smtp_rcpt_to().await?;
     let trans_fn = |t: &mut rusqlite::Transaction| {
         let mut row_ids = Vec::<i64>::new();
@@ -3087,13 +3486,14 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
             for recipients_chunk in recipients.chunks(chunk_size) {
                 let recipients_chunk = recipients_chunk.join(" ");
                 let row_id = t.execute(
-                    "INSERT INTO smtp (rfc724_mid, recipients, mime, msg_id) \
-                    VALUES            (?1,         ?2,         ?3,   ?4)",
+                    "INSERT INTO smtp (rfc724_mid, recipients, mime, msg_id, send_after) \
+                    VALUES            (?1,         ?2,         ?3,   ?4,     ?5)",
                     (
                         &rendered_msg.rfc724_mid,
                         recipients_chunk,
                         &rendered_msg.message,
                         msg.id,
+                        send_after,
                     ),
                 )?;
                 row_ids.push(row_id.try_into()?);
@@ -3101,7 +3501,14 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
         }
         Ok(row_ids)
     };
-    context.sql.transaction(trans_fn).await
+    let row_ids = context.sql.transaction(trans_fn).await?;
+    if !row_ids.is_empty() {
+        latency::record_queued(context, msg.id, send_after)
+            .await
+            .log_err(context)
+            .ok();
+    }
+    Ok(row_ids)
 }
 
 /// Sends a text message to the given chat.
@@ -3183,6 +3590,7 @@ pub async fn get_chat_msgs_ex(
         info_only,
         add_daymarker,
     } = options;
+    let timezone = LocalTimezone::load(context).await?;
     let process_row = if info_only {
         |row: &rusqlite::Row| {
             // is_info logic taken from Message.is_info()
@@ -3229,18 +3637,16 @@ pub async fn get_chat_msgs_ex(
         sorted_rows.sort_unstable();
 
         let mut ret = Vec::new();
-        let mut last_day = 0;
-        let cnv_to_local = gm2local_offset();
+        let mut last_day = None;
 
         for (ts, curr_id) in sorted_rows {
             if add_daymarker {
-                let curr_local_timestamp = ts + cnv_to_local;
-                let curr_day = curr_local_timestamp / 86400;
-                if curr_day != last_day {
+                let curr_day = timezone.date(ts);
+                if last_day != Some(curr_day) {
                     ret.push(ChatItem::DayMarker {
-                        timestamp: curr_day * 86400, // Convert day back to Unix timestamp
+                        timestamp: timezone.day_start(ts),
                     });
-                    last_day = curr_day;
+                    last_day = Some(curr_day);
                 }
             }
             ret.push(ChatItem::Message { msg_id: curr_id });
@@ -3297,6 +3703,41 @@ pub(crate) async fn marknoticed_chat_if_older_than(
     Ok(())
 }
 
+/// Returns the first incoming message after the read position of the chat,
+/// `None` if there are no unread messages.
+///
+/// The read position is the newest message marked as seen
+/// with [`crate::message::markseen_msgs`] on any of the own devices,
+/// so UIs can scroll to the returned message when the chat is opened.
+/// If no position is known yet, the first incoming message
+/// after the newest seen one is returned.
+pub async fn get_first_unread_msg(context: &Context, chat_id: ChatId) -> Result<Option<MsgId>> {
+    context
+        .sql
+        .query_get_value(
+            "SELECT m.id FROM msgs m
+             WHERE m.chat_id=?1 AND m.hidden=0 AND m.state IN (?2, ?3, ?4)
+             AND IFNULL(
+                 (m.timestamp, m.id) > (SELECT p.timestamp, p.id FROM msgs p, chats c
+                                        WHERE c.id=?1 AND p.id=c.read_position),
+                 m.state!=?4 AND IFNULL(
+                     (m.timestamp, m.id) > (SELECT s.timestamp, s.id FROM msgs s
+                                            WHERE s.chat_id=?1 AND s.hidden=0 AND s.state=?4
+                                            ORDER BY s.timestamp DESC, s.id DESC LIMIT 1),
+                     1
+                 )
+             )
+             ORDER BY m.timestamp, m.id LIMIT 1",
+            (
+                chat_id,
+                MessageState::InFresh,
+                MessageState::InNoticed,
+                MessageState::InSeen,
+            ),
+        )
+        .await
+}
+
 /// Marks all messages in the chat as noticed.
 /// If the given chat-id is the archive-link, marks all messages in all archived chats as noticed.
 pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()> {
@@ -3318,42 +3759,75 @@ pub async fn marknoticed_chat(context: &Context, chat_id: ChatId) -> Result<()>
             return Ok(());
         }
 
-        context
+        let noticed_msgs = context
             .sql
-            .execute(
-                &format!(
-                    "UPDATE msgs SET state=13 WHERE state=10 AND hidden=0 AND chat_id IN ({});",
+            .call_write(|conn| {
+                let mut stmt = conn.prepare(&format!(
+                    "UPDATE msgs SET state=13 WHERE state=10 AND hidden=0 AND chat_id IN ({})
+                     RETURNING id, chat_id",
                     sql::repeat_vars(chat_ids_in_archive.len())
-                ),
-                rusqlite::params_from_iter(&chat_ids_in_archive),
-            )
+                ))?;
+                let rows = stmt
+                    .query_map(rusqlite::params_from_iter(&chat_ids_in_archive), |row| {
+                        Ok((row.get::<_, MsgId>(0)?, row.get::<_, ChatId>(1)?))
+                    })?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+                    .map_err(Into::into)
+            })
             .await?;
+        emit_msgs_noticed_state_changed(context, &noticed_msgs);
         for chat_id_in_archive in chat_ids_in_archive {
             context.emit_event(EventType::MsgsNoticed(chat_id_in_archive));
             chatlist_events::emit_chatlist_item_changed(context, chat_id_in_archive);
         }
-    } else if context
-        .sql
-        .execute(
-            "UPDATE msgs
-            SET state=?
-          WHERE state=?
-            AND hidden=0
-            AND chat_id=?;",
-            (MessageState::InNoticed, MessageState::InFresh, chat_id),
-        )
-        .await?
-        == 0
-    {
-        return Ok(());
+    } else {
+        let noticed_msgs = context
+            .sql
+            .call_write(|conn| {
+                let mut stmt = conn.prepare(
+                    "UPDATE msgs
+                    SET state=?
+                  WHERE state=?
+                    AND hidden=0
+                    AND chat_id=?
+                  RETURNING id, chat_id",
+                )?;
+                let rows = stmt.query_map(
+                    (MessageState::InNoticed, MessageState::InFresh, chat_id),
+                    |row| Ok((row.get::<_, MsgId>(0)?, row.get::<_, ChatId>(1)?)),
+                )?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+                    .map_err(Into::into)
+            })
+            .await?;
+        if noticed_msgs.is_empty() {
+            return Ok(());
+        }
+        emit_msgs_noticed_state_changed(context, &noticed_msgs);
     }
 
     context.emit_event(EventType::MsgsNoticed(chat_id));
     chatlist_events::emit_chatlist_item_changed(context, chat_id);
-    context.on_archived_chats_maybe_noticed();
+    // The changed messages are reported by `MsgStateChanged` events,
+    // so only the counter of the archive link needs to be updated.
+    chatlist_events::emit_chatlist_item_changed(context, DC_CHAT_ID_ARCHIVED_LINK);
     Ok(())
 }
 
+/// Emits [`EventType::MsgStateChanged`] for fresh messages marked as noticed.
+fn emit_msgs_noticed_state_changed(context: &Context, msgs: &[(MsgId, ChatId)]) {
+    for &(msg_id, chat_id) in msgs {
+        message::emit_msg_state_changed(
+            context,
+            chat_id,
+            msg_id,
+            MessageState::InFresh,
+            MessageState::InNoticed,
+            MsgStateChangeCause::MarkedSeen,
+        );
+    }
+}
+
 /// Marks messages preceding outgoing messages as noticed.
 ///
 /// In a chat, if there is an outgoing message, it can be assumed that all previous
@@ -3471,6 +3945,52 @@ pub async fn get_chat_media(
     Ok(list)
 }
 
+/// Saves copies of all attachments of the chat `chat_id` to the directory `dir`,
+/// e.g. to export all media of a chat.
+///
+/// The original file names are used, files with the same name are numbered.
+/// Messages which are not fully downloaded are skipped.
+/// Returns the paths of the saved files.
+pub async fn save_all_attachments(
+    context: &Context,
+    chat_id: ChatId,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID {chat_id}");
+    let msg_ids = context
+        .sql
+        .query_map(
+            "SELECT id FROM msgs
+             WHERE chat_id=? AND hidden=0 AND type NOT IN (?, ?)
+             ORDER BY timestamp, id",
+            (chat_id, Viewtype::Unknown, Viewtype::Text),
+            |row| row.get::<_, MsgId>(0),
+            |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    tokio::fs::create_dir_all(dir).await?;
+    let mut paths = Vec::new();
+    for msg_id in msg_ids {
+        let msg = Message::load_from_db(context, msg_id).await?;
+        if !msg.viewtype.has_file() || msg.download_state != DownloadState::Done {
+            continue;
+        }
+        let Some(filename) = msg.get_filename() else {
+            continue;
+        };
+        let mut filename = sanitize_filename(&filename);
+        if filename.is_empty() {
+            filename = "file".to_string();
+        }
+        let path = msg
+            .save_file_ex(context, &dir.join(filename), SaveFilePolicy::AutoNumber)
+            .await
+            .with_context(|| format!("Failed to save file of message {msg_id}"))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
 /// Returns a vector of contact IDs for given chat ID.
 pub async fn get_chat_contacts(context: &Context, chat_id: ChatId) -> Result<Vec<ContactId>> {
     // Normal chats do not include SELF.  Group chats do (as it may happen that one is deleted from a
@@ -3494,6 +4014,131 @@ pub async fn get_chat_contacts(context: &Context, chat_id: ChatId) -> Result<Vec
     Ok(list)
 }
 
+/// Message statistics of a chat, see [`get_stats()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ChatStats {
+    /// Number of messages, info messages and other system messages are not counted.
+    pub msg_cnt: usize,
+
+    /// Timestamp of the first message.
+    pub first_timestamp: Option<i64>,
+
+    /// Timestamp of the last message.
+    pub last_timestamp: Option<i64>,
+
+    /// Statistics per sender, the most active senders first.
+    pub members: Vec<ChatMemberStats>,
+
+    /// Number of messages per viewtype for messages with attachments.
+    pub media_cnt: Vec<(Viewtype, usize)>,
+
+    /// Average number of seconds between a message
+    /// and the previous message from a different sender.
+    pub avg_response_time: Option<i64>,
+}
+
+/// Message statistics of a single sender in a chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChatMemberStats {
+    pub contact_id: ContactId,
+
+    /// Number of messages sent by the contact.
+    pub msg_cnt: usize,
+
+    /// Timestamp of the first message sent by the contact.
+    pub first_timestamp: i64,
+
+    /// Timestamp of the last message sent by the contact.
+    pub last_timestamp: i64,
+}
+
+/// Returns message statistics of the chat.
+///
+/// Only messages stored on this device are taken into account.
+pub async fn get_stats(context: &Context, chat_id: ChatId) -> Result<ChatStats> {
+    ensure!(!chat_id.is_special(), "Invalid chat ID");
+
+    // Info messages are recognized by `Param::Cmd` which can't be checked in SQL.
+    let msgs = context
+        .sql
+        .query_map(
+            "SELECT from_id, timestamp, type, param
+               FROM msgs
+              WHERE chat_id=?1 AND hidden=0 AND from_id!=?2 AND to_id!=?2
+              ORDER BY timestamp, id",
+            (chat_id, ContactId::INFO),
+            |row| {
+                let from_id: ContactId = row.get(0)?;
+                let timestamp: i64 = row.get(1)?;
+                let viewtype: Viewtype = row.get(2)?;
+                let param: String = row.get(3)?;
+                Ok((from_id, timestamp, viewtype, param))
+            },
+            |rows| {
+                let mut msgs = Vec::new();
+                for row in rows {
+                    let (from_id, timestamp, viewtype, param) = row?;
+                    let cmd = Params::from_str(&param).unwrap_or_default().get_cmd();
+                    if cmd == SystemMessage::Unknown || cmd == SystemMessage::AutocryptSetupMessage
+                    {
+                        msgs.push((from_id, timestamp, viewtype));
+                    }
+                }
+                Ok(msgs)
+            },
+        )
+        .await?;
+
+    let mut members: Vec<ChatMemberStats> = Vec::new();
+    let mut media_cnt: Vec<(Viewtype, usize)> = Vec::new();
+    let mut response_time_sum = 0;
+    let mut response_cnt = 0;
+    let mut prev: Option<(ContactId, i64)> = None;
+    for (from_id, timestamp, viewtype) in msgs {
+        match members
+            .iter_mut()
+            .find(|member| member.contact_id == from_id)
+        {
+            Some(member) => {
+                member.msg_cnt += 1;
+                member.first_timestamp = member.first_timestamp.min(timestamp);
+                member.last_timestamp = member.last_timestamp.max(timestamp);
+            }
+            None => members.push(ChatMemberStats {
+                contact_id: from_id,
+                msg_cnt: 1,
+                first_timestamp: timestamp,
+                last_timestamp: timestamp,
+            }),
+        }
+        if viewtype != Viewtype::Unknown && viewtype != Viewtype::Text {
+            match media_cnt.iter_mut().find(|(t, _)| *t == viewtype) {
+                Some((_, cnt)) => *cnt += 1,
+                None => media_cnt.push((viewtype, 1)),
+            }
+        }
+        if let Some((prev_from_id, prev_timestamp)) = prev {
+            if prev_from_id != from_id {
+                response_time_sum += timestamp - prev_timestamp;
+                response_cnt += 1;
+            }
+        }
+        prev = Some((from_id, timestamp));
+    }
+    members.sort_by_key(|member| (cmp::Reverse(member.msg_cnt), member.contact_id));
+    media_cnt.sort_by_key(|(viewtype, cnt)| (cmp::Reverse(*cnt), *viewtype as u32));
+
+    Ok(ChatStats {
+        msg_cnt: members.iter().map(|member| member.msg_cnt).sum(),
+        first_timestamp: members.iter().map(|member| member.first_timestamp).min(),
+        last_timestamp: members.iter().map(|member| member.last_timestamp).max(),
+        members,
+        media_cnt,
+        avg_response_time: (response_cnt > 0)
+            .then(|| (response_time_sum as f64 / response_cnt as f64).round() as i64),
+    })
+}
+
 /// Creates a group chat with a given `name`.
 pub async fn create_group_chat(
     context: &Context,
@@ -3638,9 +4283,11 @@ pub(crate) async fn update_chat_contacts_table(
                     (id, contact_id),
                 )?;
             }
+            reset_encryption_state(transaction, id)?;
             Ok(())
         })
         .await?;
+    chatlist_events::emit_chatlist_item_changed(context, id);
     Ok(())
 }
 
@@ -3659,10 +4306,11 @@ pub(crate) async fn add_to_chat_contacts_table(
                     (chat_id, contact_id),
                 )?;
             }
+            reset_encryption_state(transaction, chat_id)?;
             Ok(())
         })
         .await?;
-
+    chatlist_events::emit_chatlist_item_changed(context, chat_id);
     Ok(())
 }
 
@@ -3674,11 +4322,27 @@ pub(crate) async fn remove_from_chat_contacts_table(
 ) -> Result<()> {
     context
         .sql
-        .execute(
-            "DELETE FROM chats_contacts WHERE chat_id=? AND contact_id=?",
-            (chat_id, contact_id),
-        )
+        .transaction(move |transaction| {
+            transaction.execute(
+                "DELETE FROM chats_contacts WHERE chat_id=? AND contact_id=?",
+                (chat_id, contact_id),
+            )?;
+            reset_encryption_state(transaction, chat_id)?;
+            Ok(())
+        })
         .await?;
+    chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    Ok(())
+}
+
+/// Makes [`ChatId::get_encryption_state`] recompute the state after the chat members changed.
+///
+/// The caller should emit a `ChatlistItemChanged` event for the chat after the transaction.
+fn reset_encryption_state(transaction: &rusqlite::Transaction, chat_id: ChatId) -> Result<()> {
+    transaction.execute(
+        "UPDATE chats SET encryption_state=NULL WHERE id=?",
+        (chat_id,),
+    )?;
     Ok(())
 }
 
@@ -3810,7 +4474,7 @@ pub(crate) async fn add_contact_to_chat_ex(
 /// a `Chat-User-Avatar: 0` header should be sent to reset the avatar.
 pub(crate) async fn shall_attach_selfavatar(context: &Context, chat_id: ChatId) -> Result<bool> {
     let timestamp_some_days_ago = time() - DC_RESEND_USER_AVATAR_DAYS * 24 * 60 * 60;
-    let needs_attach = context
+    let (needs_attach, all_need_attach) = context
         .sql
         .query_map(
             "SELECT c.selfavatar_sent
@@ -3821,18 +4485,53 @@ pub(crate) async fn shall_attach_selfavatar(context: &Context, chat_id: ChatId)
             |row| Ok(row.get::<_, i64>(0)),
             |rows| {
                 let mut needs_attach = false;
+                let mut all_need_attach = true;
                 for row in rows {
                     let row = row?;
                     let selfavatar_sent = row?;
                     if selfavatar_sent < timestamp_some_days_ago {
                         needs_attach = true;
+                    } else {
+                        all_need_attach = false;
                     }
                 }
-                Ok(needs_attach)
+                Ok((needs_attach, all_need_attach))
             },
         )
         .await?;
-    Ok(needs_attach)
+    if !needs_attach || all_need_attach || !is_large_group(context, chat_id).await? {
+        return Ok(needs_attach);
+    }
+
+    // Only some members miss the avatar, usually because they joined recently.
+    // In large groups they are served in batches
+    // instead of attaching the avatar to every message while members join.
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    let sent_timestamp = chat
+        .param
+        .get_i64(Param::SelfavatarSentTimestamp)
+        .unwrap_or_default();
+    if time() < sent_timestamp.saturating_add(LARGE_GROUP_AVATAR_INTERVAL) {
+        NetworkCounters::inc(&context.network_counters.avatars_throttled);
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Returns true if the chat has more members than [`Config::LargeGroupSize`].
+pub(crate) async fn is_large_group(context: &Context, chat_id: ChatId) -> Result<bool> {
+    let large_group_size = context.get_config_int(Config::LargeGroupSize).await?;
+    if large_group_size <= 0 {
+        return Ok(false);
+    }
+    let member_cnt = context
+        .sql
+        .count(
+            "SELECT COUNT(*) FROM chats_contacts WHERE chat_id=?",
+            (chat_id,),
+        )
+        .await?;
+    Ok(member_cnt > usize::try_from(large_group_size)?)
 }
 
 /// Chat mute duration.
@@ -3996,7 +4695,7 @@ pub async fn remove_contact_from_chat(
     Ok(())
 }
 
-async fn set_group_explicitly_left(context: &Context, grpid: &str) -> Result<()> {
+pub(crate) async fn set_group_explicitly_left(context: &Context, grpid: &str) -> Result<()> {
     if !is_group_explicitly_left(context, grpid).await? {
         context
             .sql
@@ -4192,6 +4891,8 @@ pub async fn forward_msgs(context: &Context, msg_ids: &[MsgId], chat_id: ChatId)
         msg.param.remove(Param::WebxdcDocumentTimestamp);
         msg.param.remove(Param::WebxdcSummary);
         msg.param.remove(Param::WebxdcSummaryTimestamp);
+        // The received attachment may have been converted, it is hashed again when sending.
+        msg.param.remove(Param::FileHash);
         msg.in_reply_to = None;
 
         // do not leak data as group names; a default subject is generated by mimefactory
@@ -4222,7 +4923,7 @@ pub async fn forward_msgs(context: &Context, msg_ids: &[MsgId], chat_id: ChatId)
                 .await?;
             curr_timestamp += 1;
             if !create_send_msg_jobs(context, &mut msg).await?.is_empty() {
-                context.scheduler.interrupt_smtp().await;
+                context.interrupt_smtp().await;
             }
         }
         created_chats.push(chat_id);
@@ -4282,6 +4983,14 @@ pub async fn resend_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
             chat_id: msg.chat_id,
             msg_id: msg.id,
         });
+        message::emit_msg_state_changed(
+            context,
+            msg.chat_id,
+            msg.id,
+            msg.get_state(),
+            MessageState::OutPending,
+            MsgStateChangeCause::ResendRequested,
+        );
         msg.timestamp_sort = create_smeared_timestamp(context);
         // note(treefit): only matters if it is the last message in chat (but probably to expensive to check, debounce also solves it)
         chatlist_events::emit_chatlist_item_changed(context, msg.chat_id);
@@ -4307,17 +5016,17 @@ pub async fn resend_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
                 // runs in parallel, it would miss the race and instead of resending just remove the
                 // updates thinking that they have been already sent.
                 conn.execute(
-                    "INSERT INTO smtp_status_updates (msg_id, first_serial, last_serial, descr) \
-                     VALUES(?, ?, ?, '') \
+                    "INSERT INTO smtp_status_updates (msg_id, first_serial, last_serial, descr, timestamp) \
+                     VALUES(?, ?, ?, '', ?) \
                      ON CONFLICT(msg_id) \
                      DO UPDATE SET first_serial=min(first_serial - 1, excluded.first_serial)",
-                    (msg.id, range.0, range.1),
+                    (msg.id, range.0, range.1, time()),
                 )?;
                 Ok(())
             };
             context.sql.call_write(conn_fn).await?;
         }
-        context.scheduler.interrupt_smtp().await;
+        context.interrupt_smtp().await;
     }
     Ok(())
 }
@@ -4386,7 +5095,7 @@ pub async fn add_device_msg_with_importance(
         chat_id = ChatId::get_for_contact(context, ContactId::DEVICE).await?;
 
         let rfc724_mid = create_outgoing_rfc724_mid();
-        prepare_msg_blob(context, msg).await?;
+        prepare_msg_blob(context, chat_id, msg).await?;
 
         let timestamp_sent = create_smeared_timestamp(context);
 
@@ -4458,7 +5167,7 @@ pub async fn add_device_msg_with_importance(
     }
 
     if !msg_id.is_unset() {
-        chat_id.emit_msg_event(context, msg_id, important);
+        chat_id.emit_msg_event(context, msg_id, important, false);
     }
 
     Ok(msg_id)
@@ -4652,6 +5361,8 @@ pub(crate) enum SyncAction {
     Rename(String),
     /// Set chat contacts by their addresses.
     SetContacts(Vec<String>),
+    /// Advance the read position to the message with the given Message-ID.
+    SetReadPosition(String),
 }
 
 impl Context {
@@ -4710,6 +5421,18 @@ pub(crate) async fn sync_alter_chat(&self, id: &SyncId, action: &SyncAction) ->
             }
             SyncAction::Rename(to) => rename_ex(self, Nosync, chat_id, to).await,
             SyncAction::SetContacts(addrs) => set_contacts_by_addrs(self, chat_id, addrs).await,
+            SyncAction::SetReadPosition(rfc724_mid) => {
+                // If the message is not received yet, the position is advanced
+                // once the message is marked as seen on this device.
+                let Some((msg_id, _)) = message::rfc724_mid_exists(self, rfc724_mid).await? else {
+                    info!(self, "Read position {rfc724_mid} not found in {chat_id}.");
+                    return Ok(());
+                };
+                if chat_id.set_read_position_ex(self, Nosync, msg_id).await? {
+                    self.emit_event(EventType::ChatModified(chat_id));
+                }
+                Ok(())
+            }
         }
     }
 
@@ -4728,9 +5451,9 @@ mod tests {
     use crate::chatlist::get_archived_cnt;
     use crate::constants::{DC_GCL_ARCHIVED_ONLY, DC_GCL_NO_SPECIALS};
     use crate::headerdef::HeaderDef;
-    use crate::message::delete_msgs;
+    use crate::message::{delete_msgs, markseen_msgs};
     use crate::receive_imf::receive_imf;
-    use crate::test_utils::{sync, TestContext, TestContextManager};
+    use crate::test_utils::{mark_as_verified, sync, TestContext, TestContextManager};
     use strum::IntoEnumIterator;
     use tokio::fs;
 
@@ -5901,25 +6624,21 @@ async fn msg_from(t: &TestContext, name: &str, num: u32) -> Result<()> {
         // mark one of the archived+muted chats as noticed: check that the archive-link counter is changed as well
         t.evtracker.clear_events();
         marknoticed_chat(&t, claire_chat_id).await?;
-        let ev = t
-            .evtracker
+        t.evtracker
             .get_matching(|ev| {
                 matches!(
                     ev,
-                    EventType::MsgsChanged {
-                        chat_id: DC_CHAT_ID_ARCHIVED_LINK,
-                        ..
+                    EventType::ChatlistItemChanged {
+                        chat_id: Some(DC_CHAT_ID_ARCHIVED_LINK),
                     }
                 )
             })
             .await;
-        assert_eq!(
-            ev,
-            EventType::MsgsChanged {
-                chat_id: DC_CHAT_ID_ARCHIVED_LINK,
-                msg_id: MsgId::new(0),
-            }
-        );
+        let ev = t
+            .evtracker
+            .get_matching_opt(&t, |ev| matches!(ev, EventType::MsgsChanged { .. }))
+            .await;
+        assert!(ev.is_none());
         assert_eq!(bob_chat_id.get_fresh_msg_cnt(&t).await?, 2);
         assert_eq!(claire_chat_id.get_fresh_msg_cnt(&t).await?, 0);
         assert_eq!(DC_CHAT_ID_ARCHIVED_LINK.get_fresh_msg_cnt(&t).await?, 1);
@@ -6120,6 +6839,35 @@ async fn test_shall_attach_selfavatar() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_shall_attach_selfavatar_large_group() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(Config::LargeGroupSize, Some("3")).await?;
+        let chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, "foo").await?;
+        for addr in ["bob@example.net", "fiona@example.net"] {
+            let contact_id = Contact::create(&t, "", addr).await?;
+            add_contact_to_chat(&t, chat_id, contact_id).await?;
+        }
+        assert!(!is_large_group(&t, chat_id).await?);
+        assert!(shall_attach_selfavatar(&t, chat_id).await?);
+        chat_id.set_selfavatar_timestamp(&t, time()).await?;
+
+        // A new member of the large group waits for the next batch.
+        let contact_id = Contact::create(&t, "", "claire@example.net").await?;
+        add_contact_to_chat(&t, chat_id, contact_id).await?;
+        assert!(is_large_group(&t, chat_id).await?);
+        assert!(!shall_attach_selfavatar(&t, chat_id).await?);
+        assert_eq!(t.get_network_stats().avatars_throttled, 1);
+
+        // A changed avatar is sent at once.
+        t.set_config(Config::Selfavatar, None).await?;
+        assert!(shall_attach_selfavatar(&t, chat_id).await?);
+
+        t.set_config(Config::LargeGroupSize, Some("0")).await?;
+        assert!(!is_large_group(&t, chat_id).await?);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_set_mute_duration() {
         let t = TestContext::new().await;
@@ -7418,6 +8166,91 @@ async fn test_blob_renaming() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_original_quality() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let chat_id = alice
+            .create_chat_with_contact("bob", "bob@example.net")
+            .await
+            .id;
+        let bytes = include_bytes!("../test-data/image/screenshot.jpg");
+        let file = alice.get_blobdir().join("screenshot.jpg");
+        fs::write(&file, bytes).await?;
+
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file(file.to_str().unwrap(), None);
+        msg.set_original_quality(true);
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let msg = Message::load_from_db(&alice, sent.sender_msg_id).await?;
+        assert_eq!(msg.get_viewtype(), Viewtype::Image);
+        assert_eq!(msg.get_width(), 1920);
+        assert_eq!(fs::read(msg.get_file(&alice).unwrap()).await?, bytes);
+
+        // Without the flag, the image is scaled down.
+        let mut msg = Message::new(Viewtype::Image);
+        msg.set_file_from_bytes(&alice, "screenshot.jpg", bytes, None)
+            .await?;
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let msg = Message::load_from_db(&alice, sent.sender_msg_id).await?;
+        assert_eq!(msg.get_width(), constants::BALANCED_IMAGE_SIZE as i32);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_save_all_attachments() -> Result<()> {
+        let alice = TestContext::new_alice().await;
+        let chat_id = alice
+            .create_chat_with_contact("bob", "bob@example.net")
+            .await
+            .id;
+        let dir = tempfile::tempdir()?;
+        for content in ["first", "second"] {
+            let file = dir.path().join(content).join("report.txt");
+            fs::create_dir_all(file.parent().unwrap()).await?;
+            fs::write(&file, content).await?;
+            let mut msg = Message::new(Viewtype::File);
+            msg.set_file(file.to_str().unwrap(), None);
+            alice.send_msg(chat_id, &mut msg).await;
+        }
+        alice.send_text(chat_id, "no attachment").await;
+
+        let export_dir = dir.path().join("export");
+        let paths = save_all_attachments(&alice, chat_id, &export_dir).await?;
+        assert_eq!(
+            paths,
+            [
+                export_dir.join("report.txt"),
+                export_dir.join("report (1).txt")
+            ]
+        );
+        assert_eq!(fs::read_to_string(&paths[0]).await?, "first");
+        assert_eq!(fs::read_to_string(&paths[1]).await?, "second");
+
+        let msg_id = get_chat_msgs(&alice, chat_id)
+            .await?
+            .into_iter()
+            .find_map(|item| match item {
+                ChatItem::Message { msg_id } => Some(msg_id),
+                _ => None,
+            })
+            .unwrap();
+        let msg = Message::load_from_db(&alice, msg_id).await?;
+        assert!(msg.save_file(&alice, &paths[1]).await.is_err());
+        let path = msg
+            .save_file_ex(&alice, &paths[1], SaveFilePolicy::Overwrite)
+            .await?;
+        assert_eq!(path, paths[1]);
+        assert_eq!(fs::read_to_string(&paths[1]).await?, "first");
+        // The temporary file is renamed.
+        let mut entries = fs::read_dir(&export_dir).await?;
+        let mut count = 0;
+        while entries.next_entry().await?.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_sync_blocked() -> Result<()> {
         let alice0 = &TestContext::new_alice().await;
@@ -7623,6 +8456,119 @@ async fn test_sync_visibility() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sync_read_position() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+        let bob = &TestContext::new_bob().await;
+        let a0b_chat_id = alice0.create_chat(bob).await.id;
+        let a1b_chat_id = alice1.create_chat(bob).await.id;
+        let bob_chat_id = bob.create_chat(alice0).await.id;
+
+        let mut a0_msgs = Vec::new();
+        let mut a1_msgs = Vec::new();
+        for text in ["one", "two", "three"] {
+            let sent = bob.send_text(bob_chat_id, text).await;
+            a0_msgs.push(alice0.recv_msg(&sent).await.id);
+            a1_msgs.push(alice1.recv_msg(&sent).await.id);
+        }
+        assert_eq!(
+            get_first_unread_msg(alice1, a1b_chat_id).await?,
+            Some(a1_msgs[0])
+        );
+
+        // Without a read position, only messages after the newest seen one are unread.
+        alice1
+            .sql
+            .execute(
+                "UPDATE msgs SET state=? WHERE id=?",
+                (MessageState::InSeen, a1_msgs[1]),
+            )
+            .await?;
+        assert_eq!(
+            get_first_unread_msg(alice1, a1b_chat_id).await?,
+            Some(a1_msgs[2])
+        );
+        alice1
+            .sql
+            .execute(
+                "UPDATE msgs SET state=? WHERE id=?",
+                (MessageState::InFresh, a1_msgs[1]),
+            )
+            .await?;
+
+        markseen_msgs(alice0, vec![a0_msgs[0], a0_msgs[1]]).await?;
+        assert_eq!(
+            get_first_unread_msg(alice0, a0b_chat_id).await?,
+            Some(a0_msgs[2])
+        );
+        sync(alice0, alice1).await;
+        assert_eq!(
+            get_first_unread_msg(alice1, a1b_chat_id).await?,
+            Some(a1_msgs[2])
+        );
+
+        // Marking an older message as seen does not move the position back.
+        markseen_msgs(alice1, vec![a1_msgs[0]]).await?;
+        assert_eq!(
+            get_first_unread_msg(alice1, a1b_chat_id).await?,
+            Some(a1_msgs[2])
+        );
+
+        markseen_msgs(alice1, vec![a1_msgs[2]]).await?;
+        assert_eq!(get_first_unread_msg(alice1, a1b_chat_id).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_validate_draft() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        mark_as_verified(alice, bob).await;
+        let sent = fiona
+            .send_text(fiona.create_chat(alice).await.id, "hi")
+            .await;
+        alice.recv_msg(&sent).await;
+        let fiona_id = alice.add_or_lookup_contact(fiona).await.id;
+        let dora_id = Contact::create(alice, "Dora", "dora@example.net").await?;
+
+        let chat_id = alice
+            .create_group_with_members(ProtectionStatus::Protected, "Group", &[bob])
+            .await;
+        assert!(chat_id.validate_draft(alice).await?.is_empty());
+
+        add_to_chat_contacts_table(alice, chat_id, &[fiona_id, dora_id]).await?;
+        let mut problems = chat_id.validate_draft(alice).await?;
+        problems.sort_by_key(|(contact_id, _)| *contact_id);
+        assert_eq!(
+            problems,
+            vec![
+                (fiona_id, KeyProblem::Unverified),
+                (dora_id, KeyProblem::Missing)
+            ]
+        );
+
+        // Sending fails naming the members.
+        let err = send_text_msg(alice, chat_id, "Hi".to_string())
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("fiona@example.net"));
+        assert!(err.contains("dora@example.net"));
+        assert!(!err.contains("bob@example.net"));
+
+        let chat_id = alice
+            .create_group_with_members(ProtectionStatus::Unprotected, "Group", &[fiona])
+            .await;
+        assert!(chat_id.validate_draft(alice).await?.is_empty());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_sync_muted() -> Result<()> {
         let alice0 = &TestContext::new_alice().await;
@@ -7790,4 +8736,97 @@ async fn test_do_not_overwrite_draft() -> Result<()> {
         assert_eq!(draft1.timestamp_sort, draft2.timestamp_sort);
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_stats() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        bob.create_chat(alice).await;
+
+        let stats = get_stats(alice, alice_chat_id).await?;
+        assert_eq!(stats, ChatStats::default());
+
+        tcm.send_recv(alice, bob, "Hi").await;
+        tcm.send_recv(bob, alice, "Hello").await;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "notes.txt", b"notes", None)
+            .await?;
+        alice.send_msg(alice_chat_id, &mut msg).await;
+        // Info messages are not counted.
+        alice_chat_id
+            .set_ephemeral_timer(alice, EphemeralTimer::Enabled { duration: 60 })
+            .await?;
+        add_info_msg(alice, alice_chat_id, "Info", time()).await?;
+
+        let stats = get_stats(alice, alice_chat_id).await?;
+        assert_eq!(stats.msg_cnt, 3);
+        let bob_id = alice.add_or_lookup_contact_id(bob).await;
+        assert_eq!(
+            stats
+                .members
+                .iter()
+                .map(|member| (member.contact_id, member.msg_cnt))
+                .collect::<Vec<_>>(),
+            [(ContactId::SELF, 2), (bob_id, 1)]
+        );
+        assert!(stats.first_timestamp <= stats.last_timestamp);
+        assert_eq!(stats.media_cnt, [(Viewtype::File, 1)]);
+        assert!(stats.avg_response_time.unwrap() >= 0);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_encryption_state() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let chat_id = create_group_chat(alice, ProtectionStatus::Unprotected, "Group").await?;
+        let bob_id = alice.add_or_lookup_contact_id(bob).await;
+        add_contact_to_chat(alice, chat_id, bob_id).await?;
+        assert_eq!(
+            chat_id.get_encryption_state(alice).await?,
+            ChatEncryption::Unavailable
+        );
+
+        // Receiving Bob's key updates the cached state.
+        alice.evtracker.clear_events();
+        tcm.send_recv(bob, alice, "Hi").await;
+        alice
+            .evtracker
+            .get_matching(|evt| {
+                matches!(
+                    evt,
+                    EventType::ChatlistItemChanged { chat_id: Some(id) } if *id == chat_id
+                )
+            })
+            .await;
+        assert_eq!(
+            chat_id.get_encryption_state(alice).await?,
+            ChatEncryption::Available
+        );
+
+        mark_as_verified(alice, bob).await;
+        assert_eq!(
+            chat_id.get_encryption_state(alice).await?,
+            ChatEncryption::Verified
+        );
+
+        let fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+        add_contact_to_chat(alice, chat_id, fiona_id).await?;
+        assert_eq!(
+            chat_id.get_encryption_state(alice).await?,
+            ChatEncryption::Unavailable
+        );
+
+        let self_chat = alice.get_self_chat().await;
+        assert_eq!(
+            self_chat.id.get_encryption_state(alice).await?,
+            ChatEncryption::Verified
+        );
+        Ok(())
+    }
 }
@@ -1,15 +1,21 @@
 //! # Chat list module.
 
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use anyhow::{ensure, Context as _, Result};
 use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
 
 use crate::chat::{update_special_chat_names, Chat, ChatId, ChatVisibility};
+use crate::collation::NameCollator;
 use crate::constants::{
     Blocked, Chattype, DC_CHAT_ID_ALLDONE_HINT, DC_CHAT_ID_ARCHIVED_LINK, DC_GCL_ADD_ALLDONE_HINT,
-    DC_GCL_ARCHIVED_ONLY, DC_GCL_FOR_FORWARDING, DC_GCL_NO_SPECIALS,
+    DC_GCL_ARCHIVED_ONLY, DC_GCL_FOR_FORWARDING, DC_GCL_NO_SPECIALS, DC_GCL_SORT_BY_NAME,
 };
 use crate::contact::{Contact, ContactId};
 use crate::context::Context;
+use crate::events::EventType;
 use crate::message::{Message, MessageState, MsgId};
 use crate::param::{Param, Params};
 use crate::stock_str;
@@ -82,6 +88,9 @@ impl Chatlist {
     ///   not needed when DC_GCL_ARCHIVED_ONLY is already set)
     /// - if the flag DC_GCL_ADD_ALLDONE_HINT is set, DC_CHAT_ID_ALLDONE_HINT
     ///   is added as needed.
+    /// - if the flag DC_GCL_SORT_BY_NAME is set, chats are sorted alphabetically by name
+    ///   according to [`Config::Locale`](crate::config::Config::Locale).
+    ///   Special entries and pinned chats stay on top.
     ///
     /// `query`: An optional query for filtering the list. Only chats matching this query
     /// are returned. When `is:unread` is contained in the query, the chatlist is
@@ -99,6 +108,7 @@ pub async fn try_load(
         let flag_for_forwarding = 0 != listflags & DC_GCL_FOR_FORWARDING;
         let flag_no_specials = 0 != listflags & DC_GCL_NO_SPECIALS;
         let flag_add_alldone_hint = 0 != listflags & DC_GCL_ADD_ALLDONE_HINT;
+        let flag_sort_by_name = 0 != listflags & DC_GCL_SORT_BY_NAME;
 
         let process_row = |row: &rusqlite::Row| {
             let chat_id: ChatId = row.get(0)?;
@@ -129,7 +139,7 @@ pub async fn try_load(
         //
         // The query shows messages from blocked contacts in
         // groups. Otherwise it would be hard to follow conversations.
-        let ids = if let Some(query_contact_id) = query_contact_id {
+        let mut ids = if let Some(query_contact_id) = query_contact_id {
             // show chats shared with a given contact
             context.sql.query_map(
                 "SELECT c.id, m.id
@@ -144,6 +154,7 @@ pub async fn try_load(
                                   ORDER BY timestamp DESC, id DESC LIMIT 1)
                  WHERE c.id>9
                    AND c.blocked!=1
+                   AND c.pending_deletion=0
                    AND c.id IN(SELECT chat_id FROM chats_contacts WHERE contact_id=?2)
                  GROUP BY c.id
                  ORDER BY c.archived=?3 DESC, IFNULL(m.timestamp,c.created_timestamp) DESC, m.id DESC;",
@@ -171,6 +182,7 @@ pub async fn try_load(
                                   ORDER BY timestamp DESC, id DESC LIMIT 1)
                  WHERE c.id>9
                    AND c.blocked!=1
+                   AND c.pending_deletion=0
                    AND c.archived=1
                  GROUP BY c.id
                  ORDER BY IFNULL(m.timestamp,c.created_timestamp) DESC, m.id DESC;",
@@ -207,6 +219,7 @@ pub async fn try_load(
                                   ORDER BY timestamp DESC, id DESC LIMIT 1)
                  WHERE c.id>9 AND c.id!=?2
                    AND c.blocked!=1
+                   AND c.pending_deletion=0
                    AND c.name LIKE ?3
                    AND (NOT ?4 OR EXISTS (SELECT 1 FROM msgs m WHERE m.chat_id = c.id AND m.state == ?5 AND hidden=0))
                  GROUP BY c.id
@@ -260,6 +273,7 @@ pub async fn try_load(
                                       ORDER BY timestamp DESC, id DESC LIMIT 1)
                      WHERE c.id>9 AND c.id!=?
                        AND c.blocked=0
+                       AND c.pending_deletion=0
                        AND NOT c.archived=?
                        AND (c.type!=? OR c.id IN(SELECT chat_id FROM chats_contacts WHERE contact_id=?))
                      GROUP BY c.id
@@ -287,6 +301,7 @@ pub async fn try_load(
                                       ORDER BY timestamp DESC, id DESC LIMIT 1)
                      WHERE c.id>9 AND c.id!=?
                        AND (c.blocked=0 OR c.blocked=2)
+                       AND c.pending_deletion=0
                        AND NOT c.archived=?
                      GROUP BY c.id
                      ORDER BY c.id=0 DESC, c.archived=? DESC, IFNULL(m.timestamp,c.created_timestamp) DESC, m.id DESC;",
@@ -304,6 +319,11 @@ pub async fn try_load(
             ids
         };
 
+        if flag_sort_by_name {
+            sort_by_name(context, &mut ids).await?;
+        }
+
+        context.on_chatlist_loaded();
         Ok(Chatlist { ids })
     }
 
@@ -445,12 +465,43 @@ pub fn iter(&self) -> impl Iterator<Item = &(ChatId, Option<MsgId>)> {
     }
 }
 
+/// Sorts chats alphabetically by name.
+///
+/// Special entries such as the archive link and pinned chats stay on top.
+async fn sort_by_name(context: &Context, ids: &mut [(ChatId, Option<MsgId>)]) -> Result<()> {
+    let chats: HashMap<ChatId, (String, bool)> = context
+        .sql
+        .query_map(
+            "SELECT id, name, archived=? FROM chats WHERE id>9",
+            (ChatVisibility::Pinned,),
+            |row| {
+                let chat_id: ChatId = row.get(0)?;
+                let name: String = row.get(1)?;
+                let pinned: bool = row.get(2)?;
+                Ok((chat_id, (name, pinned)))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<HashMap<_, _>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    let collator = NameCollator::load(context).await?;
+    ids.sort_by(|(a, _), (b, _)| match (chats.get(a), chats.get(b)) {
+        (Some((name_a, pinned_a)), Some((name_b, pinned_b))) => pinned_b
+            .cmp(pinned_a)
+            .then_with(|| collator.compare(name_a, name_b)),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    });
+    Ok(())
+}
+
 /// Returns the number of archived chats
 pub async fn get_archived_cnt(context: &Context) -> Result<usize> {
     let count = context
         .sql
         .count(
-            "SELECT COUNT(*) FROM chats WHERE blocked!=? AND archived=?;",
+            "SELECT COUNT(*) FROM chats WHERE blocked!=? AND archived=? AND pending_deletion=0;",
             (Blocked::Yes, ChatVisibility::Archived),
         )
         .await?;
@@ -476,6 +527,168 @@ pub async fn get_last_message_for_chat(
         .await
 }
 
+/// State of the chatlist diff events, see [`set_chatlist_diff_events`].
+#[derive(Debug, Default)]
+pub(crate) struct ChatlistDiffState {
+    /// True if diff events are enabled.
+    enabled: AtomicBool,
+
+    /// True if computing the diff is scheduled but has not started yet.
+    scheduled: AtomicBool,
+
+    /// The chatlist as known to the UI, `None` if diff events are disabled.
+    snapshot: Mutex<Option<ChatlistSnapshot>>,
+}
+
+/// Chat IDs and fresh message counts of the default chatlist.
+#[derive(Debug, Default)]
+struct ChatlistSnapshot {
+    chat_ids: Vec<ChatId>,
+    fresh_msg_counts: HashMap<ChatId, usize>,
+}
+
+impl ChatlistSnapshot {
+    async fn load(context: &Context) -> Result<Self> {
+        let chat_ids: Vec<ChatId> = Chatlist::try_load(context, 0, None, None)
+            .await?
+            .iter()
+            .map(|(chat_id, _)| *chat_id)
+            .collect();
+
+        // Same as `ChatId::get_fresh_msg_cnt()`, but for all chats at once.
+        let mut fresh_msg_counts = context
+            .sql
+            .query_map(
+                "SELECT chat_id, COUNT(*) FROM msgs
+                 WHERE state=? AND hidden=0 AND chat_id>9
+                 GROUP BY chat_id",
+                (MessageState::InFresh,),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    let count: usize = row.get(1)?;
+                    Ok((chat_id, count))
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<HashMap<_, _>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await?;
+        if chat_ids.contains(&DC_CHAT_ID_ARCHIVED_LINK) {
+            fresh_msg_counts.insert(
+                DC_CHAT_ID_ARCHIVED_LINK,
+                DC_CHAT_ID_ARCHIVED_LINK.get_fresh_msg_cnt(context).await?,
+            );
+        }
+        Ok(Self {
+            chat_ids,
+            fresh_msg_counts,
+        })
+    }
+
+    /// Returns the events transforming the `self` chatlist into the `new` one.
+    fn diff(&self, new: &ChatlistSnapshot) -> Vec<EventType> {
+        let mut events = Vec::new();
+        let new_chat_ids: HashSet<ChatId> = new.chat_ids.iter().copied().collect();
+        let mut chat_ids = Vec::with_capacity(self.chat_ids.len());
+        for &chat_id in &self.chat_ids {
+            if new_chat_ids.contains(&chat_id) {
+                chat_ids.push(chat_id);
+            } else {
+                events.push(EventType::ChatlistItemRemoved { chat_id });
+            }
+        }
+
+        // `chat_ids` and `new.chat_ids` have the same prefix up to `index`,
+        // so `index` is never out of bounds of `chat_ids`.
+        for (index, &chat_id) in new.chat_ids.iter().enumerate() {
+            if chat_ids.get(index) != Some(&chat_id) {
+                if let Some(old_index) = chat_ids.iter().position(|id| *id == chat_id) {
+                    chat_ids.remove(old_index);
+                }
+                chat_ids.insert(index, chat_id);
+                events.push(EventType::ChatlistItemMoved { chat_id, index });
+            }
+        }
+
+        for &chat_id in &new.chat_ids {
+            let count = new
+                .fresh_msg_counts
+                .get(&chat_id)
+                .copied()
+                .unwrap_or_default();
+            let old_count = self
+                .fresh_msg_counts
+                .get(&chat_id)
+                .copied()
+                .unwrap_or_default();
+            if count != old_count {
+                events.push(EventType::ChatlistItemFreshMsgCountChanged { chat_id, count });
+            }
+        }
+        events
+    }
+}
+
+/// Enables or disables chatlist diff events.
+///
+/// If enabled, changes of the default chatlist, i.e. the one returned by
+/// `Chatlist::try_load(context, 0, None, None)`, are additionally reported as
+/// [`EventType::ChatlistItemRemoved`], [`EventType::ChatlistItemMoved`]
+/// and [`EventType::ChatlistItemFreshMsgCountChanged`] events,
+/// so the UI can update the list without reloading it.
+/// The diffs are relative to the chatlist at the time of enabling,
+/// so the UI should load the chatlist after calling this.
+///
+/// Diff events are disabled by default, because computing them requires
+/// loading the chatlist after each change.
+pub async fn set_chatlist_diff_events(context: &Context, enabled: bool) -> Result<()> {
+    let mut snapshot = context.chatlist_diff.snapshot.lock().await;
+    *snapshot = match enabled {
+        true => Some(ChatlistSnapshot::load(context).await?),
+        false => None,
+    };
+    context
+        .chatlist_diff
+        .enabled
+        .store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Schedules computing and emitting the chatlist diff events if they are enabled.
+///
+/// Changes happening before the diff is computed are reported together.
+pub(crate) fn schedule_chatlist_diff(context: &Context) {
+    let state = &context.chatlist_diff;
+    if !state.enabled.load(Ordering::Relaxed) || state.scheduled.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let context = context.clone();
+    tokio::spawn(async move {
+        if let Err(err) = emit_chatlist_diff(&context).await {
+            warn!(context, "Failed to compute chatlist diff: {err:#}.");
+        }
+    });
+}
+
+async fn emit_chatlist_diff(context: &Context) -> Result<()> {
+    let mut snapshot = context.chatlist_diff.snapshot.lock().await;
+    // Changes from now on schedule a new diff.
+    context
+        .chatlist_diff
+        .scheduled
+        .store(false, Ordering::Relaxed);
+    let Some(snapshot) = snapshot.as_mut() else {
+        return Ok(());
+    };
+    let new_snapshot = ChatlistSnapshot::load(context).await?;
+    for event in snapshot.diff(&new_snapshot) {
+        context.emit_event(event);
+    }
+    *snapshot = new_snapshot;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -567,6 +780,21 @@ async fn test_try_load() {
         assert_eq!(chats.len(), 1);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sort_by_name() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let bob = create_group_chat(&t, ProtectionStatus::Unprotected, "bob").await?;
+        let carol = create_group_chat(&t, ProtectionStatus::Unprotected, "Carol").await?;
+        let alice = create_group_chat(&t, ProtectionStatus::Unprotected, "alice").await?;
+        let dave = create_group_chat(&t, ProtectionStatus::Unprotected, "Dave").await?;
+        dave.set_visibility(&t, ChatVisibility::Pinned).await?;
+
+        let chats = Chatlist::try_load(&t, DC_GCL_SORT_BY_NAME, None, None).await?;
+        let ids: Vec<ChatId> = chats.iter().map(|(chat_id, _)| *chat_id).collect();
+        assert_eq!(ids, [dave, alice, bob, carol]);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_sort_self_talk_up_on_forward() {
         let t = TestContext::new().await;
@@ -822,4 +1050,65 @@ async fn test_load_broken() {
         assert!(chats.get_summary(&t, 2, None).await.is_err());
         assert_eq!(chats.get_index_for_id(chat_id1).unwrap(), 2);
     }
+
+    #[test]
+    fn test_chatlist_snapshot_diff() {
+        let ids = |ids: &[u32]| ids.iter().map(|id| ChatId::new(*id)).collect::<Vec<_>>();
+        let old = ChatlistSnapshot {
+            chat_ids: ids(&[10, 11, 12, 13]),
+            fresh_msg_counts: HashMap::from([(ChatId::new(12), 1)]),
+        };
+        let new = ChatlistSnapshot {
+            chat_ids: ids(&[12, 10, 14, 13]),
+            fresh_msg_counts: HashMap::from([(ChatId::new(12), 2)]),
+        };
+        let events = old.diff(&new);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            events[0],
+            EventType::ChatlistItemRemoved { chat_id } if chat_id == ChatId::new(11)
+        ));
+        assert!(matches!(
+            events[1],
+            EventType::ChatlistItemMoved { chat_id, index: 0 } if chat_id == ChatId::new(12)
+        ));
+        assert!(matches!(
+            events[2],
+            EventType::ChatlistItemMoved { chat_id, index: 2 } if chat_id == ChatId::new(14)
+        ));
+        assert!(matches!(
+            events[3],
+            EventType::ChatlistItemFreshMsgCountChanged { chat_id, count: 2 }
+                if chat_id == ChatId::new(12)
+        ));
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_chatlist_diff_events() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id1 = create_group_chat(&t, ProtectionStatus::Unprotected, "a chat").await?;
+        let chat_id2 = create_group_chat(&t, ProtectionStatus::Unprotected, "b chat").await?;
+        set_chatlist_diff_events(&t, true).await?;
+        t.evtracker.clear_events();
+
+        send_text_msg(&t, chat_id1, "hello".to_string()).await?;
+        t.evtracker
+            .get_matching(|evt| {
+                matches!(
+                    evt,
+                    EventType::ChatlistItemMoved { chat_id, index: 0 } if *chat_id == chat_id1
+                )
+            })
+            .await;
+
+        set_chatlist_diff_events(&t, false).await?;
+        send_text_msg(&t, chat_id2, "hello".to_string()).await?;
+        assert!(t
+            .evtracker
+            .get_matching_opt(&t, |evt| matches!(evt, EventType::ChatlistItemMoved { .. }))
+            .await
+            .is_none());
+        Ok(())
+    }
 }
@@ -0,0 +1,208 @@
+//! # Locale-aware sorting of names.
+//!
+//! Contact and chat names are sorted according to the locale set in [`Config::Locale`],
+//! so that e.g. "Ö" is sorted next to "O" in German and Cyrillic or CJK names
+//! are sorted naturally.
+//!
+//! Locale-aware collation requires the `icu-collation` feature.
+//! Without it, or if no locale is set,
+//! names are compared case-insensitively as before.
+//!
+//! In SQL queries, the collation is available as `icu_<locale>`,
+//! see [`sql_collation`].
+//! The collation is registered lazily by [`register_collations`]
+//! when a query uses it for the first time on a connection.
+
+use std::cmp::Ordering;
+
+use anyhow::{ensure, Result};
+
+use crate::config::Config;
+use crate::context::Context;
+
+/// Prefix of SQL collation names using a locale.
+#[cfg(feature = "icu-collation")]
+const COLLATION_PREFIX: &str = "icu_";
+
+/// Validates a BCP 47 language tag such as `de-DE`
+/// and returns its canonical form.
+pub(crate) fn parse_locale(tag: &str) -> Result<String> {
+    // Only ASCII letters, digits and separators are allowed,
+    // so the tag can be used in SQL collation names.
+    ensure!(
+        !tag.is_empty()
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "Invalid locale {tag:?}"
+    );
+
+    #[cfg(feature = "icu-collation")]
+    {
+        let locale = tag
+            .replace('_', "-")
+            .parse::<icu_locid::Locale>()
+            .map_err(|err| anyhow::anyhow!("Invalid locale {tag:?}: {err:?}"))?;
+        Ok(locale.to_string())
+    }
+
+    #[cfg(not(feature = "icu-collation"))]
+    Ok(tag.replace('_', "-"))
+}
+
+/// Returns the locale configured for sorting, if any.
+async fn get_locale(context: &Context) -> Result<Option<String>> {
+    match context
+        .get_config(Config::Locale)
+        .await?
+        .filter(|tag| !tag.is_empty())
+    {
+        Some(tag) => Ok(Some(parse_locale(&tag)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the SQL collation to use for sorting names.
+///
+/// The result can be used in SQL directly, e.g. `ORDER BY name COLLATE {collation}`.
+pub(crate) async fn sql_collation(context: &Context) -> Result<String> {
+    #[cfg(feature = "icu-collation")]
+    if let Some(locale) = get_locale(context).await? {
+        return Ok(format!("\"{COLLATION_PREFIX}{locale}\""));
+    }
+
+    #[cfg(not(feature = "icu-collation"))]
+    let _ = context;
+
+    Ok("NOCASE".to_string())
+}
+
+/// Makes locale collations available on the SQLite connection.
+#[cfg(feature = "icu-collation")]
+pub(crate) fn register_collations(conn: &rusqlite::Connection) -> Result<()> {
+    conn.collation_needed(|conn, name| {
+        if let Some(tag) = name.strip_prefix(COLLATION_PREFIX) {
+            let collator = NameCollator::new(Some(tag));
+            conn.create_collation(name, move |a, b| collator.compare(a, b))?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Compares names according to a locale.
+#[derive(Debug)]
+pub(crate) struct NameCollator {
+    #[cfg(feature = "icu-collation")]
+    collator: Option<std::panic::AssertUnwindSafe<icu_collator::Collator>>,
+}
+
+impl NameCollator {
+    /// Creates a collator for the given locale.
+    ///
+    /// Falls back to case-insensitive comparison
+    /// if the locale is unknown or unset.
+    pub(crate) fn new(locale: Option<&str>) -> Self {
+        #[cfg(feature = "icu-collation")]
+        {
+            let collator = locale.and_then(|tag| {
+                let locale = parse_locale(tag).ok()?.parse::<icu_locid::Locale>().ok()?;
+                let mut options = icu_collator::CollatorOptions::new();
+                // Ignore case, but not accents.
+                options.strength = Some(icu_collator::Strength::Secondary);
+                icu_collator::Collator::try_new(&(&locale).into(), options).ok()
+            });
+            Self {
+                collator: collator.map(std::panic::AssertUnwindSafe),
+            }
+        }
+
+        #[cfg(not(feature = "icu-collation"))]
+        {
+            let _ = locale;
+            Self {}
+        }
+    }
+
+    /// Creates a collator for the locale configured for the context.
+    pub(crate) async fn load(context: &Context) -> Result<Self> {
+        let locale = get_locale(context).await?;
+        Ok(Self::new(locale.as_deref()))
+    }
+
+    /// Compares two names.
+    pub(crate) fn compare(&self, a: &str, b: &str) -> Ordering {
+        #[cfg(feature = "icu-collation")]
+        if let Some(collator) = &self.collator {
+            return collator.compare(a, b);
+        }
+
+        a.to_lowercase().cmp(&b.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(parse_locale("de-DE").unwrap(), "de-DE");
+        assert_eq!(parse_locale("sv_SE").unwrap(), "sv-SE");
+        assert!(parse_locale("").is_err());
+        assert!(parse_locale("de\"; DROP TABLE").is_err());
+    }
+
+    #[test]
+    fn test_compare_fallback() {
+        let collator = NameCollator::new(None);
+        let mut names = vec!["bob", "Alice", "carol", "Bea"];
+        names.sort_by(|a, b| collator.compare(a, b));
+        assert_eq!(names, ["Alice", "Bea", "bob", "carol"]);
+    }
+
+    #[cfg(feature = "icu-collation")]
+    #[test]
+    fn test_compare_locale() {
+        let collator = NameCollator::new(Some("de-DE"));
+        let mut names = vec!["Zoe", "Özil", "Otto", "anna"];
+        names.sort_by(|a, b| collator.compare(a, b));
+        assert_eq!(names, ["anna", "Otto", "Özil", "Zoe"]);
+
+        // Swedish sorts "Ö" after "Z".
+        let collator = NameCollator::new(Some("sv-SE"));
+        names.sort_by(|a, b| collator.compare(a, b));
+        assert_eq!(names, ["anna", "Otto", "Zoe", "Özil"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sql_collation() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert_eq!(sql_collation(&t).await?, "NOCASE");
+
+        assert!(t.set_config(Config::Locale, Some("x'y")).await.is_err());
+        t.set_config(Config::Locale, Some("de-DE")).await?;
+        let collation = sql_collation(&t).await?;
+        if cfg!(feature = "icu-collation") {
+            assert_eq!(collation, "\"icu_de-DE\"");
+        } else {
+            assert_eq!(collation, "NOCASE");
+        }
+
+        let names: Vec<String> = t
+            .sql
+            .query_map(
+                &format!(
+                    "SELECT name FROM (SELECT 'Zoe' AS name UNION SELECT 'anna' UNION SELECT 'Otto')
+                     ORDER BY name COLLATE {collation}"
+                ),
+                (),
+                |row| row.get(0),
+                |rows| Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?),
+            )
+            .await?;
+        assert_eq!(names, ["anna", "Otto", "Zoe"]);
+        Ok(())
+    }
+}
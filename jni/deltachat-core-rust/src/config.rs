@@ -13,6 +13,9 @@
 use tokio::fs;
 
 use crate::blob::BlobObject;
+use crate::chatlist_events;
+use crate::collation;
+use crate::config_audit::{self, ConfigOrigin};
 use crate::constants;
 use crate::context::Context;
 use crate::events::EventType;
@@ -20,6 +23,7 @@
 use crate::mimefactory::RECOMMENDED_FILE_SIZE;
 use crate::provider::{get_provider_by_id, Provider};
 use crate::sync::{self, Sync::*, SyncData};
+use crate::timezone;
 use crate::tools::get_abs_path;
 
 /// The available configuration keys.
@@ -155,6 +159,28 @@ pub enum Config {
     #[strum(props(default = "1"))]
     MdnsEnabled,
 
+    /// Delay in seconds for which read receipts are collected before sending,
+    /// so that read receipts for several messages are sent as a single message.
+    ///
+    /// 0 sends read receipts at once.
+    #[strum(props(default = "0"))]
+    MdnsBatchDelay,
+
+    /// If set to "1", optional metadata is stripped from outgoing messages
+    /// to reduce the fingerprinting surface:
+    /// the `Date` header is rounded down to minutes
+    /// and names of images, videos and voice messages do not contain the time of sending.
+    #[strum(props(default = "0"))]
+    MinimalHeaders,
+
+    /// Outbound rate limits per recipient domain
+    /// as whitespace-separated `domain=N` entries,
+    /// where `N` is the number of messages per minute,
+    /// e.g. `gmail.com=20 googlemail.com=20`.
+    ///
+    /// Messages to other domains are not limited.
+    SendDomainLimits,
+
     /// True if "Sent" folder should be watched for changes.
     #[strum(props(default = "0"))]
     SentboxWatch,
@@ -179,6 +205,53 @@ pub enum Config {
     #[strum(props(default = "0"))] // also change MediaQuality.default() on changes
     MediaQuality,
 
+    /// JPEG quality used when recoding images, from 1 to 100.
+    #[strum(props(default = "75"))]
+    JpegQuality,
+
+    /// Maximum width and height of images scaled down with [`MediaQuality::Balanced`].
+    ///
+    /// [`MediaQuality::Balanced`]: crate::constants::MediaQuality::Balanced
+    #[strum(props(default = "1280"))] // also change constants::BALANCED_IMAGE_SIZE on changes
+    BalancedImageSize,
+
+    /// Maximum size in bytes of images sent without recoding with [`MediaQuality::Balanced`].
+    ///
+    /// [`MediaQuality::Balanced`]: crate::constants::MediaQuality::Balanced
+    #[strum(props(default = "500000"))]
+    // also change constants::BALANCED_IMAGE_BYTES on changes
+    BalancedImageBytes,
+
+    /// Maximum width and height of images scaled down with [`MediaQuality::Worse`].
+    ///
+    /// [`MediaQuality::Worse`]: crate::constants::MediaQuality::Worse
+    #[strum(props(default = "640"))] // also change constants::WORSE_IMAGE_SIZE on changes
+    WorseImageSize,
+
+    /// Maximum size in bytes of images sent without recoding with [`MediaQuality::Worse`].
+    ///
+    /// [`MediaQuality::Worse`]: crate::constants::MediaQuality::Worse
+    #[strum(props(default = "130000"))] // also change constants::WORSE_IMAGE_BYTES on changes
+    WorseImageBytes,
+
+    /// Maximum number of pixels of images decoded for recoding, 0 for no limit.
+    ///
+    /// Larger images are sent as files instead of being recoded
+    /// to protect against decompression bombs.
+    #[strum(props(default = "100000000"))]
+    MaxImagePixels,
+
+    /// Maximum number of bytes allocated for decoding an image, 0 for no limit.
+    #[strum(props(default = "536870912"))]
+    MaxImageDecodedBytes,
+
+    /// Whether to remove metadata such as the location, the recording device or the author
+    /// from videos, audio files and PDF documents before sending them.
+    ///
+    /// Metadata of images is always removed when they are recoded.
+    #[strum(props(default = "1"))]
+    StripAttachmentMetadata,
+
     /// If set to "1", on the first time `start_io()` is called after configuring,
     /// the newest existing messages are fetched.
     /// Existing recipients are added to the contact database regardless of this setting.
@@ -309,6 +382,10 @@ pub enum Config {
     /// ID of the configured provider from the provider database.
     ConfiguredProvider,
 
+    /// Features of the server as a JSON object,
+    /// see [`Context::get_server_capabilities`].
+    ConfiguredServerCapabilities,
+
     /// True if account is configured.
     Configured,
 
@@ -344,6 +421,21 @@ pub enum Config {
     /// True if it is a bot account.
     Bot,
 
+    /// Accept contact requests from contacts
+    /// who are members of a protected group together with us.
+    #[strum(props(default = "0"))]
+    AutoAcceptVerifiedGroupMembers,
+
+    /// Space- or comma-separated list of domains.
+    /// Contact requests from senders with an address on one of these domains
+    /// are accepted automatically
+    /// if the message is signed or DKIM passed according to the provider.
+    AutoAcceptDomains,
+
+    /// Accept contact requests automatically if they are started by a SecureJoin handshake.
+    #[strum(props(default = "0"))]
+    AutoAcceptSecurejoin,
+
     /// True when to skip initial start messages in groups.
     #[strum(props(default = "0"))]
     SkipStartMessages,
@@ -361,6 +453,106 @@ pub enum Config {
     /// address to webrtc instance to use for videochats
     WebrtcInstance,
 
+    /// Number of seconds during which deleted messages and chats
+    /// can be restored with `undo_last_deletion()`.
+    ///
+    /// 0 means that deletions are applied immediately.
+    #[strum(props(default = "30"))]
+    DeletionUndoWindow,
+
+    /// Number of seconds outgoing messages are kept back before they are submitted,
+    /// sending can be cancelled with `cancel_send()` meanwhile.
+    ///
+    /// 0 means that messages are submitted immediately.
+    #[strum(props(default = "0"))]
+    SendDelay,
+
+    /// Number of members above which membership changes in groups
+    /// are summarized in a daily digest instead of one info message each,
+    /// see [`crate::member_digest`].
+    ///
+    /// 0 means that every membership change is shown.
+    #[strum(props(default = "0"))]
+    MemberDigestThreshold,
+
+    /// Address reported messages are sent to if the chat has no moderators,
+    /// see [`crate::report`].
+    ///
+    /// If unset, reports are sent to the admin contact of chatmail relays.
+    AbuseAddress,
+
+    /// IANA name of the timezone used for day markers, e.g. `Europe/Berlin`,
+    /// see [`crate::timezone`].
+    ///
+    /// If unset, the timezone of the device is used.
+    Timezone,
+
+    /// BCP 47 language tag used for sorting contact and chat names, e.g. `de-DE`,
+    /// see [`crate::collation`].
+    ///
+    /// If unset, names are compared case-insensitively.
+    Locale,
+
+    /// Legal hold. If set, all automatic deletion of messages is suspended,
+    /// including ephemeral messages, `DeleteDeviceAfter`, `DeleteServerAfter`
+    /// and per-chat retention policies.
+    ///
+    /// Messages are deleted as usual once the legal hold is lifted.
+    #[strum(props(default = "0"))]
+    LegalHold,
+
+    /// Number of days without activity after which chats are archived by housekeeping.
+    ///
+    /// Pinned chats, verified 1:1 chats, Saved Messages and the device chat
+    /// are never archived automatically.
+    /// 0 disables automatic archival.
+    #[strum(props(default = "0"))]
+    AutoArchiveDays,
+
+    /// Newline-separated keywords to watch incoming messages for,
+    /// see [`crate::watch`].
+    WatchKeywords,
+
+    /// Newline-separated rules assigning incoming classic emails,
+    /// see [`crate::email_rules`].
+    EmailRules,
+
+    /// URL of the WebDAV collection or S3 bucket backups are uploaded to,
+    /// see [`crate::remote_backup`].
+    ///
+    /// Remote backups are disabled if unset.
+    RemoteBackupUrl,
+
+    /// Kind of the remote backup target, `webdav` or `s3`.
+    #[strum(props(default = "webdav"))]
+    RemoteBackupKind,
+
+    /// WebDAV user or S3 access key ID.
+    RemoteBackupUser,
+
+    /// WebDAV password or S3 secret access key.
+    RemoteBackupPassword,
+
+    /// Region of the S3 bucket.
+    #[strum(props(default = "us-east-1"))]
+    RemoteBackupRegion,
+
+    /// Passphrase remote backups are encrypted with.
+    RemoteBackupPassphrase,
+
+    /// Number of hours between automated remote backups.
+    ///
+    /// 0 means that remote backups are only uploaded on demand.
+    #[strum(props(default = "24"))]
+    RemoteBackupInterval,
+
+    /// Number of remote backups to keep.
+    #[strum(props(default = "7"))]
+    RemoteBackupKeep,
+
+    /// Timestamp of the last successful remote backup.
+    LastRemoteBackup,
+
     /// Timestamp of the last time housekeeping was run
     LastHousekeeping,
 
@@ -402,6 +594,14 @@ pub enum Config {
     /// and older Delta Chat versions (core version <= v1.149.0).
     ProtectAutocrypt,
 
+    /// If set to "1", the subject of encrypted messages is only sent in the encrypted part
+    /// as a protected header, the unencrypted header shows `[...]` instead.
+    ///
+    /// If set to "0", the subject is additionally sent unencrypted
+    /// so that email clients not supporting protected headers show it.
+    #[strum(props(default = "1"))]
+    ProtectSubject,
+
     /// Let the core save all events to the database.
     /// This value is used internally to remember the MsgId of the logging xdc
     #[strum(props(default = "0"))]
@@ -417,6 +617,18 @@ pub enum Config {
     #[strum(props(default = "172800"))]
     GossipPeriod,
 
+    /// Groups with more members than this are large groups, 0 disables the limit.
+    ///
+    /// In large groups the own avatar is sent to new members in batches
+    /// and Autocrypt keys are gossiped less often,
+    /// so that outgoing messages are not inflated by profile data and keys of all members.
+    #[strum(props(default = "50"))]
+    LargeGroupSize,
+
+    /// Whether to apply avatar updates of contacts received in large groups.
+    #[strum(props(default = "1"))]
+    LargeGroupAvatars,
+
     /// Feature flag for verified 1:1 chats; the UI should set it
     /// to 1 if it supports verified 1:1 chats.
     /// Regardless of this setting, `chat.is_protected()` returns true while the key is verified,
@@ -447,6 +659,13 @@ pub enum Config {
     /// If it has not changed, we do not store
     /// the device token again.
     DeviceToken,
+
+    /// Whether to defer starting IO until the chatlist is loaded for the first time.
+    ///
+    /// The scheduler, and with it housekeeping, is started after the first chatlist is loaded
+    /// or after a timeout, so the chatlist becomes available sooner on slow devices.
+    #[strum(props(default = "0"))]
+    LazyStart,
 }
 
 impl Config {
@@ -467,7 +686,24 @@ pub(crate) fn is_synced(&self) -> bool {
                 | Self::MvboxMove
                 | Self::ShowEmails
                 | Self::Selfavatar
-                | Self::Selfstatus,
+                | Self::Selfstatus
+                | Self::WatchKeywords
+                | Self::EmailRules,
+        )
+    }
+
+    /// Whether the config value is a secret, such as a password,
+    /// which must not be kept anywhere else.
+    pub(crate) fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            Self::MailPw
+                | Self::SendPw
+                | Self::Socks5Password
+                | Self::ConfiguredMailPw
+                | Self::ConfiguredSendPw
+                | Self::RemoteBackupPassword
+                | Self::ProxyUrl
         )
     }
 
@@ -629,7 +865,11 @@ pub(crate) async fn should_send_mdns(&self) -> Result<bool> {
     ///
     /// `None` means never delete the message, `Some(0)` means delete
     /// at once, `Some(x)` means delete after `x` seconds.
+    /// Always `None` while `Config::LegalHold` is set.
     pub async fn get_config_delete_server_after(&self) -> Result<Option<i64>> {
+        if self.get_config_bool(Config::LegalHold).await? {
+            return Ok(None);
+        }
         let val = match self
             .get_config_parsed::<i64>(Config::DeleteServerAfter)
             .await?
@@ -657,7 +897,11 @@ pub async fn get_configured_provider(&self) -> Result<Option<&'static Provider>>
     ///
     /// `None` means never delete the message, `Some(x)` means delete
     /// after `x` seconds.
+    /// Always `None` while `Config::LegalHold` is set.
     pub async fn get_config_delete_device_after(&self) -> Result<Option<i64>> {
+        if self.get_config_bool(Config::LegalHold).await? {
+            return Ok(None);
+        }
         match self.get_config_int(Config::DeleteDeviceAfter).await? {
             0 => Ok(None),
             x => Ok(Some(i64::from(x))),
@@ -676,7 +920,10 @@ pub(crate) async fn sync_config(&self, key: &Config, value: &str) -> Result<()>
             _ => Some(value),
         };
         match key.is_synced() {
-            true => self.set_config_ex(Nosync, *key, value).await,
+            true => {
+                self.set_config_audited(ConfigOrigin::Sync, Nosync, *key, value)
+                    .await
+            }
             false => Ok(()),
         }
     }
@@ -696,15 +943,54 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
             | Config::SaveMimeHeaders
             | Config::Configured
             | Config::Bot
+            | Config::AutoAcceptVerifiedGroupMembers
+            | Config::AutoAcceptSecurejoin
             | Config::NotifyAboutWrongPw
             | Config::SyncMsgs
             | Config::SignUnencrypted
-            | Config::DisableIdle => {
+            | Config::LegalHold
+            | Config::DisableIdle
+            | Config::LazyStart
+            | Config::StripAttachmentMetadata => {
                 ensure!(
                     matches!(value, None | Some("0") | Some("1")),
                     "Boolean value must be either 0 or 1"
                 );
             }
+            Config::JpegQuality => {
+                ensure!(
+                    value.map_or(true, |v| matches!(v.parse::<u8>(), Ok(1..=100))),
+                    "JPEG quality must be between 1 and 100"
+                );
+            }
+            Config::BalancedImageSize | Config::WorseImageSize => {
+                ensure!(
+                    value.map_or(true, |v| v
+                        .parse::<u32>()
+                        .is_ok_and(|size| size >= constants::MIN_IMAGE_SIZE)),
+                    "Image size must be a number of at least {}",
+                    constants::MIN_IMAGE_SIZE
+                );
+            }
+            Config::BalancedImageBytes | Config::WorseImageBytes => {
+                ensure!(
+                    value.map_or(true, |v| v
+                        .parse::<usize>()
+                        .is_ok_and(|bytes| bytes >= constants::MIN_IMAGE_BYTES)),
+                    "Image byte budget must be a number of at least {}",
+                    constants::MIN_IMAGE_BYTES
+                );
+            }
+            Config::Timezone => {
+                if let Some(name) = value.filter(|name| !name.is_empty()) {
+                    timezone::parse_timezone(name)?;
+                }
+            }
+            Config::Locale => {
+                if let Some(tag) = value.filter(|tag| !tag.is_empty()) {
+                    collation::parse_locale(tag)?;
+                }
+            }
             _ => (),
         }
         Ok(())
@@ -714,19 +1000,48 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
     /// This may restart the IO scheduler. If `None` is passed as a value the value is cleared and
     /// set to the default if there is one.
     pub async fn set_config(&self, key: Config, value: Option<&str>) -> Result<()> {
+        self.set_config_from(ConfigOrigin::User, key, value).await
+    }
+
+    /// Like [`Context::set_config`], but records `origin` in the audit log
+    /// instead of assuming that the user made the change.
+    pub(crate) async fn set_config_from(
+        &self,
+        origin: ConfigOrigin,
+        key: Config,
+        value: Option<&str>,
+    ) -> Result<()> {
         Self::check_config(key, value)?;
 
         let _pause = match key.needs_io_restart() {
             true => self.scheduler.pause(self.clone()).await?,
             _ => Default::default(),
         };
-        self.set_config_internal(key, value).await?;
+        self.set_config_audited(origin, Sync, key, value).await?;
         if key == Config::SentboxWatch {
             self.last_full_folder_scan.lock().await.take();
         }
         Ok(())
     }
 
+    /// Sets the config key as [`Context::set_config_ex`] does
+    /// and records the change in the audit log.
+    async fn set_config_audited(
+        &self,
+        origin: ConfigOrigin,
+        sync: sync::Sync,
+        key: Config,
+        value: Option<&str>,
+    ) -> Result<()> {
+        let old_value = self.sql.get_raw_config(key.as_ref()).await?;
+        self.set_config_ex(sync, key, value).await?;
+        config_audit::record(self, origin, key, old_value)
+            .await
+            .log_err(self)
+            .ok();
+        Ok(())
+    }
+
     pub(crate) async fn set_config_internal(&self, key: Config, value: Option<&str>) -> Result<()> {
         self.set_config_ex(Sync, key, value).await
     }
@@ -769,7 +1084,7 @@ pub(crate) async fn set_config_ex(
                 }
                 self.emit_event(EventType::SelfavatarChanged);
             }
-            Config::DeleteDeviceAfter => {
+            Config::DeleteDeviceAfter | Config::LegalHold => {
                 let ret = self.sql.set_raw_config(key.as_ref(), value).await;
                 // Interrupt ephemeral loop to delete old messages immediately.
                 self.scheduler.interrupt_ephemeral_task().await;
@@ -793,6 +1108,18 @@ pub(crate) async fn set_config_ex(
                     .set_raw_config(constants::DC_FOLDERS_CONFIGURED_KEY, None)
                     .await?;
             }
+            Config::Timezone => {
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                // Day markers and chatlist dates need to be recomputed.
+                self.emit_msgs_changed_without_ids();
+                chatlist_events::emit_chatlist_changed(self);
+            }
+            Config::Locale => {
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                // Contact and chat lists sorted by name need to be reloaded.
+                self.emit_event(EventType::ContactsChanged(None));
+                chatlist_events::emit_chatlist_changed(self);
+            }
             _ => {
                 self.sql.set_raw_config(key.as_ref(), value).await?;
             }
@@ -1001,6 +1328,51 @@ async fn test_media_quality_config_option() {
         assert_eq!(media_quality, constants::MediaQuality::Worse);
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_image_recoding_config() -> Result<()> {
+        let t = TestContext::new().await;
+        assert_eq!(t.get_config_int(Config::JpegQuality).await?, 75);
+        assert_eq!(
+            t.get_config_parsed::<u32>(Config::BalancedImageSize)
+                .await?,
+            Some(constants::BALANCED_IMAGE_SIZE)
+        );
+        assert_eq!(
+            t.get_config_parsed::<usize>(Config::BalancedImageBytes)
+                .await?,
+            Some(constants::BALANCED_IMAGE_BYTES)
+        );
+        assert_eq!(
+            t.get_config_parsed::<u32>(Config::WorseImageSize).await?,
+            Some(constants::WORSE_IMAGE_SIZE)
+        );
+        assert_eq!(
+            t.get_config_parsed::<usize>(Config::WorseImageBytes)
+                .await?,
+            Some(constants::WORSE_IMAGE_BYTES)
+        );
+
+        t.set_config(Config::JpegQuality, Some("90")).await?;
+        assert_eq!(t.get_config_int(Config::JpegQuality).await?, 90);
+        assert!(t.set_config(Config::JpegQuality, Some("0")).await.is_err());
+        assert!(t
+            .set_config(Config::JpegQuality, Some("101"))
+            .await
+            .is_err());
+
+        t.set_config(Config::BalancedImageSize, Some("2560"))
+            .await?;
+        assert!(t
+            .set_config(Config::BalancedImageSize, Some("10"))
+            .await
+            .is_err());
+        assert!(t
+            .set_config(Config::WorseImageBytes, Some("many"))
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_ui_config() -> Result<()> {
         let t = TestContext::new().await;
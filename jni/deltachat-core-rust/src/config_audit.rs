@@ -0,0 +1,265 @@
+//! # Audit log of configuration changes.
+//!
+//! Every change of a config key made by the user, received from another device
+//! or made by scanning a QR code is recorded together with its origin,
+//! so that "my settings changed by themselves" reports can be diagnosed
+//! with [`Context::get_config_changes`] and undone with [`Context::rollback_config_change`].
+//!
+//! Only hashes of the values are exposed.
+//! The previous value is stored to allow the rollback,
+//! except for secrets such as passwords which cannot be rolled back.
+//! Not even hashes of secrets are stored as they would allow to verify guesses.
+//! Changes made internally by the core, e.g. during configuration, are not recorded.
+
+use std::str::FromStr;
+
+use anyhow::{ensure, Context as _, Result};
+use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::tools::time;
+
+/// Number of changes kept in the audit log.
+const MAX_CHANGES: u32 = 200;
+
+/// Origin of a config change.
+#[derive(
+    Debug, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+#[repr(u32)]
+pub enum ConfigOrigin {
+    /// The change was made using the API, usually by the user.
+    User = 1,
+
+    /// The change was received from another device in a sync message.
+    Sync = 2,
+
+    /// The change was made by scanning a QR code, e.g. a login or proxy QR code.
+    Qr = 3,
+}
+
+/// Recorded change of a config key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigChange {
+    /// ID of the change, used to roll it back.
+    pub id: u32,
+
+    /// Changed config key.
+    pub key: String,
+
+    /// Hex-encoded SHA-256 hash of the previous value,
+    /// `None` if the key was unset or is a secret such as a password.
+    pub old_value_hash: Option<String>,
+
+    /// Hex-encoded SHA-256 hash of the new value,
+    /// `None` if the key was unset or is a secret such as a password.
+    pub new_value_hash: Option<String>,
+
+    /// Time of the change, in seconds since the epoch.
+    pub timestamp: i64,
+
+    /// Origin of the change.
+    pub origin: ConfigOrigin,
+
+    /// True if the change can be rolled back with [`Context::rollback_config_change`].
+    pub can_rollback: bool,
+}
+
+fn hash_value(value: Option<&str>) -> Option<String> {
+    value.map(|value| hex::encode(Sha256::digest(value.as_bytes())))
+}
+
+/// Returns true if the previous value of `key` may be stored to roll back a change.
+fn is_rollback_supported(key: Config) -> bool {
+    // The avatar is a blob which may be removed by the housekeeping.
+    !key.is_secret() && key != Config::Selfavatar
+}
+
+/// Records a change of `key` from `old_value` to its current value.
+///
+/// Nothing is recorded if the value did not change.
+pub(crate) async fn record(
+    context: &Context,
+    origin: ConfigOrigin,
+    key: Config,
+    old_value: Option<String>,
+) -> Result<()> {
+    let new_value = context.sql.get_raw_config(key.as_ref()).await?;
+    if new_value == old_value {
+        return Ok(());
+    }
+    let can_rollback = is_rollback_supported(key);
+    let (old_hash, new_hash) = if key.is_secret() {
+        (None, None)
+    } else {
+        (
+            hash_value(old_value.as_deref()),
+            hash_value(new_value.as_deref()),
+        )
+    };
+    context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute(
+                "INSERT INTO config_changes
+                 (keyname, old_hash, new_hash, old_value, can_rollback, timestamp, origin)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+                (
+                    key.as_ref(),
+                    old_hash,
+                    new_hash,
+                    old_value.filter(|_| can_rollback),
+                    can_rollback,
+                    time(),
+                    origin as u32,
+                ),
+            )?;
+            transaction.execute(
+                "DELETE FROM config_changes WHERE id<=(SELECT MAX(id) FROM config_changes)-?",
+                (MAX_CHANGES,),
+            )?;
+            Ok(())
+        })
+        .await?;
+    info!(context, "Config key {key} changed, origin: {origin}.");
+    Ok(())
+}
+
+impl Context {
+    /// Returns the recorded config changes, newest first.
+    pub async fn get_config_changes(&self) -> Result<Vec<ConfigChange>> {
+        self.sql
+            .query_map(
+                "SELECT id, keyname, old_hash, new_hash, timestamp, origin, can_rollback
+                 FROM config_changes ORDER BY id DESC",
+                (),
+                |row| {
+                    let origin: u32 = row.get(5)?;
+                    Ok(ConfigChange {
+                        id: row.get(0)?,
+                        key: row.get(1)?,
+                        old_value_hash: row.get(2)?,
+                        new_value_hash: row.get(3)?,
+                        timestamp: row.get(4)?,
+                        origin: ConfigOrigin::from_u32(origin).unwrap_or(ConfigOrigin::User),
+                        can_rollback: row.get(6)?,
+                    })
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await
+    }
+
+    /// Restores the value a config key had before the change `id`.
+    ///
+    /// Fails if the key was changed again since,
+    /// so that later changes are not lost silently,
+    /// or if the change cannot be rolled back, see [`ConfigChange::can_rollback`].
+    /// The rollback is recorded as a change made by the user.
+    pub async fn rollback_config_change(&self, id: u32) -> Result<()> {
+        let (key, new_hash, old_value, can_rollback) = self
+            .sql
+            .query_row_optional(
+                "SELECT keyname, new_hash, old_value, can_rollback
+                 FROM config_changes WHERE id=?",
+                (id,),
+                |row| {
+                    let key: String = row.get(0)?;
+                    let new_hash: Option<String> = row.get(1)?;
+                    let old_value: Option<String> = row.get(2)?;
+                    let can_rollback: bool = row.get(3)?;
+                    Ok((key, new_hash, old_value, can_rollback))
+                },
+            )
+            .await?
+            .with_context(|| format!("Config change {id} not found"))?;
+        let key = Config::from_str(&key).with_context(|| format!("Unknown config key {key}"))?;
+        ensure!(can_rollback, "Changes of {key} cannot be rolled back");
+        let current = self.sql.get_raw_config(key.as_ref()).await?;
+        ensure!(
+            hash_value(current.as_deref()) == new_hash,
+            "{key} was changed again after change {id}"
+        );
+        self.set_config(key, old_value.as_deref()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::set_config_from_qr;
+    use crate::test_utils::{sync, TestContext};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_config_audit() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(Config::Displayname, Some("Alice")).await?;
+        t.set_config(Config::Displayname, Some("Alice")).await?;
+        t.set_config(Config::MailPw, Some("secret")).await?;
+        set_config_from_qr(&t, "https://t.me/socks?server=1.2.3.4&port=1080").await?;
+
+        let changes = t.get_config_changes().await?;
+        let keys: Vec<_> = changes
+            .iter()
+            .map(|change| (change.key.as_str(), change.origin))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("proxy_enabled", ConfigOrigin::Qr),
+                ("proxy_url", ConfigOrigin::Qr),
+                ("mail_pw", ConfigOrigin::User),
+                ("displayname", ConfigOrigin::User),
+            ]
+        );
+        let displayname = &changes[3];
+        assert_eq!(displayname.old_value_hash, None);
+        assert_eq!(displayname.new_value_hash, hash_value(Some("Alice")));
+        assert!(displayname.can_rollback);
+        assert!(!changes[2].can_rollback);
+        // Secrets are not hashed.
+        for change in &changes[1..3] {
+            assert_eq!(change.old_value_hash, None);
+            assert_eq!(change.new_value_hash, None);
+        }
+
+        assert!(t.rollback_config_change(changes[2].id).await.is_err());
+        t.rollback_config_change(changes[0].id).await?;
+        assert!(!t.get_config_bool(Config::ProxyEnabled).await?);
+        // Rolling back again fails because the value changed.
+        assert!(t.rollback_config_change(changes[0].id).await.is_err());
+
+        t.rollback_config_change(displayname.id).await?;
+        assert_eq!(t.get_config(Config::Displayname).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_config_audit_sync() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+
+        alice0.set_config(Config::Selfstatus, Some("Busy")).await?;
+        sync(alice0, alice1).await;
+
+        let change = alice1
+            .get_config_changes()
+            .await?
+            .into_iter()
+            .find(|change| change.key == "selfstatus")
+            .unwrap();
+        assert_eq!(change.origin, ConfigOrigin::Sync);
+        assert_eq!(change.new_value_hash, hash_value(Some("Busy")));
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@
 mod auto_outlook;
 pub(crate) mod server_params;
 
-use anyhow::{bail, ensure, format_err, Context as _, Result};
+use anyhow::{ensure, format_err, Context as _, Result};
 use auto_mozilla::moz_autoconfigure;
 use auto_outlook::outlk_autodiscover;
 use deltachat_contact_tools::EmailAddress;
@@ -23,18 +23,21 @@
 use server_params::{expand_param_vector, ServerParams};
 use tokio::task;
 
+use crate::activity::{self, ActivityKind};
 use crate::config::{self, Config};
 use crate::constants::NON_ALPHANUMERIC_WITHOUT_DOT;
 use crate::context::Context;
+use crate::error::ClassifiedError;
 use crate::imap::Imap;
 use crate::log::LogExt;
 use crate::login_param::{
-    ConfiguredCertificateChecks, ConfiguredLoginParam, ConfiguredServerLoginParam,
-    ConnectionCandidate, EnteredCertificateChecks, EnteredLoginParam,
+    forget_preferred_server_login_param, ConfiguredCertificateChecks, ConfiguredLoginParam,
+    ConfiguredServerLoginParam, ConnectionCandidate, EnteredCertificateChecks, EnteredLoginParam,
 };
 use crate::message::Message;
 use crate::oauth2::get_oauth2_addr;
 use crate::provider::{Protocol, Socket, UsernamePattern};
+use crate::server_capabilities;
 use crate::smtp::Smtp;
 use crate::sync::Sync::*;
 use crate::tools::time;
@@ -374,6 +377,11 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Configure
 
     progress!(ctx, 550);
 
+    // Probe all candidates,
+    // the ones that work are preferred afterwards.
+    forget_preferred_server_login_param(ctx, "smtp").await?;
+    forget_preferred_server_login_param(ctx, "imap").await?;
+
     // Spawn SMTP configuration task
     // to try SMTP while connecting to IMAP.
     let context_smtp = ctx.clone();
@@ -415,7 +423,10 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Configure
     let configuring = true;
     let mut imap_session = match imap.connect(ctx, configuring).await {
         Ok(session) => session,
-        Err(err) => bail!("{}", nicer_configuration_error(ctx, err.to_string()).await),
+        Err(err) => {
+            let message = nicer_configuration_error(ctx, err.to_string()).await;
+            return Err(ClassifiedError::with_message(&err, message));
+        }
     };
 
     progress!(ctx, 850);
@@ -448,6 +459,13 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Configure
         ctx.set_config(Config::E2eeEnabled, Some("1")).await?;
     }
 
+    // The account may have been moved to another server.
+    *ctx.metadata.write().await = None;
+    if let Err(err) = imap_session.fetch_metadata(ctx).await {
+        warn!(ctx, "Failed to fetch metadata: {err:#}.");
+    }
+    server_capabilities::update(ctx, &imap_session).await?;
+
     let create_mvbox = !is_chatmail;
     imap.configure_folders(ctx, &mut imap_session, create_mvbox)
         .await?;
@@ -472,6 +490,10 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Configure
     configured_param.save_as_configured_params(ctx).await?;
     ctx.set_config_internal(Config::ConfiguredTimestamp, Some(&time().to_string()))
         .await?;
+    activity::record(ctx, ActivityKind::Configured, &param.addr)
+        .await
+        .log_err(ctx)
+        .ok();
 
     progress!(ctx, 920);
 
@@ -104,13 +104,18 @@ pub enum VideochatType {
 pub const DC_GCL_NO_SPECIALS: usize = 0x02;
 pub const DC_GCL_ADD_ALLDONE_HINT: usize = 0x04;
 pub const DC_GCL_FOR_FORWARDING: usize = 0x08;
+pub const DC_GCL_SORT_BY_NAME: usize = 0x10;
 
 pub const DC_GCL_VERIFIED_ONLY: u32 = 0x01;
 pub const DC_GCL_ADD_SELF: u32 = 0x02;
+pub const DC_GCL_WITH_CHAT_ONLY: u32 = 0x04;
 
 // unchanged user avatars are resent to the recipients every some days
 pub(crate) const DC_RESEND_USER_AVATAR_DAYS: i64 = 14;
 
+// in large groups, the user avatar is sent to new members at most once in this number of seconds
+pub(crate) const LARGE_GROUP_AVATAR_INTERVAL: i64 = 24 * 60 * 60;
+
 // warn about an outdated app after a given number of days.
 // as we use the "provider-db generation date" as reference (that might not be updated very often)
 // and as not all system get speedy updates,
@@ -119,6 +124,8 @@ pub enum VideochatType {
 
 /// messages that should be deleted get this chat_id; the messages are deleted from the working thread later then. This is also needed as rfc724_mid should be preset as long as the message is not deleted on the server (otherwise it is downloaded again)
 pub const DC_CHAT_ID_TRASH: ChatId = ChatId::new(3);
+/// messages deleted within the undo window get this chat_id until they are purged or restored
+pub(crate) const DC_CHAT_ID_PENDING_DELETION: ChatId = ChatId::new(4);
 /// only an indicator in a chatlist
 pub const DC_CHAT_ID_ARCHIVED_LINK: ChatId = ChatId::new(6);
 /// only an indicator in a chatlist
@@ -200,18 +207,29 @@ pub enum Chattype {
 /// How many existing messages shall be fetched after configuration.
 pub(crate) const DC_FETCH_EXISTING_MSGS_COUNT: i64 = 100;
 
-// max. weight of images to send w/o recoding
+// max. weight of images to send w/o recoding,
+// defaults of `Config::BalancedImageBytes` and `Config::WorseImageBytes`
 pub const BALANCED_IMAGE_BYTES: usize = 500_000;
 pub const WORSE_IMAGE_BYTES: usize = 130_000;
 
+// max. weight of images sent in original quality, larger images are recoded anyway.
+// Most providers accept messages of 25 MB, Base64 encoding adds a third.
+pub(crate) const MAX_ORIGINAL_IMAGE_BYTES: u64 = 18_000_000;
+
 // max. width/height of an avatar
 pub(crate) const BALANCED_AVATAR_SIZE: u32 = 256;
 pub(crate) const WORSE_AVATAR_SIZE: u32 = 128;
 
-// max. width/height of images scaled down because of being too huge
+// max. width/height of images scaled down because of being too huge,
+// defaults of `Config::BalancedImageSize` and `Config::WorseImageSize`
 pub const BALANCED_IMAGE_SIZE: u32 = 1280;
 pub const WORSE_IMAGE_SIZE: u32 = 640;
 
+// min. values of the configurable image sizes and byte budgets,
+// smaller images are hardly recognizable.
+pub(crate) const MIN_IMAGE_SIZE: u32 = 160;
+pub(crate) const MIN_IMAGE_BYTES: usize = 10_000;
+
 // Key for the folder configuration version (see below).
 pub(crate) const DC_FOLDERS_CONFIGURED_KEY: &str = "folders_configured";
 // this value can be increased if the folder configuration is changed and must be redone on next program start
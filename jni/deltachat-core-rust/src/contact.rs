@@ -23,6 +23,7 @@
 use crate::aheader::{Aheader, EncryptPreference};
 use crate::blob::BlobObject;
 use crate::chat::{ChatId, ChatIdBlocked, ProtectionStatus};
+use crate::collation;
 use crate::color::str_to_color;
 use crate::config::Config;
 use crate::constants::{Blocked, Chattype, DC_GCL_ADD_SELF, DC_GCL_VERIFIED_ONLY};
@@ -169,7 +170,7 @@ pub async fn reset_encryption(self, context: &Context) -> Result<()> {
         let addr = self.addr(context).await?;
         if let Some(mut peerstate) = Peerstate::from_addr(context, &addr).await? {
             peerstate.degrade_encryption(now);
-            peerstate.save_to_db(&context.sql).await?;
+            peerstate.save_to_db_ex(context, None).await?;
         }
 
         // Reset 1:1 chat protection.
@@ -233,7 +234,8 @@ pub async fn make_vcard(context: &Context, contacts: &[ContactId]) -> Result<Str
                 .and_then(|peerstate| peerstate.take_key(false)),
         };
         let key = key.map(|k| k.to_base64());
-        let profile_image = match c.get_profile_image(context).await? {
+        // Local avatar overrides must not be shared.
+        let profile_image = match c.get_profile_image_ex(context, false).await? {
             None => None,
             Some(path) => tokio::fs::read(path)
                 .await
@@ -325,7 +327,7 @@ async fn import_vcard_contact(context: &Context, contact: &VcardContact) -> Resu
         } else {
             Peerstate::from_gossip(&aheader, timestamp)
         };
-        if let Err(e) = peerstate.save_to_db(&context.sql).await {
+        if let Err(e) = peerstate.save_to_db_ex(context, None).await {
             warn!(
                 context,
                 "import_vcard_contact: Could not save peerstate for {}: {e:#}.", contact.addr
@@ -515,6 +517,101 @@ pub fn is_known(self) -> bool {
     }
 }
 
+/// Entry of an address book passed to [`Contact::apply_address_book_delta`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressBookEntry {
+    /// Name as stored in the address book, normalized before use.
+    pub name: String,
+
+    /// Email address.
+    pub addr: String,
+}
+
+impl AddressBookEntry {
+    /// Parses entries from a multiline string in the format
+    /// `Name one\nAddress one\nName two\nAddress two`
+    /// as passed to [`Contact::add_address_book`].
+    pub fn parse_list(addr_book: &str) -> Vec<Self> {
+        split_address_book(addr_book)
+            .into_iter()
+            .map(|(name, addr)| Self {
+                name: name.to_string(),
+                addr: addr.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Sort order of [`Contact::get_page`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum ContactSortOrder {
+    /// Recently seen contacts first.
+    #[default]
+    LastSeen = 0,
+
+    /// Alphabetically by name, case-insensitive.
+    ///
+    /// Names are sorted according to [`Config::Locale`] if set.
+    ///
+    /// Nicknames set with [`Contact::set_local_override`] are not taken into account.
+    Name = 1,
+}
+
+/// Filter and page for [`Contact::get_page`] and [`Contact::get_count`].
+#[derive(Debug, Default, Clone)]
+pub struct ContactListFilter {
+    /// String to search for in names and addresses.
+    pub query: Option<String>,
+
+    /// Return only contacts with a verified key.
+    pub verified_only: bool,
+
+    /// Return only contacts with an unblocked 1:1 chat.
+    pub with_chat_only: bool,
+
+    /// Return only contacts tagged with this tag,
+    /// see [`ContactId::set_tags`](crate::contact::ContactId::set_tags).
+    pub tag: Option<String>,
+
+    /// Sort order.
+    pub sort: ContactSortOrder,
+
+    /// Number of contacts to skip.
+    pub offset: usize,
+
+    /// Maximum number of contacts to return, `None` for no limit.
+    pub limit: Option<usize>,
+}
+
+/// Lightweight contact list entry returned by [`Contact::get_page`].
+///
+/// Contains just what is needed to display a contact list item
+/// without loading the whole [`Contact`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactListEntry {
+    /// The contact ID.
+    pub id: ContactId,
+
+    /// Display name as returned by [`Contact::get_display_name`].
+    pub display_name: String,
+
+    /// E-Mail-Address of the contact.
+    pub addr: String,
+
+    /// Color as returned by [`Contact::get_color`].
+    pub color: u32,
+
+    /// Time when the contact was seen last time, Unix time in seconds.
+    pub last_seen: i64,
+
+    /// Whether we have a verified key for the contact.
+    ///
+    /// This is what [`ContactListFilter::verified_only`] filters by,
+    /// use [`Contact::is_verified`] to decide whether to show a green checkmark.
+    pub has_verified_key: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub(crate) enum Modifier {
     None,
@@ -908,9 +1005,9 @@ pub(crate) async fn add_or_lookup(
 
                         if let Some(chat_id) = chat_id {
                             let contact_id = ContactId::new(row_id);
-                            let (addr, name, authname) =
+                            let (addr, name, authname, param) =
                                 transaction.query_row(
-                                    "SELECT addr, name, authname
+                                    "SELECT addr, name, authname, param
                                      FROM contacts
                                      WHERE id=?",
                                      (contact_id,),
@@ -918,10 +1015,19 @@ pub(crate) async fn add_or_lookup(
                                     let addr: String = row.get(0)?;
                                     let name: String = row.get(1)?;
                                     let authname: String = row.get(2)?;
-                                    Ok((addr, name, authname))
+                                    let param: String = row.get(3)?;
+                                    Ok((addr, name, authname, param))
                                 })?;
-
-                            let chat_name = if !name.is_empty() {
+                            let local_name = param
+                                .parse::<Params>()
+                                .unwrap_or_default()
+                                .get(Param::LocalDisplayname)
+                                .map(|s| s.to_string())
+                                .unwrap_or_default();
+
+                            let chat_name = if !local_name.is_empty() {
+                                local_name
+                            } else if !name.is_empty() {
                                 name
                             } else if !authname.is_empty() {
                                 authname
@@ -1028,6 +1134,104 @@ pub async fn add_address_book(context: &Context, addr_book: &str) -> Result<usiz
         Ok(modify_cnt)
     }
 
+    /// Applies changes of an address book to the contacts.
+    ///
+    /// Unlike [`Contact::add_address_book`], only the changed entries need to be passed.
+    /// `source` tags the address book the entries come from, e.g. an account on the device,
+    /// so that it is tracked for every contact which address books list it.
+    ///
+    /// Contacts in `upserts` are added or get their name updated
+    /// unless the name was changed manually by the user.
+    /// Addresses in `deletions` are removed from the address book `source`.
+    /// A contact known only from address books is deleted
+    /// once it is removed from all of them.
+    /// If there are chats with the contact, only the name from the address book is removed.
+    ///
+    /// Returns the number of modified contacts.
+    pub async fn apply_address_book_delta(
+        context: &Context,
+        source: &str,
+        upserts: &[AddressBookEntry],
+        deletions: &[String],
+    ) -> Result<usize> {
+        ensure!(!source.is_empty(), "Address book source must not be empty");
+        let mut modify_cnt = 0;
+
+        for entry in upserts {
+            let (name, addr) = sanitize_name_and_addr(&entry.name, &entry.addr);
+            let addr = match ContactAddress::new(&addr) {
+                Ok(addr) => addr,
+                Err(err) => {
+                    warn!(context, "{:#}.", err);
+                    continue;
+                }
+            };
+            let contact_id =
+                match Contact::add_or_lookup(context, &name, &addr, Origin::AddressBook).await {
+                    Ok((contact_id, modified)) => {
+                        if modified != Modifier::None {
+                            modify_cnt += 1;
+                        }
+                        contact_id
+                    }
+                    Err(err) => {
+                        warn!(
+                            context,
+                            "Failed to add address {} from address book: {}", addr, err
+                        );
+                        continue;
+                    }
+                };
+            if contact_id.is_special() {
+                continue;
+            }
+            context
+                .sql
+                .execute(
+                    "INSERT OR IGNORE INTO address_book_sources (contact_id, source) VALUES (?, ?)",
+                    (contact_id, source),
+                )
+                .await?;
+        }
+
+        for addr in deletions {
+            let Some(contact_id) =
+                Contact::lookup_id_by_addr_ex(context, addr, Origin::Unknown, None).await?
+            else {
+                continue;
+            };
+            if contact_id.is_special() {
+                continue;
+            }
+            if remove_address_book_source(context, contact_id, source).await? {
+                modify_cnt += 1;
+            }
+        }
+
+        if modify_cnt > 0 {
+            context.emit_event(EventType::ContactsChanged(None));
+        }
+
+        Ok(modify_cnt)
+    }
+
+    /// Returns the tags of the address books listing the contact,
+    /// see [`Contact::apply_address_book_delta`].
+    pub async fn get_address_book_sources(
+        context: &Context,
+        contact_id: ContactId,
+    ) -> Result<Vec<String>> {
+        context
+            .sql
+            .query_map(
+                "SELECT source FROM address_book_sources WHERE contact_id=? ORDER BY source",
+                (contact_id,),
+                |row| row.get::<_, String>(0),
+                |sources| sources.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+            )
+            .await
+    }
+
     /// Returns known and unblocked contacts.
     ///
     /// To get information about a single contact, see get_contact().
@@ -1047,11 +1251,7 @@ pub async fn get_all(
         let mut ret = Vec::new();
         let flag_verified_only = (listflags & DC_GCL_VERIFIED_ONLY) != 0;
         let flag_add_self = (listflags & DC_GCL_ADD_SELF) != 0;
-        let minimal_origin = if context.get_config_bool(Config::Bot).await? {
-            Origin::Unknown
-        } else {
-            Origin::IncomingReplyTo
-        };
+        let minimal_origin = get_list_minimal_origin(context).await?;
         if flag_verified_only || query.is_some() {
             let s3str_like_cmd = format!("%{}%", query.unwrap_or(""));
             context
@@ -1143,6 +1343,128 @@ pub async fn get_all(
         Ok(ret)
     }
 
+    /// Returns a page of known and unblocked contacts.
+    ///
+    /// Unlike [`Contact::get_all`], filtering, sorting and paging is done by the database
+    /// and only lightweight entries are loaded,
+    /// so this is suitable for accounts with a lot of contacts.
+    /// SELF is never returned.
+    pub async fn get_page(
+        context: &Context,
+        filter: &ContactListFilter,
+    ) -> Result<Vec<ContactListEntry>> {
+        let self_addrs = context.get_all_self_addrs().await?;
+        let minimal_origin = get_list_minimal_origin(context).await?;
+        let like_query = format!("%{}%", filter.query.as_deref().unwrap_or(""));
+        let order_by = match filter.sort {
+            ContactSortOrder::LastSeen => "c.last_seen DESC, c.id DESC".to_string(),
+            ContactSortOrder::Name => format!(
+                "iif(c.name='',iif(c.authname='',c.addr,c.authname),c.name) COLLATE {}, c.id",
+                collation::sql_collation(context).await?
+            ),
+        };
+        // Negative LIMIT means no limit in SQLite.
+        let limit = filter
+            .limit
+            .map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX));
+        let offset = i64::try_from(filter.offset).unwrap_or(i64::MAX);
+        let entries = context
+            .sql
+            .query_map(
+                &format!(
+                    "SELECT c.id, c.name, c.authname, c.addr, c.last_seen, c.param,
+                     IFNULL(LENGTH(ps.verified_key_fingerprint), 0)!=0
+                     FROM contacts c
+                     LEFT JOIN acpeerstates ps ON c.addr=ps.addr
+                     WHERE {}
+                     ORDER BY {order_by}
+                     LIMIT ? OFFSET ?",
+                    list_filter_condition(self_addrs.len())
+                ),
+                rusqlite::params_from_iter(
+                    params_iter(&self_addrs)
+                        .chain(params_slice![
+                            ContactId::LAST_SPECIAL,
+                            minimal_origin,
+                            like_query,
+                            like_query,
+                            filter.verified_only,
+                            filter.with_chat_only,
+                            Chattype::Single,
+                            filter.tag,
+                            filter.tag
+                        ])
+                        .chain(params_slice![limit, offset]),
+                ),
+                |row| {
+                    let id: ContactId = row.get(0)?;
+                    let name: String = row.get(1)?;
+                    let authname: String = row.get(2)?;
+                    let addr: String = row.get(3)?;
+                    let last_seen: i64 = row.get(4)?;
+                    let param: String = row.get(5)?;
+                    let has_verified_key: bool = row.get(6)?;
+                    Ok((id, name, authname, addr, last_seen, param, has_verified_key))
+                },
+                |rows| {
+                    let mut entries = Vec::new();
+                    for row in rows {
+                        let (id, name, authname, addr, last_seen, param, has_verified_key) = row?;
+                        let param: Params = param.parse().unwrap_or_default();
+                        let display_name = param
+                            .get(Param::LocalDisplayname)
+                            .filter(|nickname| !nickname.is_empty())
+                            .or_else(|| Some(name.as_str()).filter(|name| !name.is_empty()))
+                            .or_else(|| Some(authname.as_str()).filter(|name| !name.is_empty()))
+                            .unwrap_or(&addr)
+                            .to_string();
+                        entries.push(ContactListEntry {
+                            id,
+                            display_name,
+                            color: str_to_color(&addr.to_lowercase()),
+                            addr,
+                            last_seen,
+                            has_verified_key,
+                        });
+                    }
+                    Ok(entries)
+                },
+            )
+            .await?;
+        Ok(entries)
+    }
+
+    /// Returns the number of contacts matching `filter`,
+    /// ignoring [`ContactListFilter::offset`] and [`ContactListFilter::limit`].
+    pub async fn get_count(context: &Context, filter: &ContactListFilter) -> Result<usize> {
+        let self_addrs = context.get_all_self_addrs().await?;
+        let minimal_origin = get_list_minimal_origin(context).await?;
+        let like_query = format!("%{}%", filter.query.as_deref().unwrap_or(""));
+        let count = context
+            .sql
+            .count(
+                &format!(
+                    "SELECT COUNT(*) FROM contacts c
+                     LEFT JOIN acpeerstates ps ON c.addr=ps.addr
+                     WHERE {}",
+                    list_filter_condition(self_addrs.len())
+                ),
+                rusqlite::params_from_iter(params_iter(&self_addrs).chain(params_slice![
+                    ContactId::LAST_SPECIAL,
+                    minimal_origin,
+                    like_query,
+                    like_query,
+                    filter.verified_only,
+                    filter.with_chat_only,
+                    Chattype::Single,
+                    filter.tag,
+                    filter.tag
+                ])),
+            )
+            .await?;
+        Ok(count)
+    }
+
     /// Adds blocked mailinglists as contacts
     /// to allow unblocking them as if they are contacts
     /// (this way, only one unblock-ffi is needed and only one set of ui-functions,
@@ -1327,6 +1649,66 @@ pub async fn update_param(&self, context: &Context) -> Result<()> {
         Ok(())
     }
 
+    /// Sets a nickname and an avatar for the contact
+    /// overriding the ones chosen by the contact and the contact name.
+    ///
+    /// The override is stored only locally and is never sent to anyone,
+    /// not even to other devices of the user.
+    /// `None` removes the nickname or avatar override respectively.
+    pub async fn set_local_override(
+        context: &Context,
+        contact_id: ContactId,
+        nickname: Option<&str>,
+        avatar: Option<&Path>,
+    ) -> Result<()> {
+        ensure!(
+            !contact_id.is_special(),
+            "Can't set local override for special contact"
+        );
+        let mut contact = Contact::get_by_id(context, contact_id).await?;
+        match nickname.map(sanitize_name) {
+            Some(nickname) if !nickname.is_empty() => {
+                contact.param.set(Param::LocalDisplayname, &nickname)
+            }
+            _ => contact.param.remove(Param::LocalDisplayname),
+        };
+        match avatar {
+            Some(path) => {
+                let mut blob = BlobObject::new_from_path(context, path).await?;
+                blob.recode_to_avatar_size(context).await?;
+                contact.param.set(Param::LocalProfileImage, blob.as_name())
+            }
+            None => contact.param.remove(Param::LocalProfileImage),
+        };
+        contact.update_param(context).await?;
+
+        if let Some(ChatIdBlocked { id: chat_id, .. }) =
+            ChatIdBlocked::lookup_by_contact(context, contact_id).await?
+        {
+            let chat_name = contact.get_display_name().to_string();
+            let count = context
+                .sql
+                .execute(
+                    "UPDATE chats SET name=?1 WHERE id=?2 AND name!=?1",
+                    (chat_name, chat_id),
+                )
+                .await?;
+            if count > 0 {
+                context.emit_event(EventType::ChatModified(chat_id));
+                chatlist_events::emit_chatlist_items_changed_for_contact(context, contact_id);
+            }
+        }
+        context.emit_event(EventType::ContactsChanged(Some(contact_id)));
+        Ok(())
+    }
+
+    /// Returns the nickname set with [`Contact::set_local_override`], if any.
+    pub fn get_local_nickname(&self) -> Option<&str> {
+        self.param
+            .get(Param::LocalDisplayname)
+            .filter(|nickname| !nickname.is_empty())
+    }
+
     /// Updates `status` column in the database.
     pub async fn update_status(&self, context: &Context) -> Result<()> {
         context
@@ -1363,12 +1745,16 @@ pub fn get_name(&self) -> &str {
         &self.name
     }
 
-    /// Get display name. This is the name as defined by the contact himself,
+    /// Get display name. This is the nickname set with [`Contact::set_local_override`]
+    /// or the name as defined by the contact himself,
     /// modified by the user or, if both are unset, the email address.
     ///
     /// This name is typically used in lists.
     /// To get the name editable in a formular, use `Contact::get_name`.
     pub fn get_display_name(&self) -> &str {
+        if let Some(nickname) = self.get_local_nickname() {
+            return nickname;
+        }
         if !self.name.is_empty() {
             return &self.name;
         }
@@ -1404,7 +1790,21 @@ pub fn get_authname_n_addr(&self) -> String {
     /// The summary is typically used when asking the user something about the contact.
     /// The attached email address makes the question unique, eg. "Chat with Alan Miller (am@uniquedomain.com)?"
     pub fn get_name_n_addr(&self) -> String {
-        if !self.name.is_empty() {
+        self.get_name_n_addr_ex(true)
+    }
+
+    /// Get a summary of name and address, optionally ignoring the local nickname.
+    ///
+    /// Texts stored in messages are sent to other devices and may be forwarded,
+    /// so they must not contain the nickname.
+    pub(crate) fn get_name_n_addr_ex(&self, local_override: bool) -> String {
+        let nickname = match local_override {
+            true => self.get_local_nickname(),
+            false => None,
+        };
+        if let Some(nickname) = nickname {
+            format!("{} ({})", nickname, self.addr)
+        } else if !self.name.is_empty() {
             format!("{} ({})", self.name, self.addr)
         } else if !self.authname.is_empty() {
             format!("{} ({})", self.authname, self.addr)
@@ -1415,15 +1815,30 @@ pub fn get_name_n_addr(&self) -> String {
 
     /// Get the contact's profile image.
     /// This is the image set by each remote user on their own
-    /// using set_config(context, "selfavatar", image).
+    /// using set_config(context, "selfavatar", image),
+    /// unless overridden locally with [`Contact::set_local_override`].
     pub async fn get_profile_image(&self, context: &Context) -> Result<Option<PathBuf>> {
+        self.get_profile_image_ex(context, true).await
+    }
+
+    async fn get_profile_image_ex(
+        &self,
+        context: &Context,
+        local_override: bool,
+    ) -> Result<Option<PathBuf>> {
         if self.id == ContactId::SELF {
             if let Some(p) = context.get_config(Config::Selfavatar).await? {
                 return Ok(Some(PathBuf::from(p)));
             }
-        } else if let Some(image_rel) = self.param.get(Param::ProfileImage) {
-            if !image_rel.is_empty() {
-                return Ok(Some(get_abs_path(context, Path::new(image_rel))));
+        } else {
+            let local_image = match local_override {
+                true => self.param.get(Param::LocalProfileImage),
+                false => None,
+            };
+            if let Some(image_rel) = local_image.or(self.param.get(Param::ProfileImage)) {
+                if !image_rel.is_empty() {
+                    return Ok(Some(get_abs_path(context, Path::new(image_rel))));
+                }
             }
         }
         Ok(None)
@@ -1778,6 +2193,81 @@ pub(crate) async fn update_last_seen(
     Ok(())
 }
 
+/// Removes the contact from the address book `source`.
+///
+/// If the contact is not listed in any address book anymore and is known only from them,
+/// it is deleted or, if there are chats with it, loses the name from the address book.
+///
+/// Returns whether the contact was modified.
+async fn remove_address_book_source(
+    context: &Context,
+    contact_id: ContactId,
+    source: &str,
+) -> Result<bool> {
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "DELETE FROM address_book_sources WHERE contact_id=? AND source=?",
+                (contact_id, source),
+            )?;
+            let still_listed = transaction.query_row(
+                "SELECT COUNT(*) FROM address_book_sources WHERE contact_id=?",
+                (contact_id,),
+                |row| row.get::<_, i64>(0),
+            )? > 0;
+            let origin: Origin = transaction.query_row(
+                "SELECT origin FROM contacts WHERE id=?",
+                (contact_id,),
+                |row| row.get(0),
+            )?;
+            if still_listed || origin != Origin::AddressBook {
+                return Ok(false);
+            }
+            let deleted = transaction.execute(
+                "DELETE FROM contacts WHERE id=?
+                 AND NOT EXISTS (SELECT 1 FROM chats_contacts WHERE contact_id=?)",
+                (contact_id, contact_id),
+            )?;
+            if deleted == 0 {
+                transaction.execute("UPDATE contacts SET name='' WHERE id=?", (contact_id,))?;
+            }
+            Ok(true)
+        })
+        .await
+}
+
+/// Returns the minimal origin of contacts shown in the contact list.
+async fn get_list_minimal_origin(context: &Context) -> Result<Origin> {
+    Ok(if context.get_config_bool(Config::Bot).await? {
+        Origin::Unknown
+    } else {
+        Origin::IncomingReplyTo
+    })
+}
+
+/// Returns the SQL condition for [`Contact::get_page`] and [`Contact::get_count`].
+///
+/// The parameters are the self addresses, the last special contact ID, the minimal origin,
+/// the LIKE pattern twice, the `verified_only` and `with_chat_only` flags
+/// and [`Chattype::Single`].
+fn list_filter_condition(self_addrs_cnt: usize) -> String {
+    format!(
+        "c.addr NOT IN ({})
+         AND c.id>?
+         AND c.origin>=?
+         AND c.blocked=0
+         AND (iif(c.name='',c.authname,c.name) LIKE ? OR c.addr LIKE ?)
+         AND (NOT ? OR IFNULL(LENGTH(ps.verified_key_fingerprint), 0)!=0)
+         AND (NOT ? OR EXISTS (
+             SELECT 1 FROM chats_contacts cc INNER JOIN chats ch ON ch.id=cc.chat_id
+             WHERE cc.contact_id=c.id AND ch.type=? AND ch.blocked=0))
+         AND (? IS NULL OR EXISTS (
+             SELECT 1 FROM contact_tags t WHERE t.contact_id=c.id AND t.tag=?))",
+        sql::repeat_vars(self_addrs_cnt)
+    )
+}
+
 fn cat_fingerprint(
     ret: &mut String,
     addr: &str,
@@ -2034,6 +2524,170 @@ fn test_split_address_book() {
         )
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_apply_address_book_delta() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let entry = |name: &str, addr: &str| AddressBookEntry {
+            name: name.to_string(),
+            addr: addr.to_string(),
+        };
+
+        let cnt = Contact::apply_address_book_delta(
+            &t,
+            "phone",
+            &[
+                entry("Bob", "bob@example.net"),
+                entry("Claire", "claire@example.org"),
+                entry("", "invalid"),
+            ],
+            &[],
+        )
+        .await?;
+        assert_eq!(cnt, 2);
+        Contact::apply_address_book_delta(&t, "work", &[entry("Bob", "bob@example.net")], &[])
+            .await?;
+        let bob_id = Contact::lookup_id_by_addr(&t, "bob@example.net", Origin::Unknown)
+            .await?
+            .unwrap();
+        let claire_id = Contact::lookup_id_by_addr(&t, "claire@example.org", Origin::Unknown)
+            .await?
+            .unwrap();
+        assert_eq!(
+            Contact::get_address_book_sources(&t, bob_id).await?,
+            ["phone", "work"]
+        );
+
+        // Renaming updates the name.
+        let cnt = Contact::apply_address_book_delta(
+            &t,
+            "phone",
+            &[entry("Bobby", "bob@example.net")],
+            &[],
+        )
+        .await?;
+        assert_eq!(cnt, 1);
+        assert_eq!(
+            Contact::get_by_id(&t, bob_id).await?.get_display_name(),
+            "Bobby"
+        );
+
+        // Bob is still in the "work" address book.
+        let deletions = [
+            "bob@example.net".to_string(),
+            "claire@example.org".to_string(),
+        ];
+        Contact::apply_address_book_delta(&t, "phone", &[], &deletions).await?;
+        assert_eq!(
+            Contact::get_address_book_sources(&t, bob_id).await?,
+            ["work"]
+        );
+        assert!(Contact::get_by_id_optional(&t, claire_id).await?.is_none());
+
+        // There is a chat with Bob, so only the name is removed.
+        ChatId::create_for_contact(&t, bob_id).await?;
+        Contact::apply_address_book_delta(&t, "work", &[], &deletions).await?;
+        let bob = Contact::get_by_id(&t, bob_id).await?;
+        assert_eq!(bob.get_name(), "");
+        assert!(Contact::get_address_book_sources(&t, bob_id)
+            .await?
+            .is_empty());
+
+        // Manually created contacts are not deleted.
+        let dora_id = Contact::create(&t, "Dora", "dora@example.org").await?;
+        Contact::apply_address_book_delta(&t, "phone", &[entry("D", "dora@example.org")], &[])
+            .await?;
+        Contact::apply_address_book_delta(&t, "phone", &[], &["dora@example.org".to_string()])
+            .await?;
+        assert_eq!(
+            Contact::get_by_id(&t, dora_id).await?.get_display_name(),
+            "Dora"
+        );
+
+        assert!(Contact::apply_address_book_delta(&t, "", &[], &[])
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_page() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let bob_id = Contact::create(alice, "bob", "bob@example.net").await?;
+        let zoe_id = Contact::create(alice, "Zoe", "zoe@example.org").await?;
+        let anna_id = Contact::create(alice, "anna", "anna@example.org").await?;
+        for (contact_id, last_seen) in [(bob_id, 100), (zoe_id, 300), (anna_id, 200)] {
+            alice
+                .sql
+                .execute(
+                    "UPDATE contacts SET last_seen=? WHERE id=?",
+                    (last_seen, contact_id),
+                )
+                .await?;
+        }
+        test_utils::mark_as_verified(alice, bob).await;
+        ChatId::create_for_contact(alice, zoe_id).await?;
+
+        let ids = |entries: Vec<ContactListEntry>| -> Vec<ContactId> {
+            entries.into_iter().map(|entry| entry.id).collect()
+        };
+
+        let mut filter = ContactListFilter::default();
+        let entries = Contact::get_page(alice, &filter).await?;
+        assert_eq!(ids(entries.clone()), [zoe_id, anna_id, bob_id]);
+        let zoe = entries.first().unwrap();
+        assert_eq!(zoe.display_name, "Zoe");
+        assert_eq!(zoe.addr, "zoe@example.org");
+        assert_eq!(zoe.last_seen, 300);
+        assert_eq!(
+            zoe.color,
+            Contact::get_by_id(alice, zoe_id).await?.get_color()
+        );
+        assert!(!zoe.has_verified_key);
+        assert_eq!(Contact::get_count(alice, &filter).await?, 3);
+
+        filter.sort = ContactSortOrder::Name;
+        assert_eq!(
+            ids(Contact::get_page(alice, &filter).await?),
+            [anna_id, bob_id, zoe_id]
+        );
+        filter.offset = 1;
+        filter.limit = Some(1);
+        assert_eq!(ids(Contact::get_page(alice, &filter).await?), [bob_id]);
+        assert_eq!(Contact::get_count(alice, &filter).await?, 3);
+
+        let filter = ContactListFilter {
+            verified_only: true,
+            ..Default::default()
+        };
+        let entries = Contact::get_page(alice, &filter).await?;
+        assert_eq!(ids(entries.clone()), [bob_id]);
+        assert!(entries.first().unwrap().has_verified_key);
+
+        let filter = ContactListFilter {
+            with_chat_only: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(Contact::get_page(alice, &filter).await?), [zoe_id]);
+        assert_eq!(Contact::get_count(alice, &filter).await?, 1);
+
+        let filter = ContactListFilter {
+            query: Some("an".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(Contact::get_page(alice, &filter).await?), [anna_id]);
+
+        // Blocked contacts are not listed.
+        Contact::block(alice, anna_id).await?;
+        assert_eq!(
+            Contact::get_count(alice, &ContactListFilter::default()).await?,
+            2
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_get_contacts() -> Result<()> {
         let context = TestContext::new().await;
@@ -2343,6 +2997,37 @@ async fn test_contact_name_changes() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_local_override() -> Result<()> {
+        let alice = &TestContext::new_alice().await;
+        let chat = alice
+            .create_chat_with_contact("Bob", "bob@example.net")
+            .await;
+        let contact_id = *get_chat_contacts(alice, chat.id).await?.first().unwrap();
+
+        let avatar_src = alice.dir.path().join("avatar.png");
+        tokio::fs::write(&avatar_src, test_utils::AVATAR_900x900_BYTES).await?;
+        Contact::set_local_override(alice, contact_id, Some("Bobby"), Some(&avatar_src)).await?;
+        let contact = Contact::get_by_id(alice, contact_id).await?;
+        assert_eq!(contact.get_display_name(), "Bobby");
+        assert_eq!(contact.get_name_n_addr(), "Bobby (bob@example.net)");
+        assert_eq!(contact.get_name(), "Bob");
+        assert!(contact.get_profile_image(alice).await?.is_some());
+        assert_eq!(Chat::load_from_db(alice, chat.id).await?.name, "Bobby");
+
+        // The override is not shared.
+        let vcard = make_vcard(alice, &[contact_id]).await?;
+        assert!(!vcard.contains("Bobby"));
+        assert!(!vcard.contains("PHOTO"));
+
+        Contact::set_local_override(alice, contact_id, None, None).await?;
+        let contact = Contact::get_by_id(alice, contact_id).await?;
+        assert_eq!(contact.get_display_name(), "Bob");
+        assert_eq!(contact.get_profile_image(alice).await?, None);
+        assert_eq!(Chat::load_from_db(alice, chat.id).await?.name, "Bob");
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_delete() -> Result<()> {
         let alice = TestContext::new_alice().await;
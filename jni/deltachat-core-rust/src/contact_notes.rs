@@ -0,0 +1,264 @@
+//! # Notes and tags on contacts.
+//!
+//! Contacts can have a free-text note and any number of string tags,
+//! e.g. to record where a contact was met or which group of people it belongs to.
+//! Notes and tags are never sent to the contact,
+//! but synchronized to other devices of the user with sync messages.
+//! Contacts are identified by their address there.
+//!
+//! [`ContactListFilter::tag`](crate::contact::ContactListFilter::tag)
+//! can be used to list contacts with a given tag.
+
+use std::collections::BTreeSet;
+
+use anyhow::{ensure, Context as _, Result};
+use deltachat_contact_tools::ContactAddress;
+
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::sync::Sync::*;
+use crate::sync::{self, SyncData};
+
+/// Trims the tags and removes empty and duplicate ones.
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    tags.iter()
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+impl ContactId {
+    /// Returns the note on the contact, empty if there is none.
+    pub async fn get_note(self, context: &Context) -> Result<String> {
+        Ok(context
+            .sql
+            .query_get_value("SELECT note FROM contacts WHERE id=?", (self,))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Sets the note on the contact. An empty note removes it.
+    pub async fn set_note(self, context: &Context, note: &str) -> Result<()> {
+        set_note_ex(context, Sync, self, note).await
+    }
+
+    /// Returns the tags of the contact in alphabetical order.
+    pub async fn get_tags(self, context: &Context) -> Result<Vec<String>> {
+        context
+            .sql
+            .query_map(
+                "SELECT tag FROM contact_tags WHERE contact_id=? ORDER BY tag",
+                (self,),
+                |row| row.get::<_, String>(0),
+                |tags| tags.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+            )
+            .await
+    }
+
+    /// Replaces the tags of the contact with `tags`.
+    ///
+    /// Tags are trimmed, empty and duplicate tags are ignored.
+    pub async fn set_tags(self, context: &Context, tags: &[String]) -> Result<()> {
+        set_tags_ex(context, Sync, self, tags).await
+    }
+
+    /// Adds `tag` to the tags of the contact.
+    pub async fn add_tag(self, context: &Context, tag: &str) -> Result<()> {
+        let mut tags = self.get_tags(context).await?;
+        tags.push(tag.to_string());
+        self.set_tags(context, &tags).await
+    }
+
+    /// Removes `tag` from the tags of the contact.
+    pub async fn remove_tag(self, context: &Context, tag: &str) -> Result<()> {
+        let mut tags = self.get_tags(context).await?;
+        tags.retain(|t| t != tag.trim());
+        self.set_tags(context, &tags).await
+    }
+}
+
+/// Returns all tags used for any contact in alphabetical order.
+pub async fn get_all_tags(context: &Context) -> Result<Vec<String>> {
+    context
+        .sql
+        .query_map(
+            "SELECT DISTINCT tag FROM contact_tags ORDER BY tag",
+            (),
+            |row| row.get::<_, String>(0),
+            |tags| tags.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+/// Returns the contacts tagged with `tag`.
+pub async fn get_contacts_by_tag(context: &Context, tag: &str) -> Result<Vec<ContactId>> {
+    context
+        .sql
+        .query_map(
+            "SELECT contact_id FROM contact_tags WHERE tag=? ORDER BY contact_id",
+            (tag.trim(),),
+            |row| row.get::<_, ContactId>(0),
+            |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+pub(crate) async fn set_note_ex(
+    context: &Context,
+    sync: sync::Sync,
+    contact_id: ContactId,
+    note: &str,
+) -> Result<()> {
+    ensure!(
+        !contact_id.is_special(),
+        "Cannot set note on special contact"
+    );
+    let note = note.trim();
+    context
+        .sql
+        .execute("UPDATE contacts SET note=? WHERE id=?", (note, contact_id))
+        .await?;
+    context.emit_event(EventType::ContactsChanged(Some(contact_id)));
+
+    if sync.into() {
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        context
+            .add_sync_item(SyncData::ContactNote {
+                addr: contact.get_addr().to_string(),
+                note: note.to_string(),
+            })
+            .await?;
+        context.scheduler.interrupt_inbox().await;
+    }
+    Ok(())
+}
+
+pub(crate) async fn set_tags_ex(
+    context: &Context,
+    sync: sync::Sync,
+    contact_id: ContactId,
+    tags: &[String],
+) -> Result<()> {
+    ensure!(
+        !contact_id.is_special(),
+        "Cannot set tags on special contact"
+    );
+    let tags = normalize_tags(tags);
+    let tags1 = tags.clone();
+    context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute("DELETE FROM contact_tags WHERE contact_id=?", (contact_id,))?;
+            let mut stmt =
+                transaction.prepare("INSERT INTO contact_tags (contact_id, tag) VALUES (?, ?)")?;
+            for tag in &tags1 {
+                stmt.execute((contact_id, tag))?;
+            }
+            Ok(())
+        })
+        .await?;
+    context.emit_event(EventType::ContactsChanged(Some(contact_id)));
+
+    if sync.into() {
+        let contact = Contact::get_by_id(context, contact_id).await?;
+        context
+            .add_sync_item(SyncData::ContactTags {
+                addr: contact.get_addr().to_string(),
+                tags,
+            })
+            .await?;
+        context.scheduler.interrupt_inbox().await;
+    }
+    Ok(())
+}
+
+impl Context {
+    async fn lookup_or_add_synced_contact(&self, addr: &str) -> Result<ContactId> {
+        let addr = ContactAddress::new(addr).context("Invalid address")?;
+        let (contact_id, _) = Contact::add_or_lookup(self, "", &addr, Origin::Hidden).await?;
+        Ok(contact_id)
+    }
+
+    pub(crate) async fn sync_contact_note(&self, addr: &str, note: &str) -> Result<()> {
+        let contact_id = self.lookup_or_add_synced_contact(addr).await?;
+        set_note_ex(self, Nosync, contact_id, note).await
+    }
+
+    pub(crate) async fn sync_contact_tags(&self, addr: &str, tags: &[String]) -> Result<()> {
+        let contact_id = self.lookup_or_add_synced_contact(addr).await?;
+        set_tags_ex(self, Nosync, contact_id, tags).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::contact::ContactListFilter;
+    use crate::test_utils::{sync, TestContext};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_contact_notes_and_tags() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+
+        let bob_id = Contact::create(alice0, "Bob", "bob@example.net").await?;
+        let fiona_id = Contact::create(alice0, "Fiona", "fiona@example.net").await?;
+        assert_eq!(bob_id.get_note(alice0).await?, "");
+        assert!(bob_id.get_tags(alice0).await?.is_empty());
+
+        bob_id.set_note(alice0, " Met at the meetup.\n").await?;
+        bob_id
+            .set_tags(
+                alice0,
+                &[
+                    "organizer".to_string(),
+                    " ".to_string(),
+                    "berlin ".to_string(),
+                ],
+            )
+            .await?;
+        fiona_id.add_tag(alice0, "berlin").await?;
+        fiona_id.add_tag(alice0, "berlin").await?;
+        assert_eq!(bob_id.get_note(alice0).await?, "Met at the meetup.");
+        assert_eq!(bob_id.get_tags(alice0).await?, ["berlin", "organizer"]);
+        assert_eq!(get_all_tags(alice0).await?, ["berlin", "organizer"]);
+        assert_eq!(
+            get_contacts_by_tag(alice0, "berlin").await?,
+            [bob_id, fiona_id]
+        );
+        let filter = ContactListFilter {
+            tag: Some("organizer".to_string()),
+            ..Default::default()
+        };
+        let entries = Contact::get_page(alice0, &filter).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, bob_id);
+        assert_eq!(Contact::get_count(alice0, &filter).await?, 1);
+
+        sync(alice0, alice1).await;
+        let alice1_bob_id = Contact::lookup_id_by_addr(alice1, "bob@example.net", Origin::Unknown)
+            .await?
+            .unwrap();
+        assert_eq!(alice1_bob_id.get_note(alice1).await?, "Met at the meetup.");
+        assert_eq!(
+            alice1_bob_id.get_tags(alice1).await?,
+            ["berlin", "organizer"]
+        );
+
+        bob_id.remove_tag(alice0, "organizer").await?;
+        sync(alice0, alice1).await;
+        assert_eq!(alice1_bob_id.get_tags(alice1).await?, ["berlin"]);
+        assert!(get_contacts_by_tag(alice1, "organizer").await?.is_empty());
+
+        assert!(ContactId::SELF.set_note(alice0, "me").await.is_err());
+        Ok(())
+    }
+}
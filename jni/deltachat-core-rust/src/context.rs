@@ -4,7 +4,7 @@
 use std::ffi::OsString;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -13,10 +13,14 @@
 use pgp::types::PublicKeyTrait;
 use pgp::SignedPublicKey;
 use ratelimit::Ratelimit;
-use tokio::sync::{Mutex, Notify, RwLock};
+use tokio::sync::{watch, Mutex, Notify, RwLock};
 
 use crate::aheader::EncryptPreference;
+use crate::blob::avatar_cache::AvatarCache;
+use crate::blob::video::VideoDecoder;
+use crate::blob::ImageRecodes;
 use crate::chat::{get_chat_cnt, ChatId, ProtectionStatus};
+use crate::chatlist::ChatlistDiffState;
 use crate::chatlist_events;
 use crate::config::Config;
 use crate::constants::{
@@ -26,21 +30,31 @@
 use crate::debug_logging::DebugLogging;
 use crate::download::DownloadState;
 use crate::events::{Event, EventEmitter, EventType, Events};
+use crate::health::HealthCounters;
+use crate::housekeeping::HousekeepingState;
 use crate::imap::{FolderMeaning, Imap, ServerMetadata};
 use crate::key::{load_self_public_key, load_self_secret_key, DcKey as _};
 use crate::login_param::{ConfiguredLoginParam, EnteredLoginParam};
 use crate::message::{self, Message, MessageState, MsgId};
+use crate::net::stats::NetworkCounters;
+use crate::net::NetworkKind;
+use crate::ownership::OwnershipState;
 use crate::param::{Param, Params};
 use crate::peer_channels::Iroh;
 use crate::peerstate::Peerstate;
 use crate::push::PushSubscriber;
 use crate::quota::QuotaInfo;
+use crate::remote_backup::RemoteBackupState;
 use crate::scheduler::{convert_folder_meaning, SchedulerState};
 use crate::sql::Sql;
+use crate::staging::StagingTasks;
+use crate::startup::StartupState;
 use crate::stock_str::StockStrings;
 use crate::timesmearing::SmearedTimestamp;
 use crate::tools::{self, create_id, duration_to_str, time, time_elapsed};
 
+pub use crate::sql::stats::{PoolStats, SlowQuery, SqlStats, StatementStats};
+
 /// Builder for the [`Context`].
 ///
 /// Many arguments to the [`Context`] are kind of optional and only needed to handle
@@ -90,6 +104,8 @@ pub struct ContextBuilder {
     events: Events,
     stock_strings: StockStrings,
     password: Option<String>,
+    sql_pool_size: Option<usize>,
+    sql_statement_cache_capacity: Option<usize>,
 
     push_subscriber: Option<PushSubscriber>,
 }
@@ -107,6 +123,8 @@ pub fn new(dbfile: PathBuf) -> Self {
             events: Events::new(),
             stock_strings: StockStrings::new(),
             password: None,
+            sql_pool_size: None,
+            sql_statement_cache_capacity: None,
             push_subscriber: None,
         }
     }
@@ -162,6 +180,23 @@ pub fn with_password(mut self, password: String) -> Self {
         self
     }
 
+    /// Sets the number of database connections.
+    ///
+    /// Defaults to 3. More connections allow more parallel database readers,
+    /// but each connection has its own page cache.
+    pub fn with_sql_pool_size(mut self, pool_size: usize) -> Self {
+        self.sql_pool_size = Some(pool_size);
+        self
+    }
+
+    /// Sets the capacity of the prepared statement cache of each database connection.
+    ///
+    /// Defaults to 16.
+    pub fn with_sql_statement_cache_capacity(mut self, capacity: usize) -> Self {
+        self.sql_statement_cache_capacity = Some(capacity);
+        self
+    }
+
     /// Sets push subscriber.
     pub(crate) fn with_push_subscriber(mut self, push_subscriber: PushSubscriber) -> Self {
         self.push_subscriber = Some(push_subscriber);
@@ -179,6 +214,12 @@ pub async fn build(self) -> Result<Context> {
             push_subscriber,
         )
         .await?;
+        if let Some(pool_size) = self.sql_pool_size {
+            context.sql.set_pool_size(pool_size)?;
+        }
+        if let Some(capacity) = self.sql_statement_cache_capacity {
+            context.sql.set_statement_cache_capacity(capacity);
+        }
         Ok(context)
     }
 
@@ -238,6 +279,10 @@ pub struct InnerContext {
     /// Mutex to prevent a race condition when a "your pw is wrong" warning is sent, resulting in multiple messages being sent.
     pub(crate) wrong_pw_warning_mutex: Mutex<()>,
     pub(crate) translated_stockstrings: StockStrings,
+
+    /// Translations of this account taking precedence over `translated_stockstrings`
+    /// shared by all accounts.
+    pub(crate) account_stockstrings: StockStrings,
     pub(crate) events: Events,
 
     pub(crate) scheduler: SchedulerState,
@@ -271,7 +316,7 @@ pub struct InnerContext {
     /// be identified by this ID.
     pub(crate) id: u32,
 
-    creation_time: tools::Time,
+    pub(crate) creation_time: tools::Time,
 
     /// The text of the last error logged and emitted as an event.
     /// If the ui wants to display an error after a failure,
@@ -291,8 +336,61 @@ pub struct InnerContext {
     /// True if account has subscribed to push notifications via IMAP.
     pub(crate) push_subscribed: AtomicBool,
 
+    /// Counters for [`Context::get_network_stats`].
+    pub(crate) network_counters: NetworkCounters,
+
+    /// Signals for [`Context::get_account_health`].
+    pub(crate) health_counters: HealthCounters,
+
     /// Iroh for realtime peer channels.
     pub(crate) iroh: Arc<RwLock<Option<Iroh>>>,
+
+    /// State of the chatlist diff events.
+    pub(crate) chatlist_diff: ChatlistDiffState,
+
+    /// Whether the app is in the foreground, see [`Context::set_foreground`].
+    pub(crate) foreground: watch::Sender<bool>,
+
+    /// Chat the user is interacting with, see [`Context::set_interactive_chat`].
+    pub(crate) interactive_chat: parking_lot::Mutex<Option<ChatId>>,
+
+    /// Running image recodings of messages being sent,
+    /// see [`ChatId::cancel_image_recoding`].
+    pub(crate) image_recodes: parking_lot::Mutex<ImageRecodes>,
+
+    /// Stagings of draft attachments running in this process, see [`crate::staging`].
+    pub(crate) staging: StagingTasks,
+
+    /// Recently recoded avatars, see [`crate::blob::avatar_cache`].
+    pub(crate) avatar_cache: AvatarCache,
+
+    /// State of the housekeeping job.
+    pub(crate) housekeeping: HousekeepingState,
+
+    /// State of the remote backup job.
+    pub(crate) remote_backup: RemoteBackupState,
+
+    /// Class of the network the device is connected to, see [`Context::on_network_changed`].
+    pub(crate) network_kind: parking_lot::Mutex<NetworkKind>,
+
+    /// Identity of the network the device is connected to,
+    /// see [`Context::set_network_identity`].
+    pub(crate) network_identity: parking_lot::Mutex<Option<String>>,
+
+    /// Decoder of videos supplied by the platform, see [`Context::set_video_decoder`].
+    pub(crate) video_decoder: parking_lot::Mutex<Option<Arc<dyn VideoDecoder>>>,
+
+    /// Number of network changes reported with [`Context::on_network_changed`].
+    ///
+    /// IMAP and SMTP loops compare it with the value seen when connecting
+    /// to drop connections made over the old network and reset their backoff.
+    pub(crate) network_generation: AtomicU64,
+
+    /// Startup timings and deferred IO start.
+    pub(crate) startup: StartupState,
+
+    /// Lock on the account held by the owner process, see [`Context::is_owner`].
+    pub(crate) ownership: OwnershipState,
 }
 
 /// The state of ongoing process.
@@ -436,6 +534,7 @@ pub(crate) fn with_blobdir(
             oauth2_mutex: Mutex::new(()),
             wrong_pw_warning_mutex: Mutex::new(()),
             translated_stockstrings: stockstrings,
+            account_stockstrings: StockStrings::new(),
             events,
             scheduler: SchedulerState::new(),
             ratelimit: RwLock::new(Ratelimit::new(Duration::new(60, 0), 6.0)), // Allow at least 1 message every 10 seconds + a burst of 6.
@@ -450,17 +549,37 @@ pub(crate) fn with_blobdir(
             debug_logging: std::sync::RwLock::new(None),
             push_subscriber,
             push_subscribed: AtomicBool::new(false),
+            network_counters: NetworkCounters::default(),
+            health_counters: HealthCounters::default(),
             iroh: Arc::new(RwLock::new(None)),
+            chatlist_diff: ChatlistDiffState::default(),
+            foreground: watch::Sender::new(false),
+            interactive_chat: parking_lot::Mutex::new(None),
+            image_recodes: Default::default(),
+            staging: StagingTasks::default(),
+            avatar_cache: AvatarCache::default(),
+            housekeeping: HousekeepingState::default(),
+            remote_backup: RemoteBackupState::default(),
+            network_kind: parking_lot::Mutex::new(NetworkKind::default()),
+            network_identity: parking_lot::Mutex::new(None),
+            video_decoder: parking_lot::Mutex::new(None),
+            network_generation: AtomicU64::new(0),
+            startup: StartupState::default(),
+            ownership: OwnershipState::default(),
         };
 
         let ctx = Context {
             inner: Arc::new(inner),
         };
+        ctx.sql.set_log_context(&ctx);
 
         Ok(ctx)
     }
 
     /// Starts the IO scheduler.
+    ///
+    /// If another process owns the account, IO is started once it released the ownership,
+    /// see [`crate::ownership`].
     pub async fn start_io(&self) {
         if !self.is_configured().await.unwrap_or_default() {
             warn!(self, "can not start io on a context that is not configured");
@@ -480,11 +599,26 @@ pub async fn start_io(&self) {
         // Now, some configs may have changed, so, we need to invalidate the cache.
         self.sql.config_cache.write().await.clear();
 
-        self.scheduler.start(self.clone()).await;
+        match self.try_acquire_ownership().await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!(
+                    self,
+                    "Not starting IO yet, the account is used by another process."
+                );
+                self.wait_for_ownership();
+                return;
+            }
+            Err(err) => warn!(self, "Cannot check the ownership of the account: {err:#}."),
+        }
+
+        self.start_scheduler().await;
     }
 
     /// Stops the IO scheduler.
     pub async fn stop_io(&self) {
+        self.startup.abort_pending_io_start();
+        self.ownership.stop_waiting();
         self.scheduler.stop(self).await;
         if let Some(iroh) = self.iroh.write().await.take() {
             // Close all QUIC connections.
@@ -501,6 +635,35 @@ pub async fn stop_io(&self) {
         }
     }
 
+    /// Pauses the IO scheduler for `reason`, e.g. while a backup is in progress
+    /// or while the battery saver is on.
+    ///
+    /// Pauses are counted per reason, IO is resumed
+    /// when [`Context::resume_io`] was called for every pause.
+    /// Unlike [`Context::stop_io`], pausing for one reason does not interfere
+    /// with pauses for other reasons and [`Context::start_io`] calls in the meantime.
+    /// The reasons are shown in the connectivity view,
+    /// see [`Context::get_io_pause_reasons`].
+    pub async fn pause_io(&self, reason: &str) -> Result<()> {
+        ensure!(!reason.trim().is_empty(), "Pause reason must not be empty");
+        info!(self, "Pausing IO for {reason:?}.");
+        self.scheduler.pause_reason(self, reason.trim()).await
+    }
+
+    /// Releases one pause of the IO scheduler made with [`Context::pause_io`] for `reason`.
+    ///
+    /// Fails if IO is not paused for `reason`.
+    pub async fn resume_io(&self, reason: &str) -> Result<()> {
+        info!(self, "Resuming IO for {reason:?}.");
+        self.scheduler.resume_reason(self, reason.trim())
+    }
+
+    /// Returns the reasons IO is currently paused for with [`Context::pause_io`],
+    /// in alphabetical order.
+    pub fn get_io_pause_reasons(&self) -> Vec<String> {
+        self.scheduler.pause_reasons()
+    }
+
     /// Restarts the IO scheduler if it was running before
     /// when it is not running this is an no-op
     pub async fn restart_io_if_running(&self) {
@@ -515,6 +678,48 @@ pub async fn maybe_network(&self) {
         self.scheduler.maybe_network().await;
     }
 
+    /// Indicates that the device switched to a network of class `kind`.
+    ///
+    /// Unlike [`Context::maybe_network`], connections made over the old network
+    /// are dropped instead of waiting for them to time out,
+    /// connection backoff is reset and the proxy configuration is reloaded,
+    /// so that messages arrive shortly after switching the network.
+    /// [`NetworkKind::None`] indicates that the network is lost.
+    pub async fn on_network_changed(&self, kind: NetworkKind) {
+        let old_kind = std::mem::replace(&mut *self.network_kind.lock(), kind);
+        info!(self, "Network changed from {old_kind} to {kind}.");
+        if kind == NetworkKind::None {
+            self.scheduler.maybe_network_lost(self).await;
+            return;
+        }
+        self.network_generation.fetch_add(1, Ordering::Relaxed);
+        self.maybe_network().await;
+    }
+
+    /// Returns the class of the network the device is connected to
+    /// as reported by [`Context::on_network_changed`].
+    pub fn get_network_kind(&self) -> NetworkKind {
+        *self.network_kind.lock()
+    }
+
+    /// Sets the identity of the network the device is connected to,
+    /// e.g. a hash of the Wi-Fi SSID, or `None` if it is not known.
+    ///
+    /// The connection candidate that works is remembered per network,
+    /// so that alternative ports needed on networks blocking the standard ports
+    /// are only used there.
+    /// The identity is not sent anywhere and only stored hashed.
+    pub fn set_network_identity(&self, identity: Option<&str>) {
+        *self.network_identity.lock() = identity
+            .filter(|identity| !identity.is_empty())
+            .map(|identity| identity.to_string());
+    }
+
+    /// Returns the identity of the network set with [`Context::set_network_identity`].
+    pub(crate) fn get_network_identity(&self) -> Option<String> {
+        self.network_identity.lock().clone()
+    }
+
     /// Returns true if an account is on a chatmail server.
     pub async fn is_chatmail(&self) -> Result<bool> {
         self.get_config_bool(Config::IsChatmail).await
@@ -666,8 +871,12 @@ pub fn emit_msgs_changed(&self, chat_id: ChatId, msg_id: MsgId) {
     }
 
     /// Emits an IncomingMsg event with specified chat and message ids
-    pub fn emit_incoming_msg(&self, chat_id: ChatId, msg_id: MsgId) {
-        self.emit_event(EventType::IncomingMsg { chat_id, msg_id });
+    pub fn emit_incoming_msg(&self, chat_id: ChatId, msg_id: MsgId, watched: bool) {
+        self.emit_event(EventType::IncomingMsg {
+            chat_id,
+            msg_id,
+            watched,
+        });
         chatlist_events::emit_chatlist_changed(self);
         chatlist_events::emit_chatlist_item_changed(self, chat_id);
     }
@@ -765,6 +974,51 @@ pub(crate) async fn shall_stop_ongoing(&self) -> bool {
      * UI chat/message related API
      ******************************************************************************/
 
+    /// Tells whether the app is in the foreground, i.e. the user is interacting with it.
+    ///
+    /// Background jobs such as housekeeping, full folder scans
+    /// and sending webxdc updates of other chats than the interactive one
+    /// are deferred while the app is in the foreground.
+    pub async fn set_foreground(&self, foreground: bool) {
+        let was_foreground = self.foreground.send_replace(foreground);
+        if was_foreground && !foreground {
+            // Run deferred jobs.
+            self.scheduler.interrupt_inbox().await;
+            self.scheduler.interrupt_smtp().await;
+        }
+    }
+
+    /// Returns true if the app is in the foreground, see [`Context::set_foreground`].
+    pub(crate) fn is_foreground(&self) -> bool {
+        *self.foreground.borrow()
+    }
+
+    /// Tells which chat the user is interacting with, `None` if no chat is open.
+    ///
+    /// While the app is in the foreground, the folder containing the messages of this chat
+    /// is fetched before other folders and webxdc updates of this chat are not deferred.
+    pub async fn set_interactive_chat(&self, chat_id: Option<ChatId>) {
+        let old_chat_id = std::mem::replace(&mut *self.interactive_chat.lock(), chat_id);
+        if chat_id.is_some() && chat_id != old_chat_id {
+            self.scheduler.interrupt_inbox().await;
+            self.scheduler.interrupt_smtp().await;
+        }
+    }
+
+    /// Returns the chat the user is interacting with
+    /// if the app is in the foreground.
+    pub(crate) fn get_interactive_chat(&self) -> Option<ChatId> {
+        if !self.is_foreground() {
+            return None;
+        }
+        *self.interactive_chat.lock()
+    }
+
+    /// Returns database query statistics and connection pool metrics.
+    pub async fn get_sql_stats(&self) -> SqlStats {
+        self.sql.get_stats().await
+    }
+
     /// Returns information about the context as key-value pairs.
     pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
         let unset = "0";
@@ -777,6 +1031,7 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
         let chats = get_chat_cnt(self).await?;
         let unblocked_msgs = message::get_unblocked_msg_cnt(self).await;
         let request_msgs = message::get_request_msg_cnt(self).await;
+        let removed_from_server_msgs = message::get_removed_from_server_cnt(self).await?;
         let contacts = Contact::get_real_cnt(self).await?;
         let is_configured = self.get_config_int(Config::Configured).await?;
         let proxy_enabled = self.get_config_int(Config::ProxyEnabled).await?;
@@ -792,6 +1047,7 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
             .unwrap_or_else(|| "unknown".to_string());
         let e2ee_enabled = self.get_config_int(Config::E2eeEnabled).await?;
         let mdns_enabled = self.get_config_int(Config::MdnsEnabled).await?;
+        let mdns_batch_delay = self.get_config_int(Config::MdnsBatchDelay).await?;
         let bcc_self = self.get_config_int(Config::BccSelf).await?;
         let sync_msgs = self.get_config_int(Config::SyncMsgs).await?;
         let disable_idle = self.get_config_bool(Config::DisableIdle).await?;
@@ -840,6 +1096,10 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
         res.insert("number_of_chats", chats.to_string());
         res.insert("number_of_chat_messages", unblocked_msgs.to_string());
         res.insert("messages_in_contact_requests", request_msgs.to_string());
+        res.insert(
+            "messages_removed_from_server",
+            removed_from_server_msgs.to_string(),
+        );
         res.insert("number_of_contacts", contacts.to_string());
         res.insert("database_dir", self.get_dbfile().display().to_string());
         res.insert("database_version", dbversion.to_string());
@@ -851,6 +1111,10 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .map_or_else(|| "closed".to_string(), |b| b.to_string()),
         );
         res.insert("journal_mode", journal_mode);
+        res.insert(
+            "sql_slow_queries",
+            self.sql.get_stats().await.slow_queries.len().to_string(),
+        );
         res.insert("blobdir", self.get_blobdir().display().to_string());
         res.insert("displayname", displayname.unwrap_or_else(|| unset.into()));
         res.insert(
@@ -937,6 +1201,7 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
         res.insert("configured_mvbox_folder", configured_mvbox_folder);
         res.insert("configured_trash_folder", configured_trash_folder);
         res.insert("mdns_enabled", mdns_enabled.to_string());
+        res.insert("mdns_batch_delay", mdns_batch_delay.to_string());
         res.insert("e2ee_enabled", e2ee_enabled.to_string());
         res.insert(
             "key_gen_type",
@@ -982,6 +1247,12 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "last_remote_backup",
+            self.get_config_i64(Config::LastRemoteBackup)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "last_cant_decrypt_outgoing_msgs",
             self.get_config_int(Config::LastCantDecryptOutgoingMsgs)
@@ -1030,6 +1301,18 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
             "gossip_period",
             self.get_config_int(Config::GossipPeriod).await?.to_string(),
         );
+        res.insert(
+            "large_group_size",
+            self.get_config_int(Config::LargeGroupSize)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "large_group_avatars",
+            self.get_config_bool(Config::LargeGroupAvatars)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "verified_one_on_one_chats",
             self.get_config_bool(Config::VerifiedOneOnOneChats)
@@ -1201,7 +1484,7 @@ pub async fn draft_self_report(&self) -> Result<ChatId> {
         );
         let fingerprint = public_key.dc_fingerprint();
         peerstate.set_verified(public_key, fingerprint, "".to_string())?;
-        peerstate.save_to_db(&self.sql).await?;
+        peerstate.save_to_db_ex(self, None).await?;
         chat_id
             .set_protection(self, ProtectionStatus::Protected, time(), Some(contact_id))
             .await?;
@@ -1235,6 +1518,7 @@ pub async fn get_fresh_msgs(&self) -> Result<Vec<MsgId>> {
                     "   AND m.chat_id>9",
                     "   AND ct.blocked=0",
                     "   AND c.blocked=0",
+                    "   AND c.pending_deletion=0",
                     "   AND NOT(c.muted_until=-1 OR c.muted_until>?)",
                     " ORDER BY m.timestamp DESC,m.id DESC;"
                 ),
@@ -1291,6 +1575,7 @@ pub async fn get_next_msgs(&self) -> Result<Vec<MsgId>> {
                        AND m.chat_id>9
                        AND ct.blocked=0
                        AND c.blocked!=1
+                       AND c.pending_deletion=0
                      ORDER BY m.id ASC",
                 (
                     last_msg_id.to_u32(), // Explicitly convert to u32 because 0 is allowed.
@@ -1389,6 +1674,7 @@ pub async fn search_msgs(&self, chat_id: Option<ChatId>, query: &str) -> Result<
                  WHERE m.chat_id>9
                    AND m.hidden=0
                    AND c.blocked!=1
+                   AND c.pending_deletion=0
                    AND ct.blocked=0
                    AND IFNULL(txt_normalized, txt) LIKE ?
                  ORDER BY m.id DESC LIMIT 1000",
@@ -2123,4 +2409,65 @@ async fn test_cache_is_cleared_when_io_is_started() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_on_network_changed() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert_eq!(t.get_network_kind(), NetworkKind::Unknown);
+        let generation = t.network_generation.load(Ordering::Relaxed);
+
+        t.on_network_changed(NetworkKind::Wifi).await;
+        assert_eq!(t.get_network_kind(), NetworkKind::Wifi);
+        assert_eq!(t.network_generation.load(Ordering::Relaxed), generation + 1);
+
+        // Losing the network does not invalidate connections,
+        // they are invalidated when the new network becomes available.
+        t.on_network_changed(NetworkKind::None).await;
+        assert_eq!(t.get_network_kind(), NetworkKind::None);
+        assert_eq!(t.network_generation.load(Ordering::Relaxed), generation + 1);
+
+        t.on_network_changed(NetworkKind::Cellular).await;
+        assert_eq!(t.network_generation.load(Ordering::Relaxed), generation + 2);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pause_io() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert!(t.get_io_pause_reasons().is_empty());
+        assert!(t.pause_io(" ").await.is_err());
+        assert!(t.resume_io("backup").await.is_err());
+
+        t.pause_io("backup").await?;
+        t.pause_io("battery saver").await?;
+        t.pause_io("backup").await?;
+        assert_eq!(t.get_io_pause_reasons(), ["backup", "battery saver"]);
+        assert!(!t.scheduler.is_running().await);
+        assert_eq!(
+            t.get_connectivity().await,
+            crate::scheduler::connectivity::Connectivity::NotConnected
+        );
+        assert!(t
+            .get_connectivity_html()
+            .await?
+            .contains("<li>Paused: battery saver</li>"));
+        t.set_stock_translation(
+            crate::stock_str::StockMessage::IoPaused,
+            "Pausiert: %1$s".to_string(),
+        )
+        .await?;
+        assert!(t
+            .get_connectivity_html()
+            .await?
+            .contains("<li>Pausiert: battery saver</li>"));
+
+        t.resume_io("backup").await?;
+        assert_eq!(t.get_io_pause_reasons(), ["backup", "battery saver"]);
+        t.resume_io("battery saver").await?;
+        assert_eq!(t.get_io_pause_reasons(), ["backup"]);
+        t.resume_io("backup").await?;
+        assert!(t.get_io_pause_reasons().is_empty());
+        assert!(t.resume_io("backup").await.is_err());
+        Ok(())
+    }
 }
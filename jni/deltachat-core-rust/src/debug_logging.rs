@@ -1,6 +1,7 @@
 //! Forward log messages to logging webxdc
 use crate::chat::ChatId;
 use crate::config::Config;
+use crate::contact::ContactId;
 use crate::context::Context;
 use crate::events::EventType;
 use crate::message::{Message, MsgId, Viewtype};
@@ -67,6 +68,7 @@ pub async fn debug_logging_loop(context: &Context, events: Receiver<DebugEventLo
                     notify: None,
                 },
                 time,
+                ContactId::SELF,
             )
             .await
         {
@@ -1,10 +1,13 @@
 //! End-to-end decryption support.
 
 use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
 
 use anyhow::Result;
 use deltachat_contact_tools::addr_cmp;
 use mailparse::ParsedMail;
+use tokio::task::JoinHandle;
 
 use crate::aheader::Aheader;
 use crate::context::Context;
@@ -29,6 +32,34 @@ pub fn try_decrypt(
     Ok(Some(msg))
 }
 
+/// Maximum number of messages decrypted in parallel ahead of time
+/// by [`spawn_decryption`].
+pub(crate) fn decryption_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .clamp(1, 8)
+}
+
+/// Starts decrypting the message on a blocking thread
+/// if it is structured as an Autocrypt message.
+///
+/// This is used to decrypt a burst of fetched messages on multiple cores
+/// while they are still waiting to be passed to `receive_imf()`.
+/// The result is passed to [`crate::mimeparser::MimeMessage::from_bytes`]
+/// instead of decrypting the message there again.
+/// Signatures are still checked when the message is received,
+/// because the keys to check them against may change with the previous messages.
+pub(crate) fn spawn_decryption(
+    body: &[u8],
+    private_keyring: Arc<Vec<SignedSecretKey>>,
+) -> Option<JoinHandle<Result<::pgp::composed::Message>>> {
+    let mail = mailparse::parse_mail(body).ok()?;
+    let data = get_encrypted_mime(&mail)?.get_body_raw().ok()?;
+    Some(tokio::task::spawn_blocking(move || {
+        pgp::pk_decrypt(data, &private_keyring)
+    }))
+}
+
 /// Returns a reference to the encrypted payload of a message.
 pub(crate) fn get_encrypted_mime<'a, 'b>(mail: &'a ParsedMail<'b>) -> Option<&'a ParsedMail<'b>> {
     get_autocrypt_mime(mail)
@@ -194,7 +225,7 @@ pub(crate) async fn get_autocrypt_peerstate(
             if addr_cmp(&peerstate.addr, from) {
                 if allow_change {
                     peerstate.apply_header(context, header, message_time);
-                    peerstate.save_to_db(&context.sql).await?;
+                    peerstate.save_to_db_ex(context, None).await?;
                 } else {
                     info!(
                         context,
@@ -210,7 +241,7 @@ pub(crate) async fn get_autocrypt_peerstate(
             // to the database.
         } else {
             let p = Peerstate::from_header(header, message_time);
-            p.save_to_db(&context.sql).await?;
+            p.save_to_db_ex(context, None).await?;
             peerstate = Some(p);
         }
     } else {
@@ -223,8 +254,10 @@ pub(crate) async fn get_autocrypt_peerstate(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::key::load_self_secret_keyring;
+    use crate::mimeparser::MimeMessage;
     use crate::receive_imf::receive_imf;
-    use crate::test_utils::TestContext;
+    use crate::test_utils::{TestContext, TestContextManager};
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_mixed_up_mime() -> Result<()> {
@@ -277,4 +310,30 @@ async fn test_mixed_up_mime_long() -> Result<()> {
         assert!(msg.id.get_html(&bob).await?.unwrap().len() > 40000);
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_spawn_decryption() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = tcm.send_recv_accept(bob, alice, "Hi Alice").await.chat_id;
+        let sent = alice.send_text(chat_id, "Hi Bob").await;
+
+        let private_keyring = Arc::new(load_self_secret_keyring(bob).await?);
+        assert!(
+            spawn_decryption(b"Subject: Plaintext\r\n\r\nHi", private_keyring.clone()).is_none()
+        );
+
+        let decryption = spawn_decryption(sent.payload().as_bytes(), private_keyring).unwrap();
+        let mime_message = MimeMessage::from_bytes_predecrypted(
+            bob,
+            sent.payload().as_bytes(),
+            None,
+            Some(decryption.await?),
+        )
+        .await?;
+        assert!(mime_message.was_encrypted());
+        assert_eq!(mime_message.parts[0].msg, "Hi Bob");
+        Ok(())
+    }
 }
@@ -12,6 +12,7 @@
 use crate::imap::session::Session;
 use crate::message::{Message, MsgId, Viewtype};
 use crate::mimeparser::{MimeMessage, Part};
+use crate::param::Param;
 use crate::tools::time;
 use crate::{chatlist_events, stock_str, EventType};
 
@@ -80,6 +81,9 @@ impl MsgId {
     /// Schedules full message download for partially downloaded message.
     pub async fn download_full(self, context: &Context) -> Result<()> {
         let msg = Message::load_from_db(context, self).await?;
+        if msg.is_removed_from_server() {
+            return Err(anyhow!("Message was removed from the server."));
+        }
         match msg.download_state() {
             DownloadState::Done | DownloadState::Undecipherable => {
                 return Err(anyhow!("Nothing to download."))
@@ -254,11 +258,17 @@ pub(crate) async fn create_stub_from_partial_download(
 
         info!(context, "Partial download: {}", text);
 
-        self.parts.push(Part {
+        let mut part = Part {
             typ: Viewtype::Text,
             msg: text,
             ..Default::default()
-        });
+        };
+        // The thumbnail of a video is sent in a header,
+        // so it can be shown before the video is downloaded.
+        if let Some(thumbnail) = self.store_video_thumbnail(context).await {
+            part.param.set(Param::Thumbnail, thumbnail);
+        }
+        self.parts.push(part);
 
         Ok(())
     }
@@ -266,11 +276,14 @@ pub(crate) async fn create_stub_from_partial_download(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use num_traits::FromPrimitive;
 
     use super::*;
     use crate::chat::{get_chat_msgs, send_msg};
     use crate::ephemeral::Timer;
+    use crate::imap::remove_vanished_uids;
     use crate::receive_imf::receive_imf_from_inbox;
     use crate::test_utils::TestContext;
 
@@ -313,6 +326,60 @@ async fn test_download_limit() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_download_removed_from_server() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat = t.create_chat_with_contact("Bob", "bob@example.org").await;
+
+        let mut msg = Message::new_text("Hi Bob".to_owned());
+        let msg_id = send_msg(&t, chat.id, &mut msg).await?;
+        msg_id
+            .update_download_state(&t, DownloadState::Available)
+            .await?;
+        assert!(!Message::load_from_db(&t, msg_id)
+            .await?
+            .is_removed_from_server());
+        assert_eq!(crate::message::get_removed_from_server_cnt(&t).await?, 0);
+
+        let rfc724_mid = Message::load_from_db(&t, msg_id).await?.rfc724_mid;
+        for (uid, rfc724_mid, target) in [
+            (1, rfc724_mid.as_str(), "INBOX"),
+            (2, "kept@example.org", "INBOX"),
+            (3, "moved@example.org", "DeltaChat"),
+        ] {
+            t.sql
+                .execute(
+                    "INSERT INTO imap (rfc724_mid, folder, uid, uidvalidity, target)
+                     VALUES (?, 'INBOX', ?, 1, ?)",
+                    (rfc724_mid, uid, target),
+                )
+                .await?;
+        }
+
+        // Only the message still on the server is kept,
+        // the message vanished while being moved by us is not flagged.
+        let server_uids = HashSet::from([2]);
+        assert_eq!(remove_vanished_uids(&t, "INBOX", 1, &server_uids).await?, 1);
+        assert_eq!(t.sql.count("SELECT COUNT(*) FROM imap", ()).await?, 1);
+        // Nothing is flagged twice.
+        assert_eq!(remove_vanished_uids(&t, "INBOX", 1, &server_uids).await?, 0);
+
+        let msg = Message::load_from_db(&t, msg_id).await?;
+        assert!(msg.is_removed_from_server());
+        assert_eq!(crate::message::get_removed_from_server_cnt(&t).await?, 1);
+        assert_eq!(
+            t.get_info().await?.get("messages_removed_from_server"),
+            Some(&"1".to_string())
+        );
+        assert!(msg_id.download_full(&t).await.is_err());
+        assert_eq!(
+            Message::load_from_db(&t, msg_id).await?.download_state(),
+            DownloadState::Available
+        );
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_update_download_state() -> Result<()> {
         let t = TestContext::new_alice().await;
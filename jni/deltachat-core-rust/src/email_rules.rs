@@ -0,0 +1,354 @@
+//! # Assignment rules for classic email.
+//!
+//! Rules set with [`set_email_rules`] decide whether incoming classic emails,
+//! i.e. messages neither sent by a messenger nor replying to a messenger message,
+//! are shown, hidden or routed into a single "Mailbox" chat.
+//! This way, e.g. newsletters can be collected in one place
+//! without creating a contact request for each of them.
+//!
+//! Rules are checked in order and the first matching rule applies.
+//! If no rule matches, [`Config::ShowEmails`] decides as usual.
+//!
+//! Rules are stored in [`Config::EmailRules`], one rule per line
+//! in the form `<condition> <value> <action>`, e.g. `list-id news.example.org mailbox`.
+
+use std::str::FromStr;
+
+use anyhow::{ensure, Result};
+use strum_macros::{Display, EnumString};
+
+use crate::chat::{self, ChatId, ProtectionStatus};
+use crate::config::Config;
+use crate::constants::{Blocked, Chattype};
+use crate::context::Context;
+use crate::headerdef::HeaderDef;
+use crate::mimeparser::MimeMessage;
+use crate::receive_imf::mailinglist_header_listid;
+use crate::stock_str;
+use crate::tools::time;
+
+/// Group ID of the mailbox chat.
+///
+/// Contains a colon so that it cannot clash with the ID of a mailing list.
+pub(crate) const MAILBOX_GRPID: &str = "dc:mailbox";
+
+/// What an [`EmailRule`] is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum EmailRuleCondition {
+    /// Domain of the sender address, e.g. `example.org`.
+    SenderDomain,
+
+    /// ID of the mailing list from the `List-Id` header, e.g. `news.example.org`.
+    ListId,
+
+    /// Suffix of an own address the email is sent to,
+    /// e.g. `shop` for emails sent to `alice+shop@example.org`.
+    PlusSuffix,
+}
+
+/// What happens to a classic email matched by an [`EmailRule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum EmailRuleAction {
+    /// Show the email as if [`Config::ShowEmails`] was set to show all emails.
+    Show,
+
+    /// Do not show the email.
+    Hide,
+
+    /// Show the email in the mailbox chat, see [`get_mailbox_chat`].
+    Mailbox,
+}
+
+/// Rule assigning incoming classic emails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailRule {
+    /// What the rule is matched against.
+    pub condition: EmailRuleCondition,
+
+    /// Value to match, compared case-insensitively.
+    pub value: String,
+
+    /// What happens to matching emails.
+    pub action: EmailRuleAction,
+}
+
+impl EmailRule {
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let condition = EmailRuleCondition::from_str(parts.next()?).ok()?;
+        let value = parts.next()?.to_lowercase();
+        let action = EmailRuleAction::from_str(parts.next()?).ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            condition,
+            value,
+            action,
+        })
+    }
+}
+
+/// Properties of an incoming email the rules are matched against.
+#[derive(Debug, Default)]
+struct EmailProperties {
+    sender_domain: String,
+    list_id: Option<String>,
+    plus_suffixes: Vec<String>,
+}
+
+impl EmailProperties {
+    async fn new(context: &Context, mime_parser: &MimeMessage) -> Result<Self> {
+        let sender_domain = mime_parser
+            .from
+            .addr
+            .rsplit_once('@')
+            .map(|(_local, domain)| domain.to_lowercase())
+            .unwrap_or_default();
+        let list_id = match mime_parser.get_header(HeaderDef::ListId) {
+            Some(list_id) => Some(mailinglist_header_listid(list_id)?.to_lowercase()),
+            None => None,
+        };
+        let mut plus_suffixes = Vec::new();
+        for recipient in &mime_parser.recipients {
+            let Some((local, domain)) = recipient.addr.rsplit_once('@') else {
+                continue;
+            };
+            let Some((base, suffix)) = local.split_once('+') else {
+                continue;
+            };
+            if context.is_self_addr(&format!("{base}@{domain}")).await? {
+                plus_suffixes.push(suffix.to_lowercase());
+            }
+        }
+        Ok(Self {
+            sender_domain,
+            list_id,
+            plus_suffixes,
+        })
+    }
+
+    fn matches(&self, rule: &EmailRule) -> bool {
+        match rule.condition {
+            EmailRuleCondition::SenderDomain => self.sender_domain == rule.value,
+            EmailRuleCondition::ListId => self.list_id.as_ref() == Some(&rule.value),
+            EmailRuleCondition::PlusSuffix => self.plus_suffixes.contains(&rule.value),
+        }
+    }
+}
+
+/// Returns the email assignment rules.
+pub async fn get_email_rules(context: &Context) -> Result<Vec<EmailRule>> {
+    Ok(context
+        .get_config(Config::EmailRules)
+        .await?
+        .unwrap_or_default()
+        .lines()
+        .filter_map(EmailRule::parse)
+        .collect())
+}
+
+/// Replaces the email assignment rules with `rules`.
+///
+/// Only emails received afterwards are assigned according to the new rules.
+pub async fn set_email_rules(context: &Context, rules: &[EmailRule]) -> Result<()> {
+    let mut lines = Vec::new();
+    for rule in rules {
+        let value = rule.value.trim().to_lowercase();
+        ensure!(
+            !value.is_empty() && !value.contains(char::is_whitespace),
+            "Invalid email rule value {:?}",
+            rule.value
+        );
+        lines.push(format!("{} {value} {}", rule.condition, rule.action));
+    }
+    let value = lines.join("\n");
+    context
+        .set_config(
+            Config::EmailRules,
+            Some(value.as_str()).filter(|v| !v.is_empty()),
+        )
+        .await
+}
+
+/// Returns the action of the first rule matching the incoming classic email,
+/// `None` if no rule matches.
+pub(crate) async fn lookup_action(
+    context: &Context,
+    mime_parser: &MimeMessage,
+) -> Result<Option<EmailRuleAction>> {
+    let rules = get_email_rules(context).await?;
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let properties = EmailProperties::new(context, mime_parser).await?;
+    Ok(rules
+        .iter()
+        .find(|rule| properties.matches(rule))
+        .map(|rule| rule.action))
+}
+
+/// Returns the mailbox chat collecting emails routed by rules with [`EmailRuleAction::Mailbox`],
+/// `None` if no email was routed there yet.
+pub async fn get_mailbox_chat(context: &Context) -> Result<Option<ChatId>> {
+    Ok(chat::get_chat_id_by_grpid(context, MAILBOX_GRPID)
+        .await?
+        .map(|(chat_id, _protected, _blocked)| chat_id))
+}
+
+/// Returns the mailbox chat, creating it if needed.
+pub(crate) async fn get_or_create_mailbox_chat(context: &Context) -> Result<(ChatId, Blocked)> {
+    if let Some((chat_id, _protected, blocked)) =
+        chat::get_chat_id_by_grpid(context, MAILBOX_GRPID).await?
+    {
+        return Ok((chat_id, blocked));
+    }
+    let chat_id = ChatId::create_multiuser_record(
+        context,
+        Chattype::Mailinglist,
+        MAILBOX_GRPID,
+        &stock_str::mailbox(context).await,
+        Blocked::Not,
+        ProtectionStatus::Unprotected,
+        None,
+        time(),
+    )
+    .await?;
+    info!(context, "Created mailbox chat {chat_id}.");
+    Ok((chat_id, Blocked::Not))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::Chat;
+    use crate::constants::DC_CHAT_ID_TRASH;
+    use crate::receive_imf::receive_imf;
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_parse_email_rule() {
+        assert_eq!(
+            EmailRule::parse("list-id News.example.org mailbox"),
+            Some(EmailRule {
+                condition: EmailRuleCondition::ListId,
+                value: "news.example.org".to_string(),
+                action: EmailRuleAction::Mailbox,
+            })
+        );
+        assert!(EmailRule::parse("sender-domain example.org").is_none());
+        assert!(EmailRule::parse("subject foo hide").is_none());
+        assert!(EmailRule::parse("plus-suffix shop hide extra").is_none());
+    }
+
+    async fn receive_email(t: &TestContext, from: &str, to: &str, extra: &str, n: u32) {
+        let imf = format!(
+            "From: {from}\n\
+             To: {to}\n\
+             Subject: Offer {n}\n\
+             Message-ID: <offer{n}@example.net>\n\
+             Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+             {extra}\
+             \n\
+             Great offer!\n"
+        );
+        receive_imf(t, imf.as_bytes(), false).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_email_rules() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        set_email_rules(
+            &t,
+            &[
+                EmailRule {
+                    condition: EmailRuleCondition::ListId,
+                    value: "News.example.net".to_string(),
+                    action: EmailRuleAction::Mailbox,
+                },
+                EmailRule {
+                    condition: EmailRuleCondition::PlusSuffix,
+                    value: "shop".to_string(),
+                    action: EmailRuleAction::Mailbox,
+                },
+                EmailRule {
+                    condition: EmailRuleCondition::SenderDomain,
+                    value: "spam.example.net".to_string(),
+                    action: EmailRuleAction::Hide,
+                },
+            ],
+        )
+        .await?;
+        assert_eq!(get_email_rules(&t).await?.len(), 3);
+        assert!(get_mailbox_chat(&t).await?.is_none());
+
+        receive_email(
+            &t,
+            "news@example.net",
+            "alice@example.org",
+            "List-Id: Newsletter <news.example.net>\n",
+            1,
+        )
+        .await;
+        let msg = t.get_last_msg().await;
+        let mailbox_chat_id = get_mailbox_chat(&t).await?.unwrap();
+        assert_eq!(msg.chat_id, mailbox_chat_id);
+        let chat = Chat::load_from_db(&t, mailbox_chat_id).await?;
+        assert_eq!(chat.get_name(), "Mailbox");
+        assert_eq!(chat.blocked, Blocked::Not);
+        assert!(!chat.can_send(&t).await?);
+
+        receive_email(&t, "shop@example.com", "alice+Shop@example.org", "", 2).await;
+        assert_eq!(t.get_last_msg().await.chat_id, mailbox_chat_id);
+        assert_eq!(
+            Chat::load_from_db(&t, mailbox_chat_id).await?.get_name(),
+            "Mailbox"
+        );
+
+        receive_email(&t, "offers@spam.example.net", "alice@example.org", "", 3).await;
+        let chat_id: ChatId = t
+            .sql
+            .query_get_value(
+                "SELECT chat_id FROM msgs WHERE rfc724_mid='offer3@example.net'",
+                (),
+            )
+            .await?
+            .unwrap();
+        assert_eq!(chat_id, DC_CHAT_ID_TRASH);
+
+        // Emails not matching any rule are shown as configured.
+        receive_email(&t, "bob@example.net", "alice@example.org", "", 4).await;
+        let msg = t.get_last_msg().await;
+        assert_ne!(msg.chat_id, mailbox_chat_id);
+        assert_eq!(msg.get_subject(), "Offer 4");
+
+        // Emails cannot be sent to the mailbox chat with a forged `List-Id`.
+        receive_email(
+            &t,
+            "bob@example.net",
+            "alice@example.org",
+            "List-Id: <dc:mailbox>\n",
+            5,
+        )
+        .await;
+        let msg = t.get_last_msg().await;
+        assert_ne!(msg.chat_id, mailbox_chat_id);
+        assert_eq!(msg.get_subject(), "Offer 5");
+
+        assert!(set_email_rules(
+            &t,
+            &[EmailRule {
+                condition: EmailRuleCondition::SenderDomain,
+                value: "example net".to_string(),
+                action: EmailRuleAction::Show,
+            }],
+        )
+        .await
+        .is_err());
+        set_email_rules(&t, &[]).await?;
+        assert!(get_email_rules(&t).await?.is_empty());
+        Ok(())
+    }
+}
@@ -61,6 +61,20 @@
 //! Server deletion happens by updating the `imap` table based on
 //! the database entries which are expired either according to their
 //! ephemeral message timers or global `delete_server_after` setting.
+//!
+//! ## Retention policies and legal hold
+//!
+//! Each chat may additionally have a local [`RetentionPolicy`]
+//! limiting the age or the number of messages kept on the device.
+//! Unlike the ephemeral timer, the policy is not sent to other chat members.
+//!
+//! Chats and contacts can be exempted from `delete_device_after`
+//! with [`ChatId::set_auto_delete_exempt`] and [`ContactId::set_auto_delete_exempt`],
+//! the latter keeping the 1:1 chat with the contact.
+//!
+//! If the `legal_hold` setting is enabled, no messages are deleted automatically,
+//! neither locally nor from the server.
+//! Deletion resumes once the legal hold is lifted.
 
 use std::cmp::max;
 use std::collections::BTreeSet;
@@ -75,7 +89,8 @@
 use tokio::time::timeout;
 
 use crate::chat::{send_msg, ChatId, ChatIdBlocked};
-use crate::constants::{DC_CHAT_ID_LAST_SPECIAL, DC_CHAT_ID_TRASH};
+use crate::config::Config;
+use crate::constants::{Chattype, DC_CHAT_ID_LAST_SPECIAL, DC_CHAT_ID_TRASH};
 use crate::contact::ContactId;
 use crate::context::Context;
 use crate::download::MIN_DELETE_SERVER_AFTER;
@@ -87,6 +102,7 @@
 use crate::sql::{self, params_iter};
 use crate::stock_str;
 use crate::tools::{duration_to_str, time, SystemTime};
+use crate::undo;
 
 /// Ephemeral timer value.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
@@ -238,6 +254,139 @@ pub async fn set_ephemeral_timer(self, context: &Context, timer: Timer) -> Resul
     }
 }
 
+/// Local history retention policy of a chat.
+///
+/// The policy only applies to this device and is not sent to other chat members.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Delete messages older than this number of days, 0 means no limit.
+    pub keep_days: u32,
+
+    /// Keep only this number of the newest messages, 0 means no limit.
+    pub keep_last: u32,
+}
+
+impl ChatId {
+    /// Returns the local history retention policy of the chat.
+    pub async fn get_retention_policy(self, context: &Context) -> Result<RetentionPolicy> {
+        context
+            .sql
+            .query_row_optional(
+                "SELECT retention_days, retention_count FROM chats WHERE id=?",
+                (self,),
+                |row| {
+                    Ok(RetentionPolicy {
+                        keep_days: row.get(0)?,
+                        keep_last: row.get(1)?,
+                    })
+                },
+            )
+            .await?
+            .with_context(|| format!("Chat {self} not found"))
+    }
+
+    /// Sets the local history retention policy of the chat.
+    ///
+    /// Messages exceeding the policy are deleted from the device
+    /// by the ephemeral loop unless `Config::LegalHold` is set.
+    pub async fn set_retention_policy(
+        self,
+        context: &Context,
+        policy: RetentionPolicy,
+    ) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        context
+            .sql
+            .execute(
+                "UPDATE chats SET retention_days=?, retention_count=? WHERE id=?",
+                (policy.keep_days, policy.keep_last, self),
+            )
+            .await?;
+        context.emit_event(EventType::ChatModified(self));
+        context.scheduler.interrupt_ephemeral_task().await;
+        Ok(())
+    }
+}
+
+impl ChatId {
+    /// Returns whether the chat is exempted from the `delete_device_after` setting.
+    pub async fn is_auto_delete_exempt(self, context: &Context) -> Result<bool> {
+        context
+            .sql
+            .query_row_optional(
+                "SELECT auto_delete_exempt FROM chats WHERE id=?",
+                (self,),
+                |row| row.get::<_, bool>(0),
+            )
+            .await?
+            .with_context(|| format!("Chat {self} not found"))
+    }
+
+    /// Exempts the chat from the `delete_device_after` setting
+    /// or removes the exemption.
+    ///
+    /// The retention policy and the ephemeral timer of the chat still apply.
+    pub async fn set_auto_delete_exempt(self, context: &Context, exempt: bool) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid chat ID");
+        context
+            .sql
+            .execute(
+                "UPDATE chats SET auto_delete_exempt=? WHERE id=?",
+                (exempt, self),
+            )
+            .await?;
+        context.emit_event(EventType::ChatModified(self));
+        context.scheduler.interrupt_ephemeral_task().await;
+        Ok(())
+    }
+}
+
+impl ContactId {
+    /// Returns whether the 1:1 chat with the contact is exempted
+    /// from the `delete_device_after` setting.
+    pub async fn is_auto_delete_exempt(self, context: &Context) -> Result<bool> {
+        context
+            .sql
+            .query_row_optional(
+                "SELECT auto_delete_exempt FROM contacts WHERE id=?",
+                (self,),
+                |row| row.get::<_, bool>(0),
+            )
+            .await?
+            .with_context(|| format!("Contact {self} not found"))
+    }
+
+    /// Exempts the 1:1 chat with the contact from the `delete_device_after` setting
+    /// or removes the exemption.
+    ///
+    /// Unlike [`ChatId::set_auto_delete_exempt`], the exemption persists
+    /// if the 1:1 chat is deleted and created again.
+    pub async fn set_auto_delete_exempt(self, context: &Context, exempt: bool) -> Result<()> {
+        ensure!(!self.is_special(), "Invalid contact ID");
+        context
+            .sql
+            .execute(
+                "UPDATE contacts SET auto_delete_exempt=? WHERE id=?",
+                (exempt, self),
+            )
+            .await?;
+        context.emit_event(EventType::ContactsChanged(Some(self)));
+        context.scheduler.interrupt_ephemeral_task().await;
+        Ok(())
+    }
+}
+
+/// Returns true if any chat has a retention policy limiting the number of messages.
+///
+/// New messages may exceed the limit, so the ephemeral loop
+/// should be interrupted when they arrive.
+pub(crate) async fn has_retention_count_policies(context: &Context) -> Result<bool> {
+    context
+        .sql
+        .exists("SELECT COUNT(*) FROM chats WHERE retention_count>0", ())
+        .await
+}
+
 /// Returns a stock message saying that ephemeral timer is changed to `timer` by `from_id`.
 pub(crate) async fn stock_ephemeral_timer_changed(
     context: &Context,
@@ -398,20 +547,27 @@ async fn select_expired_messages(
             .sql
             .query_map(
                 r#"
-SELECT id, chat_id, type, location_id
-FROM msgs
+SELECT m.id AS id, m.chat_id AS chat_id, m.type AS type, m.location_id AS location_id
+FROM msgs m
+INNER JOIN chats c ON c.id=m.chat_id
 WHERE
-  timestamp < ?1
-  AND timestamp_rcvd < ?1
-  AND chat_id > ?
-  AND chat_id != ?
-  AND chat_id != ?
+  m.timestamp < ?1
+  AND m.timestamp_rcvd < ?1
+  AND m.chat_id > ?2
+  AND m.chat_id != ?3
+  AND m.chat_id != ?4
+  AND c.auto_delete_exempt=0
+  AND NOT (c.type=?5 AND EXISTS (
+    SELECT 1 FROM chats_contacts cc
+    INNER JOIN contacts ct ON ct.id=cc.contact_id
+    WHERE cc.chat_id=c.id AND ct.auto_delete_exempt=1))
 "#,
                 (
                     threshold_timestamp,
                     DC_CHAT_ID_LAST_SPECIAL,
                     self_chat_id,
                     device_chat_id,
+                    Chattype::Single,
                 ),
                 |row| {
                     let id: MsgId = row.get("id")?;
@@ -427,6 +583,65 @@ async fn select_expired_messages(
         rows.extend(rows_expired);
     }
 
+    let rows_retention_days = context
+        .sql
+        .query_map(
+            r#"
+SELECT m.id AS id, m.chat_id AS chat_id, m.type AS type, m.location_id AS location_id
+FROM msgs m
+INNER JOIN chats c ON c.id=m.chat_id
+WHERE
+  c.retention_days > 0
+  AND m.timestamp < ?1 - c.retention_days * 86400
+  AND m.timestamp_rcvd < ?1 - c.retention_days * 86400
+  AND m.chat_id > ?2
+"#,
+            (now, DC_CHAT_ID_LAST_SPECIAL),
+            |row| {
+                let id: MsgId = row.get("id")?;
+                let chat_id: ChatId = row.get("chat_id")?;
+                let viewtype: Viewtype = row.get("type")?;
+                let location_id: u32 = row.get("location_id")?;
+                Ok((id, chat_id, viewtype, location_id))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    rows.extend(rows_retention_days);
+
+    let rows_retention_count = context
+        .sql
+        .query_map(
+            r#"
+SELECT id, chat_id, type, location_id
+FROM (
+  SELECT m.id AS id, m.chat_id AS chat_id, m.type AS type, m.location_id AS location_id,
+    c.retention_count AS retention_count,
+    ROW_NUMBER() OVER (PARTITION BY m.chat_id ORDER BY m.timestamp DESC, m.id DESC) AS n
+  FROM msgs m
+  INNER JOIN chats c ON c.id=m.chat_id
+  WHERE c.retention_count > 0
+    AND m.chat_id > ?
+)
+WHERE n > retention_count
+"#,
+            (DC_CHAT_ID_LAST_SPECIAL,),
+            |row| {
+                let id: MsgId = row.get("id")?;
+                let chat_id: ChatId = row.get("chat_id")?;
+                let viewtype: Viewtype = row.get("type")?;
+                let location_id: u32 = row.get("location_id")?;
+                Ok((id, chat_id, viewtype, location_id))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    rows.extend(rows_retention_count);
+
+    // A message may be expired for several reasons.
+    rows.sort_unstable_by_key(|(id, ..)| *id);
+    rows.dedup_by_key(|(id, ..)| *id);
+
     Ok(rows)
 }
 
@@ -436,6 +651,9 @@ async fn select_expired_messages(
 /// Emits relevant `MsgsChanged` and `WebxdcInstanceDeleted` events
 /// if messages are deleted.
 pub(crate) async fn delete_expired_messages(context: &Context, now: i64) -> Result<()> {
+    if context.get_config_bool(Config::LegalHold).await? {
+        return Ok(());
+    }
     let rows = select_expired_messages(context, now).await?;
 
     if !rows.is_empty() {
@@ -510,14 +728,25 @@ async fn next_delete_device_after_timestamp(context: &Context) -> Result<Option<
             .sql
             .query_get_value(
                 r#"
-                SELECT min(max(timestamp, timestamp_rcvd))
-                FROM msgs
-                WHERE chat_id > ?
-                  AND chat_id != ?
-                  AND chat_id != ?
+                SELECT min(max(m.timestamp, m.timestamp_rcvd))
+                FROM msgs m
+                INNER JOIN chats c ON c.id=m.chat_id
+                WHERE m.chat_id > ?
+                  AND m.chat_id != ?
+                  AND m.chat_id != ?
+                  AND c.auto_delete_exempt=0
+                  AND NOT (c.type=? AND EXISTS (
+                    SELECT 1 FROM chats_contacts cc
+                    INNER JOIN contacts ct ON ct.id=cc.contact_id
+                    WHERE cc.chat_id=c.id AND ct.auto_delete_exempt=1))
                 HAVING count(*) > 0
                 "#,
-                (DC_CHAT_ID_TRASH, self_chat_id, device_chat_id),
+                (
+                    DC_CHAT_ID_TRASH,
+                    self_chat_id,
+                    device_chat_id,
+                    Chattype::Single,
+                ),
             )
             .await?;
 
@@ -527,11 +756,56 @@ async fn next_delete_device_after_timestamp(context: &Context) -> Result<Option<
     }
 }
 
+/// Calculates the next timestamp when a message will be deleted
+/// due to a chat retention policy.
+async fn next_retention_timestamp(context: &Context, now: i64) -> Result<Option<i64>> {
+    let count_exceeded = context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM chats c
+             WHERE c.retention_count>0
+             AND c.retention_count<(SELECT COUNT(*) FROM msgs WHERE chat_id=c.id)",
+            (),
+        )
+        .await?;
+    if count_exceeded {
+        return Ok(Some(now));
+    }
+
+    let retention_days_timestamp: Option<i64> = context
+        .sql
+        .query_get_value(
+            r#"
+            SELECT min(max(m.timestamp, m.timestamp_rcvd) + c.retention_days * 86400)
+            FROM msgs m
+            INNER JOIN chats c ON c.id=m.chat_id
+            WHERE c.retention_days > 0
+              AND m.chat_id > ?
+            HAVING count(*) > 0
+            "#,
+            (DC_CHAT_ID_LAST_SPECIAL,),
+        )
+        .await?;
+    Ok(retention_days_timestamp)
+}
+
 /// Calculates next timestamp when expiration of some message will happen.
 ///
-/// Expiration can happen either because user has set `delete_device_after` setting or because the
-/// message itself has an ephemeral timer.
+/// Expiration can happen either because user has set `delete_device_after` setting,
+/// because the message itself has an ephemeral timer
+/// or because of the retention policy of the chat.
+///
+/// Returns `None` while `Config::LegalHold` is set.
 async fn next_expiration_timestamp(context: &Context) -> Option<i64> {
+    match context.get_config_bool(Config::LegalHold).await {
+        Ok(false) => {}
+        Ok(true) => return None,
+        Err(err) => {
+            warn!(context, "Can't get legal hold setting: {err:#}.");
+            return None;
+        }
+    }
+
     let ephemeral_timestamp: Option<i64> = match context
         .sql
         .query_get_value(
@@ -565,9 +839,23 @@ async fn next_expiration_timestamp(context: &Context) -> Option<i64> {
             Ok(timestamp) => timestamp,
         };
 
+    let retention_timestamp = next_retention_timestamp(context, time())
+        .await
+        .context("Can't calculate timestamp of the next retention policy expiration")
+        .log_err(context)
+        .unwrap_or_default();
+
+    let purge_timestamp = undo::next_purge_timestamp(context)
+        .await
+        .context("Can't calculate timestamp of the next deletion purge")
+        .log_err(context)
+        .unwrap_or_default();
+
     ephemeral_timestamp
         .into_iter()
         .chain(delete_device_after_timestamp)
+        .chain(retention_timestamp)
+        .chain(purge_timestamp)
         .min()
 }
 
@@ -618,11 +906,19 @@ pub(crate) async fn ephemeral_loop(context: &Context, interrupt_receiver: Receiv
             .await
             .log_err(context)
             .ok();
+
+        undo::purge_pending_deletions(context, time())
+            .await
+            .log_err(context)
+            .ok();
     }
 }
 
 /// Schedules expired IMAP messages for deletion.
 pub(crate) async fn delete_expired_imap_messages(context: &Context) -> Result<()> {
+    if context.get_config_bool(Config::LegalHold).await? {
+        return Ok(());
+    }
     let now = time();
 
     let (threshold_timestamp, threshold_timestamp_extended) =
@@ -695,7 +991,6 @@ pub(crate) async fn start_ephemeral_timers(context: &Context) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
     use crate::download::DownloadState;
     use crate::location;
     use crate::message::markseen_msgs;
@@ -1416,6 +1711,104 @@ async fn test_ephemeral_poi_location() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_retention_policy() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat = t.create_chat_with_contact("", "bob@example.net").await;
+        assert_eq!(
+            chat.id.get_retention_policy(&t).await?,
+            RetentionPolicy::default()
+        );
+
+        let msg1 = t.send_text(chat.id, "First").await.sender_msg_id;
+        let msg2 = t.send_text(chat.id, "Second").await.sender_msg_id;
+        let msg3 = t.send_text(chat.id, "Third").await.sender_msg_id;
+
+        let policy = RetentionPolicy {
+            keep_days: 0,
+            keep_last: 2,
+        };
+        chat.id.set_retention_policy(&t, policy).await?;
+        assert_eq!(chat.id.get_retention_policy(&t).await?, policy);
+
+        // Nothing is deleted under legal hold.
+        t.set_config_bool(Config::LegalHold, true).await?;
+        assert_eq!(next_expiration_timestamp(&t).await, None);
+        delete_expired_messages(&t, time()).await?;
+        assert_eq!(Message::load_from_db(&t, msg1).await?.chat_id, chat.id);
+
+        t.set_config_bool(Config::LegalHold, false).await?;
+        assert!(next_expiration_timestamp(&t).await.is_some());
+        delete_expired_messages(&t, time()).await?;
+        assert!(Message::load_from_db_optional(&t, msg1).await?.is_none());
+        assert_eq!(Message::load_from_db(&t, msg2).await?.chat_id, chat.id);
+        assert_eq!(Message::load_from_db(&t, msg3).await?.chat_id, chat.id);
+
+        let policy = RetentionPolicy {
+            keep_days: 1,
+            keep_last: 0,
+        };
+        chat.id.set_retention_policy(&t, policy).await?;
+        delete_expired_messages(&t, time()).await?;
+        assert_eq!(Message::load_from_db(&t, msg2).await?.chat_id, chat.id);
+        delete_expired_messages(&t, time() + 2 * 86400).await?;
+        assert!(Message::load_from_db_optional(&t, msg2).await?.is_none());
+        assert!(Message::load_from_db_optional(&t, msg3).await?.is_none());
+
+        assert!(ChatId::new(12345).get_retention_policy(&t).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_auto_delete_exempt() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let bob_chat = t.create_chat_with_contact("", "bob@example.net").await;
+        let fiona_chat = t.create_chat_with_contact("", "fiona@example.net").await;
+        let charlie_chat = t.create_chat_with_contact("", "charlie@example.net").await;
+        let bob_msg = t.send_text(bob_chat.id, "Hi Bob").await.sender_msg_id;
+        let fiona_msg = t.send_text(fiona_chat.id, "Hi Fiona").await.sender_msg_id;
+        let charlie_msg = t
+            .send_text(charlie_chat.id, "Hi Charlie")
+            .await
+            .sender_msg_id;
+        t.set_config(Config::DeleteDeviceAfter, Some("86400"))
+            .await?;
+
+        let bob_id = chat::get_chat_contacts(&t, bob_chat.id).await?[0];
+        assert!(!bob_id.is_auto_delete_exempt(&t).await?);
+        bob_id.set_auto_delete_exempt(&t, true).await?;
+        assert!(bob_id.is_auto_delete_exempt(&t).await?);
+        fiona_chat.id.set_auto_delete_exempt(&t, true).await?;
+        assert!(fiona_chat.id.is_auto_delete_exempt(&t).await?);
+        assert!(!charlie_chat.id.is_auto_delete_exempt(&t).await?);
+
+        let now = time() + 2 * 86400;
+        delete_expired_messages(&t, now).await?;
+        assert_eq!(
+            Message::load_from_db(&t, bob_msg).await?.chat_id,
+            bob_chat.id
+        );
+        assert_eq!(
+            Message::load_from_db(&t, fiona_msg).await?.chat_id,
+            fiona_chat.id
+        );
+        assert!(Message::load_from_db_optional(&t, charlie_msg)
+            .await?
+            .is_none());
+
+        // Exempted messages do not cause the ephemeral loop to wake up.
+        assert_eq!(next_delete_device_after_timestamp(&t).await?, None);
+
+        bob_id.set_auto_delete_exempt(&t, false).await?;
+        delete_expired_messages(&t, now).await?;
+        assert!(Message::load_from_db_optional(&t, bob_msg).await?.is_none());
+        assert_eq!(
+            Message::load_from_db(&t, fiona_msg).await?.chat_id,
+            fiona_chat.id
+        );
+        Ok(())
+    }
+
     /// Tests that `.get_ephemeral_timer()` returns an error for invalid chat ID.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_get_ephemeral_timer_wrong_chat_id() -> Result<()> {
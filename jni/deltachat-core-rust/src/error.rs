@@ -0,0 +1,268 @@
+//! # Classification of errors for API bindings.
+//!
+//! Most functions of the core return [`anyhow::Error`]s
+//! whose messages are meant to be shown to the user.
+//! [`classify`] inspects the chain of an error
+//! so that bindings can tell e.g. network failures from rejected credentials
+//! and decide whether to retry without parsing the message.
+
+use std::error::Error as StdError;
+use std::io;
+
+/// Kind of an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The error could not be classified.
+    Other,
+
+    /// The network is unavailable or the connection failed,
+    /// e.g. because of a failed DNS lookup or a timeout.
+    Network,
+
+    /// Local I/O error, e.g. a missing file or a full disk.
+    Io,
+
+    /// The server rejected the credentials.
+    Authentication,
+
+    /// The IMAP server returned an error.
+    Imap,
+
+    /// The SMTP server returned an error.
+    Smtp,
+}
+
+/// Classification of an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorInfo {
+    /// Kind of the error.
+    pub kind: ErrorKind,
+
+    /// True if the same operation may succeed when retried later.
+    pub retryable: bool,
+
+    /// Underlying error code:
+    /// the OS error number for I/O and network errors,
+    /// the response code such as `AUTHENTICATIONFAILED` for IMAP errors, see RFC 5530,
+    /// or the reply code for SMTP errors.
+    pub code: Option<String>,
+}
+
+impl ErrorInfo {
+    const OTHER: ErrorInfo = ErrorInfo {
+        kind: ErrorKind::Other,
+        retryable: false,
+        code: None,
+    };
+}
+
+/// Error whose message replaced the message of an underlying error,
+/// e.g. with a translated one.
+///
+/// The underlying error is not kept as a source
+/// so that it does not appear in the message,
+/// only its classification is kept.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub(crate) struct ClassifiedError {
+    info: ErrorInfo,
+    message: String,
+}
+
+impl ClassifiedError {
+    /// Returns an error with the message `message`
+    /// and the classification of `err`.
+    pub(crate) fn with_message(err: &anyhow::Error, message: String) -> anyhow::Error {
+        anyhow::Error::new(ClassifiedError {
+            info: classify(err),
+            message,
+        })
+    }
+}
+
+/// Classifies an error by the first error in its chain
+/// that is known to the core.
+pub fn classify(err: &anyhow::Error) -> ErrorInfo {
+    err.chain()
+        .find_map(classify_single)
+        .unwrap_or(ErrorInfo::OTHER)
+}
+
+fn classify_single(err: &(dyn StdError + 'static)) -> Option<ErrorInfo> {
+    if let Some(err) = err.downcast_ref::<ClassifiedError>() {
+        Some(err.info.clone())
+    } else if let Some(err) = err.downcast_ref::<io::Error>() {
+        Some(classify_io(err))
+    } else if err.is::<tokio::time::error::Elapsed>() {
+        Some(ErrorInfo {
+            kind: ErrorKind::Network,
+            retryable: true,
+            code: None,
+        })
+    } else if let Some(err) = err.downcast_ref::<async_imap::error::Error>() {
+        match err {
+            async_imap::error::Error::No(text) | async_imap::error::Error::Bad(text) => {
+                let code = imap_response_code(text);
+                let (kind, retryable) = match code {
+                    Some("AUTHENTICATIONFAILED" | "AUTHORIZATIONFAILED" | "EXPIRED") => {
+                        (ErrorKind::Authentication, false)
+                    }
+                    Some("UNAVAILABLE" | "INUSE") => (ErrorKind::Imap, true),
+                    _ => (ErrorKind::Imap, false),
+                };
+                Some(ErrorInfo {
+                    kind,
+                    retryable,
+                    code: code.map(str::to_string),
+                })
+            }
+            async_imap::error::Error::ConnectionLost => Some(ErrorInfo {
+                kind: ErrorKind::Network,
+                retryable: true,
+                code: None,
+            }),
+            // Other errors, e.g. I/O errors, are classified by their source.
+            _ => None,
+        }
+    } else if let Some(err) = err.downcast_ref::<async_smtp::error::Error>() {
+        match err {
+            async_smtp::error::Error::Transient(response) => Some(ErrorInfo {
+                kind: ErrorKind::Smtp,
+                retryable: true,
+                code: Some(response.code.to_string()),
+            }),
+            async_smtp::error::Error::Permanent(response) => {
+                let code = response.code.to_string();
+                Some(ErrorInfo {
+                    // 535 is "Authentication credentials invalid", see RFC 4954.
+                    kind: match code.as_str() {
+                        "535" => ErrorKind::Authentication,
+                        _ => ErrorKind::Smtp,
+                    },
+                    retryable: false,
+                    code: Some(code),
+                })
+            }
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Returns the response code of a tagged IMAP response, e.g. `AUTHENTICATIONFAILED`
+/// for `NO [AUTHENTICATIONFAILED] Invalid credentials`.
+///
+/// `async-imap` only parses the response codes of RFC 3501,
+/// other codes remain at the start of the response text,
+/// which is formatted into the error message as `info: Some("...")`.
+fn imap_response_code(text: &str) -> Option<&str> {
+    let info = text
+        .split_once(r#"info: Some(""#)
+        .map_or(text, |(_, info)| info);
+    let (code, _) = info.strip_prefix('[')?.split_once(']')?;
+    // Response codes may have arguments, e.g. `[BADCHARSET (UTF-8)]`.
+    let code = code.split(' ').next()?;
+    (!code.is_empty() && code.bytes().all(|b| b.is_ascii_uppercase() || b == b'-')).then_some(code)
+}
+
+fn classify_io(err: &io::Error) -> ErrorInfo {
+    let network = matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::UnexpectedEof
+    );
+    ErrorInfo {
+        kind: match network {
+            true => ErrorKind::Network,
+            false => ErrorKind::Io,
+        },
+        retryable: network || err.kind() == io::ErrorKind::Interrupted,
+        code: err.raw_os_error().map(|code| code.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context as _;
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let err = anyhow::anyhow!("Something went wrong");
+        assert_eq!(classify(&err), ErrorInfo::OTHER);
+
+        let err = Err::<(), _>(io::Error::from_raw_os_error(2))
+            .context("Failed to open file")
+            .unwrap_err();
+        let info = classify(&err);
+        assert_eq!(info.kind, ErrorKind::Io);
+        assert_eq!(info.code.as_deref(), Some("2"));
+        assert!(!info.retryable);
+
+        let err = Err::<(), _>(io::Error::from(io::ErrorKind::TimedOut))
+            .context("Failed to connect")
+            .unwrap_err();
+        let info = classify(&err);
+        assert_eq!(info.kind, ErrorKind::Network);
+        assert!(info.retryable);
+
+        // The classification is kept when the message is replaced.
+        let err = ClassifiedError::with_message(&err, "No network".to_string());
+        assert_eq!(format!("{err:#}"), "No network");
+        assert_eq!(classify(&err).kind, ErrorKind::Network);
+    }
+
+    #[test]
+    fn test_classify_imap() {
+        let err = anyhow::Error::new(async_imap::error::Error::No(
+            r#"code: None, info: Some("[AUTHENTICATIONFAILED] Invalid credentials")"#.to_string(),
+        ))
+        .context("LOGIN failed");
+        let info = classify(&err);
+        assert_eq!(info.kind, ErrorKind::Authentication);
+        assert_eq!(info.code.as_deref(), Some("AUTHENTICATIONFAILED"));
+        assert!(!info.retryable);
+
+        let err = anyhow::Error::new(async_imap::error::Error::No(
+            r#"code: None, info: Some("[UNAVAILABLE] Try again later")"#.to_string(),
+        ));
+        let info = classify(&err);
+        assert_eq!(info.kind, ErrorKind::Imap);
+        assert_eq!(info.code.as_deref(), Some("UNAVAILABLE"));
+        assert!(info.retryable);
+
+        // The response text is not interpreted.
+        let err = anyhow::Error::new(async_imap::error::Error::No(
+            r#"code: None, info: Some("Authentication failed")"#.to_string(),
+        ));
+        let info = classify(&err);
+        assert_eq!(info.kind, ErrorKind::Imap);
+        assert_eq!(info.code, None);
+    }
+
+    #[test]
+    fn test_imap_response_code() {
+        assert_eq!(
+            imap_response_code(r#"code: None, info: Some("[EXPIRED] Password expired")"#),
+            Some("EXPIRED")
+        );
+        assert_eq!(
+            imap_response_code("[BADCHARSET (UTF-8)] Unsupported charset"),
+            Some("BADCHARSET")
+        );
+        assert_eq!(
+            imap_response_code(r#"code: None, info: Some("Mailbox [INBOX] does not exist")"#),
+            None
+        );
+        assert_eq!(imap_response_code("[] empty"), None);
+        assert_eq!(imap_response_code("no code"), None);
+    }
+}
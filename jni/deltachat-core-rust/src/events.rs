@@ -1,7 +1,13 @@
 //! # Events specification.
 
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
 use anyhow::Result;
 use tokio::sync::Mutex;
+use tokio::time::{timeout_at, Instant};
+
+use crate::message::MsgId;
 
 pub(crate) mod chatlist_events;
 mod payload;
@@ -50,7 +56,7 @@ pub fn emit(&self, event: Event) {
 
     /// Creates an event emitter.
     pub fn get_emitter(&self) -> EventEmitter {
-        EventEmitter(Mutex::new(self.sender.new_receiver()))
+        EventEmitter::new(self.sender.new_receiver())
     }
 }
 
@@ -60,24 +66,86 @@ pub fn get_emitter(&self) -> EventEmitter {
 /// created events emitted by the [`Context`] will only be delivered to one of the
 /// `EventEmitter`s.
 ///
+/// Floods of events, e.g. during the initial fetch of messages,
+/// can be reduced with [`EventEmitter::set_coalescing_window`].
+///
 /// [`Context`]: crate::context::Context
 /// [`Context::get_event_emitter`]: crate::context::Context::get_event_emitter
 #[derive(Debug)]
-pub struct EventEmitter(Mutex<async_broadcast::Receiver<Event>>);
+pub struct EventEmitter {
+    inner: Mutex<EmitterState>,
+
+    /// Coalescing window in milliseconds, 0 if coalescing is disabled.
+    coalescing_window: AtomicU64,
+}
+
+#[derive(Debug)]
+struct EmitterState {
+    receiver: async_broadcast::Receiver<Event>,
+
+    /// Event received while coalescing, but not delivered yet.
+    pending: Option<Event>,
+}
 
 impl EventEmitter {
+    fn new(receiver: async_broadcast::Receiver<Event>) -> Self {
+        Self {
+            inner: Mutex::new(EmitterState {
+                receiver,
+                pending: None,
+            }),
+            coalescing_window: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the time window to merge events in.
+    ///
+    /// Low-priority events such as [`EventType::MsgsChanged`] or
+    /// [`EventType::ChatlistChanged`] are delayed by up to `window`
+    /// and identical events received meanwhile are dropped.
+    /// [`EventType::MsgsChanged`] events of the same chat, but for different messages
+    /// are merged into one with the message ID 0.
+    /// Other events such as [`EventType::IncomingMsg`] are never delayed.
+    ///
+    /// Coalescing is disabled by default and if `window` is zero.
+    pub fn set_coalescing_window(&self, window: Duration) {
+        let millis = u64::try_from(window.as_millis()).unwrap_or(u64::MAX);
+        self.coalescing_window.store(millis, Ordering::Relaxed);
+    }
+
     /// Async recv of an event. Return `None` if the `Sender` has been dropped.
     ///
     /// [`try_recv`]: Self::try_recv
     pub async fn recv(&self) -> Option<Event> {
-        let mut lock = self.0.lock().await;
-        match lock.recv().await {
-            Err(async_broadcast::RecvError::Overflowed(n)) => Some(Event {
-                id: 0,
-                typ: EventType::EventChannelOverflow { n },
-            }),
-            Err(async_broadcast::RecvError::Closed) => None,
-            Ok(event) => Some(event),
+        let mut lock = self.inner.lock().await;
+        let event = match lock.pending.take() {
+            Some(event) => event,
+            None => recv_event(&mut lock.receiver).await?,
+        };
+
+        let window = Duration::from_millis(self.coalescing_window.load(Ordering::Relaxed));
+        if window.is_zero() || !is_coalescable(&event.typ) {
+            return Some(event);
+        }
+        // The held event is kept in `pending` while waiting
+        // so it is not lost if the future is dropped.
+        lock.pending = Some(event);
+        let deadline = Instant::now() + window;
+        loop {
+            match timeout_at(deadline, recv_event(&mut lock.receiver)).await {
+                Ok(Some(next)) => {
+                    match lock
+                        .pending
+                        .as_ref()
+                        .and_then(|event| merge_events(event, &next))
+                    {
+                        Some(merged) => lock.pending = Some(merged),
+                        None => return lock.pending.replace(next),
+                    }
+                }
+                // Timeout or channel closed.
+                Err(_) | Ok(None) => return lock.pending.take(),
+            }
         }
     }
 
@@ -92,8 +160,11 @@ pub fn try_recv(&self) -> Result<Event> {
         // Using `try_lock` instead of `lock`
         // to avoid blocking
         // in case there is a concurrent call to `recv`.
-        let mut lock = self.0.try_lock()?;
-        match lock.try_recv() {
+        let mut lock = self.inner.try_lock()?;
+        if let Some(event) = lock.pending.take() {
+            return Ok(event);
+        }
+        match lock.receiver.try_recv() {
             Err(async_broadcast::TryRecvError::Overflowed(n)) => {
                 // Some events have been lost,
                 // but the channel is not closed.
@@ -109,6 +180,64 @@ pub fn try_recv(&self) -> Result<Event> {
     }
 }
 
+/// Receives the next event from the channel.
+async fn recv_event(receiver: &mut async_broadcast::Receiver<Event>) -> Option<Event> {
+    match receiver.recv().await {
+        Err(async_broadcast::RecvError::Overflowed(n)) => Some(Event {
+            id: 0,
+            typ: EventType::EventChannelOverflow { n },
+        }),
+        Err(async_broadcast::RecvError::Closed) => None,
+        Ok(event) => Some(event),
+    }
+}
+
+/// Returns true if the event may be delayed to merge it with following events.
+fn is_coalescable(typ: &EventType) -> bool {
+    matches!(
+        typ,
+        EventType::MsgsChanged { .. }
+            | EventType::ReactionsChanged { .. }
+            | EventType::MsgsNoticed(_)
+            | EventType::ChatModified(_)
+            | EventType::ContactsChanged(_)
+            | EventType::LocationChanged(_)
+            | EventType::ConnectivityChanged
+            | EventType::ChatlistChanged
+            | EventType::ChatlistItemChanged { .. }
+            | EventType::AccountsChanged
+            | EventType::AccountsItemChanged
+    )
+}
+
+/// Merges the event `next` into the coalescable event `event`.
+///
+/// Returns `None` if the events cannot be merged.
+fn merge_events(event: &Event, next: &Event) -> Option<Event> {
+    if event == next {
+        return Some(event.clone());
+    }
+    if event.id != next.id {
+        return None;
+    }
+    match (&event.typ, &next.typ) {
+        (
+            EventType::MsgsChanged { chat_id, .. },
+            EventType::MsgsChanged {
+                chat_id: next_chat_id,
+                ..
+            },
+        ) if chat_id == next_chat_id && !chat_id.is_unset() => Some(Event {
+            id: event.id,
+            typ: EventType::MsgsChanged {
+                chat_id: *chat_id,
+                msg_id: MsgId::new(0),
+            },
+        }),
+        _ => None,
+    }
+}
+
 /// The event emitted by a [`Context`] from an [`EventEmitter`].
 ///
 /// Events are documented on the C/FFI API in `deltachat.h` as `DC_EVENT_*` constants.  The
@@ -130,3 +259,69 @@ pub struct Event {
     /// These are documented in `deltachat.h` as the `DC_EVENT_*` constants.
     pub typ: EventType,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::ChatId;
+
+    fn msgs_changed(chat_id: u32, msg_id: u32) -> Event {
+        Event {
+            id: 1,
+            typ: EventType::MsgsChanged {
+                chat_id: ChatId::new(chat_id),
+                msg_id: MsgId::new(msg_id),
+            },
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_event_coalescing() -> Result<()> {
+        let events = Events::new();
+        let emitter = events.get_emitter();
+        emitter.set_coalescing_window(Duration::from_millis(100));
+
+        for _ in 0..100 {
+            events.emit(msgs_changed(10, 0));
+        }
+        let incoming_msg = Event {
+            id: 1,
+            typ: EventType::IncomingMsg {
+                chat_id: ChatId::new(10),
+                msg_id: MsgId::new(20),
+                watched: false,
+            },
+        };
+        events.emit(incoming_msg.clone());
+        events.emit(msgs_changed(11, 21));
+        events.emit(msgs_changed(11, 22));
+
+        assert_eq!(emitter.recv().await, Some(msgs_changed(10, 0)));
+        // The high-priority event is not merged and keeps its order.
+        assert_eq!(emitter.recv().await, Some(incoming_msg));
+        assert_eq!(emitter.recv().await, Some(msgs_changed(11, 0)));
+        assert!(emitter.try_recv().is_err());
+
+        // Without coalescing, all events are delivered.
+        emitter.set_coalescing_window(Duration::ZERO);
+        events.emit(msgs_changed(10, 0));
+        events.emit(msgs_changed(10, 0));
+        assert_eq!(emitter.recv().await, Some(msgs_changed(10, 0)));
+        assert_eq!(emitter.recv().await, Some(msgs_changed(10, 0)));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_event_coalescing_cancelled() -> Result<()> {
+        let events = Events::new();
+        let emitter = events.get_emitter();
+        emitter.set_coalescing_window(Duration::from_secs(60));
+
+        events.emit(msgs_changed(10, 20));
+        // Dropping `recv()` while it waits for more events must not lose the event.
+        let res = tokio::time::timeout(Duration::from_millis(100), emitter.recv()).await;
+        assert!(res.is_err());
+        assert_eq!(emitter.try_recv()?, msgs_changed(10, 20));
+        Ok(())
+    }
+}
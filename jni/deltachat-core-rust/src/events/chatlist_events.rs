@@ -1,8 +1,11 @@
-use crate::{chat::ChatId, contact::ContactId, context::Context, EventType};
+use crate::{
+    chat::ChatId, chatlist::schedule_chatlist_diff, contact::ContactId, context::Context, EventType,
+};
 
 /// order or content of chatlist changes (chat ids, not the actual chatlist item)
 pub(crate) fn emit_chatlist_changed(context: &Context) {
     context.emit_event(EventType::ChatlistChanged);
+    schedule_chatlist_diff(context);
 }
 
 /// Chatlist item of a specific chat changed
@@ -10,6 +13,7 @@ pub(crate) fn emit_chatlist_item_changed(context: &Context, chat_id: ChatId) {
     context.emit_event(EventType::ChatlistItemChanged {
         chat_id: Some(chat_id),
     });
+    schedule_chatlist_diff(context);
 }
 
 /// Used when you don't know which chatlist items changed, this reloads all cached chatlist items in the UI
@@ -19,6 +23,7 @@ pub(crate) fn emit_chatlist_item_changed(context: &Context, chat_id: ChatId) {
 /// This method is not public, so you have to define and document your new case here in this file.
 fn emit_unknown_chatlist_items_changed(context: &Context) {
     context.emit_event(EventType::ChatlistItemChanged { chat_id: None });
+    schedule_chatlist_diff(context);
 }
 
 /// update event for the 1:1 chat with the contact
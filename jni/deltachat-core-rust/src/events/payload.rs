@@ -7,8 +7,11 @@
 use crate::config::Config;
 use crate::contact::ContactId;
 use crate::ephemeral::Timer as EphemeralTimer;
-use crate::message::MsgId;
+use crate::health::HealthCategory;
+use crate::imex::ImexPhase;
+use crate::message::{MessageState, MsgId, MsgStateChangeCause};
 use crate::reaction::Reaction;
+use crate::staging::StagedAttachmentId;
 use crate::webxdc::StatusUpdateSerial;
 
 /// Event payload.
@@ -122,6 +125,67 @@ pub enum EventType {
         href: Option<String>,
     },
 
+    /// A report of an offending message was received from another user,
+    /// see [`crate::report::get_msg_reports`].
+    IncomingMsgReport {
+        /// ID of the report.
+        report_id: u32,
+    },
+
+    /// A call was placed by a contact and the device should ring,
+    /// see [`crate::calls`].
+    IncomingCall {
+        /// ID of the 1:1 chat of the caller.
+        chat_id: ChatId,
+
+        /// ID of the call invite message.
+        msg_id: MsgId,
+
+        /// Payload of the caller, e.g. an SDP offer.
+        payload: String,
+    },
+
+    /// An incoming call was accepted on this or another device of the user,
+    /// the device should stop ringing.
+    IncomingCallAccepted {
+        /// ID of the 1:1 chat of the caller.
+        chat_id: ChatId,
+
+        /// ID of the call invite message.
+        msg_id: MsgId,
+    },
+
+    /// An outgoing call was accepted by the callee.
+    OutgoingCallAccepted {
+        /// ID of the 1:1 chat of the callee.
+        chat_id: ChatId,
+
+        /// ID of the call invite message.
+        msg_id: MsgId,
+
+        /// Payload of the callee, e.g. an SDP answer.
+        payload: String,
+    },
+
+    /// A call was ended or rejected.
+    CallEnded {
+        /// ID of the 1:1 chat of the call.
+        chat_id: ChatId,
+
+        /// ID of the call invite message.
+        msg_id: MsgId,
+    },
+
+    /// An incoming call was cancelled by the caller before it was accepted
+    /// or was received too late to ring.
+    CallMissed {
+        /// ID of the 1:1 chat of the caller.
+        chat_id: ChatId,
+
+        /// ID of the call invite message.
+        msg_id: MsgId,
+    },
+
     /// There is a fresh message. Typically, the user will show an notification
     /// when receiving this message.
     ///
@@ -132,6 +196,26 @@ pub enum EventType {
 
         /// ID of the message.
         msg_id: MsgId,
+
+        /// Whether the message contains a watch keyword, see [`crate::watch`].
+        ///
+        /// The UI may show a notification even if the chat is muted then.
+        watched: bool,
+    },
+
+    /// An incoming message from an unknown sender uses the name of a known contact
+    /// and possibly impersonates it.
+    ///
+    /// Emitted in addition to [`EventType::IncomingMsg`] or [`EventType::MsgsChanged`].
+    IncomingMsgImpersonation {
+        /// ID of the chat where the message is assigned.
+        chat_id: ChatId,
+
+        /// ID of the message.
+        msg_id: MsgId,
+
+        /// ID of the contact whose name is used.
+        contact_id: ContactId,
     },
 
     /// Downloading a bunch of messages just finished.
@@ -171,6 +255,28 @@ pub enum EventType {
         msg_id: MsgId,
     },
 
+    /// State of a single message changed.
+    ///
+    /// Emitted in addition to the less specific events such as [`EventType::MsgDelivered`],
+    /// [`EventType::MsgRead`] or [`EventType::MsgsNoticed`],
+    /// so UIs can update a single message instead of reloading the whole chat.
+    MsgStateChanged {
+        /// ID of the chat which the message belongs to.
+        chat_id: ChatId,
+
+        /// ID of the message.
+        msg_id: MsgId,
+
+        /// State before the change.
+        old_state: MessageState,
+
+        /// State after the change.
+        new_state: MessageState,
+
+        /// What caused the change.
+        cause: MsgStateChangeCause,
+    },
+
     /// A single message was deleted.
     ///
     /// This event means that the message will no longer appear in the messagelist.
@@ -210,6 +316,13 @@ pub enum EventType {
         timer: EphemeralTimer,
     },
 
+    /// Inactive chats were archived by housekeeping,
+    /// see [`Config::AutoArchiveDays`](crate::config::Config::AutoArchiveDays).
+    ChatsAutoArchived {
+        /// IDs of the archived chats.
+        chat_ids: Vec<ChatId>,
+    },
+
     /// Contact(s) created, renamed, blocked, deleted or changed their "recently seen" status.
     ///
     /// @param data1 (int) If set, this is the contact_id of an added contact that should be selected.
@@ -239,6 +352,28 @@ pub enum EventType {
     /// @param data2 0
     ImexProgress(usize),
 
+    /// Detailed progress of a backup export or import,
+    /// emitted together with [`EventType::ImexProgress`] and when the phase changes.
+    ImexProgressDetail {
+        /// Current phase.
+        phase: ImexPhase,
+
+        /// Number of archive entries transferred completely.
+        items_done: usize,
+
+        /// Total number of archive entries, `None` if unknown as during import.
+        items_total: Option<usize>,
+
+        /// Number of bytes transferred.
+        bytes_done: u64,
+
+        /// Expected total number of bytes.
+        bytes_total: u64,
+
+        /// Estimated remaining time in seconds, `None` if not known yet.
+        eta_secs: Option<u64>,
+    },
+
     /// A file has been exported. A file has been written by imex().
     /// This event may be sent multiple times by a single call to imex().
     ///
@@ -248,6 +383,38 @@ pub enum EventType {
     /// @param data2 0
     ImexFileWritten(PathBuf),
 
+    /// Inform about the progress of housekeeping,
+    /// see [`crate::housekeeping`].
+    ///
+    /// @param data1 (usize) 0=error or cancelled, 1-999=progress in permille, 1000=success and done
+    /// @param data2 0
+    HousekeepingProgress(usize),
+
+    /// Inform about the progress of recoding an image of a message being sent.
+    /// Not emitted for device messages.
+    ///
+    /// The recoding can be cancelled with [`crate::chat::ChatId::cancel_image_recoding`].
+    ImageRecodeProgress {
+        /// ID of the chat the message is sent to.
+        chat_id: ChatId,
+
+        /// 0=error or cancelled, 1-999=progress in permille, 1000=success and done
+        progress: usize,
+    },
+
+    /// Inform about the progress of preparing a staged attachment,
+    /// see [`crate::staging`].
+    AttachmentStagingProgress {
+        /// ID of the chat the attachment is staged for.
+        chat_id: ChatId,
+
+        /// ID of the staged attachment.
+        staged_id: StagedAttachmentId,
+
+        /// 0=error or cancelled, 500=copied into the blobdir, 1000=ready
+        progress: usize,
+    },
+
     /// Progress information of a secure-join handshake from the view of the inviter
     /// (Alice, the person who shows the QR code).
     ///
@@ -286,6 +453,35 @@ pub enum EventType {
     /// dc_get_connectivity_html() for details.
     ConnectivityChanged,
 
+    /// The provider status endpoint reports an incident,
+    /// checked when connecting to the server fails repeatedly.
+    ProviderIncident {
+        /// Short summary of the incident.
+        title: String,
+
+        /// Description of the incident, possibly followed by a link to the incident page.
+        details: String,
+    },
+
+    /// A problem with the account was detected by the periodic health evaluation,
+    /// see [`crate::health`].
+    /// Every warning is reported once until the problem is fixed.
+    AccountHealthWarning {
+        /// Machine-readable category of the warning.
+        category: HealthCategory,
+
+        /// Technical details, not translated.
+        details: String,
+    },
+
+    /// The features of the server changed,
+    /// see [`crate::context::Context::get_server_capabilities`].
+    ServerCapabilitiesChanged,
+
+    /// The process became the owner of the account or released the ownership,
+    /// see [`crate::context::Context::is_owner`].
+    OwnershipChanged,
+
     /// The user's avatar changed.
     /// Deprecated by `ConfigSynced`.
     SelfavatarChanged,
@@ -347,6 +543,36 @@ pub enum EventType {
         chat_id: Option<ChatId>,
     },
 
+    /// A chat was inserted into the chatlist or moved to another position.
+    ///
+    /// The UI should remove the chat from its old position, if any,
+    /// and insert it at `index`.
+    /// Only emitted if enabled with [`crate::chatlist::set_chatlist_diff_events`].
+    ChatlistItemMoved {
+        /// ID of the moved chat
+        chat_id: ChatId,
+        /// New position of the chat in the chatlist
+        index: usize,
+    },
+
+    /// A chat was removed from the chatlist, e.g. because it was archived or deleted.
+    ///
+    /// Only emitted if enabled with [`crate::chatlist::set_chatlist_diff_events`].
+    ChatlistItemRemoved {
+        /// ID of the removed chat
+        chat_id: ChatId,
+    },
+
+    /// The number of fresh messages of a chat in the chatlist changed.
+    ///
+    /// Only emitted if enabled with [`crate::chatlist::set_chatlist_diff_events`].
+    ChatlistItemFreshMsgCountChanged {
+        /// ID of the chat
+        chat_id: ChatId,
+        /// New number of fresh messages
+        count: usize,
+    },
+
     /// Inform that the list of accounts has changed (an account removed or added or (not yet implemented) the account order changes)
     ///
     /// This event is only emitted by the account manager
@@ -0,0 +1,409 @@
+//! # Sharing of recent group messages with new members.
+//!
+//! If enabled for a group, the device adding a member with a QR code
+//! sends the last messages of the group to the new member,
+//! so the new member does not join a chat without context.
+//!
+//! The messages are sent in a hidden message
+//! that is delivered to the new member only and always end-to-end encrypted.
+//! The receiver adds the messages to the group unless they already exist,
+//! so messages shared by several devices are added only once.
+//! History is only accepted from the member who added the receiver
+//! and the messages are shown as sent by that member,
+//! as the original senders cannot be verified.
+
+use anyhow::{ensure, Result};
+use deltachat_contact_tools::{addr_cmp, ContactAddress};
+use lettre_email::PartBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, is_contact_in_chat, Chat, ChatId};
+use crate::config::Config;
+use crate::constants::Chattype;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::{self, rfc724_mid_exists, Message, MessageState, Viewtype};
+use crate::mimefactory::wrapped_base64_encode;
+use crate::mimeparser::{MimeMessage, ParsedAttachment, SystemMessage};
+use crate::param::{Param, Params};
+
+/// Maximum number of messages shared with new members.
+pub const MAX_SHARED_MESSAGES: u32 = 50;
+
+/// A message shared with a new member.
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedMessage {
+    /// Message-ID of the original message, used for deduplication.
+    rfc724_mid: String,
+
+    from_addr: String,
+
+    #[serde(default)]
+    from_name: String,
+
+    timestamp: i64,
+
+    /// Summary of the message, for messages with attachments
+    /// the attachment itself is not shared.
+    text: String,
+}
+
+/// Sets the number of recent messages shared with members added to the group `chat_id`.
+///
+/// The number is capped at [`MAX_SHARED_MESSAGES`], 0 disables sharing.
+/// History is only shared in protected groups.
+pub async fn set_history_sharing(context: &Context, chat_id: ChatId, count: u32) -> Result<()> {
+    let mut chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(
+        chat.typ == Chattype::Group,
+        "History can only be shared in groups"
+    );
+    let count = count.min(MAX_SHARED_MESSAGES);
+    if count == 0 {
+        chat.param.remove(Param::HistorySharing);
+    } else {
+        chat.param.set_int(Param::HistorySharing, count as i32);
+    }
+    chat.update_param(context).await?;
+    context.emit_event(EventType::ChatModified(chat_id));
+    Ok(())
+}
+
+/// Returns the number of recent messages shared with members added to the group `chat_id`.
+pub async fn get_history_sharing(context: &Context, chat_id: ChatId) -> Result<u32> {
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    let count = chat
+        .param
+        .get_int(Param::HistorySharing)
+        .unwrap_or_default();
+    Ok(u32::try_from(count)
+        .unwrap_or_default()
+        .min(MAX_SHARED_MESSAGES))
+}
+
+/// Sends the recent messages of the group `chat_id` to the new member `contact_id`
+/// if history sharing is enabled for the group.
+pub(crate) async fn send_history(
+    context: &Context,
+    chat_id: ChatId,
+    contact_id: ContactId,
+) -> Result<()> {
+    let count = get_history_sharing(context, chat_id).await?;
+    if count == 0 {
+        return Ok(());
+    }
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    if !chat.is_protected() {
+        warn!(
+            context,
+            "Not sharing history of unprotected group {chat_id}."
+        );
+        return Ok(());
+    }
+
+    let msg_ids = context
+        .sql
+        .query_map(
+            "SELECT id FROM msgs
+             WHERE chat_id=? AND hidden=0 AND rfc724_mid!='' AND state!=?
+               AND (from_id=? OR from_id>?) AND from_id!=?
+             ORDER BY timestamp DESC, id DESC LIMIT ?",
+            (
+                chat_id,
+                MessageState::OutDraft,
+                ContactId::SELF,
+                ContactId::LAST_SPECIAL,
+                contact_id,
+                count,
+            ),
+            |row| row.get::<_, message::MsgId>(0),
+            |ids| {
+                ids.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+
+    let self_addr = context.get_primary_self_addr().await?;
+    let self_name = context
+        .get_config(Config::Displayname)
+        .await?
+        .unwrap_or_default();
+    let mut shared = Vec::with_capacity(msg_ids.len());
+    for msg_id in msg_ids.into_iter().rev() {
+        let msg = Message::load_from_db(context, msg_id).await?;
+        if msg.is_info() {
+            continue;
+        }
+        let (from_addr, from_name) = if msg.from_id == ContactId::SELF {
+            (self_addr.clone(), self_name.clone())
+        } else {
+            let contact = Contact::get_by_id(context, msg.from_id).await?;
+            (
+                contact.get_addr().to_string(),
+                contact.get_authname().to_string(),
+            )
+        };
+        shared.push(SharedMessage {
+            rfc724_mid: msg.rfc724_mid.clone(),
+            from_addr,
+            from_name,
+            timestamp: msg.timestamp_sort,
+            text: msg.get_summary_text(context).await,
+        });
+    }
+    if shared.is_empty() {
+        return Ok(());
+    }
+
+    let contact = Contact::get_by_id(context, contact_id).await?;
+    let mut msg = Message::new(Viewtype::Text);
+    msg.hidden = true;
+    msg.param.set_cmd(SystemMessage::GroupHistory);
+    msg.param.set(Param::Arg, serde_json::to_string(&shared)?);
+    msg.param.set(Param::Arg2, contact.get_addr());
+    msg.param.set_int(Param::GuaranteeE2ee, 1);
+    chat::send_msg(context, chat_id, &mut msg).await?;
+    info!(
+        context,
+        "Shared {} messages of {chat_id} with {contact_id}.",
+        shared.len()
+    );
+    Ok(())
+}
+
+pub(crate) fn build_history_part(json: &str) -> PartBuilder {
+    let encoded_body = wrapped_base64_encode(json.as_bytes());
+
+    PartBuilder::new()
+        .content_type(&"application/json".parse::<mime::Mime>().unwrap())
+        .header((
+            "Content-Disposition",
+            ParsedAttachment::GroupHistory.content_disposition(),
+        ))
+        .header(("Content-Transfer-Encoding", "base64"))
+        .body(encoded_body)
+}
+
+/// Returns the member who added us to the group `chat_id`, if known.
+async fn get_adder(context: &Context, chat_id: ChatId) -> Result<Option<ContactId>> {
+    let rows = context
+        .sql
+        .query_map(
+            "SELECT from_id, param FROM msgs
+             WHERE chat_id=? AND from_id>? AND param!=''
+             ORDER BY timestamp DESC, id DESC",
+            (chat_id, ContactId::LAST_SPECIAL),
+            |row| {
+                let from_id: ContactId = row.get(0)?;
+                let param: String = row.get(1)?;
+                Ok((from_id, param))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    for (from_id, param) in rows {
+        let param: Params = param.parse().unwrap_or_default();
+        if param.get_cmd() != SystemMessage::MemberAddedToGroup {
+            continue;
+        }
+        if let Some(addr) = param.get(Param::Arg) {
+            if context.is_self_addr(addr).await? {
+                return Ok(Some(from_id));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Adds the messages shared by `from_id` to the group `chat_id`.
+///
+/// The original sender is only shown next to the message, see the module documentation.
+pub(crate) async fn receive_history(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    chat_id: ChatId,
+    from_id: ContactId,
+) -> Result<()> {
+    let Some(json) = &mime_parser.group_history else {
+        return Ok(());
+    };
+    ensure!(
+        mime_parser.was_encrypted(),
+        "Group history is not encrypted"
+    );
+    ensure!(
+        is_contact_in_chat(context, chat_id, from_id).await?,
+        "Group history sender {from_id} is not a member of {chat_id}"
+    );
+    ensure!(
+        get_adder(context, chat_id).await? == Some(from_id),
+        "Group history sender {from_id} did not add us to {chat_id}"
+    );
+    let from_addr = Contact::get_by_id(context, from_id)
+        .await?
+        .get_addr()
+        .to_string();
+    let shared: Vec<SharedMessage> = serde_json::from_str(json)?;
+
+    let mut added = 0;
+    for item in shared.into_iter().take(MAX_SHARED_MESSAGES as usize) {
+        if item.rfc724_mid.is_empty()
+            || rfc724_mid_exists(context, &item.rfc724_mid)
+                .await?
+                .is_some()
+        {
+            continue;
+        }
+        let Ok(addr) = ContactAddress::new(&item.from_addr) else {
+            warn!(
+                context,
+                "Ignoring shared message with invalid sender {:?}.", item.from_addr
+            );
+            continue;
+        };
+        let mut param = Params::new();
+        if !addr_cmp(&addr, &from_addr) {
+            let name = if item.from_name.is_empty() {
+                &item.from_addr
+            } else {
+                &item.from_name
+            };
+            param.set(Param::OverrideSenderDisplayname, name);
+        }
+        let timestamp = item.timestamp.min(mime_parser.timestamp_sent);
+        context
+            .sql
+            .execute(
+                "INSERT INTO msgs
+                 (rfc724_mid, chat_id, from_id, to_id,
+                  timestamp, timestamp_sent, timestamp_rcvd,
+                  type, state, msgrmsg, txt, txt_normalized, param)
+                 VALUES (?,?,?,?, ?,?,?, ?,?,1,?,?,?)",
+                (
+                    &item.rfc724_mid,
+                    chat_id,
+                    from_id,
+                    ContactId::SELF,
+                    timestamp,
+                    timestamp,
+                    mime_parser.timestamp_rcvd,
+                    Viewtype::Text,
+                    MessageState::InSeen,
+                    &item.text,
+                    message::normalize_text(&item.text),
+                    param.to_string(),
+                ),
+            )
+            .await?;
+        added += 1;
+    }
+    if added > 0 {
+        info!(context, "Added {added} shared messages to {chat_id}.");
+        context.emit_msgs_changed_without_ids();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use deltachat_contact_tools::EmailAddress;
+
+    use super::*;
+    use crate::chat::{
+        add_contact_to_chat, create_group_chat, get_chat_msgs, ChatItem, ProtectionStatus,
+    };
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    async fn get_texts(t: &TestContext, chat_id: ChatId) -> Result<Vec<String>> {
+        let mut texts = Vec::new();
+        for item in get_chat_msgs(t, chat_id).await? {
+            if let ChatItem::Message { msg_id } = item {
+                let msg = Message::load_from_db(t, msg_id).await?;
+                if !msg.is_info() {
+                    texts.push(msg.get_text());
+                }
+            }
+        }
+        Ok(texts)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_group_history() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        tcm.execute_securejoin(bob, alice).await;
+        tcm.execute_securejoin(fiona, alice).await;
+
+        let alice_chat_id = create_group_chat(alice, ProtectionStatus::Protected, "Group").await?;
+        assert_eq!(get_history_sharing(alice, alice_chat_id).await?, 0);
+        set_history_sharing(alice, alice_chat_id, 1000).await?;
+        assert_eq!(
+            get_history_sharing(alice, alice_chat_id).await?,
+            MAX_SHARED_MESSAGES
+        );
+        set_history_sharing(alice, alice_chat_id, 2).await?;
+        let alice_fiona_id = alice.add_or_lookup_contact(fiona).await.id;
+        add_contact_to_chat(alice, alice_chat_id, alice_fiona_id).await?;
+        let fiona_chat_id = fiona.recv_msg(&alice.pop_sent_msg().await).await.chat_id;
+        fiona_chat_id.accept(fiona).await?;
+        for text in ["first", "second"] {
+            alice.send_text(alice_chat_id, text).await;
+        }
+        alice
+            .recv_msg(&fiona.send_text(fiona_chat_id, "third").await)
+            .await;
+
+        let alice_bob_id = alice.add_or_lookup_contact(bob).await.id;
+        add_contact_to_chat(alice, alice_chat_id, alice_bob_id).await?;
+        let bob_chat_id = bob.recv_msg(&alice.pop_sent_msg().await).await.chat_id;
+        assert!(get_texts(bob, bob_chat_id).await?.is_empty());
+
+        send_history(alice, alice_chat_id, alice_bob_id).await?;
+        let sent = alice.pop_sent_msg().await;
+        assert_eq!(sent.recipient(), EmailAddress::new("bob@example.net")?);
+        bob.recv_msg_trash(&sent).await;
+        assert_eq!(get_texts(bob, bob_chat_id).await?, ["second", "third"]);
+        let msg = bob.get_last_msg_in(bob_chat_id).await;
+        assert_eq!(msg.get_info_type(), SystemMessage::MemberAddedToGroup);
+
+        // Shared messages are attributed to the member sharing them.
+        let bob_alice_id = bob.add_or_lookup_contact(alice).await.id;
+        for item in get_chat_msgs(bob, bob_chat_id).await? {
+            if let ChatItem::Message { msg_id } = item {
+                let msg = Message::load_from_db(bob, msg_id).await?;
+                if msg.is_info() {
+                    continue;
+                }
+                assert_eq!(msg.from_id, bob_alice_id);
+                assert_eq!(
+                    msg.get_override_sender_name().is_some(),
+                    msg.get_text() == "third"
+                );
+            }
+        }
+
+        // History is only accepted from the member who added us.
+        let bob_fiona_id = bob.add_or_lookup_contact(fiona).await.id;
+        assert!(is_contact_in_chat(bob, bob_chat_id, bob_fiona_id).await?);
+        let mime_parser = MimeMessage::from_bytes(bob, sent.payload().as_bytes(), None).await?;
+        assert!(mime_parser.group_history.is_some());
+        assert!(
+            receive_history(bob, &mime_parser, bob_chat_id, bob_fiona_id)
+                .await
+                .is_err()
+        );
+
+        // Messages shared again are not duplicated.
+        send_history(alice, alice_chat_id, alice_bob_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        assert_eq!(get_texts(bob, bob_chat_id).await?, ["second", "third"]);
+        Ok(())
+    }
+}
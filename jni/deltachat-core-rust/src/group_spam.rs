@@ -0,0 +1,209 @@
+//! # Heuristics against unsolicited group additions.
+//!
+//! Spammers create groups with many recipients and add unsuspecting users to them.
+//! A group created by an unknown, unverified contact with many recipients
+//! is quarantined: it is shown as a contact request with the additional flag
+//! [`Chat::is_quarantined`], so that the UI can warn the user
+//! and offer to [`leave_and_block_creator`] in a single step.
+//!
+//! Accepting the chat lifts the quarantine.
+
+use anyhow::{ensure, Result};
+
+use crate::chat::{self, Chat, ChatId};
+use crate::constants::Chattype;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+
+/// Minimum number of recipients of a group created by an unknown contact
+/// for the group to be quarantined.
+pub(crate) const SUSPICIOUS_RECIPIENT_COUNT: usize = 10;
+
+/// Returns true if a group created by `from_id` for the recipients `to_ids`
+/// looks like unsolicited group addition.
+pub(crate) async fn is_suspicious_group_add(
+    context: &Context,
+    from_id: ContactId,
+    to_ids: &[ContactId],
+) -> Result<bool> {
+    if from_id.is_special() || to_ids.len() < SUSPICIOUS_RECIPIENT_COUNT {
+        return Ok(false);
+    }
+    let contact = Contact::get_by_id(context, from_id).await?;
+    Ok(!contact.origin.is_known() && !contact.is_verified(context).await?)
+}
+
+/// Marks the group `chat_id` as quarantined.
+pub(crate) async fn quarantine(context: &Context, chat_id: ChatId) -> Result<()> {
+    context
+        .sql
+        .execute("UPDATE chats SET quarantined=1 WHERE id=?", (chat_id,))
+        .await?;
+    info!(context, "Quarantined unsolicited group {chat_id}.");
+    Ok(())
+}
+
+/// Lifts the quarantine of `chat_id`, e.g. when the user accepts the chat.
+pub(crate) async fn unquarantine(context: &Context, chat_id: ChatId) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE chats SET quarantined=0 WHERE id=? AND quarantined=1",
+            (chat_id,),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Returns the contact who sent the first message to the group `chat_id`.
+async fn get_group_creator(context: &Context, chat_id: ChatId) -> Result<Option<ContactId>> {
+    context
+        .sql
+        .query_get_value(
+            "SELECT from_id FROM msgs
+             WHERE chat_id=? AND from_id>?
+             ORDER BY timestamp, id LIMIT 1",
+            (chat_id, ContactId::LAST_SPECIAL),
+        )
+        .await
+}
+
+/// Leaves the group `chat_id`, deletes it and blocks the contact who added the user.
+///
+/// If the group is a contact request, no leave message is sent,
+/// so that the creator does not learn that the address is in use.
+/// Later messages to the group do not recreate it.
+pub async fn leave_and_block_creator(context: &Context, chat_id: ChatId) -> Result<()> {
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(chat.typ == Chattype::Group, "{chat_id} is not a group chat");
+
+    let creator = get_group_creator(context, chat_id).await?;
+    if chat.is_contact_request() {
+        chat::set_group_explicitly_left(context, &chat.grpid).await?;
+    } else if chat::is_contact_in_chat(context, chat_id, ContactId::SELF).await? {
+        chat::remove_contact_from_chat(context, chat_id, ContactId::SELF).await?;
+    }
+    if let Some(creator) = creator {
+        Contact::block(context, creator).await?;
+    }
+    chat_id.block(context).await?;
+    info!(context, "Left group {chat_id} and blocked its creator.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{add_contact_to_chat, create_group_chat, ProtectionStatus};
+    use crate::receive_imf::receive_imf;
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    /// Receives a group creation message from an unknown sender with `recipients` recipients.
+    async fn receive_group(t: &TestContext, grpid: &str, recipients: usize) -> Result<ChatId> {
+        let to = (0..recipients)
+            .map(|i| format!("member{i}@example.net"))
+            .chain(std::iter::once("alice@example.org".to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let raw = format!(
+            "From: spammer@example.net\n\
+             To: {to}\n\
+             Subject: Great offers\n\
+             Message-ID: <{grpid}@example.net>\n\
+             Chat-Version: 1.0\n\
+             Chat-Group-ID: {grpid}\n\
+             Chat-Group-Name: Great offers\n\
+             Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+             \n\
+             Buy now!\n"
+        );
+        receive_imf(t, raw.as_bytes(), false).await?;
+        Ok(t.get_last_msg().await.chat_id)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_quarantine_group_add() -> Result<()> {
+        let t = TestContext::new_alice().await;
+
+        let chat_id = receive_group(&t, "smallgroup01", 2).await?;
+        let chat = Chat::load_from_db(&t, chat_id).await?;
+        assert!(chat.is_contact_request());
+        assert!(!chat.is_quarantined());
+
+        let chat_id = receive_group(&t, "largegroup01", SUSPICIOUS_RECIPIENT_COUNT).await?;
+        let chat = Chat::load_from_db(&t, chat_id).await?;
+        assert!(chat.is_contact_request());
+        assert!(chat.is_quarantined());
+
+        chat_id.accept(&t).await?;
+        let chat = Chat::load_from_db(&t, chat_id).await?;
+        assert!(!chat.is_contact_request());
+        assert!(!chat.is_quarantined());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_leave_and_block_creator() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = receive_group(&t, "largegroup02", SUSPICIOUS_RECIPIENT_COUNT).await?;
+        let creator = t.get_last_msg().await.from_id;
+
+        leave_and_block_creator(&t, chat_id).await?;
+        assert!(Chat::load_from_db(&t, chat_id).await.is_err());
+        assert!(Contact::get_by_id(&t, creator).await?.is_blocked());
+        // Nothing was sent to the spammer.
+        assert!(t
+            .pop_sent_msg_opt(std::time::Duration::ZERO)
+            .await
+            .is_none());
+
+        // The group is not recreated by later messages.
+        let raw = "From: member0@example.net\n\
+                   To: alice@example.org\n\
+                   Subject: Re: Great offers\n\
+                   Message-ID: <followup02@example.net>\n\
+                   Chat-Version: 1.0\n\
+                   Chat-Group-ID: largegroup02\n\
+                   Chat-Group-Name: Great offers\n\
+                   Date: Sun, 22 Mar 2020 22:38:57 +0000\n\
+                   \n\
+                   Still there?\n";
+        receive_imf(&t, raw.as_bytes(), false).await?;
+        assert!(chat::get_chat_id_by_grpid(&t, "largegroup02")
+            .await?
+            .is_none());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_known_creator_not_quarantined() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        bob.create_chat(alice).await;
+        let bob_chat_id = create_group_chat(bob, ProtectionStatus::Unprotected, "Team").await?;
+        add_contact_to_chat(bob, bob_chat_id, bob.add_or_lookup_contact_id(alice).await).await?;
+        for i in 0..SUSPICIOUS_RECIPIENT_COUNT {
+            let contact_id = Contact::create(bob, "", &format!("member{i}@example.net")).await?;
+            add_contact_to_chat(bob, bob_chat_id, contact_id).await?;
+        }
+        let sent = bob.send_text(bob_chat_id, "Hello team").await;
+
+        // Alice does not know Bob, so the group is quarantined.
+        let alice_chat_id = alice.recv_msg(&sent).await.chat_id;
+        assert!(Chat::load_from_db(alice, alice_chat_id)
+            .await?
+            .is_quarantined());
+
+        // Groups created by known contacts are normal contact requests.
+        alice.create_chat(bob).await;
+        alice_chat_id.delete(alice).await?;
+        let sent = bob.send_text(bob_chat_id, "Hello again").await;
+        let alice_chat_id = alice.recv_msg(&sent).await.chat_id;
+        assert!(!Chat::load_from_db(alice, alice_chat_id)
+            .await?
+            .is_quarantined());
+        Ok(())
+    }
+}
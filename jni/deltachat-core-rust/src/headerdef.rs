@@ -68,6 +68,12 @@ pub enum HeaderDef {
     /// Duration of the attached media file.
     ChatDuration,
 
+    /// Hash of the attachment as sent, e.g. `sha256=<hex>`.
+    ChatAttachmentHash,
+
+    /// Base64-encoded JPEG thumbnail of a video attachment, e.g. `base64:<data>`.
+    ChatVideoThumbnail,
+
     ChatDispositionNotificationTo,
     ChatWebrtcRoom,
 
@@ -0,0 +1,401 @@
+//! # Account health evaluation.
+//!
+//! Signals indicating that the account is about to stop working,
+//! such as repeated login failures or a nearly full mailbox,
+//! are combined into a score and a list of warnings, see [`Context::get_account_health`].
+//! The health is evaluated periodically by the IO scheduler
+//! and [`EventType::AccountHealthWarning`] is emitted once for every new warning,
+//! so users can fix the problem before messages stop arriving.
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::events::EventType;
+use crate::login_param::ConfiguredLoginParam;
+use crate::message::MessageState;
+use crate::net::tls::cert_expiry;
+use crate::quota::{
+    get_highest_usage, QUOTA_ERROR_THRESHOLD_PERCENTAGE, QUOTA_WARN_THRESHOLD_PERCENTAGE,
+};
+use crate::tools::{time, timestamp_to_str};
+
+/// Number of consecutive authentication failures resulting in a warning.
+const AUTH_FAILURES_THRESHOLD: u32 = 3;
+
+/// Number of messages failed during [`BOUNCES_PERIOD`] resulting in a warning.
+const BOUNCES_THRESHOLD: usize = 3;
+
+/// Period in which failed messages are counted, in seconds.
+const BOUNCES_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+/// Warn about certificates expiring within this number of seconds.
+const CERT_EXPIRY_WARNING_PERIOD: i64 = 14 * 24 * 60 * 60;
+
+/// Minimum interval between two evaluations by the scheduler, in seconds.
+const EVALUATION_INTERVAL: i64 = 10 * 60;
+
+/// Raw config key storing the timestamp of the last evaluation.
+const LAST_EVALUATION_KEY: &str = "health_evaluated";
+
+/// Raw config key storing the space-separated categories of the warnings reported already.
+const REPORTED_KEY: &str = "health_warnings_reported";
+
+/// Category of an account health warning.
+#[derive(
+    Debug,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    FromPrimitive,
+    ToPrimitive,
+    EnumString,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+#[repr(u32)]
+pub enum HealthCategory {
+    /// Logging in to the server failed repeatedly because of wrong credentials.
+    /// The user should check the password.
+    AuthFailures = 1,
+
+    /// The server reports that the mailbox uses most of its quota.
+    /// The user should delete old messages.
+    QuotaWarning = 2,
+
+    /// The mailbox is nearly full and new messages may be rejected soon.
+    MailboxNearlyFull = 3,
+
+    /// The TLS certificate of the server expires soon or is expired.
+    /// The user should contact the server administrator.
+    CertificateExpiry = 4,
+
+    /// Several outgoing messages failed recently,
+    /// e.g. because they bounced.
+    Bounces = 5,
+}
+
+impl HealthCategory {
+    /// Returns the number of points the warning subtracts from the health score.
+    fn penalty(self) -> u32 {
+        match self {
+            Self::AuthFailures => 50,
+            Self::QuotaWarning => 10,
+            Self::MailboxNearlyFull => 30,
+            Self::CertificateExpiry => 20,
+            Self::Bounces => 20,
+        }
+    }
+}
+
+/// Warning about a problem with the account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthWarning {
+    /// Machine-readable category of the warning.
+    pub category: HealthCategory,
+
+    /// Technical details, e.g. the affected server, not translated.
+    pub details: String,
+}
+
+/// Result of an account health evaluation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountHealth {
+    /// Health score from 0 to 100, 100 meaning no problems were detected.
+    pub score: u32,
+
+    /// Detected problems, at most one per category.
+    pub warnings: Vec<HealthWarning>,
+}
+
+/// Signals of the account health collected in memory since the account was opened.
+#[derive(Debug, Default)]
+pub(crate) struct HealthCounters {
+    /// Number of consecutive IMAP authentication failures.
+    auth_failures: AtomicU32,
+}
+
+impl HealthCounters {
+    /// Counts an IMAP login attempt.
+    pub(crate) fn on_login(&self, auth_failed: bool) {
+        if auth_failed {
+            self.auth_failures.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.auth_failures.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Context {
+    /// Evaluates the health of the account.
+    ///
+    /// Unlike the periodic evaluation, this does not emit any events.
+    pub async fn get_account_health(&self) -> Result<AccountHealth> {
+        let now = time();
+        let mut warnings = Vec::new();
+
+        let auth_failures = self.health_counters.auth_failures.load(Ordering::Relaxed);
+        if auth_failures >= AUTH_FAILURES_THRESHOLD {
+            warnings.push(HealthWarning {
+                category: HealthCategory::AuthFailures,
+                details: format!("{auth_failures} consecutive login failures."),
+            });
+        }
+
+        if let Some(usage) = self.quota_usage().await? {
+            if usage >= QUOTA_ERROR_THRESHOLD_PERCENTAGE {
+                warnings.push(HealthWarning {
+                    category: HealthCategory::MailboxNearlyFull,
+                    details: format!("{usage}% of the quota used."),
+                });
+            } else if usage >= QUOTA_WARN_THRESHOLD_PERCENTAGE {
+                warnings.push(HealthWarning {
+                    category: HealthCategory::QuotaWarning,
+                    details: format!("{usage}% of the quota used."),
+                });
+            }
+        }
+
+        let hosts = match ConfiguredLoginParam::load(self).await {
+            Ok(Some(param)) => param
+                .imap
+                .iter()
+                .chain(param.smtp.iter())
+                .map(|server| server.connection.host.clone())
+                .collect::<BTreeSet<_>>(),
+            Ok(None) => BTreeSet::new(),
+            Err(err) => {
+                warn!(self, "Failed to load login parameters: {err:#}.");
+                BTreeSet::new()
+            }
+        };
+        if let Some(warning) = cert_warning(hosts.iter().map(String::as_str), now) {
+            warnings.push(warning);
+        }
+
+        let failed_msgs = self
+            .sql
+            .count(
+                "SELECT COUNT(*) FROM msgs WHERE state=? AND hidden=0 AND timestamp>?",
+                (MessageState::OutFailed, now.saturating_sub(BOUNCES_PERIOD)),
+            )
+            .await?;
+        if failed_msgs >= BOUNCES_THRESHOLD {
+            warnings.push(HealthWarning {
+                category: HealthCategory::Bounces,
+                details: format!("{failed_msgs} messages failed during the last 7 days."),
+            });
+        }
+
+        let penalty: u32 = warnings.iter().map(|w| w.category.penalty()).sum();
+        Ok(AccountHealth {
+            score: 100u32.saturating_sub(penalty),
+            warnings,
+        })
+    }
+
+    /// Returns the highest quota usage in percent
+    /// as reported by the server recently
+    /// or when a quota warning was added last time.
+    async fn quota_usage(&self) -> Result<Option<u64>> {
+        if let Some(quota) = self.quota.read().await.as_ref() {
+            if let Ok(roots) = &quota.recent {
+                if let Ok((usage, _, _)) = get_highest_usage(roots) {
+                    return Ok(Some(usage));
+                }
+            }
+        }
+        let warned_at = self.get_config_int(Config::QuotaExceeding).await?;
+        Ok((warned_at > 0).then_some(warned_at as u64))
+    }
+}
+
+/// Returns a warning if a certificate of one of the `hosts` expires soon.
+fn cert_warning<'a>(hosts: impl Iterator<Item = &'a str>, now: i64) -> Option<HealthWarning> {
+    let expiring: Vec<String> = hosts
+        .filter_map(|host| {
+            let not_after = cert_expiry(host)?;
+            (not_after < now.saturating_add(CERT_EXPIRY_WARNING_PERIOD)).then(|| {
+                let verb = if not_after < now {
+                    "expired"
+                } else {
+                    "expires"
+                };
+                format!(
+                    "Certificate of {host} {verb} {}.",
+                    timestamp_to_str(not_after)
+                )
+            })
+        })
+        .collect();
+    (!expiring.is_empty()).then(|| HealthWarning {
+        category: HealthCategory::CertificateExpiry,
+        details: expiring.join("\n"),
+    })
+}
+
+/// Evaluates the account health unless it was evaluated recently
+/// and emits [`EventType::AccountHealthWarning`] for every new warning.
+pub(crate) async fn maybe_evaluate(context: &Context) -> Result<()> {
+    let now = time();
+    let last_evaluation = context
+        .sql
+        .get_raw_config_int64(LAST_EVALUATION_KEY)
+        .await?
+        .unwrap_or_default();
+    if last_evaluation <= now && now < last_evaluation.saturating_add(EVALUATION_INTERVAL) {
+        return Ok(());
+    }
+    context
+        .sql
+        .set_raw_config_int64(LAST_EVALUATION_KEY, now)
+        .await?;
+    evaluate(context).await
+}
+
+async fn evaluate(context: &Context) -> Result<()> {
+    let health = context.get_account_health().await?;
+    let reported = context
+        .sql
+        .get_raw_config(REPORTED_KEY)
+        .await?
+        .unwrap_or_default();
+    let reported: BTreeSet<&str> = reported.split_whitespace().collect();
+
+    // Remember only the current warnings,
+    // so a problem is reported again if it comes back after being fixed.
+    let current: Vec<String> = health
+        .warnings
+        .iter()
+        .map(|warning| warning.category.to_string())
+        .collect();
+    context
+        .sql
+        .set_raw_config(REPORTED_KEY, Some(&current.join(" ")))
+        .await?;
+
+    for warning in health.warnings {
+        if reported.contains(warning.category.to_string().as_str()) {
+            continue;
+        }
+        warn!(
+            context,
+            "Account health warning {}: {}", warning.category, warning.details
+        );
+        context.emit_event(EventType::AccountHealthWarning {
+            category: warning.category,
+            details: warning.details,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{self, Message};
+    use crate::net::tls::set_cert_expiry;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_cert_warning() {
+        let now = 1_700_000_000;
+        set_cert_expiry("imap.health.example.org", now + 3 * 24 * 60 * 60);
+        set_cert_expiry("smtp.health.example.org", now + 90 * 24 * 60 * 60);
+
+        assert!(cert_warning(["smtp.health.example.org"].into_iter(), now).is_none());
+        assert!(cert_warning(["unknown.health.example.org"].into_iter(), now).is_none());
+        let warning = cert_warning(
+            ["imap.health.example.org", "smtp.health.example.org"].into_iter(),
+            now,
+        )
+        .unwrap();
+        assert_eq!(warning.category, HealthCategory::CertificateExpiry);
+        assert!(warning.details.contains("imap.health.example.org expires"));
+        assert!(!warning.details.contains("smtp"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_account_health() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let health = alice.get_account_health().await?;
+        assert_eq!(health.score, 100);
+        assert!(health.warnings.is_empty());
+
+        for _ in 0..AUTH_FAILURES_THRESHOLD {
+            alice.health_counters.on_login(true);
+        }
+        let chat_id = alice.create_chat(bob).await.id;
+        for _ in 0..BOUNCES_THRESHOLD {
+            let msg_id = alice.send_text(chat_id, "Hi").await.sender_msg_id;
+            let mut msg = Message::load_from_db(alice, msg_id).await?;
+            message::set_msg_failed(alice, &mut msg, "550 Mailbox unavailable").await?;
+        }
+        alice
+            .set_config_internal(Config::QuotaExceeding, Some("85"))
+            .await?;
+
+        let health = alice.get_account_health().await?;
+        let categories: Vec<HealthCategory> = health.warnings.iter().map(|w| w.category).collect();
+        assert_eq!(
+            categories,
+            [
+                HealthCategory::AuthFailures,
+                HealthCategory::QuotaWarning,
+                HealthCategory::Bounces
+            ]
+        );
+        assert_eq!(health.score, 20);
+
+        // Every warning is reported once.
+        alice.evtracker.clear_events();
+        evaluate(alice).await?;
+        for category in categories {
+            let event = alice
+                .evtracker
+                .get_matching(|evt| matches!(evt, EventType::AccountHealthWarning { .. }))
+                .await;
+            assert_eq!(
+                event,
+                EventType::AccountHealthWarning {
+                    category,
+                    details: health
+                        .warnings
+                        .iter()
+                        .find(|w| w.category == category)
+                        .unwrap()
+                        .details
+                        .clone(),
+                }
+            );
+        }
+        evaluate(alice).await?;
+        assert!(alice
+            .evtracker
+            .get_matching_opt(alice, |evt| matches!(
+                evt,
+                EventType::AccountHealthWarning { .. }
+            ))
+            .await
+            .is_none());
+
+        // After a successful login, only the remaining problems are reported.
+        alice.health_counters.on_login(false);
+        let health = alice.get_account_health().await?;
+        assert_eq!(health.warnings.len(), 2);
+        assert_eq!(health.score, 70);
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! # HEIF image decoding.
+//!
+//! iPhones store photos as HEIC (HEIF with HEVC compression)
+//! and some cameras and browsers produce AVIF (HEIF with AV1 compression).
+//! Neither is supported by the `image` crate and they can't be displayed by many clients.
+//! If the `heif` feature is enabled, such images are decoded with libheif
+//! and converted to JPEG when sending and receiving them.
+//! Decoding AVIF requires libheif to be built with an AV1 decoder.
+
+use std::path::Path;
+
+use anyhow::Result;
+use image::DynamicImage;
+
+use crate::blob::DecodingLimits;
+
+/// Whether HEIF decoding is supported by this build.
+pub(crate) const SUPPORTED: bool = cfg!(feature = "heif");
+
+/// Returns whether the file at `path` is a HEIF image, including AVIF, judging by its extension.
+pub(crate) fn is_heif(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["heic", "heif", "avif"]
+                .iter()
+                .any(|heif_ext| ext.eq_ignore_ascii_case(heif_ext))
+        })
+}
+
+/// Decodes the primary image of HEIF `data`.
+///
+/// Transformations stored in the container, such as rotation, are applied.
+#[cfg(feature = "heif")]
+pub(crate) fn decode(data: &[u8], limits: &DecodingLimits) -> Result<DynamicImage> {
+    use anyhow::Context as _;
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let ctx = HeifContext::read_from_bytes(data).context("Failed to read HEIF container")?;
+    let handle = ctx
+        .primary_image_handle()
+        .context("No primary image in HEIF container")?;
+    let (width, height) = (handle.width(), handle.height());
+    limits.check_dimensions(width, height)?;
+    limits.check_alloc(u64::from(width) * u64::from(height) * 3)?;
+
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .context("Failed to decode HEIF image")?;
+    let plane = image
+        .planes()
+        .interleaved
+        .context("No interleaved plane in decoded HEIF image")?;
+    let row_len = usize::try_from(plane.width)? * 3;
+    let stride = plane.stride;
+    let mut buf = Vec::with_capacity(row_len * usize::try_from(plane.height)?);
+    for row in plane
+        .data
+        .chunks(stride)
+        .take(usize::try_from(plane.height)?)
+    {
+        buf.extend_from_slice(row.get(..row_len).context("Truncated HEIF image row")?);
+    }
+    let img = image::RgbImage::from_raw(plane.width, plane.height, buf)
+        .context("Invalid HEIF image buffer")?;
+    Ok(DynamicImage::ImageRgb8(img))
+}
+
+/// Decodes the primary image of HEIF `data`.
+///
+/// Always fails as HEIF support is not enabled in this build.
+#[cfg(not(feature = "heif"))]
+pub(crate) fn decode(_data: &[u8], _limits: &DecodingLimits) -> Result<DynamicImage> {
+    anyhow::bail!("HEIF support is not enabled")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_heif() {
+        assert!(is_heif(Path::new("IMG_0001.HEIC")));
+        assert!(is_heif(Path::new("$BLOBDIR/photo.heif")));
+        assert!(is_heif(Path::new("photo.avif")));
+        assert!(!is_heif(Path::new("photo.jpg")));
+        assert!(!is_heif(Path::new("heic")));
+    }
+}
@@ -0,0 +1,516 @@
+//! # Housekeeping job.
+//!
+//! Housekeeping removes unused blobs, prunes tombstones and caches and optimizes the database.
+//! It is started automatically once a day by the IO scheduler,
+//! but can also be started explicitly with [`start_housekeeping`],
+//! monitored with [`get_housekeeping_status`]
+//! and [`EventType::HousekeepingProgress`] events
+//! and cancelled with [`cancel_housekeeping`].
+//!
+//! Housekeeping started automatically pauses between steps while the app is in the foreground,
+//! see [`Context::set_foreground`], so it does not compete with user interactions.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _, Result};
+use serde::Serialize;
+use tokio::sync::{watch, Mutex, OwnedMutexGuard};
+
+use crate::chat::{Chat, ChatId, ChatVisibility, ProtectionStatus};
+use crate::config::Config;
+use crate::constants::{Blocked, Chattype, DC_CHAT_ID_LAST_SPECIAL, DC_CHAT_ID_TRASH};
+use crate::context::Context;
+use crate::ephemeral::start_ephemeral_timers;
+use crate::events::EventType;
+use crate::location::delete_orphaned_poi_locations;
+use crate::log::LogExt;
+use crate::net::dns::prune_dns_cache;
+use crate::net::latency::prune_delivery_times;
+use crate::net::prune_connection_history;
+use crate::sql;
+use crate::staging;
+use crate::sync::Sync::*;
+use crate::tools::time;
+
+/// Interval between automatic housekeeping runs in seconds.
+const HOUSEKEEPING_INTERVAL: i64 = 60 * 60 * 24;
+
+/// Step of the housekeeping job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    PruneStagedAttachments,
+    RemoveUnusedFiles,
+    StartEphemeralTimers,
+    PruneTombstones,
+    IncrementalVacuum,
+    PruneMdns,
+    PruneStatusUpdates,
+    PrunePendingQuotes,
+    PruneConnectionHistory,
+    PruneDnsCache,
+    PruneDeliveryTimes,
+    DeleteOrphanedPoiLocations,
+    ArchiveInactiveChats,
+    TruncateWal,
+}
+
+const STEPS: [Step; 14] = [
+    // Discarded staged attachments leave unused files.
+    Step::PruneStagedAttachments,
+    Step::RemoveUnusedFiles,
+    Step::StartEphemeralTimers,
+    Step::PruneTombstones,
+    Step::IncrementalVacuum,
+    Step::PruneMdns,
+    Step::PruneStatusUpdates,
+    Step::PrunePendingQuotes,
+    Step::PruneConnectionHistory,
+    Step::PruneDnsCache,
+    Step::PruneDeliveryTimes,
+    Step::DeleteOrphanedPoiLocations,
+    Step::ArchiveInactiveChats,
+    // Vacuuming and deleting above may have grown the WAL file.
+    Step::TruncateWal,
+];
+
+/// State of the housekeeping job.
+#[derive(Debug)]
+pub(crate) struct HousekeepingState {
+    /// Locked while housekeeping runs.
+    running: Arc<Mutex<()>>,
+
+    /// Set to request cancellation of the running job.
+    cancel: watch::Sender<bool>,
+
+    /// Progress of the running job in permille.
+    progress: AtomicUsize,
+
+    /// Whether the running job pauses while the app is in the foreground.
+    yields: AtomicBool,
+}
+
+impl Default for HousekeepingState {
+    fn default() -> Self {
+        Self {
+            running: Default::default(),
+            cancel: watch::Sender::new(false),
+            progress: AtomicUsize::new(0),
+            yields: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Status of the housekeeping job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HousekeepingStatus {
+    /// Whether housekeeping is currently running.
+    pub running: bool,
+
+    /// Progress of the running job in permille, 0 if not running.
+    pub progress: usize,
+
+    /// Whether the running job is paused because the app is in the foreground.
+    pub paused: bool,
+
+    /// Unix timestamp of the start of the last housekeeping run, 0 if it never ran.
+    pub last_run: i64,
+}
+
+/// Starts housekeeping in the background.
+///
+/// Unlike automatic housekeeping, housekeeping started this way
+/// does not pause while the app is in the foreground.
+/// Fails if housekeeping is already running.
+pub async fn start_housekeeping(context: &Context) -> Result<()> {
+    spawn(context, false)
+}
+
+/// Requests cancellation of the running housekeeping job.
+///
+/// The job stops after the current step
+/// and emits [`EventType::HousekeepingProgress`] with progress 0.
+pub async fn cancel_housekeeping(context: &Context) {
+    if is_running(context) {
+        info!(context, "Cancelling housekeeping.");
+        context.housekeeping.cancel.send_replace(true);
+    }
+}
+
+/// Returns the status of the housekeeping job.
+pub async fn get_housekeeping_status(context: &Context) -> Result<HousekeepingStatus> {
+    let state = &context.housekeeping;
+    let running = is_running(context);
+    Ok(HousekeepingStatus {
+        running,
+        progress: if running {
+            state.progress.load(Ordering::Relaxed)
+        } else {
+            0
+        },
+        paused: running && state.yields.load(Ordering::Relaxed) && *context.foreground.borrow(),
+        last_run: context.get_config_i64(Config::LastHousekeeping).await?,
+    })
+}
+
+/// Starts housekeeping in the background if it did not run for a day.
+///
+/// Housekeeping started this way pauses while the app is in the foreground.
+pub(crate) async fn maybe_start_housekeeping(context: &Context) {
+    match context.get_config_i64(Config::LastHousekeeping).await {
+        Ok(last_housekeeping_time) => {
+            let next_housekeeping_time =
+                last_housekeeping_time.saturating_add(HOUSEKEEPING_INTERVAL);
+            if next_housekeeping_time <= time() && !is_running(context) {
+                spawn(context, true).log_err(context).ok();
+            }
+        }
+        Err(err) => {
+            warn!(context, "Failed to get last housekeeping time: {}", err);
+        }
+    };
+}
+
+/// Runs housekeeping to completion without pausing.
+///
+/// A job running in the background is cancelled first.
+pub(crate) async fn run(context: &Context) -> Result<()> {
+    cancel_housekeeping(context).await;
+    let running = Arc::clone(&context.housekeeping.running).lock_owned().await;
+    run_job(context, false, running).await
+}
+
+fn is_running(context: &Context) -> bool {
+    context.housekeeping.running.try_lock().is_err()
+}
+
+/// Spawns the housekeeping job.
+///
+/// The job is marked as running before returning,
+/// so it cannot be spawned twice.
+fn spawn(context: &Context, yields: bool) -> Result<()> {
+    let running = Arc::clone(&context.housekeeping.running)
+        .try_lock_owned()
+        .ok()
+        .context("Housekeeping is already running")?;
+    let context = context.clone();
+    tokio::spawn(async move {
+        run_job(&context, yields, running)
+            .await
+            .log_err(&context)
+            .ok();
+    });
+    Ok(())
+}
+
+async fn run_job(context: &Context, yields: bool, _running: OwnedMutexGuard<()>) -> Result<()> {
+    let state = &context.housekeeping;
+    state.cancel.send_replace(false);
+    state.progress.store(0, Ordering::Relaxed);
+    state.yields.store(yields, Ordering::Relaxed);
+
+    // Setting `Config::LastHousekeeping` at the beginning avoids endless loops when things do not
+    // work out for whatever reason or are interrupted by the OS.
+    if let Err(e) = context
+        .set_config_internal(Config::LastHousekeeping, Some(&time().to_string()))
+        .await
+    {
+        warn!(context, "Can't set config: {e:#}.");
+    }
+
+    let res = run_steps(context, yields).await;
+    match res {
+        Ok(()) => {
+            info!(context, "Housekeeping done.");
+            context.emit_event(EventType::HousekeepingProgress(1000));
+        }
+        Err(_) => context.emit_event(EventType::HousekeepingProgress(0)),
+    }
+    res
+}
+
+async fn run_steps(context: &Context, yields: bool) -> Result<()> {
+    for (i, step) in STEPS.into_iter().enumerate() {
+        if yields {
+            yield_to_foreground(context).await?;
+        } else if *context.housekeeping.cancel.borrow() {
+            bail!("Housekeeping cancelled");
+        }
+
+        if let Err(err) = run_step(context, step).await {
+            warn!(context, "Housekeeping: step {step:?} failed: {err:#}.");
+        }
+
+        let progress = (i + 1) * 1000 / (STEPS.len() + 1);
+        context
+            .housekeeping
+            .progress
+            .store(progress, Ordering::Relaxed);
+        context.emit_event(EventType::HousekeepingProgress(progress));
+    }
+    Ok(())
+}
+
+/// Waits while the app is in the foreground.
+///
+/// Returns an error if the job is cancelled.
+async fn yield_to_foreground(context: &Context) -> Result<()> {
+    let mut foreground = context.foreground.subscribe();
+    let mut cancel = context.housekeeping.cancel.subscribe();
+    let mut paused = false;
+    loop {
+        if *cancel.borrow_and_update() {
+            bail!("Housekeeping cancelled");
+        }
+        if !*foreground.borrow_and_update() {
+            if paused {
+                info!(context, "Resuming housekeeping.");
+            }
+            return Ok(());
+        }
+        if !paused {
+            info!(
+                context,
+                "Pausing housekeeping while the app is in the foreground."
+            );
+            paused = true;
+        }
+        tokio::select! {
+            res = foreground.changed() => res?,
+            res = cancel.changed() => res?,
+        }
+    }
+}
+
+async fn run_step(context: &Context, step: Step) -> Result<()> {
+    match step {
+        Step::PruneStagedAttachments => staging::prune_staged_attachments(context).await,
+        Step::RemoveUnusedFiles => sql::remove_unused_files(context).await,
+        Step::StartEphemeralTimers => start_ephemeral_timers(context).await,
+        Step::PruneTombstones => sql::prune_tombstones(&context.sql).await,
+        Step::IncrementalVacuum => sql::incremental_vacuum(context).await,
+        Step::PruneMdns => {
+            context
+                .sql
+                .execute(
+                    "DELETE FROM msgs_mdns WHERE msg_id NOT IN \
+                    (SELECT id FROM msgs WHERE chat_id!=?)",
+                    (DC_CHAT_ID_TRASH,),
+                )
+                .await
+                .context("failed to remove old MDNs")?;
+            Ok(())
+        }
+        Step::PruneStatusUpdates => {
+            context
+                .sql
+                .execute(
+                    "DELETE FROM msgs_status_updates WHERE msg_id NOT IN \
+                    (SELECT id FROM msgs WHERE chat_id!=?)",
+                    (DC_CHAT_ID_TRASH,),
+                )
+                .await
+                .context("failed to remove old webxdc status updates")?;
+            Ok(())
+        }
+        Step::PrunePendingQuotes => {
+            context
+                .sql
+                .execute(
+                    "DELETE FROM pending_quotes WHERE msg_id NOT IN \
+                    (SELECT id FROM msgs WHERE chat_id!=?)",
+                    (DC_CHAT_ID_TRASH,),
+                )
+                .await
+                .context("failed to remove pending quotes of deleted messages")?;
+            Ok(())
+        }
+        Step::PruneConnectionHistory => prune_connection_history(context)
+            .await
+            .context("Failed to prune connection history"),
+        Step::PruneDnsCache => prune_dns_cache(context)
+            .await
+            .context("Failed to prune DNS cache"),
+        Step::PruneDeliveryTimes => prune_delivery_times(context)
+            .await
+            .context("Failed to prune delivery latency metrics"),
+        // Delete POI locations
+        // which don't have corresponding message.
+        Step::DeleteOrphanedPoiLocations => delete_orphaned_poi_locations(context)
+            .await
+            .context("Failed to delete orphaned POI locations"),
+        Step::ArchiveInactiveChats => archive_inactive_chats(context)
+            .await
+            .map(|_| ())
+            .context("Failed to archive inactive chats"),
+        Step::TruncateWal => sql::truncate_wal(context)
+            .await
+            .context("Failed to truncate WAL"),
+    }
+}
+
+/// Archives chats without activity for [`Config::AutoArchiveDays`] days.
+///
+/// Pinned chats, verified 1:1 chats, contact requests, Saved Messages
+/// and the device chat are skipped.
+/// Emits [`EventType::ChatsAutoArchived`] if any chats were archived.
+async fn archive_inactive_chats(context: &Context) -> Result<Vec<ChatId>> {
+    let days = context.get_config_i64(Config::AutoArchiveDays).await?;
+    if days <= 0 {
+        return Ok(Vec::new());
+    }
+    let threshold = time().saturating_sub(days.saturating_mul(24 * 60 * 60));
+    let candidates = context
+        .sql
+        .query_map(
+            "SELECT c.id FROM chats c
+             WHERE c.id>? AND c.archived=? AND c.blocked=?
+             AND NOT (c.type=? AND c.protected=?)
+             AND IFNULL(
+                 (SELECT MAX(m.timestamp) FROM msgs m WHERE m.chat_id=c.id AND m.hidden=0),
+                 c.created_timestamp
+             )<?",
+            (
+                DC_CHAT_ID_LAST_SPECIAL,
+                ChatVisibility::Normal,
+                Blocked::Not,
+                Chattype::Single,
+                ProtectionStatus::Protected,
+                threshold,
+            ),
+            |row| row.get::<_, ChatId>(0),
+            |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+
+    let mut archived = Vec::new();
+    for chat_id in candidates {
+        let chat = Chat::load_from_db(context, chat_id).await?;
+        if chat.is_self_talk() || chat.is_device_talk() {
+            continue;
+        }
+        // Every device archives inactive chats on its own.
+        chat_id
+            .set_visibility_ex(context, Nosync, ChatVisibility::Archived)
+            .await?;
+        archived.push(chat_id);
+    }
+    if !archived.is_empty() {
+        info!(context, "Archived inactive chats {archived:?}.");
+        context.emit_event(EventType::ChatsAutoArchived {
+            chat_ids: archived.clone(),
+        });
+    }
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::chat;
+    use crate::message::Message;
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_housekeeping_job() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        start_housekeeping(&t).await?;
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::HousekeepingProgress(1000)))
+            .await;
+        let status = get_housekeeping_status(&t).await?;
+        assert!(!status.running);
+        assert!(status.last_run > 0);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_housekeeping_yields_to_foreground() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_foreground(true).await;
+        maybe_start_housekeeping(&t).await;
+
+        // The job waits for the app to go to the background.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = get_housekeeping_status(&t).await?;
+        assert!(status.running);
+        assert!(status.paused);
+        assert_eq!(status.progress, 0);
+
+        cancel_housekeeping(&t).await;
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::HousekeepingProgress(0)))
+            .await;
+
+        // The job only runs once a day.
+        t.set_foreground(false).await;
+        maybe_start_housekeeping(&t).await;
+        assert!(!get_housekeeping_status(&t).await?.running);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_archive_inactive_chats() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let bob_chat_id = alice.create_chat(bob).await.id;
+        alice.send_text(bob_chat_id, "Hi Bob").await;
+        let fiona_chat_id = alice.create_chat(fiona).await.id;
+        alice.send_text(fiona_chat_id, "Hi Fiona").await;
+        fiona_chat_id
+            .set_visibility(alice, ChatVisibility::Pinned)
+            .await?;
+        let self_chat_id = alice.get_self_chat().await.id;
+        alice.send_text(self_chat_id, "Note to self").await;
+        let mut device_msg = Message::new_text("Device message".to_string());
+        let device_msg_id = chat::add_device_msg(alice, None, Some(&mut device_msg)).await?;
+        let device_chat_id = Message::load_from_db(alice, device_msg_id).await?.chat_id;
+        let old = time() - 40 * 24 * 60 * 60;
+        alice
+            .sql
+            .execute("UPDATE msgs SET timestamp=?", (old,))
+            .await?;
+        alice
+            .sql
+            .execute("UPDATE chats SET created_timestamp=?", (old,))
+            .await?;
+
+        // Disabled by default.
+        assert!(archive_inactive_chats(alice).await?.is_empty());
+
+        alice
+            .set_config(Config::AutoArchiveDays, Some("30"))
+            .await?;
+        let archived = archive_inactive_chats(alice).await?;
+        assert!(archived.contains(&bob_chat_id));
+        assert!(!archived.contains(&fiona_chat_id));
+        assert!(!archived.contains(&self_chat_id));
+        assert!(!archived.contains(&device_chat_id));
+        let event = alice
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::ChatsAutoArchived { .. }))
+            .await;
+        assert_eq!(event, EventType::ChatsAutoArchived { chat_ids: archived });
+        assert_eq!(
+            Chat::load_from_db(alice, bob_chat_id)
+                .await?
+                .get_visibility(),
+            ChatVisibility::Archived
+        );
+
+        // Chats with recent activity are not archived.
+        alice
+            .set_config(Config::AutoArchiveDays, Some("50"))
+            .await?;
+        bob_chat_id
+            .set_visibility(alice, ChatVisibility::Normal)
+            .await?;
+        assert!(archive_inactive_chats(alice).await?.is_empty());
+        Ok(())
+    }
+}
@@ -6,10 +6,10 @@
 use std::{
     cmp::max,
     cmp::min,
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     iter::Peekable,
     mem::take,
-    sync::atomic::Ordering,
+    sync::{atomic::Ordering, Arc},
     time::{Duration, UNIX_EPOCH},
 };
 
@@ -30,17 +30,23 @@
 use crate::constants::{self, Blocked, Chattype, ShowEmails};
 use crate::contact::{Contact, ContactId, Modifier, Origin};
 use crate::context::Context;
+use crate::decrypt;
+use crate::ephemeral;
+use crate::error::ClassifiedError;
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
+use crate::key::load_self_secret_keyring;
 use crate::log::LogExt;
 use crate::login_param::{
-    prioritize_server_login_params, ConfiguredLoginParam, ConfiguredServerLoginParam,
+    preferred_server_login_param_failed, select_server_login_params,
+    set_preferred_server_login_param, ConfiguredLoginParam, ConfiguredServerLoginParam,
 };
 use crate::message::{self, Message, MessageState, MessengerMessage, MsgId};
 use crate::mimeparser;
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionStream;
 use crate::oauth2::get_oauth2_access_token;
+use crate::provider;
 use crate::push::encrypt_device_token;
 use crate::receive_imf::{
     from_field_to_contact_id, get_prefetch_parent_message, receive_imf_inner, ReceivedMsg,
@@ -92,6 +98,13 @@ pub(crate) struct Imap {
 
     authentication_failed_once: bool,
 
+    /// Value of [`Context::network_generation`] when connecting last time.
+    network_generation: u64,
+
+    /// Number of consecutive failures to connect, used to decide
+    /// when to check the provider status endpoint.
+    connection_failures: u32,
+
     pub(crate) connectivity: ConnectivityStore,
 
     conn_last_try: tools::Time,
@@ -124,6 +137,14 @@ pub(crate) struct ServerMetadata {
     pub admin: Option<String>,
 
     pub iroh_relay: Option<Url>,
+
+    /// Maximum size of a message in bytes announced by chatmail servers
+    /// in `/shared/vendor/deltachat/maxmessagesize`.
+    pub max_message_size: Option<u64>,
+
+    /// Number of days after which chatmail servers delete messages,
+    /// announced in `/shared/vendor/deltachat/retentiondays`.
+    pub retention_days: Option<u32>,
 }
 
 impl async_imap::Authenticator for OAuth2 {
@@ -234,6 +255,12 @@ fn next(&mut self) -> Option<Self::Item> {
     }
 }
 
+/// Returns the ratelimit for IMAP connection attempts.
+fn new_connection_ratelimit() -> Ratelimit {
+    // 1 connection per minute + a burst of 2.
+    Ratelimit::new(Duration::new(120, 0), 2.0)
+}
+
 impl Imap {
     /// Creates new disconnected IMAP client using the specific login parameters.
     ///
@@ -256,11 +283,12 @@ pub fn new(
             strict_tls,
             oauth2,
             authentication_failed_once: false,
+            network_generation: 0,
+            connection_failures: 0,
             connectivity: Default::default(),
             conn_last_try: UNIX_EPOCH,
             conn_backoff_ms: 0,
-            // 1 connection per minute + a burst of 2.
-            ratelimit: Ratelimit::new(Duration::new(120, 0), 2.0),
+            ratelimit: new_connection_ratelimit(),
         }
     }
 
@@ -273,7 +301,7 @@ pub async fn new_configured(
             .await?
             .context("Not configured")?;
         let imap = Self::new(
-            param.imap.clone(),
+            param.imap_candidates(context).await?,
             param.imap_password.clone(),
             param.proxy_config.clone(),
             &param.addr,
@@ -296,6 +324,19 @@ pub(crate) async fn connect(
         context: &Context,
         configuring: bool,
     ) -> Result<Session> {
+        if self.network_changed(context) {
+            self.network_generation = context.network_generation.load(Ordering::Relaxed);
+            info!(
+                context,
+                "Network changed, resetting IMAP connection backoff."
+            );
+            self.conn_backoff_ms = 0;
+            self.ratelimit = new_connection_ratelimit();
+            if !configuring {
+                self.proxy_config = ProxyConfig::load(context).await?;
+            }
+        }
+
         let now = tools::Time::now();
         let until_can_send = max(
             min(self.conn_last_try, now)
@@ -337,7 +378,7 @@ pub(crate) async fn connect(
         );
         self.conn_backoff_ms = max(BACKOFF_MIN_MS, self.conn_backoff_ms);
 
-        let login_params = prioritize_server_login_params(&context.sql, &self.lp, "imap").await?;
+        let login_params = select_server_login_params(context, &self.lp, "imap").await?;
         let mut first_error = None;
         for lp in login_params {
             info!(context, "IMAP trying to connect to {}.", &lp.connection);
@@ -353,10 +394,18 @@ pub(crate) async fn connect(
                 Ok(client) => client,
                 Err(err) => {
                     warn!(context, "IMAP failed to connect: {err:#}.");
+                    preferred_server_login_param_failed(context, "imap", &lp)
+                        .await
+                        .log_err(context)
+                        .ok();
                     first_error.get_or_insert(err);
                     continue;
                 }
             };
+            set_preferred_server_login_param(context, "imap", &lp)
+                .await
+                .log_err(context)
+                .ok();
 
             self.conn_backoff_ms = BACKOFF_MIN_MS;
             self.ratelimit.send();
@@ -404,6 +453,7 @@ pub(crate) async fn connect(
                     lock.clone_from(&session.capabilities.server_id);
 
                     self.authentication_failed_once = false;
+                    context.health_counters.on_login(false);
                     context.emit_event(EventType::ImapConnected(format!(
                         "IMAP-LOGIN as {}",
                         lp.user
@@ -418,11 +468,14 @@ pub(crate) async fn connect(
                     let message = stock_str::cannot_login(context, &imap_user).await;
 
                     warn!(context, "IMAP failed to login: {err:#}.");
-                    first_error.get_or_insert(format_err!("{message} ({err:#})"));
+                    first_error.get_or_insert_with(|| {
+                        ClassifiedError::with_message(&err, format!("{message} ({err:#})"))
+                    });
 
                     // If it looks like the password is wrong, send a notification:
                     let _lock = context.wrong_pw_warning_mutex.lock().await;
                     if err.to_string().to_lowercase().contains("authentication") {
+                        context.health_counters.on_login(true);
                         if self.authentication_failed_once
                             && !configuring
                             && context.get_config_bool(Config::NotifyAboutWrongPw).await?
@@ -457,6 +510,12 @@ pub(crate) async fn connect(
         Err(first_error.unwrap_or_else(|| format_err!("No IMAP connection candidates provided")))
     }
 
+    /// Returns true if the network changed since connecting last time,
+    /// so the existing session is likely broken.
+    pub(crate) fn network_changed(&self, context: &Context) -> bool {
+        self.network_generation != context.network_generation.load(Ordering::Relaxed)
+    }
+
     /// Prepare for IMAP operation.
     ///
     /// Ensure that IMAP client is connected, folders are created and IMAP capabilities are
@@ -464,9 +523,19 @@ pub(crate) async fn connect(
     pub(crate) async fn prepare(&mut self, context: &Context) -> Result<Session> {
         let configuring = false;
         let mut session = match self.connect(context, configuring).await {
-            Ok(session) => session,
+            Ok(session) => {
+                self.connection_failures = 0;
+                session
+            }
             Err(err) => {
                 self.connectivity.set_err(context, &err).await;
+                self.connection_failures = self.connection_failures.saturating_add(1);
+                if self.connection_failures >= provider::status::CONNECTION_FAILURES_BEFORE_CHECK {
+                    provider::status::maybe_check(context)
+                        .await
+                        .log_err(context)
+                        .ok();
+                }
                 return Err(err);
             }
         };
@@ -508,7 +577,10 @@ pub async fn fetch_move_delete(
             .fetch_new_messages(context, session, watch_folder, folder_meaning, false)
             .await
             .context("fetch_new_messages")?;
-        if msgs_fetched && context.get_config_delete_device_after().await?.is_some() {
+        if msgs_fetched
+            && (context.get_config_delete_device_after().await?.is_some()
+                || ephemeral::has_retention_count_policies(context).await?)
+        {
             // New messages were fetched and shall be deleted later, restart ephemeral loop.
             // Note that the `Config::DeleteDeviceAfter` timer starts as soon as the messages are
             // fetched while the per-chat ephemeral timers start as soon as the messages are marked
@@ -516,6 +588,13 @@ pub async fn fetch_move_delete(
             context.scheduler.interrupt_ephemeral_task().await;
         }
 
+        session
+            .detect_vanished_messages(context, watch_folder)
+            .await
+            .context("detect_vanished_messages")
+            .log_err(context)
+            .ok();
+
         session
             .move_delete_messages(context, watch_folder)
             .await
@@ -876,9 +955,16 @@ pub(crate) async fn resync_folder_uids(
         let uid_validity = get_uidvalidity(context, folder).await?;
 
         // Write collected UIDs to SQLite database.
-        context
+        let removed_cnt = context
             .sql
             .transaction(move |transaction| {
+                // Messages we knew in this folder and did not move or delete ourselves.
+                // If they are gone after the resync, another client removed them.
+                let known_mids = transaction
+                    .prepare("SELECT rfc724_mid FROM imap WHERE folder=? AND target=folder")?
+                    .query_map((folder,), |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
                 transaction.execute("DELETE FROM imap WHERE folder=?", (folder,))?;
                 for (uid, (rfc724_mid, target)) in &msgs {
                     // This may detect previously undetected moved
@@ -891,10 +977,74 @@ pub(crate) async fn resync_folder_uids(
                                        target=excluded.target",
                         (rfc724_mid, folder, uid, uid_validity, target),
                     )?;
+                    transaction.execute(
+                        "UPDATE msgs SET removed_from_server=0
+                         WHERE rfc724_mid=? AND removed_from_server=1",
+                        (rfc724_mid,),
+                    )?;
                 }
-                Ok(())
+                let mut removed_cnt = 0;
+                for rfc724_mid in &known_mids {
+                    removed_cnt += transaction.execute(
+                        "UPDATE msgs SET removed_from_server=1
+                         WHERE rfc724_mid=?1 AND removed_from_server=0
+                         AND NOT EXISTS (SELECT 1 FROM imap WHERE rfc724_mid=?1)",
+                        (rfc724_mid,),
+                    )?;
+                }
+                Ok(removed_cnt)
             })
             .await?;
+        if removed_cnt > 0 {
+            info!(
+                context,
+                "Resync: {removed_cnt} messages were removed from folder {folder} by another client."
+            );
+            context.emit_msgs_changed_without_ids();
+        }
+        Ok(())
+    }
+
+    /// Detects messages which vanished from the selected `folder`,
+    /// i.e. were expunged by another client, see [`remove_vanished_uids`].
+    ///
+    /// The UIDs on the server are only listed
+    /// if the folder has fewer messages than known to be in it,
+    /// so this is cheap as long as no messages vanish.
+    pub(crate) async fn detect_vanished_messages(
+        &mut self,
+        context: &Context,
+        folder: &str,
+    ) -> Result<()> {
+        if self.selected_folder.as_deref() != Some(folder) {
+            return Ok(());
+        }
+        let Some(exists) = self.selected_mailbox.as_ref().map(|mailbox| mailbox.exists) else {
+            return Ok(());
+        };
+        let uid_validity = get_uidvalidity(context, folder).await?;
+        let known_cnt = context
+            .sql
+            .count(
+                "SELECT COUNT(*) FROM imap WHERE folder=? AND uidvalidity=?",
+                (folder, uid_validity),
+            )
+            .await?;
+        if known_cnt <= usize::try_from(exists)? {
+            return Ok(());
+        }
+
+        let server_uids = self
+            .uid_search("ALL")
+            .await
+            .with_context(|| format!("Failed to list UIDs in {folder:?}"))?;
+        let removed_cnt = remove_vanished_uids(context, folder, uid_validity, &server_uids).await?;
+        if removed_cnt > 0 {
+            info!(
+                context,
+                "{removed_cnt} messages were removed from folder {folder} by another client."
+            );
+        }
         Ok(())
     }
 
@@ -1319,6 +1469,15 @@ pub(crate) async fn fetch_many_msgs(
             return Ok((last_uid, received_msgs));
         }
 
+        // Encrypted messages are decrypted in parallel ahead of time
+        // while the messages before them are received.
+        let read_ahead = if fetch_partially {
+            0
+        } else {
+            decrypt::decryption_parallelism()
+        };
+        let private_keyring = Arc::new(load_self_secret_keyring(context).await?);
+
         for (request_uids, set) in build_sequence_sets(&request_uids)? {
             info!(
                 context,
@@ -1344,13 +1503,17 @@ pub(crate) async fn fetch_many_msgs(
             // when we want to process other messages first.
             let mut uid_msgs = HashMap::with_capacity(request_uids.len());
 
+            // Map from UIDs to decryptions running in the background.
+            let mut decryptions = HashMap::new();
+
             let mut count = 0;
             for &request_uid in &request_uids {
                 // Check if FETCH response is already in `uid_msgs`.
                 let mut fetch_response = uid_msgs.remove(&request_uid);
 
-                // Try to find a requested UID in returned FETCH responses.
-                while fetch_response.is_none() {
+                // Try to find a requested UID in returned FETCH responses
+                // and read ahead the following ones to start decrypting them.
+                while fetch_response.is_none() || uid_msgs.len() < read_ahead {
                     let next_fetch_response =
                         if let Some(next_fetch_response) = fetch_responses.next().await {
                             next_fetch_response
@@ -1363,6 +1526,19 @@ pub(crate) async fn fetch_many_msgs(
                         next_fetch_response.context("Failed to process IMAP FETCH result")?;
 
                     if let Some(next_uid) = next_fetch_response.uid {
+                        if read_ahead > 0
+                            && request_uids.contains(&next_uid)
+                            && !next_fetch_response
+                                .flags()
+                                .any(|flag| flag == Flag::Deleted)
+                        {
+                            if let Some(decryption) = next_fetch_response.body().and_then(|body| {
+                                decrypt::spawn_decryption(body, Arc::clone(&private_keyring))
+                            }) {
+                                decryptions.insert(next_uid, decryption);
+                            }
+                        }
+
                         if next_uid == request_uid {
                             fetch_response = Some(next_fetch_response);
                         } else if !request_uids.contains(&next_uid) {
@@ -1433,6 +1609,13 @@ pub(crate) async fn fetch_many_msgs(
                     continue;
                 };
 
+                let predecrypted = match decryptions.remove(&request_uid) {
+                    Some(decryption) => {
+                        Some(decryption.await.map_err(Into::into).and_then(|res| res))
+                    }
+                    None => None,
+                };
+
                 info!(
                     context,
                     "Passing message UID {} to receive_imf().", request_uid
@@ -1447,6 +1630,7 @@ pub(crate) async fn fetch_many_msgs(
                     is_seen,
                     partial,
                     fetching_existing_messages,
+                    predecrypted,
                 )
                 .await
                 {
@@ -1509,6 +1693,8 @@ pub(crate) async fn fetch_metadata(&mut self, context: &Context) -> Result<()> {
         let mut comment = None;
         let mut admin = None;
         let mut iroh_relay = None;
+        let mut max_message_size = None;
+        let mut retention_days = None;
 
         let mailbox = "";
         let options = "";
@@ -1516,7 +1702,8 @@ pub(crate) async fn fetch_metadata(&mut self, context: &Context) -> Result<()> {
             .get_metadata(
                 mailbox,
                 options,
-                "(/shared/comment /shared/admin /shared/vendor/deltachat/irohrelay)",
+                "(/shared/comment /shared/admin /shared/vendor/deltachat/irohrelay \
+                 /shared/vendor/deltachat/maxmessagesize /shared/vendor/deltachat/retentiondays)",
             )
             .await?;
         for m in metadata {
@@ -1539,6 +1726,12 @@ pub(crate) async fn fetch_metadata(&mut self, context: &Context) -> Result<()> {
                         }
                     }
                 }
+                "/shared/vendor/deltachat/maxmessagesize" => {
+                    max_message_size = m.value.and_then(|value| value.trim().parse().ok());
+                }
+                "/shared/vendor/deltachat/retentiondays" => {
+                    retention_days = m.value.and_then(|value| value.trim().parse().ok());
+                }
                 _ => {}
             }
         }
@@ -1546,6 +1739,8 @@ pub(crate) async fn fetch_metadata(&mut self, context: &Context) -> Result<()> {
             comment,
             admin,
             iroh_relay,
+            max_message_size,
+            retention_days,
         });
         Ok(())
     }
@@ -2389,6 +2584,58 @@ pub(crate) async fn set_uidvalidity(
     Ok(())
 }
 
+/// Removes the UIDs of `folder` which are not in `server_uids` from the database
+/// and flags the messages which vanished this way as removed from the server,
+/// unless they were to be moved or deleted by us anyway.
+///
+/// Returns the number of flagged messages.
+pub(crate) async fn remove_vanished_uids(
+    context: &Context,
+    folder: &str,
+    uid_validity: u32,
+    server_uids: &HashSet<u32>,
+) -> Result<usize> {
+    let server_uids = server_uids.clone();
+    let folder = folder.to_string();
+    let removed_cnt = context
+        .sql
+        .transaction(move |transaction| {
+            let vanished = transaction
+                .prepare(
+                    "SELECT id, uid, rfc724_mid, target=folder FROM imap
+                     WHERE folder=? AND uidvalidity=?",
+                )?
+                .query_map((&folder, uid_validity), |row| {
+                    let id: i64 = row.get(0)?;
+                    let uid: u32 = row.get(1)?;
+                    let rfc724_mid: String = row.get(2)?;
+                    let kept: bool = row.get(3)?;
+                    Ok((id, uid, rfc724_mid, kept))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|(_, uid, _, _)| !server_uids.contains(uid));
+            let mut removed_cnt = 0;
+            for (id, _, rfc724_mid, kept) in vanished {
+                transaction.execute("DELETE FROM imap WHERE id=?", (id,))?;
+                if kept {
+                    removed_cnt += transaction.execute(
+                        "UPDATE msgs SET removed_from_server=1
+                         WHERE rfc724_mid=?1 AND removed_from_server=0
+                         AND NOT EXISTS (SELECT 1 FROM imap WHERE rfc724_mid=?1)",
+                        (rfc724_mid,),
+                    )?;
+                }
+            }
+            Ok(removed_cnt)
+        })
+        .await?;
+    if removed_cnt > 0 {
+        context.emit_msgs_changed_without_ids();
+    }
+    Ok(removed_cnt)
+}
+
 async fn get_uidvalidity(context: &Context, folder: &str) -> Result<u32> {
     Ok(context
         .sql
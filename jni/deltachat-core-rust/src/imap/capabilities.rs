@@ -44,6 +44,11 @@ pub(crate) struct Capabilities {
     /// e.g. the ability to move messages to Delta Chat folder.
     pub is_chatmail: bool,
 
+    /// Maximum size of a message which can be appended in bytes
+    /// if the server has APPENDLIMIT capability as defined in
+    /// <https://tools.ietf.org/html/rfc7889>
+    pub append_limit: Option<u64>,
+
     /// Server ID if the server supports ID capability.
     pub server_id: Option<HashMap<String, String>>,
 }
@@ -2,6 +2,7 @@
 use std::ops::{Deref, DerefMut};
 
 use anyhow::{Context as _, Result};
+use async_imap::types::Capability;
 use async_imap::Client as ImapClient;
 use async_imap::Session as ImapSession;
 use tokio::io::BufWriter;
@@ -71,11 +72,23 @@ pub(crate) async fn determine_capabilities(
         can_compress: caps.has_str("COMPRESS=DEFLATE"),
         can_push: caps.has_str("XDELTAPUSH"),
         is_chatmail: caps.has_str("XCHATMAIL"),
+        append_limit: parse_append_limit(caps.iter()),
         server_id,
     };
     Ok(capabilities)
 }
 
+/// Returns the maximum message size announced with the APPENDLIMIT capability, see RFC 7889.
+///
+/// APPENDLIMIT without a value means that the limit differs between mailboxes,
+/// it is treated as unknown.
+fn parse_append_limit<'a>(mut caps: impl Iterator<Item = &'a Capability>) -> Option<u64> {
+    caps.find_map(|cap| match cap {
+        Capability::Atom(atom) => atom.strip_prefix("APPENDLIMIT=")?.parse().ok(),
+        _ => None,
+    })
+}
+
 impl Client {
     fn new(stream: Box<dyn SessionStream>) -> Self {
         Self {
@@ -324,3 +337,26 @@ async fn connect_starttls_proxy(
         Ok(client)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_append_limit() {
+        let caps = |atoms: &[&str]| -> Vec<Capability> {
+            atoms
+                .iter()
+                .map(|atom| Capability::Atom(atom.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            parse_append_limit(caps(&["IDLE", "APPENDLIMIT=35882577"]).iter()),
+            Some(35882577)
+        );
+        assert_eq!(parse_append_limit(caps(&["IDLE", "MOVE"]).iter()), None);
+        assert_eq!(parse_append_limit(caps(&["APPENDLIMIT"]).iter()), None);
+        assert_eq!(parse_append_limit(caps(&["APPENDLIMIT=big"]).iter()), None);
+        assert_eq!(parse_append_limit([Capability::Imap4rev1].iter()), None);
+    }
+}
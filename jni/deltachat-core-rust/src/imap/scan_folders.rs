@@ -9,6 +9,9 @@
 use crate::tools::{self, time_elapsed};
 use crate::{context::Context, imap::FolderMeaning};
 
+/// Maximum time in seconds the full folder scan is deferred while the app is in the foreground.
+const FOREGROUND_SCAN_DEFER_SECS: u64 = 60 * 60;
+
 impl Imap {
     /// Returns true if folders were scanned, false if scanning was postponed.
     pub(crate) async fn scan_folders(
@@ -27,6 +30,12 @@ pub(crate) async fn scan_folders(
             if elapsed_secs < debounce_secs {
                 return Ok(false);
             }
+
+            // Do not compete with user interactions,
+            // but do not postpone the scan forever either.
+            if context.is_foreground() && elapsed_secs < FOREGROUND_SCAN_DEFER_SECS {
+                return Ok(false);
+            }
         }
         info!(context, "Starting full folder scan");
 
@@ -94,6 +103,40 @@ pub(crate) async fn scan_folders(
         last_scan.replace(tools::Time::now());
         Ok(true)
     }
+
+    /// Fetches the folder containing the latest message of the interactive chat
+    /// if it is not watched, so new messages of this chat do not wait for the full folder scan.
+    pub(crate) async fn fetch_interactive_chat_folder(
+        &mut self,
+        context: &Context,
+        session: &mut Session,
+    ) -> Result<()> {
+        let Some(chat_id) = context.get_interactive_chat() else {
+            return Ok(());
+        };
+        let Some(folder): Option<String> = context
+            .sql
+            .query_get_value(
+                "SELECT imap.folder FROM imap, msgs
+                 WHERE msgs.chat_id=? AND imap.rfc724_mid=msgs.rfc724_mid
+                 ORDER BY msgs.timestamp DESC LIMIT 1",
+                (chat_id,),
+            )
+            .await?
+        else {
+            return Ok(());
+        };
+        if get_watched_folders(context).await?.contains(&folder) {
+            return Ok(());
+        }
+        let folder_meaning = get_folder_meaning_by_name(&folder);
+        if folder_meaning == FolderMeaning::Drafts || folder_meaning == FolderMeaning::Trash {
+            return Ok(());
+        }
+        self.fetch_move_delete(context, session, &folder, folder_meaning)
+            .await
+            .with_context(|| format!("Can't fetch interactive chat folder {folder:?}"))
+    }
 }
 
 pub(crate) async fn get_watched_folder_configs(context: &Context) -> Result<Vec<Config>> {
@@ -101,6 +101,11 @@ pub fn can_push(&self) -> bool {
         self.capabilities.can_push
     }
 
+    /// Returns the maximum size of a message the server accepts, if it announces it.
+    pub fn append_limit(&self) -> Option<u64> {
+        self.capabilities.append_limit
+    }
+
     // Returns true if IMAP server has `XCHATMAIL` capability.
     pub fn is_chatmail(&self) -> bool {
         self.capabilities.is_chatmail
@@ -3,20 +3,26 @@
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
 use ::pgp::types::PublicKeyTrait;
 use anyhow::{bail, ensure, format_err, Context as _, Result};
 use futures::TryStreamExt;
 use futures_lite::FutureExt;
 use pin_project::pin_project;
-
+use rusqlite::{OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
 use tokio::fs::{self, File};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio_tar::Archive;
 
+use crate::activity::{self, ActivityKind};
 use crate::blob::BlobDirContents;
 use crate::chat::{self, delete_and_reset_all_device_msgs};
 use crate::config::Config;
+use crate::constants::DC_CHAT_ID_LAST_SPECIAL;
+use crate::contact::ContactId;
 use crate::context::Context;
 use crate::e2ee;
 use crate::events::EventType;
@@ -26,17 +32,20 @@
 use crate::pgp;
 use crate::sql;
 use crate::tools::{
-    create_folder, delete_file, get_filesuffix_lc, read_file, time, write_file, TempPathGuard,
+    self, create_folder, delete_file, get_filesuffix_lc, read_file, time, time_elapsed, write_file,
+    TempPathGuard,
 };
 
+mod identity;
 mod key_transfer;
+mod merge;
 mod transfer;
 
 pub use key_transfer::{continue_key_transfer, initiate_key_transfer};
 pub use transfer::{get_backup, BackupProvider};
 
 // Name of the database file in the backup.
-const DBFILE_BACKUP_NAME: &str = "dc_database_backup.sqlite";
+pub(crate) const DBFILE_BACKUP_NAME: &str = "dc_database_backup.sqlite";
 pub(crate) const BLOBS_BACKUP_NAME: &str = "blobs_backup";
 
 /// Import/export command.
@@ -65,6 +74,23 @@ pub enum ImexMode {
     /// created by DC_IMEX_EXPORT_BACKUP and detected by imex_has_backup(). Importing a backup
     /// is only possible as long as the context is not configured or used in another way.
     ImportBackup = 12,
+
+    /// `path` is the backup file to merge into the configured account.
+    /// Chats, contacts and messages missing in the account are added,
+    /// messages are deduplicated by their Message-ID and groups by their group ID.
+    /// Keys and settings of the account are not changed.
+    MergeBackup = 13,
+
+    /// Export an identity bundle encrypted with the given `passphrase`
+    /// to the directory given as `path`.
+    /// The bundle contains the own keys, the verified keys of contacts and the login settings,
+    /// but no messages.
+    /// The name of the bundle is `delta-chat-identity-<addr>.asc`.
+    ExportIdentity = 21,
+
+    /// `path` is the identity bundle file to import, created by `ExportIdentity`.
+    /// Importing an identity bundle is only possible as long as the context is not configured.
+    ImportIdentity = 22,
 }
 
 /// Import/export things.
@@ -139,6 +165,157 @@ pub async fn has_backup(_context: &Context, dir_name: &Path) -> Result<String> {
     }
 }
 
+/// Summary of a verified backup, see [`verify_backup`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BackupInfo {
+    /// Address of the account the backup was made of.
+    pub addr: Option<String>,
+
+    /// Unix timestamp of the backup.
+    pub backup_time: i64,
+
+    /// Version of the database schema.
+    pub db_version: i32,
+
+    /// Number of messages, excluding special and trashed ones.
+    pub msg_count: usize,
+
+    /// Number of chats, excluding special ones.
+    pub chat_count: usize,
+
+    /// Number of contacts, excluding special ones.
+    pub contact_count: usize,
+
+    /// Number of blobs.
+    pub blob_count: usize,
+
+    /// Total size of the blobs in bytes.
+    pub blob_bytes: u64,
+}
+
+/// Verifies the backup file at `path` without importing it.
+///
+/// Reads the whole archive, checking that no entry is truncated,
+/// opens the contained database with `passphrase`, empty if the backup is unencrypted,
+/// and runs an integrity check on it.
+/// Returns an error if any check fails, otherwise a summary of the backup contents.
+pub async fn verify_backup(
+    context: &Context,
+    path: &Path,
+    passphrase: String,
+) -> Result<BackupInfo> {
+    let context_dir = context
+        .get_blobdir()
+        .parent()
+        .context("Context dir not found")?;
+    // Several backups may be verified at the same time.
+    let dbfile = TempPathGuard::new(
+        context_dir.join(format!("dc_database_verify-{}.sqlite", tools::create_id())),
+    );
+
+    let backup_file = File::open(path)
+        .await
+        .with_context(|| format!("Failed to open backup {}", path.display()))?;
+    let mut archive = Archive::new(backup_file);
+    let mut entries = archive.entries().context("Failed to get archive entries")?;
+    let mut info = BackupInfo::default();
+    let mut has_db = false;
+    while let Some(mut entry) = entries
+        .try_next()
+        .await
+        .context("Failed to get next entry")?
+    {
+        let is_db = entry
+            .path()
+            .context("Failed to get entry path")?
+            .file_name()
+            == Some(OsStr::new(DBFILE_BACKUP_NAME));
+        if is_db {
+            entry
+                .unpack(&*dbfile)
+                .await
+                .context("Failed to unpack database")?;
+            has_db = true;
+            continue;
+        }
+        let size = entry.header().size().context("Invalid entry size")?;
+        let read = tokio::io::copy(&mut entry, &mut tokio::io::sink())
+            .await
+            .context("Failed to read entry")?;
+        ensure!(read == size, "Backup entry is truncated");
+        if entry.header().entry_type().is_file() {
+            info.blob_count += 1;
+            info.blob_bytes += size;
+        }
+    }
+    ensure!(has_db, "Backup contains no database");
+
+    let dbpath = dbfile.to_path_buf();
+    let db_info = tokio::task::spawn_blocking(move || -> Result<BackupInfo> {
+        let conn = rusqlite::Connection::open_with_flags(&dbpath, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context("Failed to open backup database")?;
+        if !passphrase.is_empty() {
+            conn.pragma_update(None, "key", &passphrase)?;
+        }
+        let integrity: String = conn
+            .query_row("PRAGMA integrity_check", (), |row| row.get(0))
+            .context("Failed to read backup database, wrong passphrase?")?;
+        ensure!(
+            integrity == "ok",
+            "Backup database is corrupted: {integrity}"
+        );
+
+        let get_config = |key: &str| -> Result<Option<String>> {
+            let value = conn
+                .query_row("SELECT value FROM config WHERE keyname=?", (key,), |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            Ok(value)
+        };
+        let count = |sql: &str, param: u32| -> Result<usize> {
+            let count: i64 = conn.query_row(sql, (param,), |row| row.get(0))?;
+            Ok(usize::try_from(count)?)
+        };
+        Ok(BackupInfo {
+            addr: get_config("configured_addr")?,
+            backup_time: get_config("backup_time")?
+                .and_then(|time| time.parse().ok())
+                .unwrap_or_default(),
+            db_version: get_config("dbversion")?
+                .and_then(|version| version.parse().ok())
+                .unwrap_or_default(),
+            msg_count: count(
+                "SELECT COUNT(*) FROM msgs WHERE chat_id>?",
+                DC_CHAT_ID_LAST_SPECIAL.to_u32(),
+            )?,
+            chat_count: count(
+                "SELECT COUNT(*) FROM chats WHERE id>?",
+                DC_CHAT_ID_LAST_SPECIAL.to_u32(),
+            )?,
+            contact_count: count(
+                "SELECT COUNT(*) FROM contacts WHERE id>?",
+                ContactId::LAST_SPECIAL.to_u32(),
+            )?,
+            ..Default::default()
+        })
+    })
+    .await??;
+
+    info!(
+        context,
+        "Verified backup {}: {} messages, {} blobs.",
+        path.display(),
+        db_info.msg_count,
+        info.blob_count
+    );
+    Ok(BackupInfo {
+        blob_count: info.blob_count,
+        blob_bytes: info.blob_bytes,
+        ..db_info
+    })
+}
+
 async fn maybe_add_bcc_self_device_msg(context: &Context) -> Result<()> {
     if !context.sql.get_raw_config_bool("bcc_self").await? {
         let mut msg = Message::new(Viewtype::Text);
@@ -209,15 +386,21 @@ async fn imex_inner(
         context,
         "{} path: {}",
         match what {
-            ImexMode::ExportSelfKeys | ImexMode::ExportBackup => "Export",
-            ImexMode::ImportSelfKeys | ImexMode::ImportBackup => "Import",
+            ImexMode::ExportSelfKeys | ImexMode::ExportBackup | ImexMode::ExportIdentity =>
+                "Export",
+            ImexMode::ImportSelfKeys | ImexMode::ImportBackup | ImexMode::ImportIdentity =>
+                "Import",
+            ImexMode::MergeBackup => "Merge",
         },
         path.display()
     );
     ensure!(context.sql.is_open().await, "Database not opened.");
     context.emit_event(EventType::ImexProgress(1));
 
-    if what == ImexMode::ExportBackup || what == ImexMode::ExportSelfKeys {
+    if matches!(
+        what,
+        ImexMode::ExportBackup | ImexMode::ExportSelfKeys | ImexMode::ExportIdentity
+    ) {
         // before we export anything, make sure the private key exists
         e2ee::ensure_secret_key_exists(context)
             .await
@@ -236,6 +419,15 @@ async fn imex_inner(
         ImexMode::ImportBackup => {
             import_backup(context, path, passphrase.unwrap_or_default()).await
         }
+        ImexMode::MergeBackup => {
+            merge::merge_backup(context, path, passphrase.unwrap_or_default()).await
+        }
+        ImexMode::ExportIdentity => {
+            identity::export_identity(context, path, &passphrase.unwrap_or_default()).await
+        }
+        ImexMode::ImportIdentity => {
+            identity::import_identity(context, path, &passphrase.unwrap_or_default()).await
+        }
     }
 }
 
@@ -297,37 +489,147 @@ pub(crate) async fn import_backup_stream<R: tokio::io::AsyncRead + Unpin>(
         .0
 }
 
-/// Reader that emits progress events as bytes are read from it.
-#[pin_project]
-struct ProgressReader<R> {
-    /// Wrapped reader.
-    #[pin]
-    inner: R,
+/// Phase of a backup export or import,
+/// see [`EventType::ImexProgressDetail`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum ImexPhase {
+    /// The database is written into or read from the backup.
+    Database,
+
+    /// Files are written into or read from the backup.
+    Blobs,
 
-    /// Number of bytes successfully read from the internal reader.
-    read: usize,
+    /// The imported database is set up or the exported backup is completed.
+    Finishing,
+}
 
-    /// Total size of the backup .tar file expected to be read from the reader.
-    /// Used to calculate the progress.
-    file_size: usize,
+/// Minimum time a transfer must run before an ETA is estimated,
+/// earlier estimates are too inaccurate to be shown.
+const ETA_MIN_ELAPSED: Duration = Duration::from_secs(2);
+
+/// Estimates the remaining time of a transfer
+/// from the time `elapsed` to transfer `done` of `total` bytes.
+fn estimate_eta(elapsed: Duration, done: u64, total: u64) -> Option<Duration> {
+    if done == 0 || elapsed < ETA_MIN_ELAPSED {
+        return None;
+    }
+    let remaining = total.saturating_sub(done);
+    Some(Duration::from_secs_f64(
+        elapsed.as_secs_f64() * remaining as f64 / done as f64,
+    ))
+}
+
+#[derive(Debug)]
+struct ProgressState {
+    phase: ImexPhase,
+    items_done: usize,
+    items_total: Option<usize>,
+    bytes_done: u64,
+    bytes_total: u64,
+    started: tools::Time,
 
     /// Last progress emitted to avoid emitting the same progress value twice.
     last_progress: usize,
+}
+
+impl ProgressState {
+    fn detail_event(&self) -> EventType {
+        EventType::ImexProgressDetail {
+            phase: self.phase,
+            items_done: self.items_done,
+            items_total: self.items_total,
+            bytes_done: self.bytes_done,
+            bytes_total: self.bytes_total,
+            eta_secs: estimate_eta(
+                time_elapsed(&self.started),
+                self.bytes_done,
+                self.bytes_total,
+            )
+            .map(|eta| eta.as_secs_f64().ceil() as u64),
+        }
+    }
+}
 
-    /// Context for emitting progress events.
+/// Tracks the progress of a backup export or import
+/// and emits [`EventType::ImexProgress`] and [`EventType::ImexProgressDetail`] events.
+///
+/// Clones share the state, so bytes can be counted by the stream wrapper
+/// while phases and items are updated by the code processing archive entries.
+#[derive(Clone)]
+struct ProgressTracker {
     context: Context,
+    state: Arc<parking_lot::Mutex<ProgressState>>,
 }
 
-impl<R> ProgressReader<R> {
-    fn new(r: R, context: Context, file_size: u64) -> Self {
+impl ProgressTracker {
+    fn new(context: Context, bytes_total: u64, items_total: Option<usize>) -> Self {
         Self {
-            inner: r,
-            read: 0,
-            file_size: file_size as usize,
-            last_progress: 1,
             context,
+            state: Arc::new(parking_lot::Mutex::new(ProgressState {
+                phase: ImexPhase::Database,
+                items_done: 0,
+                items_total,
+                bytes_done: 0,
+                bytes_total,
+                started: tools::Time::now(),
+                last_progress: 1,
+            })),
+        }
+    }
+
+    /// Records that `n` more bytes were transferred.
+    fn add_bytes(&self, n: usize) {
+        let events = {
+            let mut state = self.state.lock();
+            state.bytes_done = state.bytes_done.saturating_add(n as u64);
+            let progress =
+                std::cmp::min(1000 * state.bytes_done / state.bytes_total.max(1), 999) as usize;
+            if progress <= state.last_progress {
+                return;
+            }
+            state.last_progress = progress;
+            [EventType::ImexProgress(progress), state.detail_event()]
+        };
+        for event in events {
+            self.context.emit_event(event);
         }
     }
+
+    /// Records that an archive entry was transferred completely.
+    fn item_done(&self) {
+        self.state.lock().items_done += 1;
+    }
+
+    fn set_phase(&self, phase: ImexPhase) {
+        let event = {
+            let mut state = self.state.lock();
+            if state.phase == phase {
+                return;
+            }
+            state.phase = phase;
+            state.detail_event()
+        };
+        self.context.emit_event(event);
+    }
+}
+
+/// Reader that emits progress events as bytes are read from it.
+#[pin_project]
+struct ProgressReader<R> {
+    /// Wrapped reader.
+    #[pin]
+    inner: R,
+
+    /// Progress of the import, the total size is the size of the backup .tar file
+    /// expected to be read from the reader.
+    progress: ProgressTracker,
+}
+
+impl<R> ProgressReader<R> {
+    fn new(r: R, progress: ProgressTracker) -> Self {
+        Self { inner: r, progress }
+    }
 }
 
 impl<R> AsyncRead for ProgressReader<R>
@@ -343,13 +645,7 @@ fn poll_read(
         let before = buf.filled().len();
         let res = this.inner.poll_read(cx, buf);
         if let std::task::Poll::Ready(Ok(())) = res {
-            *this.read = this.read.saturating_add(buf.filled().len() - before);
-
-            let progress = std::cmp::min(1000 * *this.read / *this.file_size, 999);
-            if progress > *this.last_progress {
-                this.context.emit_event(EventType::ImexProgress(progress));
-                *this.last_progress = progress;
-            }
+            this.progress.add_bytes(buf.filled().len() - before);
         }
         res
     }
@@ -361,7 +657,8 @@ async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
     file_size: u64,
     passphrase: String,
 ) -> (Result<()>,) {
-    let backup_file = ProgressReader::new(backup_file, context.clone(), file_size);
+    let progress = ProgressTracker::new(context.clone(), file_size, None);
+    let backup_file = ProgressReader::new(backup_file, progress.clone());
     let mut archive = Archive::new(backup_file);
 
     let mut entries = match archive.entries() {
@@ -380,10 +677,17 @@ async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
             Ok(path) => path.to_path_buf(),
             Err(e) => break Err(e).context("Failed to get entry path"),
         };
+        let is_database = path.file_name() == Some(OsStr::new(DBFILE_BACKUP_NAME));
+        progress.set_phase(if is_database {
+            ImexPhase::Database
+        } else {
+            ImexPhase::Blobs
+        });
         if let Err(e) = f.unpack_in(context.get_blobdir()).await {
             break Err(e).context("Failed to unpack file");
         }
-        if path.file_name() == Some(OsStr::new(DBFILE_BACKUP_NAME)) {
+        progress.item_done();
+        if is_database {
             continue;
         }
         // async_tar unpacked to $BLOBDIR/BLOBS_BACKUP_NAME/, so we move the file afterwards.
@@ -409,6 +713,7 @@ async fn import_backup_stream_inner<R: tokio::io::AsyncRead + Unpin>(
 
     let unpacked_database = context.get_blobdir().join(DBFILE_BACKUP_NAME);
     if res.is_ok() {
+        progress.set_phase(ImexPhase::Finishing);
         res = context
             .sql
             .import(&unpacked_database, passphrase.clone())
@@ -509,6 +814,10 @@ async fn export_backup(context: &Context, dir: &Path, passphrase: String) -> Res
         .await
         .context("Exporting backup to file failed")?;
     fs::rename(temp_path, &dest_path).await?;
+    activity::record(context, ActivityKind::BackupExported, "")
+        .await
+        .log_err(context)
+        .ok();
     context.emit_event(EventType::ImexFileWritten(dest_path));
     Ok(())
 }
@@ -520,29 +829,14 @@ struct ProgressWriter<W> {
     #[pin]
     inner: W,
 
-    /// Number of bytes successfully written into the internal writer.
-    written: usize,
-
-    /// Total size of the backup .tar file expected to be written into the writer.
-    /// Used to calculate the progress.
-    file_size: usize,
-
-    /// Last progress emitted to avoid emitting the same progress value twice.
-    last_progress: usize,
-
-    /// Context for emitting progress events.
-    context: Context,
+    /// Progress of the export, the total size is the size of the backup .tar file
+    /// expected to be written into the writer.
+    progress: ProgressTracker,
 }
 
 impl<W> ProgressWriter<W> {
-    fn new(w: W, context: Context, file_size: u64) -> Self {
-        Self {
-            inner: w,
-            written: 0,
-            file_size: file_size as usize,
-            last_progress: 1,
-            context,
-        }
+    fn new(w: W, progress: ProgressTracker) -> Self {
+        Self { inner: w, progress }
     }
 }
 
@@ -558,13 +852,7 @@ fn poll_write(
         let this = self.project();
         let res = this.inner.poll_write(cx, buf);
         if let std::task::Poll::Ready(Ok(written)) = res {
-            *this.written = this.written.saturating_add(written);
-
-            let progress = std::cmp::min(1000 * *this.written / *this.file_size, 999);
-            if progress > *this.last_progress {
-                this.context.emit_event(EventType::ImexProgress(progress));
-                *this.last_progress = progress;
-            }
+            this.progress.add_bytes(written);
         }
         res
     }
@@ -595,20 +883,26 @@ pub(crate) async fn export_backup_stream<'a, W>(
 where
     W: tokio::io::AsyncWrite + tokio::io::AsyncWriteExt + Unpin + Send + 'static,
 {
-    let writer = ProgressWriter::new(writer, context.clone(), file_size);
+    let items_total = 1 + blobdir.iter().count();
+    let progress = ProgressTracker::new(context.clone(), file_size, Some(items_total));
+    let writer = ProgressWriter::new(writer, progress.clone());
     let mut builder = tokio_tar::Builder::new(writer);
 
     builder
         .append_path_with_name(temp_db_path, DBFILE_BACKUP_NAME)
         .await?;
+    progress.item_done();
+    progress.set_phase(ImexPhase::Blobs);
 
     for blob in blobdir.iter() {
         let mut file = File::open(blob.to_abs_path()).await?;
         let path_in_archive = PathBuf::from(BLOBS_BACKUP_NAME).join(blob.as_name());
         builder.append_file(path_in_archive, &mut file).await?;
+        progress.item_done();
     }
 
     builder.finish().await?;
+    progress.set_phase(ImexPhase::Finishing);
     Ok(())
 }
 
@@ -808,24 +1102,51 @@ async fn export_database(
             conn.execute("VACUUM;", ())
                 .map_err(|err| warn!(context, "Vacuum failed, exporting anyway {err}"))
                 .ok();
-            conn.execute("ATTACH DATABASE ? AS backup KEY ?", (dest, passphrase))
-                .context("failed to attach backup database")?;
-            let res = conn
-                .query_row("SELECT sqlcipher_export('backup')", [], |_row| Ok(()))
-                .context("failed to export to attached backup database");
-            conn.execute(
-                "UPDATE backup.config SET value='0' WHERE keyname='verified_one_on_one_chats';",
-                [],
-            )
-            .ok(); // If verified_one_on_one_chats was not set, this errors, which we ignore
-            conn.execute("DETACH DATABASE backup", [])
-                .context("failed to detach backup database")?;
-            res?;
-            Ok(())
+            copy_database(conn, dest, passphrase)
         })
+        .await?;
+
+    // VACUUM rewrites the whole database into the WAL.
+    sql::truncate_wal(context).await.log_err(context).ok();
+    Ok(())
+}
+
+/// Exports a snapshot of the database to *dest*, encrypted using *passphrase*,
+/// while IO may be running.
+///
+/// Unlike [`export_database`], this does not prepare the database for a full backup,
+/// blobs referenced by the snapshot may be deleted meanwhile.
+pub(crate) async fn export_database_snapshot(
+    context: &Context,
+    dest: &Path,
+    passphrase: String,
+) -> Result<()> {
+    let dest = dest
+        .to_str()
+        .with_context(|| format!("path {} is not valid unicode", dest.display()))?;
+    context
+        .sql
+        .call_write(|conn| copy_database(conn, dest, passphrase))
         .await
 }
 
+fn copy_database(conn: &rusqlite::Connection, dest: &str, passphrase: String) -> Result<()> {
+    conn.execute("ATTACH DATABASE ? AS backup KEY ?", (dest, passphrase))
+        .context("failed to attach backup database")?;
+    let res = conn
+        .query_row("SELECT sqlcipher_export('backup')", [], |_row| Ok(()))
+        .context("failed to export to attached backup database");
+    conn.execute(
+        "UPDATE backup.config SET value='0' WHERE keyname='verified_one_on_one_chats';",
+        [],
+    )
+    .ok(); // If verified_one_on_one_chats was not set, this errors, which we ignore
+    conn.execute("DETACH DATABASE backup", [])
+        .context("failed to detach backup database")?;
+    res?;
+    Ok(())
+}
+
 /// Sets `Config::DeleteServerAfter` to "never" if needed so that new messages are present on the
 /// server after a backup restoration or available for all devices in multi-device case.
 /// NB: Calling this after a backup import isn't reliable as we can crash in between, but this is a
@@ -952,6 +1273,19 @@ async fn test_import_second_key() -> Result<()> {
         Ok(())
     }
 
+    #[test]
+    fn test_estimate_eta() {
+        let elapsed = Duration::from_secs(10);
+        assert_eq!(estimate_eta(elapsed, 0, 100), None);
+        assert_eq!(estimate_eta(Duration::from_secs(1), 50, 100), None);
+        assert_eq!(
+            estimate_eta(elapsed, 25, 100),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(estimate_eta(elapsed, 100, 100), Some(Duration::ZERO));
+        assert_eq!(estimate_eta(elapsed, 120, 100), Some(Duration::ZERO));
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_export_and_import_backup() -> Result<()> {
         for set_verified_oneonone_chats in [true, false] {
@@ -975,6 +1309,41 @@ async fn test_export_and_import_backup() -> Result<()> {
                     .await
                     .is_ok()
             );
+            let event = context1
+                .evtracker
+                .get_matching(|evt| matches!(evt, EventType::ImexProgressDetail { .. }))
+                .await;
+            let EventType::ImexProgressDetail {
+                items_total,
+                bytes_total,
+                ..
+            } = event
+            else {
+                unreachable!()
+            };
+            assert!(items_total >= Some(1));
+            assert!(bytes_total > 0);
+            let event = context1
+                .evtracker
+                .get_matching(|evt| {
+                    matches!(
+                        evt,
+                        EventType::ImexProgressDetail {
+                            phase: ImexPhase::Finishing,
+                            ..
+                        }
+                    )
+                })
+                .await;
+            let EventType::ImexProgressDetail {
+                items_done,
+                items_total,
+                ..
+            } = event
+            else {
+                unreachable!()
+            };
+            assert_eq!(Some(items_done), items_total);
             let _event = context1
                 .evtracker
                 .get_matching(|evt| matches!(evt, EventType::ImexProgress(1000)))
@@ -1024,6 +1393,51 @@ async fn test_export_and_import_backup() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_verify_backup() -> Result<()> {
+        let backup_dir = tempfile::tempdir()?;
+        let alice = &TestContext::new_alice().await;
+        let file = alice.get_blobdir().join("hello.txt");
+        fs::write(&file, "i am attachment").await?;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file(file.to_str().unwrap(), None);
+        let chat_id = alice.get_self_chat().await.id;
+        chat::send_msg(alice, chat_id, &mut msg).await?;
+        imex(alice, ImexMode::ExportBackup, backup_dir.path(), None).await?;
+        let backup = has_backup(alice, backup_dir.path()).await?;
+
+        let info = verify_backup(alice, backup.as_ref(), String::new()).await?;
+        assert_eq!(info.addr, Some("alice@example.org".to_string()));
+        assert!(info.backup_time > 0);
+        assert!(info.db_version > 0);
+        assert!(info.msg_count >= 1);
+        assert!(info.chat_count >= 1);
+        assert!(info.blob_count >= 1);
+        assert!(info.blob_bytes >= 15);
+
+        // Backups can be verified concurrently.
+        let (res1, res2) = tokio::join!(
+            verify_backup(alice, backup.as_ref(), String::new()),
+            verify_backup(alice, backup.as_ref(), String::new())
+        );
+        assert_eq!(res1?, info);
+        assert_eq!(res2?, info);
+
+        // Unencrypted backup can't be opened with a passphrase.
+        assert!(verify_backup(alice, backup.as_ref(), "foobar".to_string())
+            .await
+            .is_err());
+
+        // Truncated backup fails verification.
+        let data = fs::read(&backup).await?;
+        let truncated = backup_dir.path().join("truncated.tar");
+        fs::write(&truncated, &data[..data.len() / 2]).await?;
+        assert!(verify_backup(alice, &truncated, String::new())
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_export_import_chatmail_backup() -> Result<()> {
         let backup_dir = tempfile::tempdir().unwrap();
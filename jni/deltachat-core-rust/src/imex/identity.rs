@@ -0,0 +1,340 @@
+//! # Identity bundle export and import.
+//!
+//! An identity bundle contains the secret keys, the verified keys of contacts
+//! and the essential settings needed to log in,
+//! but no messages, so it is small enough to be stored in a password manager.
+//! The bundle is symmetrically encrypted with a passphrase
+//! and stored as an ASCII-armored OpenPGP message.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{ensure, Context as _, Result};
+use deltachat_contact_tools::ContactAddress;
+use serde::{Deserialize, Serialize};
+
+use super::set_self_key;
+use crate::aheader::EncryptPreference;
+use crate::config::Config;
+use crate::contact::{Contact, Origin};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::key::{DcKey, SignedPublicKey, SignedSecretKey};
+use crate::peerstate::Peerstate;
+use crate::pgp;
+use crate::tools::{delete_file, read_file, write_file};
+
+/// Version of the identity bundle format.
+const BUNDLE_VERSION: u32 = 1;
+
+/// Settings stored in the identity bundle.
+const BUNDLE_CONFIGS: [Config; 17] = [
+    Config::Addr,
+    Config::MailServer,
+    Config::MailUser,
+    Config::MailPw,
+    Config::MailPort,
+    Config::MailSecurity,
+    Config::ImapCertificateChecks,
+    Config::SendServer,
+    Config::SendUser,
+    Config::SendPw,
+    Config::SendPort,
+    Config::SendSecurity,
+    Config::SmtpCertificateChecks,
+    Config::ProxyEnabled,
+    Config::ProxyUrl,
+    Config::Displayname,
+    Config::Selfstatus,
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IdentityBundle {
+    version: u32,
+
+    /// Settings by their key names.
+    configs: BTreeMap<String, String>,
+
+    /// Own keypairs.
+    keys: Vec<BundleKey>,
+
+    /// Verified keys of contacts.
+    verifications: Vec<BundleVerification>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleKey {
+    /// ASCII-armored secret key.
+    secret_key: String,
+
+    /// Whether the key is the default key.
+    default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleVerification {
+    addr: String,
+
+    /// ASCII-armored verified public key.
+    public_key: String,
+
+    /// Address that introduced the verified key.
+    verifier: Option<String>,
+}
+
+/// Returns the name of the identity bundle file for `addr`.
+fn bundle_file_name(addr: &str) -> String {
+    format!("delta-chat-identity-{addr}.asc")
+}
+
+/// Exports the identity bundle encrypted with `passphrase` to the directory `dir`.
+pub(crate) async fn export_identity(context: &Context, dir: &Path, passphrase: &str) -> Result<()> {
+    ensure!(
+        !passphrase.is_empty(),
+        "Identity bundle requires a passphrase"
+    );
+
+    let mut configs = BTreeMap::new();
+    for key in BUNDLE_CONFIGS {
+        if let Some(value) = context.get_config(key).await? {
+            configs.insert(key.as_ref().to_string(), value);
+        }
+    }
+
+    let keys = context
+        .sql
+        .query_map(
+            "SELECT private_key, id=(SELECT value FROM config WHERE keyname='key_id')
+             FROM keypairs ORDER BY id",
+            (),
+            |row| {
+                let private_key: Vec<u8> = row.get(0)?;
+                let default: bool = row.get(1)?;
+                Ok((private_key, default))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?
+        .into_iter()
+        .map(|(private_key, default)| {
+            Ok(BundleKey {
+                secret_key: SignedSecretKey::from_slice(&private_key)?.to_asc(None),
+                default,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(!keys.is_empty(), "No keys to export");
+
+    let verifications = context
+        .sql
+        .query_map(
+            "SELECT addr, verified_key, verifier FROM acpeerstates WHERE verified_key IS NOT NULL",
+            (),
+            |row| {
+                let addr: String = row.get(0)?;
+                let verified_key: Vec<u8> = row.get(1)?;
+                let verifier: Option<String> = row.get(2)?;
+                Ok((addr, verified_key, verifier))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?
+        .into_iter()
+        .filter_map(|(addr, verified_key, verifier)| {
+            let public_key = SignedPublicKey::from_slice(&verified_key).ok()?;
+            Some(BundleVerification {
+                addr,
+                public_key: public_key.to_asc(None),
+                verifier: verifier.filter(|verifier| !verifier.is_empty()),
+            })
+        })
+        .collect();
+
+    let bundle = IdentityBundle {
+        version: BUNDLE_VERSION,
+        configs,
+        keys,
+        verifications,
+    };
+    let plain = serde_json::to_vec(&bundle)?;
+    let encrypted = pgp::symm_encrypt(passphrase, &plain).await?;
+
+    let path = dir.join(bundle_file_name(&context.get_primary_self_addr().await?));
+    info!(context, "Exporting identity bundle to {}.", path.display());
+    delete_file(context, &path).await.ok();
+    write_file(context, &path, encrypted.as_bytes())
+        .await
+        .with_context(|| format!("Cannot write identity bundle to {}", path.display()))?;
+    context.emit_event(EventType::ImexFileWritten(path));
+    Ok(())
+}
+
+/// Imports the identity bundle at `path` encrypted with `passphrase`.
+///
+/// Like importing a backup, this is only possible
+/// as long as the account is not configured.
+pub(crate) async fn import_identity(
+    context: &Context,
+    path: &Path,
+    passphrase: &str,
+) -> Result<()> {
+    ensure!(
+        !context.is_configured().await?,
+        "Cannot import identity to accounts in use."
+    );
+
+    let encrypted = read_file(context, path).await?;
+    let plain = pgp::symm_decrypt(passphrase, Cursor::new(encrypted))
+        .await
+        .context("Failed to decrypt identity bundle, wrong passphrase?")?;
+    let bundle: IdentityBundle =
+        serde_json::from_slice(&plain).context("Failed to parse identity bundle")?;
+    ensure!(
+        bundle.version <= BUNDLE_VERSION,
+        "Identity bundle version {} is not supported",
+        bundle.version
+    );
+
+    for (key, value) in &bundle.configs {
+        match Config::from_str(key) {
+            Ok(key) if BUNDLE_CONFIGS.contains(&key) => {
+                context.set_config(key, Some(value)).await?;
+            }
+            _ => warn!(context, "Ignoring unknown identity bundle setting {key:?}."),
+        }
+    }
+
+    // Import the default key last so it is not replaced by other keys.
+    ensure!(
+        bundle.keys.iter().any(|key| key.default),
+        "Identity bundle contains no default key"
+    );
+    let (default_keys, other_keys): (Vec<_>, Vec<_>) =
+        bundle.keys.iter().partition(|key| key.default);
+    for key in other_keys.into_iter().chain(default_keys) {
+        set_self_key(context, &key.secret_key, key.default).await?;
+    }
+
+    for verification in &bundle.verifications {
+        if let Err(err) = import_verification(context, verification).await {
+            warn!(
+                context,
+                "Failed to import verification of {}: {err:#}.", verification.addr
+            );
+        }
+    }
+    context.emit_event(EventType::ContactsChanged(None));
+    info!(
+        context,
+        "Imported identity bundle with {} keys and {} verifications.",
+        bundle.keys.len(),
+        bundle.verifications.len()
+    );
+    Ok(())
+}
+
+async fn import_verification(context: &Context, verification: &BundleVerification) -> Result<()> {
+    let addr = ContactAddress::new(&verification.addr)?;
+    if context.is_self_addr(&addr).await? {
+        return Ok(());
+    }
+    let (public_key, _) = SignedPublicKey::from_asc(&verification.public_key)?;
+    let fingerprint = public_key.dc_fingerprint();
+    let mut peerstate = match Peerstate::from_addr(context, &addr).await? {
+        Some(peerstate) => peerstate,
+        None => Peerstate::from_public_key(&addr, 0, EncryptPreference::Mutual, &public_key),
+    };
+    let verifier = verification
+        .verifier
+        .clone()
+        .unwrap_or_else(|| addr.to_string());
+    peerstate.set_verified(public_key, fingerprint, verifier)?;
+    peerstate.save_to_db_ex(context, None).await?;
+    Contact::add_or_lookup(context, "", &addr, Origin::SecurejoinJoined).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imex::{imex, ImexMode};
+    use crate::key::load_self_public_key;
+    use crate::test_utils::{bob_keypair, TestContext};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_import_identity() -> Result<()> {
+        let export_dir = tempfile::tempdir()?;
+        let alice = &TestContext::new_alice().await;
+        alice.set_config(Config::Displayname, Some("Alice")).await?;
+        let bob_key = bob_keypair().public;
+        let mut peerstate =
+            Peerstate::from_public_key("bob@example.net", 10, EncryptPreference::Mutual, &bob_key);
+        peerstate.set_verified(
+            bob_key.clone(),
+            bob_key.dc_fingerprint(),
+            "bob@example.net".to_string(),
+        )?;
+        peerstate.save_to_db(&alice.sql).await?;
+
+        // A passphrase is required.
+        assert!(
+            imex(alice, ImexMode::ExportIdentity, export_dir.path(), None)
+                .await
+                .is_err()
+        );
+        let passphrase = Some("secret".to_string());
+        imex(
+            alice,
+            ImexMode::ExportIdentity,
+            export_dir.path(),
+            passphrase.clone(),
+        )
+        .await?;
+        let path = export_dir
+            .path()
+            .join(bundle_file_name("alice@example.org"));
+        assert!(path.exists());
+
+        let t = &TestContext::new().await;
+        assert!(imex(
+            t,
+            ImexMode::ImportIdentity,
+            &path,
+            Some("wrong".to_string())
+        )
+        .await
+        .is_err());
+        imex(t, ImexMode::ImportIdentity, &path, passphrase).await?;
+        assert_eq!(
+            t.get_config(Config::Addr).await?,
+            Some("alice@example.org".to_string())
+        );
+        assert_eq!(
+            t.get_config(Config::Displayname).await?,
+            Some("Alice".to_string())
+        );
+        assert_eq!(
+            load_self_public_key(t).await?.dc_fingerprint(),
+            load_self_public_key(alice).await?.dc_fingerprint()
+        );
+        let peerstate = Peerstate::from_addr(t, "bob@example.net").await?.unwrap();
+        assert_eq!(
+            peerstate.verified_key_fingerprint,
+            Some(bob_key.dc_fingerprint())
+        );
+
+        // Configured accounts can't import identities.
+        assert!(imex(alice, ImexMode::ImportIdentity, &path, None)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
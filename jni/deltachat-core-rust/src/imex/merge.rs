@@ -0,0 +1,559 @@
+//! # Merging backups into configured accounts.
+//!
+//! Unlike importing a backup, which replaces the whole database,
+//! merging folds the chats, contacts and messages of a backup
+//! into the database of an account which is already in use.
+//! Messages already existing in the account are recognized by their Message-ID
+//! and groups by their group ID, so merging the same backup twice does not duplicate anything.
+//!
+//! Keys, settings, device messages, drafts and the raw MIME of messages are not merged.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as _, Result};
+use deltachat_contact_tools::ContactAddress;
+use futures::TryStreamExt;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use tokio::fs::{self, File};
+use tokio_tar::Archive;
+
+use super::{ProgressReader, BLOBS_BACKUP_NAME, DBFILE_BACKUP_NAME};
+use crate::blob::BlobObject;
+use crate::chat::{self, ChatId, ChatIdBlocked, ProtectionStatus};
+use crate::constants::{Blocked, Chattype, DC_CHAT_ID_LAST_SPECIAL};
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::log::LogExt;
+use crate::message::{self, MessageState, Viewtype};
+use crate::param::{Param, Params};
+
+/// Name of the directory the backup is unpacked to, relative to the context directory.
+const MERGE_DIR_NAME: &str = "dc_merge_backup";
+
+/// Number of messages read from the backup database at once.
+const PAGE_SIZE: u32 = 500;
+
+/// Minimum version of the backup database schema, the `subject` column is added in version 76.
+const MIN_DBVERSION: i32 = 76;
+
+#[derive(Debug)]
+struct BackupContact {
+    id: u32,
+    name: String,
+    addr: String,
+    origin: Origin,
+    authname: String,
+}
+
+#[derive(Debug)]
+struct BackupChat {
+    id: u32,
+    typ: Chattype,
+    name: String,
+    grpid: String,
+    blocked: Blocked,
+    created_timestamp: i64,
+    members: Vec<u32>,
+}
+
+#[derive(Debug)]
+struct BackupMsg {
+    id: u32,
+    rfc724_mid: String,
+    chat_id: u32,
+    from_id: u32,
+    to_id: u32,
+    timestamp: i64,
+    timestamp_sent: i64,
+    timestamp_rcvd: i64,
+    viewtype: Viewtype,
+    state: MessageState,
+    msgrmsg: i32,
+    txt: String,
+    subject: String,
+    txt_raw: String,
+    param: String,
+    hidden: bool,
+    bytes: i64,
+    mime_in_reply_to: String,
+    mime_references: String,
+    error: String,
+}
+
+/// Message mapped to the chats and contacts of the account.
+#[derive(Debug)]
+struct MergedMsg {
+    msg: BackupMsg,
+    chat_id: ChatId,
+    from_id: ContactId,
+    to_id: ContactId,
+}
+
+/// Merges the backup at `path` into the database of the configured account.
+/// The backup must be of the same address.
+///
+/// `passphrase` is the passphrase used to open backup database. If backup is unencrypted, pass
+/// empty string here.
+pub(crate) async fn merge_backup(context: &Context, path: &Path, passphrase: String) -> Result<()> {
+    ensure!(
+        context.is_configured().await?,
+        "Cannot merge backups into unconfigured accounts, import the backup instead."
+    );
+
+    let merge_dir = context
+        .get_blobdir()
+        .parent()
+        .context("Context dir not found")?
+        .join(MERGE_DIR_NAME);
+    if fs::metadata(&merge_dir).await.is_ok() {
+        fs::remove_dir_all(&merge_dir).await?;
+    }
+    fs::create_dir_all(&merge_dir).await?;
+
+    let res = merge_backup_inner(context, path, passphrase, &merge_dir).await;
+    fs::remove_dir_all(&merge_dir)
+        .await
+        .context("Cannot remove unpacked backup")
+        .log_err(context)
+        .ok();
+    res
+}
+
+async fn merge_backup_inner(
+    context: &Context,
+    path: &Path,
+    passphrase: String,
+    merge_dir: &Path,
+) -> Result<()> {
+    let backup_file = File::open(path)
+        .await
+        .with_context(|| format!("Failed to open backup {}", path.display()))?;
+    let file_size = backup_file.metadata().await?.len();
+    info!(
+        context,
+        "Merge \"{}\" ({} bytes) into \"{}\".",
+        path.display(),
+        file_size,
+        context.get_dbfile().display()
+    );
+    let backup_file = ProgressReader::new(backup_file, context.clone(), file_size);
+    let mut archive = Archive::new(backup_file);
+    let mut entries = archive.entries().context("Failed to get archive entries")?;
+    while let Some(mut entry) = entries
+        .try_next()
+        .await
+        .context("Failed to get next entry")?
+    {
+        entry
+            .unpack_in(merge_dir)
+            .await
+            .context("Failed to unpack file")?;
+    }
+
+    let dbfile = merge_dir.join(DBFILE_BACKUP_NAME);
+    ensure!(
+        fs::metadata(&dbfile).await.is_ok(),
+        "Backup contains no database"
+    );
+    let (mut conn, backup_addr, contacts, chats) =
+        tokio::task::spawn_blocking(move || open_backup_db(&dbfile, &passphrase)).await??;
+    ensure!(
+        context.is_self_addr(&backup_addr).await?,
+        "Backup of {backup_addr:?} cannot be merged into another account"
+    );
+
+    let mut contact_map = HashMap::new();
+    for contact in contacts {
+        let origin = if contact.origin == Origin::Unknown {
+            Origin::Hidden
+        } else {
+            contact.origin
+        };
+        let name = match origin {
+            Origin::ManuallyCreated | Origin::AddressBook if !contact.name.is_empty() => {
+                &contact.name
+            }
+            _ => &contact.authname,
+        };
+        let Ok(addr) = ContactAddress::new(&contact.addr) else {
+            warn!(
+                context,
+                "Skipping contact with invalid address {:?}.", contact.addr
+            );
+            continue;
+        };
+        let (contact_id, _) = Contact::add_or_lookup(context, name, &addr, origin).await?;
+        contact_map.insert(contact.id, contact_id);
+    }
+    let map_contact = |id: u32| -> Option<ContactId> {
+        if id <= ContactId::LAST_SPECIAL.to_u32() {
+            Some(ContactId::new(id))
+        } else {
+            contact_map.get(&id).copied()
+        }
+    };
+
+    let mut chat_map = HashMap::new();
+    for chat in chats {
+        let members: Vec<ContactId> = chat
+            .members
+            .iter()
+            .filter_map(|id| map_contact(*id))
+            .collect();
+        let chat_id = match chat.typ {
+            Chattype::Single => {
+                let Some(contact_id) = members.first().copied() else {
+                    continue;
+                };
+                if contact_id == ContactId::DEVICE {
+                    // Device messages are not merged, they are added by each device itself.
+                    continue;
+                }
+                ChatIdBlocked::get_for_contact(context, contact_id, chat.blocked)
+                    .await?
+                    .id
+            }
+            Chattype::Group | Chattype::Mailinglist | Chattype::Broadcast => {
+                if chat.grpid.is_empty() {
+                    warn!(context, "Skipping chat {} without group ID.", chat.id);
+                    continue;
+                }
+                if let Some((chat_id, _, _)) =
+                    chat::get_chat_id_by_grpid(context, &chat.grpid).await?
+                {
+                    chat_id
+                } else {
+                    let chat_id = ChatId::create_multiuser_record(
+                        context,
+                        chat.typ,
+                        &chat.grpid,
+                        &chat.name,
+                        chat.blocked,
+                        ProtectionStatus::Unprotected,
+                        None,
+                        chat.created_timestamp,
+                    )
+                    .await?;
+                    chat::add_to_chat_contacts_table(context, chat_id, &members).await?;
+                    chat_id
+                }
+            }
+        };
+        chat_map.insert(chat.id, chat_id);
+    }
+
+    let blobs_dir = merge_dir.join(BLOBS_BACKUP_NAME);
+    let mut merged = 0;
+    let mut last_id = 0;
+    loop {
+        let page;
+        (conn, page) = tokio::task::spawn_blocking(move || {
+            let page = read_msgs(&conn, last_id);
+            (conn, page)
+        })
+        .await?;
+        let page = page?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        let mut msgs = Vec::new();
+        for mut msg in page {
+            let Some(chat_id) = chat_map.get(&msg.chat_id).copied() else {
+                continue;
+            };
+            let (Some(from_id), Some(to_id)) = (map_contact(msg.from_id), map_contact(msg.to_id))
+            else {
+                continue;
+            };
+            if msg.rfc724_mid.is_empty()
+                || msg.state == MessageState::OutDraft
+                || context
+                    .sql
+                    .exists(
+                        "SELECT COUNT(*) FROM msgs WHERE rfc724_mid=?",
+                        (&msg.rfc724_mid,),
+                    )
+                    .await?
+            {
+                continue;
+            }
+
+            msg.state = match msg.state {
+                MessageState::InFresh | MessageState::InNoticed => MessageState::InSeen,
+                MessageState::OutPreparing | MessageState::OutPending => MessageState::OutFailed,
+                state => state,
+            };
+            let mut param: Params = msg.param.parse().unwrap_or_default();
+            if let Some(name) = param
+                .get(Param::File)
+                .and_then(|file| file.strip_prefix("$BLOBDIR/"))
+                .map(|name| name.to_string())
+            {
+                match copy_blob(context, &blobs_dir, &name).await {
+                    Ok(blob_name) => {
+                        param.set(Param::File, blob_name);
+                    }
+                    Err(err) => {
+                        warn!(context, "Cannot merge file {name}: {err:#}.");
+                        param.remove(Param::File);
+                    }
+                }
+            }
+            param.remove(Param::PrepForwards);
+            msg.param = param.to_string();
+
+            msgs.push(MergedMsg {
+                msg,
+                chat_id,
+                from_id,
+                to_id,
+            });
+        }
+
+        merged += msgs.len();
+        context
+            .sql
+            .transaction(move |transaction| {
+                let mut stmt = transaction.prepare(
+                    "INSERT INTO msgs
+                     (rfc724_mid, chat_id, from_id, to_id,
+                      timestamp, timestamp_sent, timestamp_rcvd,
+                      type, state, msgrmsg, txt, txt_normalized, subject, txt_raw,
+                      param, hidden, bytes, mime_in_reply_to, mime_references, error)
+                     VALUES (?,?,?,?, ?,?,?, ?,?,?,?,?,?,?, ?,?,?,?,?,?)",
+                )?;
+                for MergedMsg {
+                    msg,
+                    chat_id,
+                    from_id,
+                    to_id,
+                } in msgs
+                {
+                    stmt.execute(params![
+                        &msg.rfc724_mid,
+                        chat_id,
+                        from_id,
+                        to_id,
+                        msg.timestamp,
+                        msg.timestamp_sent,
+                        msg.timestamp_rcvd,
+                        msg.viewtype,
+                        msg.state,
+                        msg.msgrmsg,
+                        &msg.txt,
+                        message::normalize_text(&msg.txt),
+                        &msg.subject,
+                        &msg.txt_raw,
+                        &msg.param,
+                        msg.hidden,
+                        msg.bytes,
+                        &msg.mime_in_reply_to,
+                        &msg.mime_references,
+                        &msg.error,
+                    ])?;
+                }
+                Ok(())
+            })
+            .await
+            .context("Failed to insert merged messages")?;
+    }
+
+    info!(
+        context,
+        "Merged {} chats and {} new messages from backup.",
+        chat_map.len(),
+        merged
+    );
+    context.emit_msgs_changed_without_ids();
+    Ok(())
+}
+
+/// Opens the unpacked backup database
+/// and reads the contacts and chats, excluding special ones.
+fn open_backup_db(
+    dbfile: &Path,
+    passphrase: &str,
+) -> Result<(Connection, String, Vec<BackupContact>, Vec<BackupChat>)> {
+    let conn = Connection::open_with_flags(dbfile, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context("Failed to open backup database")?;
+    if !passphrase.is_empty() {
+        conn.pragma_update(None, "key", passphrase)?;
+    }
+    let dbversion: i32 = conn
+        .query_row(
+            "SELECT value FROM config WHERE keyname='dbversion'",
+            (),
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .context("Failed to read backup database, wrong passphrase?")?
+        .and_then(|version| version.parse().ok())
+        .unwrap_or_default();
+    ensure!(
+        dbversion >= MIN_DBVERSION,
+        "Backup is too old to be merged, database version {dbversion}"
+    );
+    let addr: String = conn
+        .query_row(
+            "SELECT value FROM config WHERE keyname='configured_addr'",
+            (),
+            |row| row.get(0),
+        )
+        .optional()?
+        .context("Backup has no configured address")?;
+
+    let contacts = conn
+        .prepare("SELECT id, name, addr, origin, authname FROM contacts WHERE id>?")?
+        .query_map((ContactId::LAST_SPECIAL.to_u32(),), |row| {
+            Ok(BackupContact {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                addr: row.get(2)?,
+                origin: row.get(3)?,
+                authname: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut chats = conn
+        .prepare(
+            "SELECT id, type, name, IFNULL(grpid, ''), IFNULL(blocked, 0), created_timestamp
+             FROM chats WHERE id>?",
+        )?
+        .query_map((DC_CHAT_ID_LAST_SPECIAL.to_u32(),), |row| {
+            Ok(BackupChat {
+                id: row.get(0)?,
+                typ: row.get(1)?,
+                name: row.get(2)?,
+                grpid: row.get(3)?,
+                blocked: row.get(4)?,
+                created_timestamp: row.get(5)?,
+                members: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut stmt = conn.prepare("SELECT contact_id FROM chats_contacts WHERE chat_id=?")?;
+    for chat in &mut chats {
+        chat.members = stmt
+            .query_map((chat.id,), |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+    }
+    drop(stmt);
+    Ok((conn, addr, contacts, chats))
+}
+
+/// Reads the next page of messages with IDs greater than `last_id`, excluding trashed ones.
+fn read_msgs(conn: &Connection, last_id: u32) -> Result<Vec<BackupMsg>> {
+    let msgs = conn
+        .prepare(
+            "SELECT id, rfc724_mid, chat_id, from_id, to_id,
+                    timestamp, timestamp_sent, timestamp_rcvd,
+                    type, state, msgrmsg, txt, IFNULL(subject, ''), txt_raw,
+                    param, hidden, bytes,
+                    IFNULL(mime_in_reply_to, ''), IFNULL(mime_references, ''), IFNULL(error, '')
+             FROM msgs WHERE id>? AND chat_id>? ORDER BY id LIMIT ?",
+        )?
+        .query_map(
+            (last_id, DC_CHAT_ID_LAST_SPECIAL.to_u32(), PAGE_SIZE),
+            |row| {
+                Ok(BackupMsg {
+                    id: row.get(0)?,
+                    rfc724_mid: row.get(1)?,
+                    chat_id: row.get(2)?,
+                    from_id: row.get(3)?,
+                    to_id: row.get(4)?,
+                    timestamp: row.get(5)?,
+                    timestamp_sent: row.get(6)?,
+                    timestamp_rcvd: row.get(7)?,
+                    viewtype: row.get(8)?,
+                    state: row.get(9)?,
+                    msgrmsg: row.get(10)?,
+                    txt: row.get(11)?,
+                    subject: row.get(12)?,
+                    txt_raw: row.get(13)?,
+                    param: row.get(14)?,
+                    hidden: row.get(15)?,
+                    bytes: row.get(16)?,
+                    mime_in_reply_to: row.get(17)?,
+                    mime_references: row.get(18)?,
+                    error: row.get(19)?,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(msgs)
+}
+
+/// Copies the blob `name` unpacked to `blobs_dir` to the blobdir,
+/// returning the name to store in [`Param::File`].
+async fn copy_blob(context: &Context, blobs_dir: &Path, name: &str) -> Result<String> {
+    let src: PathBuf = blobs_dir.join(name);
+    ensure!(
+        src.file_name() == Some(OsStr::new(name)),
+        "Invalid blob name"
+    );
+    let blob = BlobObject::create_and_copy(context, &src).await?;
+    Ok(blob.as_name().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{add_contact_to_chat, create_group_chat, send_text_msg, Chat};
+    use crate::imex::{has_backup, imex, ImexMode};
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_merge_backup() -> Result<()> {
+        let backup_dir = tempfile::tempdir()?;
+        let alice1 = &TestContext::new_alice().await;
+        let bob_chat = alice1
+            .create_chat_with_contact("Bob", "bob@example.net")
+            .await;
+        send_text_msg(alice1, bob_chat.id, "Hi Bob".to_string()).await?;
+        let bob_id = Contact::lookup_id_by_addr(alice1, "bob@example.net", Origin::Unknown)
+            .await?
+            .unwrap();
+        let group_id = create_group_chat(alice1, ProtectionStatus::Unprotected, "Group").await?;
+        add_contact_to_chat(alice1, group_id, bob_id).await?;
+        send_text_msg(alice1, group_id, "Hi group".to_string()).await?;
+        let grpid = Chat::load_from_db(alice1, group_id).await?.grpid;
+        let bob_msg_cnt = bob_chat.id.get_msg_cnt(alice1).await?;
+        let group_msg_cnt = group_id.get_msg_cnt(alice1).await?;
+        imex(alice1, ImexMode::ExportBackup, backup_dir.path(), None).await?;
+        let backup = has_backup(alice1, backup_dir.path()).await?;
+
+        let alice2 = &TestContext::new_alice().await;
+        let bob_chat2 = alice2
+            .create_chat_with_contact("Bob", "bob@example.net")
+            .await;
+        send_text_msg(alice2, bob_chat2.id, "Hi again".to_string()).await?;
+
+        for _ in 0..2 {
+            imex(alice2, ImexMode::MergeBackup, backup.as_ref(), None).await?;
+            assert_eq!(bob_chat2.id.get_msg_cnt(alice2).await?, bob_msg_cnt + 1);
+            let (group_id2, _, _) = chat::get_chat_id_by_grpid(alice2, &grpid).await?.unwrap();
+            assert_eq!(group_id2.get_msg_cnt(alice2).await?, group_msg_cnt);
+            let group2 = Chat::load_from_db(alice2, group_id2).await?;
+            assert_eq!(group2.get_name(), "Group");
+            assert_eq!(chat::get_chat_contacts(alice2, group_id2).await?.len(), 2);
+        }
+
+        // Backups of other accounts can't be merged.
+        let bob = &TestContext::new_bob().await;
+        assert!(imex(bob, ImexMode::MergeBackup, backup.as_ref(), None)
+            .await
+            .is_err());
+
+        // Merging requires a configured account.
+        let t = &TestContext::new().await;
+        assert!(imex(t, ImexMode::MergeBackup, backup.as_ref(), None)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
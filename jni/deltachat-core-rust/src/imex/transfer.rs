@@ -25,14 +25,22 @@
 //! Getter receives the backup and acknowledges successful reception
 //! by sending a single byte.
 //! Provider closes the endpoint after receiving an acknowledgment.
+//!
+//! In addition to the iroh node address the QR code contains [`BackupHints`]:
+//! the provider announces itself with mDNS and lists the addresses of all its local network
+//! interfaces, so the getter can find it on networks where iroh discovery is blocked,
+//! e.g. on a Wi-Fi hotspot opened by one of the devices.
 
 use std::future::Future;
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll;
 
 use anyhow::{bail, format_err, Context as _, Result};
 use futures_lite::FutureExt;
+use iroh_net::discovery::local_swarm_discovery::LocalSwarmDiscovery;
+use iroh_net::key::SecretKey;
 use iroh_net::relay::RelayMode;
 use iroh_net::Endpoint;
 use tokio::fs;
@@ -43,7 +51,7 @@
 use crate::context::Context;
 use crate::imex::BlobDirContents;
 use crate::message::Message;
-use crate::qr::Qr;
+use crate::qr::{BackupHints, Qr};
 use crate::stock_str::backup_transfer_msg_body;
 use crate::tools::{create_id, time, TempPathGuard};
 use crate::EventType;
@@ -53,6 +61,9 @@
 /// ALPN protocol identifier for the backup transfer protocol.
 const BACKUP_ALPN: &[u8] = b"/deltachat/backup";
 
+/// Name of the mDNS service used by iroh local swarm discovery.
+const MDNS_SERVICE: &str = "iroh.local.swarm";
+
 /// Provide or send a backup of this device.
 ///
 /// This creates a backup of the current device and starts a service which offers another
@@ -71,6 +82,9 @@ pub struct BackupProvider {
     /// iroh-net address.
     node_addr: iroh_net::NodeAddr,
 
+    /// Network bootstrap hints.
+    hints: BackupHints,
+
     /// Authentication token that should be submitted
     /// to retrieve the backup.
     auth_token: String,
@@ -95,12 +109,26 @@ impl BackupProvider {
     /// [`Accounts::stop_io`]: crate::accounts::Accounts::stop_io
     pub async fn prepare(context: &Context) -> Result<Self> {
         let relay_mode = RelayMode::Disabled;
-        let endpoint = Endpoint::builder()
+        let secret_key = SecretKey::generate();
+        let mut builder = Endpoint::builder()
+            .secret_key(secret_key.clone())
             .alpns(vec![BACKUP_ALPN.to_vec()])
-            .relay_mode(relay_mode)
-            .bind()
-            .await?;
+            .relay_mode(relay_mode);
+        let mut hints = BackupHints::default();
+        match LocalSwarmDiscovery::new(secret_key.public()) {
+            Ok(discovery) => {
+                builder = builder.discovery(Box::new(discovery));
+                hints.mdns_service = Some(MDNS_SERVICE.to_string());
+            }
+            Err(err) => warn!(context, "Failed to start mDNS discovery: {err:#}."),
+        }
+        let endpoint = builder.bind().await?;
         let node_addr = endpoint.node_addr().await?;
+        let (bound_v4, bound_v6) = endpoint.bound_sockets();
+        hints.direct_addrs = local_addr_candidates(bound_v4, bound_v6)
+            .into_iter()
+            .filter(|addr| !node_addr.info.direct_addresses.contains(addr))
+            .collect();
 
         // Acquire global "ongoing" mutex.
         let cancel_token = context.alloc_ongoing().await?;
@@ -154,6 +182,7 @@ pub async fn prepare(context: &Context) -> Result<Self> {
         Ok(Self {
             _endpoint: endpoint,
             node_addr,
+            hints,
             auth_token,
             handle,
             _drop_guard: drop_token.drop_guard(),
@@ -276,10 +305,48 @@ pub fn qr(&self) -> Qr {
             node_addr: self.node_addr.clone(),
 
             auth_token: self.auth_token.clone(),
+
+            hints: self.hints.clone(),
         }
     }
 }
 
+/// Returns addresses the endpoint bound to `bound_v4` and `bound_v6` may be reachable at.
+///
+/// These are the addresses of the network interfaces which are up,
+/// which may be missing from the iroh node address when relays are disabled.
+fn local_addr_candidates(bound_v4: SocketAddr, bound_v6: Option<SocketAddr>) -> Vec<SocketAddr> {
+    let mut addrs = Vec::new();
+    for interface in netdev::get_interfaces() {
+        if !interface.is_up() || interface.is_loopback() {
+            continue;
+        }
+        let v4_addrs = interface
+            .ipv4
+            .iter()
+            .map(|net| SocketAddr::new(net.addr.into(), bound_v4.port()));
+        let v6_addrs = bound_v6.into_iter().flat_map(|bound_v6| {
+            interface
+                .ipv6
+                .iter()
+                // Link-local addresses are not reachable without the scope ID.
+                .filter(|net| {
+                    !net.addr
+                        .segments()
+                        .first()
+                        .is_some_and(|segment| segment & 0xffc0 == 0xfe80)
+                })
+                .map(move |net| SocketAddr::new(net.addr.into(), bound_v6.port()))
+        });
+        for addr in v4_addrs.chain(v6_addrs) {
+            if !addr.ip().is_unspecified() && !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+    }
+    addrs
+}
+
 impl Future for BackupProvider {
     type Output = Result<()>;
 
@@ -291,12 +358,41 @@ fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self:
 
 pub async fn get_backup2(
     context: &Context,
-    node_addr: iroh_net::NodeAddr,
+    mut node_addr: iroh_net::NodeAddr,
     auth_token: String,
+    hints: BackupHints,
 ) -> Result<()> {
     let relay_mode = RelayMode::Disabled;
 
-    let endpoint = Endpoint::builder().relay_mode(relay_mode).bind().await?;
+    let secret_key = SecretKey::generate();
+    let mut builder = Endpoint::builder()
+        .secret_key(secret_key.clone())
+        .relay_mode(relay_mode);
+    if let Some(service) = &hints.mdns_service {
+        if service == MDNS_SERVICE {
+            match LocalSwarmDiscovery::new(secret_key.public()) {
+                Ok(discovery) => builder = builder.discovery(Box::new(discovery)),
+                Err(err) => warn!(context, "Failed to start mDNS discovery: {err:#}."),
+            }
+        } else {
+            warn!(
+                context,
+                "Unsupported mDNS service {service:?} in backup QR code."
+            );
+        }
+    }
+    if !hints.direct_addrs.is_empty() {
+        info!(
+            context,
+            "Trying {} additional backup provider addresses.",
+            hints.direct_addrs.len()
+        );
+        node_addr
+            .info
+            .direct_addresses
+            .extend(hints.direct_addrs.iter().copied());
+    }
+    let endpoint = builder.bind().await?;
 
     let conn = endpoint.connect(node_addr, BACKUP_ALPN).await?;
     let (mut send_stream, mut recv_stream) = conn.open_bi().await?;
@@ -344,9 +440,10 @@ pub async fn get_backup(context: &Context, qr: Qr) -> Result<()> {
         Qr::Backup2 {
             node_addr,
             auth_token,
+            hints,
         } => {
             let cancel_token = context.alloc_ongoing().await?;
-            let res = get_backup2(context, node_addr, auth_token)
+            let res = get_backup2(context, node_addr, auth_token, hints)
                 .race(async {
                     cancel_token.recv().await.ok();
                     Err(format_err!("Backup reception cancelled"))
@@ -51,28 +51,44 @@
 pub(crate) mod events;
 pub use events::*;
 
+pub mod activity;
 mod aheader;
 mod blob;
+pub mod calls;
 pub mod chat;
 pub mod chatlist;
+mod collation;
 pub mod config;
+pub mod config_audit;
 mod configure;
 pub mod constants;
 pub mod contact;
+pub mod contact_notes;
 pub mod context;
 mod decrypt;
 pub mod download;
 mod e2ee;
+pub mod email_rules;
 pub mod ephemeral;
+pub mod error;
+pub mod group_history;
+pub mod group_spam;
+pub mod health;
+mod heif;
+pub mod housekeeping;
 mod imap;
 pub mod imex;
 pub mod key;
 pub mod location;
 mod login_param;
+pub mod mdn;
+pub mod member_digest;
 pub mod message;
 mod mimefactory;
 pub mod mimeparser;
+pub mod notes;
 pub mod oauth2;
+pub mod ownership;
 mod param;
 pub mod peerstate;
 mod pgp;
@@ -81,15 +97,23 @@
 pub mod qr_code_generator;
 pub mod quota;
 pub mod release;
+pub mod remote_backup;
+pub mod report;
 mod scheduler;
 pub mod securejoin;
-mod simplify;
+pub mod server_capabilities;
+pub mod simplify;
 mod smtp;
+pub mod staging;
+pub mod startup;
 pub mod stock_str;
 mod sync;
 mod timesmearing;
+pub mod timezone;
 mod token;
+pub mod undo;
 mod update_helper;
+pub mod watch;
 pub mod webxdc;
 #[macro_use]
 mod dehtml;
@@ -109,6 +133,10 @@
 pub mod peer_channels;
 pub mod reaction;
 
+pub use blob::integrity::check_blob_integrity;
+pub use blob::normalize_orientation;
+pub use blob::video::{VideoDecoder, VideoMetadata};
+
 /// If set IMAP/incoming and SMTP/outgoing MIME messages will be printed.
 pub const DCC_MIME_DEBUG: &str = "DCC_MIME_DEBUG";
 
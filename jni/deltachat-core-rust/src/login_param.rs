@@ -5,6 +5,7 @@
 use anyhow::{format_err, Context as _, Result};
 use deltachat_contact_tools::EmailAddress;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::config::Config;
 use crate::configure::server_params::{expand_param_vector, ServerParams};
@@ -354,6 +355,227 @@ pub(crate) async fn prioritize_server_login_params(
     Ok(res.into_iter().map(|(_ts, param)| param).collect())
 }
 
+/// Number of consecutive failures of the preferred connection candidate
+/// after which other candidates are tried again.
+const PREFERRED_SERVER_MAX_FAILURES: i64 = 3;
+
+/// Maximum number of networks for which the preferred connection candidate is remembered.
+const PREFERRED_SERVER_MAX_NETWORKS: usize = 16;
+
+/// Port on which chatmail relays serve IMAP and SMTP in addition to HTTPS,
+/// distinguishing the protocols by ALPN.
+const CHATMAIL_ALTERNATIVE_PORT: u16 = 443;
+
+/// Returns the key storing the preferred connection candidate.
+///
+/// If the platform reported the identity of the current network,
+/// the preferred candidate is stored per network,
+/// so that e.g. a candidate working around blocked ports in a hotel Wi-Fi
+/// does not replace the candidate working at home.
+fn preferred_server_key(context: &Context, alpn: &str) -> String {
+    match context.get_network_identity() {
+        Some(identity) => {
+            let hash = Sha256::digest(identity.as_bytes());
+            format!(
+                "{alpn}_preferred_server_{}",
+                hex::encode(hash.get(..8).unwrap_or_default())
+            )
+        }
+        None => format!("{alpn}_preferred_server"),
+    }
+}
+
+fn preferred_server_failures_key(context: &Context, alpn: &str) -> String {
+    format!("{}_failures", preferred_server_key(context, alpn))
+}
+
+async fn load_preferred_server_login_param(
+    context: &Context,
+    alpn: &str,
+) -> Result<Option<ConfiguredServerLoginParam>> {
+    let Some(json) = context
+        .sql
+        .get_raw_config(&preferred_server_key(context, alpn))
+        .await?
+    else {
+        return Ok(None);
+    };
+    Ok(serde_json::from_str(&json).ok())
+}
+
+/// Returns the connection candidates to try, in order.
+///
+/// If a candidate is known to work on the current network,
+/// only this candidate is returned
+/// until it fails [`PREFERRED_SERVER_MAX_FAILURES`] times in a row,
+/// then all candidates are tried as ordered by [`prioritize_server_login_params`].
+pub(crate) async fn select_server_login_params(
+    context: &Context,
+    params: &[ConfiguredServerLoginParam],
+    alpn: &str,
+) -> Result<Vec<ConfiguredServerLoginParam>> {
+    if let Some(preferred) = load_preferred_server_login_param(context, alpn).await? {
+        let failures = context
+            .sql
+            .get_raw_config_int64(&preferred_server_failures_key(context, alpn))
+            .await?
+            .unwrap_or_default();
+        if failures < PREFERRED_SERVER_MAX_FAILURES && params.contains(&preferred) {
+            return Ok(vec![preferred]);
+        }
+    }
+    prioritize_server_login_params(&context.sql, params, alpn).await
+}
+
+/// Records that the preferred connection candidate of the current network was used last
+/// and forgets the candidates of the networks used longest ago
+/// if there are more than [`PREFERRED_SERVER_MAX_NETWORKS`].
+async fn prune_preferred_server_login_params(context: &Context, alpn: &str) -> Result<()> {
+    let key = preferred_server_key(context, alpn);
+    let networks_key = format!("{alpn}_preferred_server_networks");
+    let networks = context
+        .sql
+        .get_raw_config(&networks_key)
+        .await?
+        .unwrap_or_default();
+    let mut keys: Vec<&str> = networks.split_whitespace().filter(|k| *k != key).collect();
+    keys.push(&key);
+    let excess = keys.len().saturating_sub(PREFERRED_SERVER_MAX_NETWORKS);
+    for old_key in keys.drain(..excess) {
+        context.sql.set_raw_config(old_key, None).await?;
+        context
+            .sql
+            .set_raw_config(&format!("{old_key}_failures"), None)
+            .await?;
+    }
+    context
+        .sql
+        .set_raw_config(&networks_key, Some(&keys.join(" ")))
+        .await
+}
+
+/// Remembers `param` as the connection candidate that worked last on the current network.
+pub(crate) async fn set_preferred_server_login_param(
+    context: &Context,
+    alpn: &str,
+    param: &ConfiguredServerLoginParam,
+) -> Result<()> {
+    if load_preferred_server_login_param(context, alpn)
+        .await?
+        .as_ref()
+        != Some(param)
+    {
+        context
+            .sql
+            .set_raw_config(
+                &preferred_server_key(context, alpn),
+                Some(&serde_json::to_string(param)?),
+            )
+            .await?;
+    }
+    context
+        .sql
+        .set_raw_config_int64(&preferred_server_failures_key(context, alpn), 0)
+        .await?;
+    prune_preferred_server_login_params(context, alpn).await
+}
+
+/// Records that connecting with `param` failed.
+///
+/// Only failures of the preferred candidate are counted.
+pub(crate) async fn preferred_server_login_param_failed(
+    context: &Context,
+    alpn: &str,
+    param: &ConfiguredServerLoginParam,
+) -> Result<()> {
+    if load_preferred_server_login_param(context, alpn)
+        .await?
+        .as_ref()
+        != Some(param)
+    {
+        return Ok(());
+    }
+    let key = preferred_server_failures_key(context, alpn);
+    let failures = context
+        .sql
+        .get_raw_config_int64(&key)
+        .await?
+        .unwrap_or_default();
+    context
+        .sql
+        .set_raw_config_int64(&key, failures.saturating_add(1))
+        .await
+}
+
+/// Forgets the preferred connection candidate of the current network,
+/// so that all candidates are tried.
+pub(crate) async fn forget_preferred_server_login_param(
+    context: &Context,
+    alpn: &str,
+) -> Result<()> {
+    context
+        .sql
+        .set_raw_config(&preferred_server_key(context, alpn), None)
+        .await?;
+    context
+        .sql
+        .set_raw_config(&preferred_server_failures_key(context, alpn), None)
+        .await
+}
+
+/// Appends alternative connection candidates
+/// for networks blocking the standard ports, e.g. hotel Wi-Fi.
+///
+/// Alternative candidates are servers listed in the provider database
+/// that are not configured, e.g. because the account was configured
+/// before they were added to the database,
+/// and port 443 of chatmail relays.
+/// They are tried after the configured candidates.
+fn with_alternative_ports(
+    params: &[ConfiguredServerLoginParam],
+    provider: Option<&Provider>,
+    protocol: Protocol,
+    is_chatmail: bool,
+) -> Vec<ConfiguredServerLoginParam> {
+    let mut res = params.to_vec();
+    let Some(user) = params.first().map(|param| param.user.clone()) else {
+        return res;
+    };
+
+    let mut alternatives = Vec::new();
+    for server in provider.map_or(&[][..], |provider| provider.server) {
+        if server.protocol != protocol {
+            continue;
+        }
+        if let Ok(security) = server.socket.try_into() {
+            alternatives.push(ConnectionCandidate {
+                host: server.hostname.to_string(),
+                port: server.port,
+                security,
+            });
+        }
+    }
+    if is_chatmail {
+        for param in params {
+            alternatives.push(ConnectionCandidate {
+                host: param.connection.host.clone(),
+                port: CHATMAIL_ALTERNATIVE_PORT,
+                security: ConnectionSecurity::Tls,
+            });
+        }
+    }
+
+    for connection in alternatives {
+        if !res.iter().any(|param| param.connection == connection) {
+            res.push(ConfiguredServerLoginParam {
+                connection,
+                user: user.clone(),
+            });
+        }
+    }
+    res
+}
+
 /// Login parameters saved to the database
 /// after successful configuration.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -788,6 +1010,32 @@ pub fn strict_tls(&self) -> bool {
             | ConfiguredCertificateChecks::AcceptInvalidCertificates2 => false,
         }
     }
+
+    /// Returns the IMAP connection candidates including alternative ports.
+    pub(crate) async fn imap_candidates(
+        &self,
+        context: &Context,
+    ) -> Result<Vec<ConfiguredServerLoginParam>> {
+        Ok(with_alternative_ports(
+            &self.imap,
+            self.provider,
+            Protocol::Imap,
+            context.is_chatmail().await?,
+        ))
+    }
+
+    /// Returns the SMTP connection candidates including alternative ports.
+    pub(crate) async fn smtp_candidates(
+        &self,
+        context: &Context,
+    ) -> Result<Vec<ConfiguredServerLoginParam>> {
+        Ok(with_alternative_ports(
+            &self.smtp,
+            self.provider,
+            Protocol::Smtp,
+            context.is_chatmail().await?,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -796,6 +1044,191 @@ mod tests {
     use crate::provider::get_provider_by_id;
     use crate::test_utils::TestContext;
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_preferred_server_login_param() -> Result<()> {
+        let t = TestContext::new().await;
+        let params: Vec<ConfiguredServerLoginParam> = [
+            (465, ConnectionSecurity::Tls),
+            (587, ConnectionSecurity::Starttls),
+        ]
+        .into_iter()
+        .map(|(port, security)| ConfiguredServerLoginParam {
+            connection: ConnectionCandidate {
+                host: "smtp.example.org".to_string(),
+                port,
+                security,
+            },
+            user: "alice".to_string(),
+        })
+        .collect();
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?.len(),
+            2
+        );
+
+        set_preferred_server_login_param(&t, "smtp", &params[1]).await?;
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[1].clone()]
+        );
+        // Other protocols are not affected.
+        assert_eq!(
+            select_server_login_params(&t, &params, "imap").await?.len(),
+            2
+        );
+
+        // Failures of other candidates are not counted.
+        for _ in 0..PREFERRED_SERVER_MAX_FAILURES {
+            preferred_server_login_param_failed(&t, "smtp", &params[0]).await?;
+        }
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[1].clone()]
+        );
+
+        for _ in 0..PREFERRED_SERVER_MAX_FAILURES {
+            preferred_server_login_param_failed(&t, "smtp", &params[1]).await?;
+        }
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?.len(),
+            2
+        );
+
+        set_preferred_server_login_param(&t, "smtp", &params[0]).await?;
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[0].clone()]
+        );
+
+        // Preferred candidate is ignored if it is not configured anymore.
+        assert_eq!(
+            select_server_login_params(&t, &params[1..], "smtp")
+                .await?
+                .len(),
+            1
+        );
+
+        forget_preferred_server_login_param(&t, "smtp").await?;
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?.len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_preferred_server_per_network() -> Result<()> {
+        let t = TestContext::new().await;
+        let params: Vec<ConfiguredServerLoginParam> = [465, 443]
+            .into_iter()
+            .map(|port| ConfiguredServerLoginParam {
+                connection: ConnectionCandidate {
+                    host: "example.org".to_string(),
+                    port,
+                    security: ConnectionSecurity::Tls,
+                },
+                user: "alice".to_string(),
+            })
+            .collect();
+
+        set_preferred_server_login_param(&t, "smtp", &params[0]).await?;
+
+        // In the hotel, only port 443 works.
+        t.set_network_identity(Some("hotel"));
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?.len(),
+            2
+        );
+        set_preferred_server_login_param(&t, "smtp", &params[1]).await?;
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[1].clone()]
+        );
+
+        // At home, the standard port is still preferred.
+        t.set_network_identity(None);
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[0].clone()]
+        );
+        t.set_network_identity(Some("hotel"));
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[1].clone()]
+        );
+
+        // Only the networks used last are remembered.
+        for i in 0..PREFERRED_SERVER_MAX_NETWORKS {
+            t.set_network_identity(Some(&format!("network {i}")));
+            set_preferred_server_login_param(&t, "smtp", &params[1]).await?;
+        }
+        let remembered = t
+            .sql
+            .count(
+                "SELECT COUNT(*) FROM config WHERE keyname LIKE 'smtp_preferred_server%_failures'",
+                (),
+            )
+            .await?;
+        assert_eq!(remembered, PREFERRED_SERVER_MAX_NETWORKS);
+        t.set_network_identity(None);
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?.len(),
+            2
+        );
+        t.set_network_identity(Some("network 0"));
+        assert_eq!(
+            select_server_login_params(&t, &params, "smtp").await?,
+            [params[1].clone()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_alternative_ports() {
+        let params = vec![ConfiguredServerLoginParam {
+            connection: ConnectionCandidate {
+                host: "chat.example.org".to_string(),
+                port: 993,
+                security: ConnectionSecurity::Tls,
+            },
+            user: "alice@chat.example.org".to_string(),
+        }];
+
+        assert_eq!(
+            with_alternative_ports(&params, None, Protocol::Imap, false),
+            params
+        );
+
+        let res = with_alternative_ports(&params, None, Protocol::Imap, true);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0], params[0]);
+        assert_eq!(res[1].connection.host, "chat.example.org");
+        assert_eq!(res[1].connection.port, 443);
+        assert_eq!(res[1].connection.security, ConnectionSecurity::Tls);
+        assert_eq!(res[1].user, "alice@chat.example.org");
+        // Alternative ports are not added twice.
+        assert_eq!(
+            with_alternative_ports(&res, None, Protocol::Imap, true),
+            res
+        );
+
+        // Servers listed in the provider database are added.
+        let provider = get_provider_by_id("nine.testrun.org").unwrap();
+        let params = vec![ConfiguredServerLoginParam {
+            connection: ConnectionCandidate {
+                host: "nine.testrun.org".to_string(),
+                port: 465,
+                security: ConnectionSecurity::Tls,
+            },
+            user: "alice@nine.testrun.org".to_string(),
+        }];
+        let res = with_alternative_ports(&params, Some(provider), Protocol::Smtp, false);
+        assert_eq!(res[0], params[0]);
+        assert!(res.iter().any(|param| param.connection.port == 443
+            && param.connection.security == ConnectionSecurity::Tls));
+        assert!(res.iter().all(|param| param.connection.port != 993));
+    }
+
     #[test]
     fn test_certificate_checks_display() {
         use std::string::ToString;
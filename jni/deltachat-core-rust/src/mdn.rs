@@ -0,0 +1,341 @@
+//! # Read receipt (MDN) sending rules.
+//!
+//! Whether a read receipt is sent for a seen message is decided by [`MdnPolicy`]
+//! set for the chat, or, if the chat has no policy set, for the sender.
+//! If neither is set, [`Config::MdnsEnabled`] decides.
+//! The policy is checked both when the message is marked as seen
+//! and when the read receipt is about to be sent,
+//! so changing the policy also affects read receipts which are still queued.
+//!
+//! Read receipts may be delayed by [`Config::MdnsBatchDelay`] seconds
+//! so that receipts for several messages from the same contact are sent as one message.
+
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use num_traits::FromPrimitive;
+
+use crate::chat::{Chat, ChatId};
+use crate::config::Config;
+use crate::constants::DC_CHAT_ID_LAST_SPECIAL;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::MsgId;
+use crate::param::{Param, Params};
+use crate::tools::time;
+
+/// Read receipt policy of a chat or a contact.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum MdnPolicy {
+    /// Not set, the policy of the contact or [`Config::MdnsEnabled`] is used.
+    #[default]
+    Default = 0,
+
+    /// Always send read receipts.
+    Always = 1,
+
+    /// Never send read receipts.
+    Never = 2,
+
+    /// Send read receipts only if the contact is verified.
+    VerifiedOnly = 3,
+}
+
+fn get_policy(param: &Params) -> MdnPolicy {
+    param
+        .get_int(Param::MdnPolicy)
+        .and_then(MdnPolicy::from_i32)
+        .unwrap_or_default()
+}
+
+fn set_policy(param: &mut Params, policy: MdnPolicy) {
+    match policy {
+        MdnPolicy::Default => param.remove(Param::MdnPolicy),
+        _ => param.set_int(Param::MdnPolicy, policy as i32),
+    };
+}
+
+impl Chat {
+    /// Returns the read receipt policy of the chat.
+    pub fn get_mdn_policy(&self) -> MdnPolicy {
+        get_policy(&self.param)
+    }
+}
+
+impl Contact {
+    /// Returns the read receipt policy of the contact.
+    pub fn get_mdn_policy(&self) -> MdnPolicy {
+        get_policy(&self.param)
+    }
+}
+
+impl ChatId {
+    /// Sets the read receipt policy of the chat.
+    ///
+    /// The chat policy takes precedence over the policy of the contact.
+    pub async fn set_mdn_policy(self, context: &Context, policy: MdnPolicy) -> Result<()> {
+        let mut chat = Chat::load_from_db(context, self).await?;
+        set_policy(&mut chat.param, policy);
+        chat.update_param(context).await?;
+        context.emit_event(EventType::ChatModified(self));
+        Ok(())
+    }
+}
+
+impl ContactId {
+    /// Sets the read receipt policy of the contact.
+    pub async fn set_mdn_policy(self, context: &Context, policy: MdnPolicy) -> Result<()> {
+        let mut contact = Contact::get_by_id(context, self).await?;
+        set_policy(&mut contact.param, policy);
+        contact.update_param(context).await?;
+        context.emit_event(EventType::ContactsChanged(Some(self)));
+        Ok(())
+    }
+}
+
+/// Returns whether a read receipt should be sent
+/// for a message in the chat `chat_id` sent by `contact_id`.
+pub(crate) async fn should_send_mdn(
+    context: &Context,
+    chat_id: ChatId,
+    contact_id: ContactId,
+) -> Result<bool> {
+    let chat_policy = Chat::load_from_db(context, chat_id).await?.get_mdn_policy();
+    is_allowed(context, chat_policy, contact_id).await
+}
+
+/// Returns whether the queued read receipt for the message `msg_id`
+/// should still be sent to `contact_id`.
+///
+/// If the message is deleted meanwhile, only the contact policy is taken into account.
+pub(crate) async fn should_send_queued_mdn(
+    context: &Context,
+    msg_id: MsgId,
+    contact_id: ContactId,
+) -> Result<bool> {
+    let chat_id: Option<ChatId> = context
+        .sql
+        .query_get_value("SELECT chat_id FROM msgs WHERE id=?", (msg_id,))
+        .await?;
+    let chat_policy = match chat_id {
+        Some(chat_id) if !chat_id.is_special() => {
+            Chat::load_from_db(context, chat_id).await?.get_mdn_policy()
+        }
+        _ => MdnPolicy::Default,
+    };
+    is_allowed(context, chat_policy, contact_id).await
+}
+
+/// Returns the Message-IDs of the read receipts queued for `contact_id`
+/// other than `except_rfc724_mid` which should still be sent,
+/// see [`should_send_queued_mdn`].
+pub(crate) async fn get_queued_mdns_to_send(
+    context: &Context,
+    contact_id: ContactId,
+    except_rfc724_mid: &str,
+) -> Result<Vec<String>> {
+    let queued = context
+        .sql
+        .query_map(
+            "SELECT s.rfc724_mid, c.param
+             FROM smtp_mdns s
+             LEFT JOIN msgs m ON m.id=s.msg_id
+             LEFT JOIN chats c ON c.id=m.chat_id AND c.id>?
+             WHERE s.from_id=? AND s.rfc724_mid!=?",
+            (DC_CHAT_ID_LAST_SPECIAL, contact_id, except_rfc724_mid),
+            |row| {
+                let rfc724_mid: String = row.get(0)?;
+                let param: Option<String> = row.get(1)?;
+                let chat_policy = param
+                    .map(|param| get_policy(&param.parse().unwrap_or_default()))
+                    .unwrap_or_default();
+                Ok((rfc724_mid, chat_policy))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+
+    // There are only a few policies, so each is only checked once.
+    let mut checked: Vec<(MdnPolicy, bool)> = Vec::new();
+    let mut rfc724_mids = Vec::new();
+    for (rfc724_mid, chat_policy) in queued {
+        let allowed = match checked.iter().find(|(policy, _)| *policy == chat_policy) {
+            Some((_, allowed)) => *allowed,
+            None => {
+                let allowed = is_allowed(context, chat_policy, contact_id).await?;
+                checked.push((chat_policy, allowed));
+                allowed
+            }
+        };
+        if allowed {
+            rfc724_mids.push(rfc724_mid);
+        }
+    }
+    Ok(rfc724_mids)
+}
+
+async fn is_allowed(
+    context: &Context,
+    chat_policy: MdnPolicy,
+    contact_id: ContactId,
+) -> Result<bool> {
+    let contact = Contact::get_by_id(context, contact_id).await?;
+    let policy = match chat_policy {
+        MdnPolicy::Default => contact.get_mdn_policy(),
+        policy => policy,
+    };
+    match policy {
+        MdnPolicy::Default => context.should_send_mdns().await,
+        MdnPolicy::Always => Ok(true),
+        MdnPolicy::Never => Ok(false),
+        MdnPolicy::VerifiedOnly => contact.is_verified(context).await,
+    }
+}
+
+/// Queues a read receipt for the message `msg_id` to be sent to `contact_id`.
+pub(crate) async fn queue_mdn(
+    context: &Context,
+    msg_id: MsgId,
+    contact_id: ContactId,
+    rfc724_mid: &str,
+) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "INSERT INTO smtp_mdns (msg_id, from_id, rfc724_mid, timestamp) VALUES(?, ?, ?, ?)",
+            (msg_id, contact_id, rfc724_mid, time()),
+        )
+        .await
+        .context("failed to insert into smtp_mdns")?;
+    context.interrupt_smtp().await;
+    Ok(())
+}
+
+/// Returns the timestamp up to which queued read receipts are due.
+pub(crate) async fn due_timestamp(context: &Context) -> Result<i64> {
+    let delay = context.get_config_i64(Config::MdnsBatchDelay).await?;
+    Ok(time().saturating_sub(delay.max(0)))
+}
+
+/// Returns the time until the next queued read receipt is due,
+/// `None` if there are no read receipts waiting for the batch delay to pass.
+pub(crate) async fn time_until_next_due(context: &Context) -> Result<Option<Duration>> {
+    let due_timestamp = due_timestamp(context).await?;
+    let next: Option<i64> = context
+        .sql
+        .query_get_value(
+            "SELECT MIN(timestamp) FROM smtp_mdns WHERE timestamp>?",
+            (due_timestamp,),
+        )
+        .await?;
+    Ok(next.map(|next| Duration::from_secs(next.saturating_sub(due_timestamp) as u64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::markseen_msgs;
+    use crate::test_utils::{mark_as_verified, TestContextManager};
+
+    async fn queued_mdns(context: &Context) -> Result<usize> {
+        context
+            .sql
+            .count("SELECT COUNT(*) FROM smtp_mdns", ())
+            .await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_mdn_policy() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let msg = tcm.send_recv_accept(alice, bob, "hi").await;
+        let bob_chat_id = msg.chat_id;
+        let alice_id = msg.from_id;
+        assert_eq!(
+            Chat::load_from_db(bob, bob_chat_id).await?.get_mdn_policy(),
+            MdnPolicy::Default
+        );
+
+        bob.set_config_bool(Config::MdnsEnabled, false).await?;
+        alice_id.set_mdn_policy(bob, MdnPolicy::Always).await?;
+        assert_eq!(
+            Contact::get_by_id(bob, alice_id).await?.get_mdn_policy(),
+            MdnPolicy::Always
+        );
+        assert!(should_send_mdn(bob, bob_chat_id, alice_id).await?);
+
+        bob_chat_id.set_mdn_policy(bob, MdnPolicy::Never).await?;
+        assert!(!should_send_mdn(bob, bob_chat_id, alice_id).await?);
+        markseen_msgs(bob, vec![msg.id]).await?;
+        assert_eq!(queued_mdns(bob).await?, 0);
+
+        bob_chat_id
+            .set_mdn_policy(bob, MdnPolicy::VerifiedOnly)
+            .await?;
+        assert!(!should_send_mdn(bob, bob_chat_id, alice_id).await?);
+        mark_as_verified(bob, alice).await;
+        assert!(should_send_mdn(bob, bob_chat_id, alice_id).await?);
+
+        bob_chat_id.set_mdn_policy(bob, MdnPolicy::Default).await?;
+        alice_id.set_mdn_policy(bob, MdnPolicy::Default).await?;
+        assert!(!should_send_mdn(bob, bob_chat_id, alice_id).await?);
+        bob.set_config_bool(Config::MdnsEnabled, true).await?;
+        assert!(should_send_mdn(bob, bob_chat_id, alice_id).await?);
+
+        let msg = tcm.send_recv(alice, bob, "seen").await;
+        markseen_msgs(bob, vec![msg.id]).await?;
+        assert_eq!(queued_mdns(bob).await?, 1);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_queued_mdns_to_send() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let msg = tcm.send_recv_accept(alice, bob, "hi").await;
+        let alice_id = msg.from_id;
+        queue_mdn(bob, msg.id, alice_id, "first@example.org").await?;
+        queue_mdn(bob, msg.id, alice_id, "second@example.org").await?;
+        // Read receipts of deleted messages are only subject to the contact policy.
+        queue_mdn(bob, MsgId::new(12345), alice_id, "deleted@example.org").await?;
+
+        let mut queued = get_queued_mdns_to_send(bob, alice_id, "first@example.org").await?;
+        queued.sort();
+        assert_eq!(queued, ["deleted@example.org", "second@example.org"]);
+
+        msg.chat_id.set_mdn_policy(bob, MdnPolicy::Never).await?;
+        assert_eq!(
+            get_queued_mdns_to_send(bob, alice_id, "first@example.org").await?,
+            ["deleted@example.org"]
+        );
+        alice_id.set_mdn_policy(bob, MdnPolicy::Never).await?;
+        assert!(get_queued_mdns_to_send(bob, alice_id, "first@example.org")
+            .await?
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_mdns_batch_delay() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let msg = tcm.send_recv_accept(alice, bob, "hi").await;
+        markseen_msgs(bob, vec![msg.id]).await?;
+        assert_eq!(time_until_next_due(bob).await?, None);
+
+        bob.set_config(Config::MdnsBatchDelay, Some("600")).await?;
+        let next = time_until_next_due(bob).await?.unwrap();
+        assert!(next > Duration::from_secs(590));
+        assert!(next <= Duration::from_secs(600));
+        assert!(due_timestamp(bob).await? < time());
+        Ok(())
+    }
+}
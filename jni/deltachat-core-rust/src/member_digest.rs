@@ -0,0 +1,217 @@
+//! # Digests of membership changes in large groups.
+//!
+//! In groups with more than [`Config::MemberDigestThreshold`] members,
+//! received member additions and removals are not shown as one info message each.
+//! Instead, they are recorded in the `member_changes` table
+//! and summarized in a single info message per chat and day
+//! which is updated as more changes arrive.
+//! The recorded changes can be listed with [`get_member_changes`].
+//!
+//! Changes affecting the user, e.g. being added to or removed from a group,
+//! are always shown as usual.
+
+use anyhow::Result;
+
+use crate::chat::{self, ChatId};
+use crate::config::Config;
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::message::MsgId;
+use crate::stock_str;
+use crate::timezone::LocalTimezone;
+use crate::tools::{smeared_time, time};
+
+/// Recorded addition or removal of a group member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberChange {
+    /// Contact added to or removed from the group.
+    pub contact_id: ContactId,
+
+    /// True if the contact was added, false if it was removed or left the group.
+    pub added: bool,
+
+    /// Contact who added or removed the member,
+    /// the member itself if it left the group.
+    pub by_contact_id: ContactId,
+
+    /// Time of the change as sent by `by_contact_id`.
+    pub timestamp: i64,
+}
+
+/// Returns whether membership changes in a group with `member_count` members are digested.
+pub(crate) async fn is_digested(context: &Context, member_count: usize) -> Result<bool> {
+    let threshold = context
+        .get_config_int(Config::MemberDigestThreshold)
+        .await?;
+    Ok(threshold > 0 && member_count > threshold as usize)
+}
+
+/// Returns the digest message of today for the chat if there is one.
+async fn get_todays_digest(context: &Context, chat_id: ChatId) -> Result<Option<(MsgId, i64)>> {
+    let digest = context
+        .sql
+        .query_row_optional(
+            "SELECT m.id, m.timestamp FROM member_changes c
+             INNER JOIN msgs m ON m.id=c.digest_msg_id
+             WHERE c.chat_id=? AND m.chat_id=?
+             ORDER BY c.id DESC LIMIT 1",
+            (chat_id, chat_id),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let timestamp: i64 = row.get(1)?;
+                Ok((msg_id, timestamp))
+            },
+        )
+        .await?;
+    let timezone = LocalTimezone::load(context).await?;
+    Ok(digest.filter(|(_, timestamp)| timezone.is_same_day(*timestamp, time())))
+}
+
+/// Records a membership change and adds it to the digest message of today,
+/// creating the message if needed.
+pub(crate) async fn record_change(
+    context: &Context,
+    chat_id: ChatId,
+    change: MemberChange,
+) -> Result<()> {
+    let (digest_msg_id, digest_timestamp) = match get_todays_digest(context, chat_id).await? {
+        Some(digest) => digest,
+        None => {
+            let timestamp = smeared_time(context);
+            let msg_id = chat::add_info_msg(context, chat_id, "", timestamp).await?;
+            (msg_id, timestamp)
+        }
+    };
+    context
+        .sql
+        .execute(
+            "INSERT INTO member_changes
+             (chat_id, contact_id, added, by_contact_id, timestamp, digest_msg_id)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                chat_id,
+                change.contact_id,
+                change.added,
+                change.by_contact_id,
+                change.timestamp,
+                digest_msg_id,
+            ),
+        )
+        .await?;
+
+    let (added, removed) = context
+        .sql
+        .query_row(
+            "SELECT COUNT(*) FILTER (WHERE added), COUNT(*) FILTER (WHERE NOT added)
+             FROM member_changes WHERE digest_msg_id=?",
+            (digest_msg_id,),
+            |row| {
+                let added: usize = row.get(0)?;
+                let removed: usize = row.get(1)?;
+                Ok((added, removed))
+            },
+        )
+        .await?;
+    let text = stock_str::member_changes_digest(context, added, removed).await;
+    chat::update_msg_text_and_timestamp(context, chat_id, digest_msg_id, &text, digest_timestamp)
+        .await
+}
+
+/// Returns the recorded membership changes of the group, oldest first.
+pub async fn get_member_changes(context: &Context, chat_id: ChatId) -> Result<Vec<MemberChange>> {
+    context
+        .sql
+        .query_map(
+            "SELECT contact_id, added, by_contact_id, timestamp FROM member_changes
+             WHERE chat_id=? ORDER BY id",
+            (chat_id,),
+            |row| {
+                Ok(MemberChange {
+                    contact_id: row.get(0)?,
+                    added: row.get(1)?,
+                    by_contact_id: row.get(2)?,
+                    timestamp: row.get(3)?,
+                })
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{add_contact_to_chat, remove_contact_from_chat, ProtectionStatus};
+    use crate::contact::{Contact, Origin};
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_is_same_local_day() {
+        let now = time();
+        assert!(LocalTimezone::Device.is_same_day(now, now));
+        assert!(!LocalTimezone::Device.is_same_day(now, now - 2 * 24 * 60 * 60));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_member_changes_digest() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        bob.set_config(Config::MemberDigestThreshold, Some("2"))
+            .await?;
+
+        let alice_chat_id =
+            chat::create_group_chat(alice, ProtectionStatus::Unprotected, "Crowd").await?;
+        let alice_bob_id = alice.add_or_lookup_contact_id(bob).await;
+        let alice_fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+        let alice_charlie_id = Contact::create(alice, "Charlie", "charlie@example.net").await?;
+        add_contact_to_chat(alice, alice_chat_id, alice_bob_id).await?;
+        add_contact_to_chat(alice, alice_chat_id, alice_fiona_id).await?;
+        let sent = alice.send_text(alice_chat_id, "Welcome").await;
+        let bob_chat_id = bob.recv_msg(&sent).await.chat_id;
+        assert!(get_member_changes(bob, bob_chat_id).await?.is_empty());
+
+        add_contact_to_chat(alice, alice_chat_id, alice_charlie_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        let digest = bob.get_last_msg_in(bob_chat_id).await;
+        assert!(digest.is_info());
+        assert_eq!(digest.get_text(), "Members joined: 1, left: 0");
+
+        remove_contact_from_chat(alice, alice_chat_id, alice_fiona_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        let digest2 = bob.get_last_msg_in(bob_chat_id).await;
+        assert_eq!(digest2.id, digest.id);
+        assert_eq!(digest2.get_text(), "Members joined: 1, left: 1");
+
+        let changes = get_member_changes(bob, bob_chat_id).await?;
+        assert_eq!(changes.len(), 2);
+        let bob_alice_id = bob.add_or_lookup_contact_id(alice).await;
+        let bob_charlie_id =
+            Contact::lookup_id_by_addr(bob, "charlie@example.net", Origin::Unknown).await?;
+        assert_eq!(Some(changes[0].contact_id), bob_charlie_id);
+        assert!(changes[0].added);
+        assert_eq!(changes[0].by_contact_id, bob_alice_id);
+        assert_eq!(
+            changes[1].contact_id,
+            bob.add_or_lookup_contact_id(fiona).await
+        );
+        assert!(!changes[1].added);
+
+        // Removing the user is always shown.
+        remove_contact_from_chat(alice, alice_chat_id, alice_bob_id).await?;
+        let msg = bob.recv_msg(&alice.pop_sent_msg().await).await;
+        assert!(msg.is_info());
+        assert_eq!(get_member_changes(bob, bob_chat_id).await?.len(), 2);
+
+        // The changes are removed with the chat.
+        bob_chat_id.delete(bob).await?;
+        assert_eq!(
+            bob.sql
+                .count("SELECT COUNT(*) FROM member_changes", ())
+                .await?,
+            0
+        );
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
 //! # Messages and their identifiers.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use std::str;
 
-use anyhow::{ensure, format_err, Context as _, Result};
+use anyhow::{bail, ensure, format_err, Context as _, Result};
+use base64::Engine as _;
 use deltachat_contact_tools::{parse_vcard, VcardContact};
 use deltachat_derive::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
+use strum_macros::Display;
 use tokio::{fs, io};
 
 use crate::blob::BlobObject;
@@ -23,18 +25,23 @@
 use crate::download::DownloadState;
 use crate::ephemeral::{start_ephemeral_timers_msgids, Timer as EphemeralTimer};
 use crate::events::EventType;
+use crate::heif;
 use crate::imap::markseen_on_imap_table;
 use crate::location::delete_poi_location;
+use crate::mdn;
 use crate::mimeparser::{parse_message_id, SystemMessage};
 use crate::param::{Param, Params};
 use crate::pgp::split_armored_data;
 use crate::reaction::get_msg_reactions;
 use crate::sql;
 use crate::summary::Summary;
+use crate::sync;
+use crate::timezone::LocalTimezone;
 use crate::tools::{
-    buf_compress, buf_decompress, get_filebytes, get_filemeta, gm2local_offset, read_file, time,
-    timestamp_to_str, truncate,
+    buf_compress, buf_decompress, create_id, get_filebytes, get_filemeta, read_file, time,
+    timestamp_to_str, truncate, TempPathGuard,
 };
+use crate::undo;
 
 /// Message ID, including reserved IDs.
 ///
@@ -149,16 +156,33 @@ pub async fn trash(self, context: &Context, on_server: bool) -> Result<()> {
     }
 
     pub(crate) async fn set_delivered(self, context: &Context) -> Result<()> {
-        update_msg_state(context, self, MessageState::OutDelivered).await?;
-        let chat_id: Option<ChatId> = context
+        let row = context
             .sql
-            .query_get_value("SELECT chat_id FROM msgs WHERE id=?", (self,))
+            .query_row_optional(
+                "SELECT chat_id, state FROM msgs WHERE id=?",
+                (self,),
+                |row| {
+                    let chat_id: ChatId = row.get(0)?;
+                    let state: MessageState = row.get(1)?;
+                    Ok((chat_id, state))
+                },
+            )
             .await?;
+        update_msg_state(context, self, MessageState::OutDelivered).await?;
+        let chat_id = row.map(|(chat_id, _state)| chat_id);
         context.emit_event(EventType::MsgDelivered {
             chat_id: chat_id.unwrap_or_default(),
             msg_id: self,
         });
-        if let Some(chat_id) = chat_id {
+        if let Some((chat_id, old_state)) = row {
+            emit_msg_state_changed(
+                context,
+                chat_id,
+                self,
+                old_state,
+                MessageState::OutDelivered,
+                MsgStateChangeCause::SmtpAccepted,
+            );
             chatlist_events::emit_chatlist_item_changed(context, chat_id);
         }
         Ok(())
@@ -339,6 +363,9 @@ pub async fn get_info(self, context: &Context) -> Result<String> {
         if duration != 0 {
             ret += &format!("Duration: {duration} ms\n",);
         }
+        if let Some(hash) = msg.param.get(Param::FileHash) {
+            ret += &format!("SHA-256: {hash}\n");
+        }
         if !rawtxt.is_empty() {
             ret += &format!("\n{rawtxt}\n");
         }
@@ -481,6 +508,10 @@ pub struct Message {
     pub(crate) location_id: u32,
     pub(crate) error: Option<String>,
     pub(crate) param: Params,
+
+    /// True if the message vanished from the server
+    /// because another client deleted or expunged it.
+    pub(crate) removed_from_server: bool,
 }
 
 impl Message {
@@ -548,6 +579,7 @@ pub async fn load_from_db_optional(context: &Context, id: MsgId) -> Result<Optio
                     "    m.param AS param,",
                     "    m.hidden AS hidden,",
                     "    m.location_id AS location,",
+                    "    m.removed_from_server AS removed_from_server,",
                     "    c.blocked AS blocked",
                     " FROM msgs m",
                     " LEFT JOIN chats c ON c.id=m.chat_id",
@@ -604,6 +636,7 @@ pub async fn load_from_db_optional(context: &Context, id: MsgId) -> Result<Optio
                         param: row.get::<_, String>("param")?.parse().unwrap_or_default(),
                         hidden: row.get("hidden")?,
                         location_id: row.get("location")?,
+                        removed_from_server: row.get("removed_from_server")?,
                         chat_blocked: row
                             .get::<_, Option<Blocked>>("blocked")?
                             .unwrap_or_default(),
@@ -642,6 +675,35 @@ pub fn get_file(&self, context: &Context) -> Option<PathBuf> {
         self.param.get_path(Param::File, context).unwrap_or(None)
     }
 
+    /// Returns the full path to the thumbnail of a video attachment, if any.
+    ///
+    /// The thumbnail is a JPEG image which can be shown
+    /// without decoding the video.
+    pub fn get_thumbnail(&self, context: &Context) -> Option<PathBuf> {
+        self.param
+            .get_path(Param::Thumbnail, context)
+            .unwrap_or(None)
+    }
+
+    /// Returns the full path to the preview of a PDF attachment, if any.
+    ///
+    /// The preview is a JPEG image of the first page.
+    /// It is only rendered if the core is built with the `pdf-preview` feature.
+    pub fn get_preview_blob(&self, context: &Context) -> Option<PathBuf> {
+        self.param.get_path(Param::Preview, context).unwrap_or(None)
+    }
+
+    /// Returns the waveform of a voice message, if any.
+    ///
+    /// The waveform consists of 64 peaks scaled so that the highest one is 255,
+    /// evenly distributed over the duration of the message.
+    pub fn get_waveform(&self) -> Option<Vec<u8>> {
+        let waveform = self.param.get(Param::Waveform)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(waveform)
+            .ok()
+    }
+
     /// Returns vector of vcards if the file has a vCard attachment.
     pub async fn vcard_contacts(&self, context: &Context) -> Result<Vec<VcardContact>> {
         if self.viewtype != Viewtype::Vcard {
@@ -657,16 +719,50 @@ pub async fn vcard_contacts(&self, context: &Context) -> Result<Vec<VcardContact
     }
 
     /// Save file copy at the user-provided path.
+    ///
+    /// Fails if file already exists at the provided path.
     pub async fn save_file(&self, context: &Context, path: &Path) -> Result<()> {
+        self.save_file_ex(context, path, SaveFilePolicy::Error)
+            .await?;
+        Ok(())
+    }
+
+    /// Save file copy at the user-provided path,
+    /// resolving conflicts with existing files according to `policy`.
+    ///
+    /// Returns the path the file was saved at, with [`SaveFilePolicy::AutoNumber`]
+    /// this may differ from `path`.
+    pub async fn save_file_ex(
+        &self,
+        context: &Context,
+        path: &Path,
+        policy: SaveFilePolicy,
+    ) -> Result<PathBuf> {
         let path_src = self.get_file(context).context("No file")?;
+        ensure!(path != path_src, "Cannot save file onto itself");
         let mut src = fs::OpenOptions::new().read(true).open(path_src).await?;
-        let mut dst = fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(path)
-            .await?;
+        if policy == SaveFilePolicy::Overwrite {
+            // Copy to a temporary file first,
+            // so that the existing file is not destroyed if copying fails.
+            let file_name = path.file_name().context("No file name")?;
+            let tmp_path = TempPathGuard::new(path.with_file_name(format!(
+                ".{}.{}.tmp",
+                file_name.to_string_lossy(),
+                create_id()
+            )));
+            let mut dst = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&*tmp_path)
+                .await?;
+            io::copy(&mut src, &mut dst).await?;
+            dst.sync_all().await?;
+            fs::rename(&*tmp_path, path).await?;
+            return Ok(path.to_path_buf());
+        }
+        let (path, mut dst) = create_save_file(path, policy).await?;
         io::copy(&mut src, &mut dst).await?;
-        Ok(())
+        Ok(path)
     }
 
     /// If message is an image or gif, set Param::Width and Param::Height
@@ -780,6 +876,27 @@ pub fn force_sticker(&mut self) {
         self.param.set_int(Param::ForceSticker, 1);
     }
 
+    /// Sets whether an image should be sent in original quality.
+    ///
+    /// The image is then sent as [Viewtype::Image] without scaling it down or recoding it,
+    /// regardless of [`Config::MediaQuality`], so metadata such as the location is kept as well.
+    /// Images too large for common providers are recoded anyway.
+    pub fn set_original_quality(&mut self, original_quality: bool) {
+        if original_quality {
+            self.param.set_int(Param::OriginalQuality, 1);
+        } else {
+            self.param.remove(Param::OriginalQuality);
+        }
+    }
+
+    /// Returns whether an image should be sent in original quality,
+    /// see [`Message::set_original_quality`].
+    pub fn is_original_quality(&self) -> bool {
+        self.param
+            .get_bool(Param::OriginalQuality)
+            .unwrap_or_default()
+    }
+
     /// Returns the state of the message.
     pub fn get_state(&self) -> MessageState {
         self.state
@@ -904,6 +1021,16 @@ pub fn get_override_sender_name(&self) -> Option<String> {
             .map(|name| name.to_string())
     }
 
+    /// Returns the known contact whose name the unknown sender of the message uses,
+    /// the message possibly impersonates that contact.
+    /// `ContactId::SELF` is returned if the sender uses our own name.
+    pub fn get_impersonated_contact(&self) -> Option<ContactId> {
+        self.param
+            .get_int(Param::ImpersonatedContact)
+            .and_then(|id| u32::try_from(id).ok())
+            .map(ContactId::new)
+    }
+
     // Exposing this function over the ffi instead of get_override_sender_name() would mean that at least Android Java code has
     // to handle raw C-data (as it is done for msg_get_summary())
     pub(crate) fn get_sender_name(&self, contact: &Contact) -> String {
@@ -915,12 +1042,10 @@ pub(crate) fn get_sender_name(&self, contact: &Contact) -> String {
     ///
     /// A message has a deviating timestamp when it is sent on
     /// another day as received/sorted by.
-    pub fn has_deviating_timestamp(&self) -> bool {
-        let cnv_to_local = gm2local_offset();
-        let sort_timestamp = self.get_sort_timestamp() + cnv_to_local;
-        let send_timestamp = self.get_timestamp() + cnv_to_local;
-
-        sort_timestamp / 86400 != send_timestamp / 86400
+    /// Days are computed in the timezone set in [`Config::Timezone`].
+    pub async fn has_deviating_timestamp(&self, context: &Context) -> Result<bool> {
+        let timezone = LocalTimezone::load(context).await?;
+        Ok(!timezone.is_same_day(self.get_sort_timestamp(), self.get_timestamp()))
     }
 
     /// Returns true if the message was successfully delivered to the outgoing server or even
@@ -934,6 +1059,15 @@ pub fn is_forwarded(&self) -> bool {
         0 != self.param.get_int(Param::Forwarded).unwrap_or_default()
     }
 
+    /// Returns true if the message was removed from the server by another client,
+    /// e.g. deleted or expunged by a different mail program.
+    ///
+    /// The local copy is kept, but it cannot be downloaded anymore
+    /// and deleting it will not affect the server.
+    pub fn is_removed_from_server(&self) -> bool {
+        self.removed_from_server
+    }
+
     /// Returns true if the message is an informational message.
     pub fn is_info(&self) -> bool {
         let cmd = self.param.get_cmd();
@@ -1098,6 +1232,9 @@ pub fn set_file(&mut self, file: impl ToString, filemime: Option<&str>) {
         }
         self.param.set(Param::File, file);
         self.param.set_optional(Param::MimeType, filemime);
+        // A replaced staged attachment must be prepared again.
+        self.param.remove(Param::Staged);
+        self.param.remove(Param::FileHash);
     }
 
     /// Creates a new blob and sets it as a file associated with a message.
@@ -1112,6 +1249,8 @@ pub async fn set_file_from_bytes(
         self.param.set(Param::Filename, suggested_name);
         self.param.set(Param::File, blob.as_name());
         self.param.set_optional(Param::MimeType, filemime);
+        self.param.remove(Param::Staged);
+        self.param.remove(Param::FileHash);
         Ok(())
     }
 
@@ -1272,6 +1411,23 @@ pub async fn parent(&self, context: &Context) -> Result<Option<Message>> {
         Ok(None)
     }
 
+    /// Returns the root of the reply thread the message belongs to.
+    ///
+    /// Threads are chains of quote replies within a chat.
+    /// If the message does not quote a message of the same chat,
+    /// the message is the root of its own thread.
+    pub async fn get_thread_root(&self, context: &Context) -> Result<MsgId> {
+        let mut root = self.clone();
+        let mut seen = BTreeSet::new();
+        while seen.insert(root.id) {
+            match root.quoted_message(context).await? {
+                Some(parent) if parent.chat_id == self.chat_id && !parent.hidden => root = parent,
+                _ => break,
+            }
+        }
+        Ok(root.id)
+    }
+
     /// Force the message to be sent in plain text.
     pub fn force_plaintext(&mut self) {
         self.param.set_int(Param::ForcePlaintext, 1);
@@ -1431,6 +1587,46 @@ pub(crate) fn with_mdns(self, has_mdns: bool) -> Self {
     }
 }
 
+/// Cause of a message state change, see [`EventType::MsgStateChanged`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum MsgStateChangeCause {
+    /// The SMTP server accepted the message.
+    SmtpAccepted,
+
+    /// Sending the message failed or a non-delivery notification was received.
+    SendFailed,
+
+    /// A read receipt was received.
+    MdnReceived,
+
+    /// Resending the message was requested.
+    ResendRequested,
+
+    /// The message was marked as seen or noticed on this device.
+    MarkedSeen,
+}
+
+/// Emits [`EventType::MsgStateChanged`] if the message state has actually changed.
+pub(crate) fn emit_msg_state_changed(
+    context: &Context,
+    chat_id: ChatId,
+    msg_id: MsgId,
+    old_state: MessageState,
+    new_state: MessageState,
+    cause: MsgStateChangeCause,
+) {
+    if old_state != new_state {
+        context.emit_event(EventType::MsgStateChanged {
+            chat_id,
+            msg_id,
+            old_state,
+            new_state,
+            cause,
+        });
+    }
+}
+
 /// Returns contacts that sent read receipts and the time of reading.
 pub async fn get_msg_read_receipts(
     context: &Context,
@@ -1451,8 +1647,90 @@ pub async fn get_msg_read_receipts(
         .await
 }
 
+/// Returns the replies in the thread with the root `root_id`,
+/// including replies to replies, in the order of the chat.
+///
+/// See [`Message::get_thread_root()`].
+pub async fn get_thread_replies(context: &Context, root_id: MsgId) -> Result<Vec<MsgId>> {
+    let replies = load_thread_replies(context, root_id).await?;
+    Ok(replies.into_iter().map(|(msg_id, _state)| msg_id).collect())
+}
+
+/// Returns the number of fresh replies in the thread with the root `root_id`.
+pub async fn get_thread_fresh_msg_cnt(context: &Context, root_id: MsgId) -> Result<usize> {
+    let replies = load_thread_replies(context, root_id).await?;
+    Ok(replies
+        .iter()
+        .filter(|(_msg_id, state)| *state == MessageState::InFresh)
+        .count())
+}
+
+async fn load_thread_replies(
+    context: &Context,
+    root_id: MsgId,
+) -> Result<Vec<(MsgId, MessageState)>> {
+    let root = Message::load_from_db(context, root_id).await?;
+    if root.rfc724_mid.is_empty() {
+        return Ok(Vec::new());
+    }
+    let msgs = context
+        .sql
+        .query_map(
+            "SELECT id, rfc724_mid, mime_in_reply_to, param, state FROM msgs
+             WHERE chat_id=? AND hidden=0 AND id!=? AND IFNULL(mime_in_reply_to, '')!=''
+             ORDER BY timestamp, id",
+            (root.chat_id, root_id),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let rfc724_mid: String = row.get(1)?;
+                let in_reply_to: String = row.get(2)?;
+                let param: Params = row.get::<_, String>(3)?.parse().unwrap_or_default();
+                let state: MessageState = row.get(4)?;
+                Ok((msg_id, rfc724_mid, in_reply_to, param, state))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+
+    // Only quotes make a message a reply,
+    // `In-Reply-To` of other messages points to the previous message of the chat.
+    let mut children: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, (_msg_id, _rfc724_mid, in_reply_to, param, _state)) in msgs.iter().enumerate() {
+        if param.get(Param::Quote).is_none()
+            || param.get_int(Param::Forwarded).unwrap_or_default() != 0
+        {
+            continue;
+        }
+        if let Ok(parent_mid) = parse_message_id(in_reply_to) {
+            children.entry(parent_mid).or_default().push(index);
+        }
+    }
+
+    let mut thread = Vec::new();
+    let mut queue = vec![root.rfc724_mid];
+    while let Some(parent_mid) = queue.pop() {
+        for index in children.remove(&parent_mid).unwrap_or_default() {
+            if let Some((_msg_id, rfc724_mid, ..)) = msgs.get(index) {
+                queue.push(rfc724_mid.clone());
+                thread.push(index);
+            }
+        }
+    }
+    thread.sort_unstable();
+    Ok(thread
+        .into_iter()
+        .filter_map(|index| msgs.get(index).map(|msg| (msg.0, msg.4)))
+        .collect())
+}
+
 pub(crate) fn guess_msgtype_from_suffix(path: &Path) -> Option<(Viewtype, &str)> {
     let extension: &str = &path.extension()?.to_str()?.to_lowercase();
+    // HEIF images are converted to JPEG when sending if this build can decode them.
+    let heif_viewtype = if heif::SUPPORTED {
+        Viewtype::Image
+    } else {
+        Viewtype::File
+    };
     let info = match extension {
         // before using viewtype other than Viewtype::File,
         // make sure, all target UIs support that type in the context of the used viewer/player.
@@ -1461,7 +1739,7 @@ pub(crate) fn guess_msgtype_from_suffix(path: &Path) -> Option<(Viewtype, &str)>
         "3gp" => (Viewtype::Video, "video/3gpp"),
         "aac" => (Viewtype::Audio, "audio/aac"),
         "avi" => (Viewtype::Video, "video/x-msvideo"),
-        "avif" => (Viewtype::File, "image/avif"), // supported since Android 12 / iOS 16
+        "avif" => (heif_viewtype, "image/avif"), // supported since Android 12 / iOS 16
         "doc" => (Viewtype::File, "application/msword"),
         "docx" => (
             Viewtype::File,
@@ -1470,8 +1748,8 @@ pub(crate) fn guess_msgtype_from_suffix(path: &Path) -> Option<(Viewtype, &str)>
         "epub" => (Viewtype::File, "application/epub+zip"),
         "flac" => (Viewtype::Audio, "audio/flac"),
         "gif" => (Viewtype::Gif, "image/gif"),
-        "heic" => (Viewtype::File, "image/heic"), // supported since Android 10 / iOS 11
-        "heif" => (Viewtype::File, "image/heif"), // supported since Android 10 / iOS 11
+        "heic" => (heif_viewtype, "image/heic"), // supported since Android 10 / iOS 11
+        "heif" => (heif_viewtype, "image/heif"), // supported since Android 10 / iOS 11
         "html" => (Viewtype::File, "text/html"),
         "htm" => (Viewtype::File, "text/html"),
         "ico" => (Viewtype::File, "image/vnd.microsoft.icon"),
@@ -1598,7 +1876,25 @@ pub async fn get_mime_headers(context: &Context, msg_id: MsgId) -> Result<Vec<u8
 /// Deletes requested messages
 /// by moving them to the trash chat
 /// and scheduling for deletion on IMAP.
+///
+/// If `Config::DeletionUndoWindow` is set, the messages are only hidden
+/// and can be restored with [`crate::undo::undo_last_deletion`] until the window passes.
 pub async fn delete_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
+    if undo::get_undo_window(context).await? > 0 {
+        return undo::delete_msgs(context, msg_ids).await;
+    }
+    delete_msgs_ex(context, msg_ids, true).await
+}
+
+/// Deletes messages immediately.
+///
+/// `emit_events` is false if the messages were already removed from the UI,
+/// e.g. because they are purged after the undo window.
+pub(crate) async fn delete_msgs_ex(
+    context: &Context,
+    msg_ids: &[MsgId],
+    emit_events: bool,
+) -> Result<()> {
     let mut modified_chat_ids = BTreeSet::new();
     let mut res = Ok(());
 
@@ -1613,16 +1909,18 @@ pub async fn delete_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
             .await
             .with_context(|| format!("Unable to trash message {msg_id}"))?;
 
-        context.emit_event(EventType::MsgDeleted {
-            chat_id: msg.chat_id,
-            msg_id,
-        });
+        if emit_events {
+            context.emit_event(EventType::MsgDeleted {
+                chat_id: msg.chat_id,
+                msg_id,
+            });
 
-        if msg.viewtype == Viewtype::Webxdc {
-            context.emit_event(EventType::WebxdcInstanceDeleted { msg_id });
-        }
+            if msg.viewtype == Viewtype::Webxdc {
+                context.emit_event(EventType::WebxdcInstanceDeleted { msg_id });
+            }
 
-        modified_chat_ids.insert(msg.chat_id);
+            modified_chat_ids.insert(msg.chat_id);
+        }
 
         let target = context.get_delete_msgs_target().await?;
         let update_db = |conn: &mut rusqlite::Connection| {
@@ -1660,8 +1958,10 @@ pub async fn delete_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
     }
 
     if !msg_ids.is_empty() {
-        context.emit_msgs_changed_without_ids();
-        chatlist_events::emit_chatlist_changed(context);
+        if emit_events {
+            context.emit_msgs_changed_without_ids();
+            chatlist_events::emit_chatlist_changed(context);
+        }
         // Run housekeeping to delete unused blobs.
         context
             .set_config_internal(Config::LastHousekeeping, None)
@@ -1766,11 +2066,27 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
                 // Don't mark partially downloaded messages as seen or send a read receipt since
                 // they are not really seen by the user.
                 update_msg_state(context, id, MessageState::InNoticed).await?;
+                emit_msg_state_changed(
+                    context,
+                    curr_chat_id,
+                    id,
+                    curr_state,
+                    MessageState::InNoticed,
+                    MsgStateChangeCause::MarkedSeen,
+                );
                 updated_chat_ids.insert(curr_chat_id);
             }
         } else if curr_state == MessageState::InFresh || curr_state == MessageState::InNoticed {
             update_msg_state(context, id, MessageState::InSeen).await?;
             info!(context, "Seen message {}.", id);
+            emit_msg_state_changed(
+                context,
+                curr_chat_id,
+                id,
+                curr_state,
+                MessageState::InSeen,
+                MsgStateChangeCause::MarkedSeen,
+            );
 
             markseen_on_imap_table(context, &curr_rfc724_mid).await?;
 
@@ -1786,17 +2102,9 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
             if curr_blocked == Blocked::Not
                 && curr_param.get_bool(Param::WantsMdn).unwrap_or_default()
                 && curr_param.get_cmd() == SystemMessage::Unknown
-                && context.should_send_mdns().await?
+                && mdn::should_send_mdn(context, curr_chat_id, curr_from_id).await?
             {
-                context
-                    .sql
-                    .execute(
-                        "INSERT INTO smtp_mdns (msg_id, from_id, rfc724_mid) VALUES(?, ?, ?)",
-                        (id, curr_from_id, curr_rfc724_mid),
-                    )
-                    .await
-                    .context("failed to insert into smtp_mdns")?;
-                context.scheduler.interrupt_smtp().await;
+                mdn::queue_mdn(context, id, curr_from_id, &curr_rfc724_mid).await?;
             }
             updated_chat_ids.insert(curr_chat_id);
         }
@@ -1812,6 +2120,37 @@ pub async fn markseen_msgs(context: &Context, msg_ids: Vec<MsgId>) -> Result<()>
         context.on_archived_chats_maybe_noticed();
     }
 
+    update_read_positions(context, &msg_ids).await?;
+    Ok(())
+}
+
+/// Advances the read position of each chat to the newest of the seen messages in it
+/// and synchronizes the positions to other devices.
+async fn update_read_positions(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
+    // SQLite returns the values of the row with the maximum timestamp for the bare `id` column.
+    let newest = context
+        .sql
+        .query_map(
+            &format!(
+                "SELECT chat_id, id, MAX(timestamp) FROM msgs
+                 WHERE id IN ({}) AND chat_id>9
+                 GROUP BY chat_id",
+                sql::repeat_vars(msg_ids.len())
+            ),
+            rusqlite::params_from_iter(msg_ids),
+            |row| {
+                let chat_id: ChatId = row.get(0)?;
+                let msg_id: MsgId = row.get(1)?;
+                Ok((chat_id, msg_id))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    for (chat_id, msg_id) in newest {
+        chat_id
+            .set_read_position_ex(context, sync::Sync::Sync, msg_id)
+            .await?;
+    }
     Ok(())
 }
 
@@ -1851,6 +2190,7 @@ pub(crate) async fn set_msg_failed(
     msg: &mut Message,
     error: &str,
 ) -> Result<()> {
+    let old_state = msg.state;
     if msg.state.can_fail() {
         msg.state = MessageState::OutFailed;
         warn!(context, "{} failed: {}", msg.id, error);
@@ -1875,6 +2215,14 @@ pub(crate) async fn set_msg_failed(
         msg_id: msg.id,
     });
     if exists {
+        emit_msg_state_changed(
+            context,
+            msg.chat_id,
+            msg.id,
+            old_state,
+            msg.state,
+            MsgStateChangeCause::SendFailed,
+        );
         chatlist_events::emit_chatlist_item_changed(context, msg.chat_id);
     }
     Ok(())
@@ -1920,6 +2268,17 @@ pub async fn get_request_msg_cnt(context: &Context) -> usize {
     }
 }
 
+/// Returns the number of messages that were removed from the server by another client.
+pub async fn get_removed_from_server_cnt(context: &Context) -> Result<usize> {
+    context
+        .sql
+        .count(
+            "SELECT COUNT(*) FROM msgs WHERE removed_from_server=1 AND chat_id>9",
+            (),
+        )
+        .await
+}
+
 /// Estimates the number of messages that will be deleted
 /// by the options `delete_device_after` or `delete_server_after`.
 ///
@@ -1965,15 +2324,22 @@ pub async fn estimate_deletion_cnt(
             .count(
                 "SELECT COUNT(*)
              FROM msgs m
+             INNER JOIN chats c ON c.id=m.chat_id
              WHERE m.id > ?
-               AND timestamp < ?
-               AND chat_id != ?
-               AND chat_id != ? AND hidden = 0;",
+               AND m.timestamp < ?
+               AND m.chat_id != ?
+               AND m.chat_id != ? AND m.hidden = 0
+               AND c.auto_delete_exempt=0
+               AND NOT (c.type=? AND EXISTS (
+                 SELECT 1 FROM chats_contacts cc
+                 INNER JOIN contacts ct ON ct.id=cc.contact_id
+                 WHERE cc.chat_id=c.id AND ct.auto_delete_exempt=1));",
                 (
                     DC_MSG_ID_LAST_SPECIAL,
                     threshold_timestamp,
                     self_chat_id,
                     DC_CHAT_ID_TRASH,
+                    Chattype::Single,
                 ),
             )
             .await?
@@ -2067,6 +2433,55 @@ pub(crate) fn get_vcard_summary(vcard: &[u8]) -> Option<String> {
     Some(c.display_name().to_string())
 }
 
+/// What to do if the destination of [`Message::save_file_ex`] already exists.
+#[derive(Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum SaveFilePolicy {
+    /// Fail.
+    #[default]
+    Error = 0,
+
+    /// Replace the existing file.
+    Overwrite = 1,
+
+    /// Save the file under a name with a number added,
+    /// e.g. `photo (1).jpg` instead of `photo.jpg`.
+    AutoNumber = 2,
+}
+
+/// Creates the file to save a message file at,
+/// `policy` must not be [`SaveFilePolicy::Overwrite`].
+///
+/// Returns the path of the created file and its handle.
+async fn create_save_file(path: &Path, policy: SaveFilePolicy) -> Result<(PathBuf, fs::File)> {
+    /// Maximum number tried with [`SaveFilePolicy::AutoNumber`].
+    const MAX_NUMBER: u32 = 1000;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    match options.open(path).await {
+        Ok(file) => return Ok((path.to_path_buf(), file)),
+        Err(err)
+            if policy == SaveFilePolicy::AutoNumber
+                && err.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(err) => return Err(err.into()),
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    for number in 1..=MAX_NUMBER {
+        let numbered_path = path.with_file_name(format!("{stem} ({number}){ext}"));
+        match options.open(&numbered_path).await {
+            Ok(file) => return Ok((numbered_path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    bail!("Too many files named like {}", path.display())
+}
+
 /// How a message is primarily displayed.
 #[derive(
     Debug,
@@ -2204,6 +2619,17 @@ fn test_guess_msgtype_from_suffix() {
             guess_msgtype_from_suffix(Path::new("foo/file.xdc")),
             Some((Viewtype::Webxdc, "application/webxdc+zip"))
         );
+        assert_eq!(
+            guess_msgtype_from_suffix(Path::new("foo/IMG_0001.HEIC")),
+            Some((
+                if heif::SUPPORTED {
+                    Viewtype::Image
+                } else {
+                    Viewtype::File
+                },
+                "image/heic"
+            ))
+        );
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -2442,6 +2868,43 @@ async fn test_unencrypted_quote_encrypted_message() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_thread_replies() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let root = tcm.send_recv_accept(alice, bob, "Root").await;
+        let other = tcm.send_recv(alice, bob, "Other").await;
+
+        let mut reply = Message::new_text("Reply".to_string());
+        reply.set_quote(bob, Some(&root)).await?;
+        let sent = bob.send_msg(root.chat_id, &mut reply).await;
+        let alice_reply = alice.recv_msg(&sent).await;
+
+        let mut reply2 = Message::new_text("Reply to reply".to_string());
+        reply2.set_quote(alice, Some(&alice_reply)).await?;
+        let sent = alice.send_msg(alice_reply.chat_id, &mut reply2).await;
+        let bob_reply2 = bob.recv_msg(&sent).await;
+
+        assert_eq!(bob_reply2.get_thread_root(bob).await?, root.id);
+        assert_eq!(root.get_thread_root(bob).await?, root.id);
+        // `In-Reply-To` without a quote does not make a reply.
+        assert_eq!(other.get_thread_root(bob).await?, other.id);
+
+        assert_eq!(
+            get_thread_replies(bob, root.id).await?,
+            [reply.id, bob_reply2.id]
+        );
+        assert_eq!(get_thread_replies(bob, reply.id).await?, [bob_reply2.id]);
+        assert!(get_thread_replies(bob, other.id).await?.is_empty());
+        assert_eq!(get_thread_fresh_msg_cnt(bob, root.id).await?, 1);
+
+        marknoticed_chat(bob, root.chat_id).await?;
+        assert_eq!(get_thread_fresh_msg_cnt(bob, root.id).await?, 0);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_get_chat_id() {
         // Alice receives a message that pops up as a contact request
@@ -2750,6 +3213,102 @@ async fn assert_state(t: &Context, msg_id: MsgId, state: MessageState) {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_msg_state_changed_event() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let alice_chat = alice.create_chat(bob).await;
+
+        async fn expect_state_change(
+            t: &TestContext,
+            expected_msg_id: MsgId,
+            expected_old: MessageState,
+            expected_new: MessageState,
+            expected_cause: MsgStateChangeCause,
+        ) {
+            let EventType::MsgStateChanged {
+                msg_id,
+                old_state,
+                new_state,
+                cause,
+                ..
+            } = t
+                .evtracker
+                .get_matching(|evt| matches!(evt, EventType::MsgStateChanged { .. }))
+                .await
+            else {
+                unreachable!();
+            };
+            assert_eq!(msg_id, expected_msg_id);
+            assert_eq!(old_state, expected_old);
+            assert_eq!(new_state, expected_new);
+            assert_eq!(cause, expected_cause);
+        }
+
+        let msg_id = send_text_msg(alice, alice_chat.id, "hi!".to_string()).await?;
+        let sent = alice.pop_sent_msg().await;
+        update_msg_state(alice, msg_id, MessageState::OutPending).await?;
+        alice.evtracker.clear_events();
+        msg_id.set_delivered(alice).await?;
+        expect_state_change(
+            alice,
+            msg_id,
+            MessageState::OutPending,
+            MessageState::OutDelivered,
+            MsgStateChangeCause::SmtpAccepted,
+        )
+        .await;
+
+        let mut msg = Message::load_from_db(alice, msg_id).await?;
+        set_msg_failed(alice, &mut msg, "badly failed").await?;
+        expect_state_change(
+            alice,
+            msg_id,
+            MessageState::OutDelivered,
+            MessageState::OutFailed,
+            MsgStateChangeCause::SendFailed,
+        )
+        .await;
+
+        chat::resend_msgs(alice, &[msg_id]).await?;
+        expect_state_change(
+            alice,
+            msg_id,
+            MessageState::OutFailed,
+            MessageState::OutPending,
+            MsgStateChangeCause::ResendRequested,
+        )
+        .await;
+
+        let bob_msg = bob.recv_msg(&sent).await;
+        bob.evtracker.clear_events();
+        markseen_msgs(bob, vec![bob_msg.id]).await?;
+        expect_state_change(
+            bob,
+            bob_msg.id,
+            MessageState::InFresh,
+            MessageState::InSeen,
+            MsgStateChangeCause::MarkedSeen,
+        )
+        .await;
+
+        let sent = alice.send_text(alice_chat.id, "again").await;
+        let bob_msg = bob.recv_msg(&sent).await;
+        bob.evtracker.clear_events();
+        chat::marknoticed_chat(bob, bob_msg.chat_id).await?;
+        expect_state_change(
+            bob,
+            bob_msg.id,
+            MessageState::InFresh,
+            MessageState::InNoticed,
+            MsgStateChangeCause::MarkedSeen,
+        )
+        .await;
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_is_bot() -> Result<()> {
         let alice = TestContext::new_alice().await;
@@ -5,31 +5,37 @@
 use anyhow::{bail, Context as _, Result};
 use base64::Engine as _;
 use chrono::TimeZone;
+use deltachat_contact_tools::addr_cmp;
 use email::Mailbox;
 use lettre_email::{Address, Header, MimeMultipartType, PartBuilder};
 use tokio::fs;
 
 use crate::blob::BlobObject;
+use crate::calls;
 use crate::chat::{self, Chat};
 use crate::config::Config;
-use crate::constants::{Chattype, DC_FROM_HANDSHAKE};
+use crate::constants::{Chattype, DC_ELLIPSIS, DC_FROM_HANDSHAKE};
 use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
 use crate::e2ee::EncryptHelper;
 use crate::ephemeral::Timer as EphemeralTimer;
+use crate::group_history;
 use crate::headerdef::HeaderDef;
 use crate::html::new_html_mimepart;
 use crate::location;
 use crate::message::{self, Message, MsgId, Viewtype};
 use crate::mimeparser::SystemMessage;
+use crate::net::stats::NetworkCounters;
 use crate::param::Param;
 use crate::peer_channels::create_iroh_header;
 use crate::peerstate::Peerstate;
+use crate::report;
 use crate::simplify::escape_message_footer_marks;
 use crate::stock_str;
 use crate::tools::IsNoneOrEmpty;
 use crate::tools::{
-    create_outgoing_rfc724_mid, create_smeared_timestamp, remove_subject_prefix, time,
+    create_outgoing_rfc724_mid, create_smeared_timestamp, is_subject_placeholder,
+    remove_subject_prefix, time,
 };
 use crate::webxdc::StatusUpdateSerial;
 
@@ -64,6 +70,10 @@ pub struct MimeFactory {
     /// names are alsways read from the `From:`-header.
     sender_displayname: Option<String>,
 
+    /// Own name used in automatic subjects,
+    /// the display name if profile data is attached and the address otherwise.
+    self_name: String,
+
     selfstatus: String,
 
     /// Vector of pairs of recipient name and address
@@ -93,6 +103,10 @@ pub struct MimeFactory {
     /// and must be deleted if the message is actually queued for sending.
     sync_ids_to_delete: Option<String>,
 
+    /// Hex-encoded SHA-256 hash of the attachment, if the message has one.
+    /// Returned via `RenderedEmail` to be stored in the message params.
+    attachment_hash: Option<String>,
+
     /// True if the avatar should be attached.
     pub attach_selfavatar: bool,
 }
@@ -110,6 +124,10 @@ pub struct RenderedEmail {
     /// from `multi_device_sync` once the message is actually queued for sending.
     pub sync_ids_to_delete: Option<String>,
 
+    /// Hex-encoded SHA-256 hash of the attachment
+    /// sent in the `Chat-Attachment-Hash` header.
+    pub attachment_hash: Option<String>,
+
     /// Message ID (Message in the sense of Email)
     pub rfc724_mid: String,
 
@@ -134,6 +152,10 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
             .get_config(Config::Displayname)
             .await?
             .unwrap_or_default();
+        let self_name = match attach_profile_data && !config_displayname.is_empty() {
+            true => config_displayname.clone(),
+            false => from_addr.clone(),
+        };
         let (from_displayname, sender_displayname) =
             if let Some(override_name) = msg.param.get(Param::OverrideSenderDisplayname) {
                 (override_name.to_string(), Some(config_displayname))
@@ -190,6 +212,11 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
                     },
                 )
                 .await?;
+            if msg.param.get_cmd() == SystemMessage::GroupHistory {
+                // Group history is only sent to the new member.
+                let addr = msg.param.get(Param::Arg2).unwrap_or_default();
+                recipients.retain(|(_, recipient_addr)| addr_cmp(recipient_addr, addr));
+            }
             let recipient_ids: Vec<_> = recipient_ids.into_iter().collect();
             ContactId::scaleup_origin(context, &recipient_ids, Origin::OutgoingTo).await?;
 
@@ -230,6 +257,7 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
             from_addr,
             from_displayname,
             sender_displayname,
+            self_name,
             selfstatus,
             recipients,
             timestamp: msg.timestamp_sort,
@@ -239,6 +267,7 @@ pub async fn from_msg(context: &Context, msg: Message) -> Result<MimeFactory> {
             req_mdn,
             last_added_location_id: None,
             sync_ids_to_delete: None,
+            attachment_hash: None,
             attach_selfavatar,
         };
         Ok(factory)
@@ -258,6 +287,7 @@ pub async fn from_mdn(
             from_addr,
             from_displayname: "".to_string(),
             sender_displayname: None,
+            self_name: "".to_string(),
             selfstatus: "".to_string(),
             recipients: vec![("".to_string(), contact.get_addr().to_string())],
             timestamp,
@@ -270,6 +300,7 @@ pub async fn from_mdn(
             req_mdn: false,
             last_added_location_id: None,
             sync_ids_to_delete: None,
+            attachment_hash: None,
             attach_selfavatar: false,
         };
 
@@ -358,9 +389,23 @@ async fn should_do_gossip(&self, context: &Context, multiple_recipients: bool) -
                     // enabling gossip in every message.
                     // Otherwise "smeared timestamps" may result in the condition
                     // to fail even if the clock is monotonic.
-                    if gossip_period == 0 || time() >= gossiped_timestamp + gossip_period {
+                    if gossip_period == 0 {
+                        return Ok(true);
+                    }
+                    // Gossip in large groups less often,
+                    // every gossiping message carries the keys of all members.
+                    let large_group_size = context.get_config_int(Config::LargeGroupSize).await?;
+                    let period_factor = match usize::try_from(large_group_size) {
+                        Ok(size) if size > 0 => self.recipients.len() / size + 1,
+                        _ => 1,
+                    };
+                    let now = time();
+                    if now >= gossiped_timestamp + gossip_period * period_factor as i64 {
                         Ok(true)
                     } else {
+                        if now >= gossiped_timestamp + gossip_period {
+                            NetworkCounters::inc(&context.network_counters.gossip_throttled);
+                        }
                         Ok(false)
                     }
                 } else {
@@ -432,21 +477,36 @@ fn grpimage(&self) -> Option<String> {
     async fn subject_str(&self, context: &Context) -> Result<String> {
         let subject = match &self.loaded {
             Loaded::Message { ref chat, msg } => {
-                let quoted_msg_subject = msg.quoted_message(context).await?.map(|m| m.subject);
+                // Subjects of replies from email clients not supporting protected headers
+                // are ignored, they only contain the placeholder of the encrypted subject.
+                let quoted_msg_subject = msg
+                    .quoted_message(context)
+                    .await?
+                    .map(|m| m.subject)
+                    .filter(|subject| !is_subject_placeholder(subject));
 
                 if !msg.subject.is_empty() {
                     return Ok(msg.subject.clone());
                 }
 
-                if (chat.typ == Chattype::Group || chat.typ == Chattype::Broadcast)
-                    && quoted_msg_subject.is_none_or_empty()
-                {
+                let is_group = chat.typ == Chattype::Group || chat.typ == Chattype::Broadcast;
+                if quoted_msg_subject.is_none_or_empty() {
                     let re = if self.in_reply_to.is_empty() {
                         ""
                     } else {
                         "Re: "
                     };
-                    return Ok(format!("{}{}", re, chat.name));
+                    // Groups keep the group name as subject, subject templates are ignored.
+                    if is_group {
+                        return Ok(format!("{}{}", re, chat.name));
+                    }
+                    let template = chat.id.get_subject_template(context).await?;
+                    if !template.is_empty() {
+                        return Ok(format!(
+                            "{re}{}",
+                            template.replace("{name}", &self.self_name)
+                        ));
+                    }
                 }
 
                 let parent_subject = if quoted_msg_subject.is_none_or_empty() {
@@ -458,15 +518,7 @@ async fn subject_str(&self, context: &Context) -> Result<String> {
                     return Ok(format!("Re: {}", remove_subject_prefix(last_subject)));
                 }
 
-                let self_name = match Self::should_attach_profile_data(msg) {
-                    true => context.get_config(Config::Displayname).await?,
-                    false => None,
-                };
-                let self_name = &match self_name {
-                    Some(name) => name,
-                    None => context.get_config(Config::Addr).await?.unwrap_or_default(),
-                };
-                stock_str::subject_for_new_contact(context, self_name).await
+                stock_str::subject_for_new_contact(context, &self.self_name).await
             }
             Loaded::Mdn { .. } => "Receipt Notification".to_string(), // untranslated to no reveal sender's language
         };
@@ -554,7 +606,11 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
         };
         headers.push(Header::new("Subject".into(), encoded_subject));
 
-        let date = chrono::DateTime::<chrono::Utc>::from_timestamp(self.timestamp, 0)
+        let timestamp = match context.get_config_bool(Config::MinimalHeaders).await? {
+            true => self.timestamp - self.timestamp.rem_euclid(60),
+            false => self.timestamp,
+        };
+        let date = chrono::DateTime::<chrono::Utc>::from_timestamp(timestamp, 0)
             .unwrap()
             .to_rfc2822();
         headers.push(Header::new("Date".into(), date));
@@ -740,7 +796,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             if header_name == "message-id" {
                 unprotected_headers.push(header.clone());
                 hidden_headers.push(header);
-            } else if header_name == "chat-user-avatar" {
+            } else if header_name == "chat-user-avatar" || header_name == "chat-video-thumbnail" {
                 hidden_headers.push(header);
             } else if header_name == "autocrypt"
                 && !context.get_config_bool(Config::ProtectAutocrypt).await?
@@ -795,7 +851,12 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
 
                 match header_name.as_str() {
                     "subject" => {
-                        unprotected_headers.push(Header::new(header.name, "[...]".to_string()));
+                        if context.get_config_bool(Config::ProtectSubject).await? {
+                            unprotected_headers
+                                .push(Header::new(header.name, DC_ELLIPSIS.to_string()));
+                        } else {
+                            unprotected_headers.push(header);
+                        }
                     }
                     "date"
                     | "in-reply-to"
@@ -836,6 +897,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                 for peerstate in peerstates.iter().filter_map(|(state, _)| state.as_ref()) {
                     if let Some(header) = peerstate.render_gossip_header(verified) {
                         message = message.header(Header::new("Autocrypt-Gossip".into(), header));
+                        NetworkCounters::inc(&context.network_counters.gossip_headers);
                         is_gossiped = true;
                     }
                 }
@@ -978,6 +1040,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             is_gossiped,
             last_added_location_id,
             sync_ids_to_delete: self.sync_ids_to_delete,
+            attachment_hash: self.attachment_hash,
             rfc724_mid,
             subject: subject_str,
         })
@@ -1227,6 +1290,46 @@ async fn render_message(
                     "protection-disabled".to_string(),
                 ));
             }
+            SystemMessage::GroupHistory => {
+                headers.push(Header::new(
+                    "Chat-Content".to_string(),
+                    "group-history".to_string(),
+                ));
+                headers.push(Header::new(
+                    "Auto-Submitted".to_string(),
+                    "auto-generated".to_string(),
+                ));
+            }
+            SystemMessage::MsgReport => {
+                headers.push(Header::new(
+                    "Chat-Content".to_string(),
+                    "message-report".to_string(),
+                ));
+                headers.push(Header::new(
+                    "Auto-Submitted".to_string(),
+                    "auto-generated".to_string(),
+                ));
+            }
+            SystemMessage::CallInvite => {
+                headers.push(Header::new(
+                    "Chat-Content".to_string(),
+                    "call-invite".to_string(),
+                ));
+            }
+            SystemMessage::CallAccepted
+            | SystemMessage::CallRejected
+            | SystemMessage::CallEnded => {
+                let value = match command {
+                    SystemMessage::CallAccepted => "call-accepted",
+                    SystemMessage::CallRejected => "call-rejected",
+                    _ => "call-ended",
+                };
+                headers.push(Header::new("Chat-Content".to_string(), value.to_string()));
+                headers.push(Header::new(
+                    "Auto-Submitted".to_string(),
+                    "auto-generated".to_string(),
+                ));
+            }
             SystemMessage::IrohNodeAddr => {
                 headers.push(Header::new(
                     HeaderDef::IrohNodeAddr.get_headername().to_string(),
@@ -1280,6 +1383,39 @@ async fn render_message(
             }
         }
 
+        if msg.viewtype == Viewtype::Video {
+            if let Some(thumbnail) = msg.param.get(Param::Thumbnail) {
+                match build_avatar_file(context, thumbnail).await {
+                    Ok(thumbnail) => headers.push(Header::new(
+                        "Chat-Video-Thumbnail".into(),
+                        format!("base64:{thumbnail}"),
+                    )),
+                    Err(err) => warn!(context, "Cannot attach video thumbnail: {err:#}."),
+                }
+            }
+        }
+
+        if msg.viewtype.has_file() {
+            // Let the receiver detect attachments corrupted on the way.
+            // If the message is encrypted, the header is signed together with the attachment.
+            // The hash is computed when the message is prepared,
+            // messages prepared by older versions or forwarded messages are hashed here.
+            let hash = match msg.param.get(Param::FileHash) {
+                Some(hash) => Some(hash.to_string()),
+                None => match msg.param.get_blob(Param::File, context, true).await? {
+                    Some(blob) => Some(blob.checksum(context).await?),
+                    None => None,
+                },
+            };
+            if let Some(hash) = hash {
+                headers.push(Header::new(
+                    "Chat-Attachment-Hash".into(),
+                    format!("sha256={hash}"),
+                ));
+                self.attachment_hash = Some(hash);
+            }
+        }
+
         // add text part - we even add empty text and force a MIME-multipart-message as:
         // - some Apps have problems with Non-text in the main part (eg. "Mail" from stock Android)
         // - we can add "forward hints" this way
@@ -1393,6 +1529,21 @@ async fn render_message(
         } else if command == SystemMessage::WebxdcStatusUpdate {
             let json = msg.param.get(Param::Arg).unwrap_or_default();
             parts.push(context.build_status_update_part(json));
+        } else if command == SystemMessage::GroupHistory && self.is_e2ee_guaranteed() {
+            let json = msg.param.get(Param::Arg).unwrap_or_default();
+            parts.push(group_history::build_history_part(json));
+        } else if command == SystemMessage::MsgReport {
+            let json = msg.param.get(Param::Arg).unwrap_or_default();
+            parts.push(report::build_report_part(json));
+        } else if matches!(
+            command,
+            SystemMessage::CallInvite
+                | SystemMessage::CallAccepted
+                | SystemMessage::CallRejected
+                | SystemMessage::CallEnded
+        ) {
+            let payload = msg.param.get(Param::Arg).unwrap_or_default();
+            parts.push(calls::build_call_part(payload)?);
         } else if msg.viewtype == Viewtype::Webxdc {
             headers.push(create_iroh_header(context, msg.id).await?);
             if let (Some(json), _) = context
@@ -1525,44 +1676,30 @@ async fn build_body_file(
     // not transfer the original filenames eg. for images; these names
     // are normally not needed and contain timestamps, running numbers
     // etc.
-    let filename_to_send: String = match msg.viewtype {
-        Viewtype::Voice => format!(
-            "voice-messsage_{}.{}",
+    // With minimal headers, the names do not contain the time of sending either.
+    let time_suffix = match context.get_config_bool(Config::MinimalHeaders).await? {
+        true => "".to_string(),
+        false => format!(
+            "_{}",
             chrono::Utc
                 .timestamp_opt(msg.timestamp_sort, 0)
                 .single()
                 .map_or_else(
                     || "YY-mm-dd_hh:mm:ss".to_string(),
                     |ts| ts.format("%Y-%m-%d_%H-%M-%S").to_string()
-                ),
-            &suffix
+                )
         ),
-        Viewtype::Image | Viewtype::Gif => format!(
-            "image_{}.{}",
+    };
+    let filename_to_send: String = match msg.viewtype {
+        Viewtype::Voice => format!("voice-messsage{time_suffix}.{suffix}"),
+        Viewtype::Image | Viewtype::Gif => {
             if base_name.is_empty() {
-                chrono::Utc
-                    .timestamp_opt(msg.timestamp_sort, 0)
-                    .single()
-                    .map_or_else(
-                        || "YY-mm-dd_hh:mm:ss".to_string(),
-                        |ts| ts.format("%Y-%m-%d_%H-%M-%S").to_string(),
-                    )
+                format!("image{time_suffix}.{suffix}")
             } else {
-                base_name.to_string()
-            },
-            &suffix,
-        ),
-        Viewtype::Video => format!(
-            "video_{}.{}",
-            chrono::Utc
-                .timestamp_opt(msg.timestamp_sort, 0)
-                .single()
-                .map_or_else(
-                    || "YY-mm-dd_hh:mm:ss".to_string(),
-                    |ts| ts.format("%Y-%m-%d_%H-%M-%S").to_string()
-                ),
-            &suffix
-        ),
+                format!("image_{base_name}.{suffix}")
+            }
+        }
+        Viewtype::Video => format!("video{time_suffix}.{suffix}"),
         _ => msg
             .param
             .get(Param::Filename)
@@ -1854,6 +1991,70 @@ async fn test_subject_outgoing() {
         assert_eq!(first_subject_str(t).await, "Message from Alice");
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_subject_template() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(Config::Displayname, Some("Alice")).await?;
+        let chat = t.create_chat_with_contact("Dave", "dave@example.com").await;
+        chat.id
+            .set_subject_template(&t, " Support request from {name} ")
+            .await?;
+        assert_eq!(
+            chat.id.get_subject_template(&t).await?,
+            "Support request from {name}"
+        );
+
+        let sent = t.send_text(chat.id, "Hi").await;
+        assert!(sent
+            .payload()
+            .contains("\r\nSubject: Support request from Alice\r\n"));
+        let rfc724_mid = t.get_last_msg().await.rfc724_mid;
+
+        receive_imf(
+            &t,
+            format!(
+                "From: dave@example.com\n\
+                 To: alice@example.org\n\
+                 Subject: Something else\n\
+                 Message-ID: <reply@example.com>\n\
+                 In-Reply-To: <{rfc724_mid}>\n\
+                 Date: Sun, 22 Mar 2020 22:37:56 +0000\n\
+                 \n\
+                 Thanks\n"
+            )
+            .as_bytes(),
+            false,
+        )
+        .await?;
+        let sent = t.send_text(chat.id, "You are welcome").await;
+        assert!(sent
+            .payload()
+            .contains("\r\nSubject: Re: Support request from Alice\r\n"));
+
+        // Explicit subject takes precedence.
+        let mut msg = Message::new_text("Hi".to_string());
+        msg.set_subject("Invoice".to_string());
+        let sent = t.send_msg(chat.id, &mut msg).await;
+        assert!(sent.payload().contains("\r\nSubject: Invoice\r\n"));
+
+        chat.id.set_subject_template(&t, "").await?;
+        let sent = t.send_text(chat.id, "Hi").await;
+        assert!(sent
+            .payload()
+            .contains("\r\nSubject: Re: Something else\r\n"));
+
+        // Groups keep the group name as subject.
+        let group_id = create_group_chat(&t, ProtectionStatus::Unprotected, "Team").await?;
+        let dave = Contact::create(&t, "Dave", "dave@example.com").await?;
+        add_contact_to_chat(&t, group_id, dave).await?;
+        group_id
+            .set_subject_template(&t, "Support request from {name}")
+            .await?;
+        let sent = t.send_text(group_id, "Hi all").await;
+        assert!(sent.payload().contains("\r\nSubject: Team\r\n"));
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_subject_unicode() {
         // 4. Receive messages with unicode characters and make sure that we do not panic (we do not care about the result)
@@ -2543,4 +2744,103 @@ async fn test_protected_headers_directive() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_minimal_headers() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat = t.create_chat_with_contact("bob", "bob@example.org").await;
+
+        let mut msg = Message::new(Viewtype::Video);
+        msg.set_file_from_bytes(&t, "my-video.mp4", b"content", None)
+            .await?;
+        let sent = t.send_msg(chat.id, &mut msg).await;
+        assert!(sent.payload().contains("filename=\"video_"));
+
+        t.set_config_bool(Config::MinimalHeaders, true).await?;
+        let sent = t.send_text(chat.id, "hi").await;
+        let date = sent
+            .payload()
+            .lines()
+            .find_map(|line| line.strip_prefix("Date: ").map(|date| date.to_string()))
+            .unwrap();
+        assert!(date.ends_with(":00 +0000"), "{date}");
+        let mut msg = Message::new(Viewtype::Video);
+        msg.set_file_from_bytes(&t, "my-video.mp4", b"content", None)
+            .await?;
+        let sent = t.send_msg(chat.id, &mut msg).await;
+        assert!(sent.payload().contains("filename=\"video.mp4\""));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_protect_subject() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let mut msg = Message::new_text("hi".to_string());
+        msg.set_subject("Holiday plans".to_string());
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        assert!(sent.payload().contains("\r\nSubject: [...]\r\n"));
+        assert!(!sent.payload().contains("Holiday plans"));
+        assert_eq!(bob.recv_msg(&sent).await.get_subject(), "Holiday plans");
+
+        alice.set_config_bool(Config::ProtectSubject, false).await?;
+        let mut msg = Message::new_text("hi".to_string());
+        msg.set_subject("Holiday plans".to_string());
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        assert!(sent.payload().contains("\r\nSubject: Holiday plans\r\n"));
+        assert_eq!(bob.recv_msg(&sent).await.get_subject(), "Holiday plans");
+        Ok(())
+    }
+
+    /// Tests that replies from email clients not supporting protected headers,
+    /// which only see the placeholder subject, do not break the subject of the thread.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_subject_placeholder_reply() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(Config::ShowEmails, Some("2")).await?;
+        receive_imf(
+            &t,
+            b"From: Bob <bob@example.com>\n\
+              To: alice@example.org\n\
+              Subject: Holiday plans\n\
+              Message-ID: <plans1@example.com>\n\
+              Date: Sun, 22 Mar 2020 22:37:56 +0000\n\
+              \n\
+              Where do we go?\n",
+            false,
+        )
+        .await?;
+        receive_imf(
+            &t,
+            b"From: Bob <bob@example.com>\n\
+              To: alice@example.org\n\
+              Subject: [...]\n\
+              Message-ID: <plans2@example.com>\n\
+              In-Reply-To: <plans1@example.com>\n\
+              Date: Sun, 22 Mar 2020 22:38:56 +0000\n\
+              \n\
+              The mountains?\n",
+            false,
+        )
+        .await?;
+        let reply = t.get_last_msg().await;
+        assert_eq!(reply.get_text(), "The mountains?");
+        let chat_id = reply.chat_id;
+        chat_id.accept(&t).await?;
+
+        let mut new_msg = Message::new_text("Hi".to_string());
+        chat::prepare_msg(&t, chat_id, &mut new_msg).await?;
+        let mf = MimeFactory::from_msg(&t, new_msg).await?;
+        assert_eq!(mf.subject_str(&t).await?, "Re: Holiday plans");
+
+        let mut new_msg = Message::new_text("Hi".to_string());
+        new_msg.set_quote(&t, Some(&reply)).await?;
+        chat::prepare_msg(&t, chat_id, &mut new_msg).await?;
+        let mf = MimeFactory::from_msg(&t, new_msg).await?;
+        assert_eq!(mf.subject_str(&t).await?, "Re: Holiday plans");
+        Ok(())
+    }
 }
@@ -3,20 +3,24 @@
 use std::cmp::min;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::pin::Pin;
 use std::str;
 use std::str::FromStr;
+use std::task::Poll;
 
 use anyhow::{bail, Context as _, Result};
 use deltachat_contact_tools::{addr_cmp, addr_normalize, sanitize_bidi_characters};
 use deltachat_derive::{FromSql, ToSql};
 use format_flowed::unformat_flowed;
 use lettre_email::mime::Mime;
+use mailparse::body::Body;
 use mailparse::{addrparse_header, DispositionType, MailHeader, MailHeaderMap, SingleInfo};
 use rand::distributions::{Alphanumeric, DistString};
+use tokio::io::{AsyncRead, ReadBuf};
 
 use crate::aheader::{Aheader, EncryptPreference};
 use crate::authres::handle_authres;
-use crate::blob::BlobObject;
+use crate::blob::{video, waveform, BlobObject};
 use crate::chat::{add_info_msg, ChatId};
 use crate::config::Config;
 use crate::constants::{self, Chattype};
@@ -27,17 +31,23 @@
     validate_detached_signature,
 };
 use crate::dehtml::dehtml;
+use crate::email_rules::MAILBOX_GRPID;
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::key::{self, load_self_secret_keyring, DcKey, Fingerprint, SignedPublicKey};
-use crate::message::{self, get_vcard_summary, set_msg_failed, Message, MsgId, Viewtype};
+use crate::message::{
+    self, get_vcard_summary, set_msg_failed, Message, MessageState, MsgId, MsgStateChangeCause,
+    Viewtype,
+};
+use crate::net::latency;
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
+use crate::receive_imf::mailinglist_header_listid;
 use crate::simplify::{simplify, SimplifiedText};
 use crate::sync::SyncItems;
 use crate::tools::{
-    create_smeared_timestamp, get_filemeta, parse_receive_headers, smeared_time, truncate_msg_text,
-    validate_id,
+    create_smeared_timestamp, get_filemeta, get_filemeta_from_path, is_subject_placeholder,
+    parse_receive_headers, smeared_time, truncate_msg_text, validate_id,
 };
 use crate::{chatlist_events, location, stock_str, tools};
 
@@ -67,6 +77,9 @@ pub(crate) struct MimeMessage {
     /// Whether the From address was repeated in the signed part
     /// (and we know that the signer intended to send from this address)
     pub from_is_signed: bool,
+    /// Whether the Authentication-Results header of our provider says that DKIM passed,
+    /// see [`crate::authres::DkimResults::dkim_verified`].
+    pub(crate) dkim_verified: bool,
     /// Whether the message is incoming or outgoing (self-sent).
     pub incoming: bool,
     /// The List-Post address is only set for mailing lists. Users can send
@@ -95,6 +108,9 @@ pub(crate) struct MimeMessage {
     pub message_kml: Option<location::Kml>,
     pub(crate) sync_items: Option<SyncItems>,
     pub(crate) webxdc_status_update: Option<String>,
+    pub(crate) group_history: Option<String>,
+    pub(crate) msg_report: Option<String>,
+    pub(crate) call_data: Option<String>,
     pub(crate) user_avatar: Option<AvatarAction>,
     pub(crate) group_avatar: Option<AvatarAction>,
     pub(crate) mdn_reports: Vec<Report>,
@@ -204,10 +220,83 @@ pub enum SystemMessage {
 
     /// This message contains a users iroh node address.
     IrohNodeAddr = 40,
+
+    /// Hidden message sharing recent group messages with a new member.
+    GroupHistory = 50,
+
+    /// Hidden message reporting an offending message to group moderators.
+    MsgReport = 60,
+
+    /// Invite to a call, see [`crate::calls`].
+    CallInvite = 70,
+
+    /// Hidden message accepting a call.
+    CallAccepted = 71,
+
+    /// Hidden message rejecting a call.
+    CallRejected = 72,
+
+    /// Hidden message ending or cancelling a call.
+    CallEnded = 73,
 }
 
 const MIME_AC_SETUP_FILE: &str = "application/autocrypt-setup";
 
+/// Attachments which are not shown to the user, but parsed into the [`MimeMessage`].
+///
+/// Their content is needed while parsing,
+/// so they are never decoded into blobs, see [`MimeMessage::is_streamable_file_part`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParsedAttachment {
+    /// Items synchronized between own devices.
+    SyncItems,
+
+    /// Webxdc status update.
+    StatusUpdate,
+
+    /// Messages shared with a new group member, see [`crate::group_history`].
+    GroupHistory,
+
+    /// Report of an offending message, see [`crate::report`].
+    MsgReport,
+
+    /// Payload of a call control message, see [`crate::calls`].
+    Call,
+}
+
+impl ParsedAttachment {
+    const ALL: [Self; 5] = [
+        Self::SyncItems,
+        Self::StatusUpdate,
+        Self::GroupHistory,
+        Self::MsgReport,
+        Self::Call,
+    ];
+
+    /// Returns the file name the attachment is sent with.
+    pub(crate) fn filename(self) -> &'static str {
+        match self {
+            Self::SyncItems => "multi-device-sync.json",
+            Self::StatusUpdate => "status-update.json",
+            Self::GroupHistory => "group-history.json",
+            Self::MsgReport => "message-report.json",
+            Self::Call => "call.json",
+        }
+    }
+
+    /// Returns the `Content-Disposition` header value the attachment is sent with.
+    pub(crate) fn content_disposition(self) -> String {
+        format!("attachment; filename=\"{}\"", self.filename())
+    }
+
+    /// Returns the parsed attachment with the given file name, if any.
+    fn from_filename(filename: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|attachment| attachment.filename() == filename)
+    }
+}
+
 impl MimeMessage {
     /// Parse a mime message.
     ///
@@ -217,6 +306,17 @@ pub(crate) async fn from_bytes(
         context: &Context,
         body: &[u8],
         partial: Option<u32>,
+    ) -> Result<Self> {
+        Self::from_bytes_predecrypted(context, body, partial, None).await
+    }
+
+    /// Same as [`MimeMessage::from_bytes`], but takes the result of decrypting the message
+    /// if it was already decrypted with [`crate::decrypt::spawn_decryption`].
+    pub(crate) async fn from_bytes_predecrypted(
+        context: &Context,
+        body: &[u8],
+        partial: Option<u32>,
+        predecrypted: Option<Result<::pgp::composed::Message>>,
     ) -> Result<Self> {
         let mail = mailparse::parse_mail(body)?;
 
@@ -320,42 +420,45 @@ pub(crate) async fn from_bytes(
         let mail_raw; // Memory location for a possible decrypted message.
         let decrypted_msg; // Decrypted signed OpenPGP message.
 
-        let (mail, encrypted) =
-            match tokio::task::block_in_place(|| try_decrypt(&mail, &private_keyring)) {
-                Ok(Some(msg)) => {
-                    mail_raw = msg.get_content()?.unwrap_or_default();
-
-                    let decrypted_mail = mailparse::parse_mail(&mail_raw)?;
-                    if std::env::var(crate::DCC_MIME_DEBUG).is_ok() {
-                        info!(
-                            context,
-                            "decrypted message mime-body:\n{}",
-                            String::from_utf8_lossy(&mail_raw),
-                        );
-                    }
-
-                    decrypted_msg = Some(msg);
-                    if let Some(protected_aheader_value) = decrypted_mail
-                        .headers
-                        .get_header_value(HeaderDef::Autocrypt)
-                    {
-                        aheader_value = Some(protected_aheader_value);
-                    }
+        let decryption_result = match predecrypted {
+            Some(result) => result.map(Some),
+            None => tokio::task::block_in_place(|| try_decrypt(&mail, &private_keyring)),
+        };
+        let (mail, encrypted) = match decryption_result {
+            Ok(Some(msg)) => {
+                mail_raw = msg.get_content()?.unwrap_or_default();
 
-                    (Ok(decrypted_mail), true)
-                }
-                Ok(None) => {
-                    mail_raw = Vec::new();
-                    decrypted_msg = None;
-                    (Ok(mail), false)
+                let decrypted_mail = mailparse::parse_mail(&mail_raw)?;
+                if std::env::var(crate::DCC_MIME_DEBUG).is_ok() {
+                    info!(
+                        context,
+                        "decrypted message mime-body:\n{}",
+                        String::from_utf8_lossy(&mail_raw),
+                    );
                 }
-                Err(err) => {
-                    mail_raw = Vec::new();
-                    decrypted_msg = None;
-                    warn!(context, "decryption failed: {:#}", err);
-                    (Err(err), false)
+
+                decrypted_msg = Some(msg);
+                if let Some(protected_aheader_value) = decrypted_mail
+                    .headers
+                    .get_header_value(HeaderDef::Autocrypt)
+                {
+                    aheader_value = Some(protected_aheader_value);
                 }
-            };
+
+                (Ok(decrypted_mail), true)
+            }
+            Ok(None) => {
+                mail_raw = Vec::new();
+                decrypted_msg = None;
+                (Ok(mail), false)
+            }
+            Err(err) => {
+                mail_raw = Vec::new();
+                decrypted_msg = None;
+                warn!(context, "decryption failed: {:#}", err);
+                (Err(err), false)
+            }
+        };
 
         let autocrypt_header = if !incoming {
             None
@@ -394,7 +497,9 @@ pub(crate) async fn from_bytes(
         };
 
         let mut signatures = if let Some(ref decrypted_msg) = decrypted_msg {
-            crate::pgp::valid_signature_fingerprints(decrypted_msg, &public_keyring)?
+            tokio::task::block_in_place(|| {
+                crate::pgp::valid_signature_fingerprints(decrypted_msg, &public_keyring)
+            })?
         } else {
             HashSet::new()
         };
@@ -498,7 +603,7 @@ pub(crate) async fn from_bytes(
         if let Some(peerstate) = &mut peerstate {
             if peerstate.prefer_encrypt != EncryptPreference::Mutual && !signatures.is_empty() {
                 peerstate.prefer_encrypt = EncryptPreference::Mutual;
-                peerstate.save_to_db(&context.sql).await?;
+                peerstate.save_to_db_ex(context, None).await?;
             }
         }
 
@@ -509,6 +614,7 @@ pub(crate) async fn from_bytes(
             list_post,
             from,
             from_is_signed,
+            dkim_verified: dkim_results.dkim_verified,
             incoming,
             chat_disposition_notification_to,
             autocrypt_header,
@@ -525,6 +631,9 @@ pub(crate) async fn from_bytes(
             message_kml: None,
             sync_items: None,
             webxdc_status_update: None,
+            group_history: None,
+            msg_report: None,
+            call_data: None,
             user_avatar: None,
             group_avatar: None,
             delivery_report: None,
@@ -565,7 +674,12 @@ pub(crate) async fn from_bytes(
             },
         };
 
-        if parser.mdn_reports.is_empty() && parser.webxdc_status_update.is_none() {
+        if parser.mdn_reports.is_empty()
+            && parser.webxdc_status_update.is_none()
+            && parser.group_history.is_none()
+            && parser.msg_report.is_none()
+            && parser.call_data.is_none()
+        {
             // "Auto-Submitted" is also set by holiday-notices so we also check "chat-version".
             let is_bot = parser.headers.get("auto-submitted")
                 == Some(&"auto-generated".to_string())
@@ -620,6 +734,18 @@ fn parse_system_message_headers(&mut self, context: &Context) {
                 self.is_system_message = SystemMessage::ChatProtectionDisabled;
             } else if value == "group-avatar-changed" {
                 self.is_system_message = SystemMessage::GroupImageChanged;
+            } else if value == "group-history" {
+                self.is_system_message = SystemMessage::GroupHistory;
+            } else if value == "message-report" {
+                self.is_system_message = SystemMessage::MsgReport;
+            } else if value == "call-invite" {
+                self.is_system_message = SystemMessage::CallInvite;
+            } else if value == "call-accepted" {
+                self.is_system_message = SystemMessage::CallAccepted;
+            } else if value == "call-rejected" {
+                self.is_system_message = SystemMessage::CallRejected;
+            } else if value == "call-ended" {
+                self.is_system_message = SystemMessage::CallEnded;
             }
         } else if self.get_header(HeaderDef::ChatGroupMemberRemoved).is_some() {
             self.is_system_message = SystemMessage::MemberRemovedFromGroup;
@@ -739,6 +865,54 @@ fn parse_attachments(&mut self) {
         }
     }
 
+    /// Stores the thumbnail of a video attachment sent in the `Chat-Video-Thumbnail` header
+    /// and returns the name of its blob.
+    pub(crate) async fn store_video_thumbnail(&self, context: &Context) -> Option<String> {
+        let thumbnail = self
+            .get_header(HeaderDef::ChatVideoThumbnail)?
+            .split_ascii_whitespace()
+            .collect::<String>();
+        let base64 = thumbnail.strip_prefix("base64:")?;
+        BlobObject::store_from_base64(context, base64, "thumbnail")
+            .await
+            .map_err(|err| warn!(context, "Could not store video thumbnail: {err:#}."))
+            .ok()
+    }
+
+    /// Stores the thumbnail of a video attachment sent in the `Chat-Video-Thumbnail` header
+    /// and extracts the metadata that was not sent from the video itself.
+    async fn parse_video_thumbnail(&mut self, context: &Context) {
+        if self.parts.len() != 1 || self.parts.first().map(|part| part.typ) != Some(Viewtype::Video)
+        {
+            return;
+        }
+        let thumbnail = self.store_video_thumbnail(context).await;
+        let Some(part) = self.parts.first_mut() else {
+            return;
+        };
+        if let Some(thumbnail) = thumbnail {
+            part.param.set(Param::Thumbnail, thumbnail);
+        }
+        if let Err(err) = video::set_video_params(context, &mut part.param).await {
+            warn!(context, "Could not read video metadata: {err:#}.");
+        }
+    }
+
+    /// Computes the waveforms of voice messages.
+    async fn parse_voice_waveforms(&mut self, context: &Context) {
+        for part in &mut self.parts {
+            if part.typ != Viewtype::Voice {
+                continue;
+            }
+            if let Err(err) = waveform::set_waveform_param(context, &mut part.param).await {
+                warn!(
+                    context,
+                    "Could not compute voice message waveform: {err:#}."
+                );
+            }
+        }
+    }
+
     async fn parse_headers(&mut self, context: &Context) -> Result<()> {
         self.parse_system_message_headers(context);
         self.parse_avatar_headers(context).await;
@@ -767,7 +941,7 @@ async fn parse_headers(&mut self, context: &Context) -> Result<()> {
                     prepend_subject = true;
                 }
 
-                if prepend_subject && !subject.is_empty() {
+                if prepend_subject && !subject.is_empty() && !is_subject_placeholder(subject) {
                     let part_with_text = self
                         .parts
                         .iter_mut()
@@ -786,6 +960,8 @@ async fn parse_headers(&mut self, context: &Context) -> Result<()> {
         }
 
         self.parse_attachments();
+        self.parse_video_thumbnail(context).await;
+        self.parse_voice_waveforms(context).await;
 
         // See if an MDN is requested from the other side
         if !self.decrypting_failed && !self.parts.is_empty() {
@@ -1137,16 +1313,31 @@ async fn add_single_part_if_known(
 
         match filename {
             Some(filename) => {
-                self.do_add_single_file_part(
-                    context,
-                    msg_type,
-                    mime_type,
-                    &raw_mime,
-                    &mail.get_body_raw()?,
-                    &filename,
-                    is_related,
-                )
-                .await?;
+                let blob = if Self::is_streamable_file_part(&mime_type, msg_type, &filename) {
+                    Self::create_blob_from_part(context, mail, &filename).await
+                } else {
+                    None
+                };
+                match blob {
+                    Some(blob) => {
+                        self.do_add_blob_file_part(
+                            context, blob, msg_type, mime_type, &raw_mime, &filename, is_related,
+                        )
+                        .await?;
+                    }
+                    None => {
+                        self.do_add_single_file_part(
+                            context,
+                            msg_type,
+                            mime_type,
+                            &raw_mime,
+                            &mail.get_body_raw()?,
+                            &filename,
+                            is_related,
+                        )
+                        .await?;
+                    }
+                }
             }
             None => {
                 match mime_type.type_() {
@@ -1329,25 +1520,9 @@ async fn do_add_single_file_part(
                 return Ok(());
             }
             msg_type
-        } else if filename == "multi-device-sync.json" {
-            if !context.get_config_bool(Config::SyncMsgs).await? {
-                return Ok(());
-            }
-            let serialized = String::from_utf8_lossy(decoded_data)
-                .parse()
-                .unwrap_or_default();
-            self.sync_items = context
-                .parse_sync_items(serialized)
-                .map_err(|err| {
-                    warn!(context, "failed to parse sync data: {:#}", err);
-                })
-                .ok();
-            return Ok(());
-        } else if filename == "status-update.json" {
-            let serialized = String::from_utf8_lossy(decoded_data)
-                .parse()
-                .unwrap_or_default();
-            self.webxdc_status_update = Some(serialized);
+        } else if let Some(attachment) = ParsedAttachment::from_filename(filename) {
+            self.add_parsed_attachment(context, attachment, decoded_data)
+                .await?;
             return Ok(());
         } else if msg_type == Viewtype::Vcard {
             if let Some(summary) = get_vcard_summary(decoded_data) {
@@ -1381,18 +1556,218 @@ async fn do_add_single_file_part(
                 part.param.set_int(Param::Height, height as i32);
             }
         }
+        part.bytes = decoded_data.len();
+
+        self.add_file_part(
+            context, part, blob, msg_type, mime_type, raw_mime, filename, is_related,
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Parses an attachment which is not shown to the user into the message.
+    async fn add_parsed_attachment(
+        &mut self,
+        context: &Context,
+        attachment: ParsedAttachment,
+        decoded_data: &[u8],
+    ) -> Result<()> {
+        match attachment {
+            ParsedAttachment::SyncItems => {
+                if !context.get_config_bool(Config::SyncMsgs).await? {
+                    return Ok(());
+                }
+                let serialized = String::from_utf8_lossy(decoded_data)
+                    .parse()
+                    .unwrap_or_default();
+                self.sync_items = context
+                    .parse_sync_items(serialized)
+                    .map_err(|err| {
+                        warn!(context, "failed to parse sync data: {:#}", err);
+                    })
+                    .ok();
+            }
+            ParsedAttachment::StatusUpdate => {
+                let serialized = String::from_utf8_lossy(decoded_data)
+                    .parse()
+                    .unwrap_or_default();
+                self.webxdc_status_update = Some(serialized);
+            }
+            ParsedAttachment::GroupHistory => {
+                self.group_history = Some(String::from_utf8_lossy(decoded_data).to_string());
+            }
+            ParsedAttachment::MsgReport => {
+                self.msg_report = Some(String::from_utf8_lossy(decoded_data).to_string());
+            }
+            ParsedAttachment::Call => {
+                self.call_data = Some(String::from_utf8_lossy(decoded_data).to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a file part for an attachment that was already decoded into a blob
+    /// by [`MimeMessage::create_blob_from_part`].
+    #[allow(clippy::too_many_arguments)]
+    async fn do_add_blob_file_part(
+        &mut self,
+        context: &Context,
+        blob: BlobObject<'_>,
+        msg_type: Viewtype,
+        mime_type: Mime,
+        raw_mime: &str,
+        filename: &str,
+        is_related: bool,
+    ) -> Result<()> {
+        let path = blob.to_abs_path();
+        let bytes = tokio::fs::metadata(&path).await?.len();
+        if bytes == 0 {
+            tokio::fs::remove_file(&path).await.ok();
+            return Ok(());
+        }
+        info!(context, "added blobfile: {:?}", blob.as_name());
+
+        let mut part = Part::default();
+        if mime_type.type_() == mime::IMAGE {
+            if let Ok((width, height)) = get_filemeta_from_path(&path) {
+                part.param.set_int(Param::Width, width as i32);
+                part.param.set_int(Param::Height, height as i32);
+            }
+        }
+        part.bytes = usize::try_from(bytes).unwrap_or(usize::MAX);
+
+        self.add_file_part(
+            context, part, blob, msg_type, mime_type, raw_mime, filename, is_related,
+        )
+        .await;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn add_file_part(
+        &mut self,
+        context: &Context,
+        mut part: Part,
+        mut blob: BlobObject<'_>,
+        mut msg_type: Viewtype,
+        mut mime_type: Mime,
+        mut raw_mime: &str,
+        filename: &str,
+        is_related: bool,
+    ) {
+        if !is_related {
+            if let Some(expected) = self
+                .get_header(HeaderDef::ChatAttachmentHash)
+                .and_then(|value| value.strip_prefix("sha256="))
+                .map(|hash| hash.trim().to_ascii_lowercase())
+            {
+                // Verify the attachment before it is converted below.
+                match blob.sha256().await {
+                    Ok(hash) if hash == expected => {}
+                    Ok(hash) => {
+                        warn!(
+                            context,
+                            "Attachment {} has SHA-256 {hash}, but {expected} was sent.",
+                            blob.as_name()
+                        );
+                        part.error = Some(
+                            "The attachment does not match the sent file, it may be corrupted."
+                                .to_string(),
+                        );
+                    }
+                    Err(err) => warn!(context, "Cannot verify attachment hash: {err:#}."),
+                }
+                part.param.set(Param::FileHash, expected);
+            }
+        }
+
+        // HEIF images from iPhones can't be displayed by many clients, convert them to JPEG.
+        let converted_filename;
+        let mut filename = filename;
+        if mime_type.type_() == mime::IMAGE || msg_type == Viewtype::Image {
+            match blob.convert_heif_to_jpeg(context).await {
+                Ok(true) => {
+                    let path = blob.to_abs_path();
+                    if let Ok((width, height)) = get_filemeta_from_path(&path) {
+                        part.param.set_int(Param::Width, width as i32);
+                        part.param.set_int(Param::Height, height as i32);
+                    }
+                    if let Ok(metadata) = tokio::fs::metadata(&path).await {
+                        part.bytes = usize::try_from(metadata.len()).unwrap_or(usize::MAX);
+                    }
+                    msg_type = Viewtype::Image;
+                    mime_type = mime::IMAGE_JPEG;
+                    raw_mime = "image/jpeg";
+                    let stem = Path::new(filename)
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .unwrap_or("image");
+                    converted_filename = format!("{stem}.jpg");
+                    filename = &converted_filename;
+                }
+                Ok(false) => {}
+                Err(err) => warn!(context, "Cannot convert HEIF image: {err:#}."),
+            }
+        }
 
         part.typ = msg_type;
         part.org_filename = Some(filename.to_string());
         part.mimetype = Some(mime_type);
-        part.bytes = decoded_data.len();
         part.param.set(Param::File, blob.as_name());
         part.param.set(Param::Filename, filename);
         part.param.set(Param::MimeType, raw_mime);
         part.is_related = is_related;
 
         self.do_add_single_part(part);
-        Ok(())
+    }
+
+    /// Returns whether the attachment can be decoded directly into a blob,
+    /// i.e. its content does not need to be inspected while parsing the message.
+    fn is_streamable_file_part(mime_type: &Mime, msg_type: Viewtype, filename: &str) -> bool {
+        !(mime_type.type_() == mime::APPLICATION && mime_type.subtype().as_str() == "pgp-keys")
+            && msg_type != Viewtype::Vcard
+            && !filename.ends_with(".xdc")
+            && !filename.ends_with(".kml")
+            && ParsedAttachment::from_filename(filename).is_none()
+    }
+
+    /// Decodes the body of an attachment directly into a new blob
+    /// without making a decoded copy of the attachment in memory.
+    ///
+    /// Note that this does not bound the memory used for parsing:
+    /// the raw message, including the encoded attachment, is still held in memory,
+    /// only the additional decoded copy is avoided.
+    ///
+    /// Returns `None` if the transfer encoding is not supported for this
+    /// or the body cannot be decoded this way,
+    /// the caller should fall back to decoding the body in memory then.
+    async fn create_blob_from_part<'a>(
+        context: &'a Context,
+        mail: &mailparse::ParsedMail<'_>,
+        filename: &str,
+    ) -> Option<BlobObject<'a>> {
+        let res = match mail.get_body_encoded() {
+            Body::Base64(body) => {
+                let decoder =
+                    base64::read::DecoderReader::new(SkipWhitespace(body.get_raw()), &BASE64_MIME);
+                BlobObject::create_from_reader(context, filename, &mut InMemoryReader(decoder))
+                    .await
+            }
+            Body::SevenBit(body) | Body::EightBit(body) => {
+                BlobObject::create_from_reader(context, filename, &mut body.get_raw()).await
+            }
+            Body::Binary(body) => {
+                BlobObject::create_from_reader(context, filename, &mut body.get_raw()).await
+            }
+            Body::QuotedPrintable(_) => return None,
+        };
+        res.map_err(|err| {
+            warn!(
+                context,
+                "Could not decode mime part {filename} into blob, decoding in memory: {err:#}."
+            );
+        })
+        .ok()
     }
 
     /// Returns whether a key from the attachment was set as peer's pubkey.
@@ -1450,7 +1825,7 @@ async fn try_set_peer_key_from_file_part(
             "using attached PGP key for peer '{}' with prefer-encrypt=mutual", peerstate.addr,
         );
         peerstate.prefer_encrypt = EncryptPreference::Mutual;
-        peerstate.save_to_db(&context.sql).await?;
+        peerstate.save_to_db_ex(context, None).await?;
         Ok(true)
     }
 
@@ -1462,23 +1837,29 @@ fn do_add_single_part(&mut self, mut part: Part) {
     }
 
     pub(crate) fn get_mailinglist_header(&self) -> Option<&str> {
-        if let Some(list_id) = self.get_header(HeaderDef::ListId) {
+        let header = if let Some(list_id) = self.get_header(HeaderDef::ListId) {
             // The message belongs to a mailing list and has a `ListId:`-header
             // that should be used to get a unique id.
-            return Some(list_id);
-        } else if let Some(sender) = self.get_header(HeaderDef::Sender) {
+            list_id
+        } else {
             // the `Sender:`-header alone is no indicator for mailing list
             // as also used for bot-impersonation via `set_override_sender_name()`
-            if let Some(precedence) = self.get_header(HeaderDef::Precedence) {
-                if precedence == "list" || precedence == "bulk" {
-                    // The message belongs to a mailing list, but there is no `ListId:`-header;
-                    // `Sender:`-header is be used to get a unique id.
-                    // This method is used by implementations as Majordomo.
-                    return Some(sender);
-                }
+            let sender = self.get_header(HeaderDef::Sender)?;
+            let precedence = self.get_header(HeaderDef::Precedence)?;
+            if precedence != "list" && precedence != "bulk" {
+                return None;
             }
+            // The message belongs to a mailing list, but there is no `ListId:`-header;
+            // `Sender:`-header is be used to get a unique id.
+            // This method is used by implementations as Majordomo.
+            sender
+        };
+        // The mailbox chat only collects emails routed there by the own email rules,
+        // emails claiming to belong to it are forged.
+        if mailinglist_header_listid(header).is_ok_and(|listid| listid == MAILBOX_GRPID) {
+            return None;
         }
-        None
+        Some(header)
     }
 
     pub(crate) fn is_mailinglist_message(&self) -> bool {
@@ -1879,11 +2260,11 @@ async fn update_gossip_peerstates(
         let peerstate;
         if let Some(mut p) = Peerstate::from_addr(context, &header.addr).await? {
             p.apply_gossip(&header, message_time);
-            p.save_to_db(&context.sql).await?;
+            p.save_to_db_ex(context, None).await?;
             peerstate = p;
         } else {
             let p = Peerstate::from_gossip(&header, message_time);
-            p.save_to_db(&context.sql).await?;
+            p.save_to_db_ex(context, None).await?;
             peerstate = p;
         };
         peerstate
@@ -2005,6 +2386,55 @@ pub struct Part {
     pub(crate) is_reaction: bool,
 }
 
+/// Base64 engine accepting the variations found in the wild,
+/// same as the in-memory decoding of `mailparse`.
+const BASE64_MIME: base64::engine::GeneralPurpose = base64::engine::GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    base64::engine::GeneralPurposeConfig::new()
+        .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+/// Reader over a byte slice skipping ASCII whitespace,
+/// used to feed line-wrapped base64 into a decoder.
+struct SkipWhitespace<'a>(&'a [u8]);
+
+impl std::io::Read for SkipWhitespace<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut written = 0;
+        let mut slots = buf.iter_mut();
+        while let Some((&byte, rest)) = self.0.split_first() {
+            if !byte.is_ascii_whitespace() {
+                let Some(slot) = slots.next() else {
+                    break;
+                };
+                *slot = byte;
+                written += 1;
+            }
+            self.0 = rest;
+        }
+        Ok(written)
+    }
+}
+
+/// Adapter to pass a synchronous reader over in-memory data
+/// where an [`AsyncRead`] is expected.
+///
+/// Reads never block as the data is already in memory.
+struct InMemoryReader<R>(R);
+
+impl<R: std::io::Read + Unpin> AsyncRead for InMemoryReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let n = self.get_mut().0.read(buf.initialize_unfilled())?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
 /// Returns the mimetype and viewtype for a parsed mail.
 ///
 /// This only looks at the metadata, not at the content;
@@ -2207,13 +2637,14 @@ async fn handle_mdn(
         return Ok(());
     }
 
-    let Some((msg_id, chat_id, has_mdns, is_dup)) = context
+    let Some((msg_id, chat_id, state, has_mdns, is_dup)) = context
         .sql
         .query_row_optional(
             concat!(
                 "SELECT",
                 "    m.id AS msg_id,",
                 "    c.id AS chat_id,",
+                "    m.state AS state,",
                 "    mdns.contact_id AS mdn_contact",
                 " FROM msgs m ",
                 " LEFT JOIN chats c ON m.chat_id=c.id",
@@ -2226,10 +2657,12 @@ async fn handle_mdn(
             |row| {
                 let msg_id: MsgId = row.get("msg_id")?;
                 let chat_id: ChatId = row.get("chat_id")?;
+                let state: MessageState = row.get("state")?;
                 let mdn_contact: Option<ContactId> = row.get("mdn_contact")?;
                 Ok((
                     msg_id,
                     chat_id,
+                    state,
                     mdn_contact.is_some(),
                     mdn_contact == Some(from_id),
                 ))
@@ -2255,7 +2688,21 @@ async fn handle_mdn(
         )
         .await?;
     if !has_mdns {
+        if let Err(err) = latency::record_mdn_received(context, msg_id).await {
+            warn!(
+                context,
+                "Failed to record MDN latency of {msg_id}: {err:#}."
+            );
+        }
         context.emit_event(EventType::MsgRead { chat_id, msg_id });
+        message::emit_msg_state_changed(
+            context,
+            chat_id,
+            msg_id,
+            state,
+            MessageState::OutMdnRcvd,
+            MsgStateChangeCause::MdnReceived,
+        );
         // note(treefit): only matters if it is the last message in chat (but probably too expensive to check, debounce also solves it)
         chatlist_events::emit_chatlist_item_changed(context, chat_id);
     }
@@ -3112,6 +3559,52 @@ async fn test_parse_inline_attachment() {
         assert_eq!(message.parts[0].msg, "Mail with inline attachment – Hello!");
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stream_attachment_into_blob() -> Result<()> {
+        use base64::Engine as _;
+
+        let t = TestContext::new_alice().await;
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
+        let wrapped = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|line| String::from_utf8_lossy(line).into_owned())
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        for (encoding, body, expected) in [
+            ("base64", wrapped.as_str(), data.as_slice()),
+            ("7bit", "hello world", b"hello world".as_slice()),
+            // Quoted-printable is still decoded in memory.
+            (
+                "quoted-printable",
+                "hello=3Dworld",
+                b"hello=world".as_slice(),
+            ),
+        ] {
+            let raw = format!(
+                "From: sender@example.com\r\n\
+                 To: receiver@example.com\r\n\
+                 Subject: Attachment\r\n\
+                 Content-Type: application/octet-stream; name=\"data.bin\"\r\n\
+                 Content-Disposition: attachment; filename=\"data.bin\"\r\n\
+                 Content-Transfer-Encoding: {encoding}\r\n\
+                 \r\n\
+                 {body}"
+            );
+            let message = MimeMessage::from_bytes(&t, raw.as_bytes(), None).await?;
+            assert_eq!(message.parts.len(), 1);
+            assert_eq!(message.parts[0].bytes, expected.len());
+            let blob = message.parts[0]
+                .param
+                .get_blob(Param::File, &t, false)
+                .await?
+                .unwrap();
+            assert_eq!(tokio::fs::read(blob.to_abs_path()).await?, expected);
+        }
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_hide_html_without_content() {
         let t = TestContext::new_alice().await;
@@ -4188,4 +4681,52 @@ async fn test_protect_autocrypt() -> Result<()> {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_attachment_hash() -> Result<()> {
+        use sha2::{Digest, Sha256};
+
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let bob2 = &tcm.bob().await;
+
+        let chat = alice.create_chat(bob).await;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "hello.txt", b"Hello world!", None)
+            .await?;
+        let sent = alice.send_msg(chat.id, &mut msg).await;
+        let hash = hex::encode(Sha256::digest(b"Hello world!"));
+        let msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        assert_eq!(msg.param.get(Param::FileHash), Some(hash.as_str()));
+        assert!(sent
+            .payload()
+            .contains(&format!("Chat-Attachment-Hash: sha256={hash}")));
+
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.param.get(Param::FileHash), Some(hash.as_str()));
+        assert!(msg.error().is_none());
+
+        // Forwarded attachments are hashed again.
+        let bob_chat = bob.create_chat(alice).await;
+        chat::forward_msgs(bob, &[msg.id], bob_chat.id).await?;
+        let forwarded = bob.pop_sent_msg().await;
+        assert!(forwarded
+            .payload()
+            .contains(&format!("Chat-Attachment-Hash: sha256={hash}")));
+        let msg = Message::load_from_db(bob, forwarded.sender_msg_id).await?;
+        assert_eq!(msg.param.get(Param::FileHash), Some(hash.as_str()));
+
+        // The attachment does not match the hash anymore.
+        let wrong_hash = "0".repeat(64);
+        let corrupted = sent.payload().replace(&hash, &wrong_hash);
+        let msg = receive_imf(bob2, corrupted.as_bytes(), false)
+            .await?
+            .unwrap();
+        let msg = Message::load_from_db(bob2, *msg.msg_ids.last().unwrap()).await?;
+        assert_eq!(msg.get_viewtype(), Viewtype::File);
+        assert_eq!(msg.param.get(Param::FileHash), Some(wrong_hash.as_str()));
+        assert!(msg.error().is_some());
+        Ok(())
+    }
 }
@@ -16,20 +16,57 @@
 use crate::tools::time;
 
 pub(crate) mod dns;
+#[cfg(any(test, feature = "net-fault-injection"))]
+pub mod fault;
 pub(crate) mod http;
+pub(crate) mod latency;
 pub(crate) mod proxy;
 pub(crate) mod session;
+pub(crate) mod stats;
 pub(crate) mod tls;
 
 use dns::lookup_host_with_cache;
 pub use http::{read_url, read_url_blob, Response as HttpResponse};
+pub use latency::{DeliveryLatencyStats, LatencySummary};
+pub use stats::NetworkStats;
 use tls::wrap_tls;
 
+/// Class of the network the device is connected to,
+/// see [`Context::on_network_changed`].
+#[derive(Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum NetworkKind {
+    /// The network class is not known.
+    #[default]
+    Unknown = 0,
+
+    /// There is no network.
+    None = 1,
+
+    /// Wi-Fi or other unmetered network.
+    Wifi = 2,
+
+    /// Cellular network.
+    Cellular = 3,
+
+    /// Virtual private network.
+    Vpn = 4,
+}
+
 /// Connection, write and read timeout.
 ///
 /// This constant should be more than the largest expected RTT.
 pub(crate) const TIMEOUT: Duration = Duration::from_secs(60);
 
+/// TCP stream underlying all connections.
+#[cfg(not(feature = "net-fault-injection"))]
+pub(crate) type RawTcpStream = TcpStream;
+
+/// TCP stream underlying all connections,
+/// wrapped to simulate network faults.
+#[cfg(feature = "net-fault-injection")]
+pub(crate) type RawTcpStream = fault::FaultyStream<TcpStream>;
+
 /// TTL for caches in seconds.
 pub(crate) const CACHE_TTL: u64 = 30 * 24 * 60 * 60;
 
@@ -105,7 +142,10 @@ pub(crate) async fn load_connection_timestamp(
 /// to the network, which is important to reduce the latency of interactive protocols such as IMAP.
 pub(crate) async fn connect_tcp_inner(
     addr: SocketAddr,
-) -> Result<Pin<Box<TimeoutStream<TcpStream>>>> {
+) -> Result<Pin<Box<TimeoutStream<RawTcpStream>>>> {
+    #[cfg(feature = "net-fault-injection")]
+    let faults = fault::on_connect().await?;
+
     let tcp_stream = timeout(TIMEOUT, TcpStream::connect(addr))
         .await
         .context("connection timeout")?
@@ -114,6 +154,9 @@ pub(crate) async fn connect_tcp_inner(
     // Disable Nagle's algorithm.
     tcp_stream.set_nodelay(true)?;
 
+    #[cfg(feature = "net-fault-injection")]
+    let tcp_stream = fault::FaultyStream::new(tcp_stream, faults);
+
     let mut timeout_stream = TimeoutStream::new(tcp_stream);
     timeout_stream.set_write_timeout(Some(TIMEOUT));
     timeout_stream.set_read_timeout(Some(TIMEOUT));
@@ -235,7 +278,7 @@ pub(crate) async fn connect_tcp(
     host: &str,
     port: u16,
     load_cache: bool,
-) -> Result<Pin<Box<TimeoutStream<TcpStream>>>> {
+) -> Result<Pin<Box<TimeoutStream<RawTcpStream>>>> {
     let connection_futures = lookup_host_with_cache(context, host, port, "", load_cache)
         .await?
         .into_iter()
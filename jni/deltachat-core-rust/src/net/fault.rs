@@ -0,0 +1,282 @@
+//! # Fault injection for network connections.
+//!
+//! Simulates bad network conditions such as high latency,
+//! dropped connection attempts, connections reset in the middle of a transfer
+//! and limited bandwidth.
+//! This allows to exercise reconnection logic, IDLE renewal and upload resumption
+//! without a real flaky network.
+//!
+//! Faults are only applied to new TCP connections
+//! if the crate is compiled with the `net-fault-injection` feature.
+//! They are process-wide because connections are established
+//! independently of the context.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context as TaskContext, Poll};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{sleep, Sleep};
+
+/// Simulated network conditions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetFaults {
+    /// Delay added to each connection attempt and to each read.
+    pub latency: Duration,
+
+    /// Number of upcoming connection attempts that should fail.
+    ///
+    /// Decremented by each connection attempt.
+    pub drop_connections: u32,
+
+    /// Number of bytes after which a connection is reset.
+    ///
+    /// Bytes read and written are counted together.
+    pub reset_after_bytes: Option<u64>,
+
+    /// Maximum throughput of a connection in bytes per second.
+    pub throttle_bytes_per_sec: Option<u64>,
+}
+
+static NET_FAULTS: Lazy<RwLock<NetFaults>> = Lazy::new(Default::default);
+
+/// Sets simulated network conditions for new connections.
+///
+/// Pass `NetFaults::default()` to disable fault injection.
+pub fn set_net_faults(faults: NetFaults) {
+    *NET_FAULTS.write() = faults;
+}
+
+/// Returns currently simulated network conditions.
+pub fn get_net_faults() -> NetFaults {
+    NET_FAULTS.read().clone()
+}
+
+/// Applies connection faults before establishing a new connection.
+///
+/// Returns the faults to apply to the established connection.
+pub(crate) async fn on_connect() -> Result<NetFaults> {
+    let faults = {
+        let mut current = NET_FAULTS.write();
+        let faults = current.clone();
+        current.drop_connections = current.drop_connections.saturating_sub(1);
+        faults
+    };
+    if !faults.latency.is_zero() {
+        sleep(faults.latency).await;
+    }
+    if faults.drop_connections > 0 {
+        bail!("Simulated connection drop");
+    }
+    Ok(faults)
+}
+
+/// Stream wrapper injecting latency, resets and throttling.
+#[derive(Debug)]
+pub(crate) struct FaultyStream<S> {
+    inner: S,
+    faults: NetFaults,
+
+    /// Number of bytes read and written so far.
+    transferred: u64,
+
+    /// Whether the latency of the next read is already accounted for.
+    read_delayed: bool,
+
+    /// Pending delay before the next read or write.
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> FaultyStream<S> {
+    pub(crate) fn new(inner: S, faults: NetFaults) -> Self {
+        Self {
+            inner,
+            faults,
+            transferred: 0,
+            read_delayed: false,
+            delay: None,
+        }
+    }
+
+    fn poll_delay(&mut self, cx: &mut TaskContext<'_>) -> Poll<()> {
+        if let Some(delay) = &mut self.delay {
+            ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+        Poll::Ready(())
+    }
+
+    fn check_reset(&self) -> io::Result<()> {
+        match self.faults.reset_after_bytes {
+            Some(limit) if self.transferred >= limit => Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "Simulated connection reset",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Limits the number of bytes to transfer
+    /// so the connection is reset exactly after `reset_after_bytes`.
+    fn allowed_len(&self, len: usize) -> usize {
+        match self.faults.reset_after_bytes {
+            Some(limit) => {
+                let remaining = limit.saturating_sub(self.transferred);
+                usize::try_from(remaining).map_or(len, |remaining| len.min(remaining))
+            }
+            None => len,
+        }
+    }
+
+    /// Accounts for transferred bytes and schedules the throttling delay.
+    fn on_transfer(&mut self, len: usize) {
+        self.transferred = self.transferred.saturating_add(len as u64);
+        if let Some(rate) = self.faults.throttle_bytes_per_sec.filter(|rate| *rate > 0) {
+            let delay = Duration::from_secs_f64(len as f64 / rate as f64);
+            if !delay.is_zero() {
+                self.delay = Some(Box::pin(sleep(delay)));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for FaultyStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.check_reset()?;
+        if !this.read_delayed {
+            this.read_delayed = true;
+            if !this.faults.latency.is_zero() && this.delay.is_none() {
+                this.delay = Some(Box::pin(sleep(this.faults.latency)));
+            }
+        }
+        ready!(this.poll_delay(cx));
+
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        let len = this.allowed_len(buf.filled().len() - filled);
+        buf.set_filled(filled + len);
+        this.read_delayed = false;
+        this.on_transfer(len);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FaultyStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.check_reset()?;
+        ready!(this.poll_delay(cx));
+
+        let len = this.allowed_len(buf.len());
+        let len = ready!(Pin::new(&mut this.inner).poll_write(cx, buf.get(..len).unwrap_or(buf)))?;
+        this.on_transfer(len);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_reset_after_bytes() -> Result<()> {
+        let (client, mut server) = duplex(1024);
+        let faults = NetFaults {
+            reset_after_bytes: Some(10),
+            ..Default::default()
+        };
+        let mut stream = FaultyStream::new(client, faults);
+
+        server.write_all(b"0123456789abcdef").await?;
+        let mut buf = [0u8; 16];
+        let len = stream.read(&mut buf).await?;
+        assert_eq!(&buf[..len], b"0123456789");
+
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        let err = stream.write_all(b"hello").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_partial_write_before_reset() -> Result<()> {
+        let (client, mut server) = duplex(1024);
+        let faults = NetFaults {
+            reset_after_bytes: Some(3),
+            ..Default::default()
+        };
+        let mut stream = FaultyStream::new(client, faults);
+
+        assert!(stream.write_all(b"hello").await.is_err());
+        let mut buf = [0u8; 5];
+        let len = server.read(&mut buf).await?;
+        assert_eq!(&buf[..len], b"hel");
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_latency_and_throttling() -> Result<()> {
+        let (client, mut server) = duplex(1024);
+        let faults = NetFaults {
+            latency: Duration::from_millis(100),
+            throttle_bytes_per_sec: Some(100),
+            ..Default::default()
+        };
+        let mut stream = FaultyStream::new(client, faults);
+
+        server.write_all(b"ping").await?;
+        let start = Instant::now();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await?;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // 4 bytes at 100 bytes per second delay the next write by 40 ms.
+        let start = Instant::now();
+        stream.write_all(b"pong").await?;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_drop_connections() -> Result<()> {
+        set_net_faults(NetFaults {
+            drop_connections: 2,
+            ..Default::default()
+        });
+        assert!(on_connect().await.is_err());
+        assert!(on_connect().await.is_err());
+        assert_eq!(get_net_faults().drop_connections, 0);
+        assert!(on_connect().await.is_ok());
+
+        set_net_faults(NetFaults::default());
+        assert_eq!(get_net_faults(), NetFaults::default());
+        Ok(())
+    }
+}
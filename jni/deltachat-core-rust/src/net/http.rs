@@ -241,3 +241,46 @@ pub(crate) async fn post_form<T: Serialize + ?Sized>(
     let bytes = response.collect().await?.to_bytes();
     Ok(bytes)
 }
+
+/// Sends a request with the given method, headers and body to the URL.
+///
+/// Returns the response without reading its body,
+/// so that large bodies can be streamed.
+///
+/// Does not follow redirects.
+pub(crate) async fn request<B>(
+    context: &Context,
+    method: hyper::Method,
+    url: &str,
+    headers: &[(&str, String)],
+    body: B,
+) -> Result<hyper::Response<hyper::body::Incoming>>
+where
+    B: hyper::body::Body + 'static + Send,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let parsed_url = url
+        .parse::<hyper::Uri>()
+        .with_context(|| format!("Failed to parse URL {url:?}"))?;
+    let scheme = parsed_url.scheme_str().context("URL has no scheme")?;
+    if scheme != "https" {
+        bail!("Requests to non-HTTPS URLs are not allowed");
+    }
+
+    let mut sender = get_http_sender(context, parsed_url.clone()).await?;
+    let authority = parsed_url
+        .authority()
+        .context("URL has no authority")?
+        .clone();
+    let mut builder = hyper::Request::builder()
+        .method(method)
+        .uri(parsed_url.path())
+        .header(hyper::header::HOST, authority.as_str());
+    for (name, value) in headers {
+        builder = builder.header(*name, value);
+    }
+    let request = builder.body(body)?;
+    let response = sender.send_request(request).await?;
+    Ok(response)
+}
@@ -0,0 +1,384 @@
+//! # Message delivery latency metrics.
+//!
+//! For each outgoing message the following times are recorded in milliseconds:
+//! - when the message is queued for sending,
+//!   or when its send delay ends if [`Config::SendDelay`] is set,
+//! - when it is first submitted to the SMTP server,
+//! - when the SMTP server accepted it for all recipients,
+//! - when the first read receipt (MDN) for it is received.
+//!
+//! Together with the provider the message was sent with,
+//! this allows to quantify reports of slow messaging.
+//! Entries are kept for [`CACHE_TTL`] and only stored locally.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::message::MsgId;
+use crate::net::CACHE_TTL;
+use crate::tools::{time, SystemTime};
+
+/// Returns the current time in milliseconds since the epoch.
+fn time_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// Returns the provider messages are currently sent with.
+///
+/// This is the ID of the provider in the provider database
+/// or the domain of the configured address for unknown providers.
+async fn current_provider(context: &Context) -> Result<String> {
+    if let Some(provider) = context.get_configured_provider().await? {
+        return Ok(provider.id.to_string());
+    }
+    let addr = context
+        .get_config(Config::ConfiguredAddr)
+        .await?
+        .unwrap_or_default();
+    Ok(addr
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.to_lowercase())
+        .unwrap_or_default())
+}
+
+/// Records that the message was queued for sending.
+///
+/// `send_after` is the time before which the message must not be submitted
+/// as returned by [`crate::undo::send_after`].
+/// Resending the message restarts the measurement.
+pub(crate) async fn record_queued(context: &Context, msg_id: MsgId, send_after: i64) -> Result<()> {
+    let provider = current_provider(context).await?;
+    let queued = time_ms().max(send_after.saturating_mul(1000));
+    context
+        .sql
+        .execute(
+            "INSERT OR REPLACE INTO msg_delivery_times (msg_id, provider, queued) VALUES (?, ?, ?)",
+            (msg_id, provider, queued),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Records that the message is submitted to the SMTP server for the first time.
+pub(crate) async fn record_submitted(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE msg_delivery_times SET submitted=? WHERE msg_id=? AND submitted IS NULL",
+            (time_ms(), msg_id),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Records that the SMTP server accepted the message for all recipients.
+pub(crate) async fn record_accepted(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE msg_delivery_times SET accepted=? WHERE msg_id=? AND accepted IS NULL",
+            (time_ms(), msg_id),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Records that the first read receipt for the message was received.
+pub(crate) async fn record_mdn_received(context: &Context, msg_id: MsgId) -> Result<()> {
+    context
+        .sql
+        .execute(
+            "UPDATE msg_delivery_times SET mdn_received=? WHERE msg_id=? AND mdn_received IS NULL",
+            (time_ms(), msg_id),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Removes latency entries after [`CACHE_TTL`].
+pub(crate) async fn prune_delivery_times(context: &Context) -> Result<()> {
+    let min_queued = time().saturating_sub(CACHE_TTL as i64).saturating_mul(1000);
+    context
+        .sql
+        .execute(
+            "DELETE FROM msg_delivery_times WHERE queued<?",
+            (min_queued,),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Summary of the durations of one delivery stage.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LatencySummary {
+    /// Number of messages that completed the stage.
+    pub count: u64,
+
+    /// Mean duration in milliseconds.
+    pub mean_ms: u64,
+
+    /// Median duration in milliseconds.
+    pub median_ms: u64,
+
+    /// 95th percentile of the duration in milliseconds.
+    pub p95_ms: u64,
+
+    /// Maximum duration in milliseconds.
+    pub max_ms: u64,
+}
+
+impl LatencySummary {
+    fn from_durations(mut durations: Vec<u64>) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+        durations.sort_unstable();
+        let count = durations.len();
+        let percentile = |p: usize| {
+            durations
+                .get((count - 1) * p / 100)
+                .copied()
+                .unwrap_or_default()
+        };
+        Self {
+            count: count as u64,
+            mean_ms: durations.iter().sum::<u64>() / count as u64,
+            median_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: durations.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Aggregated delivery latencies of outgoing messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DeliveryLatencyStats {
+    /// Number of messages queued for sending.
+    pub messages: u64,
+
+    /// Time from queueing a message until it is submitted to the SMTP server.
+    pub queued_to_submitted: LatencySummary,
+
+    /// Time from submitting a message until the SMTP server accepted it.
+    pub submitted_to_accepted: LatencySummary,
+
+    /// Time from the SMTP server accepting a message until the first read receipt.
+    pub accepted_to_mdn: LatencySummary,
+}
+
+/// Durations of the delivery stages collected for one group of messages.
+#[derive(Debug, Default)]
+struct Durations {
+    messages: u64,
+    queued_to_submitted: Vec<u64>,
+    submitted_to_accepted: Vec<u64>,
+    accepted_to_mdn: Vec<u64>,
+}
+
+impl Durations {
+    fn add(
+        &mut self,
+        queued: i64,
+        submitted: Option<i64>,
+        accepted: Option<i64>,
+        mdn: Option<i64>,
+    ) {
+        // Clock changes may result in negative durations, count them as zero.
+        let duration = |from: i64, to: i64| to.saturating_sub(from).max(0) as u64;
+        self.messages += 1;
+        if let Some(submitted) = submitted {
+            self.queued_to_submitted.push(duration(queued, submitted));
+            if let Some(accepted) = accepted {
+                self.submitted_to_accepted
+                    .push(duration(submitted, accepted));
+            }
+        }
+        if let (Some(accepted), Some(mdn)) = (accepted, mdn) {
+            self.accepted_to_mdn.push(duration(accepted, mdn));
+        }
+    }
+
+    fn stats(self) -> DeliveryLatencyStats {
+        DeliveryLatencyStats {
+            messages: self.messages,
+            queued_to_submitted: LatencySummary::from_durations(self.queued_to_submitted),
+            submitted_to_accepted: LatencySummary::from_durations(self.submitted_to_accepted),
+            accepted_to_mdn: LatencySummary::from_durations(self.accepted_to_mdn),
+        }
+    }
+}
+
+impl Context {
+    /// Returns delivery latencies of messages queued since the timestamp `since`, in seconds,
+    /// grouped by the provider they were sent with.
+    ///
+    /// Pass `since=0` to get the latencies of all recorded messages.
+    pub async fn get_delivery_latency_stats_by_provider(
+        &self,
+        since: i64,
+    ) -> Result<BTreeMap<String, DeliveryLatencyStats>> {
+        let durations = self
+            .sql
+            .query_map(
+                "SELECT provider, queued, submitted, accepted, mdn_received
+                 FROM msg_delivery_times WHERE queued>=?",
+                (since.saturating_mul(1000),),
+                |row| {
+                    let provider: String = row.get(0)?;
+                    let queued: i64 = row.get(1)?;
+                    let submitted: Option<i64> = row.get(2)?;
+                    let accepted: Option<i64> = row.get(3)?;
+                    let mdn_received: Option<i64> = row.get(4)?;
+                    Ok((provider, queued, submitted, accepted, mdn_received))
+                },
+                |rows| {
+                    let mut durations = BTreeMap::<String, Durations>::new();
+                    for row in rows {
+                        let (provider, queued, submitted, accepted, mdn_received) = row?;
+                        durations.entry(provider).or_default().add(
+                            queued,
+                            submitted,
+                            accepted,
+                            mdn_received,
+                        );
+                    }
+                    Ok(durations)
+                },
+            )
+            .await?;
+        Ok(durations
+            .into_iter()
+            .map(|(provider, durations)| (provider, durations.stats()))
+            .collect())
+    }
+
+    /// Returns delivery latencies of all messages of the account
+    /// queued since the timestamp `since`, in seconds.
+    pub async fn get_delivery_latency_stats(&self, since: i64) -> Result<DeliveryLatencyStats> {
+        let durations = self
+            .sql
+            .query_map(
+                "SELECT queued, submitted, accepted, mdn_received
+                 FROM msg_delivery_times WHERE queued>=?",
+                (since.saturating_mul(1000),),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                |rows| {
+                    let mut durations = Durations::default();
+                    for row in rows {
+                        let (queued, submitted, accepted, mdn_received) = row?;
+                        durations.add(queued, submitted, accepted, mdn_received);
+                    }
+                    Ok(durations)
+                },
+            )
+            .await?;
+        Ok(durations.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::message::{Message, MessageState};
+    use crate::receive_imf::receive_imf;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_delivery_latency_stats() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(chat_id, "Hello").await;
+        let msg_id = sent.sender_msg_id;
+        record_submitted(alice, msg_id).await?;
+        SystemTime::shift(Duration::from_secs(2));
+        record_accepted(alice, msg_id).await?;
+
+        SystemTime::shift(Duration::from_secs(60));
+        let rfc724_mid = Message::load_from_db(alice, msg_id).await?.rfc724_mid;
+        receive_imf(
+            alice,
+            format!(
+                "From: bob@example.net\n\
+                 To: alice@example.org\n\
+                 Subject: message opened\n\
+                 Date: Sun, 22 Mar 2020 23:37:57 +0000\n\
+                 Chat-Version: 1.0\n\
+                 Message-ID: <mdn@example.net>\n\
+                 Content-Type: multipart/report; report-type=disposition-notification; boundary=\"SNIPP\"\n\
+                 \n\
+                 --SNIPP\n\
+                 Content-Type: text/plain; charset=utf-8\n\
+                 \n\
+                 Read receipts do not guarantee sth. was read.\n\
+                 \n\
+                 --SNIPP\n\
+                 Content-Type: message/disposition-notification\n\
+                 \n\
+                 Original-Recipient: rfc822;bob@example.net\n\
+                 Final-Recipient: rfc822;bob@example.net\n\
+                 Original-Message-ID: <{rfc724_mid}>\n\
+                 Disposition: manual-action/MDN-sent-automatically; displayed\n\
+                 \n\
+                 --SNIPP--"
+            )
+            .as_bytes(),
+            false,
+        )
+        .await?;
+        assert_eq!(
+            Message::load_from_db(alice, msg_id).await?.state,
+            MessageState::OutMdnRcvd
+        );
+
+        // Another message is not submitted yet.
+        alice.send_text(chat_id, "Are you there?").await;
+
+        let stats = alice.get_delivery_latency_stats(0).await?;
+        assert_eq!(stats.messages, 2);
+        assert_eq!(stats.queued_to_submitted.count, 1);
+        assert_eq!(stats.submitted_to_accepted.count, 1);
+        assert!(stats.submitted_to_accepted.median_ms >= 2000);
+        assert_eq!(stats.accepted_to_mdn.count, 1);
+        assert!(stats.accepted_to_mdn.max_ms >= 60000);
+
+        let by_provider = alice.get_delivery_latency_stats_by_provider(0).await?;
+        assert_eq!(by_provider.len(), 1);
+        assert_eq!(by_provider.get("example.org"), Some(&stats));
+
+        // Future messages only.
+        let stats = alice.get_delivery_latency_stats(time() + 3600).await?;
+        assert_eq!(stats, DeliveryLatencyStats::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_latency_summary() {
+        let summary = LatencySummary::from_durations((1..=100).rev().collect());
+        assert_eq!(
+            summary,
+            LatencySummary {
+                count: 100,
+                mean_ms: 50,
+                median_ms: 50,
+                p95_ms: 95,
+                max_ms: 100,
+            }
+        );
+        assert_eq!(
+            LatencySummary::from_durations(Vec::new()),
+            LatencySummary::default()
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! # Network traffic statistics.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::context::Context;
+
+/// Counters of profile data and Autocrypt gossip attached to outgoing messages.
+#[derive(Debug, Default)]
+pub(crate) struct NetworkCounters {
+    pub(crate) avatars_attached: AtomicU64,
+    pub(crate) avatars_throttled: AtomicU64,
+    pub(crate) gossip_headers: AtomicU64,
+    pub(crate) gossip_throttled: AtomicU64,
+}
+
+impl NetworkCounters {
+    pub(crate) fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Statistics of outgoing messages since the context was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NetworkStats {
+    /// Number of messages the own avatar was attached to.
+    pub avatars_attached: u64,
+
+    /// Number of messages the own avatar was not attached to because of large group throttling.
+    pub avatars_throttled: u64,
+
+    /// Number of `Autocrypt-Gossip` headers sent.
+    pub gossip_headers: u64,
+
+    /// Number of messages sent without gossip because of large group throttling.
+    pub gossip_throttled: u64,
+}
+
+impl Context {
+    /// Returns statistics of outgoing messages since the context was created.
+    pub fn get_network_stats(&self) -> NetworkStats {
+        let counters = &self.network_counters;
+        NetworkStats {
+            avatars_attached: counters.avatars_attached.load(Ordering::Relaxed),
+            avatars_throttled: counters.avatars_throttled.load(Ordering::Relaxed),
+            gossip_headers: counters.gossip_headers.load(Ordering::Relaxed),
+            gossip_throttled: counters.gossip_throttled.load(Ordering::Relaxed),
+        }
+    }
+}
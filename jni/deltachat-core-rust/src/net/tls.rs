@@ -1,10 +1,36 @@
 //! TLS support.
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::Result;
+use once_cell::sync::Lazy;
 
 use crate::net::session::SessionStream;
 
+/// Map from hostname to the expiration timestamp of the certificate
+/// presented by the server when connecting last time.
+///
+/// NOTE: sync RwLock is used, so it must not be held across `.await`.
+static CERT_EXPIRY: Lazy<parking_lot::RwLock<HashMap<String, i64>>> = Lazy::new(Default::default);
+
+/// Returns the expiration timestamp of the certificate
+/// presented by `hostname` when connecting last time.
+pub(crate) fn cert_expiry(hostname: &str) -> Option<i64> {
+    CERT_EXPIRY.read().get(hostname).copied()
+}
+
+/// Remembers the expiration timestamp of the certificate presented by `hostname`.
+pub(crate) fn set_cert_expiry(hostname: &str, not_after: i64) {
+    CERT_EXPIRY.write().insert(hostname.to_string(), not_after);
+}
+
+/// Remembers the expiration timestamp of the DER-encoded end-entity certificate.
+fn record_cert_expiry(hostname: &str, der: &[u8]) {
+    if let Ok((_, cert)) = x509_parser::parse_x509_certificate(der) {
+        set_cert_expiry(hostname, cert.validity().not_after.timestamp());
+    }
+}
+
 pub async fn wrap_tls(
     strict_tls: bool,
     hostname: &str,
@@ -25,6 +51,11 @@ pub async fn wrap_tls(
             .danger_accept_invalid_hostnames(true)
             .danger_accept_invalid_certs(true);
         let tls_stream = tls.connect(hostname, stream).await?;
+        if let Ok(Some(cert)) = tls_stream.peer_certificate() {
+            if let Ok(der) = cert.to_der() {
+                record_cert_expiry(hostname, &der);
+            }
+        }
         let boxed_stream: Box<dyn SessionStream> = Box::new(tls_stream);
         Ok(boxed_stream)
     }
@@ -46,5 +77,13 @@ pub async fn wrap_rustls(
     let tls = tokio_rustls::TlsConnector::from(Arc::new(config));
     let name = rustls_pki_types::ServerName::try_from(hostname)?.to_owned();
     let tls_stream = tls.connect(name, stream).await?;
+    if let Some(cert) = tls_stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+    {
+        record_cert_expiry(hostname, cert);
+    }
     Ok(tls_stream)
 }
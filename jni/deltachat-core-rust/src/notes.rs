@@ -0,0 +1,222 @@
+//! # Todo items in the Saved Messages chat.
+//!
+//! Messages in the Saved Messages chat can be marked as todo items
+//! which can be checked and have an optional due date.
+//! The todo state is stored in the message parameters
+//! and synchronized to other devices with sync messages,
+//! the messages themselves are synchronized as usual.
+
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, Chat, ChatId};
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::{rfc724_mid_exists, Message, MsgId};
+use crate::param::{Param, Params};
+use crate::sync::Sync::*;
+use crate::sync::{self, SyncData};
+
+/// State of a todo item.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Todo {
+    /// Whether the todo item is checked.
+    pub done: bool,
+
+    /// Due date as a unix timestamp.
+    pub due: Option<i64>,
+}
+
+impl Message {
+    /// Returns the todo state if the message is a todo item.
+    pub fn get_todo(&self) -> Option<Todo> {
+        get_todo_param(&self.param)
+    }
+}
+
+fn get_todo_param(param: &Params) -> Option<Todo> {
+    let done = param.get_bool(Param::TodoDone)?;
+    Some(Todo {
+        done,
+        due: param.get_i64(Param::TodoDue),
+    })
+}
+
+/// Adds a todo item with the text `text` to the Saved Messages chat.
+pub async fn add_todo(context: &Context, text: &str, due: Option<i64>) -> Result<MsgId> {
+    let chat_id = ChatId::create_for_contact(context, ContactId::SELF).await?;
+    let mut msg = Message::new_text(text.to_string());
+    let msg_id = chat::send_msg(context, chat_id, &mut msg).await?;
+    set_todo(context, msg_id, Some(Todo { done: false, due })).await?;
+    Ok(msg_id)
+}
+
+/// Marks the message `msg_id` of the Saved Messages chat as a todo item
+/// or turns the todo item back into a regular message if `todo` is `None`.
+pub async fn set_todo(context: &Context, msg_id: MsgId, todo: Option<Todo>) -> Result<()> {
+    set_todo_ex(context, Sync, msg_id, todo).await
+}
+
+/// Checks or unchecks the todo item `msg_id`.
+///
+/// Returns whether the todo item is checked now.
+pub async fn toggle_todo(context: &Context, msg_id: MsgId) -> Result<bool> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    let Some(mut todo) = msg.get_todo() else {
+        bail!("Message {msg_id} is not a todo item");
+    };
+    todo.done = !todo.done;
+    set_todo(context, msg_id, Some(todo)).await?;
+    Ok(todo.done)
+}
+
+/// Returns the todo items of the Saved Messages chat,
+/// unchecked items first, then ordered by due date.
+pub async fn get_todos(context: &Context, include_done: bool) -> Result<Vec<MsgId>> {
+    let Some(chat_id) = ChatId::lookup_by_contact(context, ContactId::SELF).await? else {
+        return Ok(Vec::new());
+    };
+    // Only messages with `Param::TodoDone` set are todo items.
+    let mut todos = context
+        .sql
+        .query_map(
+            "SELECT id, param FROM msgs
+             WHERE chat_id=? AND hidden=0
+             AND (param LIKE '5=%' OR param LIKE '%' || char(10) || '5=%')
+             ORDER BY timestamp, id",
+            (chat_id,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let param: String = row.get(1)?;
+                Ok((get_todo_param(&param.parse().unwrap_or_default()), msg_id))
+            },
+            |rows| {
+                let mut todos = Vec::new();
+                for row in rows {
+                    if let (Some(todo), msg_id) = row? {
+                        if include_done || !todo.done {
+                            todos.push((todo, msg_id));
+                        }
+                    }
+                }
+                Ok(todos)
+            },
+        )
+        .await?;
+    // Stable sort keeps the chat order for items with the same due date.
+    todos.sort_by_key(|(todo, _msg_id)| (todo.done, todo.due.is_none(), todo.due));
+    Ok(todos.into_iter().map(|(_todo, msg_id)| msg_id).collect())
+}
+
+pub(crate) async fn set_todo_ex(
+    context: &Context,
+    sync: sync::Sync,
+    msg_id: MsgId,
+    todo: Option<Todo>,
+) -> Result<()> {
+    let mut msg = Message::load_from_db(context, msg_id).await?;
+    let chat = Chat::load_from_db(context, msg.chat_id).await?;
+    ensure!(
+        chat.is_self_talk(),
+        "Only messages in Saved Messages can be todo items"
+    );
+    match todo {
+        Some(todo) => {
+            msg.param.set_int(Param::TodoDone, todo.done.into());
+            msg.param.set_optional(Param::TodoDue, todo.due);
+        }
+        None => {
+            msg.param.remove(Param::TodoDone);
+            msg.param.remove(Param::TodoDue);
+        }
+    }
+    msg.update_param(context).await?;
+    context.emit_event(EventType::MsgsChanged {
+        chat_id: msg.chat_id,
+        msg_id,
+    });
+
+    if sync.into() && !msg.rfc724_mid.is_empty() {
+        context
+            .add_sync_item(SyncData::Todo {
+                msg: msg.rfc724_mid,
+                todo,
+            })
+            .await?;
+        context.scheduler.interrupt_inbox().await;
+    }
+    Ok(())
+}
+
+impl Context {
+    pub(crate) async fn sync_todo(&self, rfc724_mid: &str, todo: Option<Todo>) -> Result<()> {
+        let Some((msg_id, _)) = rfc724_mid_exists(self, rfc724_mid).await? else {
+            warn!(self, "sync_todo: No message {rfc724_mid:?}.");
+            return Ok(());
+        };
+        set_todo_ex(self, Nosync, msg_id, todo).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_utils::{sync, TestContext};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_todo() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+
+        let shopping = add_todo(alice0, "Shopping", None).await?;
+        let sent = alice0.pop_sent_msg().await;
+        let alice1_shopping = alice1.recv_msg(&sent).await.id;
+        let taxes = add_todo(alice0, "Taxes", Some(1700000000)).await?;
+        let sent = alice0.pop_sent_msg().await;
+        let alice1_taxes = alice1.recv_msg(&sent).await.id;
+        sync(alice0, alice1).await;
+
+        assert_eq!(get_todos(alice0, false).await?, [taxes, shopping]);
+        assert_eq!(
+            get_todos(alice1, false).await?,
+            [alice1_taxes, alice1_shopping]
+        );
+        let msg = Message::load_from_db(alice1, alice1_taxes).await?;
+        assert_eq!(
+            msg.get_todo(),
+            Some(Todo {
+                done: false,
+                due: Some(1700000000)
+            })
+        );
+
+        assert!(toggle_todo(alice0, taxes).await?);
+        sync(alice0, alice1).await;
+        assert_eq!(get_todos(alice1, false).await?, [alice1_shopping]);
+        assert_eq!(
+            get_todos(alice1, true).await?,
+            [alice1_shopping, alice1_taxes]
+        );
+
+        set_todo(alice0, shopping, None).await?;
+        sync(alice0, alice1).await;
+        let msg = Message::load_from_db(alice1, alice1_shopping).await?;
+        assert_eq!(msg.get_todo(), None);
+        assert!(toggle_todo(alice1, alice1_shopping).await.is_err());
+
+        // Only messages in Saved Messages can be todo items.
+        let chat = alice0
+            .create_chat_with_contact("bob", "bob@example.net")
+            .await;
+        let msg_id = alice0.send_text(chat.id, "Hi").await.sender_msg_id;
+        assert!(set_todo(alice0, msg_id, Some(Todo::default()))
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! # Coordination of processes sharing an account.
+//!
+//! On Android, a share target or a widget may run in a separate process
+//! and open the same account database as the main app.
+//! Only one of the processes, the owner, may run the IO scheduler,
+//! otherwise messages would be fetched and sent twice
+//! and the schedulers and the housekeeping of both processes would compete for the database.
+//!
+//! The owner holds an advisory lock on a file next to the database,
+//! which is acquired by [`Context::start_io`].
+//! The context of another process is secondary:
+//! it can read the database and create messages,
+//! but [`Context::start_io`] only starts IO once the owner released the lock,
+//! e.g. because the owner process exited or called [`Context::release_ownership`].
+//! Until then the lock is retried in the background.
+//! [`EventType::OwnershipChanged`] is emitted when the ownership changes.
+//!
+//! A secondary context queuing messages for sending touches a notify file next to the database.
+//! The SMTP loop of the owner checks it periodically while idle
+//! and sends the queued messages.
+//!
+//! iOS ensures itself that only one process accesses the account at a time
+//! and holding a lock while suspended crashes the app,
+//! so on iOS every context is the owner.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+#[cfg(not(target_os = "ios"))]
+use tokio::sync::oneshot;
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::context::Context;
+use crate::events::EventType;
+use crate::tools::time;
+
+/// Interval of retrying to become the owner after [`Context::start_io`]
+/// while another process owns the account.
+const OWNERSHIP_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Interval of checking the notify file while the SMTP loop of the owner is idle.
+pub(crate) const OWNER_NOTIFY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Ownership of the account by the process, see [`Context::is_owner`].
+#[derive(Debug, Default)]
+pub(crate) struct OwnershipState {
+    /// Task holding the lock on the lock file, `None` if the context is secondary.
+    lock_task: parking_lot::Mutex<Option<JoinHandle<()>>>,
+
+    /// Task retrying to acquire the lock to start IO, see [`Context::start_io`].
+    waiting: parking_lot::Mutex<Option<AbortHandle>>,
+}
+
+impl Drop for OwnershipState {
+    fn drop(&mut self) {
+        self.stop_waiting();
+        self.release();
+    }
+}
+
+impl OwnershipState {
+    /// Returns true if the lock is held.
+    fn is_owner(&self) -> bool {
+        cfg!(target_os = "ios")
+            || self
+                .lock_task
+                .lock()
+                .as_ref()
+                .is_some_and(|task| !task.is_finished())
+    }
+
+    /// Stops retrying to acquire the lock.
+    pub(crate) fn stop_waiting(&self) {
+        if let Some(handle) = self.waiting.lock().take() {
+            handle.abort();
+        }
+    }
+
+    /// Releases the lock if it is held.
+    fn release(&self) {
+        if let Some(task) = self.lock_task.lock().take() {
+            task.abort();
+        }
+    }
+
+    #[cfg(target_os = "ios")]
+    async fn try_acquire(&self, _lockfile: PathBuf) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// Tries to lock `lockfile` without waiting.
+    ///
+    /// Returns false if another process holds the lock.
+    #[cfg(not(target_os = "ios"))]
+    async fn try_acquire(&self, lockfile: PathBuf) -> Result<bool> {
+        if self.is_owner() {
+            return Ok(true);
+        }
+        let (locked_tx, locked_rx) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let file = match std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&lockfile)
+            {
+                Ok(file) => file,
+                Err(err) => {
+                    locked_tx.send(Err(err)).ok();
+                    return;
+                }
+            };
+            let mut lock = fd_lock::RwLock::new(file);
+            let _guard = match lock.try_write() {
+                Ok(guard) => guard,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    locked_tx.send(Ok(false)).ok();
+                    return;
+                }
+                Err(err) => {
+                    locked_tx.send(Err(err)).ok();
+                    return;
+                }
+            };
+            if locked_tx.send(Ok(true)).is_ok() {
+                // Hold the lock until the task is aborted.
+                std::future::pending::<()>().await;
+            }
+        });
+        let locked = locked_rx.await??;
+        if locked {
+            *self.lock_task.lock() = Some(task);
+        }
+        Ok(locked)
+    }
+}
+
+/// Returns the path of the file next to the database `dbfile` with `suffix` appended.
+fn sibling_path(dbfile: &Path, suffix: &str) -> PathBuf {
+    let mut name = dbfile.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    dbfile.with_file_name(name)
+}
+
+/// Returns the path of the lock file of the database `dbfile`.
+fn lockfile_path(dbfile: &Path) -> PathBuf {
+    sibling_path(dbfile, "-owner.lock")
+}
+
+/// Returns the path of the file touched when a secondary context queued messages.
+fn notify_path(dbfile: &Path) -> PathBuf {
+    sibling_path(dbfile, "-owner.notify")
+}
+
+impl Context {
+    /// Returns true if this process owns the account and may run the IO scheduler,
+    /// see [`crate::ownership`].
+    pub fn is_owner(&self) -> bool {
+        self.ownership.is_owner()
+    }
+
+    /// Tries to become the owner of the account.
+    ///
+    /// Returns false if another process owns the account.
+    /// This is done by [`Context::start_io`] automatically.
+    pub async fn try_acquire_ownership(&self) -> Result<bool> {
+        let was_owner = self.is_owner();
+        let owner = self
+            .ownership
+            .try_acquire(lockfile_path(&self.sql.dbfile))
+            .await?;
+        if !owner {
+            info!(self, "The account is owned by another process.");
+        } else if !was_owner {
+            info!(self, "Acquired the ownership of the account.");
+            self.emit_event(EventType::OwnershipChanged);
+        }
+        Ok(owner)
+    }
+
+    /// Retries to become the owner of the account in the background
+    /// and starts IO once it succeeded.
+    ///
+    /// Stopped by [`Context::stop_io`].
+    pub(crate) fn wait_for_ownership(&self) {
+        let context = self.clone();
+        let mut waiting = self.ownership.waiting.lock();
+        if let Some(handle) = waiting.take() {
+            handle.abort();
+        }
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(OWNERSHIP_RETRY_INTERVAL).await;
+                match context.try_acquire_ownership().await {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    Err(err) => {
+                        warn!(
+                            context,
+                            "Cannot check the ownership of the account: {err:#}."
+                        );
+                    }
+                }
+            }
+            context.ownership.waiting.lock().take();
+            context.start_scheduler().await;
+        });
+        *waiting = Some(handle.abort_handle());
+    }
+
+    /// Interrupts the SMTP loop to send queued messages.
+    ///
+    /// If the account is owned by another process,
+    /// the owner is notified through the notify file instead.
+    pub(crate) async fn interrupt_smtp(&self) {
+        if self.is_owner() {
+            self.scheduler.interrupt_smtp().await;
+            return;
+        }
+        let path = notify_path(&self.sql.dbfile);
+        if let Err(err) = tokio::fs::write(&path, time().to_string()).await {
+            warn!(self, "Cannot notify the owner of the account: {err:#}.");
+        }
+    }
+
+    /// Returns the last time a secondary context queued messages,
+    /// `None` if it never did.
+    pub(crate) async fn owner_notified_at(&self) -> Option<SystemTime> {
+        tokio::fs::metadata(notify_path(&self.sql.dbfile))
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Stops IO and releases the ownership of the account,
+    /// so that another process can become the owner.
+    ///
+    /// This should be called when a short-lived process such as a share target
+    /// is done with the account while the main app may be running.
+    pub async fn release_ownership(&self) {
+        if !self.is_owner() {
+            self.ownership.stop_waiting();
+            return;
+        }
+        self.stop_io().await;
+        self.ownership.release();
+        info!(self, "Released the ownership of the account.");
+        self.emit_event(EventType::OwnershipChanged);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{self, ChatId};
+    use crate::contact::ContactId;
+    use crate::events::Events;
+    use crate::stock_str::StockStrings;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ownership() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        // The ownership is only acquired when IO is started.
+        assert!(!t.is_owner());
+        assert!(t.try_acquire_ownership().await?);
+        assert!(t.is_owner());
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::OwnershipChanged))
+            .await;
+
+        // Another process opening the same database is secondary.
+        let dbfile = t.sql.dbfile.clone();
+        let secondary = Context::new(&dbfile, 2, Events::new(), StockStrings::new()).await?;
+        assert!(!secondary.is_owner());
+        assert!(!secondary.try_acquire_ownership().await?);
+        secondary.start_io().await;
+        assert!(!secondary.scheduler.is_running().await);
+        assert!(secondary.ownership.waiting.lock().is_some());
+
+        // Messages queued by the secondary context notify the owner.
+        assert_eq!(t.owner_notified_at().await, None);
+        let chat_id = ChatId::create_for_contact(&secondary, ContactId::SELF).await?;
+        chat::send_text_msg(&secondary, chat_id, "Shared".to_string()).await?;
+        assert!(t.owner_notified_at().await.is_some());
+
+        t.release_ownership().await;
+        assert!(!t.is_owner());
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::OwnershipChanged))
+            .await;
+        assert!(secondary.try_acquire_ownership().await?);
+        assert!(secondary.is_owner());
+        assert!(!t.try_acquire_ownership().await?);
+
+        secondary.stop_io().await;
+        assert!(secondary.ownership.waiting.lock().is_none());
+        drop(secondary);
+        // The lock is released asynchronously when the context is dropped.
+        let mut owner = false;
+        for _ in 0..50 {
+            owner = t.try_acquire_ownership().await?;
+            if owner {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(owner);
+        Ok(())
+    }
+}
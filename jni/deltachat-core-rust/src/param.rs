@@ -141,6 +141,16 @@ pub enum Param {
     /// For Groups and Contacts
     ProfileImage = b'i',
 
+    /// For Contacts: Avatar set by the local user, overriding [`Param::ProfileImage`].
+    ///
+    /// Never sent to anyone.
+    LocalProfileImage = b'I',
+
+    /// For Contacts: Nickname set by the local user, overriding the contact name.
+    ///
+    /// Never sent to anyone.
+    LocalDisplayname = b'M',
+
     /// For Chats
     /// Signals whether the chat is the `saved messages` chat
     Selftalk = b'K',
@@ -183,6 +193,12 @@ pub enum Param {
     /// For Chats: timestamp of member list update.
     MemberListTimestamp = b'k',
 
+    /// For Chats: number of recent messages shared with new group members.
+    HistorySharing = b'z',
+
+    /// For Chats: timestamp of the last message with the own avatar attached.
+    SelfavatarSentTimestamp = b'Z',
+
     /// For Webxdc Message Instances: Current document name
     WebxdcDocument = b'R',
 
@@ -203,6 +219,43 @@ pub enum Param {
 
     /// For messages: Whether [crate::message::Viewtype::Sticker] should be forced.
     ForceSticker = b'X',
+
+    /// For messages: Whether an image should be sent in original quality, without recoding.
+    OriginalQuality = b'8',
+
+    /// For messages in Saved Messages: Whether the todo item is checked,
+    /// the message is no todo item if unset.
+    TodoDone = b'5',
+
+    /// For messages in Saved Messages: Due date of the todo item.
+    TodoDue = b'6',
+
+    /// For messages: ID of the known contact whose name the unknown sender uses,
+    /// the message possibly impersonates that contact.
+    ImpersonatedContact = b'7',
+
+    /// For Chats and Contacts: Read receipt policy, see [`crate::mdn::MdnPolicy`].
+    MdnPolicy = b'9',
+
+    /// For Messages: hex-encoded SHA-256 hash of the attachment as sent.
+    ///
+    /// All alphanumeric keys are taken, so this key is a punctuation character.
+    FileHash = b'#',
+
+    /// For Messages: thumbnail of a video attachment, a JPEG blob.
+    Thumbnail = b'%',
+
+    /// For Messages: base64-encoded waveform of a voice message,
+    /// see [`crate::message::Message::get_waveform`].
+    Waveform = b'&',
+
+    /// For Messages: ID of the chat the attachment was staged for,
+    /// set if it was prepared for sending when it was staged, see [`crate::staging`].
+    Staged = b'$',
+
+    /// For Messages: preview of the first page of a PDF attachment, a JPEG blob,
+    /// see [`crate::message::Message::get_preview_blob`].
+    Preview = b'*',
     // 'L' was defined as ProtectionSettingsTimestamp for Chats, however, never used in production.
 }
 
@@ -308,6 +361,12 @@ pub fn set_optional(&mut self, key: Param, value: Option<impl ToString>) -> &mut
         }
     }
 
+    /// Sets all values set in `other`, replacing existing values.
+    pub(crate) fn extend(&mut self, other: Params) -> &mut Self {
+        self.inner.extend(other.inner);
+        self
+    }
+
     /// Check if there are any values in this.
     pub fn is_empty(&self) -> bool {
         self.inner.is_empty()
@@ -2,16 +2,17 @@
 
 use std::mem;
 
-use anyhow::{Context as _, Error, Result};
+use anyhow::{ensure, Context as _, Error, Result};
 use deltachat_contact_tools::{addr_cmp, ContactAddress};
 use num_traits::FromPrimitive;
+use serde::Serialize;
 
 use crate::aheader::{Aheader, EncryptPreference};
-use crate::chat::{self, Chat};
+use crate::chat::{self, Chat, ChatId};
 use crate::chatlist::Chatlist;
 use crate::config::Config;
 use crate::constants::Chattype;
-use crate::contact::{Contact, Origin};
+use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
 use crate::events::EventType;
 use crate::key::{DcKey, Fingerprint, SignedPublicKey};
@@ -533,14 +534,33 @@ pub fn set_secondary_verified_key(&mut self, gossip_key: SignedPublicKey, verifi
     }
 
     /// Saves the peerstate to the database.
+    ///
+    /// No events are emitted for chats whose encryption state changes because of this.
     pub async fn save_to_db(&self, sql: &Sql) -> Result<()> {
-        self.save_to_db_ex(sql, None).await
+        self.save_to_sql(sql, None).await?;
+        Ok(())
     }
 
-    /// Saves the peerstate to the database.
+    /// Saves the peerstate to the database
+    /// and emits `ChatlistItemChanged` events for chats whose encryption state may change.
     ///
     /// * `old_addr`: Old address of the peerstate in case of an AEAP transition.
-    pub(crate) async fn save_to_db_ex(&self, sql: &Sql, old_addr: Option<&str>) -> Result<()> {
+    pub(crate) async fn save_to_db_ex(
+        &self,
+        context: &Context,
+        old_addr: Option<&str>,
+    ) -> Result<()> {
+        let reset_chat_ids = self.save_to_sql(&context.sql, old_addr).await?;
+        for chat_id in reset_chat_ids {
+            chatlist_events::emit_chatlist_item_changed(context, chat_id);
+        }
+        Ok(())
+    }
+
+    /// Saves the peerstate to the database.
+    ///
+    /// Returns the chats whose cached encryption state is reset.
+    async fn save_to_sql(&self, sql: &Sql, old_addr: Option<&str>) -> Result<Vec<ChatId>> {
         let trans_fn = |t: &mut rusqlite::Transaction| {
             let verified_key_fingerprint =
                 self.verified_key_fingerprint.as_ref().map(|fp| fp.hex());
@@ -620,7 +640,22 @@ pub(crate) async fn save_to_db_ex(&self, sql: &Sql, old_addr: Option<&str>) -> R
                     &self.addr,
                 ),
             )?;
-            Ok(())
+            // Chats with this contact may become encryptable or verified.
+            let mut reset_chat_ids = Vec::new();
+            for addr in std::iter::once(self.addr.as_str()).chain(old_addr) {
+                let mut stmt = t.prepare(
+                    "UPDATE chats SET encryption_state=NULL
+                     WHERE encryption_state IS NOT NULL
+                     AND id IN (SELECT cc.chat_id FROM chats_contacts cc
+                                INNER JOIN contacts c ON c.id=cc.contact_id
+                                WHERE c.addr=? COLLATE NOCASE)
+                     RETURNING id",
+                )?;
+                for chat_id in stmt.query_map((addr,), |row| row.get::<_, ChatId>(0))? {
+                    reset_chat_ids.push(chat_id?);
+                }
+            }
+            Ok(reset_chat_ids)
         };
         sql.transaction(trans_fn).await
     }
@@ -854,14 +889,121 @@ pub(crate) async fn maybe_do_aeap_transition(
         )?;
         peerstate.apply_header(context, header, mime_parser.timestamp_sent);
 
-        peerstate
-            .save_to_db_ex(&context.sql, Some(&old_addr))
-            .await?;
+        peerstate.save_to_db_ex(context, Some(&old_addr)).await?;
     }
 
     Ok(())
 }
 
+/// Maximum number of verifiers followed to build [`PeerstateInfo::verifier_chain`].
+const MAX_VERIFIER_CHAIN_LEN: usize = 10;
+
+/// Peerstate of a contact for debugging encryption issues,
+/// see [`get_peerstate_info`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PeerstateInfo {
+    /// E-mail address of the contact.
+    pub addr: String,
+
+    /// Encryption preference of the contact.
+    pub prefer_encrypt: String,
+
+    /// Timestamp of the latest peerstate update.
+    pub last_seen: i64,
+
+    /// Timestamp of the latest `Autocrypt` header reception.
+    pub last_seen_autocrypt: i64,
+
+    /// Timestamp of the latest `Autocrypt-Gossip` header reception.
+    pub gossip_timestamp: i64,
+
+    /// Fingerprint of the key received in the `Autocrypt` header.
+    pub public_key_fingerprint: Option<String>,
+
+    /// Fingerprint of the key received in the `Autocrypt-Gossip` header.
+    pub gossip_key_fingerprint: Option<String>,
+
+    /// Fingerprint of the verified key.
+    pub verified_key_fingerprint: Option<String>,
+
+    /// Fingerprint of the secondary verified key.
+    pub secondary_verified_key_fingerprint: Option<String>,
+
+    /// Address that introduced the verified key.
+    pub verifier: Option<String>,
+
+    /// Address that introduced the secondary verified key.
+    pub secondary_verifier: Option<String>,
+
+    /// Addresses that introduced the verified key, the direct verifier first.
+    ///
+    /// Each address is followed by the address that verified it in turn.
+    /// The chain ends with the contact verified by ourselves.
+    pub verifier_chain: Vec<String>,
+
+    /// Whether we think the contact knows our key as verified.
+    pub backward_verified: bool,
+
+    /// Whether the key used for opportunistic encryption changed.
+    pub fingerprint_changed: bool,
+}
+
+/// Returns the peerstate of a contact, `None` if there is no peerstate.
+pub async fn get_peerstate_info(
+    context: &Context,
+    contact_id: ContactId,
+) -> Result<Option<PeerstateInfo>> {
+    ensure!(
+        !contact_id.is_special(),
+        "Can not provide peerstate for special contact"
+    );
+    let contact = Contact::get_by_id(context, contact_id).await?;
+    let Some(peerstate) = Peerstate::from_addr(context, contact.get_addr()).await? else {
+        return Ok(None);
+    };
+
+    let mut verifier_chain: Vec<String> = Vec::new();
+    let mut addr = peerstate.addr.clone();
+    let mut verifier = peerstate.verifier.clone();
+    while let Some(verifier_addr) = verifier.take() {
+        // The verifier is the contact itself if we verified it directly.
+        if addr_cmp(&verifier_addr, &addr)
+            || verifier_chain.len() >= MAX_VERIFIER_CHAIN_LEN
+            || verifier_chain
+                .iter()
+                .any(|chain_addr| addr_cmp(chain_addr, &verifier_addr))
+        {
+            break;
+        }
+        verifier_chain.push(verifier_addr.clone());
+        verifier = Peerstate::from_addr(context, &verifier_addr)
+            .await?
+            .and_then(|peerstate| peerstate.verifier);
+        addr = verifier_addr;
+    }
+
+    let fingerprint_hex =
+        |fingerprint: &Option<Fingerprint>| fingerprint.as_ref().map(|fp| fp.hex());
+    Ok(Some(PeerstateInfo {
+        prefer_encrypt: peerstate.prefer_encrypt.to_string(),
+        last_seen: peerstate.last_seen,
+        last_seen_autocrypt: peerstate.last_seen_autocrypt,
+        gossip_timestamp: peerstate.gossip_timestamp,
+        public_key_fingerprint: fingerprint_hex(&peerstate.public_key_fingerprint),
+        gossip_key_fingerprint: fingerprint_hex(&peerstate.gossip_key_fingerprint),
+        verified_key_fingerprint: fingerprint_hex(&peerstate.verified_key_fingerprint),
+        secondary_verified_key_fingerprint: fingerprint_hex(
+            &peerstate.secondary_verified_key_fingerprint,
+        ),
+        verifier: peerstate.verifier,
+        secondary_verifier: peerstate.secondary_verifier,
+        verifier_chain,
+        backward_verified: peerstate.backward_verified_key_id.is_some(),
+        fingerprint_changed: peerstate.fingerprint_changed,
+        addr: peerstate.addr,
+    }))
+}
+
 /// Type of the peerstate change.
 ///
 /// Changes to the peerstate are notified to the user via a message
@@ -1072,4 +1214,43 @@ async fn test_peerstate_degrade_reordering() {
         peerstate.apply_header(&ctx, &header, 300);
         assert_eq!(peerstate.prefer_encrypt, EncryptPreference::Mutual);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_peerstate_info() -> Result<()> {
+        let t = crate::test_utils::TestContext::new_alice().await;
+        let pub_key = alice_keypair().public;
+        let bob_id = Contact::create(&t, "Bob", "bob@example.net").await?;
+        assert_eq!(get_peerstate_info(&t, bob_id).await?, None);
+        assert!(get_peerstate_info(&t, ContactId::SELF).await.is_err());
+
+        // Bob is verified by Claire, who is verified directly.
+        for (addr, verifier) in [
+            ("bob@example.net", "claire@example.org"),
+            ("claire@example.org", "claire@example.org"),
+        ] {
+            let mut peerstate =
+                Peerstate::from_public_key(addr, 10, EncryptPreference::Mutual, &pub_key);
+            peerstate.set_verified(
+                pub_key.clone(),
+                pub_key.dc_fingerprint(),
+                verifier.to_string(),
+            )?;
+            peerstate.save_to_db(&t.sql).await?;
+        }
+
+        let info = get_peerstate_info(&t, bob_id).await?.unwrap();
+        assert_eq!(info.addr, "bob@example.net");
+        assert_eq!(info.prefer_encrypt, "mutual");
+        assert_eq!(info.last_seen_autocrypt, 10);
+        assert_eq!(
+            info.public_key_fingerprint,
+            Some(pub_key.dc_fingerprint().hex())
+        );
+        assert_eq!(info.verified_key_fingerprint, info.public_key_fingerprint);
+        assert_eq!(info.gossip_key_fingerprint, None);
+        assert_eq!(info.verifier, Some("claire@example.org".to_string()));
+        assert_eq!(info.verifier_chain, vec!["claire@example.org".to_string()]);
+        assert!(!info.backward_verified);
+        Ok(())
+    }
 }
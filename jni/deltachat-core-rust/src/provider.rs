@@ -1,6 +1,7 @@
 //! [Provider database](https://providers.delta.chat/) module.
 
 pub(crate) mod data;
+pub(crate) mod status;
 
 use anyhow::Result;
 use deltachat_contact_tools::EmailAddress;
@@ -148,6 +149,10 @@ pub struct ProviderOptions {
 
     /// Move messages to the Trash folder instead of marking them "\Deleted".
     pub delete_to_trash: bool,
+
+    /// URL of a machine-readable status endpoint reporting incidents,
+    /// checked on repeated connection failures.
+    pub status_url: Option<&'static str>,
 }
 
 impl ProviderOptions {
@@ -156,6 +161,7 @@ const fn new() -> Self {
             strict_tls: true,
             max_smtp_rcpt_to: None,
             delete_to_trash: false,
+            status_url: None,
         }
     }
 }
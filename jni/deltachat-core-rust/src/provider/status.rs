@@ -0,0 +1,187 @@
+//! # Provider status feed.
+//!
+//! Providers in the database may reference a machine-readable status endpoint
+//! with [`ProviderOptions::status_url`](crate::provider::ProviderOptions::status_url).
+//! When connecting to the server fails repeatedly, the endpoint is checked
+//! and [`EventType::ProviderIncident`] is emitted for every incident not reported yet,
+//! so the user can tell whether the problem is on their side or on the server side.
+//!
+//! The endpoint returns JSON in the following format:
+//!
+//! ```json
+//! {
+//!   "incidents": [
+//!     {
+//!       "id": "2024-05-01-imap",
+//!       "title": "IMAP outage",
+//!       "description": "Some users cannot fetch messages.",
+//!       "url": "https://status.example.org/2024-05-01-imap"
+//!     }
+//!   ]
+//! }
+//! ```
+
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+
+use crate::context::Context;
+use crate::events::EventType;
+use crate::log::LogExt;
+use crate::net::http::read_url;
+use crate::tools::time;
+
+/// Number of consecutive connection failures after which the status endpoint is checked.
+pub(crate) const CONNECTION_FAILURES_BEFORE_CHECK: u32 = 3;
+
+/// Minimum interval between two checks of the status endpoint, in seconds.
+const CHECK_INTERVAL: i64 = 30 * 60;
+
+/// Raw config key storing the timestamp of the last check.
+const LAST_CHECK_KEY: &str = "provider_status_checked";
+
+/// Raw config key storing the space-separated IDs of the incidents reported already.
+const REPORTED_KEY: &str = "provider_incidents_reported";
+
+#[derive(Debug, Default, Deserialize)]
+struct StatusFeed {
+    #[serde(default)]
+    incidents: Vec<Incident>,
+}
+
+/// Incident reported by the provider status endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Incident {
+    /// Unique ID of the incident.
+    id: String,
+
+    /// Short summary.
+    title: String,
+
+    /// Longer description.
+    #[serde(default)]
+    description: String,
+
+    /// Link to the incident page.
+    #[serde(default)]
+    url: Option<String>,
+}
+
+impl Incident {
+    /// Returns human-readable details to be shown to the user.
+    fn details(&self) -> String {
+        match &self.url {
+            Some(url) if !self.description.is_empty() => format!("{}\n\n{url}", self.description),
+            Some(url) => url.clone(),
+            None => self.description.clone(),
+        }
+    }
+}
+
+/// Parses the status feed and returns the incidents not contained in `reported`.
+fn new_incidents(feed: &str, reported: &[&str]) -> Result<Vec<Incident>> {
+    let feed: StatusFeed = serde_json::from_str(feed).context("Invalid provider status feed")?;
+    Ok(feed
+        .incidents
+        .into_iter()
+        .filter(|incident| !incident.id.contains(char::is_whitespace))
+        .filter(|incident| !reported.contains(&incident.id.as_str()))
+        .collect())
+}
+
+/// Checks the status endpoint of the configured provider in the background
+/// unless it was checked recently.
+///
+/// Called after [`CONNECTION_FAILURES_BEFORE_CHECK`] consecutive connection failures.
+pub(crate) async fn maybe_check(context: &Context) -> Result<()> {
+    let Some(status_url) = context
+        .get_configured_provider()
+        .await?
+        .and_then(|provider| provider.opt.status_url)
+    else {
+        return Ok(());
+    };
+    let now = time();
+    let last_check = context
+        .sql
+        .get_raw_config_int64(LAST_CHECK_KEY)
+        .await?
+        .unwrap_or_default();
+    if last_check <= now && now < last_check.saturating_add(CHECK_INTERVAL) {
+        return Ok(());
+    }
+    context
+        .sql
+        .set_raw_config_int64(LAST_CHECK_KEY, now)
+        .await?;
+
+    let context = context.clone();
+    tokio::spawn(async move {
+        check(&context, status_url).await.log_err(&context).ok();
+    });
+    Ok(())
+}
+
+async fn check(context: &Context, status_url: &str) -> Result<()> {
+    info!(context, "Checking provider status at {status_url}.");
+    let feed = read_url(context, status_url)
+        .await
+        .context("Failed to read provider status")?;
+    let reported = context
+        .sql
+        .get_raw_config(REPORTED_KEY)
+        .await?
+        .unwrap_or_default();
+    let reported: Vec<&str> = reported.split_whitespace().collect();
+    let incidents = new_incidents(&feed, &reported)?;
+    if incidents.is_empty() {
+        return Ok(());
+    }
+
+    let mut ids: Vec<&str> = reported;
+    ids.extend(incidents.iter().map(|incident| incident.id.as_str()));
+    // Keep the list short, old incidents are unlikely to be reported again.
+    let ids = ids.split_off(ids.len().saturating_sub(50));
+    context
+        .sql
+        .set_raw_config(REPORTED_KEY, Some(&ids.join(" ")))
+        .await?;
+    for incident in &incidents {
+        warn!(context, "Provider reports incident: {}.", incident.title);
+        context.emit_event(EventType::ProviderIncident {
+            title: incident.title.clone(),
+            details: incident.details(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_incidents() -> Result<()> {
+        let feed = r#"{
+            "incidents": [
+                {"id": "a", "title": "IMAP outage", "description": "Cannot fetch.", "url": "https://status.example.org/a"},
+                {"id": "b", "title": "Slow SMTP"},
+                {"id": "c d", "title": "Invalid ID"}
+            ]
+        }"#;
+        let incidents = new_incidents(feed, &[])?;
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(
+            incidents[0].details(),
+            "Cannot fetch.\n\nhttps://status.example.org/a"
+        );
+        assert_eq!(incidents[1].details(), "");
+
+        let incidents = new_incidents(feed, &["a"])?;
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].title, "Slow SMTP");
+
+        assert!(new_incidents("{}", &[])?.is_empty());
+        assert!(new_incidents("<html>", &[]).is_err());
+        Ok(())
+    }
+}
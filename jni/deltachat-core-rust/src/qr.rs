@@ -2,17 +2,19 @@
 
 mod dclogin_scheme;
 use std::collections::BTreeMap;
+use std::net::SocketAddr;
 
 use anyhow::{anyhow, bail, ensure, Context as _, Result};
 pub use dclogin_scheme::LoginOptions;
 use deltachat_contact_tools::{addr_normalize, may_be_valid_addr, ContactAddress};
 use once_cell::sync::Lazy;
 use percent_encoding::{percent_decode_str, percent_encode, NON_ALPHANUMERIC};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use self::dclogin_scheme::configure_from_login_qr;
 use crate::chat::ChatIdBlocked;
-use crate::config::Config;
+use crate::config::{from_bool, Config};
+use crate::config_audit::ConfigOrigin;
 use crate::constants::Blocked;
 use crate::contact::{Contact, ContactId, Origin};
 use crate::context::Context;
@@ -42,6 +44,34 @@
 /// Backup transfer based on iroh-net.
 pub(crate) const DCBACKUP2_SCHEME: &str = "DCBACKUP2:";
 
+/// Key of the [`BackupHints`] in the node address JSON of a [`DCBACKUP2_SCHEME`] QR code.
+///
+/// Older versions ignore unknown keys when parsing the node address.
+const BACKUP_HINTS_KEY: &str = "hints";
+
+/// Network bootstrap hints for the backup transfer.
+///
+/// They help to find the providing device on networks
+/// where connecting to the addresses of the iroh node address fails,
+/// e.g. on a Wi-Fi hotspot opened by one of the devices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupHints {
+    /// Name of the mDNS service the provider announces itself on, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdns_service: Option<String>,
+
+    /// Additional addresses the provider may be reachable at.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub direct_addrs: Vec<SocketAddr>,
+}
+
+impl BackupHints {
+    /// Returns true if there are no hints.
+    pub fn is_empty(&self) -> bool {
+        self.mdns_service.is_none() && self.direct_addrs.is_empty()
+    }
+}
+
 /// Scanned QR code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Qr {
@@ -116,6 +146,9 @@ pub enum Qr {
 
         /// Authentication token.
         auth_token: String,
+
+        /// Network bootstrap hints.
+        hints: BackupHints,
     },
 
     /// Ask the user if they want to use the given service for video chats.
@@ -357,8 +390,15 @@ pub fn format_backup(qr: &Qr) -> Result<String> {
         Qr::Backup2 {
             ref node_addr,
             ref auth_token,
+            ref hints,
         } => {
-            let node_addr = serde_json::to_string(node_addr)?;
+            let mut node_addr = serde_json::to_value(node_addr)?;
+            if !hints.is_empty() {
+                node_addr
+                    .as_object_mut()
+                    .context("Node address is not a JSON object")?
+                    .insert(BACKUP_HINTS_KEY.to_string(), serde_json::to_value(hints)?);
+            }
             Ok(format!("{DCBACKUP2_SCHEME}{auth_token}&{node_addr}"))
         }
         _ => Err(anyhow!("Not a backup QR code")),
@@ -386,16 +426,7 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
         .parse()
         .context("Failed to parse fingerprint in the QR code")?;
 
-    let param: BTreeMap<&str, &str> = fragment
-        .split('&')
-        .filter_map(|s| {
-            if let [key, value] = s.splitn(2, '=').collect::<Vec<_>>()[..] {
-                Some((key, value))
-            } else {
-                None
-            }
-        })
-        .collect();
+    let param = parse_fragment_params(fragment);
 
     let addr = if let Some(addr) = param.get("a") {
         Some(normalize_address(addr)?)
@@ -404,11 +435,7 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
     };
 
     let name = if let Some(encoded_name) = param.get("n") {
-        let encoded_name = encoded_name.replace('+', "%20"); // sometimes spaces are encoded as `+`
-        match percent_decode_str(&encoded_name).decode_utf8() {
-            Ok(name) => name.to_string(),
-            Err(err) => bail!("Invalid name: {}", err),
-        }
+        decode_name(encoded_name).context("Invalid name")?
     } else {
         "".to_string()
     };
@@ -428,11 +455,7 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
 
     let grpname = if grpid.is_some() {
         if let Some(encoded_name) = param.get("g") {
-            let encoded_name = encoded_name.replace('+', "%20"); // sometimes spaces are encoded as `+`
-            match percent_decode_str(&encoded_name).decode_utf8() {
-                Ok(name) => Some(name.to_string()),
-                Err(err) => bail!("Invalid group name: {}", err),
-            }
+            Some(decode_name(encoded_name).context("Invalid group name")?)
         } else {
             None
         }
@@ -535,6 +558,97 @@ async fn decode_openpgp(context: &Context, qr: &str) -> Result<Qr> {
     }
 }
 
+/// Splits `key=value` pairs separated by `&`.
+fn parse_fragment_params(fragment: &str) -> BTreeMap<&str, &str> {
+    fragment
+        .split('&')
+        .filter_map(|s| {
+            if let [key, value] = s.splitn(2, '=').collect::<Vec<_>>()[..] {
+                Some((key, value))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Decodes a percent-encoded name from a QR code parameter.
+fn decode_name(encoded_name: &str) -> Result<String> {
+    let encoded_name = encoded_name.replace('+', "%20"); // sometimes spaces are encoded as `+`
+    let name = percent_decode_str(&encoded_name).decode_utf8()?;
+    Ok(name.to_string())
+}
+
+/// Preview of an invite link.
+///
+/// The preview is decoded from the link alone,
+/// so it can be displayed before the user decides to join
+/// and without adding the inviter to the contact list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InviteLinkPreview {
+    /// Address of the inviter.
+    pub inviter_addr: String,
+
+    /// Name of the inviter, empty if the link does not contain it.
+    pub inviter_name: String,
+
+    /// Group name, `None` if the link is not a group invite.
+    pub grpname: Option<String>,
+
+    /// Number of group members at the time the link was generated,
+    /// `None` if the link does not contain it.
+    pub member_count: Option<u32>,
+}
+
+/// Returns the preview metadata of an invite link
+/// as generated by [`crate::securejoin::get_group_invite_link`]
+/// or [`crate::securejoin::get_securejoin_qr`].
+///
+/// Unlike [`check_qr`], this does not need a context and does not modify the database.
+/// Use [`check_qr`] to join.
+pub fn get_invite_link_preview(link: &str) -> Result<InviteLinkPreview> {
+    let link = link.trim();
+    let fragment = if let Some(payload) = link
+        .strip_prefix(IDELTACHAT_SCHEME)
+        .or_else(|| link.strip_prefix(IDELTACHAT_NOSLASH_SCHEME))
+    {
+        payload
+            .split_once('&')
+            .map(|(_fingerprint, fragment)| fragment)
+    } else if starts_with_ignore_case(link, OPENPGP4FPR_SCHEME) {
+        let payload = link
+            .get(OPENPGP4FPR_SCHEME.len()..)
+            .context("Invalid OPENPGP4FPR scheme")?;
+        payload
+            .split_once('#')
+            .or_else(|| payload.split_once("%23"))
+            .map(|(_fingerprint, fragment)| fragment)
+    } else {
+        bail!("Not an invite link");
+    };
+    let param = parse_fragment_params(fragment.unwrap_or_default());
+
+    let inviter_addr = normalize_address(param.get("a").context("Invite link has no address")?)?;
+    let inviter_name = match param.get("n") {
+        Some(encoded_name) => decode_name(encoded_name).context("Invalid name")?,
+        None => "".to_string(),
+    };
+    let grpname = match (param.get("x"), param.get("g")) {
+        (Some(grpid), Some(encoded_name)) if validate_id(grpid) => {
+            Some(decode_name(encoded_name).context("Invalid group name")?)
+        }
+        _ => None,
+    };
+    let member_count = param.get("m").and_then(|m| m.parse().ok());
+
+    Ok(InviteLinkPreview {
+        inviter_addr,
+        inviter_name,
+        grpname,
+        member_count,
+    })
+}
+
 /// scheme: `https://i.delta.chat[/]#FINGERPRINT&a=ADDR[&OPTIONAL_PARAMS]`
 async fn decode_ideltachat(context: &Context, prefix: &str, qr: &str) -> Result<Qr> {
     let qr = qr.replacen(prefix, OPENPGP4FPR_SCHEME, 1);
@@ -644,12 +758,22 @@ fn decode_backup2(qr: &str) -> Result<Qr> {
         .split_once('&')
         .context("Backup QR code has no separator")?;
     let auth_token = auth_token.to_string();
-    let node_addr = serde_json::from_str::<iroh_net::NodeAddr>(node_addr)
+    let mut node_addr = serde_json::from_str::<serde_json::Value>(node_addr)
+        .context("Invalid node addr in backup QR code")?;
+    let hints = match node_addr
+        .as_object_mut()
+        .and_then(|node_addr| node_addr.remove(BACKUP_HINTS_KEY))
+    {
+        Some(hints) => serde_json::from_value(hints).context("Invalid hints in backup QR code")?,
+        None => BackupHints::default(),
+    };
+    let node_addr = serde_json::from_value::<iroh_net::NodeAddr>(node_addr)
         .context("Invalid node addr in backup QR code")?;
 
     Ok(Qr::Backup2 {
         node_addr,
         auth_token,
+        hints,
     })
 }
 
@@ -686,10 +810,10 @@ async fn set_account_from_qr(context: &Context, qr: &str) -> Result<()> {
                 format!("Cannot create account, response is malformed:\n{response_text:?}")
             })?;
         context
-            .set_config_internal(Config::Addr, Some(&email))
+            .set_config_from(ConfigOrigin::Qr, Config::Addr, Some(&email))
             .await?;
         context
-            .set_config_internal(Config::MailPw, Some(&password))
+            .set_config_from(ConfigOrigin::Qr, Config::MailPw, Some(&password))
             .await?;
 
         Ok(())
@@ -718,7 +842,11 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
             instance_pattern,
         } => {
             context
-                .set_config_internal(Config::WebrtcInstance, Some(&instance_pattern))
+                .set_config_from(
+                    ConfigOrigin::Qr,
+                    Config::WebrtcInstance,
+                    Some(&instance_pattern),
+                )
                 .await?;
         }
         Qr::Proxy { url, .. } => {
@@ -738,9 +866,15 @@ pub async fn set_config_from_qr(context: &Context, qr: &str) -> Result<()> {
                 )
                 .collect();
             context
-                .set_config(Config::ProxyUrl, Some(&proxy_urls.join("\n")))
+                .set_config_from(
+                    ConfigOrigin::Qr,
+                    Config::ProxyUrl,
+                    Some(&proxy_urls.join("\n")),
+                )
+                .await?;
+            context
+                .set_config_from(ConfigOrigin::Qr, Config::ProxyEnabled, from_bool(true))
                 .await?;
-            context.set_config_bool(Config::ProxyEnabled, true).await?;
         }
         Qr::WithdrawVerifyContact {
             invitenumber,
@@ -1178,6 +1312,36 @@ async fn test_decode_ideltachat_link() -> Result<()> {
         Ok(())
     }
 
+    #[test]
+    fn test_get_invite_link_preview() -> Result<()> {
+        let preview = get_invite_link_preview(
+            "https://i.delta.chat/#79252762C34C5096AF57958F4FC3D21A81B0F0A7&a=cli%40deltachat.de&g=test%20%3F+test%20%21&x=h-0oKQf2CDK&i=9JEXlxAqGM0&s=0V7LzL9cxRL&m=12"
+        )?;
+        assert_eq!(preview.inviter_addr, "cli@deltachat.de");
+        assert_eq!(preview.inviter_name, "");
+        assert_eq!(preview.grpname.as_deref(), Some("test ? test !"));
+        assert_eq!(preview.member_count, Some(12));
+
+        let preview = get_invite_link_preview(
+            "https://i.delta.chat#79252762C34C5096AF57958F4FC3D21A81B0F0A7&a=cli%40deltachat.de&n=J%C3%B6rn&i=9JEXlxAqGM0&s=0V7LzL9cxRL"
+        )?;
+        assert_eq!(preview.inviter_name, "Jörn");
+        assert_eq!(preview.grpname, None);
+        assert_eq!(preview.member_count, None);
+
+        let preview = get_invite_link_preview(
+            "OPENPGP4FPR:79252762C34C5096AF57958F4FC3D21A81B0F0A7%23a=cli%40deltachat.de&g=test&x=h-0oKQf2CDK&i=9JEXlxAqGM0&s=0V7LzL9cxRL"
+        )?;
+        assert_eq!(preview.grpname.as_deref(), Some("test"));
+
+        assert!(get_invite_link_preview("https://example.org/#foo&a=cli%40deltachat.de").is_err());
+        assert!(get_invite_link_preview(
+            "https://i.delta.chat/#79252762C34C5096AF57958F4FC3D21A81B0F0A7&g=test"
+        )
+        .is_err());
+        Ok(())
+    }
+
     // macOS and iOS sometimes replace the # with %23 (uri encode it), we should be able to parse this wrong format too.
     // see issue https://github.com/deltachat/deltachat-core-rust/issues/1969 for more info
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -1817,6 +1981,38 @@ async fn test_set_proxy_config_from_qr() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_decode_backup2_hints() -> Result<()> {
+        let ctx = TestContext::new().await;
+        let node_addr = iroh_net::NodeAddr::new(iroh_net::key::SecretKey::generate().public());
+
+        // QR code without hints, as generated by older versions.
+        let qr = Qr::Backup2 {
+            node_addr: node_addr.clone(),
+            auth_token: "token".to_string(),
+            hints: BackupHints::default(),
+        };
+        let text = format_backup(&qr)?;
+        assert!(!text.contains(BACKUP_HINTS_KEY));
+        assert_eq!(check_qr(&ctx, &text).await?, qr);
+
+        let qr = Qr::Backup2 {
+            node_addr,
+            auth_token: "token".to_string(),
+            hints: BackupHints {
+                mdns_service: Some("iroh.local.swarm".to_string()),
+                direct_addrs: vec!["192.168.43.1:1234".parse()?],
+            },
+        };
+        let text = format_backup(&qr)?;
+        assert_eq!(check_qr(&ctx, &text).await?, qr);
+
+        // Hints are ignored when parsing only the node address, as older versions do.
+        let (_, node_addr_json) = text.split_once('&').unwrap();
+        serde_json::from_str::<iroh_net::NodeAddr>(node_addr_json)?;
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_decode_shadowsocks() -> Result<()> {
         let ctx = TestContext::new().await;
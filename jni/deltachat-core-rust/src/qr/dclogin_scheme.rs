@@ -7,6 +7,7 @@
 
 use super::{Qr, DCLOGIN_SCHEME};
 use crate::config::Config;
+use crate::config_audit::ConfigOrigin;
 use crate::context::Context;
 use crate::login_param::EnteredCertificateChecks;
 use crate::provider::Socket;
@@ -163,7 +164,7 @@ pub(crate) async fn configure_from_login_qr(
     options: LoginOptions,
 ) -> Result<()> {
     context
-        .set_config_internal(Config::Addr, Some(address))
+        .set_config_from(ConfigOrigin::Qr, Config::Addr, Some(address))
         .await?;
 
     match options {
@@ -182,26 +183,26 @@ pub(crate) async fn configure_from_login_qr(
             certificate_checks,
         } => {
             context
-                .set_config_internal(Config::MailPw, Some(&mail_pw))
+                .set_config_from(ConfigOrigin::Qr, Config::MailPw, Some(&mail_pw))
                 .await?;
             if let Some(value) = imap_host {
                 context
-                    .set_config_internal(Config::MailServer, Some(&value))
+                    .set_config_from(ConfigOrigin::Qr, Config::MailServer, Some(&value))
                     .await?;
             }
             if let Some(value) = imap_port {
                 context
-                    .set_config_internal(Config::MailPort, Some(&value.to_string()))
+                    .set_config_from(ConfigOrigin::Qr, Config::MailPort, Some(&value.to_string()))
                     .await?;
             }
             if let Some(value) = imap_username {
                 context
-                    .set_config_internal(Config::MailUser, Some(&value))
+                    .set_config_from(ConfigOrigin::Qr, Config::MailUser, Some(&value))
                     .await?;
             }
             if let Some(value) = imap_password {
                 context
-                    .set_config_internal(Config::MailPw, Some(&value))
+                    .set_config_from(ConfigOrigin::Qr, Config::MailPw, Some(&value))
                     .await?;
             }
             if let Some(value) = imap_security {
@@ -209,27 +210,31 @@ pub(crate) async fn configure_from_login_qr(
                     .to_u8()
                     .context("could not convert imap security value to number")?;
                 context
-                    .set_config_internal(Config::MailSecurity, Some(&code.to_string()))
+                    .set_config_from(
+                        ConfigOrigin::Qr,
+                        Config::MailSecurity,
+                        Some(&code.to_string()),
+                    )
                     .await?;
             }
             if let Some(value) = smtp_host {
                 context
-                    .set_config_internal(Config::SendServer, Some(&value))
+                    .set_config_from(ConfigOrigin::Qr, Config::SendServer, Some(&value))
                     .await?;
             }
             if let Some(value) = smtp_port {
                 context
-                    .set_config_internal(Config::SendPort, Some(&value.to_string()))
+                    .set_config_from(ConfigOrigin::Qr, Config::SendPort, Some(&value.to_string()))
                     .await?;
             }
             if let Some(value) = smtp_username {
                 context
-                    .set_config_internal(Config::SendUser, Some(&value))
+                    .set_config_from(ConfigOrigin::Qr, Config::SendUser, Some(&value))
                     .await?;
             }
             if let Some(value) = smtp_password {
                 context
-                    .set_config_internal(Config::SendPw, Some(&value))
+                    .set_config_from(ConfigOrigin::Qr, Config::SendPw, Some(&value))
                     .await?;
             }
             if let Some(value) = smtp_security {
@@ -237,7 +242,11 @@ pub(crate) async fn configure_from_login_qr(
                     .to_u8()
                     .context("could not convert smtp security value to number")?;
                 context
-                    .set_config_internal(Config::SendSecurity, Some(&code.to_string()))
+                    .set_config_from(
+                        ConfigOrigin::Qr,
+                        Config::SendSecurity,
+                        Some(&code.to_string()),
+                    )
                     .await?;
             }
             if let Some(value) = certificate_checks {
@@ -245,10 +254,18 @@ pub(crate) async fn configure_from_login_qr(
                     .to_u32()
                     .context("could not convert certificate checks value to number")?;
                 context
-                    .set_config_internal(Config::ImapCertificateChecks, Some(&code.to_string()))
+                    .set_config_from(
+                        ConfigOrigin::Qr,
+                        Config::ImapCertificateChecks,
+                        Some(&code.to_string()),
+                    )
                     .await?;
                 context
-                    .set_config_internal(Config::SmtpCertificateChecks, Some(&code.to_string()))
+                    .set_config_from(
+                        ConfigOrigin::Qr,
+                        Config::SmtpCertificateChecks,
+                        Some(&code.to_string()),
+                    )
                     .await?;
             }
             Ok(())
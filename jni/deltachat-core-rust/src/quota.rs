@@ -1,18 +1,26 @@
 //! # Support for IMAP QUOTA extension.
+//!
+//! The quota is queried regularly while connected to the server.
+//! The current usage is available with [`Context::get_quota_usage`],
+//! the usage over time with [`Context::get_quota_history`].
+//! If the mailbox gets full, UIs may offer [`Context::cleanup_server_messages`]
+//! to delete old messages from the server.
 
 use std::collections::BTreeMap;
 use std::time::Duration;
 
-use anyhow::{anyhow, Context as _, Result};
-use async_imap::types::{Quota, QuotaResource};
+use anyhow::{anyhow, ensure, Context as _, Result};
+use async_imap::types::{Quota, QuotaResource, QuotaResourceName};
+use serde::Serialize;
 
 use crate::chat::add_device_msg_with_importance;
 use crate::config::Config;
 use crate::context::Context;
 use crate::imap::scan_folders::get_watched_folders;
 use crate::imap::session::Session as ImapSession;
+use crate::log::LogExt;
 use crate::message::Message;
-use crate::tools::{self, time_elapsed};
+use crate::tools::{self, time, time_elapsed};
 use crate::{stock_str, EventType};
 
 /// warn about a nearly full mailbox after this usage percentage is reached.
@@ -33,6 +41,12 @@
 /// providers report bad values and we would then spam the user.
 pub const QUOTA_ALLCLEAR_PERCENTAGE: u64 = 75;
 
+/// Minimum interval between two entries of the quota history, in seconds.
+const QUOTA_HISTORY_INTERVAL: i64 = 60 * 60;
+
+/// Quota history entries are kept for this number of seconds.
+const QUOTA_HISTORY_MAX_AGE: i64 = 90 * 24 * 60 * 60;
+
 /// Server quota information with an update timestamp.
 #[derive(Debug)]
 pub struct QuotaInfo {
@@ -72,7 +86,7 @@ async fn get_unique_quota_roots_and_usage(
     Ok(unique_quota_roots)
 }
 
-fn get_highest_usage<'t>(
+pub(crate) fn get_highest_usage<'t>(
     unique_quota_roots: &'t BTreeMap<String, Vec<QuotaResource>>,
 ) -> Result<(u64, &'t String, &'t QuotaResource)> {
     let mut highest: Option<(u64, &'t String, &QuotaResource)> = None;
@@ -95,6 +109,96 @@ fn get_highest_usage<'t>(
     highest.context("no quota_resource found, this is unexpected")
 }
 
+/// Usage of a single quota resource.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuotaResourceUsage {
+    /// Name of the quota root, often empty.
+    pub root: String,
+
+    /// Name of the resource, e.g. `STORAGE` or `MESSAGE`.
+    pub name: String,
+
+    /// Current usage, in bytes for `STORAGE`.
+    pub usage: u64,
+
+    /// Limit, in bytes for `STORAGE`.
+    pub limit: u64,
+
+    /// Usage in percent of the limit.
+    pub percent: u64,
+}
+
+/// Quota usage as reported by the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuotaUsage {
+    /// Usage of all resources of all quota roots.
+    pub resources: Vec<QuotaResourceUsage>,
+
+    /// Highest usage of all resources in percent.
+    pub highest_percent: u64,
+
+    /// Timestamp of the query.
+    pub timestamp: i64,
+
+    /// True if the usage is above [`QUOTA_WARN_THRESHOLD_PERCENTAGE`]
+    /// and the UI should suggest [`Context::cleanup_server_messages`].
+    pub cleanup_suggested: bool,
+}
+
+/// Quota usage at some point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct QuotaHistoryEntry {
+    /// Timestamp of the query.
+    pub timestamp: i64,
+
+    /// Highest usage of all resources in percent.
+    pub highest_percent: u64,
+
+    /// Used storage in bytes, `None` if the server has no storage quota.
+    pub storage_usage: Option<u64>,
+
+    /// Storage limit in bytes, `None` if the server has no storage quota.
+    pub storage_limit: Option<u64>,
+}
+
+/// Returns usage and limit of the resource, converting kilobytes of storage to bytes.
+fn usage_and_limit(resource: &QuotaResource) -> (u64, u64) {
+    match resource.name {
+        QuotaResourceName::Storage => (
+            resource.usage.saturating_mul(1024),
+            resource.limit.saturating_mul(1024),
+        ),
+        _ => (resource.usage, resource.limit),
+    }
+}
+
+fn resource_name(name: &QuotaResourceName) -> String {
+    match name {
+        QuotaResourceName::Atom(name) => name.to_string(),
+        QuotaResourceName::Storage => "STORAGE".to_string(),
+        QuotaResourceName::Message => "MESSAGE".to_string(),
+    }
+}
+
+/// Converts quota roots to a flat list of resource usages.
+fn quota_usage(roots: &BTreeMap<String, Vec<QuotaResource>>) -> Vec<QuotaResourceUsage> {
+    roots
+        .iter()
+        .flat_map(|(root, resources)| {
+            resources.iter().map(move |resource| {
+                let (usage, limit) = usage_and_limit(resource);
+                QuotaResourceUsage {
+                    root: root.clone(),
+                    name: resource_name(&resource.name),
+                    usage,
+                    limit,
+                    percent: resource.get_usage_percentage(),
+                }
+            })
+        })
+        .collect()
+}
+
 /// Checks if a quota warning is needed.
 pub fn needs_quota_warning(curr_percentage: u64, warned_at_percentage: u64) -> bool {
     (curr_percentage >= QUOTA_WARN_THRESHOLD_PERCENTAGE
@@ -133,6 +237,11 @@ pub(crate) async fn update_recent_quota(&self, session: &mut ImapSession) -> Res
         if let Ok(quota) = &quota {
             match get_highest_usage(quota) {
                 Ok((highest, _, _)) => {
+                    self.add_quota_history(quota, highest)
+                        .await
+                        .context("Failed to add quota history")
+                        .log_err(self)
+                        .ok();
                     if needs_quota_warning(
                         highest,
                         self.get_config_int(Config::QuotaExceeding).await? as u64,
@@ -162,12 +271,171 @@ pub(crate) async fn update_recent_quota(&self, session: &mut ImapSession) -> Res
         self.emit_event(EventType::ConnectivityChanged);
         Ok(())
     }
+
+    /// Adds an entry to the quota history
+    /// unless an entry was added recently.
+    async fn add_quota_history(
+        &self,
+        roots: &BTreeMap<String, Vec<QuotaResource>>,
+        highest: u64,
+    ) -> Result<()> {
+        let now = time();
+        let last: Option<i64> = self
+            .sql
+            .query_get_value(
+                "SELECT timestamp FROM quota_history ORDER BY timestamp DESC LIMIT 1",
+                (),
+            )
+            .await?;
+        if let Some(last) = last {
+            if last <= now && now < last.saturating_add(QUOTA_HISTORY_INTERVAL) {
+                return Ok(());
+            }
+        }
+        let storage = roots
+            .values()
+            .flatten()
+            .find(|resource| matches!(resource.name, QuotaResourceName::Storage))
+            .map(usage_and_limit);
+        self.sql
+            .transaction(move |transaction| {
+                transaction.execute(
+                    "INSERT INTO quota_history (timestamp, highest_percent, storage_usage, storage_limit)
+                     VALUES (?, ?, ?, ?)",
+                    (
+                        now,
+                        highest,
+                        storage.map(|(usage, _)| usage),
+                        storage.map(|(_, limit)| limit),
+                    ),
+                )?;
+                transaction.execute(
+                    "DELETE FROM quota_history WHERE timestamp<?",
+                    (now.saturating_sub(QUOTA_HISTORY_MAX_AGE),),
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Returns the quota usage as reported by the server recently.
+    ///
+    /// Returns `None` if the quota was not queried yet
+    /// and an error if the server does not support quota.
+    pub async fn get_quota_usage(&self) -> Result<Option<QuotaUsage>> {
+        let quota = self.quota.read().await;
+        let Some(quota) = quota.as_ref() else {
+            return Ok(None);
+        };
+        let roots = match &quota.recent {
+            Ok(roots) => roots,
+            Err(err) => return Err(anyhow!("{err:#}")),
+        };
+        let highest_percent = get_highest_usage(roots)
+            .map(|(highest, _, _)| highest)
+            .unwrap_or_default();
+        let timestamp = quota
+            .modified
+            .duration_since(tools::Time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        Ok(Some(QuotaUsage {
+            resources: quota_usage(roots),
+            highest_percent,
+            timestamp,
+            cleanup_suggested: highest_percent >= QUOTA_WARN_THRESHOLD_PERCENTAGE,
+        }))
+    }
+
+    /// Returns the quota usage recorded since the timestamp `since`, oldest first.
+    ///
+    /// At most one entry per hour is recorded and entries are kept for 90 days.
+    pub async fn get_quota_history(&self, since: i64) -> Result<Vec<QuotaHistoryEntry>> {
+        self.sql
+            .query_map(
+                "SELECT timestamp, highest_percent, storage_usage, storage_limit
+                 FROM quota_history WHERE timestamp>=? ORDER BY timestamp",
+                (since,),
+                |row| {
+                    Ok(QuotaHistoryEntry {
+                        timestamp: row.get(0)?,
+                        highest_percent: row.get(1)?,
+                        storage_usage: row.get(2)?,
+                        storage_limit: row.get(3)?,
+                    })
+                },
+                |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+            )
+            .await
+    }
+
+    /// Returns the folder cleaned up by [`Context::cleanup_server_messages`].
+    ///
+    /// This is the DeltaChat folder if it is used, otherwise the inbox.
+    async fn cleanup_folder(&self) -> Result<String> {
+        if let Some(mvbox) = self.get_config(Config::ConfiguredMvboxFolder).await? {
+            if self.get_config_bool(Config::MvboxMove).await? {
+                return Ok(mvbox);
+            }
+        }
+        self.get_config(Config::ConfiguredInboxFolder)
+            .await?
+            .context("No configured inbox folder")
+    }
+
+    /// Returns the number of messages [`Context::cleanup_server_messages`]
+    /// would delete from the server.
+    pub async fn estimate_server_cleanup(&self, older_than_days: u32) -> Result<usize> {
+        let folder = self.cleanup_folder().await?;
+        let threshold = time().saturating_sub(i64::from(older_than_days) * 24 * 60 * 60);
+        self.sql
+            .count(
+                "SELECT COUNT(*) FROM imap
+                 WHERE folder=? AND target!=''
+                 AND rfc724_mid IN (SELECT rfc724_mid FROM msgs WHERE timestamp<?)",
+                (folder, threshold),
+            )
+            .await
+    }
+
+    /// Deletes messages older than `older_than_days` days
+    /// from the DeltaChat folder on the server, or from the inbox if it is not used,
+    /// to free space when the quota is nearly exceeded.
+    ///
+    /// Messages are kept on the device.
+    /// Only messages known to the device are deleted.
+    /// Returns the number of messages scheduled for deletion.
+    pub async fn cleanup_server_messages(&self, older_than_days: u32) -> Result<usize> {
+        ensure!(
+            !self.get_config_bool(Config::LegalHold).await?,
+            "Messages cannot be deleted while legal hold is enabled"
+        );
+        let folder = self.cleanup_folder().await?;
+        let threshold = time().saturating_sub(i64::from(older_than_days) * 24 * 60 * 60);
+        // Delete messages instead of moving them to the trash
+        // as the trash folder also counts towards the quota.
+        let count = self
+            .sql
+            .execute(
+                "UPDATE imap SET target=''
+                 WHERE folder=? AND target!=''
+                 AND rfc724_mid IN (SELECT rfc724_mid FROM msgs WHERE timestamp<?)",
+                (&folder, threshold),
+            )
+            .await?;
+        info!(
+            self,
+            "Scheduled {count} messages older than {older_than_days} days in {folder} for deletion."
+        );
+        self.scheduler.interrupt_inbox().await;
+        Ok(count)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::TestContextManager;
+    use crate::test_utils::{TestContext, TestContextManager};
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_needs_quota_warning() -> Result<()> {
@@ -225,4 +493,100 @@ async fn test_quota_needs_update() -> Result<()> {
         assert!(t.quota_needs_update(TIMEOUT).await);
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_quota_usage_and_history() -> Result<()> {
+        let t = &TestContext::new_alice().await;
+        assert_eq!(t.get_quota_usage().await?, None);
+
+        let roots = BTreeMap::from([(
+            "".to_string(),
+            vec![
+                QuotaResource {
+                    name: QuotaResourceName::Storage,
+                    usage: 850,
+                    limit: 1000,
+                },
+                QuotaResource {
+                    name: QuotaResourceName::Message,
+                    usage: 10,
+                    limit: 100,
+                },
+            ],
+        )]);
+        t.add_quota_history(&roots, 85).await?;
+        // Entries are added at most once an hour.
+        t.add_quota_history(&roots, 86).await?;
+        *t.quota.write().await = Some(QuotaInfo {
+            recent: Ok(roots),
+            modified: tools::Time::now(),
+        });
+
+        let usage = t.get_quota_usage().await?.unwrap();
+        assert_eq!(usage.highest_percent, 85);
+        assert!(usage.cleanup_suggested);
+        assert_eq!(
+            usage.resources[0],
+            QuotaResourceUsage {
+                root: "".to_string(),
+                name: "STORAGE".to_string(),
+                usage: 850 * 1024,
+                limit: 1000 * 1024,
+                percent: 85,
+            }
+        );
+        assert_eq!(usage.resources[1].name, "MESSAGE");
+        assert_eq!(usage.resources[1].percent, 10);
+
+        let history = t.get_quota_history(0).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].highest_percent, 85);
+        assert_eq!(history[0].storage_usage, Some(850 * 1024));
+        assert_eq!(history[0].storage_limit, Some(1000 * 1024));
+        assert!(t.get_quota_history(time() + 1).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cleanup_server_messages() -> Result<()> {
+        let t = &TestContext::new_alice().await;
+        const DAY: i64 = 24 * 60 * 60;
+        let now = time();
+        for (id, age) in [(1000, 100 * DAY), (1010, 40 * DAY), (1020, DAY)] {
+            let rfc724_mid = id.to_string();
+            t.sql
+                .execute(
+                    "INSERT INTO msgs (id, rfc724_mid, timestamp) VALUES (?, ?, ?)",
+                    (id, &rfc724_mid, now - age),
+                )
+                .await?;
+            t.sql
+                .execute(
+                    "INSERT INTO imap (rfc724_mid, folder, uid, target) VALUES (?, 'INBOX', ?, 'INBOX')",
+                    (&rfc724_mid, id),
+                )
+                .await?;
+        }
+
+        assert_eq!(t.estimate_server_cleanup(30).await?, 2);
+        assert_eq!(t.cleanup_server_messages(30).await?, 2);
+        assert_eq!(
+            t.sql
+                .count("SELECT COUNT(*) FROM imap WHERE target=''", ())
+                .await?,
+            2
+        );
+        assert_eq!(t.estimate_server_cleanup(30).await?, 0);
+        // Messages are kept on the device.
+        assert_eq!(
+            t.sql
+                .count("SELECT COUNT(*) FROM msgs WHERE id>=1000", ())
+                .await?,
+            3
+        );
+
+        t.set_config_bool(Config::LegalHold, true).await?;
+        assert!(t.cleanup_server_messages(0).await.is_err());
+        Ok(())
+    }
 }
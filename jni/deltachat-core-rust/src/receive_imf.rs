@@ -4,7 +4,9 @@
 use std::str::FromStr;
 
 use anyhow::{Context as _, Result};
-use deltachat_contact_tools::{addr_cmp, may_be_valid_addr, sanitize_single_line, ContactAddress};
+use deltachat_contact_tools::{
+    addr_cmp, may_be_valid_addr, sanitize_name, sanitize_single_line, ContactAddress,
+};
 use iroh_gossip::proto::TopicId;
 use mailparse::SingleInfo;
 use num_traits::FromPrimitive;
@@ -12,6 +14,8 @@
 use regex::Regex;
 
 use crate::aheader::EncryptPreference;
+use crate::blob::pdf;
+use crate::calls;
 use crate::chat::{self, Chat, ChatId, ChatIdBlocked, ProtectionStatus};
 use crate::config::Config;
 use crate::constants::{self, Blocked, Chattype, ShowEmails, DC_CHAT_ID_TRASH};
@@ -19,11 +23,15 @@
 use crate::context::Context;
 use crate::debug_logging::maybe_set_logging_xdc_inner;
 use crate::download::DownloadState;
+use crate::email_rules::{self, EmailRuleAction};
 use crate::ephemeral::{stock_ephemeral_timer_changed, Timer as EphemeralTimer};
 use crate::events::EventType;
+use crate::group_history;
+use crate::group_spam;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::imap::{markseen_on_imap_table, GENERATED_PREFIX};
 use crate::log::LogExt;
+use crate::member_digest::{self, MemberChange};
 use crate::message::{
     self, rfc724_mid_exists, rfc724_mid_exists_ex, Message, MessageState, MessengerMessage, MsgId,
     Viewtype,
@@ -33,12 +41,15 @@
 use crate::peer_channels::{add_gossip_peer_from_header, insert_topic_stub};
 use crate::peerstate::Peerstate;
 use crate::reaction::{set_msg_reaction, Reaction};
+use crate::report;
 use crate::securejoin::{self, handle_securejoin_handshake, observe_securejoin_on_other_device};
 use crate::simplify;
 use crate::sql::{self, params_iter};
 use crate::stock_str;
 use crate::sync::Sync::*;
-use crate::tools::{self, buf_compress, remove_subject_prefix};
+use crate::tools::{self, buf_compress, is_subject_placeholder, remove_subject_prefix};
+use crate::undo;
+use crate::watch;
 use crate::{chatlist_events, location};
 use crate::{contact, imap};
 
@@ -125,6 +136,7 @@ pub(crate) async fn receive_imf_from_inbox(
         seen,
         is_partial_download,
         fetching_existing_messages,
+        None,
     )
     .await
 }
@@ -158,6 +170,9 @@ async fn insert_tombstone(context: &Context, rfc724_mid: &str) -> Result<MsgId>
 /// If `is_partial_download` is set, it contains the full message size in bytes.
 /// Do not confuse that with `replace_msg_id` that will be set when the full message is loaded
 /// later.
+///
+/// `predecrypted` is the result of decrypting the message ahead of time
+/// with [`crate::decrypt::spawn_decryption`], if any.
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn receive_imf_inner(
     context: &Context,
@@ -169,6 +184,7 @@ pub(crate) async fn receive_imf_inner(
     seen: bool,
     is_partial_download: Option<u32>,
     fetching_existing_messages: bool,
+    predecrypted: Option<Result<::pgp::composed::Message>>,
 ) -> Result<Option<ReceivedMsg>> {
     if std::env::var(crate::DCC_MIME_DEBUG).is_ok() {
         info!(
@@ -178,7 +194,13 @@ pub(crate) async fn receive_imf_inner(
         );
     }
 
-    let mut mime_parser = match MimeMessage::from_bytes(context, imf_raw, is_partial_download).await
+    let mut mime_parser = match MimeMessage::from_bytes_predecrypted(
+        context,
+        imf_raw,
+        is_partial_download,
+        predecrypted,
+    )
+    .await
     {
         Err(err) => {
             warn!(context, "receive_imf: can't parse MIME: {err:#}.");
@@ -212,7 +234,7 @@ pub(crate) async fn receive_imf_inner(
         // calls to save_to_db() and not to degrade encryption if a mail wasn't parsed
         // successfully.
         if peerstate.prefer_encrypt != EncryptPreference::Mutual {
-            peerstate.save_to_db(&context.sql).await?;
+            peerstate.save_to_db_ex(context, None).await?;
         }
     }
 
@@ -348,6 +370,23 @@ pub(crate) async fn receive_imf_inner(
 
     update_verified_keys(context, &mut mime_parser, from_id).await?;
 
+    let impersonated_id = if mime_parser.incoming && !prevent_rename && !incoming_origin.is_known()
+    {
+        get_impersonated_contact(context, &mime_parser, from_id).await?
+    } else {
+        None
+    };
+    if let Some(impersonated_id) = impersonated_id {
+        warn!(
+            context,
+            "Unknown sender {from_id} uses the name of known contact {impersonated_id}."
+        );
+        for part in &mut mime_parser.parts {
+            part.param
+                .set_int(Param::ImpersonatedContact, impersonated_id.to_u32() as i32);
+        }
+    }
+
     let received_msg;
     if mime_parser.get_header(HeaderDef::SecureJoin).is_some() {
         let res;
@@ -405,7 +444,7 @@ pub(crate) async fn receive_imf_inner(
             // have our key as verified to protected chats.
             peerstate.backward_verified_key_id =
                 Some(context.get_config_i64(Config::KeyId).await?).filter(|&id| id > 0);
-            peerstate.save_to_db(&context.sql).await?;
+            peerstate.save_to_db_ex(context, None).await?;
         }
     }
 
@@ -464,6 +503,15 @@ pub(crate) async fn receive_imf_inner(
 
     save_locations(context, &mime_parser, chat_id, from_id, insert_msg_id).await?;
 
+    if mime_parser.is_system_message == SystemMessage::CallInvite
+        && !chat_id.is_trash()
+        && !insert_msg_id.is_unset()
+    {
+        if let Err(err) = calls::receive_call_invite(context, &mime_parser, insert_msg_id).await {
+            warn!(context, "Failed to receive call invite: {err:#}.");
+        }
+    }
+
     if let Some(ref sync_items) = mime_parser.sync_items {
         if from_id == ContactId::SELF {
             if mime_parser.was_encrypted() {
@@ -524,6 +572,9 @@ pub(crate) async fn receive_imf_inner(
 
     if let Some(avatar_action) = &mime_parser.user_avatar {
         if from_id != ContactId::UNDEFINED
+            && (context.get_config_bool(Config::LargeGroupAvatars).await?
+                || chat_id.is_special()
+                || !chat::is_large_group(context, chat_id).await?)
             && context
                 .update_contacts_timestamp(
                     from_id,
@@ -610,8 +661,20 @@ pub(crate) async fn receive_imf_inner(
         context.emit_msgs_changed(replace_chat_id, MsgId::new(0));
     } else if !chat_id.is_trash() {
         let fresh = received_msg.state == MessageState::InFresh;
+        let watched = mime_parser.incoming
+            && watch::check_msgs(context, &received_msg.msg_ids)
+                .await
+                .log_err(context)
+                .unwrap_or_default();
         for msg_id in &received_msg.msg_ids {
-            chat_id.emit_msg_event(context, *msg_id, mime_parser.incoming && fresh);
+            chat_id.emit_msg_event(context, *msg_id, mime_parser.incoming && fresh, watched);
+        }
+        if let (Some(contact_id), Some(msg_id)) = (impersonated_id, received_msg.msg_ids.first()) {
+            context.emit_event(EventType::IncomingMsgImpersonation {
+                chat_id,
+                msg_id: *msg_id,
+                contact_id,
+            });
         }
     }
     context.new_msgs_notify.notify_one();
@@ -676,6 +739,83 @@ pub async fn from_field_to_contact_id(
     }
 }
 
+/// Returns the known contact whose name the unknown sender `from_id` uses as display name,
+/// `ContactId::SELF` if the sender uses our own name.
+///
+/// Messages signed with a key of the known contact do not impersonate it,
+/// the contact may just write from a new address.
+async fn get_impersonated_contact(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+) -> Result<Option<ContactId>> {
+    let Some(name) = &mime_parser.from.display_name else {
+        return Ok(None);
+    };
+    let name = normalize_display_name(name);
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some(self_name) = context.get_config(Config::Displayname).await? {
+        if normalize_display_name(&self_name) == name {
+            return Ok(Some(ContactId::SELF));
+        }
+    }
+
+    let candidates = context
+        .sql
+        .query_map(
+            "SELECT id, name, authname, addr FROM contacts
+             WHERE id>? AND id!=? AND origin>=? AND blocked=0",
+            (ContactId::LAST_SPECIAL, from_id, Origin::IncomingReplyTo),
+            |row| {
+                let id: ContactId = row.get(0)?;
+                let name: String = row.get(1)?;
+                let authname: String = row.get(2)?;
+                let addr: String = row.get(3)?;
+                Ok((id, name, authname, addr))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    for (id, contact_name, authname, addr) in candidates {
+        if normalize_display_name(&contact_name) != name
+            && normalize_display_name(&authname) != name
+        {
+            continue;
+        }
+        if let Some(peerstate) = Peerstate::from_addr(context, &addr).await? {
+            let signed_by_contact = [
+                &peerstate.public_key_fingerprint,
+                &peerstate.gossip_key_fingerprint,
+                &peerstate.verified_key_fingerprint,
+            ]
+            .into_iter()
+            .flatten()
+            .any(|fingerprint| mime_parser.signatures.contains(fingerprint));
+            if signed_by_contact {
+                continue;
+            }
+        }
+        return Ok(Some(id));
+    }
+    Ok(None)
+}
+
+/// Normalizes a display name for comparison,
+/// ignoring case and differences in whitespace.
+fn normalize_display_name(name: &str) -> String {
+    sanitize_name(name)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
 /// Creates a `ReceivedMsg` from given parts which might consist of
 /// multiple messages (if there are multiple attachments).
 /// Every entry in `mime_parser.parts` produces a new row in the `msgs` table.
@@ -741,14 +881,33 @@ async fn add_parts(
         && is_dc_message == MessengerMessage::No
     {
         // this message is a classic email not a chat-message nor a reply to one
-        match show_emails {
-            ShowEmails::Off => {
+        let rule_action =
+            if mime_parser.incoming && !is_mdn && mime_parser.delivery_report.is_none() {
+                email_rules::lookup_action(context, mime_parser).await?
+            } else {
+                None
+            };
+        match (rule_action, show_emails) {
+            (Some(EmailRuleAction::Hide), _) | (None, ShowEmails::Off) => {
                 info!(context, "Classical email not shown (TRASH).");
                 chat_id = Some(DC_CHAT_ID_TRASH);
                 allow_creation = false;
             }
-            ShowEmails::AcceptedContacts => allow_creation = false,
-            ShowEmails::All => allow_creation = !is_mdn,
+            (Some(EmailRuleAction::Mailbox), _) => {
+                let (mailbox_chat_id, blocked) =
+                    email_rules::get_or_create_mailbox_chat(context).await?;
+                if blocked == Blocked::Yes {
+                    info!(context, "Classical email for blocked mailbox chat (TRASH).");
+                    chat_id = Some(DC_CHAT_ID_TRASH);
+                } else {
+                    info!(context, "Classical email assigned to mailbox chat.");
+                    chat_id = Some(mailbox_chat_id);
+                    chat_id_blocked = blocked;
+                }
+                allow_creation = false;
+            }
+            (Some(EmailRuleAction::Show), _) | (None, ShowEmails::All) => allow_creation = !is_mdn,
+            (None, ShowEmails::AcceptedContacts) => allow_creation = false,
         }
     } else {
         allow_creation = !is_mdn && !is_reaction;
@@ -790,7 +949,10 @@ async fn add_parts(
             info!(context, "Message is an MDN (TRASH).",);
         }
 
-        let create_blocked_default = if is_bot {
+        let accept_request = is_bot
+            || (test_normal_chat.map_or(true, |chat| chat.blocked != Blocked::Not)
+                && should_auto_accept(context, mime_parser, from_id).await?);
+        let create_blocked_default = if accept_request {
             Blocked::Not
         } else {
             Blocked::Request
@@ -934,7 +1096,7 @@ async fn add_parts(
             let contact = Contact::get_by_id(context, from_id).await?;
             let create_blocked = match contact.is_blocked() {
                 true => Blocked::Yes,
-                false if is_bot => Blocked::Not,
+                false if accept_request => Blocked::Not,
                 false => Blocked::Request,
             };
 
@@ -1231,6 +1393,42 @@ async fn add_parts(
         }
     }
 
+    if mime_parser.group_history.is_some() {
+        if let Some(group_chat_id) = chat_id.filter(|chat_id| !chat_id.is_special()) {
+            if let Err(err) =
+                group_history::receive_history(context, mime_parser, group_chat_id, from_id).await
+            {
+                warn!(context, "Failed to receive group history: {err:#}.");
+            }
+        }
+        chat_id = Some(DC_CHAT_ID_TRASH);
+        info!(context, "Message is a group history (TRASH).");
+        markseen_on_imap_table(context, rfc724_mid).await.ok();
+    }
+
+    if mime_parser.msg_report.is_some() {
+        // Reports sent from other devices of the user are not stored.
+        if from_id != ContactId::SELF {
+            if let Err(err) = report::receive_report(context, mime_parser, from_id).await {
+                warn!(context, "Failed to receive message report: {err:#}.");
+            }
+        }
+        chat_id = Some(DC_CHAT_ID_TRASH);
+        info!(context, "Message is a message report (TRASH).");
+        markseen_on_imap_table(context, rfc724_mid).await.ok();
+    }
+
+    if matches!(
+        mime_parser.is_system_message,
+        SystemMessage::CallAccepted | SystemMessage::CallRejected | SystemMessage::CallEnded
+    ) {
+        if let Err(err) = calls::receive_call_control(context, mime_parser, from_id).await {
+            warn!(context, "Failed to receive call control message: {err:#}.");
+        }
+        chat_id = Some(DC_CHAT_ID_TRASH);
+        info!(context, "Message is a call control message (TRASH).");
+    }
+
     let orig_chat_id = chat_id;
     let mut chat_id = if is_mdn || is_reaction {
         DC_CHAT_ID_TRASH
@@ -1422,6 +1620,15 @@ async fn add_parts(
 
     let mut created_db_entries = Vec::with_capacity(mime_parser.parts.len());
 
+    // PDFs are rendered by a large C++ library,
+    // so only attachments in chats accepted by the user are rendered.
+    let render_pdf_previews = !chat_id.is_special()
+        && mime_parser
+            .parts
+            .iter()
+            .any(|part| part.typ == Viewtype::File)
+        && Chat::load_from_db(context, chat_id).await?.blocked == Blocked::Not;
+
     if let Some(msg) = group_changes_msgs.1 {
         match &better_msg {
             None => better_msg = Some(msg),
@@ -1492,9 +1699,21 @@ async fn add_parts(
         }
 
         let mut param = part.param.clone();
+        if render_pdf_previews && part.typ == Viewtype::File {
+            if let Err(err) = pdf::set_preview_param(context, &mut param).await {
+                warn!(context, "Could not render PDF preview: {err:#}.");
+            }
+        }
         if is_system_message != SystemMessage::Unknown {
             param.set_int(Param::Cmd, is_system_message as i32);
         }
+        if is_system_message == SystemMessage::MemberAddedToGroup {
+            // Remember the added member as for sent messages,
+            // e.g. to find out who added us, see `group_history::receive_history()`.
+            if let Some(added_addr) = mime_parser.get_header(HeaderDef::ChatGroupMemberAdded) {
+                param.set(Param::Arg, added_addr);
+            }
+        }
 
         if let Some(replace_msg_id) = replace_msg_id {
             let placeholder = Message::load_from_db(context, replace_msg_id).await?;
@@ -1679,6 +1898,15 @@ async fn add_parts(
         replace_msg_id.trash(context, on_server).await?;
     }
 
+    if !chat_id.is_trash() && !created_db_entries.is_empty() {
+        resolve_pending_quotes(context, rfc724_mid_orig).await?;
+        for (part, msg_id) in mime_parser.parts.iter().zip(&created_db_entries) {
+            if part.param.get(Param::Quote).is_some() {
+                add_pending_quote(context, *msg_id, mime_in_reply_to).await?;
+            }
+        }
+    }
+
     let unarchive = match mime_parser.get_header(HeaderDef::ChatGroupMemberRemoved) {
         Some(addr) => context.is_self_addr(addr).await?,
         None => true,
@@ -1686,6 +1914,9 @@ async fn add_parts(
     if unarchive {
         chat_id.unarchive_if_not_muted(context, state).await?;
     }
+    if !chat_id.is_special() {
+        undo::restore_chat(context, chat_id).await?;
+    }
 
     info!(
         context,
@@ -1700,17 +1931,20 @@ async fn add_parts(
     if !is_mdn {
         let mut chat = Chat::load_from_db(context, chat_id).await?;
 
+        // write the last subject even if empty -
+        // otherwise a reply may get an outdated subject.
+        // Only the placeholder of an encrypted subject, e.g. in a reply from an email client
+        // not supporting protected headers, does not replace the last subject.
+        let subject = mime_parser.get_subject().unwrap_or_default();
+
         // In contrast to most other update-timestamps,
         // use `sort_timestamp` instead of `sent_timestamp` for the subject-timestamp comparison.
         // This way, `LastSubject` actually refers to the most recent message _shown_ in the chat.
-        if chat
-            .param
-            .update_timestamp(Param::SubjectTimestamp, sort_timestamp)?
+        if !is_subject_placeholder(&subject)
+            && chat
+                .param
+                .update_timestamp(Param::SubjectTimestamp, sort_timestamp)?
         {
-            // write the last subject even if empty -
-            // otherwise a reply may get an outdated subject.
-            let subject = mime_parser.get_subject().unwrap_or_default();
-
             chat.param.set(Param::LastSubject, subject);
             chat.update_param(context).await?;
         }
@@ -1743,6 +1977,150 @@ async fn add_parts(
     })
 }
 
+/// Remembers that `msg_id` quotes the message `in_reply_to`
+/// if the quoted message is not received yet,
+/// e.g. because it was not downloaded or arrives later.
+async fn add_pending_quote(context: &Context, msg_id: MsgId, in_reply_to: &str) -> Result<()> {
+    let Some(quoted_mid) = parse_message_ids(in_reply_to).into_iter().next() else {
+        return Ok(());
+    };
+    if message::rfc724_mid_exists(context, &quoted_mid)
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+    info!(
+        context,
+        "Message {msg_id} quotes {quoted_mid:?} which is not received yet."
+    );
+    context
+        .sql
+        .execute(
+            "INSERT OR REPLACE INTO pending_quotes (msg_id, rfc724_mid) VALUES (?, ?)",
+            (msg_id, quoted_mid),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Notifies the UI about messages quoting the just received message `rfc724_mid`
+/// so the quotes can be linked to it.
+async fn resolve_pending_quotes(context: &Context, rfc724_mid: &str) -> Result<()> {
+    let msgs = context
+        .sql
+        .query_map(
+            "SELECT p.msg_id, m.chat_id FROM pending_quotes p
+             INNER JOIN msgs m ON m.id=p.msg_id
+             WHERE p.rfc724_mid=?",
+            (rfc724_mid,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let chat_id: ChatId = row.get(1)?;
+                Ok((msg_id, chat_id))
+            },
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    context
+        .sql
+        .execute(
+            "DELETE FROM pending_quotes WHERE rfc724_mid=?",
+            (rfc724_mid,),
+        )
+        .await?;
+    for (msg_id, chat_id) in msgs {
+        if !chat_id.is_trash() {
+            context.emit_msgs_changed(chat_id, msg_id);
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if a contact request from `from_id` matches one of the auto-accept rules
+/// configured by `Config::AutoAcceptSecurejoin`, `Config::AutoAcceptDomains`
+/// and `Config::AutoAcceptVerifiedGroupMembers`.
+async fn should_auto_accept(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+) -> Result<bool> {
+    if from_id.is_special() {
+        return Ok(false);
+    }
+
+    if mime_parser.get_header(HeaderDef::SecureJoin).is_some()
+        && context
+            .get_config_bool(Config::AutoAcceptSecurejoin)
+            .await?
+    {
+        info!(
+            context,
+            "Auto-accepting request from {from_id}: SecureJoin handshake."
+        );
+        return Ok(true);
+    }
+
+    // The From address is only trusted if it is authenticated
+    // by a valid signature or by DKIM.
+    let from_authenticated = !mime_parser.signatures.is_empty() || mime_parser.dkim_verified;
+    if let Some(domains) = context
+        .get_config(Config::AutoAcceptDomains)
+        .await?
+        .filter(|_| from_authenticated)
+    {
+        let from_domain = mime_parser
+            .from
+            .addr
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or_default();
+        if !from_domain.is_empty()
+            && domains
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .any(|domain| domain.eq_ignore_ascii_case(from_domain))
+        {
+            info!(
+                context,
+                "Auto-accepting request from {from_id}: Domain {from_domain} is allowlisted."
+            );
+            return Ok(true);
+        }
+    }
+
+    if context
+        .get_config_bool(Config::AutoAcceptVerifiedGroupMembers)
+        .await?
+        && context
+            .sql
+            .exists(
+                "SELECT COUNT(*) FROM chats_contacts cc
+                 INNER JOIN chats c ON c.id=cc.chat_id
+                 WHERE cc.contact_id=? AND c.type=? AND c.protected=? AND c.blocked=?
+                 AND EXISTS (SELECT 1 FROM chats_contacts WHERE chat_id=c.id AND contact_id=?)",
+                (
+                    from_id,
+                    Chattype::Group,
+                    ProtectionStatus::Protected,
+                    Blocked::Not,
+                    ContactId::SELF,
+                ),
+            )
+            .await?
+    {
+        info!(
+            context,
+            "Auto-accepting request from {from_id}: Member of a protected group."
+        );
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
 /// Saves attached locations to the database.
 ///
 /// Emits an event if at least one new location was added.
@@ -2061,6 +2439,12 @@ async fn self_explicitly_added(
         members.dedup();
         chat::add_to_chat_contacts_table(context, new_chat_id, &members).await?;
 
+        if create_blocked == Blocked::Request
+            && group_spam::is_suspicious_group_add(context, from_id, to_ids).await?
+        {
+            group_spam::quarantine(context, new_chat_id).await?;
+        }
+
         context.emit_event(EventType::ChatModified(new_chat_id));
         chatlist_events::emit_chatlist_changed(context);
         chatlist_events::emit_chatlist_item_changed(context, new_chat_id);
@@ -2125,6 +2509,9 @@ async fn apply_group_changes(
 
     let mut chat_contacts =
         HashSet::<ContactId>::from_iter(chat::get_chat_contacts(context, chat_id).await?);
+    // Whether changes of other members are summarized in a digest instead of shown one by one.
+    let digest_members = member_digest::is_digested(context, chat_contacts.len()).await?;
+    let mut digested_changes = Vec::new();
     let is_from_in_chat =
         !chat_contacts.contains(&ContactId::SELF) || chat_contacts.contains(&from_id);
     // Reject group membership changes from non-members and old changes.
@@ -2194,7 +2581,10 @@ async fn apply_group_changes(
         removed_id = Contact::lookup_id_by_addr(context, removed_addr, Origin::Unknown).await?;
         if let Some(id) = removed_id {
             if allow_member_list_changes && chat_contacts.contains(&id) {
-                better_msg = if id == from_id {
+                better_msg = if digest_members && id != ContactId::SELF {
+                    digested_changes.push((id, false));
+                    None
+                } else if id == from_id {
                     Some(stock_str::msg_group_left_local(context, from_id).await)
                 } else {
                     Some(stock_str::msg_del_member_local(context, removed_addr, from_id).await)
@@ -2212,20 +2602,23 @@ async fn apply_group_changes(
         }
     } else if let Some(added_addr) = mime_parser.get_header(HeaderDef::ChatGroupMemberAdded) {
         if allow_member_list_changes {
-            let is_new_member;
+            let mut new_member_id = None;
             if let Some(contact_id) =
                 Contact::lookup_id_by_addr(context, added_addr, Origin::Unknown).await?
             {
                 if !recreate_member_list {
                     added_id = Some(contact_id);
                 }
-                is_new_member = !chat_contacts.contains(&contact_id);
+                if !chat_contacts.contains(&contact_id) {
+                    new_member_id = Some(contact_id);
+                }
             } else {
                 warn!(context, "Added {added_addr:?} has no contact id.");
-                is_new_member = false;
             }
 
-            if is_new_member || self_added {
+            if let Some(contact_id) = new_member_id.filter(|_| digest_members && !self_added) {
+                digested_changes.push((contact_id, true));
+            } else if new_member_id.is_some() || self_added {
                 better_msg =
                     Some(stock_str::msg_add_member_local(context, added_addr, from_id).await);
             }
@@ -2314,6 +2707,10 @@ async fn apply_group_changes(
             }
             group_changes_msgs.reserve(diff.len());
             for contact_id in diff {
+                if digest_members {
+                    digested_changes.push((contact_id, true));
+                    continue;
+                }
                 let contact = Contact::get_by_id(context, contact_id).await?;
                 group_changes_msgs.push(
                     stock_str::msg_add_member_local(
@@ -2384,6 +2781,16 @@ async fn apply_group_changes(
         }
     }
 
+    for (contact_id, added) in digested_changes {
+        let change = MemberChange {
+            contact_id,
+            added,
+            by_contact_id: from_id,
+            timestamp: mime_parser.timestamp_sent,
+        };
+        member_digest::record_change(context, chat_id, change).await?;
+    }
+
     if send_event_chat_modified {
         context.emit_event(EventType::ChatModified(chat_id));
         chatlist_events::emit_chatlist_item_changed(context, chat_id);
@@ -2393,7 +2800,7 @@ async fn apply_group_changes(
 
 static LIST_ID_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.+)<(.+)>$").unwrap());
 
-fn mailinglist_header_listid(list_id_header: &str) -> Result<String> {
+pub(crate) fn mailinglist_header_listid(list_id_header: &str) -> Result<String> {
     Ok(match LIST_ID_REGEX.captures(list_id_header) {
         Some(cap) => cap.get(2).context("no match??")?.as_str().trim(),
         None => list_id_header
@@ -2549,7 +2956,7 @@ async fn apply_mailinglist_changes(
     };
 
     let mut chat = Chat::load_from_db(context, chat_id).await?;
-    if chat.typ != Chattype::Mailinglist {
+    if chat.typ != Chattype::Mailinglist || chat.grpid == email_rules::MAILBOX_GRPID {
         return Ok(());
     }
     let listid = &chat.grpid;
@@ -2711,7 +3118,7 @@ async fn update_verified_keys(
             peerstate.secondary_verified_key = None;
             peerstate.secondary_verified_key_fingerprint = None;
             peerstate.secondary_verifier = None;
-            peerstate.save_to_db(&context.sql).await?;
+            peerstate.save_to_db_ex(context, None).await?;
         }
 
         // No need to notify about secondary key removal.
@@ -2721,7 +3128,7 @@ async fn update_verified_keys(
         peerstate.verified_key_fingerprint = peerstate.secondary_verified_key_fingerprint.take();
         peerstate.verifier = peerstate.secondary_verifier.take();
         peerstate.fingerprint_changed = true;
-        peerstate.save_to_db(&context.sql).await?;
+        peerstate.save_to_db_ex(context, None).await?;
 
         // Primary verified key changed.
         Ok(None)
@@ -2839,7 +3246,7 @@ async fn mark_recipients_as_verified(
                         peerstate.set_verified(gossiped_key.clone(), fp, verifier_addr)?;
                         peerstate.backward_verified_key_id =
                             Some(context.get_config_i64(Config::KeyId).await?).filter(|&id| id > 0);
-                        peerstate.save_to_db(&context.sql).await?;
+                        peerstate.save_to_db_ex(context, None).await?;
 
                         let (to_contact_id, _) = Contact::add_or_lookup(
                             context,
@@ -2859,7 +3266,7 @@ async fn mark_recipients_as_verified(
                     // The contact already has a verified key.
                     // Store gossiped key as the secondary verified key.
                     peerstate.set_secondary_verified_key(gossiped_key.clone(), verifier_addr);
-                    peerstate.save_to_db(&context.sql).await?;
+                    peerstate.save_to_db_ex(context, None).await?;
                 }
             }
         }
@@ -3026,7 +3026,9 @@ async fn test_incoming_contact_request() -> Result<()> {
         .get_matching(|evt| matches!(evt, EventType::IncomingMsg { .. }))
         .await;
     match event {
-        EventType::IncomingMsg { chat_id, msg_id } => {
+        EventType::IncomingMsg {
+            chat_id, msg_id, ..
+        } => {
             assert_eq!(msg.chat_id, chat_id);
             assert_eq!(msg.id, msg_id);
             Ok(())
@@ -3035,6 +3037,99 @@ async fn test_incoming_contact_request() -> Result<()> {
     }
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_auto_accept_domains() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    alice
+        .set_config(Config::AutoAcceptDomains, Some("example.com, Example.NET"))
+        .await?;
+
+    // The From address of an unsigned message without Authentication-Results can be forged.
+    let msg = tcm.send_recv(bob, alice, "Hi from example.net").await;
+    let chat = Chat::load_from_db(alice, msg.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Request);
+
+    let received = receive_imf(
+        alice,
+        b"Authentication-Results: example.org; dkim=pass header.d=example.net\n\
+        From: claire@example.net\n\
+        To: alice@example.org\n\
+        Message-ID: <1111@example.net>\n\
+        Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+        \n\
+        Hi from example.net\n",
+        false,
+    )
+    .await?
+    .unwrap();
+    let chat = Chat::load_from_db(alice, received.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Not);
+
+    let received = receive_imf(
+        alice,
+        b"Authentication-Results: example.org; dkim=fail header.d=example.net\n\
+        From: dora@example.net\n\
+        To: alice@example.org\n\
+        Message-ID: <2222@example.net>\n\
+        Date: Sun, 22 Mar 2020 22:37:58 +0000\n\
+        \n\
+        Hi from example.net\n",
+        false,
+    )
+    .await?
+    .unwrap();
+    let chat = Chat::load_from_db(alice, received.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Request);
+
+    alice
+        .set_config(Config::AutoAcceptDomains, Some("example.com"))
+        .await?;
+    let received = receive_imf(
+        alice,
+        b"Authentication-Results: example.org; dkim=pass header.d=example.net\n\
+        From: erin@example.net\n\
+        To: alice@example.org\n\
+        Message-ID: <3333@example.net>\n\
+        Date: Sun, 22 Mar 2020 22:37:59 +0000\n\
+        \n\
+        Hi from example.net\n",
+        false,
+    )
+    .await?
+    .unwrap();
+    let chat = Chat::load_from_db(alice, received.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Request);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_auto_accept_verified_group_members() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    alice
+        .set_config_bool(Config::AutoAcceptVerifiedGroupMembers, true)
+        .await?;
+    let group_id = create_group_chat(alice, ProtectionStatus::Protected, "Group").await?;
+    let fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+    add_to_chat_contacts_table(alice, group_id, &[fiona_id]).await?;
+
+    let msg = tcm.send_recv(fiona, alice, "Hi Alice").await;
+    let chat = Chat::load_from_db(alice, msg.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Not);
+
+    // Bob is not a member of any protected group with Alice.
+    let msg = tcm.send_recv(bob, alice, "Hi Alice").await;
+    let chat = Chat::load_from_db(alice, msg.chat_id).await?;
+    assert_eq!(chat.blocked, Blocked::Request);
+    Ok(())
+}
+
 async fn get_parent_message(
     context: &Context,
     mime_parser: &MimeMessage,
@@ -3496,7 +3591,10 @@ async fn test_bot_recv_existing_msg() -> Result<()> {
         .evtracker
         .get_matching(|ev| matches!(ev, EventType::IncomingMsg { .. }))
         .await;
-    let EventType::IncomingMsg { chat_id, msg_id } = event else {
+    let EventType::IncomingMsg {
+        chat_id, msg_id, ..
+    } = event
+    else {
         unreachable!();
     };
     assert_eq!(chat_id, msg.chat_id);
@@ -5379,3 +5477,122 @@ async fn test_prefer_chat_group_id_over_references() -> Result<()> {
     assert_ne!(chat1.id, chat2.id);
     Ok(())
 }
+
+/// Tests that a quote of a message received after the quoting message
+/// is linked once the quoted message arrives.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_quote_of_later_received_message() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let alice_chat_id = alice.create_chat(bob).await.id;
+
+    let sent1 = alice.send_text(alice_chat_id, "Original").await;
+    let original = Message::load_from_db(alice, sent1.sender_msg_id).await?;
+    let mut reply = Message::new_text("Reply".to_string());
+    reply.set_quote(alice, Some(&original)).await?;
+    let sent2 = alice.send_msg(alice_chat_id, &mut reply).await;
+
+    let bob_reply = bob.recv_msg(&sent2).await;
+    assert_eq!(bob_reply.quoted_text(), Some("Original".to_string()));
+    assert!(bob_reply.quoted_message(bob).await?.is_none());
+
+    bob.evtracker.clear_events();
+    let bob_original = bob.recv_msg(&sent1).await;
+    bob.evtracker
+        .get_matching(
+            |evt| matches!(evt, EventType::MsgsChanged { msg_id, .. } if *msg_id == bob_reply.id),
+        )
+        .await;
+    let bob_reply = Message::load_from_db(bob, bob_reply.id).await?;
+    assert_eq!(
+        bob_reply.quoted_message(bob).await?.unwrap().id,
+        bob_original.id
+    );
+    let pending: usize = bob
+        .sql
+        .count("SELECT COUNT(*) FROM pending_quotes", ())
+        .await?;
+    assert_eq!(pending, 0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_impersonation() -> Result<()> {
+    let t = &TestContext::new_alice().await;
+    t.set_config(Config::Displayname, Some("Alice")).await?;
+    let bob_id = Contact::create(t, "Bob", "bob@example.net").await?;
+
+    receive_imf(
+        t,
+        b"From: Bob <bob@example.net>\n\
+          To: alice@example.org\n\
+          Message-ID: <1@example.net>\n\
+          Date: Sun, 22 Mar 2020 22:37:57 +0000\n\
+          Chat-Version: 1.0\n\
+          \n\
+          Hi from Bob\n",
+        false,
+    )
+    .await?;
+    assert_eq!(t.get_last_msg().await.get_impersonated_contact(), None);
+
+    t.evtracker.clear_events();
+    receive_imf(
+        t,
+        b"From: \" bOB \" <bob@example.org>\n\
+          To: alice@example.org\n\
+          Message-ID: <2@example.org>\n\
+          Date: Sun, 22 Mar 2020 22:37:58 +0000\n\
+          Chat-Version: 1.0\n\
+          \n\
+          Hi, this is my new address\n",
+        false,
+    )
+    .await?;
+    let msg = t.get_last_msg().await;
+    assert_eq!(msg.get_impersonated_contact(), Some(bob_id));
+    let EventType::IncomingMsgImpersonation {
+        msg_id, contact_id, ..
+    } = t
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::IncomingMsgImpersonation { .. }))
+        .await
+    else {
+        unreachable!();
+    };
+    assert_eq!(msg_id, msg.id);
+    assert_eq!(contact_id, bob_id);
+
+    receive_imf(
+        t,
+        b"From: Alice <alice@example.com>\n\
+          To: alice@example.org\n\
+          Message-ID: <3@example.com>\n\
+          Date: Sun, 22 Mar 2020 22:37:59 +0000\n\
+          Chat-Version: 1.0\n\
+          \n\
+          Hi\n",
+        false,
+    )
+    .await?;
+    assert_eq!(
+        t.get_last_msg().await.get_impersonated_contact(),
+        Some(ContactId::SELF)
+    );
+
+    receive_imf(
+        t,
+        b"From: Charlie <charlie@example.com>\n\
+          To: alice@example.org\n\
+          Message-ID: <4@example.com>\n\
+          Date: Sun, 22 Mar 2020 22:38:00 +0000\n\
+          Chat-Version: 1.0\n\
+          \n\
+          Hi\n",
+        false,
+    )
+    .await?;
+    assert_eq!(t.get_last_msg().await.get_impersonated_contact(), None);
+    Ok(())
+}
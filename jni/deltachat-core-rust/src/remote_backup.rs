@@ -0,0 +1,941 @@
+//! # Automated backups to a WebDAV or S3 target.
+//!
+//! If [`Config::RemoteBackupUrl`] is set, backups are uploaded
+//! to the WebDAV collection or S3-compatible bucket at this URL
+//! every [`Config::RemoteBackupInterval`] hours
+//! or on demand with [`run_remote_backup`].
+//!
+//! Backups are incremental: each backup uploads a snapshot of the database,
+//! but only blobs which are not part of the previous backup.
+//! The database snapshot is encrypted by SQLCipher, blobs and metadata are encrypted with OpenPGP,
+//! both using [`Config::RemoteBackupPassphrase`].
+//! Blobs are split into chunks of [`CHUNK_SIZE`] bytes which are encrypted separately,
+//! so that large blobs are never held in memory as a whole.
+//!
+//! The target contains the following objects:
+//! - `index.asc` listing the backups,
+//! - `db-<id>.sqlite` and `manifest-<id>.asc` for each backup,
+//!   the manifest listing the blobs of the backup,
+//! - `blob-<hash>-<n>.asc` for each chunk of each blob,
+//!   where the hash is derived from the blob content.
+//!
+//! Only the newest [`Config::RemoteBackupKeep`] backups are kept,
+//! older backups and the blobs only they refer to are deleted.
+//! Backups can be listed with [`list_remote_backups`]
+//! and restored to an unconfigured account with [`restore_remote_backup`].
+
+use std::collections::BTreeSet;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use base64::Engine as _;
+use bytes::Bytes;
+use futures::Stream;
+use hmac::{Hmac, Mac};
+use http_body_util::{BodyExt, Empty, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::blob::integrity::hash_file;
+use crate::blob::BlobDirContents;
+use crate::config::Config;
+use crate::context::Context;
+use crate::imex::{self, ImexMode, BLOBS_BACKUP_NAME, DBFILE_BACKUP_NAME};
+use crate::log::LogExt;
+use crate::net::http;
+use crate::pgp;
+use crate::tools::{time, TempPathGuard};
+
+/// Minimum time between two attempts of automated remote backups in seconds.
+const RETRY_INTERVAL: i64 = 60 * 60;
+
+/// Name of the object listing the backups.
+const INDEX_NAME: &str = "index.asc";
+
+/// Size of the chunks blobs are split into in bytes.
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// State of the remote backup job.
+#[derive(Debug, Default)]
+pub(crate) struct RemoteBackupState {
+    /// Locked while a remote backup runs.
+    running: Arc<Mutex<()>>,
+
+    /// Time of the last attempt of an automated remote backup.
+    last_attempt: AtomicI64,
+}
+
+/// Backup stored on the remote target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteBackupInfo {
+    /// ID of the backup to pass to [`restore_remote_backup`].
+    pub id: i64,
+
+    /// Unix timestamp of the backup.
+    pub timestamp: i64,
+
+    /// Total size of the database snapshot and the blobs of the backup in bytes.
+    pub size: u64,
+
+    /// Number of blobs in the backup.
+    pub blob_count: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    backups: Vec<RemoteBackupInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestBlob {
+    /// Name of the blob in the blob directory.
+    name: String,
+
+    /// Name of the blob on the remote target without the chunk suffix,
+    /// see [`chunk_name`].
+    object: String,
+
+    size: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    blobs: Vec<ManifestBlob>,
+}
+
+/// WebDAV collection or S3 bucket backups are uploaded to.
+#[derive(Debug)]
+enum Target {
+    WebDav {
+        url: String,
+        user: String,
+        password: String,
+    },
+    S3 {
+        url: String,
+        access_key: String,
+        secret_key: String,
+        region: String,
+    },
+    /// Objects stored in memory.
+    #[cfg(test)]
+    Mock(std::sync::Mutex<std::collections::BTreeMap<String, Vec<u8>>>),
+}
+
+impl Target {
+    async fn load(context: &Context) -> Result<Self> {
+        let url = context
+            .get_config(Config::RemoteBackupUrl)
+            .await?
+            .filter(|url| !url.is_empty())
+            .context("No remote backup target configured")?
+            .trim_end_matches('/')
+            .to_string();
+        let user = context
+            .get_config(Config::RemoteBackupUser)
+            .await?
+            .unwrap_or_default();
+        let password = context
+            .get_config(Config::RemoteBackupPassword)
+            .await?
+            .unwrap_or_default();
+        let kind = context
+            .get_config(Config::RemoteBackupKind)
+            .await?
+            .unwrap_or_default();
+        match kind.as_str() {
+            "webdav" => Ok(Self::WebDav {
+                url,
+                user,
+                password,
+            }),
+            "s3" => Ok(Self::S3 {
+                url,
+                access_key: user,
+                secret_key: password,
+                region: context
+                    .get_config(Config::RemoteBackupRegion)
+                    .await?
+                    .unwrap_or_default(),
+            }),
+            _ => bail!("Unknown remote backup kind {kind:?}"),
+        }
+    }
+
+    /// Sends a request for the object `name`.
+    ///
+    /// `payload_hash` is the hex-encoded SHA-256 hash of `body`,
+    /// which is needed to sign S3 requests.
+    async fn request<B>(
+        &self,
+        context: &Context,
+        method: Method,
+        name: &str,
+        mut headers: Vec<(&'static str, String)>,
+        payload_hash: String,
+        body: B,
+    ) -> Result<hyper::Response<Incoming>>
+    where
+        B: hyper::body::Body + 'static + Send,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let url = match self {
+            Self::WebDav {
+                url,
+                user,
+                password,
+            } => {
+                let credentials =
+                    base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"));
+                headers.push(("authorization", format!("Basic {credentials}")));
+                format!("{url}/{name}")
+            }
+            Self::S3 {
+                url,
+                access_key,
+                secret_key,
+                region,
+            } => {
+                let url = format!("{url}/{name}");
+                let parsed_url = url.parse::<hyper::Uri>()?;
+                let host = parsed_url.authority().context("URL has no authority")?;
+                let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+                let authorization = s3_authorization(
+                    access_key,
+                    secret_key,
+                    region,
+                    method.as_str(),
+                    host.as_str(),
+                    parsed_url.path(),
+                    &payload_hash,
+                    &amz_date,
+                );
+                headers.push(("authorization", authorization));
+                headers.push(("x-amz-content-sha256", payload_hash));
+                headers.push(("x-amz-date", amz_date));
+                url
+            }
+            #[cfg(test)]
+            Self::Mock(_) => bail!("Mock target does not send requests"),
+        };
+        http::request(context, method, &url, &headers, body).await
+    }
+
+    async fn put(&self, context: &Context, name: &str, data: Vec<u8>) -> Result<()> {
+        #[cfg(test)]
+        if let Self::Mock(objects) = self {
+            objects.lock().unwrap().insert(name.to_string(), data);
+            return Ok(());
+        }
+        let payload_hash = hex::encode(Sha256::digest(&data));
+        let response = self
+            .request(
+                context,
+                Method::PUT,
+                name,
+                Vec::new(),
+                payload_hash,
+                Full::new(Bytes::from(data)),
+            )
+            .await?;
+        let status = response.status();
+        ensure!(status.is_success(), "Failed to upload {name}: {status}");
+        Ok(())
+    }
+
+    /// Uploads the file at `path` without reading it into memory as a whole.
+    async fn put_file(&self, context: &Context, name: &str, path: &Path) -> Result<()> {
+        #[cfg(test)]
+        if let Self::Mock(_) = self {
+            return self.put(context, name, fs::read(path).await?).await;
+        }
+        let payload_hash = tokio::task::spawn_blocking({
+            let path = path.to_path_buf();
+            move || hash_file(&path)
+        })
+        .await??;
+        let file = File::open(path).await?;
+        // Without the length, the body would be sent with chunked transfer encoding,
+        // which S3 does not support.
+        let headers = vec![("content-length", file.metadata().await?.len().to_string())];
+        let response = self
+            .request(
+                context,
+                Method::PUT,
+                name,
+                headers,
+                payload_hash,
+                file_body(file),
+            )
+            .await?;
+        let status = response.status();
+        ensure!(status.is_success(), "Failed to upload {name}: {status}");
+        Ok(())
+    }
+
+    /// Requests an object, returns `None` if it does not exist.
+    async fn get_response(
+        &self,
+        context: &Context,
+        name: &str,
+    ) -> Result<Option<hyper::Response<Incoming>>> {
+        let payload_hash = hex::encode(Sha256::digest([]));
+        let response = self
+            .request(
+                context,
+                Method::GET,
+                name,
+                Vec::new(),
+                payload_hash,
+                Empty::<Bytes>::new(),
+            )
+            .await?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        ensure!(status.is_success(), "Failed to download {name}: {status}");
+        Ok(Some(response))
+    }
+
+    /// Downloads an object, returns `None` if it does not exist.
+    async fn get(&self, context: &Context, name: &str) -> Result<Option<Vec<u8>>> {
+        #[cfg(test)]
+        if let Self::Mock(objects) = self {
+            return Ok(objects.lock().unwrap().get(name).cloned());
+        }
+        match self.get_response(context, name).await? {
+            Some(response) => Ok(Some(response.collect().await?.to_bytes().to_vec())),
+            None => Ok(None),
+        }
+    }
+
+    /// Downloads an object to the file at `path` without holding it in memory as a whole.
+    ///
+    /// Returns false if the object does not exist.
+    async fn get_to_file(&self, context: &Context, name: &str, path: &Path) -> Result<bool> {
+        #[cfg(test)]
+        if let Self::Mock(_) = self {
+            let Some(data) = self.get(context, name).await? else {
+                return Ok(false);
+            };
+            fs::write(path, data).await?;
+            return Ok(true);
+        }
+        let Some(response) = self.get_response(context, name).await? else {
+            return Ok(false);
+        };
+        let mut body = response.into_body();
+        let mut file = File::create(path).await?;
+        while let Some(frame) = body.frame().await {
+            if let Ok(data) = frame?.into_data() {
+                file.write_all(&data).await?;
+            }
+        }
+        file.sync_all().await?;
+        Ok(true)
+    }
+
+    async fn delete(&self, context: &Context, name: &str) -> Result<()> {
+        #[cfg(test)]
+        if let Self::Mock(objects) = self {
+            objects.lock().unwrap().remove(name);
+            return Ok(());
+        }
+        let payload_hash = hex::encode(Sha256::digest([]));
+        let response = self
+            .request(
+                context,
+                Method::DELETE,
+                name,
+                Vec::new(),
+                payload_hash,
+                Empty::<Bytes>::new(),
+            )
+            .await?;
+        let status = response.status();
+        ensure!(
+            status.is_success() || status == StatusCode::NOT_FOUND,
+            "Failed to delete {name}: {status}"
+        );
+        Ok(())
+    }
+
+    async fn put_encrypted(
+        &self,
+        context: &Context,
+        passphrase: &str,
+        name: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        let encrypted = pgp::symm_encrypt(passphrase, data).await?;
+        self.put(context, name, encrypted.into_bytes()).await
+    }
+
+    async fn get_decrypted(
+        &self,
+        context: &Context,
+        passphrase: &str,
+        name: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        match self.get(context, name).await? {
+            Some(encrypted) => Ok(Some(
+                pgp::symm_decrypt(passphrase, Cursor::new(encrypted))
+                    .await
+                    .with_context(|| format!("Failed to decrypt {name}"))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    async fn load_index(&self, context: &Context, passphrase: &str) -> Result<Index> {
+        match self.get_decrypted(context, passphrase, INDEX_NAME).await? {
+            Some(index) => Ok(serde_json::from_slice(&index)?),
+            None => Ok(Index::default()),
+        }
+    }
+
+    async fn load_manifest(
+        &self,
+        context: &Context,
+        passphrase: &str,
+        id: i64,
+    ) -> Result<Manifest> {
+        let manifest = self
+            .get_decrypted(context, passphrase, &manifest_name(id))
+            .await?
+            .with_context(|| format!("Manifest of remote backup {id} is missing"))?;
+        Ok(serde_json::from_slice(&manifest)?)
+    }
+}
+
+/// Returns a request body streaming the content of `file`.
+fn file_body(
+    file: File,
+) -> StreamBody<impl Stream<Item = std::io::Result<Frame<Bytes>>> + Send + 'static> {
+    StreamBody::new(futures::stream::try_unfold(file, |mut file| async move {
+        let mut buf = vec![0; 64 * 1024];
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            return std::io::Result::Ok(None);
+        }
+        buf.truncate(n);
+        std::io::Result::Ok(Some((Frame::data(Bytes::from(buf)), file)))
+    }))
+}
+
+fn db_name(id: i64) -> String {
+    format!("db-{id}.sqlite")
+}
+
+fn manifest_name(id: i64) -> String {
+    format!("manifest-{id}.asc")
+}
+
+/// Returns the name of the blob with the hex-encoded SHA-256 hash `sha256`
+/// on the remote target.
+///
+/// Blobs with the same content are stored once.
+/// As the hash is keyed with the passphrase,
+/// the name does not reveal whether a known file is part of the backup.
+fn blob_object_name(passphrase: &str, sha256: &str) -> String {
+    let hash = hmac_sha256(passphrase.as_bytes(), sha256.as_bytes());
+    format!("blob-{}", hex::encode(hash))
+}
+
+/// Returns the name of the object storing the chunk `i` of the blob `object`.
+fn chunk_name(object: &str, i: u64) -> String {
+    format!("{object}-{i}.asc")
+}
+
+/// Returns the number of chunks a blob of `size` bytes is split into.
+///
+/// Empty blobs are stored as one empty chunk.
+fn chunk_count(size: u64) -> u64 {
+    size.div_ceil(CHUNK_SIZE).max(1)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Derives the AWS Signature Version 4 signing key.
+fn aws_signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{secret_key}").as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+/// Returns the `Authorization` header of an S3 request
+/// signed with AWS Signature Version 4.
+#[allow(clippy::too_many_arguments)]
+fn s3_authorization(
+    access_key: &str,
+    secret_key: &str,
+    region: &str,
+    method: &str,
+    host: &str,
+    path: &str,
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    let date = amz_date.get(..8).unwrap_or_default();
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "{method}\n{path}\n\n\
+         host:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n\
+         {signed_headers}\n{payload_hash}"
+    );
+    let scope = format!("{date}/{region}/s3/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request))
+    );
+    let signing_key = aws_signing_key(secret_key, date, region, "s3");
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={access_key}/{scope}, \
+         SignedHeaders={signed_headers}, Signature={signature}"
+    )
+}
+
+async fn load_passphrase(context: &Context) -> Result<String> {
+    context
+        .get_config(Config::RemoteBackupPassphrase)
+        .await?
+        .filter(|passphrase| !passphrase.is_empty())
+        .context("No remote backup passphrase set")
+}
+
+fn temp_path(context: &Context, name: &str) -> PathBuf {
+    let mut path = context.get_dbfile().as_os_str().to_owned();
+    path.push(format!("-{name}"));
+    PathBuf::from(path)
+}
+
+/// Uploads a backup to the remote target now.
+///
+/// Fails if a remote backup is already running.
+pub async fn run_remote_backup(context: &Context) -> Result<()> {
+    let running = Arc::clone(&context.remote_backup.running)
+        .try_lock_owned()
+        .ok()
+        .context("Remote backup is already running")?;
+    run(context, running).await
+}
+
+/// Starts an automated remote backup in the background if one is due.
+pub(crate) async fn maybe_start_remote_backup(context: &Context) {
+    let due = async {
+        if context
+            .get_config(Config::RemoteBackupUrl)
+            .await?
+            .filter(|url| !url.is_empty())
+            .is_none()
+        {
+            return Ok(false);
+        }
+        let interval = context.get_config_i64(Config::RemoteBackupInterval).await?;
+        let last_backup = context.get_config_i64(Config::LastRemoteBackup).await?;
+        let now = time();
+        let last_attempt = context.remote_backup.last_attempt.load(Ordering::Relaxed);
+        anyhow::Ok(
+            interval > 0
+                && last_backup.saturating_add(interval.saturating_mul(60 * 60)) <= now
+                && last_attempt.saturating_add(RETRY_INTERVAL) <= now,
+        )
+    };
+    if !due.await.log_err(context).unwrap_or_default() {
+        return;
+    }
+    let Ok(running) = Arc::clone(&context.remote_backup.running).try_lock_owned() else {
+        return;
+    };
+    context
+        .remote_backup
+        .last_attempt
+        .store(time(), Ordering::Relaxed);
+    let context = context.clone();
+    tokio::spawn(async move {
+        run(&context, running)
+            .await
+            .context("Remote backup failed")
+            .log_err(&context)
+            .ok();
+    });
+}
+
+async fn run(context: &Context, _running: OwnedMutexGuard<()>) -> Result<()> {
+    let target = Target::load(context).await?;
+    let passphrase = load_passphrase(context).await?;
+    backup(context, &target, &passphrase).await
+}
+
+async fn backup(context: &Context, target: &Target, passphrase: &str) -> Result<()> {
+    let mut index = target.load_index(context, passphrase).await?;
+    let previous_blobs: BTreeSet<String> = match index.backups.last() {
+        Some(previous) => match target.load_manifest(context, passphrase, previous.id).await {
+            Ok(manifest) => manifest.blobs.into_iter().map(|blob| blob.object).collect(),
+            Err(err) => {
+                warn!(
+                    context,
+                    "Cannot load previous remote backup, uploading all blobs: {err:#}."
+                );
+                BTreeSet::new()
+            }
+        },
+        None => BTreeSet::new(),
+    };
+    let now = time();
+    let id = match index.backups.last() {
+        Some(previous) => now.max(previous.id + 1),
+        None => now,
+    };
+    info!(context, "Starting remote backup {id}.");
+
+    let db_path = TempPathGuard::new(temp_path(context, "remote-backup.sqlite"));
+    imex::export_database_snapshot(context, &db_path, passphrase.to_string()).await?;
+    let mut size = fs::metadata(&*db_path).await?.len();
+    target.put_file(context, &db_name(id), &db_path).await?;
+    drop(db_path);
+
+    let mut manifest = Manifest::default();
+    let mut uploaded = BTreeSet::new();
+    let blobdir = BlobDirContents::new(context).await?;
+    for blob in blobdir.iter() {
+        let path = blob.to_abs_path();
+        let Ok(metadata) = fs::metadata(&path).await else {
+            warn!(
+                context,
+                "Blob {} vanished during remote backup.",
+                blob.as_name()
+            );
+            continue;
+        };
+        let sha256 = tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || hash_file(&path)
+        })
+        .await??;
+        let object = blob_object_name(passphrase, &sha256);
+        if !previous_blobs.contains(&object) && !uploaded.contains(&object) {
+            upload_blob(context, target, passphrase, &path, &object, metadata.len()).await?;
+            uploaded.insert(object.clone());
+        }
+        size += metadata.len();
+        manifest.blobs.push(ManifestBlob {
+            name: blob.as_name().to_string(),
+            object,
+            size: metadata.len(),
+        });
+    }
+    target
+        .put_encrypted(
+            context,
+            passphrase,
+            &manifest_name(id),
+            &serde_json::to_vec(&manifest)?,
+        )
+        .await?;
+
+    index.backups.push(RemoteBackupInfo {
+        id,
+        timestamp: now,
+        size,
+        blob_count: manifest.blobs.len(),
+    });
+    let keep = context
+        .get_config_int(Config::RemoteBackupKeep)
+        .await?
+        .max(1) as usize;
+    let expired: Vec<RemoteBackupInfo> = index
+        .backups
+        .drain(..index.backups.len().saturating_sub(keep))
+        .collect();
+    target
+        .put_encrypted(
+            context,
+            passphrase,
+            INDEX_NAME,
+            &serde_json::to_vec(&index)?,
+        )
+        .await?;
+    context
+        .set_config_internal(Config::LastRemoteBackup, Some(&now.to_string()))
+        .await?;
+    info!(
+        context,
+        "Remote backup {id} done, uploaded {} of {} blobs.",
+        uploaded.len(),
+        manifest.blobs.len()
+    );
+
+    if !expired.is_empty() {
+        prune(context, target, passphrase, &index, &expired)
+            .await
+            .context("Failed to delete expired remote backups")
+            .log_err(context)
+            .ok();
+    }
+    Ok(())
+}
+
+/// Uploads the blob at `path` of `size` bytes in encrypted chunks.
+async fn upload_blob(
+    context: &Context,
+    target: &Target,
+    passphrase: &str,
+    path: &Path,
+    object: &str,
+    size: u64,
+) -> Result<()> {
+    let mut file = File::open(path).await?;
+    for i in 0..chunk_count(size) {
+        let mut chunk = Vec::new();
+        (&mut file).take(CHUNK_SIZE).read_to_end(&mut chunk).await?;
+        target
+            .put_encrypted(context, passphrase, &chunk_name(object, i), &chunk)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Deletes the `expired` backups and the blobs not referred to by the backups in `index`.
+///
+/// Backups whose manifest is missing are skipped when collecting the blobs in use
+/// and deleted without their blobs.
+async fn prune(
+    context: &Context,
+    target: &Target,
+    passphrase: &str,
+    index: &Index,
+    expired: &[RemoteBackupInfo],
+) -> Result<()> {
+    let mut kept_blobs = BTreeSet::new();
+    for backup in &index.backups {
+        match target.load_manifest(context, passphrase, backup.id).await {
+            Ok(manifest) => {
+                kept_blobs.extend(manifest.blobs.into_iter().map(|blob| blob.object));
+            }
+            Err(err) => warn!(context, "Cannot load remote backup {}: {err:#}.", backup.id),
+        }
+    }
+    for backup in expired {
+        match target.load_manifest(context, passphrase, backup.id).await {
+            Ok(manifest) => {
+                for blob in manifest.blobs {
+                    if kept_blobs.insert(blob.object.clone()) {
+                        for i in 0..chunk_count(blob.size) {
+                            target.delete(context, &chunk_name(&blob.object, i)).await?;
+                        }
+                    }
+                }
+            }
+            Err(err) => warn!(context, "Cannot load remote backup {}: {err:#}.", backup.id),
+        }
+        target.delete(context, &manifest_name(backup.id)).await?;
+        target.delete(context, &db_name(backup.id)).await?;
+        info!(context, "Deleted expired remote backup {}.", backup.id);
+    }
+    Ok(())
+}
+
+/// Returns the backups stored on the remote target, oldest first.
+///
+/// Needs the remote backup options to be set, the account may be unconfigured.
+pub async fn list_remote_backups(context: &Context) -> Result<Vec<RemoteBackupInfo>> {
+    let target = Target::load(context).await?;
+    let passphrase = load_passphrase(context).await?;
+    Ok(target.load_index(context, &passphrase).await?.backups)
+}
+
+/// Downloads the remote backup `id` and imports it.
+///
+/// Like importing a backup file, this is only possible as long as the account is not configured.
+/// Progress of the import is reported with [`EventType::ImexProgress`] events.
+///
+/// [`EventType::ImexProgress`]: crate::EventType::ImexProgress
+pub async fn restore_remote_backup(context: &Context, id: i64) -> Result<()> {
+    let target = Target::load(context).await?;
+    let passphrase = load_passphrase(context).await?;
+    restore(context, &target, &passphrase, id).await
+}
+
+async fn restore(context: &Context, target: &Target, passphrase: &str, id: i64) -> Result<()> {
+    ensure!(
+        !context.is_configured().await?,
+        "Cannot import backups to accounts in use."
+    );
+    let manifest = target.load_manifest(context, passphrase, id).await?;
+    let db_path = TempPathGuard::new(temp_path(context, "remote-backup.sqlite"));
+    ensure!(
+        target.get_to_file(context, &db_name(id), &db_path).await?,
+        "Database of remote backup {id} is missing"
+    );
+
+    let tar_path = TempPathGuard::new(temp_path(context, "remote-backup.tar"));
+    let mut builder = tokio_tar::Builder::new(File::create(&tar_path).await?);
+    builder
+        .append_path_with_name(&db_path, DBFILE_BACKUP_NAME)
+        .await?;
+    drop(db_path);
+    let blob_path = TempPathGuard::new(temp_path(context, "remote-backup.blob"));
+    for blob in manifest.blobs {
+        let mut file = File::create(&blob_path).await?;
+        for i in 0..chunk_count(blob.size) {
+            let chunk = target
+                .get_decrypted(context, passphrase, &chunk_name(&blob.object, i))
+                .await?
+                .with_context(|| format!("Blob {} of remote backup is missing", blob.name))?;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        drop(file);
+        let path = PathBuf::from(BLOBS_BACKUP_NAME).join(&blob.name);
+        builder.append_path_with_name(&blob_path, path).await?;
+    }
+    drop(blob_path);
+    builder.into_inner().await?.sync_all().await?;
+
+    info!(context, "Downloaded remote backup {id}, importing.");
+    imex::imex(
+        context,
+        ImexMode::ImportBackup,
+        &tar_path,
+        Some(passphrase.to_string()),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, Viewtype};
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    #[test]
+    fn test_aws_signing_key() {
+        // Example from the AWS Signature Version 4 documentation.
+        let key = aws_signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_blob_object_name() {
+        let sha256 = hex::encode(Sha256::digest(b"content"));
+        let name = blob_object_name("passphrase", &sha256);
+        assert!(name.starts_with("blob-"));
+        assert!(!name.contains(&sha256));
+        assert_eq!(name, blob_object_name("passphrase", &sha256));
+        assert_ne!(name, blob_object_name("other passphrase", &sha256));
+        assert_eq!(chunk_name(&name, 1), format!("{name}-1.asc"));
+    }
+
+    #[test]
+    fn test_chunk_count() {
+        assert_eq!(chunk_count(0), 1);
+        assert_eq!(chunk_count(1), 1);
+        assert_eq!(chunk_count(CHUNK_SIZE), 1);
+        assert_eq!(chunk_count(CHUNK_SIZE + 1), 2);
+    }
+
+    fn object_count(target: &Target, prefix: &str) -> usize {
+        let Target::Mock(objects) = target else {
+            unreachable!()
+        };
+        let objects = objects.lock().unwrap();
+        objects
+            .keys()
+            .filter(|name| name.starts_with(prefix))
+            .count()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_remote_backup_round_trip() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let target = Target::Mock(Default::default());
+        let passphrase = "passphrase";
+
+        let chat_id = alice.create_chat(bob).await.id;
+        let mut msg = Message::new(Viewtype::File);
+        msg.set_file_from_bytes(alice, "doc.txt", b"secret content", None)
+            .await?;
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        backup(alice, &target, passphrase).await?;
+        let index = target.load_index(alice, passphrase).await?;
+        assert_eq!(index.backups.len(), 1);
+        let blob_count = object_count(&target, "blob-");
+        assert!(blob_count > 0);
+        {
+            let Target::Mock(objects) = &target else {
+                unreachable!()
+            };
+            let objects = objects.lock().unwrap();
+            assert!(!objects
+                .values()
+                .any(|data| data.windows(14).any(|w| w == b"secret content")));
+        }
+
+        // Unchanged blobs are not uploaded again.
+        backup(alice, &target, passphrase).await?;
+        assert_eq!(target.load_index(alice, passphrase).await?.backups.len(), 2);
+        assert_eq!(object_count(&target, "blob-"), blob_count);
+
+        // A missing manifest of the previous backup does not break further backups.
+        let previous = target.load_index(alice, passphrase).await?.backups[1].id;
+        target.delete(alice, &manifest_name(previous)).await?;
+        backup(alice, &target, passphrase).await?;
+        assert_eq!(object_count(&target, "blob-"), blob_count);
+
+        // Expired backups are deleted.
+        alice
+            .set_config(Config::RemoteBackupKeep, Some("1"))
+            .await?;
+        backup(alice, &target, passphrase).await?;
+        let index = target.load_index(alice, passphrase).await?;
+        assert_eq!(index.backups.len(), 1);
+        assert_eq!(object_count(&target, "db-"), 1);
+        assert_eq!(object_count(&target, "manifest-"), 1);
+        assert_eq!(object_count(&target, "blob-"), blob_count);
+
+        let restored = &TestContext::new().await;
+        restore(restored, &target, passphrase, index.backups[0].id).await?;
+        assert_eq!(
+            restored.get_config(Config::ConfiguredAddr).await?,
+            alice.get_config(Config::ConfiguredAddr).await?
+        );
+        let msg = Message::load_from_db(restored, sent.sender_msg_id).await?;
+        assert_eq!(
+            fs::read(msg.get_file(restored).unwrap()).await?,
+            b"secret content"
+        );
+
+        // Backups can't be restored to accounts in use.
+        assert!(restore(alice, &target, passphrase, index.backups[0].id)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+//! # Reporting of offending messages.
+//!
+//! Members of a group can report messages with [`report_msg`].
+//! The report contains the reported message with its headers and the reason given by the reporter.
+//! It is sent as a hidden message to the moderators of the group set with [`set_moderators`],
+//! or, if the group has no moderators, to [`Config::AbuseAddress`],
+//! falling back to the admin contact announced by a chatmail relay.
+//! Reports are end-to-end encrypted if the keys of the recipients are known.
+//!
+//! Moderators are a local setting of each member,
+//! e.g. taken from the description of a public group.
+//!
+//! Received reports are stored, [`EventType::IncomingMsgReport`] is emitted for each of them
+//! and they can be listed with [`get_msg_reports`].
+
+use anyhow::{bail, ensure, Result};
+use deltachat_contact_tools::ContactAddress;
+use lettre_email::PartBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{self, get_chat_id_by_grpid, is_contact_in_chat, Chat, ChatId};
+use crate::config::Config;
+use crate::constants::{Blocked, Chattype};
+use crate::contact::{Contact, ContactId, Origin};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::{self, Message, MsgId, Viewtype};
+use crate::mimefactory::wrapped_base64_encode;
+use crate::mimeparser::{MimeMessage, ParsedAttachment, SystemMessage};
+use crate::param::Param;
+use crate::tools::time;
+
+/// Reported message as sent in a report.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReportData {
+    /// Message-ID of the reported message.
+    rfc724_mid: String,
+
+    /// ID of the group the message was sent to, empty for other chats.
+    grpid: String,
+
+    chat_name: String,
+
+    from_addr: String,
+
+    timestamp: i64,
+
+    text: String,
+
+    /// Headers of the reported message as far as they are known.
+    headers: String,
+
+    reason: String,
+}
+
+/// Report received from another user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MsgReport {
+    /// ID of the report to pass to [`delete_msg_report`].
+    pub id: u32,
+
+    /// Contact who reported the message.
+    pub reporter_id: ContactId,
+
+    /// Message-ID of the reported message.
+    pub rfc724_mid: String,
+
+    /// ID of the group the message was sent to, empty for other chats.
+    pub grpid: String,
+
+    /// Name of the chat the message was sent to.
+    pub chat_name: String,
+
+    /// Address of the sender of the reported message.
+    pub from_addr: String,
+
+    /// Sending time of the reported message.
+    pub msg_timestamp: i64,
+
+    /// Text of the reported message.
+    pub text: String,
+
+    /// Headers of the reported message as far as the reporter knew them.
+    pub headers: String,
+
+    /// Reason given by the reporter.
+    pub reason: String,
+
+    /// Time the report was received.
+    pub timestamp: i64,
+}
+
+/// Sets the moderators of the group `chat_id` reports are sent to.
+pub async fn set_moderators(
+    context: &Context,
+    chat_id: ChatId,
+    contact_ids: &[ContactId],
+) -> Result<()> {
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(
+        chat.typ == Chattype::Group,
+        "Moderators can only be set for groups"
+    );
+    ensure!(
+        contact_ids
+            .iter()
+            .all(|contact_id| !contact_id.is_special()),
+        "Moderators must be regular contacts"
+    );
+    context
+        .sql
+        .transaction(move |transaction| {
+            transaction.execute("DELETE FROM chat_moderators WHERE chat_id=?", (chat_id,))?;
+            let mut stmt = transaction.prepare(
+                "INSERT OR IGNORE INTO chat_moderators (chat_id, contact_id) VALUES (?, ?)",
+            )?;
+            for contact_id in contact_ids {
+                stmt.execute((chat_id, contact_id))?;
+            }
+            Ok(())
+        })
+        .await?;
+    context.emit_event(EventType::ChatModified(chat_id));
+    Ok(())
+}
+
+/// Returns the moderators of the group `chat_id`.
+pub async fn get_moderators(context: &Context, chat_id: ChatId) -> Result<Vec<ContactId>> {
+    context
+        .sql
+        .query_map(
+            "SELECT contact_id FROM chat_moderators WHERE chat_id=? ORDER BY contact_id",
+            (chat_id,),
+            |row| row.get::<_, ContactId>(0),
+            |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+/// Returns the address reports are sent to if a chat has no moderators.
+async fn get_abuse_addr(context: &Context) -> Result<Option<String>> {
+    if let Some(addr) = context
+        .get_config(Config::AbuseAddress)
+        .await?
+        .filter(|addr| !addr.is_empty())
+    {
+        return Ok(Some(addr));
+    }
+    if !context.is_chatmail().await? {
+        return Ok(None);
+    }
+    Ok(context
+        .metadata
+        .read()
+        .await
+        .as_ref()
+        .and_then(|metadata| metadata.admin.as_deref())
+        .and_then(|admin| admin.strip_prefix("mailto:"))
+        .map(|addr| addr.to_string()))
+}
+
+/// Reports the message `msg_id` with the given `reason`
+/// to the moderators of its chat or the abuse address.
+pub async fn report_msg(context: &Context, msg_id: MsgId, reason: &str) -> Result<()> {
+    let msg = Message::load_from_db(context, msg_id).await?;
+    ensure!(
+        !msg.from_id.is_special(),
+        "Only messages received from other users can be reported"
+    );
+    let chat = Chat::load_from_db(context, msg.chat_id).await?;
+
+    let mut recipients = get_moderators(context, chat.id).await?;
+    if recipients.is_empty() {
+        let Some(addr) = get_abuse_addr(context).await? else {
+            bail!("No moderators or abuse address to report the message to");
+        };
+        let (contact_id, _) =
+            Contact::add_or_lookup(context, "", &ContactAddress::new(&addr)?, Origin::Hidden)
+                .await?;
+        recipients.push(contact_id);
+    }
+
+    let from = Contact::get_by_id(context, msg.from_id).await?;
+    let mut headers =
+        String::from_utf8_lossy(&message::get_mime_headers(context, msg_id).await?).to_string();
+    if headers.is_empty() {
+        headers = format!(
+            "Message-ID: <{}>\r\nFrom: {}\r\nDate: {}\r\n",
+            msg.rfc724_mid,
+            from.get_addr(),
+            chrono::DateTime::<chrono::Utc>::from_timestamp(msg.timestamp_sent, 0)
+                .unwrap_or_default()
+                .to_rfc2822()
+        );
+    }
+    let report = ReportData {
+        rfc724_mid: msg.rfc724_mid.clone(),
+        grpid: match chat.typ {
+            Chattype::Group => chat.grpid.clone(),
+            _ => String::new(),
+        },
+        chat_name: chat.name.clone(),
+        from_addr: from.get_addr().to_string(),
+        timestamp: msg.timestamp_sent,
+        text: msg.get_text(),
+        headers,
+        reason: reason.to_string(),
+    };
+    let json = serde_json::to_string(&report)?;
+
+    for contact_id in recipients {
+        let chat_id =
+            ChatId::create_for_contact_with_blocked(context, contact_id, Blocked::Yes).await?;
+        let mut report_msg = Message::new(Viewtype::Text);
+        report_msg.hidden = true;
+        report_msg.param.set_cmd(SystemMessage::MsgReport);
+        report_msg.param.set(Param::Arg, &json);
+        chat::send_msg(context, chat_id, &mut report_msg).await?;
+    }
+    info!(context, "Reported message {msg_id}.");
+    Ok(())
+}
+
+pub(crate) fn build_report_part(json: &str) -> PartBuilder {
+    let encoded_body = wrapped_base64_encode(json.as_bytes());
+
+    PartBuilder::new()
+        .content_type(&"application/json".parse::<mime::Mime>().unwrap())
+        .header((
+            "Content-Disposition",
+            ParsedAttachment::MsgReport.content_disposition(),
+        ))
+        .header(("Content-Transfer-Encoding", "base64"))
+        .body(encoded_body)
+}
+
+/// Stores the report received from `from_id`.
+///
+/// Reports of group messages are only accepted
+/// if both we and the reporter are members of the group.
+pub(crate) async fn receive_report(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+) -> Result<()> {
+    let Some(json) = &mime_parser.msg_report else {
+        return Ok(());
+    };
+    ensure!(!from_id.is_special(), "Report from special contact");
+    let report: ReportData = serde_json::from_str(json)?;
+    if !report.grpid.is_empty() {
+        let Some((chat_id, ..)) = get_chat_id_by_grpid(context, &report.grpid).await? else {
+            bail!("Report of message in unknown group");
+        };
+        ensure!(
+            is_contact_in_chat(context, chat_id, ContactId::SELF).await?
+                && is_contact_in_chat(context, chat_id, from_id).await?,
+            "Report of message in {chat_id} from non-member {from_id}"
+        );
+    }
+    let report_id = context
+        .sql
+        .insert(
+            "INSERT INTO msg_reports
+             (reporter_id, rfc724_mid, grpid, chat_name, from_addr, msg_timestamp,
+              txt, headers, reason, timestamp)
+             VALUES (?,?,?,?,?,?, ?,?,?,?)",
+            (
+                from_id,
+                &report.rfc724_mid,
+                &report.grpid,
+                &report.chat_name,
+                &report.from_addr,
+                report.timestamp,
+                &report.text,
+                &report.headers,
+                &report.reason,
+                time(),
+            ),
+        )
+        .await?;
+    let report_id = u32::try_from(report_id)?;
+    info!(
+        context,
+        "Received report {report_id} of message {} from {from_id}.", report.rfc724_mid
+    );
+    context.emit_event(EventType::IncomingMsgReport { report_id });
+    Ok(())
+}
+
+/// Returns the received reports, newest first.
+pub async fn get_msg_reports(context: &Context) -> Result<Vec<MsgReport>> {
+    context
+        .sql
+        .query_map(
+            "SELECT id, reporter_id, rfc724_mid, grpid, chat_name, from_addr, msg_timestamp,
+                    txt, headers, reason, timestamp
+             FROM msg_reports ORDER BY timestamp DESC, id DESC",
+            (),
+            |row| {
+                Ok(MsgReport {
+                    id: row.get(0)?,
+                    reporter_id: row.get(1)?,
+                    rfc724_mid: row.get(2)?,
+                    grpid: row.get(3)?,
+                    chat_name: row.get(4)?,
+                    from_addr: row.get(5)?,
+                    msg_timestamp: row.get(6)?,
+                    text: row.get(7)?,
+                    headers: row.get(8)?,
+                    reason: row.get(9)?,
+                    timestamp: row.get(10)?,
+                })
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+/// Deletes a received report, e.g. after it was handled.
+pub async fn delete_msg_report(context: &Context, report_id: u32) -> Result<()> {
+    context
+        .sql
+        .execute("DELETE FROM msg_reports WHERE id=?", (report_id,))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{
+        add_contact_to_chat, create_group_chat, remove_contact_from_chat, ProtectionStatus,
+    };
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_report_msg() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let alice_chat_id =
+            create_group_chat(alice, ProtectionStatus::Unprotected, "Public").await?;
+        let alice_bob_id = alice.add_or_lookup_contact_id(bob).await;
+        let alice_fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+        add_contact_to_chat(alice, alice_chat_id, alice_bob_id).await?;
+        add_contact_to_chat(alice, alice_chat_id, alice_fiona_id).await?;
+        let sent = alice.send_text(alice_chat_id, "Welcome").await;
+        let bob_chat_id = bob.recv_msg(&sent).await.chat_id;
+        let fiona_chat_id = fiona.recv_msg(&sent).await.chat_id;
+        bob.create_chat(fiona).await;
+
+        let sent = alice.send_text(alice_chat_id, "Buy cheap stuff!").await;
+        let bob_msg = bob.recv_msg(&sent).await;
+
+        // Without moderators or abuse address, the message cannot be reported.
+        assert!(report_msg(bob, bob_msg.id, "Spam").await.is_err());
+
+        let bob_fiona_id = bob.add_or_lookup_contact_id(fiona).await;
+        assert!(set_moderators(bob, bob_chat_id, &[ContactId::SELF])
+            .await
+            .is_err());
+        set_moderators(bob, bob_chat_id, &[bob_fiona_id]).await?;
+        assert_eq!(get_moderators(bob, bob_chat_id).await?, [bob_fiona_id]);
+        report_msg(bob, bob_msg.id, "Spam").await?;
+        let sent = bob.pop_sent_msg().await;
+        assert!(sent.payload().contains("Chat-Content: message-report"));
+
+        fiona.recv_msg_trash(&sent).await;
+        let event = fiona
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::IncomingMsgReport { .. }))
+            .await;
+        let reports = get_msg_reports(fiona).await?;
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(
+            event,
+            EventType::IncomingMsgReport {
+                report_id: report.id
+            }
+        );
+        assert_eq!(
+            report.reporter_id,
+            fiona.add_or_lookup_contact_id(bob).await
+        );
+        assert_eq!(report.rfc724_mid, bob_msg.rfc724_mid);
+        assert_eq!(report.chat_name, "Public");
+        assert_eq!(report.from_addr, "alice@example.org");
+        assert_eq!(report.text, "Buy cheap stuff!");
+        assert_eq!(report.reason, "Spam");
+        assert!(report.headers.contains(&bob_msg.rfc724_mid));
+
+        delete_msg_report(fiona, report.id).await?;
+        assert!(get_msg_reports(fiona).await?.is_empty());
+
+        // Reports for groups the moderator is not a member of are rejected.
+        remove_contact_from_chat(fiona, fiona_chat_id, ContactId::SELF).await?;
+        report_msg(bob, bob_msg.id, "Spam").await?;
+        fiona.recv_msg_trash(&bob.pop_sent_msg().await).await;
+        assert!(get_msg_reports(fiona).await?.is_empty());
+
+        // Without moderators, reports go to the abuse address.
+        set_moderators(bob, bob_chat_id, &[]).await?;
+        bob.set_config(Config::AbuseAddress, Some("abuse@example.net"))
+            .await?;
+        report_msg(bob, bob_msg.id, "Spam").await?;
+        let sent = bob.pop_sent_msg().await;
+        assert_eq!(sent.recipient().to_string(), "abuse@example.net");
+        Ok(())
+    }
+}
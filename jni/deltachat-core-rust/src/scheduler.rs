@@ -1,4 +1,5 @@
 use std::cmp;
+use std::collections::BTreeMap;
 use std::iter::{self, once};
 use std::num::NonZeroUsize;
 use std::sync::atomic::Ordering;
@@ -18,13 +19,20 @@
 use crate::download::{download_msg, DownloadState};
 use crate::ephemeral::{self, delete_expired_imap_messages};
 use crate::events::EventType;
+use crate::health;
+use crate::housekeeping;
 use crate::imap::{session::Session, FolderMeaning, Imap};
 use crate::location;
 use crate::log::LogExt;
+use crate::mdn;
 use crate::message::MsgId;
-use crate::smtp::{send_smtp_messages, Smtp};
+use crate::ownership::OWNER_NOTIFY_INTERVAL;
+use crate::remote_backup;
+use crate::server_capabilities;
+use crate::smtp::{send_smtp_messages, throttle, Smtp};
 use crate::sql;
-use crate::tools::{self, duration_to_str, maybe_add_time_based_warnings, time, time_elapsed};
+use crate::tools::{self, duration_to_str, maybe_add_time_based_warnings, time_elapsed};
+use crate::undo;
 
 pub(crate) mod connectivity;
 
@@ -36,6 +44,9 @@
 #[derive(Debug, Default)]
 pub(crate) struct SchedulerState {
     inner: RwLock<InnerSchedulerState>,
+
+    /// Pause guards held on behalf of [`Context::pause_io`] callers, by reason.
+    pause_reasons: parking_lot::Mutex<BTreeMap<String, Vec<IoPausedGuard>>>,
 }
 
 impl SchedulerState {
@@ -194,6 +205,44 @@ pub(crate) async fn pause(&'_ self, context: Context) -> Result<IoPausedGuard> {
         Ok(IoPausedGuard { sender: Some(tx) })
     }
 
+    /// Pauses the IO scheduler for `reason` until [`SchedulerState::resume_reason`]
+    /// is called with the same reason as many times as this function.
+    pub(crate) async fn pause_reason(&self, context: &Context, reason: &str) -> Result<()> {
+        let guard = self.pause(context.clone()).await?;
+        self.pause_reasons
+            .lock()
+            .entry(reason.to_string())
+            .or_default()
+            .push(guard);
+        context.emit_event(EventType::ConnectivityChanged);
+        Ok(())
+    }
+
+    /// Releases one pause of the IO scheduler for `reason`.
+    ///
+    /// The scheduler is resumed when there are no pauses left.
+    pub(crate) fn resume_reason(&self, context: &Context, reason: &str) -> Result<()> {
+        let guard = {
+            let mut pause_reasons = self.pause_reasons.lock();
+            let Some(guards) = pause_reasons.get_mut(reason) else {
+                bail!("IO is not paused for {reason:?}");
+            };
+            let guard = guards.pop();
+            if guards.is_empty() {
+                pause_reasons.remove(reason);
+            }
+            guard
+        };
+        drop(guard);
+        context.emit_event(EventType::ConnectivityChanged);
+        Ok(())
+    }
+
+    /// Returns the reasons the IO scheduler is paused for with [`Context::pause_io`].
+    pub(crate) fn pause_reasons(&self) -> Vec<String> {
+        self.pause_reasons.lock().keys().cloned().collect()
+    }
+
     /// Restarts the scheduler, only if it is running.
     pub(crate) async fn restart(&self, context: &Context) {
         info!(context, "restarting IO");
@@ -402,12 +451,21 @@ async fn inbox_loop(
 
         let mut old_session: Option<Session> = None;
         loop {
-            let session = if let Some(session) = old_session.take() {
+            let session = if let Some(session) = old_session
+                .take()
+                .filter(|_| !connection.network_changed(&ctx))
+            {
                 session
             } else {
                 match connection.prepare(&ctx).await {
                     Err(err) => {
                         warn!(ctx, "Failed to prepare INBOX connection: {:#}.", err);
+                        // Report repeated login failures even if the connection cannot be established.
+                        health::maybe_evaluate(&ctx)
+                            .await
+                            .context("Failed to evaluate account health")
+                            .log_err(&ctx)
+                            .ok();
                         continue;
                     }
                     Ok(session) => session,
@@ -487,18 +545,19 @@ async fn inbox_fetch_idle(ctx: &Context, imap: &mut Imap, mut session: Session)
     }
 
     maybe_add_time_based_warnings(ctx).await;
+    health::maybe_evaluate(ctx)
+        .await
+        .context("Failed to evaluate account health")
+        .log_err(ctx)
+        .ok();
 
-    match ctx.get_config_i64(Config::LastHousekeeping).await {
-        Ok(last_housekeeping_time) => {
-            let next_housekeeping_time = last_housekeeping_time.saturating_add(60 * 60 * 24);
-            if next_housekeeping_time <= time() {
-                sql::housekeeping(ctx).await.log_err(ctx).ok();
-            }
-        }
-        Err(err) => {
-            warn!(ctx, "Failed to get last housekeeping time: {}", err);
-        }
-    };
+    housekeeping::maybe_start_housekeeping(ctx).await;
+    remote_backup::maybe_start_remote_backup(ctx).await;
+    sql::maybe_checkpoint_wal(ctx)
+        .await
+        .context("Failed to checkpoint WAL")
+        .log_err(ctx)
+        .ok();
 
     match ctx.get_config_bool(Config::FetchedExistingMsgs).await {
         Ok(fetched_existing_msgs) => {
@@ -531,6 +590,9 @@ async fn inbox_fetch_idle(ctx: &Context, imap: &mut Imap, mut session: Session)
         .fetch_metadata(ctx)
         .await
         .context("Failed to fetch metadata")?;
+    server_capabilities::update(ctx, &session)
+        .await
+        .context("Failed to update server capabilities")?;
     session
         .register_token(ctx)
         .await
@@ -581,6 +643,13 @@ async fn fetch_idle(
             .store_seen_flags_on_imap(ctx)
             .await
             .context("store_seen_flags_on_imap")?;
+
+        // Fetch new messages of the chat the user is looking at first.
+        connection
+            .fetch_interactive_chat_folder(ctx, &mut session)
+            .await
+            .log_err(ctx)
+            .ok();
     }
 
     if !ctx.should_delete_to_trash().await?
@@ -709,7 +778,10 @@ async fn simple_imap_loop(
 
         let mut old_session: Option<Session> = None;
         loop {
-            let session = if let Some(session) = old_session.take() {
+            let session = if let Some(session) = old_session
+                .take()
+                .filter(|_| !connection.network_changed(&ctx))
+            {
                 session
             } else {
                 match connection.prepare(&ctx).await {
@@ -765,7 +837,18 @@ async fn smtp_loop(
         }
 
         let mut timeout = None;
+        let mut network_generation = ctx.network_generation.load(Ordering::Relaxed);
         loop {
+            // Messages queued by other processes before this are sent now.
+            let notified_at = ctx.owner_notified_at().await;
+            let current_network_generation = ctx.network_generation.load(Ordering::Relaxed);
+            if current_network_generation != network_generation {
+                // The connection made over the old network is likely broken.
+                info!(ctx, "Network changed, reconnecting to SMTP server.");
+                network_generation = current_network_generation;
+                connection.disconnect();
+                timeout = None;
+            }
             if let Err(err) = send_smtp_messages(&ctx, &mut connection).await {
                 warn!(ctx, "send_smtp_messages failed: {:#}.", err);
                 timeout = Some(timeout.unwrap_or(30));
@@ -811,9 +894,45 @@ async fn smtp_loop(
                     t,
                     slept.saturating_add(rand::thread_rng().gen_range((slept / 2)..=slept)),
                 ));
+            } else if let Some(duration) = [
+                mdn::time_until_next_due(&ctx).await,
+                throttle::time_until_unthrottled(&ctx).await,
+                undo::time_until_next_send(&ctx).await,
+                ctx.time_until_deferred_status_updates().await,
+            ]
+            .into_iter()
+            .filter_map(|duration| duration.log_err(&ctx).ok().flatten())
+            .min()
+            {
+                info!(
+                    ctx,
+                    "SMTP has batched MDNs, delayed or throttled messages or deferred webxdc updates, waiting for {} or interrupt.",
+                    duration_to_str(duration)
+                );
+                tokio::time::timeout(duration, async {
+                    idle_interrupt_receiver.recv().await.unwrap_or_default()
+                })
+                .await
+                .unwrap_or_default();
             } else {
                 info!(ctx, "SMTP has no messages to retry, waiting for interrupt.");
-                idle_interrupt_receiver.recv().await.unwrap_or_default();
+                // Other processes using the account cannot interrupt the loop,
+                // they touch the notify file instead.
+                loop {
+                    if tokio::time::timeout(OWNER_NOTIFY_INTERVAL, idle_interrupt_receiver.recv())
+                        .await
+                        .is_ok()
+                    {
+                        break;
+                    }
+                    if ctx.owner_notified_at().await != notified_at {
+                        info!(
+                            ctx,
+                            "SMTP was notified of messages queued by another process."
+                        );
+                        break;
+                    }
+                }
             };
 
             info!(ctx, "SMTP fake idle interrupted.")
@@ -365,10 +365,17 @@ pub async fn get_connectivity_html(&self) -> Result<String> {
                 sched.smtp.state.connectivity.clone(),
             ),
             _ => {
-                ret += &format!(
-                    "<h3>{}</h3>\n</body></html>\n",
-                    stock_str::not_connected(self).await
-                );
+                ret += &format!("<h3>{}</h3>\n", stock_str::not_connected(self).await);
+                let pause_reasons = self.get_io_pause_reasons();
+                if !pause_reasons.is_empty() {
+                    ret += "<ul>";
+                    for reason in pause_reasons {
+                        let paused = stock_str::io_paused(self, &reason).await;
+                        ret += &format!("<li>{}</li>", escaper::encode_minimal(&paused));
+                    }
+                    ret += "</ul>\n";
+                }
+                ret += "</body></html>\n";
                 return Ok(ret);
             }
         };
@@ -12,6 +12,7 @@
 use crate::context::Context;
 use crate::e2ee::ensure_secret_key_exists;
 use crate::events::EventType;
+use crate::group_history;
 use crate::headerdef::HeaderDef;
 use crate::key::{load_self_public_key, DcKey, Fingerprint};
 use crate::message::{Message, Viewtype};
@@ -28,9 +29,11 @@
 mod bob;
 mod bobstate;
 mod qrinvite;
+mod safety_number;
 
 pub(crate) use bobstate::BobState;
 use qrinvite::QrInvite;
+pub use safety_number::{get_safety_number, mark_safety_number_verified, SafetyNumber};
 
 use crate::token::Namespace;
 
@@ -132,6 +135,20 @@ pub async fn get_securejoin_qr(context: &Context, group: Option<ChatId>) -> Resu
     Ok(qr)
 }
 
+/// Generates an HTTPS invite link for the group.
+///
+/// The link can be shared in places where QR code images are awkward,
+/// scanning or opening it starts the same protocol as the QR code
+/// returned by [`get_securejoin_qr`].
+/// In addition, the link contains the number of group members,
+/// so a preview can be displayed before joining,
+/// see [`crate::qr::get_invite_link_preview`].
+pub async fn get_group_invite_link(context: &Context, chat_id: ChatId) -> Result<String> {
+    let link = get_securejoin_qr(context, Some(chat_id)).await?;
+    let member_count = chat::get_chat_contacts(context, chat_id).await?.len();
+    Ok(format!("{link}&m={member_count}"))
+}
+
 async fn get_self_fingerprint(context: &Context) -> Result<Fingerprint> {
     let key = load_self_public_key(context)
         .await
@@ -177,7 +194,7 @@ async fn send_alice_handshake_msg(
 ) -> Result<()> {
     let mut msg = Message {
         viewtype: Viewtype::Text,
-        text: format!("Secure-Join: {step}"),
+        text: stock_str::securejoin_msg_body(context, step).await,
         hidden: true,
         ..Default::default()
     };
@@ -233,7 +250,7 @@ async fn verify_sender_by_fingerprint(
                 let verifier = contact.get_addr().to_owned();
                 peerstate.set_verified(public_key.clone(), fingerprint.clone(), verifier)?;
                 peerstate.prefer_encrypt = EncryptPreference::Mutual;
-                peerstate.save_to_db(&context.sql).await?;
+                peerstate.save_to_db_ex(context, None).await?;
                 return Ok(true);
             }
         }
@@ -351,6 +368,9 @@ pub(crate) async fn handle_securejoin_handshake(
             )
             .await
             .context("failed sending auth-required handshake message")?;
+            if !join_vg {
+                bob::handle_crossed_request(context, contact_id).await?;
+            }
             Ok(HandshakeMessage::Done)
         }
         "vg-auth-required" | "vc-auth-required" => {
@@ -452,6 +472,11 @@ pub(crate) async fn handle_securejoin_handshake(
                 .await?;
                 chat::add_contact_to_chat_ex(context, Nosync, group_chat_id, contact_id, true)
                     .await?;
+                if let Err(err) =
+                    group_history::send_history(context, group_chat_id, contact_id).await
+                {
+                    warn!(context, "Failed to share group history: {err:#}.");
+                }
                 inviter_progress(context, contact_id, 800);
                 inviter_progress(context, contact_id, 1000);
             } else {
@@ -466,6 +491,7 @@ pub(crate) async fn handle_securejoin_handshake(
                 send_alice_handshake_msg(context, contact_id, "vc-contact-confirm")
                     .await
                     .context("failed sending vc-contact-confirm message")?;
+                bob::handle_crossed_verification(context, contact_id, &fingerprint).await?;
 
                 inviter_progress(context, contact_id, 1000);
             }
@@ -626,7 +652,7 @@ pub(crate) async fn observe_securejoin_on_other_device(
             Some(context.get_config_i64(Config::KeyId).await?).filter(|&id| id > 0);
     }
     peerstate.prefer_encrypt = EncryptPreference::Mutual;
-    peerstate.save_to_db(&context.sql).await?;
+    peerstate.save_to_db_ex(context, None).await?;
 
     ChatId::set_protection_for_contact(context, contact_id, mime_message.timestamp_sent).await?;
 
@@ -713,7 +739,7 @@ async fn mark_peer_as_verified(
         peerstate.backward_verified_key_id =
             Some(context.get_config_i64(Config::KeyId).await?).filter(|&id| id > 0);
     }
-    peerstate.save_to_db(&context.sql).await?;
+    peerstate.save_to_db_ex(context, None).await?;
     Ok(true)
 }
 
@@ -749,6 +775,7 @@ mod tests {
     use crate::chatlist::Chatlist;
     use crate::constants::{self, Chattype};
     use crate::imex::{imex, ImexMode};
+    use crate::qr::{get_invite_link_preview, Qr};
     use crate::receive_imf::receive_imf;
     use crate::stock_str::{self, chat_protection_enabled};
     use crate::test_utils::get_chat_msg;
@@ -1204,6 +1231,64 @@ async fn test_setup_contact_bob_knows_alice() -> Result<()> {
         Ok(())
     }
 
+    /// Tests that setup-contact converges
+    /// if Alice and Bob scan each other's QR codes at the same time.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_setup_contact_crossed() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = tcm.alice().await;
+        let bob = tcm.bob().await;
+        let alice_qr = get_securejoin_qr(&alice, None).await?;
+        let bob_qr = get_securejoin_qr(&bob, None).await?;
+
+        let alice_chat_id = join_securejoin(&bob, &alice_qr).await?;
+        let bob_request = bob.pop_sent_msg().await;
+        let bob_chat_id = join_securejoin(&alice, &bob_qr).await?;
+        let alice_request = alice.pop_sent_msg().await;
+
+        // Each side replies with vc-auth-required as the inviter
+        // and continues its own handshake with vc-request-with-auth right away.
+        alice.recv_msg_trash(&bob_request).await;
+        let alice_request_with_auth = alice.pop_sent_msg().await;
+        let alice_auth_required = alice.pop_sent_msg().await;
+        bob.recv_msg_trash(&alice_request).await;
+        let bob_request_with_auth = bob.pop_sent_msg().await;
+        let bob_auth_required = bob.pop_sent_msg().await;
+        assert_eq!(
+            bob.parse_msg(&alice_request_with_auth)
+                .await
+                .get_header(HeaderDef::SecureJoin)
+                .unwrap(),
+            "vc-request-with-auth"
+        );
+
+        // Verifying the peer as the inviter completes the own handshake.
+        alice.recv_msg_trash(&bob_request_with_auth).await;
+        bob.recv_msg_trash(&alice_request_with_auth).await;
+        assert!(BobState::from_db(&alice.sql).await?.is_none());
+        assert!(BobState::from_db(&bob.sql).await?.is_none());
+
+        // Late handshake messages are ignored.
+        let alice_contact_confirm = alice.pop_sent_msg().await;
+        let bob_contact_confirm = bob.pop_sent_msg().await;
+        bob.recv_msg_opt(&alice_auth_required).await;
+        bob.recv_msg_opt(&alice_contact_confirm).await;
+        alice.recv_msg_opt(&bob_auth_required).await;
+        alice.recv_msg_opt(&bob_contact_confirm).await;
+
+        let contact_bob = alice.add_or_lookup_contact(&bob).await;
+        let contact_alice = bob.add_or_lookup_contact(&alice).await;
+        assert!(contact_bob.is_verified(&alice).await?);
+        assert!(contact_alice.is_verified(&bob).await?);
+        assert!(Chat::load_from_db(&alice, bob_chat_id)
+            .await?
+            .is_protected());
+        assert!(Chat::load_from_db(&bob, alice_chat_id)
+            .await?
+            .is_protected());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_setup_contact_concurrent_calls() -> Result<()> {
         let mut tcm = TestContextManager::new();
@@ -1420,6 +1505,48 @@ async fn test_secure_join() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_group_invite_link() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        let alice_chat_id =
+            chat::create_group_chat(alice, ProtectionStatus::Unprotected, "the chat").await?;
+        let link = get_group_invite_link(alice, alice_chat_id).await?;
+        assert!(link.starts_with("https://i.delta.chat/#"));
+        let preview = get_invite_link_preview(&link)?;
+        assert_eq!(preview.inviter_addr, "alice@example.org");
+        assert_eq!(preview.grpname.as_deref(), Some("the chat"));
+        assert_eq!(preview.member_count, Some(1));
+
+        // Getting the preview does not create the contact.
+        assert!(
+            Contact::lookup_id_by_addr(bob, "alice@example.org", Origin::Unknown)
+                .await?
+                .is_none()
+        );
+        assert!(matches!(
+            check_qr(bob, &link).await?,
+            Qr::AskVerifyGroup { .. }
+        ));
+
+        tcm.exec_securejoin_qr(bob, alice, &link).await;
+        assert_eq!(
+            chat::get_chat_contacts(alice, alice_chat_id).await?.len(),
+            2
+        );
+        let bob_chat = bob.get_last_msg().await.chat_id;
+        assert_eq!(
+            Chat::load_from_db(bob, bob_chat).await?.get_name(),
+            "the chat"
+        );
+
+        let link = get_group_invite_link(alice, alice_chat_id).await?;
+        assert_eq!(get_invite_link_preview(&link)?.member_count, Some(2));
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_adhoc_group_no_qr() -> Result<()> {
         let alice = TestContext::new_alice().await;
@@ -10,9 +10,10 @@
 use super::HandshakeMessage;
 use crate::chat::{is_contact_in_chat, ChatId, ProtectionStatus};
 use crate::constants::{self, Blocked, Chattype};
-use crate::contact::Contact;
+use crate::contact::{Contact, ContactId};
 use crate::context::Context;
 use crate::events::EventType;
+use crate::key::Fingerprint;
 use crate::mimeparser::{MimeMessage, SystemMessage};
 use crate::sync::Sync::*;
 use crate::tools::{create_smeared_timestamp, time};
@@ -133,6 +134,52 @@ pub(super) async fn handle_auth_required(
     }
 }
 
+/// Handles `vc-request` from the contact whose QR code we scanned ourselves.
+///
+/// Both sides scanned the QR code of the other one at the same time,
+/// so instead of waiting for `vc-auth-required`
+/// we continue our own handshake with the key from the request.
+pub(super) async fn handle_crossed_request(context: &Context, contact_id: ContactId) -> Result<()> {
+    let Some(mut bobstate) = BobState::from_db(&context.sql).await? else {
+        return Ok(());
+    };
+    if !bobstate.is_setup_contact_with(contact_id) {
+        return Ok(());
+    }
+    if bobstate.handle_crossed_request(context).await?.is_some() {
+        bobstate.set_peer_verified(context, time()).await?;
+        bobstate.emit_progress(context, JoinerProgress::RequestWithAuthSent);
+    }
+    Ok(())
+}
+
+/// Completes our own handshake with `contact_id`
+/// after we verified the contact as the inviter.
+///
+/// Both sides scanned the QR code of the other one at the same time
+/// and the contact is verified in both directions now,
+/// so `vc-contact-confirm` is not awaited anymore.
+pub(super) async fn handle_crossed_verification(
+    context: &Context,
+    contact_id: ContactId,
+    fingerprint: &Fingerprint,
+) -> Result<()> {
+    let Some(mut bobstate) = BobState::from_db(&context.sql).await? else {
+        return Ok(());
+    };
+    if !bobstate.is_setup_contact_with(contact_id) || bobstate.invite().fingerprint() != fingerprint
+    {
+        return Ok(());
+    }
+    info!(
+        context,
+        "Completing crossed setup-contact with {contact_id}."
+    );
+    bobstate.step_contact_confirm(context).await?;
+    bobstate.emit_progress(context, JoinerProgress::Succeeded);
+    Ok(())
+}
+
 /// Private implementations for user interactions about this [`BobState`].
 impl BobState {
     fn is_join_group(&self) -> bool {
@@ -24,6 +24,7 @@
 use crate::param::Param;
 use crate::securejoin::Peerstate;
 use crate::sql::Sql;
+use crate::stock_str;
 use crate::tools::time;
 
 /// The stage of the [`BobState`] securejoin handshake protocol state machine.
@@ -300,6 +301,47 @@ pub(crate) async fn handle_auth_required(
         Ok(Some(BobHandshakeStage::RequestWithAuthSent))
     }
 
+    /// Returns `true` if this is an unfinished setup-contact with `contact_id`.
+    ///
+    /// If such a contact also starts a setup-contact with us,
+    /// the handshakes are crossed.
+    pub(crate) fn is_setup_contact_with(&self, contact_id: ContactId) -> bool {
+        matches!(self.invite, QrInvite::Contact { .. })
+            && self.invite.contact_id() == contact_id
+            && self.in_progress()
+    }
+
+    /// Handles a vc-request message from Alice while we are waiting for her vc-auth-required,
+    /// i.e. both sides scanned the QR code of the other one at the same time.
+    ///
+    /// The vc-request carries Alice's key, so if it matches the scanned fingerprint,
+    /// vc-request-with-auth is sent right away without waiting for vc-auth-required.
+    /// Returns `None` if the message was not used for this handshake.
+    pub(crate) async fn handle_crossed_request(
+        &mut self,
+        context: &Context,
+    ) -> Result<Option<BobHandshakeStage>> {
+        if self.next != SecureJoinStep::AuthRequired {
+            return Ok(None);
+        }
+        if !verify_sender_by_fingerprint(
+            context,
+            self.invite.fingerprint(),
+            self.invite.contact_id(),
+        )
+        .await?
+        {
+            return Ok(None);
+        }
+        info!(context, "Crossed vc-request, fingerprint verified.");
+
+        self.update_next(&context.sql, SecureJoinStep::ContactConfirm)
+            .await?;
+        self.send_handshake_message(context, BobHandshakeMsg::RequestWithAuth)
+            .await?;
+        Ok(Some(BobHandshakeStage::RequestWithAuthSent))
+    }
+
     /// Returns `true` if the message is expected according to the protocol.
     pub(crate) fn is_msg_expected(&self, context: &Context, step: &str) -> bool {
         let variant_matches = match self.invite {
@@ -324,7 +366,7 @@ pub(crate) async fn step_contact_confirm(&mut self, context: &Context) -> Result
         // Mark peer as backward verified.
         peerstate.backward_verified_key_id =
             Some(context.get_config_i64(Config::KeyId).await?).filter(|&id| id > 0);
-        peerstate.save_to_db(&context.sql).await?;
+        peerstate.save_to_db_ex(context, None).await?;
 
         ContactId::scaleup_origin(
             context,
@@ -368,7 +410,7 @@ async fn send_handshake_message(
 ) -> Result<()> {
     let mut msg = Message {
         viewtype: Viewtype::Text,
-        text: step.body_text(invite),
+        text: step.body_text(context, invite).await,
         hidden: true,
         ..Default::default()
     };
@@ -424,8 +466,8 @@ impl BobHandshakeMsg {
     /// This text has no significance to the protocol, but would be visible if users see
     /// this email message directly, e.g. when accessing their email without using
     /// DeltaChat.
-    fn body_text(&self, invite: &QrInvite) -> String {
-        format!("Secure-Join: {}", self.securejoin_header(invite))
+    async fn body_text(&self, context: &Context, invite: &QrInvite) -> String {
+        stock_str::securejoin_msg_body(context, self.securejoin_header(invite)).await
     }
 
     /// Returns the `Secure-Join` header value.
@@ -0,0 +1,290 @@
+//! # Safety numbers.
+//!
+//! Safety numbers allow to verify a contact without scanning a QR code
+//! by comparing a numeric code derived from both fingerprints over another channel,
+//! e.g. during a phone call.
+//!
+//! The code consists of two 30-digit halves, one for each key.
+//! Halves are sorted so both sides of a 1:1 chat see the same 60 digits.
+//! An emoji representation is derived from the digits for easier comparison.
+
+use anyhow::{ensure, Context as _, Result};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::aheader::EncryptPreference;
+use crate::chat::{self, Chat, ChatId};
+use crate::config::Config;
+use crate::constants::Chattype;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::key::{load_self_public_key, DcKey, Fingerprint};
+use crate::peerstate::Peerstate;
+use crate::tools::time;
+
+/// Version of the safety number format, mixed into the hash.
+const SAFETY_NUMBER_VERSION: u16 = 0;
+
+/// Number of hash iterations per key.
+const HASH_ITERATIONS: usize = 5200;
+
+/// Number of emojis in the emoji representation.
+const EMOJI_COUNT: usize = 7;
+
+/// Emojis used for the emoji representation, indexed by 6-bit values.
+const EMOJIS: [&str; 64] = [
+    "🐶", "🐱", "🦁", "🐎", "🦄", "🐷", "🐘", "🐰", "🐼", "🐓", "🐧", "🐢", "🐟", "🐙", "🦋", "🌷",
+    "🌳", "🌵", "🍄", "🌏", "🌙", "☁️", "🔥", "🍌", "🍎", "🍓", "🌽", "🍕", "🎂", "❤️", "😀", "🤖",
+    "🎩", "👓", "🔧", "🎅", "👍", "☂️", "⌛", "⏰", "🎁", "💡", "📕", "✏️", "📎", "✂️", "🔒", "🔑",
+    "🔨", "☎️", "🏁", "🚂", "🚲", "✈️", "🚀", "🏆", "⚽", "🎸", "🎺", "🔔", "⚓", "🎧", "📁", "📌",
+];
+
+/// Safety number of a 1:1 chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetyNumber {
+    digits: String,
+    emojis: Vec<&'static str>,
+}
+
+impl SafetyNumber {
+    /// Computes the safety number for two (fingerprint, address) pairs.
+    ///
+    /// The order of the arguments does not matter.
+    fn new(
+        fingerprint1: &Fingerprint,
+        addr1: &str,
+        fingerprint2: &Fingerprint,
+        addr2: &str,
+    ) -> Self {
+        let mut halves = [
+            fingerprint_digits(fingerprint1, addr1),
+            fingerprint_digits(fingerprint2, addr2),
+        ];
+        halves.sort();
+        let digits = halves.concat();
+
+        let hash = Sha256::digest(digits.as_bytes());
+        let bits = hash
+            .iter()
+            .take(6)
+            .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+        let emojis = (0..EMOJI_COUNT)
+            .filter_map(|i| {
+                let index = (bits >> (42 - 6 * i)) & 0x3f;
+                EMOJIS.get(usize::try_from(index).ok()?).copied()
+            })
+            .collect();
+
+        Self { digits, emojis }
+    }
+
+    /// Returns the 60 digits of the safety number.
+    pub fn digits(&self) -> &str {
+        &self.digits
+    }
+
+    /// Returns the safety number formatted as 12 groups of 5 digits.
+    pub fn formatted(&self) -> String {
+        self.digits
+            .as_bytes()
+            .chunks(5)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Returns the emoji representation of the safety number.
+    pub fn emojis(&self) -> &[&'static str] {
+        &self.emojis
+    }
+}
+
+/// Returns 30 digits derived from the fingerprint and the address.
+fn fingerprint_digits(fingerprint: &Fingerprint, addr: &str) -> String {
+    let fingerprint = fingerprint.hex();
+    let mut hash = Sha512::new()
+        .chain_update(SAFETY_NUMBER_VERSION.to_be_bytes())
+        .chain_update(fingerprint.as_bytes())
+        .chain_update(addr.to_lowercase().as_bytes())
+        .finalize();
+    for _ in 0..HASH_ITERATIONS {
+        hash = Sha512::new()
+            .chain_update(hash)
+            .chain_update(fingerprint.as_bytes())
+            .finalize();
+    }
+
+    hash.chunks(5)
+        .take(6)
+        .map(|chunk| {
+            let n = chunk
+                .iter()
+                .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+            format!("{:05}", n % 100_000)
+        })
+        .collect()
+}
+
+/// Returns the contact of a 1:1 chat that can be verified by safety number comparison.
+async fn get_verifiable_contact(context: &Context, chat_id: ChatId) -> Result<Contact> {
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    ensure!(
+        chat.get_type() == Chattype::Single && !chat.is_self_talk() && !chat.is_device_talk(),
+        "Safety numbers are only available for 1:1 chats"
+    );
+    let contact_id = chat::get_chat_contacts(context, chat_id)
+        .await?
+        .into_iter()
+        .next()
+        .context("1:1 chat has no contact")?;
+    ensure!(
+        !contact_id.is_special(),
+        "Safety numbers are not available for special contacts"
+    );
+    Contact::get_by_id(context, contact_id).await
+}
+
+/// Returns the peerstate and the fingerprint of the key currently used for the contact.
+async fn get_peer_key(context: &Context, contact: &Contact) -> Result<(Peerstate, Fingerprint)> {
+    let peerstate = Peerstate::from_addr(context, contact.get_addr())
+        .await?
+        .with_context(|| format!("No key known for {}", contact.get_addr()))?;
+    let fingerprint = peerstate
+        .peek_key(false)
+        .map(|key| key.dc_fingerprint())
+        .with_context(|| format!("No key known for {}", contact.get_addr()))?;
+    Ok((peerstate, fingerprint))
+}
+
+/// Returns the safety number of the 1:1 chat.
+///
+/// Fails if the chat is not a 1:1 chat with a normal contact
+/// or if no key of the contact is known.
+pub async fn get_safety_number(context: &Context, chat_id: ChatId) -> Result<SafetyNumber> {
+    let contact = get_verifiable_contact(context, chat_id).await?;
+    let (_peerstate, peer_fingerprint) = get_peer_key(context, &contact).await?;
+    let self_addr = context.get_primary_self_addr().await?;
+    let self_fingerprint = load_self_public_key(context).await?.dc_fingerprint();
+    Ok(SafetyNumber::new(
+        &self_fingerprint,
+        &self_addr,
+        &peer_fingerprint,
+        contact.get_addr(),
+    ))
+}
+
+/// Marks the contact of the 1:1 chat as verified
+/// after the user compared the safety number with the contact.
+///
+/// `digits` are the digits the user compared, whitespace is ignored.
+/// If they do not match the current safety number,
+/// e.g. because the key of the contact changed in the meantime,
+/// nothing is verified and an error is returned.
+///
+/// On success the contact is verified the same way as after a successful SecureJoin
+/// and the chat becomes protected.
+pub async fn mark_safety_number_verified(
+    context: &Context,
+    chat_id: ChatId,
+    digits: &str,
+) -> Result<()> {
+    let contact = get_verifiable_contact(context, chat_id).await?;
+    let (mut peerstate, peer_fingerprint) = get_peer_key(context, &contact).await?;
+    let self_addr = context.get_primary_self_addr().await?;
+    let self_fingerprint = load_self_public_key(context).await?.dc_fingerprint();
+    let safety_number = SafetyNumber::new(
+        &self_fingerprint,
+        &self_addr,
+        &peer_fingerprint,
+        contact.get_addr(),
+    );
+    let digits: String = digits.chars().filter(|c| !c.is_whitespace()).collect();
+    ensure!(
+        digits == safety_number.digits(),
+        "Safety number does not match, the key of {} may have changed",
+        contact.get_addr()
+    );
+
+    let public_key = peerstate
+        .peek_key(false)
+        .context("No key known for contact")?
+        .clone();
+    peerstate.set_verified(public_key, peer_fingerprint, contact.get_addr().to_string())?;
+    peerstate.prefer_encrypt = EncryptPreference::Mutual;
+    // The user compared our fingerprint too, so the contact is backward verified as well.
+    peerstate.backward_verified_key_id =
+        Some(context.get_config_i64(Config::KeyId).await?).filter(|&id| id > 0);
+    peerstate.save_to_db_ex(context, None).await?;
+
+    let contact_id: ContactId = contact.id;
+    contact_id.regossip_keys(context).await?;
+    ChatId::set_protection_for_contact(context, contact_id, time()).await?;
+    context.emit_event(EventType::ContactsChanged(Some(contact_id)));
+    info!(
+        context,
+        "Marked {contact_id} as verified by safety number comparison."
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_safety_number_symmetric() {
+        let fp1 = Fingerprint::new(vec![1; 20]);
+        let fp2 = Fingerprint::new(vec![2; 20]);
+        let a = SafetyNumber::new(&fp1, "alice@example.org", &fp2, "bob@example.net");
+        let b = SafetyNumber::new(&fp2, "bob@example.net", &fp1, "alice@example.org");
+        assert_eq!(a, b);
+        assert_eq!(a.digits().len(), 60);
+        assert!(a.digits().chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(a.formatted().split(' ').count(), 12);
+        assert_eq!(a.emojis().len(), EMOJI_COUNT);
+
+        // Address is bound to the key.
+        let c = SafetyNumber::new(&fp1, "mallory@example.org", &fp2, "bob@example.net");
+        assert_ne!(a.digits(), c.digits());
+        let d = SafetyNumber::new(&fp1, "Alice@Example.org", &fp2, "bob@example.net");
+        assert_eq!(a, d);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_mark_safety_number_verified() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        tcm.send_recv_accept(alice, bob, "Hi Bob").await;
+        let alice_chat_id = tcm.send_recv_accept(bob, alice, "Hi Alice").await.chat_id;
+        let bob_chat_id = bob.get_chat(alice).await.id;
+
+        let alice_number = get_safety_number(alice, alice_chat_id).await?;
+        let bob_number = get_safety_number(bob, bob_chat_id).await?;
+        assert_eq!(alice_number, bob_number);
+
+        let bob_contact = alice.add_or_lookup_contact(bob).await;
+        assert!(!bob_contact.is_verified(alice).await?);
+
+        // Wrong digits are rejected.
+        assert!(
+            mark_safety_number_verified(alice, alice_chat_id, &"1".repeat(60))
+                .await
+                .is_err()
+        );
+        assert!(!bob_contact.is_verified(alice).await?);
+
+        mark_safety_number_verified(alice, alice_chat_id, &alice_number.formatted()).await?;
+        let bob_contact = alice.add_or_lookup_contact(bob).await;
+        assert!(bob_contact.is_verified(alice).await?);
+        assert!(Chat::load_from_db(alice, alice_chat_id)
+            .await?
+            .is_protected());
+
+        // Not available for the self-chat.
+        let self_chat = alice.get_self_chat().await;
+        assert!(get_safety_number(alice, self_chat.id).await.is_err());
+        Ok(())
+    }
+}
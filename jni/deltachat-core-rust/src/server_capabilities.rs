@@ -0,0 +1,218 @@
+//! # Capabilities of the server of an account.
+//!
+//! Servers, chatmail servers in particular, announce their features
+//! in IMAP capabilities and IMAP METADATA.
+//! They are collected into [`ServerCapabilities`] when the account is configured
+//! and whenever the IMAP connection is established,
+//! and stored in [`Config::ConfiguredServerCapabilities`],
+//! so that UIs can reliably enable the features supported by the server
+//! with [`Context::get_server_capabilities`], even while offline.
+//! A [`EventType::ServerCapabilitiesChanged`] event is emitted when they change.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::events::EventType;
+use crate::imap::capabilities::Capabilities;
+use crate::imap::session::Session;
+use crate::imap::ServerMetadata;
+
+/// Features of the server of an account.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    /// True if the server notifies about new messages via push notifications.
+    pub push: bool,
+
+    /// True if the account is on a chatmail server.
+    pub chatmail: bool,
+
+    /// Maximum size of a message in bytes the server accepts, if it is known.
+    pub max_message_size: Option<u64>,
+
+    /// True if all messages are kept in the inbox
+    /// as the `DeltaChat` folder is not watched,
+    /// which depends on [`Config::MvboxMove`] and [`Config::OnlyFetchMvbox`]
+    /// and is the default on chatmail servers.
+    pub inbox_only: bool,
+
+    /// Number of days after which the server deletes messages, if it does.
+    pub retention_days: Option<u32>,
+}
+
+impl ServerCapabilities {
+    /// Collects the features from the IMAP capabilities and metadata.
+    fn collect(
+        capabilities: &Capabilities,
+        metadata: Option<&ServerMetadata>,
+        chatmail: bool,
+        inbox_only: bool,
+    ) -> Self {
+        Self {
+            push: capabilities.can_push,
+            chatmail,
+            max_message_size: capabilities
+                .append_limit
+                .or_else(|| metadata.and_then(|m| m.max_message_size)),
+            inbox_only,
+            retention_days: metadata.and_then(|m| m.retention_days),
+        }
+    }
+}
+
+impl Context {
+    /// Returns the features of the server of the account
+    /// as found out when the server was last connected.
+    ///
+    /// All features are off for accounts which were not connected yet.
+    pub async fn get_server_capabilities(&self) -> Result<ServerCapabilities> {
+        let Some(json) = self
+            .get_config(Config::ConfiguredServerCapabilities)
+            .await?
+        else {
+            return Ok(ServerCapabilities::default());
+        };
+        Ok(serde_json::from_str(&json).unwrap_or_default())
+    }
+}
+
+/// Stores the features of the server `session` is connected to.
+///
+/// The IMAP METADATA should be fetched before with [`Session::fetch_metadata`].
+pub(crate) async fn update(context: &Context, session: &Session) -> Result<()> {
+    store(context, &session.capabilities).await
+}
+
+/// Stores the features collected from the IMAP `capabilities`
+/// and the fetched IMAP METADATA.
+async fn store(context: &Context, capabilities: &Capabilities) -> Result<()> {
+    let chatmail = context.is_chatmail().await?;
+    let inbox_only = !context.should_watch_mvbox().await?;
+    let capabilities = {
+        let metadata = context.metadata.read().await;
+        ServerCapabilities::collect(capabilities, metadata.as_ref(), chatmail, inbox_only)
+    };
+    if context.get_server_capabilities().await? == capabilities {
+        return Ok(());
+    }
+    info!(context, "Server capabilities changed: {capabilities:?}.");
+    context
+        .set_config_internal(
+            Config::ConfiguredServerCapabilities,
+            Some(&serde_json::to_string(&capabilities)?),
+        )
+        .await?;
+    context.emit_event(EventType::ServerCapabilitiesChanged);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_server_capabilities() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert_eq!(
+            t.get_server_capabilities().await?,
+            ServerCapabilities::default()
+        );
+
+        let mut capabilities = Capabilities {
+            can_idle: true,
+            can_move: true,
+            can_check_quota: true,
+            can_condstore: true,
+            can_metadata: true,
+            can_compress: true,
+            can_push: true,
+            is_chatmail: true,
+            append_limit: None,
+            server_id: None,
+        };
+        let metadata = ServerMetadata {
+            comment: None,
+            admin: None,
+            iroh_relay: None,
+            max_message_size: Some(31457280),
+            retention_days: Some(20),
+        };
+        let collected = ServerCapabilities::collect(&capabilities, Some(&metadata), true, true);
+        assert_eq!(
+            collected,
+            ServerCapabilities {
+                push: true,
+                chatmail: true,
+                max_message_size: Some(31457280),
+                inbox_only: true,
+                retention_days: Some(20),
+            }
+        );
+
+        // The APPENDLIMIT capability takes precedence.
+        capabilities.append_limit = Some(1000);
+        let collected = ServerCapabilities::collect(&capabilities, None, false, false);
+        assert_eq!(collected.max_message_size, Some(1000));
+        assert!(!collected.inbox_only);
+        assert_eq!(collected.retention_days, None);
+
+        t.set_config(
+            Config::ConfiguredServerCapabilities,
+            Some(&serde_json::to_string(&collected)?),
+        )
+        .await?;
+        assert_eq!(t.get_server_capabilities().await?, collected);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_store_server_capabilities() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config_bool(Config::IsChatmail, true).await?;
+        t.set_config_bool(Config::MvboxMove, false).await?;
+        t.set_config_bool(Config::OnlyFetchMvbox, false).await?;
+        let capabilities = Capabilities {
+            can_idle: true,
+            can_move: true,
+            can_check_quota: false,
+            can_condstore: false,
+            can_metadata: false,
+            can_compress: false,
+            can_push: false,
+            is_chatmail: false,
+            append_limit: Some(1000),
+            server_id: None,
+        };
+
+        t.evtracker.clear_events();
+        store(&t, &capabilities).await?;
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::ServerCapabilitiesChanged))
+            .await;
+        let stored = t.get_server_capabilities().await?;
+        assert_eq!(stored.max_message_size, Some(1000));
+        assert!(stored.inbox_only);
+
+        // Storing the same capabilities again emits no event.
+        store(&t, &capabilities).await?;
+        assert!(t
+            .evtracker
+            .get_matching_opt(&t, |evt| matches!(
+                evt,
+                EventType::ServerCapabilitiesChanged
+            ))
+            .await
+            .is_none());
+
+        t.set_config_bool(Config::MvboxMove, true).await?;
+        t.evtracker.clear_events();
+        store(&t, &capabilities).await?;
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::ServerCapabilitiesChanged))
+            .await;
+        assert!(!t.get_server_capabilities().await?.inbox_only);
+        Ok(())
+    }
+}
@@ -85,7 +85,7 @@ pub(crate) fn split_lines(buf: &str) -> Vec<&str> {
 
 /// Simplified text and some additional information gained from the input.
 #[derive(Debug, Default, PartialEq, Eq)]
-pub(crate) struct SimplifiedText {
+pub struct SimplifiedText {
     /// The text itself.
     pub text: String,
 
@@ -113,7 +113,10 @@ pub(crate) fn simplify_quote(quote: &str) -> (String, bool) {
 
 /// Simplify message text for chat display.
 /// Remove quotes, signatures, trailing empty lines etc.
-pub(crate) fn simplify(mut input: String, is_chat_message: bool) -> SimplifiedText {
+///
+/// `is_chat_message` should be set for messages sent by Delta Chat,
+/// quotes are not removed from them.
+pub fn simplify(mut input: String, is_chat_message: bool) -> SimplifiedText {
     let mut is_cut = false;
 
     input.retain(|c| c != '\r');
@@ -2,6 +2,7 @@
 
 mod connect;
 pub mod send;
+pub(crate) mod throttle;
 
 use anyhow::{bail, format_err, Context as _, Error, Result};
 use async_smtp::response::{Category, Code, Detail};
@@ -13,17 +14,22 @@
 use crate::contact::{Contact, ContactId};
 use crate::context::Context;
 use crate::events::EventType;
-use crate::login_param::prioritize_server_login_params;
-use crate::login_param::{ConfiguredLoginParam, ConfiguredServerLoginParam};
+use crate::log::LogExt;
+use crate::login_param::{
+    preferred_server_login_param_failed, select_server_login_params,
+    set_preferred_server_login_param, ConfiguredLoginParam, ConfiguredServerLoginParam,
+};
+use crate::mdn;
 use crate::message::Message;
 use crate::message::{self, MsgId};
 use crate::mimefactory::MimeFactory;
+use crate::net::latency;
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionBufStream;
 use crate::scheduler::connectivity::ConnectivityStore;
 use crate::sql;
 use crate::stock_str::unencrypted_email;
-use crate::tools::{self, time_elapsed};
+use crate::tools::{self, time, time_elapsed};
 
 #[derive(Default)]
 pub(crate) struct Smtp {
@@ -93,7 +99,7 @@ pub async fn connect_configured(&mut self, context: &Context) -> Result<()> {
             .context("Not configured")?;
         self.connect(
             context,
-            &lp.smtp,
+            &lp.smtp_candidates(context).await?,
             &lp.smtp_password,
             &lp.proxy_config,
             &lp.addr,
@@ -124,8 +130,7 @@ pub async fn connect(
             .with_context(|| format!("Invalid address {addr:?}"))?;
         self.from = Some(from);
 
-        let login_params =
-            prioritize_server_login_params(&context.sql, login_params, "smtp").await?;
+        let login_params = select_server_login_params(context, login_params, "smtp").await?;
         let mut first_error = None;
         for lp in login_params {
             info!(context, "SMTP trying to connect to {}.", &lp.connection);
@@ -144,10 +149,18 @@ pub async fn connect(
                 Ok(transport) => transport,
                 Err(err) => {
                     warn!(context, "SMTP failed to connect and authenticate: {err:#}.");
+                    preferred_server_login_param_failed(context, "smtp", &lp)
+                        .await
+                        .log_err(context)
+                        .ok();
                     first_error.get_or_insert(err);
                     continue;
                 }
             };
+            set_preferred_server_login_param(context, "smtp", &lp)
+                .await
+                .log_err(context)
+                .ok();
 
             self.transport = Some(transport);
             self.last_success = Some(tools::Time::now());
@@ -264,6 +277,12 @@ pub(crate) async fn smtp_send(
                         context,
                         "Transient error {response:?}, postponing retry for later."
                     );
+                    if throttle::is_rate_limit_response(response) {
+                        throttle::on_rate_limited(context, recipients)
+                            .await
+                            .log_err(context)
+                            .ok();
+                    }
                     SendResult::Retry
                 }
                 _ => {
@@ -299,7 +318,13 @@ pub(crate) async fn smtp_send(
             warn!(context, "Unable to load SMTP job: {err:#}.");
             SendResult::Failure(err)
         }
-        Ok(()) => SendResult::Success,
+        Ok(()) => {
+            throttle::on_sent(context, recipients)
+                .await
+                .log_err(context)
+                .ok();
+            SendResult::Success
+        }
     };
 
     if let SendResult::Failure(err) = &status {
@@ -399,6 +424,10 @@ pub(crate) async fn send_msg_to_smtp(
         )
         .collect::<Vec<_>>();
 
+    latency::record_submitted(context, msg_id)
+        .await
+        .log_err(context)
+        .ok();
     let status = smtp_send(context, &recipients_list, body.as_str(), smtp, Some(msg_id)).await;
 
     match status {
@@ -459,6 +488,10 @@ pub(crate) async fn send_msg_to_smtp(
                 .await?
             {
                 msg_id.set_delivered(context).await?;
+                latency::record_accepted(context, msg_id)
+                    .await
+                    .log_err(context)
+                    .ok();
             }
             Ok(())
         }
@@ -492,25 +525,35 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
         true
     };
 
-    let rowids = context
+    let rows = context
         .sql
         .query_map(
-            "SELECT id FROM smtp ORDER BY id ASC",
-            (),
+            "SELECT id, recipients FROM smtp WHERE send_after<=? ORDER BY id ASC",
+            (time(),),
             |row| {
                 let rowid: i64 = row.get(0)?;
-                Ok(rowid)
+                let recipients: String = row.get(1)?;
+                Ok((rowid, recipients))
             },
-            |rowids| {
-                rowids
-                    .collect::<std::result::Result<Vec<_>, _>>()
+            |rows| {
+                rows.collect::<std::result::Result<Vec<_>, _>>()
                     .map_err(Into::into)
             },
         )
         .await?;
 
+    let rowids: Vec<i64> = rows.iter().map(|(rowid, _)| *rowid).collect();
     info!(context, "Selected rows from SMTP queue: {rowids:?}.");
-    for rowid in rowids {
+    for (rowid, recipients) in rows {
+        let delay = throttle::until_can_send(context, &recipients).await?;
+        if !delay.is_zero() {
+            info!(
+                context,
+                "Sending entry {rowid} is throttled for {}.",
+                tools::duration_to_str(delay)
+            );
+            continue;
+        }
         send_msg_to_smtp(context, connection, rowid)
             .await
             .context("Failed to send message")?;
@@ -548,22 +591,10 @@ async fn send_mdn_rfc724_mid(
     }
 
     // Try to aggregate additional MDNs into this MDN.
-    let additional_rfc724_mids: Vec<String> = context
-        .sql
-        .query_map(
-            "SELECT rfc724_mid
-             FROM smtp_mdns
-             WHERE from_id=? AND rfc724_mid!=?",
-            (contact_id, &rfc724_mid),
-            |row| {
-                let rfc724_mid: String = row.get(0)?;
-                Ok(rfc724_mid)
-            },
-            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
-        )
-        .await?
-        .into_iter()
-        .collect();
+    // MDNs which are not due yet because of `Config::MdnsBatchDelay` are aggregated too.
+    // MDNs not allowed anymore are removed when they are due.
+    let additional_rfc724_mids =
+        mdn::get_queued_mdns_to_send(context, contact_id, rfc724_mid).await?;
 
     let mimefactory = MimeFactory::from_mdn(
         context,
@@ -612,32 +643,43 @@ async fn send_mdn_rfc724_mid(
 
 /// Tries to send a single MDN. Returns true if more MDNs should be sent.
 async fn send_mdn(context: &Context, smtp: &mut Smtp) -> Result<bool> {
-    if !context.should_send_mdns().await? {
-        context.sql.execute("DELETE FROM smtp_mdns", []).await?;
-        return Ok(false);
-    }
-    info!(context, "Sending MDNs.");
-
     context
         .sql
         .execute("DELETE FROM smtp_mdns WHERE retries > 6", [])
         .await?;
+    let due_timestamp = mdn::due_timestamp(context).await?;
     let Some(msg_row) = context
         .sql
         .query_row_optional(
-            "SELECT rfc724_mid, from_id FROM smtp_mdns ORDER BY retries LIMIT 1",
-            [],
+            "SELECT rfc724_mid, from_id, msg_id FROM smtp_mdns
+             WHERE timestamp<=?
+             ORDER BY retries LIMIT 1",
+            (due_timestamp,),
             |row| {
                 let rfc724_mid: String = row.get(0)?;
                 let from_id: ContactId = row.get(1)?;
-                Ok((rfc724_mid, from_id))
+                let msg_id: MsgId = row.get(2)?;
+                Ok((rfc724_mid, from_id, msg_id))
             },
         )
         .await?
     else {
         return Ok(false);
     };
-    let (rfc724_mid, contact_id) = msg_row;
+    let (rfc724_mid, contact_id, msg_id) = msg_row;
+
+    if !mdn::should_send_queued_mdn(context, msg_id, contact_id).await? {
+        info!(
+            context,
+            "MDN for {rfc724_mid} is not allowed anymore, removing it."
+        );
+        context
+            .sql
+            .execute("DELETE FROM smtp_mdns WHERE rfc724_mid = ?", (rfc724_mid,))
+            .await?;
+        return Ok(true);
+    }
+    info!(context, "Sending MDNs.");
 
     context
         .sql
@@ -0,0 +1,352 @@
+//! # Per-domain send throttling.
+//!
+//! Some providers temporarily block accounts which send too many messages to their users,
+//! e.g. when broadcasting to many recipients.
+//! [`Config::SendDomainLimits`] limits the number of messages per minute for given domains,
+//! a message counts once per domain regardless of the number of recipients there.
+//! Messages to throttled domains stay in the SMTP queue until the limit allows sending them,
+//! other messages are sent meanwhile.
+//!
+//! If the server responds with a temporary error indicating rate limiting,
+//! sending to the recipient domains is additionally suspended with exponential backoff.
+//!
+//! Sent messages and suspensions are stored in the database,
+//! so that restarting the app does not reset the throttling.
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_smtp::response::Response;
+use async_smtp::EmailAddress;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::tools::{self, time};
+
+/// Time window of the limits configured with [`Config::SendDomainLimits`], in seconds.
+const LIMIT_WINDOW: i64 = 60;
+
+/// Minimum duration for which sending to a domain is suspended after a rate limit response.
+const BACKOFF_MIN: Duration = Duration::from_secs(60);
+
+/// Maximum duration for which sending to a domain is suspended after a rate limit response.
+const BACKOFF_MAX: Duration = Duration::from_secs(60 * 60);
+
+/// Enhanced status codes of temporary errors indicating that sending is rate limited.
+///
+/// - 4.7.28: Gmail, "Our system has detected an unusual rate of unsolicited mail".
+/// - 4.2.1: Gmail, "The user you are trying to contact is receiving mail at a rate
+///   that prevents additional messages from being delivered".
+const RATE_LIMIT_STATUS_CODES: &[&str] = &["4.7.28", "4.2.1"];
+
+/// Returns the time until a message can be sent to all of `domains`.
+async fn until_can_send_to(
+    context: &Context,
+    limits: &HashMap<String, u32>,
+    domains: &BTreeSet<String>,
+) -> Result<Duration> {
+    let now = time();
+    let mut until = 0;
+    for domain in domains {
+        if let Some(limit) = limits.get(domain) {
+            // Sending is possible once the oldest of the last `limit` messages leaves the window.
+            let sent_at = context
+                .sql
+                .query_get_value::<i64>(
+                    "SELECT timestamp FROM smtp_domain_sends
+                     WHERE domain=? AND timestamp>?
+                     ORDER BY timestamp DESC LIMIT 1 OFFSET ?",
+                    (domain, now - LIMIT_WINDOW, limit - 1),
+                )
+                .await?;
+            if let Some(sent_at) = sent_at {
+                until = until.max((sent_at + LIMIT_WINDOW - now).clamp(0, LIMIT_WINDOW));
+            }
+        }
+        let backoff = context
+            .sql
+            .query_row_optional(
+                "SELECT backoff, rate_limited_at FROM smtp_domain_backoff WHERE domain=?",
+                (domain,),
+                |row| {
+                    let backoff: i64 = row.get(0)?;
+                    let rate_limited_at: i64 = row.get(1)?;
+                    Ok((backoff, rate_limited_at))
+                },
+            )
+            .await?;
+        if let Some((backoff, rate_limited_at)) = backoff {
+            until = until.max((rate_limited_at + backoff - now).min(backoff));
+        }
+    }
+    Ok(Duration::from_secs(until.try_into().unwrap_or_default()))
+}
+
+/// Records that a message was sent to recipients with the given domains.
+async fn sent_to(
+    context: &Context,
+    limits: &HashMap<String, u32>,
+    domains: BTreeSet<String>,
+) -> Result<()> {
+    let now = time();
+    let domains: Vec<(String, bool)> = domains
+        .into_iter()
+        .map(|domain| {
+            let limited = limits.contains_key(&domain);
+            (domain, limited)
+        })
+        .collect();
+    context
+        .sql
+        .transaction(move |transaction| {
+            for (domain, limited) in domains {
+                transaction.execute("DELETE FROM smtp_domain_backoff WHERE domain=?", (domain,))?;
+                if limited {
+                    transaction.execute(
+                        "INSERT INTO smtp_domain_sends (domain, timestamp) VALUES (?, ?)",
+                        (domain, now),
+                    )?;
+                }
+            }
+            transaction.execute(
+                "DELETE FROM smtp_domain_sends WHERE timestamp<=?",
+                (now - LIMIT_WINDOW,),
+            )?;
+            Ok(())
+        })
+        .await
+}
+
+/// Suspends sending to `domains` after the server responded that sending is rate limited.
+///
+/// Returns the duration of the suspension.
+async fn rate_limited_to(context: &Context, domains: &BTreeSet<String>) -> Result<Duration> {
+    let now = time();
+    let mut max_backoff = Duration::ZERO;
+    for domain in domains {
+        let backoff = context
+            .sql
+            .query_get_value::<u64>(
+                "SELECT backoff FROM smtp_domain_backoff WHERE domain=?",
+                (domain,),
+            )
+            .await?
+            .unwrap_or_default();
+        let backoff = Duration::from_secs(backoff)
+            .saturating_mul(2)
+            .clamp(BACKOFF_MIN, BACKOFF_MAX);
+        context
+            .sql
+            .execute(
+                "INSERT OR REPLACE INTO smtp_domain_backoff (domain, backoff, rate_limited_at)
+                 VALUES (?, ?, ?)",
+                (domain, i64::try_from(backoff.as_secs())?, now),
+            )
+            .await?;
+        max_backoff = max_backoff.max(backoff);
+    }
+    Ok(max_backoff)
+}
+
+/// Parses [`Config::SendDomainLimits`].
+fn parse_limits(value: &str) -> HashMap<String, u32> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|entry| {
+            let (domain, limit) = entry.split_once('=')?;
+            let limit: u32 = limit.parse().ok().filter(|limit| *limit > 0)?;
+            Some((domain.to_lowercase(), limit))
+        })
+        .collect()
+}
+
+async fn load_limits(context: &Context) -> Result<HashMap<String, u32>> {
+    Ok(parse_limits(
+        &context
+            .get_config(Config::SendDomainLimits)
+            .await?
+            .unwrap_or_default(),
+    ))
+}
+
+fn domain(addr: &str) -> Option<String> {
+    addr.rsplit_once('@')
+        .map(|(_local, domain)| domain.to_lowercase())
+}
+
+/// Returns the domains of space-separated `recipients` as stored in the `smtp` table.
+fn recipient_domains(recipients: &str) -> BTreeSet<String> {
+    recipients.split(' ').filter_map(domain).collect()
+}
+
+/// Returns whether a temporary error with the enhanced status code `status_code`
+/// and the text `message` indicates that sending is rate limited.
+fn is_rate_limit(status_code: Option<&str>, message: &str) -> bool {
+    if status_code.is_some_and(|code| RATE_LIMIT_STATUS_CODES.contains(&code)) {
+        return true;
+    }
+    let message = message.to_lowercase();
+    ["rate limit", "ratelimit", "rate-limit"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Returns whether the temporary error `response` indicates that sending is rate limited.
+pub(crate) fn is_rate_limit_response(response: &Response) -> bool {
+    is_rate_limit(response.first_word(), &response.message.join(" "))
+}
+
+/// Returns the time until the message to space-separated `recipients` can be sent,
+/// zero if it can be sent now.
+pub(crate) async fn until_can_send(context: &Context, recipients: &str) -> Result<Duration> {
+    let limits = load_limits(context).await?;
+    until_can_send_to(context, &limits, &recipient_domains(recipients)).await
+}
+
+/// Records that a message was sent to `recipients`.
+pub(crate) async fn on_sent(context: &Context, recipients: &[EmailAddress]) -> Result<()> {
+    let limits = load_limits(context).await?;
+    let domains = recipients
+        .iter()
+        .filter_map(|addr| domain(addr.as_ref()))
+        .collect();
+    sent_to(context, &limits, domains).await
+}
+
+/// Suspends sending to the domains of `recipients`
+/// after the server responded that sending is rate limited.
+pub(crate) async fn on_rate_limited(context: &Context, recipients: &[EmailAddress]) -> Result<()> {
+    let domains: BTreeSet<String> = recipients
+        .iter()
+        .filter_map(|addr| domain(addr.as_ref()))
+        .collect();
+    let backoff = rate_limited_to(context, &domains).await?;
+    warn!(
+        context,
+        "Sending to {domains:?} is rate limited, suspending for {}.",
+        tools::duration_to_str(backoff)
+    );
+    Ok(())
+}
+
+/// Returns the time until the first throttled message in the SMTP queue can be sent,
+/// `None` if no messages are throttled.
+pub(crate) async fn time_until_unthrottled(context: &Context) -> Result<Option<Duration>> {
+    let limits = load_limits(context).await?;
+    let recipients = context
+        .sql
+        .query_map(
+            "SELECT DISTINCT recipients FROM smtp",
+            (),
+            |row| row.get::<_, String>(0),
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+    let mut min_delay: Option<Duration> = None;
+    for recipients in recipients {
+        let delay = until_can_send_to(context, &limits, &recipient_domains(&recipients)).await?;
+        if !delay.is_zero() {
+            min_delay = Some(min_delay.map_or(delay, |min_delay| min_delay.min(delay)));
+        }
+    }
+    Ok(min_delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = parse_limits("gmail.com=20, Example.org=5 invalid=x zero=0 nolimit");
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits.get("gmail.com"), Some(&20));
+        assert_eq!(limits.get("example.org"), Some(&5));
+        assert!(parse_limits("").is_empty());
+    }
+
+    #[test]
+    fn test_is_rate_limit() {
+        assert!(is_rate_limit(
+            Some("4.7.28"),
+            "4.7.28 Our system has detected an unusual rate of unsolicited mail"
+        ));
+        assert!(is_rate_limit(
+            Some("4.2.1"),
+            "4.2.1 The user you are trying to contact is receiving mail at a rate"
+        ));
+        assert!(is_rate_limit(Some("4.7.0"), "4.7.0 Rate limit exceeded"));
+        assert!(!is_rate_limit(
+            Some("4.5.3"),
+            "4.5.3 Too many recipients, try again later"
+        ));
+        assert!(!is_rate_limit(Some("4.1.2"), "4.1.2 Domain not found"));
+        assert!(!is_rate_limit(None, ""));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_domain_throttle() -> Result<()> {
+        let t = &TestContext::new_alice().await;
+        let limits = parse_limits("gmail.com=2");
+        let gmail = recipient_domains("alice@gmail.com");
+        let both = recipient_domains("alice@gmail.com bob@example.org");
+        let example = recipient_domains("bob@example.org");
+        assert_eq!(until_can_send_to(t, &limits, &both).await?, Duration::ZERO);
+
+        // A message to several recipients of a domain counts once.
+        sent_to(
+            t,
+            &limits,
+            recipient_domains("alice@gmail.com carol@gmail.com"),
+        )
+        .await?;
+        assert_eq!(until_can_send_to(t, &limits, &gmail).await?, Duration::ZERO);
+        sent_to(t, &limits, both.clone()).await?;
+        assert!(until_can_send_to(t, &limits, &gmail).await? > Duration::ZERO);
+        assert!(until_can_send_to(t, &limits, &both).await? > Duration::ZERO);
+        assert_eq!(
+            until_can_send_to(t, &limits, &example).await?,
+            Duration::ZERO
+        );
+
+        // Removing the limit allows sending at once.
+        assert_eq!(
+            until_can_send_to(t, &HashMap::new(), &gmail).await?,
+            Duration::ZERO
+        );
+
+        assert_eq!(rate_limited_to(t, &example).await?, BACKOFF_MIN);
+        assert_eq!(rate_limited_to(t, &example).await?, BACKOFF_MIN * 2);
+        assert!(until_can_send_to(t, &limits, &example).await? > BACKOFF_MIN);
+        sent_to(t, &limits, example.clone()).await?;
+        assert_eq!(
+            until_can_send_to(t, &limits, &example).await?,
+            Duration::ZERO
+        );
+        assert_eq!(rate_limited_to(t, &example).await?, BACKOFF_MIN);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_domain_throttle_persisted() -> Result<()> {
+        let t = &TestContext::new_alice().await;
+        t.set_config(Config::SendDomainLimits, Some("gmail.com=1"))
+            .await?;
+        let gmail = recipient_domains("alice@gmail.com");
+        let limits = load_limits(t).await?;
+        sent_to(t, &limits, gmail.clone()).await?;
+        rate_limited_to(t, &recipient_domains("bob@example.org")).await?;
+
+        t.sql.close().await;
+        t.sql.open(t, String::new()).await?;
+        assert!(until_can_send(t, "alice@gmail.com").await? > Duration::ZERO);
+        assert!(until_can_send(t, "bob@example.org").await? > Duration::ZERO);
+        assert_eq!(
+            until_can_send(t, "carol@example.net").await?,
+            Duration::ZERO
+        );
+        Ok(())
+    }
+}
@@ -2,8 +2,11 @@
 
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Context as _, Result};
+use anyhow::{bail, ensure, Context as _, Result};
 use rusqlite::{config::DbConfig, types::ValueRef, Connection, OpenFlags, Row};
 use tokio::sync::RwLock;
 
@@ -11,17 +14,14 @@
 use crate::chat::{self, add_device_msg, update_device_icon, update_saved_messages_icon};
 use crate::config::Config;
 use crate::constants::DC_CHAT_ID_TRASH;
-use crate::context::Context;
+use crate::context::{Context, InnerContext};
 use crate::debug_logging::set_debug_logging_xdc;
-use crate::ephemeral::start_ephemeral_timers;
 use crate::imex::BLOBS_BACKUP_NAME;
-use crate::location::delete_orphaned_poi_locations;
 use crate::log::LogExt;
 use crate::message::{Message, MsgId};
-use crate::net::dns::prune_dns_cache;
-use crate::net::prune_connection_history;
 use crate::param::{Param, Params};
 use crate::peerstate::Peerstate;
+use crate::startup::StartupPhase;
 use crate::stock_str;
 use crate::tools::{delete_file, time, SystemTime};
 
@@ -51,8 +51,44 @@ pub(crate) fn params_iter(
 
 mod migrations;
 mod pool;
+pub mod stats;
 
-use pool::Pool;
+use pool::{Pool, PooledConnection};
+use stats::{SlowQuery, SqlStats, StatsCollector};
+
+/// Default number of connections in the pool.
+const DEFAULT_POOL_SIZE: usize = 3;
+
+/// Default capacity of the prepared statement cache of each connection.
+const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 16;
+
+/// Minimum interval between passive WAL checkpoints run by [`maybe_checkpoint_wal`].
+const WAL_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Mode of a WAL checkpoint, see <https://www.sqlite.org/pragma.html#pragma_wal_checkpoint>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WalCheckpointMode {
+    /// Checkpoints as many frames as possible without waiting for readers or writers.
+    Passive,
+
+    /// Checkpoints all frames and truncates the WAL file to zero bytes.
+    ///
+    /// Fails with `busy` set if there are concurrent readers.
+    Truncate,
+}
+
+/// Result of a WAL checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WalCheckpoint {
+    /// The checkpoint could not be completed because of concurrent readers or writers.
+    pub busy: bool,
+
+    /// Number of frames in the WAL file.
+    pub wal_frames: i64,
+
+    /// Number of frames checkpointed.
+    pub checkpointed_frames: i64,
+}
 
 /// A wrapper around the underlying Sqlite3 object.
 #[derive(Debug)]
@@ -69,6 +105,23 @@ pub struct Sql {
 
     /// Cache of `config` table.
     pub(crate) config_cache: RwLock<HashMap<String, Option<String>>>,
+
+    /// Query statistics.
+    stats: StatsCollector,
+
+    /// Context to log slow queries to.
+    ///
+    /// This is a weak reference because the context owns this object.
+    log_context: OnceLock<Weak<InnerContext>>,
+
+    /// Number of connections in the pool.
+    pool_size: AtomicUsize,
+
+    /// Capacity of the prepared statement cache of each connection.
+    statement_cache_capacity: AtomicUsize,
+
+    /// Time of the last WAL checkpoint.
+    last_wal_checkpoint: parking_lot::Mutex<Option<Instant>>,
 }
 
 impl Sql {
@@ -79,9 +132,82 @@ pub fn new(dbfile: PathBuf) -> Sql {
             pool: Default::default(),
             is_encrypted: Default::default(),
             config_cache: Default::default(),
+            stats: Default::default(),
+            log_context: Default::default(),
+            pool_size: AtomicUsize::new(DEFAULT_POOL_SIZE),
+            statement_cache_capacity: AtomicUsize::new(DEFAULT_STATEMENT_CACHE_CAPACITY),
+            last_wal_checkpoint: Default::default(),
         }
     }
 
+    /// Sets the number of connections in the pool.
+    ///
+    /// More connections allow more parallel readers at the cost of memory,
+    /// each connection has its own page cache.
+    /// Takes effect the next time the database is opened.
+    pub fn set_pool_size(&self, pool_size: usize) -> Result<()> {
+        ensure!(pool_size > 0, "Pool size must be positive");
+        self.pool_size.store(pool_size, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Sets the capacity of the prepared statement cache of each connection.
+    ///
+    /// Takes effect the next time the database is opened.
+    pub fn set_statement_cache_capacity(&self, capacity: usize) {
+        self.statement_cache_capacity
+            .store(capacity, Ordering::Relaxed);
+    }
+
+    /// Sets the context slow queries are logged to.
+    pub(crate) fn set_log_context(&self, context: &Context) {
+        self.log_context.set(Arc::downgrade(&context.inner)).ok();
+    }
+
+    /// Returns statistics of the queries executed since the database was opened.
+    pub async fn get_stats(&self) -> SqlStats {
+        let mut stats = self.stats.get();
+        stats.pool = self.pool.read().await.as_ref().map(|pool| pool.stats());
+        stats.wal_size = self.wal_size().await;
+        stats
+    }
+
+    /// Returns the size of the write-ahead log file in bytes,
+    /// 0 if there is no such file.
+    pub(crate) async fn wal_size(&self) -> u64 {
+        let mut wal_path = self.dbfile.clone().into_os_string();
+        wal_path.push("-wal");
+        tokio::fs::metadata(wal_path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or_default()
+    }
+
+    /// Copies the pages from the write-ahead log back into the database file.
+    ///
+    /// Without checkpoints the WAL file is never truncated,
+    /// SQLite only reuses it from the beginning.
+    pub(crate) async fn wal_checkpoint(&self, mode: WalCheckpointMode) -> Result<WalCheckpoint> {
+        let query = match mode {
+            WalCheckpointMode::Passive => "PRAGMA wal_checkpoint(PASSIVE)",
+            WalCheckpointMode::Truncate => "PRAGMA wal_checkpoint(TRUNCATE)",
+        };
+        let checkpoint = self
+            .call_write(move |conn| {
+                let checkpoint = conn.query_row(query, [], |row| {
+                    Ok(WalCheckpoint {
+                        busy: row.get::<_, i64>(0)? != 0,
+                        wal_frames: row.get(1)?,
+                        checkpointed_frames: row.get(2)?,
+                    })
+                })?;
+                Ok(checkpoint)
+            })
+            .await?;
+        *self.last_wal_checkpoint.lock() = Some(Instant::now());
+        Ok(checkpoint)
+    }
+
     /// Tests SQLCipher passphrase.
     ///
     /// Returns true if passphrase is correct, i.e. the database is new or can be unlocked with
@@ -125,6 +251,7 @@ pub(crate) async fn is_encrypted(&self) -> Option<bool> {
 
     /// Closes all underlying Sqlite connections.
     pub(crate) async fn close(&self) {
+        self.stats.reset();
         let _ = self.pool.write().await.take();
         // drop closes the connection
     }
@@ -182,25 +309,65 @@ pub(crate) async fn import(&self, path: &Path, passphrase: String) -> Result<()>
     }
 
     /// Creates a new connection pool.
-    fn new_pool(dbfile: &Path, passphrase: String) -> Result<Pool> {
+    fn new_pool(&self, dbfile: &Path, passphrase: String) -> Result<Pool> {
+        let pool_size = self.pool_size.load(Ordering::Relaxed);
+        let statement_cache_capacity = self.statement_cache_capacity.load(Ordering::Relaxed);
         let mut connections = Vec::new();
-        for _ in 0..3 {
+        for _ in 0..pool_size {
             let connection = new_connection(dbfile, &passphrase)?;
+            connection.set_prepared_statement_cache_capacity(statement_cache_capacity);
             connections.push(connection);
         }
 
-        let pool = Pool::new(connections);
+        let pool = Pool::new(connections, statement_cache_capacity);
         Ok(pool)
     }
 
     async fn try_open(&self, context: &Context, dbfile: &Path, passphrase: String) -> Result<()> {
-        *self.pool.write().await = Some(Self::new_pool(dbfile, passphrase.to_string())?);
+        let start = Instant::now();
+        *self.pool.write().await = Some(self.new_pool(dbfile, passphrase.to_string())?);
+        context
+            .startup
+            .record(StartupPhase::DbOpen, start.elapsed());
 
+        let start = Instant::now();
         self.run_migrations(context).await?;
+        context
+            .startup
+            .record(StartupPhase::Migrations, start.elapsed());
+
+        let start = Instant::now();
+        self.load_config_cache().await?;
+        context
+            .startup
+            .record(StartupPhase::ConfigLoad, start.elapsed());
 
         Ok(())
     }
 
+    /// Loads all configuration values into the cache with a single query.
+    async fn load_config_cache(&self) -> Result<()> {
+        let mut lock = self.config_cache.write().await;
+        let config = self
+            .query_map(
+                "SELECT keyname, value FROM config",
+                (),
+                |row| {
+                    let key: String = row.get(0)?;
+                    let value: Option<String> = row.get(1)?;
+                    Ok((key, value))
+                },
+                |rows| {
+                    rows.collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(Into::into)
+                },
+            )
+            .await
+            .context("Failed to load config")?;
+        lock.extend(config);
+        Ok(())
+    }
+
     /// Updates SQL schema to the latest version.
     pub async fn run_migrations(&self, context: &Context) -> Result<()> {
         // (1) update low-level database structure.
@@ -306,6 +473,11 @@ pub async fn open(&self, context: &Context, passphrase: String) -> Result<()> {
             .await
             .log_err(context)
             .ok();
+        context
+            .load_stock_translations()
+            .await
+            .log_err(context)
+            .ok();
         Ok(())
     }
 
@@ -326,7 +498,7 @@ pub async fn change_passphrase(&self, passphrase: String) -> Result<()> {
         }
         drop(pool);
 
-        *lock = Some(Self::new_pool(&self.dbfile, passphrase.to_string())?);
+        *lock = Some(self.new_pool(&self.dbfile, passphrase.to_string())?);
 
         Ok(())
     }
@@ -349,6 +521,50 @@ async fn call<'a, F, R>(&'a self, query_only: bool, function: F) -> Result<R>
         Ok(res)
     }
 
+    /// Same as [`Sql::call`], but records the execution time of `function`
+    /// as the execution time of the `sql` statement.
+    ///
+    /// `function` is expected to prepare `sql` with [`PooledConnection::prepare_cached`]
+    /// so the statement cache hits are counted.
+    async fn call_query<'a, F, R>(
+        &'a self,
+        query_only: bool,
+        sql: &'a str,
+        function: F,
+    ) -> Result<R>
+    where
+        F: 'a + FnOnce(&mut PooledConnection) -> Result<R> + Send,
+        R: Send + 'static,
+    {
+        let lock = self.pool.read().await;
+        let pool = lock.as_ref().context("no SQL connection")?;
+        let mut conn = pool.get(query_only).await?;
+        let (res, slow_query) = tokio::task::block_in_place(move || {
+            let start = Instant::now();
+            let res = function(&mut conn);
+            (res, self.stats.record(sql, start.elapsed()))
+        });
+        drop(lock);
+        if let Some(slow_query) = slow_query {
+            self.log_slow_query(&slow_query);
+        }
+        res
+    }
+
+    fn log_slow_query(&self, slow_query: &SlowQuery) {
+        let Some(inner) = self.log_context.get().and_then(Weak::upgrade) else {
+            return;
+        };
+        let context = Context { inner };
+        warn!(
+            context,
+            "Slow SQL query took {} ms ({} parameters): {}",
+            slow_query.duration_ms,
+            slow_query.param_count,
+            slow_query.sql
+        );
+    }
+
     /// Allocates a connection and calls given function, assuming it does write queries, with the
     /// connection.
     ///
@@ -368,8 +584,9 @@ pub async fn execute(
         query: &str,
         params: impl rusqlite::Params + Send,
     ) -> Result<usize> {
-        self.call_write(move |conn| {
-            let res = conn.execute(query, params)?;
+        let query_only = false;
+        self.call_query(query_only, query, move |conn| {
+            let res = conn.prepare_cached(query)?.execute(params)?;
             Ok(res)
         })
         .await
@@ -377,8 +594,9 @@ pub async fn execute(
 
     /// Executes the given query, returning the last inserted row ID.
     pub async fn insert(&self, query: &str, params: impl rusqlite::Params + Send) -> Result<i64> {
-        self.call_write(move |conn| {
-            conn.execute(query, params)?;
+        let query_only = false;
+        self.call_query(query_only, query, move |conn| {
+            conn.prepare_cached(query)?.execute(params)?;
             Ok(conn.last_insert_rowid())
         })
         .await
@@ -400,8 +618,8 @@ pub async fn query_map<T, F, G, H>(
         H: Send + 'static,
     {
         let query_only = true;
-        self.call(query_only, move |conn| {
-            let mut stmt = conn.prepare(sql)?;
+        self.call_query(query_only, sql, move |conn| {
+            let mut stmt = conn.prepare_cached(sql)?;
             let res = stmt.query_map(params, f)?;
             g(res)
         })
@@ -433,8 +651,8 @@ pub async fn query_row<T, F>(
         T: Send + 'static,
     {
         let query_only = true;
-        self.call(query_only, move |conn| {
-            let res = conn.query_row(query, params, f)?;
+        self.call_query(query_only, query, move |conn| {
+            let res = conn.prepare_cached(query)?.query_row(params, f)?;
             Ok(res)
         })
         .await
@@ -515,8 +733,8 @@ pub async fn query_row_optional<T, F>(
         T: Send + 'static,
     {
         let query_only = true;
-        self.call(query_only, move |conn| {
-            match conn.query_row(sql.as_ref(), params, f) {
+        self.call_query(query_only, sql, move |conn| {
+            match conn.prepare_cached(sql)?.query_row(params, f) {
                 Ok(res) => Ok(Some(res)),
                 Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
                 Err(err) => Err(err.into()),
@@ -676,13 +894,16 @@ fn new_connection(path: &Path, passphrase: &str) -> Result<Connection> {
     // Default synchronous=FULL is much slower. NORMAL is sufficient for WAL mode.
     conn.pragma_update(None, "synchronous", "NORMAL".to_string())?;
 
+    #[cfg(feature = "icu-collation")]
+    crate::collation::register_collations(&conn)?;
+
     Ok(conn)
 }
 
 // Tries to clear the freelist to free some space on the disk.
 //
 // This only works if auto_vacuum is enabled.
-async fn incremental_vacuum(context: &Context) -> Result<()> {
+pub(crate) async fn incremental_vacuum(context: &Context) -> Result<()> {
     context
         .sql
         .call_write(move |conn| {
@@ -709,89 +930,54 @@ async fn incremental_vacuum(context: &Context) -> Result<()> {
         .await
 }
 
-/// Cleanup the account to restore some storage and optimize the database.
-pub async fn housekeeping(context: &Context) -> Result<()> {
-    // Setting `Config::LastHousekeeping` at the beginning avoids endless loops when things do not
-    // work out for whatever reason or are interrupted by the OS.
-    if let Err(e) = context
-        .set_config_internal(Config::LastHousekeeping, Some(&time().to_string()))
-        .await
-    {
-        warn!(context, "Can't set config: {e:#}.");
-    }
-
-    if let Err(err) = remove_unused_files(context).await {
-        warn!(
-            context,
-            "Housekeeping: cannot remove unused files: {:#}.", err
-        );
-    }
-
-    if let Err(err) = start_ephemeral_timers(context).await {
-        warn!(
-            context,
-            "Housekeeping: cannot start ephemeral timers: {:#}.", err
-        );
+/// Runs a passive WAL checkpoint unless one was run recently.
+///
+/// Called when IO is idle, so the checkpoint does not compete with message processing.
+pub(crate) async fn maybe_checkpoint_wal(context: &Context) -> Result<()> {
+    let last_checkpoint = *context.sql.last_wal_checkpoint.lock();
+    if last_checkpoint.is_some_and(|last| last.elapsed() < WAL_CHECKPOINT_INTERVAL) {
+        return Ok(());
     }
+    let checkpoint = context
+        .sql
+        .wal_checkpoint(WalCheckpointMode::Passive)
+        .await?;
+    info!(
+        context,
+        "Passive WAL checkpoint: {} of {} frames checkpointed.",
+        checkpoint.checkpointed_frames,
+        checkpoint.wal_frames
+    );
+    Ok(())
+}
 
-    if let Err(err) = prune_tombstones(&context.sql).await {
-        warn!(
+/// Checkpoints the whole WAL and truncates the WAL file.
+///
+/// Used after operations which write a lot to the WAL, such as vacuuming.
+pub(crate) async fn truncate_wal(context: &Context) -> Result<()> {
+    let wal_size = context.sql.wal_size().await;
+    let checkpoint = context
+        .sql
+        .wal_checkpoint(WalCheckpointMode::Truncate)
+        .await?;
+    if checkpoint.busy {
+        info!(
             context,
-            "Housekeeping: Cannot prune message tombstones: {:#}.", err
+            "WAL file of {wal_size} bytes not truncated because the database is busy."
         );
+    } else {
+        info!(context, "Truncated WAL file of {wal_size} bytes.");
     }
-
-    if let Err(err) = incremental_vacuum(context).await {
-        warn!(context, "Failed to run incremental vacuum: {err:#}.");
-    }
-
-    context
-        .sql
-        .execute(
-            "DELETE FROM msgs_mdns WHERE msg_id NOT IN \
-            (SELECT id FROM msgs WHERE chat_id!=?)",
-            (DC_CHAT_ID_TRASH,),
-        )
-        .await
-        .context("failed to remove old MDNs")
-        .log_err(context)
-        .ok();
-
-    context
-        .sql
-        .execute(
-            "DELETE FROM msgs_status_updates WHERE msg_id NOT IN \
-            (SELECT id FROM msgs WHERE chat_id!=?)",
-            (DC_CHAT_ID_TRASH,),
-        )
-        .await
-        .context("failed to remove old webxdc status updates")
-        .log_err(context)
-        .ok();
-
-    prune_connection_history(context)
-        .await
-        .context("Failed to prune connection history")
-        .log_err(context)
-        .ok();
-    prune_dns_cache(context)
-        .await
-        .context("Failed to prune DNS cache")
-        .log_err(context)
-        .ok();
-
-    // Delete POI locations
-    // which don't have corresponding message.
-    delete_orphaned_poi_locations(context)
-        .await
-        .context("Failed to delete orphaned POI locations")
-        .log_err(context)
-        .ok();
-
-    info!(context, "Housekeeping done.");
     Ok(())
 }
 
+/// Cleanup the account to restore some storage and optimize the database.
+///
+/// See [`crate::housekeeping`] for running housekeeping as a background job.
+pub async fn housekeeping(context: &Context) -> Result<()> {
+    crate::housekeeping::run(context).await
+}
+
 /// Get the value of a column `idx` of the `row` as `Vec<u8>`.
 pub fn row_get_vec(row: &Row, idx: usize) -> rusqlite::Result<Vec<u8>> {
     row.get(idx).or_else(|err| match row.get_ref(idx)? {
@@ -815,6 +1001,41 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
         Param::File,
     )
     .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM msgs WHERE chat_id!=3;",
+        Param::Thumbnail,
+    )
+    .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM msgs WHERE chat_id!=3;",
+        Param::Preview,
+    )
+    .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM staged_attachments;",
+        Param::File,
+    )
+    .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM staged_attachments;",
+        Param::Thumbnail,
+    )
+    .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM staged_attachments;",
+        Param::Preview,
+    )
+    .await?;
     maybe_add_from_param(
         &context.sql,
         &mut files_in_use,
@@ -829,6 +1050,13 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
         Param::ProfileImage,
     )
     .await?;
+    maybe_add_from_param(
+        &context.sql,
+        &mut files_in_use,
+        "SELECT param FROM contacts;",
+        Param::LocalProfileImage,
+    )
+    .await?;
 
     context
         .sql
@@ -921,6 +1149,13 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
                             path.display(),
                             err
                         );
+                    } else if p == blobdir {
+                        context
+                            .sql
+                            .execute("DELETE FROM blob_checksums WHERE name=?", (&*name_s,))
+                            .await
+                            .log_err(context)
+                            .ok();
                     }
                 }
             }
@@ -986,7 +1221,7 @@ async fn maybe_add_from_param(
 
 /// Removes from the database stale locally deleted messages that also don't
 /// have a server UID.
-async fn prune_tombstones(sql: &Sql) -> Result<()> {
+pub(crate) async fn prune_tombstones(sql: &Sql) -> Result<()> {
     // Keep tombstones for the last two days to prevent redownloading locally deleted messages.
     let timestamp_max = time().saturating_sub(2 * 24 * 3600);
     sql.execute(
@@ -1058,6 +1293,40 @@ async fn test_col_exists() {
         assert!(!t.ctx.sql.col_exists("foobar", "foobar").await.unwrap());
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_query_stats() -> Result<()> {
+        let t = TestContext::new().await;
+        let query = "SELECT COUNT(*) FROM msgs WHERE chat_id=?";
+        t.sql.count(query, (10,)).await?;
+        t.sql.count(query, (11,)).await?;
+        let stats = t.sql.get_stats().await;
+        let statement = stats
+            .statements
+            .iter()
+            .find(|statement| statement.sql == query)
+            .unwrap();
+        assert_eq!(statement.count, 2);
+
+        let pool = stats.pool.unwrap();
+        assert_eq!(pool.size, DEFAULT_POOL_SIZE);
+        assert_eq!(
+            pool.statement_cache_capacity,
+            DEFAULT_STATEMENT_CACHE_CAPACITY
+        );
+
+        // Running a query on more connections than the pool has
+        // finds it in the cache of at least one of them.
+        let query = "SELECT COUNT(*) FROM chats WHERE id=?";
+        for _ in 0..=DEFAULT_POOL_SIZE {
+            t.sql.count(query, (10,)).await?;
+        }
+        let pool = t.sql.get_stats().await.pool.unwrap();
+        assert!(pool.statement_cache_hits >= 1);
+        assert!(pool.statement_cache_misses >= 1);
+        assert!(pool.statement_cache_hit_ratio().unwrap() < 1.0);
+        Ok(())
+    }
+
     /// Tests that auto_vacuum is enabled for new databases.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_auto_vacuum() -> Result<()> {
@@ -1377,6 +1646,26 @@ async fn test_query_only() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_wal_checkpoint() -> Result<()> {
+        let t = TestContext::new().await;
+        t.sql
+            .set_raw_config("wal_checkpoint_test", Some("foo"))
+            .await?;
+        assert!(t.sql.wal_size().await > 0);
+
+        let checkpoint = t.sql.wal_checkpoint(WalCheckpointMode::Passive).await?;
+        assert!(!checkpoint.busy);
+        assert_eq!(checkpoint.checkpointed_frames, checkpoint.wal_frames);
+        assert!(t.sql.wal_size().await > 0);
+
+        let checkpoint = t.sql.wal_checkpoint(WalCheckpointMode::Truncate).await?;
+        assert!(!checkpoint.busy);
+        assert_eq!(t.sql.wal_size().await, 0);
+        assert_eq!(t.get_sql_stats().await.wal_size, 0);
+        Ok(())
+    }
+
     /// Tests that incremental_vacuum does not fail.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_incremental_vacuum() -> Result<()> {
@@ -1088,6 +1088,374 @@ pub async fn run(context: &Context, sql: &Sql) -> Result<(bool, bool, bool, bool
         .await?;
     }
 
+    inc_and_check(&mut migration_version, 125)?;
+    if dbversion < migration_version {
+        // Local history retention policy, see `ChatId::set_retention_policy()`.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN retention_days INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE chats ADD COLUMN retention_count INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 126)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE pending_deletions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL DEFAULT 0,
+                chat_id INTEGER NOT NULL DEFAULT 0 -- deleted chat, 0 if messages were deleted
+            ) STRICT;
+            CREATE TABLE pending_deletion_msgs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                deletion_id INTEGER NOT NULL,
+                msg_id INTEGER NOT NULL,
+                chat_id INTEGER NOT NULL, -- chat of the message before deletion
+                FOREIGN KEY(deletion_id) REFERENCES pending_deletions(id) ON DELETE CASCADE
+            ) STRICT;
+            CREATE INDEX pending_deletion_msgs_index ON pending_deletion_msgs (deletion_id);
+            ALTER TABLE chats ADD COLUMN pending_deletion INTEGER NOT NULL DEFAULT 0; -- 1 if the chat is deleted but can be restored",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 127)?;
+    if dbversion < migration_version {
+        // Account events not stored elsewhere, see `activity::get_activity_log()`.
+        // Only the last configuration and backup were known before.
+        sql.execute_migration(
+            "CREATE TABLE activity_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                kind INTEGER NOT NULL, -- `ActivityKind`
+                arg TEXT NOT NULL DEFAULT ''
+            );
+            INSERT INTO activity_events (timestamp, kind, arg)
+                SELECT CAST(value AS INTEGER), 7,
+                    IFNULL((SELECT value FROM config WHERE keyname='configured_addr'), '')
+                FROM config WHERE keyname='configured_timestamp' AND CAST(value AS INTEGER)>0;
+            INSERT INTO activity_events (timestamp, kind)
+                SELECT CAST(value AS INTEGER), 8
+                FROM config WHERE keyname='backup_time' AND CAST(value AS INTEGER)>0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 128)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE pending_quotes (
+                msg_id INTEGER PRIMARY KEY, -- message quoting a message not received yet
+                rfc724_mid TEXT NOT NULL -- Message-ID of the quoted message
+            ) STRICT;
+            CREATE INDEX pending_quotes_index ON pending_quotes (rfc724_mid);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 129)?;
+    if dbversion < migration_version {
+        // Time when the webxdc status updates were queued,
+        // updates queued before are not deferred.
+        sql.execute_migration(
+            "ALTER TABLE smtp_status_updates ADD COLUMN timestamp INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 130)?;
+    if dbversion < migration_version {
+        // Address books a contact is listed in, see `Contact::apply_address_book_delta()`.
+        sql.execute_migration(
+            "CREATE TABLE address_book_sources (
+                contact_id INTEGER NOT NULL,
+                source TEXT NOT NULL, -- tag of the address book, e.g. an OS account
+                PRIMARY KEY(contact_id, source),
+                FOREIGN KEY(contact_id) REFERENCES contacts(id) ON DELETE CASCADE
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 131)?;
+    if dbversion < migration_version {
+        // Time the MDN was queued, used to send MDNs in batches, see `Config::MdnsBatchDelay`.
+        sql.execute_migration(
+            "ALTER TABLE smtp_mdns ADD COLUMN timestamp INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 132)?;
+    if dbversion < migration_version {
+        // Local notes and tags on contacts, see `contact_notes` module.
+        sql.execute_migration(
+            "ALTER TABLE contacts ADD COLUMN note TEXT NOT NULL DEFAULT '';
+            CREATE TABLE contact_tags (
+                contact_id INTEGER NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY(contact_id, tag),
+                FOREIGN KEY(contact_id) REFERENCES contacts(id) ON DELETE CASCADE
+            ) STRICT;
+            CREATE INDEX contact_tags_index ON contact_tags (tag);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 133)?;
+    if dbversion < migration_version {
+        // Incoming messages matching `Config::WatchKeywords`.
+        sql.execute_migration(
+            "CREATE TABLE watched_msgs (
+                msg_id INTEGER PRIMARY KEY,
+                keyword TEXT NOT NULL, -- the first keyword found in the message
+                FOREIGN KEY(msg_id) REFERENCES msgs(id) ON DELETE CASCADE
+            ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 134)?;
+    if dbversion < migration_version {
+        // Send throttling per recipient domain, see `smtp::throttle`.
+        sql.execute_migration(
+            "CREATE TABLE smtp_domain_sends (
+                domain TEXT NOT NULL,
+                timestamp INTEGER NOT NULL -- time of sending a message to the domain
+            );
+            CREATE INDEX smtp_domain_sends_index ON smtp_domain_sends (domain, timestamp);
+            CREATE TABLE smtp_domain_backoff (
+                domain TEXT PRIMARY KEY,
+                backoff INTEGER NOT NULL, -- seconds for which sending is suspended
+                rate_limited_at INTEGER NOT NULL -- time of the last rate limit response
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 135)?;
+    if dbversion < migration_version {
+        // Subject template for messages to classic email users.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN subject_template TEXT NOT NULL DEFAULT ''",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 136)?;
+    if dbversion < migration_version {
+        // Time before which a queued message must not be submitted, see `Config::SendDelay`.
+        sql.execute_migration(
+            "ALTER TABLE smtp ADD COLUMN send_after INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 137)?;
+    if dbversion < migration_version {
+        // Membership changes summarized in digests, see `Config::MemberDigestThreshold`.
+        sql.execute_migration(
+            "CREATE TABLE member_changes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                contact_id INTEGER NOT NULL,
+                added INTEGER NOT NULL,
+                by_contact_id INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                digest_msg_id INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX member_changes_index1 ON member_changes (chat_id);
+            CREATE INDEX member_changes_index2 ON member_changes (digest_msg_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 138)?;
+    if dbversion < migration_version {
+        // Group moderators and received reports, see `crate::report`.
+        sql.execute_migration(
+            "CREATE TABLE chat_moderators (
+                chat_id INTEGER NOT NULL,
+                contact_id INTEGER NOT NULL,
+                PRIMARY KEY(chat_id, contact_id)
+            );
+            CREATE TABLE msg_reports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                reporter_id INTEGER NOT NULL,
+                rfc724_mid TEXT NOT NULL DEFAULT '',
+                grpid TEXT NOT NULL DEFAULT '',
+                chat_name TEXT NOT NULL DEFAULT '',
+                from_addr TEXT NOT NULL DEFAULT '',
+                msg_timestamp INTEGER NOT NULL DEFAULT 0,
+                txt TEXT NOT NULL DEFAULT '',
+                headers TEXT NOT NULL DEFAULT '',
+                reason TEXT NOT NULL DEFAULT '',
+                timestamp INTEGER NOT NULL DEFAULT 0
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 139)?;
+    if dbversion < migration_version {
+        // Exceptions from `delete_device_after`, see `ChatId::set_auto_delete_exempt()`.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN auto_delete_exempt INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE contacts ADD COLUMN auto_delete_exempt INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 140)?;
+    if dbversion < migration_version {
+        // Sender of webxdc status updates, 0 for updates stored before.
+        sql.execute_migration(
+            "ALTER TABLE msgs_status_updates ADD COLUMN from_id INTEGER NOT NULL DEFAULT 0;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 141)?;
+    if dbversion < migration_version {
+        // Delivery latency metrics, timestamps in milliseconds.
+        sql.execute_migration(
+            "CREATE TABLE msg_delivery_times (
+                msg_id INTEGER PRIMARY KEY,
+                provider TEXT NOT NULL DEFAULT '',
+                queued INTEGER NOT NULL,
+                submitted INTEGER,
+                accepted INTEGER,
+                mdn_received INTEGER
+            );
+            CREATE INDEX msg_delivery_times_index1 ON msg_delivery_times (queued);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 142)?;
+    if dbversion < migration_version {
+        // Quota usage over time, storage in bytes.
+        sql.execute_migration(
+            "CREATE TABLE quota_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                highest_percent INTEGER NOT NULL,
+                storage_usage INTEGER,
+                storage_limit INTEGER
+            );
+            CREATE INDEX quota_history_index1 ON quota_history (timestamp);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 143)?;
+    if dbversion < migration_version {
+        // Set when a message vanished from the server
+        // because another client deleted or expunged it.
+        sql.execute_migration(
+            "ALTER TABLE msgs ADD COLUMN removed_from_server INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 144)?;
+    if dbversion < migration_version {
+        // Set for groups that look like unsolicited group additions.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN quarantined INTEGER NOT NULL DEFAULT 0",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 145)?;
+    if dbversion < migration_version {
+        // Audit log of config changes, `origin` is a `ConfigOrigin`.
+        sql.execute_migration(
+            "CREATE TABLE config_changes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                keyname TEXT NOT NULL,
+                old_hash TEXT,
+                new_hash TEXT,
+                old_value TEXT,
+                can_rollback INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                origin INTEGER NOT NULL
+            );",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 146)?;
+    if dbversion < migration_version {
+        // Cached `ChatEncryption`, NULL if it needs to be recomputed.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN encryption_state INTEGER",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 147)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE staged_attachments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                viewtype INTEGER NOT NULL,
+                param TEXT NOT NULL DEFAULT '',
+                state INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL
+            );
+            CREATE INDEX staged_attachments_index1 ON staged_attachments (chat_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 148)?;
+    if dbversion < migration_version {
+        // SHA-256 checksums of blobs, see `crate::blob::integrity`.
+        sql.execute_migration(
+            "CREATE TABLE blob_checksums (
+                name TEXT PRIMARY KEY,
+                sha256 TEXT NOT NULL
+            )",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 149)?;
+    if dbversion < migration_version {
+        // ID of the newest message marked as seen, see `chat::get_first_unread_msg()`.
+        sql.execute_migration(
+            "ALTER TABLE chats ADD COLUMN read_position INTEGER",
+            migration_version,
+        )
+        .await?;
+    }
+
     let new_version = sql
         .get_raw_config_int(VERSION_CFG)
         .await?
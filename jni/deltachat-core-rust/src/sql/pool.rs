@@ -16,6 +16,13 @@
 //! - SQLite's locking mechanism is non-async, blocking a thread
 //! - SQLite's locking mechanism just sleeps in a loop, which is really inefficient
 //!
+//! The pool also keeps metrics, the number of times a connection could not be allocated
+//! immediately, the total time spent waiting and the hits and misses of the per-connection
+//! prepared statement cache. rusqlite does not report cache hits, but a statement
+//! taken from the cache has been run before, while a newly prepared one has not,
+//! so [`PooledConnection::prepare_cached`] tells them apart by the run counter of the statement.
+//! Statements prepared on the underlying [`Connection`] directly are not counted.
+//!
 //! ---
 //!
 //! More considerations on alternatives to the current approach:
@@ -45,18 +52,47 @@
 //! w/o the external locking. But then cons 1. - 3. above would still be valid.
 
 use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Weak};
+use std::time::Instant;
 
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{CachedStatement, Connection, StatementStatus};
 use tokio::sync::{Mutex, OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
 
+use super::stats::PoolStats;
+
+/// Counters of the pool.
+#[derive(Debug, Default)]
+struct PoolMetrics {
+    /// Number of allocations which had to wait for a connection.
+    waits: AtomicU64,
+
+    /// Total time spent waiting for connections in milliseconds.
+    wait_ms: AtomicU64,
+
+    /// Number of statement cache hits.
+    cache_hits: AtomicU64,
+
+    /// Number of statement cache misses.
+    cache_misses: AtomicU64,
+}
+
 /// Inner connection pool.
 #[derive(Debug)]
 struct InnerPool {
     /// Available connections.
     connections: parking_lot::Mutex<Vec<Connection>>,
 
+    /// Total number of connections.
+    size: usize,
+
+    /// Capacity of the prepared statement cache of each connection.
+    statement_cache_capacity: usize,
+
+    /// Pool metrics.
+    metrics: PoolMetrics,
+
     /// Counts the number of available connections.
     semaphore: Arc<Semaphore>,
 
@@ -80,6 +116,23 @@ fn put(&self, connection: Connection) {
         drop(connections);
     }
 
+    /// Acquires a semaphore permit, recording the wait if no connection is available.
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        let start = Instant::now();
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        self.record_wait(start);
+        Ok(permit)
+    }
+
+    fn record_wait(&self, start: Instant) {
+        let wait_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.metrics.waits.fetch_add(1, Ordering::Relaxed);
+        self.metrics.wait_ms.fetch_add(wait_ms, Ordering::Relaxed);
+    }
+
     /// Retrieves a connection from the pool.
     ///
     /// Sets `query_only` pragma to the provided value
@@ -89,7 +142,7 @@ fn put(&self, connection: Connection) {
     /// to use the connection for writing.
     pub async fn get(self: Arc<Self>, query_only: bool) -> Result<PooledConnection> {
         if query_only {
-            let permit = self.semaphore.clone().acquire_owned().await?;
+            let permit = self.acquire().await?;
             let conn = {
                 let mut connections = self.connections.lock();
                 connections
@@ -108,11 +161,19 @@ pub async fn get(self: Arc<Self>, query_only: bool) -> Result<PooledConnection>
             // We get write guard first to avoid taking a permit
             // and not using it, blocking a reader from getting a connection
             // while being ourselves blocked by another wrtier.
-            let write_mutex_guard = Arc::clone(&self.write_mutex).lock_owned().await;
+            let write_mutex_guard = match Arc::clone(&self.write_mutex).try_lock_owned() {
+                Ok(guard) => guard,
+                Err(_) => {
+                    let start = Instant::now();
+                    let guard = Arc::clone(&self.write_mutex).lock_owned().await;
+                    self.record_wait(start);
+                    guard
+                }
+            };
 
             // We may still have to wait for a connection
             // to be returned by some reader.
-            let permit = self.semaphore.clone().acquire_owned().await?;
+            let permit = self.acquire().await?;
             let conn = {
                 let mut connections = self.connections.lock();
                 connections.pop().context(
@@ -159,6 +220,23 @@ fn drop(&mut self) {
     }
 }
 
+impl PooledConnection {
+    /// Prepares a statement with [`Connection::prepare_cached`],
+    /// counting the statement cache hits and misses.
+    pub fn prepare_cached(&self, sql: &str) -> rusqlite::Result<CachedStatement<'_>> {
+        let stmt = Connection::prepare_cached(self, sql)?;
+        if let Some(pool) = self.pool.upgrade() {
+            let counter = if stmt.get_status(StatementStatus::Run) > 0 {
+                &pool.metrics.cache_hits
+            } else {
+                &pool.metrics.cache_misses
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(stmt)
+    }
+}
+
 impl Deref for PooledConnection {
     type Target = Connection;
 
@@ -182,10 +260,17 @@ pub struct Pool {
 
 impl Pool {
     /// Creates a new connection pool.
-    pub fn new(connections: Vec<Connection>) -> Self {
-        let semaphore = Arc::new(Semaphore::new(connections.len()));
+    ///
+    /// `statement_cache_capacity` is the capacity of the prepared statement cache
+    /// the connections were configured with.
+    pub fn new(connections: Vec<Connection>, statement_cache_capacity: usize) -> Self {
+        let size = connections.len();
+        let semaphore = Arc::new(Semaphore::new(size));
         let inner = Arc::new(InnerPool {
             connections: parking_lot::Mutex::new(connections),
+            size,
+            statement_cache_capacity,
+            metrics: Default::default(),
             semaphore,
             write_mutex: Default::default(),
         });
@@ -195,4 +280,17 @@ pub fn new(connections: Vec<Connection>) -> Self {
     pub async fn get(&self, query_only: bool) -> Result<PooledConnection> {
         Arc::clone(&self.inner).get(query_only).await
     }
+
+    /// Returns the metrics of the pool.
+    pub fn stats(&self) -> PoolStats {
+        let metrics = &self.inner.metrics;
+        PoolStats {
+            size: self.inner.size,
+            waits: metrics.waits.load(Ordering::Relaxed),
+            total_wait_ms: metrics.wait_ms.load(Ordering::Relaxed),
+            statement_cache_capacity: self.inner.statement_cache_capacity,
+            statement_cache_hits: metrics.cache_hits.load(Ordering::Relaxed),
+            statement_cache_misses: metrics.cache_misses.load(Ordering::Relaxed),
+        }
+    }
 }
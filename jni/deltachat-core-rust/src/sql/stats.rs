@@ -0,0 +1,248 @@
+//! # SQL query statistics.
+//!
+//! Execution times of queries run through the [`Sql`](super::Sql) helpers are aggregated
+//! per statement. Queries exceeding [`SLOW_QUERY_THRESHOLD`] are additionally recorded
+//! as [`SlowQuery`] and logged. Only the statement text with its placeholders
+//! and the number of parameters are recorded, never the parameter values.
+//!
+//! Additionally connection pool metrics are reported as [`PoolStats`]
+//! together with the size of the write-ahead log.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::tools::time;
+
+/// Queries taking longer than this are recorded as slow queries.
+pub(crate) const SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Maximum number of slow queries kept, older ones are dropped.
+const MAX_SLOW_QUERIES: usize = 50;
+
+/// Maximum number of distinct statements aggregated.
+///
+/// Statements are usually static strings,
+/// but some are generated, e.g. with a variable number of placeholders.
+const MAX_STATEMENTS: usize = 500;
+
+/// Aggregated execution times of a single statement.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatementStats {
+    /// Statement text with whitespace collapsed.
+    pub sql: String,
+
+    /// Number of executions.
+    pub count: u64,
+
+    /// Total execution time in milliseconds.
+    pub total_ms: u64,
+
+    /// Longest execution time in milliseconds.
+    pub max_ms: u64,
+}
+
+/// Query which took longer than [`SLOW_QUERY_THRESHOLD`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlowQuery {
+    /// Unix timestamp of the query.
+    pub timestamp: i64,
+
+    /// Statement text with whitespace collapsed.
+    pub sql: String,
+
+    /// Number of parameter placeholders in the statement.
+    pub param_count: usize,
+
+    /// Execution time in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// Connection pool metrics since the database was opened.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PoolStats {
+    /// Number of connections in the pool.
+    pub size: usize,
+
+    /// Number of times a connection could not be allocated immediately.
+    pub waits: u64,
+
+    /// Total time spent waiting for a connection in milliseconds.
+    pub total_wait_ms: u64,
+
+    /// Capacity of the prepared statement cache of each connection.
+    pub statement_cache_capacity: usize,
+
+    /// Number of statements found in the prepared statement cache.
+    pub statement_cache_hits: u64,
+
+    /// Number of statements which had to be prepared.
+    pub statement_cache_misses: u64,
+}
+
+impl PoolStats {
+    /// Returns the ratio of statement cache hits to all lookups,
+    /// or `None` if there were no lookups yet.
+    pub fn statement_cache_hit_ratio(&self) -> Option<f64> {
+        let lookups = self
+            .statement_cache_hits
+            .saturating_add(self.statement_cache_misses);
+        if lookups == 0 {
+            return None;
+        }
+        Some(self.statement_cache_hits as f64 / lookups as f64)
+    }
+}
+
+/// Statistics of the queries executed since the database was opened.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SqlStats {
+    /// Statements, the ones with the highest total execution time first.
+    pub statements: Vec<StatementStats>,
+
+    /// Most recent slow queries, oldest first.
+    pub slow_queries: Vec<SlowQuery>,
+
+    /// Connection pool metrics, `None` if the database is closed.
+    pub pool: Option<PoolStats>,
+
+    /// Size of the write-ahead log file in bytes.
+    pub wal_size: u64,
+}
+
+#[derive(Debug, Default)]
+struct CollectorState {
+    statements: HashMap<String, StatementStats>,
+    slow_queries: VecDeque<SlowQuery>,
+}
+
+/// Collects [`SqlStats`].
+#[derive(Debug, Default)]
+pub(crate) struct StatsCollector {
+    state: Mutex<CollectorState>,
+}
+
+impl StatsCollector {
+    /// Records an execution of `sql`.
+    ///
+    /// Returns the [`SlowQuery`] if the execution exceeded the threshold.
+    pub(crate) fn record(&self, sql: &str, duration: Duration) -> Option<SlowQuery> {
+        let sql = normalize_sql(sql);
+        let duration_ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        let mut state = self.state.lock();
+
+        if state.statements.len() < MAX_STATEMENTS || state.statements.contains_key(&sql) {
+            let stats = state
+                .statements
+                .entry(sql.clone())
+                .or_insert_with(|| StatementStats {
+                    sql: sql.clone(),
+                    ..Default::default()
+                });
+            stats.count += 1;
+            stats.total_ms = stats.total_ms.saturating_add(duration_ms);
+            stats.max_ms = stats.max_ms.max(duration_ms);
+        }
+
+        if duration < SLOW_QUERY_THRESHOLD {
+            return None;
+        }
+        let slow_query = SlowQuery {
+            timestamp: time(),
+            param_count: param_count(&sql),
+            sql,
+            duration_ms,
+        };
+        if state.slow_queries.len() >= MAX_SLOW_QUERIES {
+            state.slow_queries.pop_front();
+        }
+        state.slow_queries.push_back(slow_query.clone());
+        Some(slow_query)
+    }
+
+    /// Returns the collected statistics.
+    pub(crate) fn get(&self) -> SqlStats {
+        let state = self.state.lock();
+        let mut statements: Vec<StatementStats> = state.statements.values().cloned().collect();
+        statements.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then(b.count.cmp(&a.count)));
+        SqlStats {
+            statements,
+            slow_queries: state.slow_queries.iter().cloned().collect(),
+            pool: None,
+            wal_size: 0,
+        }
+    }
+
+    /// Clears the collected statistics.
+    pub(crate) fn reset(&self) {
+        *self.state.lock() = Default::default();
+    }
+}
+
+/// Collapses whitespace so the same statement formatted differently is aggregated together.
+fn normalize_sql(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the number of parameter placeholders outside of string literals.
+fn param_count(sql: &str) -> usize {
+    let mut count = 0;
+    let mut quote = None;
+    for c in sql.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '?' => count += 1,
+            None => {}
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_param_count() {
+        assert_eq!(param_count("SELECT 1"), 0);
+        assert_eq!(
+            param_count("SELECT id FROM msgs WHERE chat_id=? AND id>?"),
+            2
+        );
+        assert_eq!(
+            param_count("SELECT '?' FROM msgs WHERE txt LIKE \"%?\" AND id=?"),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stats_collector() {
+        let collector = StatsCollector::default();
+        assert_eq!(
+            collector.record("SELECT  id\n FROM msgs", Duration::from_millis(1)),
+            None
+        );
+        let slow_query = collector
+            .record("SELECT id FROM msgs WHERE id=?", Duration::from_millis(150))
+            .unwrap();
+        assert_eq!(slow_query.sql, "SELECT id FROM msgs WHERE id=?");
+        assert_eq!(slow_query.param_count, 1);
+        assert_eq!(slow_query.duration_ms, 150);
+        collector.record("SELECT id FROM msgs", Duration::from_millis(3));
+
+        let stats = collector.get();
+        assert_eq!(stats.slow_queries, vec![slow_query]);
+        assert_eq!(stats.statements.len(), 2);
+        assert_eq!(stats.statements[0].total_ms, 150);
+        assert_eq!(stats.statements[1].sql, "SELECT id FROM msgs");
+        assert_eq!(stats.statements[1].count, 2);
+        assert_eq!(stats.statements[1].max_ms, 3);
+
+        collector.reset();
+        assert_eq!(collector.get(), SqlStats::default());
+    }
+}
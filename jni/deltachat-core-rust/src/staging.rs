@@ -0,0 +1,410 @@
+//! # Staging of draft attachments.
+//!
+//! Preparing an attachment for sending, i.e. copying it into the blobdir,
+//! recoding images and computing thumbnails and waveforms,
+//! may take a while for several large files.
+//! Attachments can be staged with [`ChatId::stage_attachment`]
+//! while the user is still composing the message,
+//! they are prepared in the background then.
+//! [`Message::set_staged_attachment`] takes over a prepared attachment,
+//! so that sending the message does not need to prepare it again.
+//!
+//! The progress is reported with [`EventType::AttachmentStagingProgress`] events.
+//! Staged attachments are discarded when the draft of the chat is deleted
+//! and by housekeeping if they are not used within [`MAX_STAGED_AGE`] seconds.
+//! Their blobs are removed by housekeeping afterwards.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use deltachat_derive::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::chat::{self, ChatId};
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::{Message, Viewtype};
+use crate::param::{Param, Params};
+use crate::tools::time;
+
+/// Time in seconds after which unused staged attachments are discarded by housekeeping.
+pub(crate) const MAX_STAGED_AGE: i64 = 60 * 60 * 24;
+
+/// ID of a staged attachment.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct StagedAttachmentId(u32);
+
+impl StagedAttachmentId {
+    /// Creates a new [`StagedAttachmentId`].
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    /// Returns the ID as a `u32`.
+    pub fn to_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the state of the staged attachment, `None` if it does not exist.
+    pub async fn get_state(self, context: &Context) -> Result<Option<StagingState>> {
+        context
+            .sql
+            .query_get_value("SELECT state FROM staged_attachments WHERE id=?", (self.0,))
+            .await
+    }
+
+    /// Cancels preparing the staged attachment and discards it,
+    /// e.g. because the user removed it from the draft.
+    pub async fn discard(self, context: &Context) -> Result<()> {
+        if let Some(cancel) = context.staging.tasks.lock().remove(&self) {
+            cancel.cancel();
+        }
+        context
+            .sql
+            .execute("DELETE FROM staged_attachments WHERE id=?", (self.0,))
+            .await?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for StagedAttachmentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Staged#{}", self.0)
+    }
+}
+
+/// State of a staged attachment.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromPrimitive,
+    ToPrimitive,
+    FromSql,
+    ToSql,
+    Serialize,
+    Deserialize,
+)]
+#[repr(u32)]
+pub enum StagingState {
+    /// The attachment is being prepared.
+    Pending = 0,
+
+    /// The attachment is prepared and can be used with [`Message::set_staged_attachment`].
+    Ready = 1,
+
+    /// Preparing the attachment failed or was interrupted.
+    Failed = 2,
+}
+
+/// Stagings running in this process.
+#[derive(Debug, Default)]
+pub(crate) struct StagingTasks {
+    /// Tokens to cancel the running stagings.
+    tasks: parking_lot::Mutex<HashMap<StagedAttachmentId, CancellationToken>>,
+
+    /// Notified when a staging finished.
+    finished: Notify,
+}
+
+impl ChatId {
+    /// Stages the attachment of `msg` for a message to be sent to the chat.
+    ///
+    /// The attachment is copied into the blobdir and prepared for sending in the background,
+    /// the message itself is not changed.
+    /// Once the returned attachment is ready,
+    /// it can be added to a message with [`Message::set_staged_attachment`].
+    pub async fn stage_attachment(
+        self,
+        context: &Context,
+        msg: &Message,
+    ) -> Result<StagedAttachmentId> {
+        ensure!(
+            msg.viewtype.has_file() && msg.param.exists(Param::File),
+            "No attachment to stage"
+        );
+        ensure!(
+            !msg.is_increation(),
+            "Cannot stage an attachment in creation"
+        );
+        let mut param = Params::new();
+        for key in [
+            Param::File,
+            Param::Filename,
+            Param::MimeType,
+            Param::OriginalQuality,
+            Param::ForceSticker,
+        ] {
+            param.set_optional(key, msg.param.get(key));
+        }
+        let row_id = context
+            .sql
+            .insert(
+                "INSERT INTO staged_attachments (chat_id, viewtype, param, state, timestamp)
+                 VALUES (?, ?, ?, ?, ?)",
+                (
+                    self,
+                    msg.viewtype,
+                    param.to_string(),
+                    StagingState::Pending,
+                    time(),
+                ),
+            )
+            .await?;
+        let id = StagedAttachmentId(u32::try_from(row_id)?);
+        let cancel = CancellationToken::new();
+        context.staging.tasks.lock().insert(id, cancel.clone());
+
+        let mut staged = Message::new(msg.viewtype);
+        staged.param = param;
+        let context = context.clone();
+        tokio::spawn(async move { run_staging(&context, self, id, staged, cancel).await });
+        Ok(id)
+    }
+
+    /// Returns the attachments staged for the chat, oldest first.
+    pub async fn get_staged_attachments(
+        self,
+        context: &Context,
+    ) -> Result<Vec<StagedAttachmentId>> {
+        context
+            .sql
+            .query_map(
+                "SELECT id FROM staged_attachments WHERE chat_id=? ORDER BY id",
+                (self,),
+                |row| row.get::<_, u32>(0),
+                |ids| {
+                    ids.map(|id| id.map(StagedAttachmentId).map_err(Into::into))
+                        .collect()
+                },
+            )
+            .await
+    }
+}
+
+impl Message {
+    /// Sets an attachment staged with [`ChatId::stage_attachment`] as the attachment of the message,
+    /// waiting until it is prepared.
+    ///
+    /// The staged attachment is consumed,
+    /// it must be staged for the chat the message is sent to,
+    /// otherwise sending the message fails.
+    /// If another file is set afterwards, it is prepared when sending as usual.
+    pub async fn set_staged_attachment(
+        &mut self,
+        context: &Context,
+        id: StagedAttachmentId,
+    ) -> Result<()> {
+        let (chat_id, viewtype, param) = loop {
+            let finished = context.staging.finished.notified();
+            // Check whether the staging is running before loading the state,
+            // the state is saved before the staging is removed from the running ones.
+            let running = context.staging.tasks.lock().contains_key(&id);
+            let row = context
+                .sql
+                .query_row_optional(
+                    "SELECT state, chat_id, viewtype, param FROM staged_attachments WHERE id=?",
+                    (id.0,),
+                    |row| {
+                        let state: StagingState = row.get(0)?;
+                        let chat_id: ChatId = row.get(1)?;
+                        let viewtype: Viewtype = row.get(2)?;
+                        let param: String = row.get(3)?;
+                        Ok((state, chat_id, viewtype, param))
+                    },
+                )
+                .await?
+                .with_context(|| format!("{id} does not exist"))?;
+            match row {
+                (StagingState::Ready, chat_id, viewtype, param) => {
+                    break (chat_id, viewtype, param)
+                }
+                (StagingState::Pending, ..) if running => finished.await,
+                (StagingState::Pending, ..) => bail!("Staging {id} was interrupted"),
+                (StagingState::Failed, ..) => bail!("Staging {id} failed"),
+            }
+        };
+        self.viewtype = viewtype;
+        self.param.extend(param.parse().unwrap_or_default());
+        self.param.set_int(Param::Staged, chat_id.to_u32() as i32);
+        context
+            .sql
+            .execute("DELETE FROM staged_attachments WHERE id=?", (id.0,))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Prepares the attachment of `msg` and saves the result as staged attachment `id`.
+async fn run_staging(
+    context: &Context,
+    chat_id: ChatId,
+    id: StagedAttachmentId,
+    mut msg: Message,
+    cancel: CancellationToken,
+) {
+    let res = tokio::select! {
+        res = prepare(context, chat_id, id, &mut msg) => res,
+        // An image being recoded is recoded to the end on its blocking thread,
+        // the resulting blob is removed by housekeeping.
+        _ = cancel.cancelled() => Err(anyhow::anyhow!("Staging cancelled")),
+    };
+    let state = match res {
+        Ok(()) => StagingState::Ready,
+        Err(err) => {
+            warn!(context, "Failed to stage attachment {id}: {err:#}.");
+            StagingState::Failed
+        }
+    };
+    if let Err(err) = context
+        .sql
+        .execute(
+            "UPDATE staged_attachments SET state=?, viewtype=?, param=? WHERE id=?",
+            (state, msg.viewtype, msg.param.to_string(), id.0),
+        )
+        .await
+    {
+        warn!(context, "Failed to save staged attachment {id}: {err:#}.");
+    }
+    context.staging.tasks.lock().remove(&id);
+    context.staging.finished.notify_waiters();
+    let progress = match state {
+        StagingState::Ready => 1000,
+        _ => 0,
+    };
+    context.emit_event(EventType::AttachmentStagingProgress {
+        chat_id,
+        staged_id: id,
+        progress,
+    });
+}
+
+/// Copies the attachment of `msg` into the blobdir and prepares it for sending.
+async fn prepare(
+    context: &Context,
+    chat_id: ChatId,
+    id: StagedAttachmentId,
+    msg: &mut Message,
+) -> Result<()> {
+    let blob = msg
+        .param
+        .get_blob(Param::File, context, true)
+        .await?
+        .context("Attachment missing")?;
+    msg.param.set(Param::File, blob.as_name());
+    context.emit_event(EventType::AttachmentStagingProgress {
+        chat_id,
+        staged_id: id,
+        progress: 500,
+    });
+    chat::prepare_msg_blob(context, chat_id, msg).await
+}
+
+/// Discards all attachments staged for the chat.
+pub(crate) async fn discard_chat_attachments(context: &Context, chat_id: ChatId) -> Result<()> {
+    for id in chat_id.get_staged_attachments(context).await? {
+        id.discard(context).await?;
+    }
+    Ok(())
+}
+
+/// Discards staged attachments which were not used for [`MAX_STAGED_AGE`] seconds.
+pub(crate) async fn prune_staged_attachments(context: &Context) -> Result<()> {
+    let ids = context
+        .sql
+        .query_map(
+            "SELECT id FROM staged_attachments WHERE timestamp<?",
+            (time() - MAX_STAGED_AGE,),
+            |row| row.get::<_, u32>(0),
+            |ids| {
+                ids.collect::<rusqlite::Result<Vec<_>>>()
+                    .map_err(Into::into)
+            },
+        )
+        .await?;
+    for id in ids {
+        StagedAttachmentId(id).discard(context).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stage_attachment() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let chat_id = alice.create_chat(bob).await.id;
+
+        let file = alice.dir.path().join("image.png");
+        tokio::fs::write(&file, include_bytes!("../test-data/image/avatar64x64.png")).await?;
+        let mut staged_msg = Message::new(Viewtype::File);
+        staged_msg.set_file(file.to_str().unwrap(), None);
+        let id = chat_id.stage_attachment(alice, &staged_msg).await?;
+        assert_eq!(chat_id.get_staged_attachments(alice).await?, vec![id]);
+
+        let mut msg = Message::new_text("Hello".to_string());
+        msg.set_staged_attachment(alice, id).await?;
+        assert_eq!(id.get_state(alice).await?, None);
+        assert_eq!(msg.get_viewtype(), Viewtype::Image);
+        assert_eq!(msg.get_width(), 64);
+        assert!(msg
+            .get_file(alice)
+            .unwrap()
+            .starts_with(alice.get_blobdir()));
+        alice
+            .evtracker
+            .get_matching(|evt| {
+                matches!(
+                    evt,
+                    EventType::AttachmentStagingProgress { progress: 1000, .. }
+                )
+            })
+            .await;
+
+        let sent = alice.send_msg(chat_id, &mut msg).await;
+        let received = bob.recv_msg(&sent).await;
+        assert_eq!(received.get_viewtype(), Viewtype::Image);
+        assert_eq!(received.get_text(), "Hello");
+
+        // Staging a missing file fails.
+        staged_msg.set_file("/nonexistent/image.png", None);
+        let id = chat_id.stage_attachment(alice, &staged_msg).await?;
+        assert!(msg.set_staged_attachment(alice, id).await.is_err());
+        assert_eq!(id.get_state(alice).await?, Some(StagingState::Failed));
+
+        // An attachment staged for another chat is not sent.
+        let fiona = &tcm.fiona().await;
+        let other_chat_id = alice.create_chat(fiona).await.id;
+        staged_msg.set_file(file.to_str().unwrap(), None);
+        let id = other_chat_id.stage_attachment(alice, &staged_msg).await?;
+        let mut msg = Message::new_text("Hello".to_string());
+        msg.set_staged_attachment(alice, id).await?;
+        assert!(chat::send_msg(alice, chat_id, &mut msg).await.is_err());
+
+        // A staged attachment replaced by another file is not considered prepared.
+        let id = chat_id.stage_attachment(alice, &staged_msg).await?;
+        let mut msg = Message::new_text("Hello".to_string());
+        msg.set_staged_attachment(alice, id).await?;
+        assert!(msg.param.exists(Param::Staged));
+        msg.set_file_from_bytes(alice, "other.txt", b"other", None)
+            .await?;
+        assert!(!msg.param.exists(Param::Staged));
+
+        // Deleting the draft discards staged attachments.
+        chat_id.set_draft(alice, None).await?;
+        assert!(chat_id.get_staged_attachments(alice).await?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! # Startup telemetry and lazy IO start.
+//!
+//! Durations of the startup phases are recorded as [`StartupTimings`],
+//! so slow starts on old devices can be diagnosed.
+//!
+//! If [`Config::LazyStart`] is enabled, [`Context::start_io`] defers starting the scheduler
+//! until the chatlist is loaded for the first time, or until [`LAZY_START_TIMEOUT`] elapses,
+//! so the database is not busy with fetching and housekeeping
+//! while the UI loads the chatlist.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::tools::{self, time_elapsed};
+
+/// Maximum time to defer IO start waiting for the chatlist to be loaded.
+pub(crate) const LAZY_START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Phase of the startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StartupPhase {
+    /// Opening the database connections.
+    DbOpen,
+
+    /// Running database migrations.
+    Migrations,
+
+    /// Loading the configuration into the cache.
+    ConfigLoad,
+
+    /// Starting the IO scheduler.
+    SchedulerStart,
+}
+
+/// Durations of the startup phases in milliseconds.
+///
+/// Phases which did not run yet are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StartupTimings {
+    /// Time to open the database connections.
+    pub db_open_ms: Option<u64>,
+
+    /// Time to run database migrations.
+    pub migrations_ms: Option<u64>,
+
+    /// Time to load the configuration.
+    pub config_load_ms: Option<u64>,
+
+    /// Time to start the IO scheduler.
+    pub scheduler_start_ms: Option<u64>,
+
+    /// Time from creating the context until IO start was requested.
+    pub io_start_requested_ms: Option<u64>,
+
+    /// Time from creating the context until the chatlist was loaded for the first time.
+    pub first_chatlist_ms: Option<u64>,
+}
+
+/// Startup state of the context.
+#[derive(Debug, Default)]
+pub(crate) struct StartupState {
+    timings: parking_lot::Mutex<StartupTimings>,
+
+    /// Set when the chatlist is loaded for the first time.
+    chatlist_loaded: AtomicBool,
+
+    /// Notified when the chatlist is loaded for the first time.
+    chatlist_notify: Notify,
+
+    /// Deferred IO start, aborted by [`Context::stop_io`].
+    pending_io_start: parking_lot::Mutex<Option<AbortHandle>>,
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+impl StartupState {
+    /// Records the duration of a startup phase.
+    pub(crate) fn record(&self, phase: StartupPhase, duration: Duration) {
+        let ms = Some(as_millis(duration));
+        let mut timings = self.timings.lock();
+        match phase {
+            StartupPhase::DbOpen => timings.db_open_ms = ms,
+            StartupPhase::Migrations => timings.migrations_ms = ms,
+            StartupPhase::ConfigLoad => timings.config_load_ms = ms,
+            StartupPhase::SchedulerStart => timings.scheduler_start_ms = ms,
+        }
+    }
+
+    /// Aborts the deferred IO start, if any.
+    pub(crate) fn abort_pending_io_start(&self) {
+        if let Some(handle) = self.pending_io_start.lock().take() {
+            handle.abort();
+        }
+    }
+}
+
+impl Context {
+    /// Returns the durations of the startup phases.
+    pub fn get_startup_timings(&self) -> StartupTimings {
+        self.startup.timings.lock().clone()
+    }
+
+    /// Called when a chatlist is loaded.
+    pub(crate) fn on_chatlist_loaded(&self) {
+        if self.startup.chatlist_loaded.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let elapsed = as_millis(time_elapsed(&self.creation_time));
+        self.startup.timings.lock().first_chatlist_ms = Some(elapsed);
+        self.startup.chatlist_notify.notify_waiters();
+    }
+
+    /// Starts the IO scheduler, deferring the start if [`Config::LazyStart`] is enabled
+    /// and the chatlist was not loaded yet.
+    pub(crate) async fn start_scheduler(&self) {
+        let elapsed = as_millis(time_elapsed(&self.creation_time));
+        self.startup.timings.lock().io_start_requested_ms = Some(elapsed);
+
+        let lazy_start = self
+            .get_config_bool(Config::LazyStart)
+            .await
+            .unwrap_or_default();
+        if !lazy_start || self.startup.chatlist_loaded.load(Ordering::Relaxed) {
+            self.start_scheduler_now().await;
+            return;
+        }
+
+        info!(self, "Deferring IO start until the chatlist is loaded.");
+        let context = self.clone();
+        let mut pending_io_start = self.startup.pending_io_start.lock();
+        if let Some(handle) = pending_io_start.take() {
+            handle.abort();
+        }
+        let handle = tokio::spawn(async move {
+            let notified = context.startup.chatlist_notify.notified();
+            if !context.startup.chatlist_loaded.load(Ordering::Relaxed)
+                && tokio::time::timeout(LAZY_START_TIMEOUT, notified)
+                    .await
+                    .is_err()
+            {
+                info!(context, "Chatlist was not loaded in time, starting IO.");
+            }
+            context.startup.pending_io_start.lock().take();
+            context.start_scheduler_now().await;
+        });
+        *pending_io_start = Some(handle.abort_handle());
+    }
+
+    async fn start_scheduler_now(&self) {
+        let start = tools::Time::now();
+        self.scheduler.start(self.clone()).await;
+        self.startup
+            .record(StartupPhase::SchedulerStart, time_elapsed(&start));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chatlist::Chatlist;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_startup_timings() -> anyhow::Result<()> {
+        let t = TestContext::new_alice().await;
+        let timings = t.get_startup_timings();
+        assert!(timings.db_open_ms.is_some());
+        assert!(timings.migrations_ms.is_some());
+        assert!(timings.config_load_ms.is_some());
+        assert_eq!(timings.scheduler_start_ms, None);
+        assert_eq!(timings.first_chatlist_ms, None);
+
+        Chatlist::try_load(&t, 0, None, None).await?;
+        assert!(t.get_startup_timings().first_chatlist_ms.is_some());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lazy_start() -> anyhow::Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config_bool(Config::LazyStart, true).await?;
+        t.start_io().await;
+        assert!(!t.scheduler.is_running().await);
+        assert!(t.get_startup_timings().io_start_requested_ms.is_some());
+
+        Chatlist::try_load(&t, 0, None, None).await?;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !t.scheduler.is_running().await {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+        t.stop_io().await;
+        Ok(())
+    }
+}
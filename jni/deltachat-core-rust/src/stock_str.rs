@@ -1,10 +1,12 @@
 //! Module to work with translatable stock strings.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Context as _, Result};
 use humansize::{format_size, BINARY};
+use num_traits::FromPrimitive;
 use strum::EnumProperty as EnumPropertyTrait;
 use strum_macros::EnumProperty;
 use tokio::sync::RwLock;
@@ -437,8 +439,52 @@ pub enum StockMessage {
         fallback = "Could not yet establish guaranteed end-to-end encryption, but you may already send a message."
     ))]
     SecurejoinWaitTimeout = 191,
+
+    #[strum(props(fallback = "Secure-Join: %1$s"))]
+    SecureJoinMsgBody = 192,
+
+    #[strum(props(fallback = "Mailbox"))]
+    Mailbox = 193,
+
+    #[strum(props(fallback = "Members joined: %1$s, left: %2$s"))]
+    MemberChangesDigest = 194,
+
+    #[strum(props(fallback = "Incoming call"))]
+    IncomingCall = 195,
+
+    #[strum(props(fallback = "Outgoing call"))]
+    OutgoingCall = 196,
+
+    #[strum(props(fallback = "Missed call"))]
+    MissedCall = 197,
+
+    #[strum(props(fallback = "Account %1$s configured"))]
+    ActivityConfigured = 198,
+
+    #[strum(props(fallback = "Backup exported"))]
+    ActivityBackupExported = 199,
+
+    #[strum(props(fallback = "Setting %1$s changed"))]
+    ActivityConfigChanged = 201,
+
+    #[strum(props(fallback = "Paused: %1$s"))]
+    IoPaused = 202,
 }
 
+/// Stock strings used in the bodies of outgoing messages
+/// that can be replaced by templates with [`Context::set_stock_template`].
+///
+/// These bodies are only shown to recipients not using Delta Chat,
+/// e.g. when a group member reads the group with a classic email client.
+pub const OUTGOING_TEMPLATES: [StockMessage; 6] = [
+    StockMessage::MsgIAddMember,
+    StockMessage::MsgIDelMember,
+    StockMessage::MsgILeftGroup,
+    StockMessage::AcSetupMsgBody,
+    StockMessage::SubjectForNewContact,
+    StockMessage::SecureJoinMsgBody,
+];
+
 impl StockMessage {
     /// Default untranslated strings for stock messages.
     ///
@@ -472,33 +518,124 @@ async fn translated(&self, id: StockMessage) -> String {
             .to_string()
     }
 
+    /// Returns the translation of `id` if there is one.
+    async fn get(&self, id: StockMessage) -> Option<String> {
+        self.translated_stockstrings
+            .read()
+            .await
+            .get(&(id as usize))
+            .cloned()
+    }
+
     async fn set_stock_translation(&self, id: StockMessage, stockstring: String) -> Result<()> {
-        if stockstring.contains("%1") && !id.fallback().contains("%1") {
-            bail!(
-                "translation {} contains invalid %1 placeholder, default is {}",
-                stockstring,
-                id.fallback()
-            );
-        }
-        if stockstring.contains("%2") && !id.fallback().contains("%2") {
-            bail!(
-                "translation {} contains invalid %2 placeholder, default is {}",
-                stockstring,
-                id.fallback()
-            );
-        }
+        check_translation(id, &stockstring)?;
         self.translated_stockstrings
             .write()
             .await
             .insert(id as usize, stockstring);
         Ok(())
     }
+
+    /// Replaces all translations with `translations`.
+    async fn replace_all(&self, translations: Vec<(StockMessage, String)>) {
+        let mut lock = self.translated_stockstrings.write().await;
+        lock.clear();
+        for (id, stockstring) in translations {
+            lock.insert(id as usize, stockstring);
+        }
+    }
+}
+
+/// Checks that the translation `stockstring` of `id`
+/// only uses placeholders used by the default string.
+fn check_translation(id: StockMessage, stockstring: &str) -> Result<()> {
+    if stockstring.contains("%1") && !id.fallback().contains("%1") {
+        bail!(
+            "translation {} contains invalid %1 placeholder, default is {}",
+            stockstring,
+            id.fallback()
+        );
+    }
+    if stockstring.contains("%2") && !id.fallback().contains("%2") {
+        bail!(
+            "translation {} contains invalid %2 placeholder, default is {}",
+            stockstring,
+            id.fallback()
+        );
+    }
+    Ok(())
 }
 
+/// Parses a translations file,
+/// a JSON object mapping stock string IDs to translations.
+///
+/// Unknown stock string IDs are ignored
+/// so translation files of newer versions can be used.
+fn parse_stock_translations(json: &str) -> Result<Vec<(StockMessage, String)>> {
+    let map: BTreeMap<String, String> =
+        serde_json::from_str(json).context("Failed to parse translations")?;
+    let mut translations = Vec::with_capacity(map.len());
+    for (id, stockstring) in map {
+        let id: u32 = id
+            .parse()
+            .with_context(|| format!("Invalid stock string ID {id:?}"))?;
+        let Some(id) = StockMessage::from_u32(id) else {
+            continue;
+        };
+        check_translation(id, &stockstring)?;
+        translations.push((id, stockstring));
+    }
+    Ok(translations)
+}
+
+/// Raw config key of the translations of the account.
+const STOCK_TRANSLATIONS_KEY: &str = "stock_translations";
+
 async fn translated(context: &Context, id: StockMessage) -> String {
+    if OUTGOING_TEMPLATES.contains(&id) {
+        match context.get_stock_template(id).await {
+            Ok(Some(template)) => return template,
+            Ok(None) => {}
+            Err(err) => warn!(context, "Failed to load stock template {id:?}: {err:#}."),
+        }
+    }
+    if let Some(stockstring) = context.account_stockstrings.get(id).await {
+        return stockstring;
+    }
     context.translated_stockstrings.translated(id).await
 }
 
+/// Returns the numbers of the `%1$s`-style placeholders used in `s`.
+fn placeholders(s: &str) -> Result<BTreeSet<u32>> {
+    let mut numbers = BTreeSet::new();
+    let mut rest = s;
+    while let Some((_, after)) = rest.split_once('%') {
+        if let Some(after) = after.strip_prefix('%') {
+            // `%%` is a literal percent sign.
+            rest = after;
+            continue;
+        }
+        let digits = after.chars().take_while(char::is_ascii_digit).count();
+        let (number, tail) = after.split_at(digits);
+        rest = tail;
+        if number.is_empty() {
+            continue;
+        }
+        let mut tail = tail.chars();
+        ensure!(
+            tail.next() == Some('$') && matches!(tail.next(), Some('s' | 'd' | '@')),
+            "Invalid placeholder %{number} in {s:?}"
+        );
+        numbers.insert(number.parse()?);
+    }
+    Ok(numbers)
+}
+
+/// Returns the raw config key of the template for the stock string `id`.
+fn stock_template_key(id: StockMessage) -> String {
+    format!("stock_template_{}", id as u32)
+}
+
 /// Helper trait only meant to be implemented for [`String`].
 trait StockStringMods: AsRef<str> + Sized {
     /// Substitutes the first replacement value if one is present.
@@ -534,10 +671,12 @@ fn replace3(&self, replacement: &str) -> String {
 
 impl ContactId {
     /// Get contact name and address for stock string, e.g. `Bob (bob@example.net)`
+    ///
+    /// The local nickname is not used as the texts of sent messages must not contain it.
     async fn get_stock_name_n_addr(self, context: &Context) -> String {
         Contact::get_by_id(context, self)
             .await
-            .map(|contact| contact.get_name_n_addr())
+            .map(|contact| contact.get_name_n_addr_ex(false))
             .unwrap_or_else(|_| self.to_string())
     }
 
@@ -655,7 +794,7 @@ pub(crate) async fn msg_add_member_local(
     let whom = &match Contact::lookup_id_by_addr(context, addr, Origin::Unknown).await {
         Ok(Some(contact_id)) => Contact::get_by_id(context, contact_id)
             .await
-            .map(|contact| contact.get_name_n_addr())
+            .map(|contact| contact.get_name_n_addr_ex(false))
             .unwrap_or_else(|_| addr.to_string()),
         _ => addr.to_string(),
     };
@@ -706,7 +845,7 @@ pub(crate) async fn msg_del_member_local(
     let whom = &match Contact::lookup_id_by_addr(context, addr, Origin::Unknown).await {
         Ok(Some(contact_id)) => Contact::get_by_id(context, contact_id)
             .await
-            .map(|contact| contact.get_name_n_addr())
+            .map(|contact| contact.get_name_n_addr_ex(false))
             .unwrap_or_else(|_| addr.to_string()),
         _ => addr.to_string(),
     };
@@ -899,6 +1038,71 @@ pub(crate) async fn ac_setup_msg_body(context: &Context) -> String {
     translated(context, StockMessage::AcSetupMsgBody).await
 }
 
+/// Stock string: `Secure-Join: %1$s`.
+pub(crate) async fn securejoin_msg_body(context: &Context, step: &str) -> String {
+    translated(context, StockMessage::SecureJoinMsgBody)
+        .await
+        .replace1(step)
+}
+
+/// Stock string: `Mailbox`.
+pub(crate) async fn mailbox(context: &Context) -> String {
+    translated(context, StockMessage::Mailbox).await
+}
+
+/// Stock string: `Members joined: %1$s, left: %2$s`.
+pub(crate) async fn member_changes_digest(
+    context: &Context,
+    added: usize,
+    removed: usize,
+) -> String {
+    translated(context, StockMessage::MemberChangesDigest)
+        .await
+        .replace1(&added.to_string())
+        .replace2(&removed.to_string())
+}
+
+/// Stock string: `Incoming call`.
+pub(crate) async fn incoming_call(context: &Context) -> String {
+    translated(context, StockMessage::IncomingCall).await
+}
+
+/// Stock string: `Outgoing call`.
+pub(crate) async fn outgoing_call(context: &Context) -> String {
+    translated(context, StockMessage::OutgoingCall).await
+}
+
+/// Stock string: `Missed call`.
+pub(crate) async fn missed_call(context: &Context) -> String {
+    translated(context, StockMessage::MissedCall).await
+}
+
+/// Stock string: `Account %1$s configured`.
+pub(crate) async fn activity_configured(context: &Context, addr: &str) -> String {
+    translated(context, StockMessage::ActivityConfigured)
+        .await
+        .replace1(addr)
+}
+
+/// Stock string: `Backup exported`.
+pub(crate) async fn activity_backup_exported(context: &Context) -> String {
+    translated(context, StockMessage::ActivityBackupExported).await
+}
+
+/// Stock string: `Setting %1$s changed`.
+pub(crate) async fn activity_config_changed(context: &Context, key: &str) -> String {
+    translated(context, StockMessage::ActivityConfigChanged)
+        .await
+        .replace1(key)
+}
+
+/// Stock string: `Paused: %1$s`.
+pub(crate) async fn io_paused(context: &Context, reason: &str) -> String {
+    translated(context, StockMessage::IoPaused)
+        .await
+        .replace1(reason)
+}
+
 /// Stock string: `Multi Device Synchronization`.
 pub(crate) async fn sync_msg_subject(context: &Context) -> String {
     translated(context, StockMessage::SyncMsgSubject).await
@@ -1377,6 +1581,85 @@ pub async fn set_stock_translation(&self, id: StockMessage, stockstring: String)
         Ok(())
     }
 
+    /// Loads the translations of the account from the file at `path`,
+    /// `None` resets the account to the translations shared by all accounts.
+    ///
+    /// The file is a JSON object mapping stock string IDs to translations,
+    /// e.g. `{"1": "Keine Nachrichten."}`.
+    /// Translations missing in the file fall back to the shared translations.
+    /// The translations are stored in the account,
+    /// so the file is not needed anymore afterwards.
+    pub async fn set_stock_translations_file(&self, path: Option<&Path>) -> Result<()> {
+        let json =
+            match path {
+                Some(path) => Some(tokio::fs::read_to_string(path).await.with_context(|| {
+                    format!("Cannot read translations from {}", path.display())
+                })?),
+                None => None,
+            };
+        let translations = match &json {
+            Some(json) => parse_stock_translations(json)?,
+            None => Vec::new(),
+        };
+        info!(
+            self,
+            "Using {} account-specific translations.",
+            translations.len()
+        );
+        self.sql
+            .set_raw_config(STOCK_TRANSLATIONS_KEY, json.as_deref())
+            .await?;
+        self.account_stockstrings.replace_all(translations).await;
+        Ok(())
+    }
+
+    /// Loads the translations stored with [`Context::set_stock_translations_file`].
+    pub(crate) async fn load_stock_translations(&self) -> Result<()> {
+        let translations = match self.sql.get_raw_config(STOCK_TRANSLATIONS_KEY).await? {
+            Some(json) => parse_stock_translations(&json)?,
+            None => Vec::new(),
+        };
+        self.account_stockstrings.replace_all(translations).await;
+        Ok(())
+    }
+
+    /// Sets the template for the body of outgoing messages
+    /// using the stock string `id`, `None` resets the template.
+    ///
+    /// Unlike translations, templates are stored per account
+    /// and take precedence over the translation.
+    /// Only the stock strings listed in [`OUTGOING_TEMPLATES`] can be customized.
+    /// The template may only use placeholders like `%1$s` used by the default string.
+    pub async fn set_stock_template(&self, id: StockMessage, template: Option<&str>) -> Result<()> {
+        ensure!(
+            OUTGOING_TEMPLATES.contains(&id),
+            "Stock string {id:?} cannot be customized"
+        );
+        if let Some(template) = template {
+            ensure!(!template.trim().is_empty(), "Template is empty");
+            let allowed = placeholders(id.fallback())?;
+            let used = placeholders(template)?;
+            if let Some(number) = used.difference(&allowed).next() {
+                bail!(
+                    "Template {template:?} contains invalid placeholder %{number}$s, default is {:?}",
+                    id.fallback()
+                );
+            }
+        }
+        self.sql
+            .set_raw_config(&stock_template_key(id), template)
+            .await
+    }
+
+    /// Returns the template set for the stock string `id`
+    /// with [`Context::set_stock_template`].
+    pub async fn get_stock_template(&self, id: StockMessage) -> Result<Option<String>> {
+        if !OUTGOING_TEMPLATES.contains(&id) {
+            return Ok(None);
+        }
+        self.sql.get_raw_config(&stock_template_key(id)).await
+    }
+
     /// Returns a stock message saying that protection status has changed.
     pub(crate) async fn stock_protection_msg(
         &self,
@@ -1480,6 +1763,109 @@ async fn test_set_stock_translation_wrong_replacements() {
             .is_err());
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stock_translations_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let p = dir.path().join("accounts");
+        let path = dir.path().join("fr.json");
+        tokio::fs::write(
+            &path,
+            r#"{"1": "Aucun message.", "3": "Brouillon", "9999": "Unknown"}"#,
+        )
+        .await?;
+
+        let id1 = {
+            let mut accounts = Accounts::new(p.clone(), true).await?;
+            accounts
+                .set_stock_translation(StockMessage::NoMessages, "Keine Nachrichten.".to_string())
+                .await?;
+            let id0 = accounts.add_account().await?;
+            let id1 = accounts.add_account().await?;
+            let ctx0 = accounts.get_account(id0).context("no account")?;
+            let ctx1 = accounts.get_account(id1).context("no account")?;
+
+            ctx1.set_stock_translations_file(Some(&path)).await?;
+            assert_eq!(no_messages(&ctx0).await, "Keine Nachrichten.");
+            assert_eq!(no_messages(&ctx1).await, "Aucun message.");
+            assert_eq!(draft(&ctx1).await, "Brouillon");
+            // Strings missing in the file fall back to the shared translations.
+            assert_eq!(self_msg(&ctx1).await, self_msg(&ctx0).await);
+            id1
+        };
+        tokio::fs::remove_file(&path).await?;
+
+        // Translations are stored in the account.
+        let accounts = Accounts::new(p, true).await?;
+        let ctx1 = accounts.get_account(id1).context("no account")?;
+        assert_eq!(no_messages(&ctx1).await, "Aucun message.");
+
+        // Files with invalid placeholders are rejected.
+        tokio::fs::write(&path, r#"{"1": "%1$s"}"#).await?;
+        assert!(ctx1.set_stock_translations_file(Some(&path)).await.is_err());
+        assert_eq!(no_messages(&ctx1).await, "Aucun message.");
+
+        ctx1.set_stock_translations_file(None).await?;
+        assert_eq!(no_messages(&ctx1).await, "No messages.");
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stock_template() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert_eq!(
+            msg_add_member_remote(&t, "bob@example.net").await,
+            "I added member bob@example.net."
+        );
+
+        // Templates take precedence over translations.
+        t.set_stock_translation(
+            StockMessage::MsgIAddMember,
+            "Ich habe %1$s hinzugefügt.".into(),
+        )
+        .await?;
+        t.set_stock_template(StockMessage::MsgIAddMember, Some("Welcome %1$s!"))
+            .await?;
+        assert_eq!(
+            t.get_stock_template(StockMessage::MsgIAddMember).await?,
+            Some("Welcome %1$s!".to_string())
+        );
+        assert_eq!(
+            msg_add_member_remote(&t, "bob@example.net").await,
+            "Welcome bob@example.net!"
+        );
+
+        // Placeholders not used by the default string are rejected.
+        assert!(t
+            .set_stock_template(StockMessage::MsgIAddMember, Some("%2$s added %1$s"))
+            .await
+            .is_err());
+        assert!(t
+            .set_stock_template(StockMessage::MsgILeftGroup, Some("%1$s left"))
+            .await
+            .is_err());
+        assert!(t
+            .set_stock_template(StockMessage::MsgIAddMember, Some("%1$x"))
+            .await
+            .is_err());
+        t.set_stock_template(StockMessage::MsgILeftGroup, Some("Bye, 100% sure."))
+            .await?;
+        assert_eq!(msg_group_left_remote(&t).await, "Bye, 100% sure.");
+
+        // Only strings of outgoing messages can be customized.
+        assert!(t
+            .set_stock_template(StockMessage::NoMessages, Some("Nothing"))
+            .await
+            .is_err());
+
+        t.set_stock_template(StockMessage::MsgIAddMember, None)
+            .await?;
+        assert_eq!(
+            msg_add_member_remote(&t, "bob@example.net").await,
+            "Ich habe bob@example.net hinzugefügt."
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_stock_str() {
         let t = TestContext::new().await;
@@ -1556,6 +1942,22 @@ async fn test_stock_system_msg_add_member_by_other_with_displayname() {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stock_system_msg_member_with_nickname() -> Result<()> {
+        let t = TestContext::new().await;
+        let contact_id = Contact::create(&t, "Alice", "alice@example.org").await?;
+        Contact::set_local_override(&t, contact_id, Some("Ally"), None).await?;
+        assert_eq!(
+            msg_add_member_local(&t, "alice@example.org", ContactId::SELF).await,
+            "You added member Alice (alice@example.org)."
+        );
+        assert_eq!(
+            msg_del_member_local(&t, "alice@example.org", ContactId::SELF).await,
+            "You removed member Alice (alice@example.org)."
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_quota_exceeding_stock_str() -> Result<()> {
         let t = TestContext::new().await;
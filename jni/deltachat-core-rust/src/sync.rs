@@ -11,7 +11,8 @@
 use crate::context::Context;
 use crate::log::LogExt;
 use crate::message::{Message, MsgId, Viewtype};
-use crate::mimeparser::SystemMessage;
+use crate::mimeparser::{ParsedAttachment, SystemMessage};
+use crate::notes::Todo;
 use crate::param::Param;
 use crate::sync::SyncData::{AddQrToken, AlterChat, DeleteQrToken};
 use crate::token::Namespace;
@@ -62,6 +63,19 @@ pub(crate) enum SyncData {
         key: Config,
         val: String,
     },
+    Todo {
+        /// Message-ID of the todo item.
+        msg: String,
+        todo: Option<Todo>,
+    },
+    ContactNote {
+        addr: String,
+        note: String,
+    },
+    ContactTags {
+        addr: String,
+        tags: Vec<String>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -228,7 +242,7 @@ pub(crate) fn build_sync_part(&self, json: String) -> PartBuilder {
             .content_type(&"application/json".parse::<mime::Mime>().unwrap())
             .header((
                 "Content-Disposition",
-                "attachment; filename=\"multi-device-sync.json\"",
+                ParsedAttachment::SyncItems.content_disposition(),
             ))
             .body(json)
     }
@@ -259,6 +273,13 @@ pub(crate) async fn execute_sync_items(&self, items: &SyncItems) {
                     DeleteQrToken(token) => self.delete_qr_token(token).await,
                     AlterChat { id, action } => self.sync_alter_chat(id, action).await,
                     SyncData::Config { key, val } => self.sync_config(key, val).await,
+                    SyncData::Todo { msg, todo } => self.sync_todo(msg, *todo).await,
+                    SyncData::ContactNote { addr, note } => {
+                        self.sync_contact_note(addr, note).await
+                    }
+                    SyncData::ContactTags { addr, tags } => {
+                        self.sync_contact_tags(addr, tags).await
+                    }
                 },
                 SyncDataOrUnknown::Unknown(data) => {
                     warn!(self, "Ignored unknown sync item: {data}.");
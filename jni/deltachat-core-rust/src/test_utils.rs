@@ -387,6 +387,9 @@ async fn new_internal(name: Option<String>, log_sink: Option<LogSink>) -> Self {
             .await
             .unwrap();
         ctx.set_config(Config::SyncMsgs, Some("0")).await.unwrap();
+        ctx.set_config(Config::DeletionUndoWindow, Some("0"))
+            .await
+            .unwrap();
 
         Self {
             ctx,
@@ -0,0 +1,205 @@
+//! # Timezone-aware day boundaries.
+//!
+//! Day markers in chats and other date computations use the timezone set in [`Config::Timezone`],
+//! falling back to the timezone of the device if it is unset.
+//! UIs should update the setting when the device changes its timezone, e.g. while travelling.
+//! Changing it emits a generic `MsgsChanged` event and a `ChatlistChanged` event
+//! so that message lists, including their day markers, are reloaded.
+//!
+//! Unlike a fixed offset to UTC, the timezone also accounts for
+//! daylight saving time being different at the time of older messages.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+use crate::config::Config;
+use crate::context::Context;
+
+/// Timezone used to compute day boundaries.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum LocalTimezone {
+    /// Timezone of the device.
+    Device,
+
+    /// Timezone set in [`Config::Timezone`].
+    Named(Tz),
+}
+
+/// Parses an IANA timezone name such as `Europe/Berlin`.
+pub(crate) fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .map_err(|err| anyhow!("Invalid timezone {name:?}: {err}"))
+}
+
+impl LocalTimezone {
+    /// Loads the timezone configured for the context.
+    ///
+    /// Falls back to the timezone of the device if the stored timezone is invalid,
+    /// e.g. because it was removed from the timezone database.
+    pub(crate) async fn load(context: &Context) -> Result<Self> {
+        let Some(name) = context
+            .get_config(Config::Timezone)
+            .await?
+            .filter(|name| !name.is_empty())
+        else {
+            return Ok(Self::Device);
+        };
+        match parse_timezone(&name) {
+            Ok(tz) => Ok(Self::Named(tz)),
+            Err(err) => {
+                warn!(context, "{err:#}, using the timezone of the device.");
+                Ok(Self::Device)
+            }
+        }
+    }
+
+    /// Returns the local date of the timestamp.
+    pub(crate) fn date(&self, timestamp: i64) -> NaiveDate {
+        let utc = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+        match self {
+            Self::Device => utc.with_timezone(&Local).date_naive(),
+            Self::Named(tz) => utc.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// Returns true if the timestamps are on the same local day.
+    pub(crate) fn is_same_day(&self, timestamp1: i64, timestamp2: i64) -> bool {
+        self.date(timestamp1) == self.date(timestamp2)
+    }
+
+    /// Returns the timestamp of the start of the local day containing `timestamp`.
+    pub(crate) fn day_start(&self, timestamp: i64) -> i64 {
+        let date = self.date(timestamp);
+        match self {
+            Self::Device => start_of_date(&Local, date),
+            Self::Named(tz) => start_of_date(tz, date),
+        }
+        .unwrap_or(timestamp)
+    }
+}
+
+/// Returns the first timestamp of `date` in the timezone.
+///
+/// This is midnight unless midnight is skipped
+/// because daylight saving time starts at that time.
+fn start_of_date<T: TimeZone>(tz: &T, date: NaiveDate) -> Option<i64> {
+    (0..24).find_map(|hour| {
+        tz.from_local_datetime(&date.and_time(NaiveTime::from_hms_opt(hour, 0, 0)?))
+            .earliest()
+            .map(|datetime| datetime.timestamp())
+    })
+}
+
+/// Returns the timestamp of the start of the local day containing `timestamp`,
+/// as used for day markers.
+pub async fn get_day_start(context: &Context, timestamp: i64) -> Result<i64> {
+    Ok(LocalTimezone::load(context).await?.day_start(timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{get_chat_msgs_ex, ChatItem, MessageListOptions};
+    use crate::events::EventType;
+    use crate::receive_imf::receive_imf;
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_day_start() -> Result<()> {
+        let berlin = LocalTimezone::Named(parse_timezone("Europe/Berlin")?);
+        // Daylight saving time starts on 2024-03-31 at 02:00.
+        assert_eq!(berlin.day_start(1711886400), 1711839600);
+        assert_eq!(berlin.day_start(1711839600), 1711839600);
+        // Daylight saving time ends on 2024-10-27 at 03:00.
+        assert_eq!(berlin.day_start(1730070000 - 1), 1729980000);
+        assert_eq!(berlin.day_start(1730070000), 1730070000);
+
+        assert!(berlin.is_same_day(1711839600, 1711886400));
+        assert!(!berlin.is_same_day(1711839600 - 1, 1711839600));
+
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_load_invalid_timezone() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.sql
+            .set_raw_config(Config::Timezone.as_ref(), Some("Mars/Olympus_Mons"))
+            .await?;
+        assert!(matches!(
+            LocalTimezone::load(&t).await?,
+            LocalTimezone::Device
+        ));
+
+        t.set_config(Config::Timezone, Some("Asia/Tokyo")).await?;
+        assert!(matches!(
+            LocalTimezone::load(&t).await?,
+            LocalTimezone::Named(tz) if tz == chrono_tz::Asia::Tokyo
+        ));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_timezone_day_markers() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        assert!(t
+            .set_config(Config::Timezone, Some("Mars/Olympus_Mons"))
+            .await
+            .is_err());
+        t.set_config(Config::Timezone, Some("UTC")).await?;
+
+        // 2024-03-31 14:00 and 16:00 UTC.
+        for (n, date) in [
+            "Sun, 31 Mar 2024 14:00:00 +0000",
+            "Sun, 31 Mar 2024 16:00:00 +0000",
+        ]
+        .iter()
+        .enumerate()
+        {
+            let imf = format!(
+                "From: bob@example.net\n\
+                 To: alice@example.org\n\
+                 Message-ID: <day{n}@example.net>\n\
+                 Chat-Version: 1.0\n\
+                 Date: {date}\n\
+                 \n\
+                 Message {n}\n"
+            );
+            receive_imf(&t, imf.as_bytes(), false).await?;
+        }
+        let chat_id = t.get_last_msg().await.chat_id;
+        let options = || MessageListOptions {
+            info_only: false,
+            add_daymarker: true,
+        };
+        let day_markers = |items: Vec<ChatItem>| -> Vec<i64> {
+            items
+                .into_iter()
+                .filter_map(|item| match item {
+                    ChatItem::DayMarker { timestamp } => Some(timestamp),
+                    ChatItem::Message { .. } => None,
+                })
+                .collect()
+        };
+        let items = get_chat_msgs_ex(&t, chat_id, options()).await?;
+        assert_eq!(day_markers(items), [1711843200]);
+
+        // In Tokyo, the second message is sent after midnight.
+        t.evtracker.clear_events();
+        t.set_config(Config::Timezone, Some("Asia/Tokyo")).await?;
+        t.evtracker
+            .get_matching(|evt| {
+                matches!(
+                    evt,
+                    EventType::MsgsChanged { chat_id, .. } if chat_id.is_unset()
+                )
+            })
+            .await;
+        let items = get_chat_msgs_ex(&t, chat_id, options()).await?;
+        assert_eq!(day_markers(items), [1711810800, 1711897200]);
+        assert_eq!(get_day_start(&t, 1711900800).await?, 1711897200);
+        Ok(())
+    }
+}
@@ -35,6 +35,7 @@
 use tokio::{fs, io};
 use url::Url;
 
+use crate::blob::BlobObject;
 use crate::chat::{add_device_msg, add_device_msg_with_importance};
 use crate::config::Config;
 use crate::constants::{self, DC_ELLIPSIS, DC_OUTDATED_WARNING_DAYS};
@@ -169,13 +170,6 @@ pub fn duration_to_str(duration: Duration) -> String {
     format!("{h}h {m}m {s}s")
 }
 
-pub(crate) fn gm2local_offset() -> i64 {
-    /* returns the offset that must be _added_ to an UTC/GMT-time to create the localtime.
-    the function may return negative values. */
-    let lt = Local::now();
-    i64::from(lt.offset().local_minus_utc())
-}
-
 /// Returns the current smeared timestamp,
 ///
 /// The returned timestamp MUST NOT be sent out.
@@ -322,6 +316,16 @@ pub fn get_filesuffix_lc(path_filename: &str) -> Option<String> {
         .map(|p| p.to_string_lossy().to_lowercase())
 }
 
+/// Sanitizes a file name the same way as names of blob files are sanitized.
+///
+/// Directories and characters not allowed in file names on common platforms are removed,
+/// the extension is lowercased and the name is truncated.
+/// Returns an empty string if nothing is left.
+pub fn sanitize_filename(name: &str) -> String {
+    let (stem, ext) = BlobObject::sanitise_name(name);
+    format!("{stem}{ext}")
+}
+
 /// Returns the `(width, height)` of the given image buffer.
 pub fn get_filemeta(buf: &[u8]) -> Result<(u32, u32)> {
     let image = image::ImageReader::new(Cursor::new(buf)).with_guessed_format()?;
@@ -329,6 +333,13 @@ pub fn get_filemeta(buf: &[u8]) -> Result<(u32, u32)> {
     Ok(dimensions)
 }
 
+/// Same as [`get_filemeta`], but reads only the image header from the file at `path`.
+pub(crate) fn get_filemeta_from_path(path: &Path) -> Result<(u32, u32)> {
+    let image = image::ImageReader::open(path)?.with_guessed_format()?;
+    let dimensions = image.into_dimensions()?;
+    Ok(dimensions)
+}
+
 /// Expand paths relative to $BLOBDIR into absolute paths.
 ///
 /// If `path` starts with "$BLOBDIR", replaces it with the blobdir path.
@@ -581,6 +592,12 @@ fn is_none_or_empty(&self) -> bool {
     }
 }
 
+/// Returns whether the subject is the placeholder replacing the subject of encrypted messages,
+/// possibly with a reply prefix added by an email client not supporting protected headers.
+pub(crate) fn is_subject_placeholder(subject: &str) -> bool {
+    remove_subject_prefix(subject) == DC_ELLIPSIS
+}
+
 pub fn remove_subject_prefix(last_subject: &str) -> String {
     let subject_start = if last_subject.starts_with("Chat:") {
         0
@@ -1065,6 +1082,14 @@ fn test_duration_to_str() {
         );
     }
 
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("../dir/Report.PDF"), "Report.pdf");
+        assert_eq!(sanitize_filename("C:\\foo?.bar"), "foo.bar");
+        assert_eq!(sanitize_filename("wot.tar.gz"), "wot.tar.gz");
+        assert_eq!(sanitize_filename("/"), "");
+    }
+
     #[test]
     fn test_get_filemeta() {
         let (w, h) = get_filemeta(test_utils::AVATAR_900x900_BYTES).unwrap();
@@ -1226,6 +1251,15 @@ fn test_remove_subject_prefix() {
         assert_eq!(remove_subject_prefix("Fw: Subject"), "Subject");
     }
 
+    #[test]
+    fn test_is_subject_placeholder() {
+        assert!(is_subject_placeholder("[...]"));
+        assert!(is_subject_placeholder("Re: [...]"));
+        assert!(is_subject_placeholder("AW: [...]"));
+        assert!(!is_subject_placeholder("Re: Subject [...]"));
+        assert!(!is_subject_placeholder("Subject"));
+    }
+
     #[test]
     fn test_parse_mailto() {
         let mailto_url = "mailto:someone@example.com";
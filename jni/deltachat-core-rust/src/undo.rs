@@ -0,0 +1,534 @@
+//! # Undo windows for deletions and sending.
+//!
+//! Deleting messages or chats does not destroy data immediately
+//! if `Config::DeletionUndoWindow` is set.
+//! Instead, deleted messages are moved to a hidden special chat
+//! and deleted chats are hidden from the chatlist by marking them as pending deletion.
+//! Using a chat pending deletion again, e.g. because a new message arrives in it
+//! or the user creates the chat again, restores it.
+//! The original state is recorded in the `pending_deletions` table.
+//!
+//! During the undo window [`undo_last_deletion`] restores the last deletion.
+//! Afterwards the ephemeral loop purges the pending deletion
+//! the same way as if the messages or the chat were deleted immediately.
+//!
+//! Similarly, if `Config::SendDelay` is set,
+//! outgoing messages stay in the SMTP queue for the configured time before they are submitted.
+//! Meanwhile [`cancel_send`] retracts a message before anything left the device.
+
+use std::time::Duration;
+
+use anyhow::{ensure, Result};
+
+use crate::chat::ChatId;
+use crate::chatlist_events;
+use crate::config::Config;
+use crate::constants::DC_CHAT_ID_PENDING_DELETION;
+use crate::context::Context;
+use crate::events::EventType;
+use crate::message::{self, Message, MsgId, Viewtype};
+use crate::tools::time;
+
+/// Maximum value of `Config::SendDelay` in seconds.
+const MAX_SEND_DELAY: i64 = 60;
+
+/// Returns the configured undo window in seconds, 0 if deletions are not undoable.
+pub(crate) async fn get_undo_window(context: &Context) -> Result<i64> {
+    Ok(context
+        .get_config_i64(Config::DeletionUndoWindow)
+        .await?
+        .max(0))
+}
+
+/// Hides the messages and records them as pending deletion.
+pub(crate) async fn delete_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<()> {
+    let mut msgs = Vec::with_capacity(msg_ids.len());
+    for &msg_id in msg_ids {
+        msgs.push(Message::load_from_db(context, msg_id).await?);
+    }
+    let now = time();
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "INSERT INTO pending_deletions (timestamp) VALUES (?)",
+                (now,),
+            )?;
+            let deletion_id = transaction.last_insert_rowid();
+            for msg in &msgs {
+                transaction.execute(
+                    "INSERT INTO pending_deletion_msgs (deletion_id, msg_id, chat_id)
+                     VALUES (?, ?, ?)",
+                    (deletion_id, msg.id, msg.chat_id),
+                )?;
+                transaction.execute(
+                    "UPDATE msgs SET chat_id=? WHERE id=?",
+                    (DC_CHAT_ID_PENDING_DELETION, msg.id),
+                )?;
+            }
+            Ok(())
+        })
+        .await?;
+
+    let mut modified_chat_ids = Vec::new();
+    for msg in &msgs {
+        context.emit_event(EventType::MsgDeleted {
+            chat_id: msg.chat_id,
+            msg_id: msg.id,
+        });
+        if msg.viewtype == Viewtype::Webxdc {
+            context.emit_event(EventType::WebxdcInstanceDeleted { msg_id: msg.id });
+        }
+        if !modified_chat_ids.contains(&msg.chat_id) {
+            modified_chat_ids.push(msg.chat_id);
+        }
+    }
+    for chat_id in modified_chat_ids {
+        context.emit_msgs_changed(chat_id, MsgId::new(0));
+        chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    }
+    if !msgs.is_empty() {
+        context.emit_msgs_changed_without_ids();
+        chatlist_events::emit_chatlist_changed(context);
+    }
+    context.scheduler.interrupt_ephemeral_task().await;
+    Ok(())
+}
+
+/// Hides the chat and records it as pending deletion.
+pub(crate) async fn delete_chat(context: &Context, chat_id: ChatId) -> Result<()> {
+    let now = time();
+    context
+        .sql
+        .transaction(|transaction| {
+            transaction.execute(
+                "INSERT INTO pending_deletions (timestamp, chat_id) VALUES (?, ?)",
+                (now, chat_id),
+            )?;
+            transaction.execute("UPDATE chats SET pending_deletion=1 WHERE id=?", (chat_id,))?;
+            Ok(())
+        })
+        .await?;
+
+    context.emit_msgs_changed_without_ids();
+    chatlist_events::emit_chatlist_changed(context);
+    context.scheduler.interrupt_ephemeral_task().await;
+    Ok(())
+}
+
+/// Restores the chat if it is pending deletion
+/// because it is used again.
+///
+/// Otherwise messages added to the chat would be purged with it.
+pub(crate) async fn restore_chat(context: &Context, chat_id: ChatId) -> Result<()> {
+    let restored = context
+        .sql
+        .execute(
+            "UPDATE chats SET pending_deletion=0 WHERE id=? AND pending_deletion=1",
+            (chat_id,),
+        )
+        .await?;
+    if restored > 0 {
+        context
+            .sql
+            .execute("DELETE FROM pending_deletions WHERE chat_id=?", (chat_id,))
+            .await?;
+        info!(
+            context,
+            "Restored {chat_id} pending deletion as it is used again."
+        );
+        chatlist_events::emit_chatlist_changed(context);
+    }
+    Ok(())
+}
+
+/// Restores the messages or the chat deleted last
+/// if the deletion happened within the undo window.
+///
+/// Returns `false` if there is nothing to restore.
+pub async fn undo_last_deletion(context: &Context) -> Result<bool> {
+    let threshold = time().saturating_sub(get_undo_window(context).await?);
+    let Some((deletion_id, chat_id)) = context
+        .sql
+        .query_row_optional(
+            "SELECT id, chat_id FROM pending_deletions
+             WHERE timestamp>?
+             ORDER BY id DESC LIMIT 1",
+            (threshold,),
+            |row| {
+                let deletion_id: i64 = row.get(0)?;
+                let chat_id: ChatId = row.get(1)?;
+                Ok((deletion_id, chat_id))
+            },
+        )
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    let restored_msgs = context
+        .sql
+        .transaction(move |transaction| {
+            let mut restored_msgs = Vec::new();
+            if chat_id.is_unset() {
+                let mut stmt = transaction.prepare(
+                    "SELECT msg_id, chat_id FROM pending_deletion_msgs WHERE deletion_id=?",
+                )?;
+                let msgs = stmt
+                    .query_map((deletion_id,), |row| {
+                        let msg_id: MsgId = row.get(0)?;
+                        let chat_id: ChatId = row.get(1)?;
+                        Ok((msg_id, chat_id))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                for (msg_id, chat_id) in msgs {
+                    // The message may have been deleted for good in the meantime,
+                    // e.g. because it was ephemeral.
+                    let updated = transaction.execute(
+                        "UPDATE msgs SET chat_id=? WHERE id=? AND chat_id=?",
+                        (chat_id, msg_id, DC_CHAT_ID_PENDING_DELETION),
+                    )?;
+                    if updated > 0 {
+                        restored_msgs.push((chat_id, msg_id));
+                    }
+                }
+            } else {
+                transaction
+                    .execute("UPDATE chats SET pending_deletion=0 WHERE id=?", (chat_id,))?;
+            }
+            transaction.execute("DELETE FROM pending_deletions WHERE id=?", (deletion_id,))?;
+            Ok(restored_msgs)
+        })
+        .await?;
+
+    for (chat_id, msg_id) in restored_msgs {
+        context.emit_msgs_changed(chat_id, msg_id);
+        chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    }
+    context.emit_msgs_changed_without_ids();
+    chatlist_events::emit_chatlist_changed(context);
+    info!(context, "Restored deletion {deletion_id}.");
+    Ok(true)
+}
+
+/// Returns the timestamp when the next pending deletion should be purged.
+pub(crate) async fn next_purge_timestamp(context: &Context) -> Result<Option<i64>> {
+    let timestamp: Option<i64> = context
+        .sql
+        .query_get_value(
+            "SELECT min(timestamp) FROM pending_deletions HAVING count(*) > 0",
+            (),
+        )
+        .await?;
+    let undo_window = get_undo_window(context).await?;
+    Ok(timestamp.map(|timestamp| timestamp.saturating_add(undo_window)))
+}
+
+/// Purges pending deletions which are older than the undo window.
+///
+/// Pending deletions are kept while `Config::LegalHold` is set.
+pub(crate) async fn purge_pending_deletions(context: &Context, now: i64) -> Result<()> {
+    if context.get_config_bool(Config::LegalHold).await? {
+        return Ok(());
+    }
+    let threshold = now.saturating_sub(get_undo_window(context).await?);
+    let deletions = context
+        .sql
+        .query_map(
+            "SELECT id, chat_id FROM pending_deletions WHERE timestamp<=?",
+            (threshold,),
+            |row| {
+                let deletion_id: i64 = row.get(0)?;
+                let chat_id: ChatId = row.get(1)?;
+                Ok((deletion_id, chat_id))
+            },
+            |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await?;
+
+    for (deletion_id, chat_id) in deletions {
+        if chat_id.is_unset() {
+            let msg_ids = context
+                .sql
+                .query_map(
+                    "SELECT p.msg_id FROM pending_deletion_msgs p
+                     INNER JOIN msgs m ON m.id=p.msg_id
+                     WHERE p.deletion_id=? AND m.chat_id=?",
+                    (deletion_id, DC_CHAT_ID_PENDING_DELETION),
+                    |row| row.get::<_, MsgId>(0),
+                    |rows| rows.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+                )
+                .await?;
+            message::delete_msgs_ex(context, &msg_ids, false).await?;
+        } else {
+            let still_pending = context
+                .sql
+                .exists(
+                    "SELECT COUNT(*) FROM chats WHERE id=? AND pending_deletion=1",
+                    (chat_id,),
+                )
+                .await?;
+            if still_pending {
+                chat_id.delete_now(context).await?;
+            } else {
+                info!(
+                    context,
+                    "Not deleting {chat_id}, it was restored in the meantime."
+                );
+            }
+        }
+        context
+            .sql
+            .execute("DELETE FROM pending_deletions WHERE id=?", (deletion_id,))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Returns the time before which the message must not be submitted,
+/// 0 if it can be submitted at once.
+///
+/// Only messages written by the user are delayed,
+/// hidden messages such as reactions and system messages are submitted at once.
+pub(crate) async fn send_after(context: &Context, msg: &Message) -> Result<i64> {
+    if msg.hidden || msg.is_system_message() {
+        return Ok(0);
+    }
+    let delay = context
+        .get_config_i64(Config::SendDelay)
+        .await?
+        .clamp(0, MAX_SEND_DELAY);
+    Ok(match delay {
+        0 => 0,
+        delay => time().saturating_add(delay),
+    })
+}
+
+/// Returns the time until the next delayed message in the SMTP queue is due,
+/// `None` if there are no delayed messages.
+pub(crate) async fn time_until_next_send(context: &Context) -> Result<Option<Duration>> {
+    let now = time();
+    let next: Option<i64> = context
+        .sql
+        .query_get_value(
+            "SELECT MIN(send_after) FROM smtp WHERE send_after>?",
+            (now,),
+        )
+        .await?;
+    Ok(next.map(|next| Duration::from_secs(next.saturating_sub(now) as u64)))
+}
+
+impl MsgId {
+    /// Returns the timestamp at which the message is submitted
+    /// if it is still kept back because of `Config::SendDelay`
+    /// and sending can be cancelled with [`cancel_send`].
+    ///
+    /// Returns `None` if the message is not delayed (anymore).
+    pub async fn get_send_delay_end(self, context: &Context) -> Result<Option<i64>> {
+        context
+            .sql
+            .query_get_value(
+                "SELECT MAX(send_after) FROM smtp WHERE msg_id=? AND send_after>?",
+                (self, time()),
+            )
+            .await
+    }
+}
+
+/// Cancels sending the message `msg_id` kept back because of `Config::SendDelay`
+/// and deletes it locally.
+///
+/// Fails if the delay is over as the message may have been submitted already.
+pub async fn cancel_send(context: &Context, msg_id: MsgId) -> Result<()> {
+    let now = time();
+    let cancelled = context
+        .sql
+        .transaction(move |transaction| {
+            let due: bool = transaction.query_row(
+                "SELECT COUNT(*)>0 FROM smtp WHERE msg_id=? AND send_after<=?",
+                (msg_id, now),
+                |row| row.get(0),
+            )?;
+            if due {
+                return Ok(0);
+            }
+            let cancelled = transaction.execute(
+                "DELETE FROM smtp WHERE msg_id=? AND send_after>?",
+                (msg_id, now),
+            )?;
+            Ok(cancelled)
+        })
+        .await?;
+    ensure!(
+        cancelled > 0,
+        "Message {msg_id} is not delayed, cannot cancel sending"
+    );
+    message::delete_msgs_ex(context, &[msg_id], true).await?;
+    info!(context, "Cancelled sending {msg_id}.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{self, Chat};
+    use crate::chatlist::Chatlist;
+    use crate::constants::Blocked;
+    use crate::message::MessageState;
+    use crate::reaction::send_reaction;
+    use crate::test_utils::{TestContext, TestContextManager};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_undo_msg_deletion() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(Config::DeletionUndoWindow, Some("30")).await?;
+        let chat = t.create_chat_with_contact("", "bob@example.net").await;
+        let msg_id = t.send_text(chat.id, "Hi").await.sender_msg_id;
+
+        message::delete_msgs(&t, &[msg_id]).await?;
+        assert!(chat::get_chat_msgs(&t, chat.id).await?.is_empty());
+        assert!(undo_last_deletion(&t).await?);
+        assert_eq!(chat::get_chat_msgs(&t, chat.id).await?.len(), 1);
+        assert_eq!(Message::load_from_db(&t, msg_id).await?.text, "Hi");
+        assert!(!undo_last_deletion(&t).await?);
+
+        message::delete_msgs(&t, &[msg_id]).await?;
+        purge_pending_deletions(&t, time()).await?;
+        assert_eq!(
+            Message::load_from_db(&t, msg_id).await?.chat_id,
+            DC_CHAT_ID_PENDING_DELETION
+        );
+        purge_pending_deletions(&t, time() + 31).await?;
+        assert!(Message::load_from_db_optional(&t, msg_id).await?.is_none());
+        assert!(!undo_last_deletion(&t).await?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_undo_chat_deletion() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        t.set_config(Config::DeletionUndoWindow, Some("30")).await?;
+        let chat = t.create_chat_with_contact("", "bob@example.net").await;
+        t.send_text(chat.id, "Hi").await;
+
+        chat.id.delete(&t).await?;
+        assert_eq!(Chatlist::try_load(&t, 0, None, None).await?.len(), 0);
+        // The chat is not blocked, so messages from the contact are still received.
+        assert_eq!(Chat::load_from_db(&t, chat.id).await?.blocked, Blocked::Not);
+        assert!(undo_last_deletion(&t).await?);
+        assert_eq!(Chatlist::try_load(&t, 0, None, None).await?.len(), 1);
+        assert_eq!(Chat::load_from_db(&t, chat.id).await?.blocked, Blocked::Not);
+
+        chat.id.delete(&t).await?;
+        assert!(next_purge_timestamp(&t).await?.is_some());
+        purge_pending_deletions(&t, time() + 31).await?;
+        assert!(Chat::load_from_db(&t, chat.id).await.is_err());
+        assert_eq!(next_purge_timestamp(&t).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_new_msg_restores_deleted_chat() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice
+            .set_config(Config::DeletionUndoWindow, Some("30"))
+            .await?;
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        alice_chat_id.delete(alice).await?;
+        assert_eq!(Chatlist::try_load(alice, 0, None, None).await?.len(), 0);
+
+        let sent = bob.send_text(bob.create_chat(alice).await.id, "Hi").await;
+        let msg = alice.recv_msg(&sent).await;
+        assert_eq!(msg.chat_id, alice_chat_id);
+        assert_eq!(Chatlist::try_load(alice, 0, None, None).await?.len(), 1);
+
+        // The chat is not deleted anymore after the undo window.
+        assert_eq!(next_purge_timestamp(alice).await?, None);
+        purge_pending_deletions(alice, time() + 31).await?;
+        assert!(Chat::load_from_db(alice, alice_chat_id).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_create_chat_restores_deleted_chat() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        alice
+            .set_config(Config::DeletionUndoWindow, Some("30"))
+            .await?;
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        alice_chat_id.delete(alice).await?;
+        assert_eq!(Chatlist::try_load(alice, 0, None, None).await?.len(), 0);
+
+        let bob_id = alice.add_or_lookup_contact_id(bob).await;
+        assert_eq!(
+            ChatId::create_for_contact(alice, bob_id).await?,
+            alice_chat_id
+        );
+        assert_eq!(Chatlist::try_load(alice, 0, None, None).await?.len(), 1);
+        let msg_id = chat::send_text_msg(alice, alice_chat_id, "Hi again".to_string()).await?;
+
+        // The message sent to the reopened chat is not purged.
+        purge_pending_deletions(alice, time() + 31).await?;
+        assert_eq!(
+            Message::load_from_db(alice, msg_id).await?.chat_id,
+            alice_chat_id
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cancel_send() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat = t.create_chat_with_contact("", "bob@example.net").await;
+        t.set_config(Config::SendDelay, Some("10")).await?;
+
+        let msg_id = chat::send_text_msg(&t, chat.id, "Oops".to_string()).await?;
+        let send_delay_end = msg_id.get_send_delay_end(&t).await?.unwrap();
+        assert!(send_delay_end > time());
+        assert!(send_delay_end <= time() + 10);
+        assert_eq!(
+            Message::load_from_db(&t, msg_id).await?.state,
+            MessageState::OutPending
+        );
+        let next = time_until_next_send(&t).await?.unwrap();
+        assert!(next <= Duration::from_secs(10));
+
+        // Reactions are not delayed.
+        send_reaction(&t, msg_id, "👍").await?;
+        let delayed: usize = t
+            .sql
+            .count("SELECT COUNT(*) FROM smtp WHERE send_after>0", ())
+            .await?;
+        assert_eq!(delayed, 1);
+        t.sql
+            .execute("DELETE FROM smtp WHERE send_after=0", ())
+            .await?;
+
+        cancel_send(&t, msg_id).await?;
+        assert!(Message::load_from_db_optional(&t, msg_id).await?.is_none());
+        assert!(chat::get_chat_msgs(&t, chat.id).await?.is_empty());
+        assert_eq!(t.sql.count("SELECT COUNT(*) FROM smtp", ()).await?, 0);
+        assert!(cancel_send(&t, msg_id).await.is_err());
+
+        // Once the delay is over, sending cannot be cancelled anymore.
+        let msg_id = chat::send_text_msg(&t, chat.id, "Hi".to_string()).await?;
+        t.sql
+            .execute(
+                "UPDATE smtp SET send_after=? WHERE msg_id=?",
+                (time(), msg_id),
+            )
+            .await?;
+        assert_eq!(msg_id.get_send_delay_end(&t).await?, None);
+        assert!(cancel_send(&t, msg_id).await.is_err());
+        assert_eq!(time_until_next_send(&t).await?, None);
+
+        t.set_config(Config::SendDelay, None).await?;
+        let sent = t.send_text(chat.id, "Now").await;
+        assert_eq!(sent.sender_msg_id.get_send_delay_end(&t).await?, None);
+        Ok(())
+    }
+}
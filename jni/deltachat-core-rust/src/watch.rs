@@ -0,0 +1,207 @@
+//! # Watch keywords.
+//!
+//! Users can define keywords with [`Config::WatchKeywords`],
+//! e.g. the name of a product a support team is responsible for.
+//! Incoming messages containing any of the keywords are flagged as watched,
+//! [`EventType::IncomingMsg`](crate::events::EventType::IncomingMsg) has `watched` set for them,
+//! so the UI can show a notification even if the chat is muted,
+//! and [`get_watched_msgs`] returns them.
+//!
+//! Keywords are matched case-insensitively against the text and the subject of the message.
+
+use anyhow::Result;
+
+use crate::chat::ChatId;
+use crate::config::Config;
+use crate::constants::DC_CHAT_ID_TRASH;
+use crate::context::Context;
+use crate::message::MsgId;
+
+/// Returns the watch keywords.
+pub async fn get_watch_keywords(context: &Context) -> Result<Vec<String>> {
+    Ok(context
+        .get_config(Config::WatchKeywords)
+        .await?
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|keyword| !keyword.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Sets the watch keywords. Empty keywords are ignored.
+///
+/// Only messages received afterwards are checked.
+pub async fn set_watch_keywords(context: &Context, keywords: &[String]) -> Result<()> {
+    let keywords: Vec<&str> = keywords
+        .iter()
+        .map(|keyword| keyword.trim())
+        .filter(|keyword| !keyword.is_empty() && !keyword.contains('\n'))
+        .collect();
+    let value = keywords.join("\n");
+    context
+        .set_config(
+            Config::WatchKeywords,
+            Some(value.as_str()).filter(|v| !v.is_empty()),
+        )
+        .await
+}
+
+/// Returns the first of `keywords` found in `text`, ignoring case.
+fn find_keyword<'a>(keywords: &'a [String], text: &str) -> Option<&'a str> {
+    let text = text.to_lowercase();
+    keywords
+        .iter()
+        .find(|keyword| text.contains(&keyword.to_lowercase()))
+        .map(|keyword| keyword.as_str())
+}
+
+/// Checks the received messages `msg_ids` for watch keywords and flags the matching ones.
+///
+/// Returns whether any of the messages matches.
+pub(crate) async fn check_msgs(context: &Context, msg_ids: &[MsgId]) -> Result<bool> {
+    let keywords = get_watch_keywords(context).await?;
+    if keywords.is_empty() {
+        return Ok(false);
+    }
+    let mut watched = false;
+    for msg_id in msg_ids {
+        let Some((text, subject)) = context
+            .sql
+            .query_row_optional(
+                "SELECT txt, subject FROM msgs WHERE id=?",
+                (msg_id,),
+                |row| {
+                    let text: String = row.get(0)?;
+                    let subject: String = row.get(1)?;
+                    Ok((text, subject))
+                },
+            )
+            .await?
+        else {
+            continue;
+        };
+        let Some(keyword) =
+            find_keyword(&keywords, &text).or_else(|| find_keyword(&keywords, &subject))
+        else {
+            continue;
+        };
+        info!(
+            context,
+            "Message {msg_id} contains watch keyword {keyword:?}."
+        );
+        context
+            .sql
+            .execute(
+                "INSERT OR REPLACE INTO watched_msgs (msg_id, keyword) VALUES (?, ?)",
+                (msg_id, keyword),
+            )
+            .await?;
+        watched = true;
+    }
+    Ok(watched)
+}
+
+impl MsgId {
+    /// Returns the watch keyword found in the message,
+    /// `None` if the message is not watched.
+    pub async fn get_watched_keyword(self, context: &Context) -> Result<Option<String>> {
+        context
+            .sql
+            .query_get_value("SELECT keyword FROM watched_msgs WHERE msg_id=?", (self,))
+            .await
+    }
+}
+
+/// Returns the watched messages, newest first.
+///
+/// If `chat_id` is set, only messages of this chat are returned.
+pub async fn get_watched_msgs(context: &Context, chat_id: Option<ChatId>) -> Result<Vec<MsgId>> {
+    context
+        .sql
+        .query_map(
+            "SELECT m.id FROM watched_msgs w
+             INNER JOIN msgs m ON m.id=w.msg_id
+             WHERE m.chat_id!=? AND m.hidden=0 AND (?=0 OR m.chat_id=?)
+             ORDER BY m.timestamp DESC, m.id DESC",
+            (
+                DC_CHAT_ID_TRASH,
+                chat_id.unwrap_or_default(),
+                chat_id.unwrap_or_default(),
+            ),
+            |row| row.get::<_, MsgId>(0),
+            |ids| ids.collect::<Result<Vec<_>, _>>().map_err(Into::into),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{self, ProtectionStatus};
+    use crate::events::EventType;
+    use crate::test_utils::TestContextManager;
+
+    #[test]
+    fn test_find_keyword() {
+        let keywords = vec!["Delta".to_string(), "chat mail".to_string()];
+        assert_eq!(find_keyword(&keywords, "I like delta chat"), Some("Delta"));
+        assert_eq!(
+            find_keyword(&keywords, "CHAT MAIL server"),
+            Some("chat mail")
+        );
+        assert_eq!(find_keyword(&keywords, "chatmail"), None);
+        assert_eq!(find_keyword(&[], "anything"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_watched_msgs() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+
+        set_watch_keywords(bob, &["Deltachat".to_string(), " ".to_string()]).await?;
+        assert_eq!(get_watch_keywords(bob).await?, ["Deltachat"]);
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let sent = alice.send_text(alice_chat_id, "Hi Bob").await;
+        let msg = bob.recv_msg(&sent).await;
+        assert_eq!(msg.id.get_watched_keyword(bob).await?, None);
+
+        let group_id =
+            chat::create_group_chat(alice, ProtectionStatus::Unprotected, "Support").await?;
+        chat::add_contact_to_chat(alice, group_id, alice.add_or_lookup_contact_id(bob).await)
+            .await?;
+        bob.evtracker.clear_events();
+        let sent = alice.send_text(group_id, "Is DeltaChat down?").await;
+        let watched_msg = bob.recv_msg(&sent).await;
+        assert_eq!(
+            watched_msg.id.get_watched_keyword(bob).await?.as_deref(),
+            Some("Deltachat")
+        );
+        let event = bob
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::IncomingMsg { .. }))
+            .await;
+        assert!(matches!(
+            event,
+            EventType::IncomingMsg { watched: true, .. }
+        ));
+
+        assert_eq!(get_watched_msgs(bob, None).await?, [watched_msg.id]);
+        assert_eq!(
+            get_watched_msgs(bob, Some(watched_msg.chat_id)).await?,
+            [watched_msg.id]
+        );
+        assert!(get_watched_msgs(bob, Some(msg.chat_id)).await?.is_empty());
+
+        // Outgoing messages are not watched.
+        set_watch_keywords(alice, &["Deltachat".to_string()]).await?;
+        let sent = alice.send_text(alice_chat_id, "Deltachat").await;
+        assert!(get_watched_msgs(alice, None).await?.is_empty());
+        bob.recv_msg(&sent).await;
+        assert_eq!(get_watched_msgs(bob, None).await?.len(), 2);
+        Ok(())
+    }
+}
@@ -15,12 +15,14 @@
 //! - `last_serial` - serial number of the last status update to send
 //! - `descr` - not used, set to empty string
 
+mod export;
 mod integration;
 mod maps_integration;
 
 use std::cmp::max;
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, ensure, format_err, Context as _, Result};
 
@@ -42,11 +44,11 @@
 use crate::message::{Message, MessageState, MsgId, Viewtype};
 use crate::mimefactory::wrapped_base64_encode;
 use crate::mimefactory::RECOMMENDED_FILE_SIZE;
-use crate::mimeparser::SystemMessage;
+use crate::mimeparser::{ParsedAttachment, SystemMessage};
 use crate::param::Param;
 use crate::param::Params;
 use crate::tools::create_id;
-use crate::tools::{create_smeared_timestamp, get_abs_path};
+use crate::tools::{create_smeared_timestamp, get_abs_path, time};
 
 /// The current API version.
 /// If `min_api` in manifest.toml is set to a larger value,
@@ -58,6 +60,10 @@
 pub const WEBXDC_SUFFIX: &str = "xdc";
 const WEBXDC_DEFAULT_ICON: &str = "__webxdc__/default-icon.png";
 
+/// Maximum time in seconds webxdc status updates of other chats than the interactive one
+/// are deferred while the app is in the foreground.
+const FOREGROUND_STATUS_UPDATE_DEFER_SECS: i64 = 5 * 60;
+
 /// Text shown to classic e-mail users in the visible e-mail body.
 const BODY_DESCR: &str = "Webxdc Status Update";
 
@@ -209,6 +215,36 @@ pub struct StatusUpdateItem {
     pub notify: Option<HashMap<String, String>>,
 }
 
+/// Filter for [`Context::get_webxdc_status_update_entries`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatusUpdateQuery {
+    /// Only updates with serials larger than this one are returned, 0 for all updates.
+    pub after_serial: StatusUpdateSerial,
+
+    /// Only updates with serials up to this one are returned, `None` for no limit.
+    pub until_serial: Option<StatusUpdateSerial>,
+
+    /// Maximum number of returned updates, `None` for no limit.
+    pub max_count: Option<u32>,
+
+    /// Only updates sent by this contact are returned, `None` for updates of all senders.
+    pub from_id: Option<ContactId>,
+}
+
+/// Status update as returned by [`Context::get_webxdc_status_update_entries`].
+#[derive(Debug)]
+pub struct StatusUpdateEntry {
+    /// Serial of the update.
+    pub serial: StatusUpdateSerial,
+
+    /// Sender of the update,
+    /// [`ContactId::UNDEFINED`] for updates stored before senders were recorded.
+    pub from_id: ContactId,
+
+    /// The update itself.
+    pub item: StatusUpdateItem,
+}
+
 /// Update items as passed to the UIs.
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct StatusUpdateItemAndSerial {
@@ -333,7 +369,7 @@ async fn create_status_update_record(
         from_id: ContactId,
     ) -> Result<Option<StatusUpdateSerial>> {
         let Some(status_update_serial) = self
-            .write_status_update_inner(&instance.id, &status_update_item, timestamp)
+            .write_status_update_inner(&instance.id, &status_update_item, timestamp, from_id)
             .await?
         else {
             return Ok(None);
@@ -441,6 +477,7 @@ pub(crate) async fn write_status_update_inner(
         instance_id: &MsgId,
         status_update_item: &StatusUpdateItem,
         timestamp: i64,
+        from_id: ContactId,
     ) -> Result<Option<StatusUpdateSerial>> {
         let uid = status_update_item.uid.as_deref();
         let status_update_item = serde_json::to_string(&status_update_item)?;
@@ -451,10 +488,11 @@ pub(crate) async fn write_status_update_inner(
             )?;
             let rowid = t
                 .query_row(
-                    "INSERT INTO msgs_status_updates (msg_id, update_item, uid) VALUES(?, ?, ?)
+                    "INSERT INTO msgs_status_updates (msg_id, update_item, uid, from_id)
+                     VALUES(?, ?, ?, ?)
                      ON CONFLICT (uid) DO NOTHING
                      RETURNING id",
-                    (instance_id, status_update_item, uid),
+                    (instance_id, status_update_item, uid, from_id),
                     |row| {
                         let id: u32 = row.get(0)?;
                         Ok(id)
@@ -557,24 +595,34 @@ pub async fn send_webxdc_status_update_struct(
 
         if send_now {
             self.sql.insert(
-                "INSERT INTO smtp_status_updates (msg_id, first_serial, last_serial, descr) VALUES(?, ?, ?, '')
+                "INSERT INTO smtp_status_updates (msg_id, first_serial, last_serial, descr, timestamp) VALUES(?, ?, ?, '', ?)
                  ON CONFLICT(msg_id)
                  DO UPDATE SET last_serial=excluded.last_serial",
-                (instance.id, status_update_serial, status_update_serial),
+                (instance.id, status_update_serial, status_update_serial, time()),
             ).await.context("Failed to insert webxdc update into SMTP queue")?;
-            self.scheduler.interrupt_smtp().await;
+            self.interrupt_smtp().await;
         }
         Ok(())
     }
 
     /// Returns one record of the queued webxdc status updates.
+    ///
+    /// While the app is in the foreground,
+    /// updates of other chats than the interactive one are deferred
+    /// for at most [`FOREGROUND_STATUS_UPDATE_DEFER_SECS`].
     async fn smtp_status_update_get(&self) -> Result<Option<(MsgId, i64, StatusUpdateSerial)>> {
+        let only_chat_id = match self.is_foreground() {
+            true => Some(self.get_interactive_chat().unwrap_or_default()),
+            false => None,
+        };
+        let deferred_until = time().saturating_sub(FOREGROUND_STATUS_UPDATE_DEFER_SECS);
         let res = self
             .sql
             .query_row_optional(
-                "SELECT msg_id, first_serial, last_serial \
-                 FROM smtp_status_updates LIMIT 1",
-                (),
+                "SELECT u.msg_id, u.first_serial, u.last_serial \
+                 FROM smtp_status_updates u LEFT JOIN msgs m ON m.id=u.msg_id \
+                 WHERE ?1 IS NULL OR m.chat_id=?1 OR u.timestamp<=?2 LIMIT 1",
+                (only_chat_id, deferred_until),
                 |row| {
                     let instance_id: MsgId = row.get(0)?;
                     let first_serial: i64 = row.get(1)?;
@@ -586,6 +634,33 @@ async fn smtp_status_update_get(&self) -> Result<Option<(MsgId, i64, StatusUpdat
         Ok(res)
     }
 
+    /// Returns the time until webxdc status updates deferred
+    /// because the app is in the foreground are due,
+    /// `None` if no updates are deferred.
+    pub(crate) async fn time_until_deferred_status_updates(&self) -> Result<Option<Duration>> {
+        if !self.is_foreground() {
+            return Ok(None);
+        }
+        let chat_id = self.get_interactive_chat().unwrap_or_default();
+        let now = time();
+        let timestamp: Option<i64> = self
+            .sql
+            .query_get_value(
+                "SELECT MIN(u.timestamp) \
+                 FROM smtp_status_updates u LEFT JOIN msgs m ON m.id=u.msg_id \
+                 WHERE m.chat_id IS NOT ? AND u.timestamp>?",
+                (
+                    chat_id,
+                    now.saturating_sub(FOREGROUND_STATUS_UPDATE_DEFER_SECS),
+                ),
+            )
+            .await?;
+        Ok(timestamp.map(|timestamp| {
+            let due = timestamp.saturating_add(FOREGROUND_STATUS_UPDATE_DEFER_SECS);
+            Duration::from_secs(due.saturating_sub(now) as u64)
+        }))
+    }
+
     async fn smtp_status_update_pop_serials(
         &self,
         msg_id: MsgId,
@@ -658,7 +733,7 @@ pub(crate) fn build_status_update_part(&self, json: &str) -> PartBuilder {
             .content_type(&"application/json".parse::<mime::Mime>().unwrap())
             .header((
                 "Content-Disposition",
-                "attachment; filename=\"status-update.json\"",
+                ParsedAttachment::StatusUpdate.content_disposition(),
             ))
             .header(("Content-Transfer-Encoding", "base64"))
             .body(encoded_body)
@@ -777,6 +852,59 @@ pub async fn get_webxdc_status_updates(
         Ok(format!("[{json}]"))
     }
 
+    /// Returns the status updates of a webxdc instance matching `query`, ordered by serial.
+    ///
+    /// Unlike [`Context::get_webxdc_status_updates`], this allows to load the updates
+    /// of long-running apps in pages of bounded size:
+    /// set [`StatusUpdateQuery::max_count`] and pass the serial of the last returned update
+    /// as [`StatusUpdateQuery::after_serial`] to get the next page.
+    pub async fn get_webxdc_status_update_entries(
+        &self,
+        instance_msg_id: MsgId,
+        query: StatusUpdateQuery,
+    ) -> Result<Vec<StatusUpdateEntry>> {
+        let param = instance_msg_id.get_param(self).await?;
+        ensure!(
+            param.get_int(Param::WebxdcIntegration).is_none(),
+            "Status updates of integrations cannot be queried as entries"
+        );
+        self.sql
+            .query_map(
+                "SELECT id, from_id, update_item FROM msgs_status_updates
+                 WHERE msg_id=?1 AND id>?2 AND id<=?3 AND (?4=0 OR from_id=?4)
+                 ORDER BY id LIMIT ?5",
+                (
+                    instance_msg_id,
+                    query.after_serial,
+                    query.until_serial.unwrap_or(StatusUpdateSerial::MAX),
+                    query.from_id.unwrap_or(ContactId::UNDEFINED),
+                    query.max_count.map_or(-1, i64::from),
+                ),
+                |row| {
+                    let serial: StatusUpdateSerial = row.get(0)?;
+                    let from_id: ContactId = row.get(1)?;
+                    let update_item: String = row.get(2)?;
+                    Ok((serial, from_id, update_item))
+                },
+                |rows| {
+                    let mut entries = Vec::new();
+                    for row in rows {
+                        let (serial, from_id, update_item) = row?;
+                        entries.push(StatusUpdateEntry {
+                            serial,
+                            from_id,
+                            item: StatusUpdateItem {
+                                uid: None, // Erase UIDs, apps, bots and tests don't need to know them.
+                                ..serde_json::from_str(&update_item)?
+                            },
+                        });
+                    }
+                    Ok(entries)
+                },
+            )
+            .await
+    }
+
     /// Renders JSON-object for status updates as used on the wire.
     ///
     /// Returns optional JSON and the first serial of updates not included due to a JSON size
@@ -1698,6 +1826,70 @@ async fn test_receive_status_update() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_webxdc_status_update_entries() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = create_group_chat(&t, ProtectionStatus::Unprotected, "foo").await?;
+        let bob_id = Contact::create(&t, "", "bob@example.net").await?;
+        add_contact_to_chat(&t, chat_id, bob_id).await?;
+        let instance = send_webxdc_instance(&t, chat_id).await?;
+        let now = tools::time();
+        for (from_id, payload) in [
+            (ContactId::SELF, 1),
+            (bob_id, 2),
+            (ContactId::SELF, 3),
+            (bob_id, 4),
+        ] {
+            t.receive_status_update(
+                from_id,
+                &instance,
+                now,
+                true,
+                &format!(r#"{{"updates":[{{"payload":{payload}}}]}}"#),
+            )
+            .await?;
+        }
+        let payloads = |entries: Vec<StatusUpdateEntry>| -> Vec<Value> {
+            entries
+                .into_iter()
+                .map(|entry| entry.item.payload)
+                .collect()
+        };
+
+        let entries = t
+            .get_webxdc_status_update_entries(instance.id, StatusUpdateQuery::default())
+            .await?;
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1].serial, StatusUpdateSerial(2));
+        assert_eq!(entries[1].from_id, bob_id);
+        assert_eq!(entries[2].from_id, ContactId::SELF);
+
+        let page = t
+            .get_webxdc_status_update_entries(
+                instance.id,
+                StatusUpdateQuery {
+                    after_serial: StatusUpdateSerial(1),
+                    max_count: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(payloads(page), [json!(2), json!(3)]);
+
+        let page = t
+            .get_webxdc_status_update_entries(
+                instance.id,
+                StatusUpdateQuery {
+                    until_serial: Some(StatusUpdateSerial(3)),
+                    from_id: Some(bob_id),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(payloads(page), [json!(2)]);
+        Ok(())
+    }
+
     async fn expect_status_update_event(t: &TestContext, instance_id: MsgId) -> Result<()> {
         let event = t
             .evtracker
@@ -1808,6 +2000,66 @@ async fn test_send_webxdc_status_update() -> Result<()> {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_defer_status_updates_in_foreground() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let alice_chat = alice.create_chat(bob).await;
+        let alice_instance = send_webxdc_instance(alice, alice_chat.id).await?;
+        alice.pop_sent_msg().await;
+
+        alice.set_foreground(true).await;
+        alice
+            .send_webxdc_status_update(alice_instance.id, r#"{"payload":1}"#)
+            .await?;
+        alice.flush_status_updates().await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+        assert!(alice.time_until_deferred_status_updates().await?.is_some());
+
+        let self_chat = alice.get_self_chat().await;
+        alice.set_interactive_chat(Some(self_chat.id)).await;
+        alice.flush_status_updates().await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+
+        alice.set_interactive_chat(Some(alice_chat.id)).await;
+        alice.flush_status_updates().await?;
+        let sent = alice.pop_sent_msg().await;
+        assert!(sent.payload().contains("report-type=status-update"));
+
+        // Updates of other chats are sent when the app goes to the background.
+        alice.set_interactive_chat(Some(self_chat.id)).await;
+        alice
+            .send_webxdc_status_update(alice_instance.id, r#"{"payload":2}"#)
+            .await?;
+        alice.flush_status_updates().await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+        alice.set_foreground(false).await;
+        alice.flush_status_updates().await?;
+        let sent = alice.pop_sent_msg().await;
+        assert!(sent.payload().contains("report-type=status-update"));
+
+        // Updates of other chats are not deferred forever.
+        alice.set_foreground(true).await;
+        alice
+            .send_webxdc_status_update(alice_instance.id, r#"{"payload":3}"#)
+            .await?;
+        alice.flush_status_updates().await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+        alice
+            .sql
+            .execute(
+                "UPDATE smtp_status_updates SET timestamp=?",
+                (time() - FOREGROUND_STATUS_UPDATE_DEFER_SECS,),
+            )
+            .await?;
+        assert_eq!(alice.time_until_deferred_status_updates().await?, None);
+        alice.flush_status_updates().await?;
+        let sent = alice.pop_sent_msg().await;
+        assert!(sent.payload().contains("report-type=status-update"));
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_send_big_webxdc_status_update() -> Result<()> {
         let alice = TestContext::new_alice().await;
@@ -0,0 +1,204 @@
+//! Export and import of single webxdc apps including their state.
+//!
+//! The exported file contains the `.xdc` file and all status updates of the instance.
+//! Importing it sends a new instance with these updates to a chat,
+//! so that e.g. a shared list can be moved to another group.
+
+use std::path::Path;
+
+use anyhow::{ensure, Context as _, Result};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::chat::{self, ChatId};
+use crate::context::Context;
+use crate::message::{Message, MsgId, Viewtype};
+use crate::param::Param;
+use crate::webxdc::StatusUpdateItem;
+
+/// Webxdc app as stored in an export file.
+#[derive(Debug, Serialize, Deserialize)]
+struct WebxdcExport {
+    /// Filename of the app, ending with `.xdc`.
+    name: String,
+
+    /// The `.xdc` file, base64-encoded.
+    xdc: String,
+
+    /// Status updates of the app, oldest first.
+    updates: Vec<StatusUpdateItem>,
+}
+
+impl Context {
+    /// Exports the webxdc instance `instance_msg_id` with all its status updates
+    /// to the file `path`, see [`Context::import_webxdc`].
+    pub async fn export_webxdc(&self, instance_msg_id: MsgId, path: &Path) -> Result<()> {
+        let instance = Message::load_from_db(self, instance_msg_id).await?;
+        ensure!(
+            instance.viewtype == Viewtype::Webxdc,
+            "Message {instance_msg_id} is not a webxdc instance"
+        );
+        ensure!(
+            instance.param.get_int(Param::WebxdcIntegration).is_none(),
+            "Integrations cannot be exported"
+        );
+        let xdc_path = instance
+            .get_file(self)
+            .context("Webxdc instance has no file")?;
+        let xdc = fs::read(&xdc_path)
+            .await
+            .with_context(|| format!("Cannot read {}", xdc_path.display()))?;
+
+        let updates = self
+            .sql
+            .query_map(
+                "SELECT update_item FROM msgs_status_updates WHERE msg_id=? ORDER BY id",
+                (instance_msg_id,),
+                |row| row.get::<_, String>(0),
+                |rows| {
+                    let mut updates = Vec::new();
+                    for row in rows {
+                        let item: StatusUpdateItem = serde_json::from_str(&row?)?;
+                        updates.push(StatusUpdateItem {
+                            // UIDs must be unique, the imported updates get new ones.
+                            uid: None,
+                            // Do not notify anyone again when importing.
+                            notify: None,
+                            ..item
+                        });
+                    }
+                    Ok(updates)
+                },
+            )
+            .await?;
+
+        let export = WebxdcExport {
+            name: instance
+                .get_filename()
+                .unwrap_or_else(|| "app.xdc".to_string()),
+            xdc: base64::engine::general_purpose::STANDARD.encode(xdc),
+            updates,
+        };
+        fs::write(path, serde_json::to_vec(&export)?)
+            .await
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+        info!(
+            self,
+            "Exported webxdc {instance_msg_id} with {} updates to {}.",
+            export.updates.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Imports a webxdc app exported with [`Context::export_webxdc`]
+    /// by sending it with all its status updates to the chat `chat_id`.
+    ///
+    /// Returns the ID of the new webxdc instance.
+    pub async fn import_webxdc(&self, chat_id: ChatId, path: &Path) -> Result<MsgId> {
+        let data = fs::read(path)
+            .await
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let export: WebxdcExport =
+            serde_json::from_slice(&data).context("Not a webxdc export file")?;
+        ensure!(
+            export.name.ends_with(".xdc"),
+            "Invalid webxdc filename {:?}",
+            export.name
+        );
+        let xdc = base64::engine::general_purpose::STANDARD.decode(&export.xdc)?;
+
+        let mut instance = Message::new(Viewtype::Webxdc);
+        instance
+            .set_file_from_bytes(self, &export.name, &xdc, None)
+            .await?;
+        chat::prepare_msg(self, chat_id, &mut instance).await?;
+
+        // Updates of prepared instances are sent together with the instance.
+        let count = export.updates.len();
+        for update in export.updates {
+            self.send_webxdc_status_update_struct(instance.id, update)
+                .await?;
+        }
+        // Reload the instance as the updates may have changed its document name and summary.
+        let mut instance = Message::load_from_db(self, instance.id).await?;
+        let instance_msg_id = chat::send_msg(self, chat_id, &mut instance).await?;
+        info!(
+            self,
+            "Imported webxdc from {} with {count} updates as {instance_msg_id}.",
+            path.display()
+        );
+        Ok(instance_msg_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContextManager;
+    use crate::webxdc::StatusUpdateSerial;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_export_import_webxdc() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let mut instance = Message::new(Viewtype::File);
+        instance
+            .set_file_from_bytes(
+                alice,
+                "minimal.xdc",
+                include_bytes!("../../test-data/webxdc/minimal.xdc"),
+                None,
+            )
+            .await?;
+        let bob_chat_id = alice.create_chat(bob).await.id;
+        let instance_msg_id = chat::send_msg(alice, bob_chat_id, &mut instance).await?;
+        alice
+            .send_webxdc_status_update(
+                instance_msg_id,
+                r#"{"payload": {"item": "milk"}, "document": "Shopping"}"#,
+            )
+            .await?;
+        alice
+            .send_webxdc_status_update(
+                instance_msg_id,
+                r#"{"payload": {"item": "bread"}, "notify": {"*": "bread added"}}"#,
+            )
+            .await?;
+
+        let path = alice.get_blobdir().join("shopping.json");
+        alice.export_webxdc(instance_msg_id, &path).await?;
+
+        let fiona_chat_id = alice.create_chat(fiona).await.id;
+        let imported_id = alice.import_webxdc(fiona_chat_id, &path).await?;
+        assert_ne!(imported_id, instance_msg_id);
+        let sent = alice.pop_sent_msg().await;
+        assert_eq!(sent.sender_msg_id, imported_id);
+        let imported = Message::load_from_db(alice, imported_id).await?;
+        assert_eq!(imported.chat_id, fiona_chat_id);
+        assert_eq!(imported.get_webxdc_info(alice).await?.document, "Shopping");
+
+        let fiona_instance = fiona.recv_msg(&sent).await;
+        assert_eq!(fiona_instance.viewtype, Viewtype::Webxdc);
+        assert_eq!(
+            fiona_instance.get_filename(),
+            Some("minimal.xdc".to_string())
+        );
+        assert_eq!(
+            fiona
+                .get_webxdc_status_updates(fiona_instance.id, StatusUpdateSerial::new(0))
+                .await?,
+            r#"[{"payload":{"item":"milk"},"document":"Shopping","serial":1,"max_serial":2},
+{"payload":{"item":"bread"},"serial":2,"max_serial":2}]"#
+        );
+
+        // Non-webxdc messages cannot be exported.
+        let text_msg_id = alice.send_text(bob_chat_id, "Hi").await.sender_msg_id;
+        assert!(alice.export_webxdc(text_msg_id, &path).await.is_err());
+        Ok(())
+    }
+}